@@ -13,6 +13,7 @@
 
     function fctName(fctId) {
         const fct = Wasabi.module.info.functions[fctId];
+        if (fct.name !== null) return fct.name;
         if (fct.export[0] !== undefined) return fct.export[0];
         if (fct.import !== null) return fct.import;
         return fctId;
@@ -24,6 +25,12 @@
             const callee = fctName(targetFunc);
             callGraphEdges.add(caller + " --> " + callee);
         },
+
+        return_call(location, targetFunc, args, indirectTableIdx) {
+            const caller = fctName(location.func);
+            const callee = fctName(targetFunc);
+            callGraphEdges.add(caller + " --> " + callee + " (tail call)");
+        },
     };
 
 })();
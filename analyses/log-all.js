@@ -61,6 +61,11 @@ Wasabi.analysis = {
         console.log(location, (location.instr === -1) ? "implicit" : "explicit", "return, values = ", values);
     },
 
+    // followed by end() of all blocks of the current function, the callee returns directly to our caller
+    return_call(location, targetFunc, args, indirectTableIdx) {
+        console.log(location, (indirectTableIdx === undefined ? "direct" : "indirect"), "tail call", "to func #", targetFunc, "args =", args);
+    },
+
     const_(location, value) {
         console.log(location, "const, value =", value);
     },
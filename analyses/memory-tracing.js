@@ -6,9 +6,33 @@
 		accesses.push({func, instr, addr: addr+offset, write});
 	};
 
+	// bulk operations access every byte in the range
+	function accessRange(loc, addr, length, write) {
+		for (let i = 0; i < length; i++) {
+			access(loc, {addr: addr+i, offset: 0}, write);
+		}
+	}
+
 	Wasabi.analysis = {
 		load(loc, op, memarg) { access(loc, memarg, false) },
 		store(loc, op, memarg) { access(loc, memarg, true) },
+		memory_fill(loc, addr, value, length) { accessRange(loc, addr, length, true) },
+		memory_copy(loc, destAddr, srcAddr, length) {
+			accessRange(loc, srcAddr, length, false);
+			accessRange(loc, destAddr, length, true);
+		},
+		memory_init(loc, dataIdx, destAddr, srcOffset, length) { accessRange(loc, destAddr, length, true) },
+		atomic_load(loc, op, memarg) { access(loc, memarg, false) },
+		atomic_store(loc, op, memarg) { access(loc, memarg, true) },
+		// read-modify-write operations (conservatively also cmpxchg) both read and write
+		atomic_rmw(loc, op, memarg) {
+			access(loc, memarg, false);
+			access(loc, memarg, true);
+		},
+		atomic_cmpxchg(loc, op, memarg) {
+			access(loc, memarg, false);
+			access(loc, memarg, true);
+		},
 	};
 
 	Wasabi.analysisResult = accesses;
@@ -94,6 +94,19 @@ Wasabi.analysis = {
         locals.push(args);
     },
 
+    return_call(location, targetFunc, args, indirectTableIdx) {
+        if (indirectTableIdx !== undefined) {
+            const jsTargetTableIdx = stack.peek().pop();
+            check("return_call_indirect table idx", location, jsTargetTableIdx, indirectTableIdx);
+        }
+        for (const arg of args.slice().reverse()) {
+            const jsArg = stack.peek().pop();
+            check("return_call args", location, jsArg, arg);
+        }
+        // NOTE unlike call_pre, no locals are pushed: end() of the current function follows, then
+        // the callee "reuses" the entry of the current function, which the original call_post pops
+    },
+
     call_post(location, vals) {
         for (const val of vals) {
             stack.peek().push(val);
@@ -34,6 +34,7 @@
     const globals = [];
 
     let returnValue; // to propagate return value's taint from end() to call_post()
+    let thrownValues = []; // to propagate the taints of an exception's values from throw_() to catch_()
 
     /*
      * Taint policy: sources and sink
@@ -205,7 +206,35 @@
             values().push(taintResult);
         },
 
+        ternary(location, op, first, second, third, result) {
+            const taint1 = ensureTaint(values().pop(), location);
+            const taint2 = ensureTaint(values().pop(), location);
+            const taint3 = ensureTaint(values().pop(), location);
+            values().push(join(join(taint1, taint2), taint3));
+        },
+
+        extract_lane(location, op, lane, input, result) {
+            const taint = ensureTaint(values().pop(), location);
+            const taintResult = new Taint();
+            taintResult.label = taint.label;
+            values().push(taintResult);
+        },
+
+        replace_lane(location, op, lane, input, value, result) {
+            const taint1 = ensureTaint(values().pop(), location);
+            const taint2 = ensureTaint(values().pop(), location);
+            values().push(join(taint1, taint2));
+        },
+
+        shuffle(location, lanes, first, second, result) {
+            const taint1 = ensureTaint(values().pop(), location);
+            const taint2 = ensureTaint(values().pop(), location);
+            values().push(join(taint1, taint2));
+        },
+
         load(location, op, memarg, value) {
+            // lane loads additionally take the vector whose lane is replaced
+            if (memarg.lane !== undefined) values().pop();
             values().pop();
             const effectiveAddr = memarg.addr + memarg.offset;
             const taint = ensureTaint(memory[effectiveAddr], location);
@@ -230,6 +259,94 @@
             values().push(new Taint());
         },
 
+        memory_fill(location, addr, value, length) {
+            values().pop();
+            const taint = ensureTaint(values().pop(), location);
+            values().pop();
+            if (debug) console.log("Memory fill of " + length + " bytes at address " + addr + " with taint " + taint);
+            for (let i = 0; i < length; i++) {
+                memory[addr + i] = taint;
+            }
+        },
+
+        memory_copy(location, destAddr, srcAddr, length) {
+            values().pop();
+            values().pop();
+            values().pop();
+            if (debug) console.log("Memory copy of " + length + " bytes from address " + srcAddr + " to " + destAddr);
+            // copy all taints first, since the ranges may overlap
+            const taints = memory.slice(srcAddr, srcAddr + length);
+            for (let i = 0; i < length; i++) {
+                memory[destAddr + i] = taints[i];
+            }
+        },
+
+        memory_init(location, dataIdx, destAddr, srcOffset, length) {
+            values().pop();
+            values().pop();
+            values().pop();
+            // data segments are constant, so they are not tainted
+            for (let i = 0; i < length; i++) {
+                memory[destAddr + i] = new Taint();
+            }
+        },
+
+        atomic_load(location, op, memarg, value) {
+            values().pop();
+            const effectiveAddr = memarg.addr + memarg.offset;
+            values().push(ensureTaint(memory[effectiveAddr], location));
+        },
+
+        atomic_store(location, op, memarg, value) {
+            const taint = ensureTaint(values().pop(), location);
+            values().pop();
+            memory[memarg.addr + memarg.offset] = taint;
+        },
+
+        atomic_rmw(location, op, memarg, operand, oldValue) {
+            const taint = ensureTaint(values().pop(), location);
+            values().pop();
+            const effectiveAddr = memarg.addr + memarg.offset;
+            const oldTaint = ensureTaint(memory[effectiveAddr], location);
+            memory[effectiveAddr] = join(oldTaint, taint);
+            values().push(oldTaint);
+        },
+
+        atomic_cmpxchg(location, op, memarg, expected, replacement, oldValue) {
+            const replacementTaint = ensureTaint(values().pop(), location);
+            values().pop();
+            values().pop();
+            const effectiveAddr = memarg.addr + memarg.offset;
+            const oldTaint = ensureTaint(memory[effectiveAddr], location);
+            // conservatively assume the exchange happened
+            memory[effectiveAddr] = join(oldTaint, replacementTaint);
+            values().push(oldTaint);
+        },
+
+        atomic_wait(location, op, memarg, expected, timeout, result) {
+            values().pop();
+            values().pop();
+            values().pop();
+            values().push(new Taint());
+        },
+
+        atomic_notify(location, memarg, count, woken) {
+            values().pop();
+            values().pop();
+            values().push(new Taint());
+        },
+
+        throw_(location, tag, args) {
+            thrownValues = args.map(() => ensureTaint(values().pop(), location)).reverse();
+        },
+
+        catch_(location, tag, vals) {
+            for (let i = 0; i < vals.length; i++) {
+                values().push(ensureTaint(thrownValues[i], location));
+            }
+            thrownValues = [];
+        },
+
         local(location, op, localIndex, value) {
             switch (op) {
                 case "set_local": {
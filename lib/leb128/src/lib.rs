@@ -9,12 +9,26 @@ mod tests;
 /* Traits for encoding and decoding Leb128 primitive integers */
 
 pub trait ReadLeb128<T>: io::Read {
-    fn read_leb128(&mut self) -> io::Result<T>;
+    fn read_leb128(&mut self) -> io::Result<T> {
+        self.read_leb128_with_size().map(|(value, _)| value)
+    }
+
+    /// Also returns the read byte count, e.g., to preserve the length of padded encodings.
+    fn read_leb128_with_size(&mut self) -> io::Result<(T, usize)>;
 }
 
 pub trait WriteLeb128<T>: io::Write {
     /// Returns the actual written byte count.
     fn write_leb128(&mut self, value: T) -> io::Result<usize>;
+
+    /// Like write_leb128(), but pads the encoding to at least min_size bytes (with continuation
+    /// bytes that only extend the sign), e.g., to keep the length of a padded original encoding.
+    fn write_leb128_padded(&mut self, value: T, min_size: usize) -> io::Result<usize>;
+}
+
+pub trait Leb128Size {
+    /// Returns the byte count that write_leb128() would write, without writing anything.
+    fn leb128_size(self) -> usize;
 }
 
 fn sign_bit(byte: u8) -> bool {
@@ -30,10 +44,9 @@ macro_rules! signed {
 macro_rules! impl_leb128_integer {
     ($T: ident) => {
         impl<R: io::Read> ReadLeb128<$T> for R {
-            fn read_leb128(&mut self) -> io::Result<$T> {
+            fn read_leb128_with_size(&mut self) -> io::Result<($T, usize)> {
                 let mut value = 0;
-                // useful if you want to preserve length when encoding this LEB128 again (unused currently though)
-                let mut _bytes_read = 0;
+                let mut bytes_read = 0;
                 let mut shift = 0;
                 let mut byte = 0x80;
 
@@ -45,7 +58,7 @@ macro_rules! impl_leb128_integer {
                     } else {
                         return Err(io::Error::new(io::ErrorKind::InvalidData, format!("LEB128 to {} overflow", stringify!($T))));
                     }
-                    _bytes_read += 1;
+                    bytes_read += 1;
                     shift += 7;
                 }
 
@@ -56,7 +69,7 @@ macro_rules! impl_leb128_integer {
                     }
                 }
 
-                Ok(value)
+                Ok((value, bytes_read))
             }
         }
 
@@ -81,6 +94,41 @@ macro_rules! impl_leb128_integer {
 
                 Ok(bytes_written as usize)
             }
+
+            fn write_leb128_padded(&mut self, value: $T, min_size: usize) -> io::Result<usize> {
+                let size = value.leb128_size();
+                if size >= min_size {
+                    return self.write_leb128(value);
+                }
+
+                let mut buf = Vec::with_capacity(min_size);
+                buf.write_leb128(value)?;
+                // padding bytes repeat the sign (i.e., all bits set for negative values)
+                let padding = if signed!($T) && sign_bit(buf[size - 1]) { 0x7F } else { 0 };
+                buf[size - 1] |= 0x80;
+                buf.resize(min_size - 1, 0x80 | padding);
+                buf.push(padding);
+                self.write_all(&buf)?;
+
+                Ok(min_size)
+            }
+        }
+
+        impl Leb128Size for $T {
+            fn leb128_size(mut self) -> usize {
+                let mut size = 0;
+                let mut more_bytes = true;
+
+                // same as in write_leb128(), but only count the bytes
+                while more_bytes {
+                    let byte = self as u8 & 0x7F;
+                    self >>= 7;
+                    size += 1;
+                    more_bytes = self != if signed!($T) && sign_bit(byte) { !0 } else { 0 };
+                }
+
+                size
+            }
         }
     }
 }
@@ -89,6 +137,8 @@ impl_leb128_integer!(u32);
 impl_leb128_integer!(usize);
 impl_leb128_integer!(i32);
 impl_leb128_integer!(i64);
+// memory64: limits and memarg offsets
+impl_leb128_integer!(u64);
 
 // for testing, can be exhaustively checked for correctness
 impl_leb128_integer!(u16);
@@ -1,4 +1,4 @@
-use {ReadLeb128, WriteLeb128};
+use {Leb128Size, ReadLeb128, WriteLeb128};
 
 #[test]
 /// exhaustively test that decode(encode(value)) == value for u16 and i16
@@ -20,4 +20,44 @@ fn roundtrips() {
                    "\nbuffer:{}",
                    buf.iter().map(|byte| format!(" 0x{:x}", byte)).collect::<Vec<String>>().concat());
     }
-}
\ No newline at end of file
+}
+
+#[test]
+/// exhaustively test that leb128_size(value) == encode(value).len() for u16 and i16
+fn sizes() {
+    for u in u16::min_value()..=u16::max_value() {
+        let mut buf: Vec<u8> = Vec::new();
+        buf.write_leb128(u).unwrap();
+        assert_eq!(u.leb128_size(), buf.len(), "wrong size for {}", u);
+    }
+
+    for i in i16::min_value()..=i16::max_value() {
+        let mut buf: Vec<u8> = Vec::new();
+        buf.write_leb128(i).unwrap();
+        assert_eq!(i.leb128_size(), buf.len(), "wrong size for {}", i);
+    }
+}
+
+#[test]
+/// exhaustively test that padded encodings decode to the same value and have the given size
+fn padded_roundtrips() {
+    for min_size in 1..=3 {
+        for u in u16::min_value()..=u16::max_value() {
+            let mut buf: Vec<u8> = Vec::new();
+            let bytes_written = buf.write_leb128_padded(u, min_size).unwrap();
+            let (u_decode, bytes_read): (u16, usize) = buf.as_slice().read_leb128_with_size().unwrap();
+            assert_eq!(u, u_decode, "wrong value for {} padded to {} bytes", u, min_size);
+            assert_eq!(bytes_written, buf.len());
+            assert_eq!(bytes_read, ::std::cmp::max(u.leb128_size(), min_size));
+        }
+
+        for i in i16::min_value()..=i16::max_value() {
+            let mut buf: Vec<u8> = Vec::new();
+            let bytes_written = buf.write_leb128_padded(i, min_size).unwrap();
+            let (i_decode, bytes_read): (i16, usize) = buf.as_slice().read_leb128_with_size().unwrap();
+            assert_eq!(i, i_decode, "wrong value for {} padded to {} bytes", i, min_size);
+            assert_eq!(bytes_written, buf.len());
+            assert_eq!(bytes_read, ::std::cmp::max(i.leb128_size(), min_size));
+        }
+    }
+}
@@ -17,21 +17,46 @@ let Wasabi = {
         "select",
         "call_pre",
         "call_post",
+        "return_call",
         "return_",
         "const_",
         "unary",
         "binary",
+        "ternary",
         "load",
         "store",
         "memory_size",
         "memory_grow",
+        "memory_fill",
+        "memory_copy",
+        "memory_init",
         "local",
-        "global"
+        "global",
+        "table_get",
+        "table_set",
+        "table_size",
+        "table_grow",
+        "table_fill",
+        "ref_null",
+        "ref_is_null",
+        "ref_func",
+        "extract_lane",
+        "replace_lane",
+        "shuffle",
+        "atomic_load",
+        "atomic_store",
+        "atomic_rmw",
+        "atomic_cmpxchg",
+        "atomic_wait",
+        "atomic_notify",
+        "atomic_fence",
+        "throw_",
+        "catch_"
     ],
 
-    // map a table index to a function index
-    resolveTableIdx: function (tableIdx) {
-        if (Wasabi.module.exports === undefined || Wasabi.module.table === undefined) {
+    // map a table index to a function index, table is the index of the table itself (0 unless there are multiple)
+    resolveTableIdx: function (tableIdx, table = 0) {
+        if (Wasabi.module.exports === undefined || Wasabi.module.tables === undefined) {
             console.warn("cannot resolve table index without exports and table (possible reason: exports and table are not available during Wasm start function)");
             return undefined;
         }
@@ -42,8 +67,8 @@ let Wasabi = {
 
         // dirty HACK subtract the "name index" of the first function, should correct for the wrong property
         // const firstFunctionIdx = parseInt(Wasabi.module.exports[Wasabi.module.info.firstFunctionExportName].name);
-        // const functionIdx = parseInt(Wasabi.module.table.get(tableIdx).name) - firstFunctionIdx;
-        const functionIdx = parseInt(Wasabi.module.table.get(tableIdx).name);
+        // const functionIdx = parseInt(Wasabi.module.tables[table].get(tableIdx).name) - firstFunctionIdx;
+        const functionIdx = parseInt(Wasabi.module.tables[table].get(tableIdx).name);
 
         return (functionIdx >= Wasabi.module.info.functions.length) ? 0 : functionIdx;
     },
@@ -61,11 +86,30 @@ let Wasabi = {
                 {func, instr: end},
                 type,
                 {func, instr: begin},
-                // not undefined only for block type "else"
+                // not undefined only for block types "else", "catch", and "catch_all" (begin of if/try)
                 (begin_if === undefined) ? undefined : {func, instr: begin_if});
         }
     },
 
+    // call end hooks for all blocks that were left by an exception, from the innermost block at the
+    // throw site up to (and including) the block whose handler caught it (-1 if the exception leaves the function)
+    endUnwoundBlocks: function(func, handlerBegin, innermostBegin) {
+        const blocks = Wasabi.module.info.functions[func].blocks;
+        let begin = innermostBegin;
+        while (true) {
+            const [[type, blockBegin, end, begin_if], parent] = blocks[begin];
+            Wasabi.analysis.end(
+                {func, instr: end},
+                type,
+                {func, instr: blockBegin},
+                (begin_if === undefined) ? undefined : {func, instr: begin_if});
+            if (begin === handlerBegin || begin === -1) {
+                break;
+            }
+            begin = parent;
+        }
+    },
+
     loc2func: function(loc) {
         // TODO
     },
@@ -78,12 +122,72 @@ let Wasabi = {
         // TODO
     },
 
+    // original byte offset of an instruction in the module (as in browser stack traces), only
+    // available if instrumented with --code-offsets, otherwise undefined
+    loc2offset: function(loc) {
+        const offsets = Wasabi.module.info.codeOffsets;
+        const funcOffsets = (offsets === undefined) ? undefined : offsets.functions[loc.func];
+        return (funcOffsets == null) ? undefined : funcOffsets.instrs[loc.instr];
+    },
+
+    // inverse of loc2offset, also for offsets inside an instruction (e.g., of its immediates)
+    offset2loc: function(offset) {
+        const offsets = Wasabi.module.info.codeOffsets;
+        if (offsets === undefined) {
+            return undefined;
+        }
+        const func = offsets.functions.findIndex(f => f != null && f.body.start <= offset && offset < f.body.end);
+        if (func === -1) {
+            return undefined;
+        }
+        const instrs = offsets.functions[func].instrs;
+        // last instruction that starts at or before the offset
+        let low = 0, high = instrs.length;
+        while (low < high) {
+            const mid = (low + high) >>> 1;
+            if (instrs[mid] <= offset) {
+                low = mid + 1;
+            } else {
+                high = mid;
+            }
+        }
+        return (low === 0) ? undefined : {func, instr: low - 1};
+    },
+
+    // source location of an instruction from the DWARF debug info of the original module (if any)
+    // returns {file, line, column, func} (column is 0 and func undefined if unknown) or undefined
+    loc2source: function(loc) {
+        const funcInfo = Wasabi.module.info.functions[loc.func];
+        const locations = (funcInfo === undefined) ? undefined : funcInfo.sourceLocations;
+        if (locations === undefined) {
+            return undefined;
+        }
+        // locations are sorted by instruction and only give the first instruction of each run with
+        // the same location, so find the last entry at or before the instruction
+        let low = 0, high = locations.length;
+        while (low < high) {
+            const mid = (low + high) >>> 1;
+            if (locations[mid][0] <= loc.instr) {
+                low = mid + 1;
+            } else {
+                high = mid;
+            }
+        }
+        if (low === 0 || locations[low - 1].length === 1) {
+            return undefined;
+        }
+        const [, file, line, column] = locations[low - 1];
+        return {file: Wasabi.module.info.sourceFiles[file], line, column, func: funcInfo.sourceName};
+    },
+
     module: {
         // filled at instrumentation time
         // TODO flatten info into module itself, by using Object.assign in generated code
         info: undefined, lowlevelHooks: undefined,
         // filled after instantiation
-        exports: undefined, table: undefined,
+        exports: undefined, tables: undefined, memories: undefined,
+        // same as tables[0], for analyses written before modules could have multiple tables
+        table: undefined,
     },
 
     // filled by user or with empty hooks (as fallback) before instantiation
@@ -109,15 +213,44 @@ let Wasabi = {
         call_pre(location, targetFunc, args, indirectTableIdx) {},
         call_post(location, values) {},
         return_(location, values) {},
+        // tail call: followed by the end of the current function, which targetFunc replaces (no call_post)
+        return_call(location, targetFunc, args, indirectTableIdx) {},
         const_(location, value) {},
         unary(location, op, input, result) {},
         binary(location, op, first, second, result) {},
+        ternary(location, op, first, second, third, result) {},
+        // memory64: for 64-bit memories, memarg.addr, memarg.offset, and page counts/lengths are Long's
         load(location, op, memarg, value) {},
         store(location, op, memarg, value) {},
-        memory_size(location, currentSizePages) {},
-        memory_grow(location, byPages, previousSizePages) {},
+        memory_size(location, currentSizePages, memory) {},
+        memory_grow(location, byPages, previousSizePages, memory) {},
+        memory_fill(location, addr, value, length, memory) {},
+        memory_copy(location, destAddr, srcAddr, length, destMemory, srcMemory) {},
+        memory_init(location, dataIdx, destAddr, srcOffset, length, memory) {},
         local(location, op, localIndex, value) {},
         global(location, op, globalIndex, value) {},
+        table_get(location, table, index, value) {},
+        table_set(location, table, index, value) {},
+        table_size(location, table, currentSize) {},
+        table_grow(location, table, value, delta, previousSize) {},
+        table_fill(location, table, index, value, length) {},
+        ref_null(location, type) {},
+        ref_is_null(location, value, isNull) {},
+        ref_func(location, func) {},
+        extract_lane(location, op, lane, input, result) {},
+        replace_lane(location, op, lane, input, value, result) {},
+        shuffle(location, lanes, first, second, result) {},
+        // ordering is always "seq_cst", the only memory ordering of the threads proposal
+        atomic_load(location, op, memarg, value, ordering) {},
+        atomic_store(location, op, memarg, value, ordering) {},
+        atomic_rmw(location, op, memarg, operand, oldValue, ordering) {},
+        atomic_cmpxchg(location, op, memarg, expected, replacement, oldValue, ordering) {},
+        atomic_wait(location, op, memarg, expected, timeout, result) {},
+        atomic_notify(location, memarg, count, woken) {},
+        atomic_fence(location, ordering) {},
+        // tag is null for catch_all (and the values of the exception are unknown)
+        throw_(location, tag, args) {},
+        catch_(location, tag, values) {},
     }
 
     const oldInstantiate = WebAssembly.instantiate;
@@ -137,10 +270,12 @@ let Wasabi = {
         importObjectWithHooks.__wasabi_hooks = Wasabi.module.lowlevelHooks;
 
         const result = oldInstantiate(sourceBuffer, importObjectWithHooks);
-        // as soon as instance is available, save exports and table
+        // as soon as instance is available, save exports, tables, and memories
         result.then(({module, instance}) => {
             Wasabi.module.exports = instance.exports;
-            Wasabi.module.table = instance.exports[Wasabi.module.info.tableExportName];
+            Wasabi.module.tables = Wasabi.module.info.tableExportNames.map(name => instance.exports[name]);
+            Wasabi.module.table = Wasabi.module.tables[0];
+            Wasabi.module.memories = Wasabi.module.info.memoryExportNames.map(name => instance.exports[name]);
         });
         return result;
     };
@@ -4,12 +4,28 @@ extern crate walkdir;
 use std::path::{Path, PathBuf};
 use std::io;
 
+/// WebAssembly proposals that the test inputs use (older WABT versions do not enable all of them by default)
+pub const WABT_FEATURES: &[&str] = &[
+    "--enable-sign-extension",
+    "--enable-saturating-float-to-int",
+    "--enable-multi-value",
+    "--enable-bulk-memory",
+    "--enable-reference-types",
+    "--enable-simd",
+    "--enable-threads",
+    "--enable-exceptions",
+    "--enable-tail-call",
+    "--enable-multi-memory",
+    "--enable-memory64",
+];
+
 /// call WABT's wasm-validate tool on a file (WABT needs to be on $PATH)
 pub fn wasm_validate(path: impl AsRef<Path>) -> Result<(), String> {
     use std::process::Command;
 
     let path = path.as_ref();
     let validate_output = Command::new("wasm-validate")
+        .args(WABT_FEATURES)
         .arg(path)
         .output()
         .map_err(|err| err.to_string())?;
@@ -25,16 +41,30 @@ pub fn wasm_validate(path: impl AsRef<Path>) -> Result<(), String> {
 
 /// return all *.wasm files under a root directory
 pub fn wasm_files(root_dir: impl AsRef<Path>) -> Result<Vec<PathBuf>, String> {
+    files_with_extension(root_dir, "wasm")
+}
+
+/// return all *.wat files under a root directory
+pub fn wat_files(root_dir: impl AsRef<Path>) -> Result<Vec<PathBuf>, String> {
+    files_with_extension(root_dir, "wat")
+}
+
+/// return all *.wast files (spec test scripts) under a root directory
+pub fn wast_files(root_dir: impl AsRef<Path>) -> Result<Vec<PathBuf>, String> {
+    files_with_extension(root_dir, "wast")
+}
+
+fn files_with_extension(root_dir: impl AsRef<Path>, extension: &str) -> Result<Vec<PathBuf>, String> {
     use walkdir::WalkDir;
 
-    let mut wasm_files = Vec::new();
+    let mut files = Vec::new();
     for entry in WalkDir::new(&root_dir) {
         let path = entry.map_err(|err| err.to_string())?.path().to_owned();
-        if let Some("wasm") = path.extension().and_then(|os_str| os_str.to_str()) {
-            wasm_files.push(path);
+        if path.extension().and_then(|os_str| os_str.to_str()) == Some(extension) {
+            files.push(path);
         }
     }
-    Ok(wasm_files)
+    Ok(files)
 }
 
 pub fn output_file(test_input_file: impl AsRef<Path>, output_subdir: &'static str) -> io::Result<PathBuf> {
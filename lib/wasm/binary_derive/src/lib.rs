@@ -10,10 +10,13 @@ use proc_macro::TokenStream;
 use quote::Tokens;
 use syn::{Attribute, Data, DataEnum, DataStruct, DeriveInput, Field, Fields, Ident, Lit, Meta, MetaNameValue, Path, PathArguments, PathSegment, Type, TypePath, Variant};
 
-#[proc_macro_derive(WasmBinary, attributes(tag))]
+#[proc_macro_derive(WasmBinary, attributes(tag, leb128_tags))]
 pub fn derive_wasm(input: TokenStream) -> TokenStream {
     let input: DeriveInput = syn::parse(input).unwrap();
     let data_name = &input.ident;
+    // enums marked with #[leb128_tags] have variant tags that are encoded as LEB128 u32 instead of
+    // a single byte, e.g., the sub-opcodes of prefixed instructions
+    let leb128_tags = input.attrs.iter().any(is_leb128_tags_attribute);
 
     let decode_expr = match &input.data {
         &Data::Struct(DataStruct { ref fields, .. }) => {
@@ -22,38 +25,38 @@ pub fn derive_wasm(input: TokenStream) -> TokenStream {
             let decode_tag = tag.map(|tag| quote! {
                 let byte = u8::decode(reader)?;
                 if byte != #tag {
-                    return Self::error(format!("expected tag for {}, got 0x{:02x}", stringify!(#data_name), byte));
+                    return Self::tag_error(stringify!(#data_name), byte);
                 }
             });
-            let decode_fields = decode_fields(&parse_quote!(#data_name), &fields);
+            let decode_fields = decode_fields(&parse_quote!(#data_name), &data_name.to_string(), &fields);
 
             quote!({
                 #( #decode_tag )*
                 #decode_fields
             })
         }
+        &Data::Enum(DataEnum { ref variants, .. }) if leb128_tags => {
+            let decode_variants = variants.iter().map(|variant| decode_variant(data_name, variant, leb128_tags));
+
+            quote!(match u32::decode(reader)? {
+                #( #decode_variants )*
+                tag => Self::leb128_tag_error(stringify!(#data_name), tag)?
+            })
+        }
         &Data::Enum(DataEnum { ref variants, .. }) => {
-            let decode_variants = variants.iter().map(|variant| decode_variant(data_name, variant));
+            let decode_variants = variants.iter().map(|variant| decode_variant(data_name, variant, leb128_tags));
 
             quote!(match u8::decode(reader)? {
                 #( #decode_variants )*
-                byte => Self::error(format!("expected tag for {}, got 0x{:02x}", stringify!(#data_name), byte))?
+                byte => Self::tag_error(stringify!(#data_name), byte)?
             })
         }
         _ => unimplemented!("can only derive(WasmBinary) for structs and enums")
     };
 
-    let encode_match_arms = match &input.data {
-        &Data::Struct(DataStruct { ref fields, .. }) => {
-            let tag: Option<u8> = attributes_to_tag(&input.attrs);
-            encode_fields(&parse_quote!(#data_name), tag, &fields)
-        }
-        &Data::Enum(DataEnum { ref variants, .. }) => {
-            let encode_variants = variants.iter().map(|variant| encode_variant(data_name, variant));
-            quote!( #( #encode_variants ),* )
-        }
-        _ => unimplemented!("can only derive(WasmBinary) for structs and enums")
-    };
+    // encoded_size() has the same structure as encode(), but sums the sizes instead of writing
+    let encode_match_arms = encode_data(&input, leb128_tags, &quote!(encode(writer)?));
+    let encoded_size_match_arms = encode_data(&input, leb128_tags, &quote!(encoded_size()));
 
     let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
     quote!(
@@ -68,35 +71,77 @@ pub fn derive_wasm(input: TokenStream) -> TokenStream {
                 };
                 Ok(bytes_written)
             }
+            fn encoded_size(&self) -> usize {
+                let mut bytes_written = 0;
+                match *self {
+                    #encoded_size_match_arms
+                };
+                bytes_written
+            }
         }
     ).into()
 }
 
 /// Take the first `#[tag = <byte literal>]` attribute and return the value of `byte literal`.
 fn attributes_to_tag(attributes: &[Attribute]) -> Option<u8> {
+    attributes_to_tag_with_max(attributes, u8::max_value() as u64).map(|tag| tag as u8)
+}
+
+/// Same, but for enums with #[leb128_tags], where tags can be any u32.
+fn attributes_to_leb128_tag(attributes: &[Attribute]) -> Option<u32> {
+    attributes_to_tag_with_max(attributes, u32::max_value() as u64).map(|tag| tag as u32)
+}
+
+fn attributes_to_tag_with_max(attributes: &[Attribute], max: u64) -> Option<u64> {
     let attribute = attributes.first()?;
     match attribute.interpret_meta() {
         Some(Meta::NameValue(MetaNameValue { ident, lit: Lit::Int(ref uint), .. }))
-        if ident.to_string() == "tag" && uint.value() <= u8::max_value() as u64 =>
-            Some(uint.value() as u8),
-        _ => panic!("attribute must be of type #[tag = <u8 literal>], got {}", quote!(#attribute))
+        if ident.to_string() == "tag" && uint.value() <= max =>
+            Some(uint.value()),
+        _ => panic!("attribute must be of type #[tag = <integer literal <= {}>], got {}", max, quote!(#attribute))
+    }
+}
+
+fn is_leb128_tags_attribute(attribute: &Attribute) -> bool {
+    match attribute.interpret_meta() {
+        Some(Meta::Word(ident)) => ident.to_string() == "leb128_tags",
+        _ => false,
+    }
+}
+
+/// quoted tag of an enum variant, the u8/u32 suffix determines how it is decoded/encoded
+fn variant_tag(variant: &Variant, leb128_tags: bool) -> Option<Tokens> {
+    if leb128_tags {
+        attributes_to_leb128_tag(&variant.attrs).map(|tag| quote!(#tag))
+    } else {
+        attributes_to_tag(&variant.attrs).map(|tag| quote!(#tag))
     }
 }
 
 /* for decode() */
 
-fn decode_variant(super_name: &Ident, variant: &Variant) -> Tokens {
-    let tag = attributes_to_tag(&variant.attrs)
+fn decode_variant(super_name: &Ident, variant: &Variant, leb128_tags: bool) -> Tokens {
+    let tag = variant_tag(variant, leb128_tags)
         .expect(&format!("every enum variant needs a tag, but {} does not have one", variant.ident));
     let variant_name = &variant.ident;
     let name = parse_quote!(#super_name::#variant_name);
 
-    let decode_fields = decode_fields(&name, &variant.fields);
+    let decode_fields = decode_fields(&name, &format!("{}::{}", super_name, variant_name), &variant.fields);
     quote!( #tag => #decode_fields, )
 }
 
-fn decode_fields(name: &TypePath, fields: &Fields) -> Tokens {
-    let decoded_fields = fields.iter().map(decode_field);
+/// context is the name of the struct or enum variant for decoding errors, e.g., "Instr::Call"
+fn decode_fields(name: &TypePath, context: &str, fields: &Fields) -> Tokens {
+    let decoded_fields = fields.iter().enumerate().map(|(i, field)| {
+        // name fields in errors like "Global.init" or "Instr::BrTable.1", but just "Instr::Call"
+        // for a single unnamed field
+        let field_context = match field.ident {
+            Some(ref field_name) => format!("{}.{}", context, field_name),
+            None if fields.iter().count() == 1 => context.to_string(),
+            None => format!("{}.{}", context, i),
+        };
+        decode_field(field, &field_context)
+    });
     match *fields {
         Fields::Unit => quote!(#name),
         Fields::Unnamed(_) => quote!(#name( #( #decoded_fields ),* )),
@@ -104,10 +149,10 @@ fn decode_fields(name: &TypePath, fields: &Fields) -> Tokens {
     }
 }
 
-fn decode_field(field: &Field) -> Tokens {
+fn decode_field(field: &Field, context: &str) -> Tokens {
     let field_name = field.ident;
     let field_ty = remove_type_arguments(&field.ty);
-    quote!( #( #field_name: )* #field_ty::decode(reader)? )
+    quote!( #( #field_name: )* #field_ty::decode(reader).map_err(|e| Self::field_context(e, #context))? )
 }
 
 /// Transform, e.g., Vec<T> into just Vec. Useful when calling trait methods on a generic type, i.e.,
@@ -125,21 +170,39 @@ fn remove_type_arguments(ty: &Type) -> Type {
     ty
 }
 
-/* for encode() */
+/* for encode() and encoded_size() */
+
+/// method_call is, e.g., "encode(writer)?", and is called on the tag and every field
+fn encode_data(input: &DeriveInput, leb128_tags: bool, method_call: &Tokens) -> Tokens {
+    let data_name = &input.ident;
+    match &input.data {
+        &Data::Struct(DataStruct { ref fields, .. }) => {
+            let tag: Option<u8> = attributes_to_tag(&input.attrs);
+            encode_fields(&parse_quote!(#data_name), tag.map(|tag| quote!(#tag)), &fields, method_call)
+        }
+        &Data::Enum(DataEnum { ref variants, .. }) => {
+            let encode_variants = variants.iter().map(|variant| encode_variant(data_name, variant, leb128_tags, method_call));
+            quote!( #( #encode_variants ),* )
+        }
+        _ => unimplemented!("can only derive(WasmBinary) for structs and enums")
+    }
+}
 
-fn encode_variant(super_name: &Ident, variant: &Variant) -> Tokens {
-    let tag = attributes_to_tag(&variant.attrs);
+fn encode_variant(super_name: &Ident, variant: &Variant, leb128_tags: bool, method_call: &Tokens) -> Tokens {
+    let tag = variant_tag(variant, leb128_tags);
     let variant_name = &variant.ident;
     let name = parse_quote!(#super_name::#variant_name);
 
-    encode_fields(&name, tag, &variant.fields)
+    encode_fields(&name, tag, &variant.fields, method_call)
 }
 
-fn encode_fields(name: &TypePath, tag: Option<u8>, fields: &Fields) -> Tokens {
+fn encode_fields(name: &TypePath, tag: Option<Tokens>, fields: &Fields, method_call: &Tokens) -> Tokens {
     let field_names: &Vec<_> = &fields.iter().enumerate().map(encode_field_name).collect();
+    let encode_tag = tag.map(|tag| quote!( bytes_written += #tag.#method_call; ));
+    let encode_fields = field_names.iter().map(|field_name| quote!( bytes_written += #field_name.#method_call; ));
     let body = quote!({
-        #( bytes_written += #tag.encode(writer)?; )*
-        #( bytes_written += #field_names.encode(writer)? );*
+        #( #encode_tag )*
+        #( #encode_fields )*
     });
     match *fields {
         Fields::Unit => quote!(#name => #body),
@@ -150,4 +213,4 @@ fn encode_fields(name: &TypePath, tag: Option<u8>, fields: &Fields) -> Tokens {
 
 fn encode_field_name((i, field): (usize, &Field)) -> Ident {
     field.ident.unwrap_or(Ident::from(format!("_{}", i)))
-}
\ No newline at end of file
+}
@@ -13,6 +13,8 @@ pub enum Val {
     I64(i64),
     F32(f32),
     F64(f64),
+    // SIMD proposal, lanes are in little-endian order (i.e., lane 0 in the lowest bits)
+    V128(u128),
 }
 
 impl Val {
@@ -22,6 +24,7 @@ impl Val {
             Val::I64(_) => ValType::I64,
             Val::F32(_) => ValType::F32,
             Val::F64(_) => ValType::F64,
+            Val::V128(_) => ValType::V128,
         }
     }
 }
@@ -36,6 +39,11 @@ pub enum ValType {
     #[tag = 0x7e] I64,
     #[tag = 0x7d] F32,
     #[tag = 0x7c] F64,
+    // SIMD proposal
+    #[tag = 0x7b] V128,
+    // reference types proposal
+    #[tag = 0x70] FuncRef,
+    #[tag = 0x6f] ExternRef,
 }
 
 impl fmt::Display for ValType {
@@ -53,6 +61,16 @@ impl ValType {
             ValType::I64 => 'I',
             ValType::F32 => 'f',
             ValType::F64 => 'F',
+            ValType::V128 => 'v',
+            ValType::FuncRef => 'r',
+            ValType::ExternRef => 'x',
+        }
+    }
+
+    pub fn is_ref(&self) -> bool {
+        match *self {
+            ValType::FuncRef | ValType::ExternRef => true,
+            _ => false,
         }
     }
 }
@@ -73,6 +91,13 @@ impl InstrType {
             results: results.into(),
         }
     }
+
+    /// memory64: replaces the (i32) address, which is always the first input, by the index type
+    /// of the accessed memory
+    pub fn with_address_type(mut self, index_type: ValType) -> Self {
+        self.inputs[0] = index_type;
+        self
+    }
 }
 
 // convert between function and instruction types
@@ -88,33 +113,52 @@ impl<'a> From<&'a InstrType> for FunctionType {
     }
 }
 
-#[derive(WasmBinary, Debug, Clone, PartialEq, Eq, Hash, Serialize, new)]
+#[derive(WasmBinary, Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, new)]
 #[tag = 0x60]
 pub struct FunctionType {
     pub params: Vec<ValType>,
     pub results: Vec<ValType>,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
-pub struct BlockType(pub Option<ValType>);
-
 #[derive(WasmBinary, Debug, Clone)]
 pub struct TableType(pub ElemType, pub Limits);
 
-#[derive(WasmBinary, Debug, Copy, Clone)]
+#[derive(WasmBinary, Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum ElemType {
-    // only value in WASM version 1
-    #[tag = 0x70]
-    Anyfunc,
+    // called anyfunc in WASM version 1, where it was the only element type
+    #[tag = 0x70] FuncRef,
+    // reference types proposal
+    #[tag = 0x6f] ExternRef,
+}
+
+impl ElemType {
+    pub fn to_type(&self) -> ValType {
+        match *self {
+            ElemType::FuncRef => ValType::FuncRef,
+            ElemType::ExternRef => ValType::ExternRef,
+        }
+    }
 }
 
 #[derive(WasmBinary, Debug, Clone)]
 pub struct MemoryType(pub Limits);
 
+impl MemoryType {
+    /// type of addresses and sizes (in pages) of this memory
+    pub fn index_type(&self) -> ValType {
+        if self.0.memory64 { ValType::I64 } else { ValType::I32 }
+    }
+}
+
 #[derive(Debug, Copy, Clone)]
 pub struct Limits {
-    pub initial_size: u32,
-    pub max_size: Option<u32>,
+    // 64-bit only because of the memory64 proposal, otherwise at most u32
+    pub initial_size: u64,
+    pub max_size: Option<u64>,
+    // threads proposal, only for memories
+    pub shared: bool,
+    // memory64 proposal, only for memories
+    pub memory64: bool,
 }
 
 #[derive(WasmBinary, Debug, Copy, Clone)]
@@ -204,5 +248,6 @@ pub struct Label;
 #[derive(WasmBinary, Debug, Copy, Clone, Default, Eq, PartialEq, Hash)]
 pub struct Memarg {
     pub alignment: u32,
-    pub offset: u32,
+    // 64-bit offsets only for memories with memory64
+    pub offset: u64,
 }
\ No newline at end of file
@@ -1,7 +1,10 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::io;
+use WasmBinary;
 use super::*;
 use super::highlevel as hl;
 use super::lowlevel as ll;
+use super::remap::{IndexRemapping, RemapIndices, check_custom_sections, remap_custom_section};
 use rayon::prelude::*;
 
 /* Conversions between high-level and low-level AST. */
@@ -9,14 +12,21 @@ use rayon::prelude::*;
 
 /* From low-level to high-level. */
 
+/// NOTE cannot reject relocatable object files, see remap::check_custom_sections (which the
+/// fallible constructors of hl::Module, e.g., from_bytes(), do)
 impl From<ll::Module> for hl::Module {
     fn from(ll::Module { sections }: ll::Module) -> Self {
         let mut module = hl::Module::default();
         let mut types: Vec<FunctionType> = Vec::new();
+        // applied only after all functions are known, since custom sections can appear anywhere
+        let mut name_section = None;
 
         for section in sections {
             match section {
-                ll::Section::Custom(bytes) => module.custom_sections.push(bytes),
+                ll::Section::Custom(bytes) => match decode_name_section(&bytes) {
+                    Some(names) => name_section = Some(names),
+                    None => module.custom_sections.push(bytes),
+                },
                 ll::Section::Type(ll::WithSize(types_)) => types = types_,
 
                 /* Imported functions, tables, memories, and globals are first added to the respective index spaces... */
@@ -31,17 +41,17 @@ impl From<ll::Module> for hl::Module {
                                 import,
                                 code: None,
                                 export,
+                                name: None,
+                                local_names: BTreeMap::new(),
                             }),
                             ll::ImportType::Table(type_) => module.tables.push(hl::Table {
                                 type_,
                                 import,
-                                elements: Vec::new(),
                                 export,
                             }),
                             ll::ImportType::Memory(type_) => module.memories.push(hl::Memory {
                                 type_,
                                 import,
-                                data: Vec::new(),
                                 export,
                             }),
                             ll::ImportType::Global(type_) => module.globals.push(hl::Global {
@@ -49,6 +59,12 @@ impl From<ll::Module> for hl::Module {
                                 import,
                                 init: None,
                                 export,
+                                name: None,
+                            }),
+                            ll::ImportType::Tag(ll::TagType(type_idx)) => module.tags.push(hl::Tag {
+                                type_: types[type_idx.0].clone(),
+                                import,
+                                export,
                             }),
                         }
                     }
@@ -63,6 +79,8 @@ impl From<ll::Module> for hl::Module {
                             import: None,
                             code: None,
                             export: Vec::new(),
+                            name: None,
+                            local_names: BTreeMap::new(),
                         });
                     }
                 }
@@ -71,7 +89,6 @@ impl From<ll::Module> for hl::Module {
                         module.tables.push(hl::Table {
                             type_,
                             import: None,
-                            elements: Vec::new(),
                             export: Vec::new(),
                         });
                     }
@@ -81,7 +98,15 @@ impl From<ll::Module> for hl::Module {
                         module.memories.push(hl::Memory {
                             type_,
                             import: None,
-                            data: Vec::new(),
+                            export: Vec::new(),
+                        });
+                    }
+                }
+                ll::Section::Tag(ll::WithSize(tags)) => {
+                    for ll::TagType(type_idx) in tags {
+                        module.tags.push(hl::Tag {
+                            type_: types[type_idx.0].clone(),
+                            import: None,
                             export: Vec::new(),
                         });
                     }
@@ -93,6 +118,7 @@ impl From<ll::Module> for hl::Module {
                             import: None,
                             init: Some(from_lowlevel_expr(init, &types)),
                             export: Vec::new(),
+                            name: None,
                         });
                     }
                 }
@@ -106,6 +132,7 @@ impl From<ll::Module> for hl::Module {
                             ll::ExportType::Table(idx) => module.tables[idx.0].export.push(name),
                             ll::ExportType::Memory(idx) => module.memories[idx.0].export.push(name),
                             ll::ExportType::Global(idx) => module.globals[idx.0].export.push(name),
+                            ll::ExportType::Tag(idx) => module.tags[idx.0].export.push(name),
                         }
                     }
                 }
@@ -114,13 +141,27 @@ impl From<ll::Module> for hl::Module {
                 /* Finally, all "contents" of the already declared functions/tables/memories. */
 
                 ll::Section::Element(ll::WithSize(elements)) => {
-                    for element in elements {
-                        module.tables[element.table_idx.0].elements.push(hl::Element {
-                            offset: from_lowlevel_expr(element.offset, &types),
-                            functions: element.init.into_iter().map(|idx| idx.0.into()).collect(),
+                    for ll::Element { mode, init } in elements {
+                        module.elements.push(hl::Element {
+                            mode: match mode {
+                                ll::ElementMode::Active { table_idx, offset } => hl::ElementMode::Active {
+                                    table_idx: table_idx.0.into(),
+                                    offset: from_lowlevel_expr(offset, &types),
+                                },
+                                ll::ElementMode::Passive => hl::ElementMode::Passive,
+                                ll::ElementMode::Declarative => hl::ElementMode::Declarative,
+                            },
+                            items: match init {
+                                ll::ElementItems::Functions(functions) =>
+                                    hl::ElementItems::Functions(functions.into_iter().map(|idx| idx.0.into()).collect()),
+                                ll::ElementItems::Exprs(elem_type, exprs) =>
+                                    hl::ElementItems::Exprs(elem_type, exprs.into_iter().map(|expr| from_lowlevel_expr(expr, &types)).collect()),
+                            },
                         })
                     }
                 }
+                // only needed for single-pass validation by engines, recomputed when encoding
+                ll::Section::DataCount(_) => {}
                 ll::Section::Code(ll::WithSize(code)) => {
                     let imported_function_count = module.functions.iter()
                         .filter(|f| f.import.is_some())
@@ -133,20 +174,147 @@ impl From<ll::Module> for hl::Module {
                     }
                 }
                 ll::Section::Data(ll::WithSize(data)) => {
-                    for data in data {
-                        module.memories[data.memory_idx.0].data.push(hl::Data {
-                            offset: from_lowlevel_expr(data.offset, &types),
-                            bytes: data.init,
+                    for ll::Data { mode, init } in data {
+                        module.data.push(hl::Data {
+                            mode: match mode {
+                                ll::DataMode::Active { memory_idx, offset } => hl::DataMode::Active {
+                                    memory_idx: memory_idx.0.into(),
+                                    offset: from_lowlevel_expr(offset, &types),
+                                },
+                                ll::DataMode::Passive => hl::DataMode::Passive,
+                            },
+                            bytes: init,
                         })
                     }
                 }
             }
         }
 
+        if let Some(names) = name_section {
+            apply_name_section(&mut module, names);
+        }
+
         module
     }
 }
 
+/// decodes the function bodies straight to high-level (in parallel), such that the low-level bodies
+/// of the whole code section are never in memory at the same time
+impl<'a> ll::LazyModule<'a> {
+    pub fn into_highlevel(self) -> io::Result<hl::Module> {
+        check_custom_sections(&self.sections)?;
+        check_body_count(&self.sections, self.bodies.len())?;
+        let types = self.sections.iter()
+            .filter_map(|section| match *section {
+                ll::Section::Type(ll::WithSize(ref types)) => Some(types.clone()),
+                _ => None,
+            })
+            .last()
+            .unwrap_or_default();
+        // each low-level body is dropped right after its conversion, i.e., only the high-level code
+        // is ever complete in memory
+        let code_hl: io::Result<Vec<_>> = (0..self.bodies.len()).into_par_iter().map(|i| -> io::Result<hl::Code> {
+            Ok(from_lowlevel_code(self.decode_body(i)?, &types))
+        }).collect();
+        let code_hl = code_hl?;
+
+        // the code section is empty, so all functions are without code so far
+        let mut module = hl::Module::from(ll::Module { sections: self.sections });
+        let imported_function_count = module.functions.iter()
+            .filter(|f| f.import.is_some())
+            .count();
+        for (i, code) in code_hl.into_iter().enumerate() {
+            module.functions[imported_function_count + i].code = Some(code);
+        }
+        Ok(module)
+    }
+
+    /// only types, imports, exports, globals etc., i.e., no function body is decoded and all
+    /// functions have no code (so the result cannot be encoded again), e.g., for static info
+    pub fn into_highlevel_without_code(self) -> io::Result<hl::Module> {
+        check_custom_sections(&self.sections)?;
+        check_body_count(&self.sections, self.bodies.len())?;
+        Ok(hl::Module::from(ll::Module { sections: self.sections }))
+    }
+}
+
+/// copies names, data etc. only here, function bodies are decoded straight from the borrowed slice
+impl<'a> ll::BorrowedModule<'a> {
+    pub fn into_highlevel(self) -> io::Result<hl::Module> {
+        self.into_lazy().into_highlevel()
+    }
+}
+
+/// the conversion to high-level assigns the i-th body to the i-th non-imported function, so
+/// reject malformed binaries up front instead of panicking (like Module::decode_strict does)
+fn check_body_count(sections: &[ll::Section], body_count: usize) -> io::Result<()> {
+    let function_count = sections.iter()
+        .map(|section| match *section {
+            ll::Section::Function(ll::WithSize(ref functions)) => functions.len(),
+            _ => 0,
+        })
+        .sum::<usize>();
+    if function_count != body_count {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, format!("function and code section have inconsistent lengths: {} functions, but {} bodies", function_count, body_count)));
+    }
+    Ok(())
+}
+
+impl ll::Module {
+    /// see check_body_count, for the fallible constructors of hl::Module (e.g., from_bytes())
+    pub(crate) fn check_body_count(&self) -> io::Result<()> {
+        let body_count = self.sections.iter()
+            .map(|section| match *section {
+                ll::Section::Code(ll::WithSize(ref code)) => code.len(),
+                _ => 0,
+            })
+            .sum();
+        check_body_count(&self.sections, body_count)
+    }
+}
+
+/// returns None if the custom section is not a name section, or if the name section is malformed
+/// (then it is kept as an opaque custom section, since it is only debug info anyway)
+fn decode_name_section(custom_section: &[u8]) -> Option<ll::NameSection> {
+    let mut reader = custom_section;
+    match String::decode(&mut reader) {
+        Ok(ref name) if name == "name" => ll::NameSection::decode(&mut reader).ok(),
+        _ => None
+    }
+}
+
+fn apply_name_section(module: &mut hl::Module, names: ll::NameSection) {
+    // NOTE engines do not validate the name section, so silently ignore out-of-bounds indices
+    for subsection in names.subsections {
+        match subsection {
+            ll::NameSubSection::Module(ll::WithSize(name)) => module.name = Some(name),
+            ll::NameSubSection::Function(ll::WithSize(function_names)) => {
+                for ll::FunctionName { function_idx, name } in function_names {
+                    if let Some(function) = module.functions.get_mut(function_idx.0) {
+                        function.name = Some(name);
+                    }
+                }
+            }
+            ll::NameSubSection::Local(ll::WithSize(local_names)) => {
+                for ll::LocalNames { function_idx, names } in local_names {
+                    if let Some(function) = module.functions.get_mut(function_idx.0) {
+                        for ll::LocalName { local_idx, name } in names {
+                            function.local_names.insert(local_idx, name);
+                        }
+                    }
+                }
+            }
+            ll::NameSubSection::Global(ll::WithSize(global_names)) => {
+                for ll::GlobalName { global_idx, name } in global_names {
+                    if let Some(global) = module.globals.get_mut(global_idx.0) {
+                        global.name = Some(name);
+                    }
+                }
+            }
+        }
+    }
+}
+
 fn from_lowlevel_code(code: ll::Code, types: &[FunctionType]) -> hl::Code {
     let mut locals = Vec::new();
     for local in code.locals {
@@ -164,17 +332,32 @@ fn from_lowlevel_expr(expr: ll::Expr, types: &[FunctionType]) -> hl::Expr {
     expr.0.into_iter().map(|instr| from_lowlevel_instr(instr, types)).collect()
 }
 
+fn from_lowlevel_block_type(block_type: ll::BlockType, types: &[FunctionType]) -> FunctionType {
+    match block_type {
+        ll::BlockType::Empty => FunctionType::default(),
+        ll::BlockType::Value(ty) => FunctionType::new(vec![], vec![ty]),
+        ll::BlockType::FunctionType(type_idx) => types[type_idx.0].clone(),
+    }
+}
+
 fn from_lowlevel_instr(instr: ll::Instr, types: &[FunctionType]) -> hl::Instr {
     match instr {
         ll::Instr::Unreachable => hl::Instr::Unreachable,
         ll::Instr::Nop => hl::Instr::Nop,
 
-        ll::Instr::Block(block_type) => hl::Instr::Block(block_type),
-        ll::Instr::Loop(block_type) => hl::Instr::Loop(block_type),
-        ll::Instr::If(block_type) => hl::Instr::If(block_type),
+        ll::Instr::Block(block_type) => hl::Instr::Block(from_lowlevel_block_type(block_type, types)),
+        ll::Instr::Loop(block_type) => hl::Instr::Loop(from_lowlevel_block_type(block_type, types)),
+        ll::Instr::If(block_type) => hl::Instr::If(from_lowlevel_block_type(block_type, types)),
         ll::Instr::Else => hl::Instr::Else,
         ll::Instr::End => hl::Instr::End,
 
+        ll::Instr::Try(block_type) => hl::Instr::Try(from_lowlevel_block_type(block_type, types)),
+        ll::Instr::Catch(tag_idx) => hl::Instr::Catch(tag_idx.0.into()),
+        ll::Instr::CatchAll => hl::Instr::CatchAll,
+        ll::Instr::Delegate(label_idx) => hl::Instr::Delegate(label_idx),
+        ll::Instr::Throw(tag_idx) => hl::Instr::Throw(tag_idx.0.into()),
+        ll::Instr::Rethrow(label_idx) => hl::Instr::Rethrow(label_idx),
+
         ll::Instr::Br(label_idx) => hl::Instr::Br(label_idx),
         ll::Instr::BrIf(label_idx) => hl::Instr::BrIf(label_idx),
         ll::Instr::BrTable(label_idx_table, default) => hl::Instr::BrTable(label_idx_table, default),
@@ -182,9 +365,13 @@ fn from_lowlevel_instr(instr: ll::Instr, types: &[FunctionType]) -> hl::Instr {
         ll::Instr::Return => hl::Instr::Return,
         ll::Instr::Call(function_idx) => hl::Instr::Call(function_idx.0.into()),
         ll::Instr::CallIndirect(type_idx, table_idx) => hl::Instr::CallIndirect(types[type_idx.0].clone(), table_idx.0.into()),
+        ll::Instr::ReturnCall(function_idx) => hl::Instr::ReturnCall(function_idx.0.into()),
+        ll::Instr::ReturnCallIndirect(type_idx, table_idx) => hl::Instr::ReturnCallIndirect(types[type_idx.0].clone(), table_idx.0.into()),
 
         ll::Instr::Drop => hl::Instr::Drop,
         ll::Instr::Select => hl::Instr::Select,
+        // the encoding allows a vector of types, but exactly one is valid
+        ll::Instr::TypedSelect(val_types) => hl::Instr::TypedSelect(val_types[0]),
 
         ll::Instr::GetLocal(local_idx) => hl::Instr::Local(hl::LocalOp::GetLocal, local_idx.0.into()),
         ll::Instr::SetLocal(local_idx) => hl::Instr::Local(hl::LocalOp::SetLocal, local_idx.0.into()),
@@ -192,33 +379,51 @@ fn from_lowlevel_instr(instr: ll::Instr, types: &[FunctionType]) -> hl::Instr {
         ll::Instr::GetGlobal(global_idx) => hl::Instr::Global(hl::GlobalOp::GetGlobal, global_idx.0.into()),
         ll::Instr::SetGlobal(global_idx) => hl::Instr::Global(hl::GlobalOp::SetGlobal, global_idx.0.into()),
 
-        ll::Instr::I32Load(memarg) => hl::Instr::Load(hl::LoadOp::I32Load, memarg),
-        ll::Instr::I64Load(memarg) => hl::Instr::Load(hl::LoadOp::I64Load, memarg),
-        ll::Instr::F32Load(memarg) => hl::Instr::Load(hl::LoadOp::F32Load, memarg),
-        ll::Instr::F64Load(memarg) => hl::Instr::Load(hl::LoadOp::F64Load, memarg),
-        ll::Instr::I32Load8S(memarg) => hl::Instr::Load(hl::LoadOp::I32Load8S, memarg),
-        ll::Instr::I32Load8U(memarg) => hl::Instr::Load(hl::LoadOp::I32Load8U, memarg),
-        ll::Instr::I32Load16S(memarg) => hl::Instr::Load(hl::LoadOp::I32Load16S, memarg),
-        ll::Instr::I32Load16U(memarg) => hl::Instr::Load(hl::LoadOp::I32Load16U, memarg),
-        ll::Instr::I64Load8S(memarg) => hl::Instr::Load(hl::LoadOp::I64Load8S, memarg),
-        ll::Instr::I64Load8U(memarg) => hl::Instr::Load(hl::LoadOp::I64Load8U, memarg),
-        ll::Instr::I64Load16S(memarg) => hl::Instr::Load(hl::LoadOp::I64Load16S, memarg),
-        ll::Instr::I64Load16U(memarg) => hl::Instr::Load(hl::LoadOp::I64Load16U, memarg),
-        ll::Instr::I64Load32S(memarg) => hl::Instr::Load(hl::LoadOp::I64Load32S, memarg),
-        ll::Instr::I64Load32U(memarg) => hl::Instr::Load(hl::LoadOp::I64Load32U, memarg),
-        ll::Instr::I32Store(memarg) => hl::Instr::Store(hl::StoreOp::I32Store, memarg),
-        ll::Instr::I64Store(memarg) => hl::Instr::Store(hl::StoreOp::I64Store, memarg),
-        ll::Instr::F32Store(memarg) => hl::Instr::Store(hl::StoreOp::F32Store, memarg),
-        ll::Instr::F64Store(memarg) => hl::Instr::Store(hl::StoreOp::F64Store, memarg),
-        ll::Instr::I32Store8(memarg) => hl::Instr::Store(hl::StoreOp::I32Store8, memarg),
-        ll::Instr::I32Store16(memarg) => hl::Instr::Store(hl::StoreOp::I32Store16, memarg),
-        ll::Instr::I64Store8(memarg) => hl::Instr::Store(hl::StoreOp::I64Store8, memarg),
-        ll::Instr::I64Store16(memarg) => hl::Instr::Store(hl::StoreOp::I64Store16, memarg),
-        ll::Instr::I64Store32(memarg) => hl::Instr::Store(hl::StoreOp::I64Store32, memarg),
+        ll::Instr::TableGet(table_idx) => hl::Instr::TableGet(table_idx.0.into()),
+        ll::Instr::TableSet(table_idx) => hl::Instr::TableSet(table_idx.0.into()),
+
+        ll::Instr::I32Load(ll::MemoryMemarg(memory_idx, memarg)) => hl::Instr::Load(hl::LoadOp::I32Load, memory_idx.0.into(), memarg),
+        ll::Instr::I64Load(ll::MemoryMemarg(memory_idx, memarg)) => hl::Instr::Load(hl::LoadOp::I64Load, memory_idx.0.into(), memarg),
+        ll::Instr::F32Load(ll::MemoryMemarg(memory_idx, memarg)) => hl::Instr::Load(hl::LoadOp::F32Load, memory_idx.0.into(), memarg),
+        ll::Instr::F64Load(ll::MemoryMemarg(memory_idx, memarg)) => hl::Instr::Load(hl::LoadOp::F64Load, memory_idx.0.into(), memarg),
+        ll::Instr::I32Load8S(ll::MemoryMemarg(memory_idx, memarg)) => hl::Instr::Load(hl::LoadOp::I32Load8S, memory_idx.0.into(), memarg),
+        ll::Instr::I32Load8U(ll::MemoryMemarg(memory_idx, memarg)) => hl::Instr::Load(hl::LoadOp::I32Load8U, memory_idx.0.into(), memarg),
+        ll::Instr::I32Load16S(ll::MemoryMemarg(memory_idx, memarg)) => hl::Instr::Load(hl::LoadOp::I32Load16S, memory_idx.0.into(), memarg),
+        ll::Instr::I32Load16U(ll::MemoryMemarg(memory_idx, memarg)) => hl::Instr::Load(hl::LoadOp::I32Load16U, memory_idx.0.into(), memarg),
+        ll::Instr::I64Load8S(ll::MemoryMemarg(memory_idx, memarg)) => hl::Instr::Load(hl::LoadOp::I64Load8S, memory_idx.0.into(), memarg),
+        ll::Instr::I64Load8U(ll::MemoryMemarg(memory_idx, memarg)) => hl::Instr::Load(hl::LoadOp::I64Load8U, memory_idx.0.into(), memarg),
+        ll::Instr::I64Load16S(ll::MemoryMemarg(memory_idx, memarg)) => hl::Instr::Load(hl::LoadOp::I64Load16S, memory_idx.0.into(), memarg),
+        ll::Instr::I64Load16U(ll::MemoryMemarg(memory_idx, memarg)) => hl::Instr::Load(hl::LoadOp::I64Load16U, memory_idx.0.into(), memarg),
+        ll::Instr::I64Load32S(ll::MemoryMemarg(memory_idx, memarg)) => hl::Instr::Load(hl::LoadOp::I64Load32S, memory_idx.0.into(), memarg),
+        ll::Instr::I64Load32U(ll::MemoryMemarg(memory_idx, memarg)) => hl::Instr::Load(hl::LoadOp::I64Load32U, memory_idx.0.into(), memarg),
+        ll::Instr::I32Store(ll::MemoryMemarg(memory_idx, memarg)) => hl::Instr::Store(hl::StoreOp::I32Store, memory_idx.0.into(), memarg),
+        ll::Instr::I64Store(ll::MemoryMemarg(memory_idx, memarg)) => hl::Instr::Store(hl::StoreOp::I64Store, memory_idx.0.into(), memarg),
+        ll::Instr::F32Store(ll::MemoryMemarg(memory_idx, memarg)) => hl::Instr::Store(hl::StoreOp::F32Store, memory_idx.0.into(), memarg),
+        ll::Instr::F64Store(ll::MemoryMemarg(memory_idx, memarg)) => hl::Instr::Store(hl::StoreOp::F64Store, memory_idx.0.into(), memarg),
+        ll::Instr::I32Store8(ll::MemoryMemarg(memory_idx, memarg)) => hl::Instr::Store(hl::StoreOp::I32Store8, memory_idx.0.into(), memarg),
+        ll::Instr::I32Store16(ll::MemoryMemarg(memory_idx, memarg)) => hl::Instr::Store(hl::StoreOp::I32Store16, memory_idx.0.into(), memarg),
+        ll::Instr::I64Store8(ll::MemoryMemarg(memory_idx, memarg)) => hl::Instr::Store(hl::StoreOp::I64Store8, memory_idx.0.into(), memarg),
+        ll::Instr::I64Store16(ll::MemoryMemarg(memory_idx, memarg)) => hl::Instr::Store(hl::StoreOp::I64Store16, memory_idx.0.into(), memarg),
+        ll::Instr::I64Store32(ll::MemoryMemarg(memory_idx, memarg)) => hl::Instr::Store(hl::StoreOp::I64Store32, memory_idx.0.into(), memarg),
 
         ll::Instr::MemorySize(memory_idx) => hl::Instr::MemorySize(memory_idx.0.into()),
         ll::Instr::MemoryGrow(memory_idx) => hl::Instr::MemoryGrow(memory_idx.0.into()),
 
+        ll::Instr::Misc(ll::MiscInstr::MemoryInit(data_idx, memory_idx)) => hl::Instr::MemoryInit(data_idx.0.into(), memory_idx.0.into()),
+        ll::Instr::Misc(ll::MiscInstr::DataDrop(data_idx)) => hl::Instr::DataDrop(data_idx.0.into()),
+        ll::Instr::Misc(ll::MiscInstr::MemoryCopy(dst_idx, src_idx)) => hl::Instr::MemoryCopy(dst_idx.0.into(), src_idx.0.into()),
+        ll::Instr::Misc(ll::MiscInstr::MemoryFill(memory_idx)) => hl::Instr::MemoryFill(memory_idx.0.into()),
+        ll::Instr::Misc(ll::MiscInstr::TableInit(element_idx, table_idx)) => hl::Instr::TableInit(element_idx.0.into(), table_idx.0.into()),
+        ll::Instr::Misc(ll::MiscInstr::ElemDrop(element_idx)) => hl::Instr::ElemDrop(element_idx.0.into()),
+        ll::Instr::Misc(ll::MiscInstr::TableCopy(dst_idx, src_idx)) => hl::Instr::TableCopy(dst_idx.0.into(), src_idx.0.into()),
+        ll::Instr::Misc(ll::MiscInstr::TableGrow(table_idx)) => hl::Instr::TableGrow(table_idx.0.into()),
+        ll::Instr::Misc(ll::MiscInstr::TableSize(table_idx)) => hl::Instr::TableSize(table_idx.0.into()),
+        ll::Instr::Misc(ll::MiscInstr::TableFill(table_idx)) => hl::Instr::TableFill(table_idx.0.into()),
+
+        ll::Instr::RefNull(elem_type) => hl::Instr::RefNull(elem_type),
+        ll::Instr::RefIsNull => hl::Instr::RefIsNull,
+        ll::Instr::RefFunc(function_idx) => hl::Instr::RefFunc(function_idx.0.into()),
+
         ll::Instr::I32Const(immediate) => hl::Instr::Const(Val::I32(immediate)),
         ll::Instr::I64Const(immediate) => hl::Instr::Const(Val::I64(immediate)),
         ll::Instr::F32Const(immediate) => hl::Instr::Const(Val::F32(immediate)),
@@ -347,6 +552,322 @@ fn from_lowlevel_instr(instr: ll::Instr, types: &[FunctionType]) -> hl::Instr {
         ll::Instr::I64ReinterpretF64 => hl::Instr::Numeric(hl::NumericOp::I64ReinterpretF64),
         ll::Instr::F32ReinterpretI32 => hl::Instr::Numeric(hl::NumericOp::F32ReinterpretI32),
         ll::Instr::F64ReinterpretI64 => hl::Instr::Numeric(hl::NumericOp::F64ReinterpretI64),
+        ll::Instr::I32Extend8S => hl::Instr::Numeric(hl::NumericOp::I32Extend8S),
+        ll::Instr::I32Extend16S => hl::Instr::Numeric(hl::NumericOp::I32Extend16S),
+        ll::Instr::I64Extend8S => hl::Instr::Numeric(hl::NumericOp::I64Extend8S),
+        ll::Instr::I64Extend16S => hl::Instr::Numeric(hl::NumericOp::I64Extend16S),
+        ll::Instr::I64Extend32S => hl::Instr::Numeric(hl::NumericOp::I64Extend32S),
+        ll::Instr::Misc(ll::MiscInstr::I32TruncSatSF32) => hl::Instr::Numeric(hl::NumericOp::I32TruncSatSF32),
+        ll::Instr::Misc(ll::MiscInstr::I32TruncSatUF32) => hl::Instr::Numeric(hl::NumericOp::I32TruncSatUF32),
+        ll::Instr::Misc(ll::MiscInstr::I32TruncSatSF64) => hl::Instr::Numeric(hl::NumericOp::I32TruncSatSF64),
+        ll::Instr::Misc(ll::MiscInstr::I32TruncSatUF64) => hl::Instr::Numeric(hl::NumericOp::I32TruncSatUF64),
+        ll::Instr::Misc(ll::MiscInstr::I64TruncSatSF32) => hl::Instr::Numeric(hl::NumericOp::I64TruncSatSF32),
+        ll::Instr::Misc(ll::MiscInstr::I64TruncSatUF32) => hl::Instr::Numeric(hl::NumericOp::I64TruncSatUF32),
+        ll::Instr::Misc(ll::MiscInstr::I64TruncSatSF64) => hl::Instr::Numeric(hl::NumericOp::I64TruncSatSF64),
+        ll::Instr::Misc(ll::MiscInstr::I64TruncSatUF64) => hl::Instr::Numeric(hl::NumericOp::I64TruncSatUF64),
+        ll::Instr::Simd(ll::SimdInstr::V128Load(ll::MemoryMemarg(memory_idx, memarg))) => hl::Instr::Load(hl::LoadOp::V128Load, memory_idx.0.into(), memarg),
+        ll::Instr::Simd(ll::SimdInstr::V128Load8x8S(ll::MemoryMemarg(memory_idx, memarg))) => hl::Instr::Load(hl::LoadOp::V128Load8x8S, memory_idx.0.into(), memarg),
+        ll::Instr::Simd(ll::SimdInstr::V128Load8x8U(ll::MemoryMemarg(memory_idx, memarg))) => hl::Instr::Load(hl::LoadOp::V128Load8x8U, memory_idx.0.into(), memarg),
+        ll::Instr::Simd(ll::SimdInstr::V128Load16x4S(ll::MemoryMemarg(memory_idx, memarg))) => hl::Instr::Load(hl::LoadOp::V128Load16x4S, memory_idx.0.into(), memarg),
+        ll::Instr::Simd(ll::SimdInstr::V128Load16x4U(ll::MemoryMemarg(memory_idx, memarg))) => hl::Instr::Load(hl::LoadOp::V128Load16x4U, memory_idx.0.into(), memarg),
+        ll::Instr::Simd(ll::SimdInstr::V128Load32x2S(ll::MemoryMemarg(memory_idx, memarg))) => hl::Instr::Load(hl::LoadOp::V128Load32x2S, memory_idx.0.into(), memarg),
+        ll::Instr::Simd(ll::SimdInstr::V128Load32x2U(ll::MemoryMemarg(memory_idx, memarg))) => hl::Instr::Load(hl::LoadOp::V128Load32x2U, memory_idx.0.into(), memarg),
+        ll::Instr::Simd(ll::SimdInstr::V128Load8Splat(ll::MemoryMemarg(memory_idx, memarg))) => hl::Instr::Load(hl::LoadOp::V128Load8Splat, memory_idx.0.into(), memarg),
+        ll::Instr::Simd(ll::SimdInstr::V128Load16Splat(ll::MemoryMemarg(memory_idx, memarg))) => hl::Instr::Load(hl::LoadOp::V128Load16Splat, memory_idx.0.into(), memarg),
+        ll::Instr::Simd(ll::SimdInstr::V128Load32Splat(ll::MemoryMemarg(memory_idx, memarg))) => hl::Instr::Load(hl::LoadOp::V128Load32Splat, memory_idx.0.into(), memarg),
+        ll::Instr::Simd(ll::SimdInstr::V128Load64Splat(ll::MemoryMemarg(memory_idx, memarg))) => hl::Instr::Load(hl::LoadOp::V128Load64Splat, memory_idx.0.into(), memarg),
+        ll::Instr::Simd(ll::SimdInstr::V128Store(ll::MemoryMemarg(memory_idx, memarg))) => hl::Instr::Store(hl::StoreOp::V128Store, memory_idx.0.into(), memarg),
+        ll::Instr::Simd(ll::SimdInstr::V128Const(immediate)) => hl::Instr::Const(Val::V128(immediate)),
+        ll::Instr::Simd(ll::SimdInstr::I8x16Shuffle(lanes)) => hl::Instr::Shuffle(lanes),
+        ll::Instr::Simd(ll::SimdInstr::I8x16Swizzle) => hl::Instr::Numeric(hl::NumericOp::I8x16Swizzle),
+        ll::Instr::Simd(ll::SimdInstr::I8x16Splat) => hl::Instr::Numeric(hl::NumericOp::I8x16Splat),
+        ll::Instr::Simd(ll::SimdInstr::I16x8Splat) => hl::Instr::Numeric(hl::NumericOp::I16x8Splat),
+        ll::Instr::Simd(ll::SimdInstr::I32x4Splat) => hl::Instr::Numeric(hl::NumericOp::I32x4Splat),
+        ll::Instr::Simd(ll::SimdInstr::I64x2Splat) => hl::Instr::Numeric(hl::NumericOp::I64x2Splat),
+        ll::Instr::Simd(ll::SimdInstr::F32x4Splat) => hl::Instr::Numeric(hl::NumericOp::F32x4Splat),
+        ll::Instr::Simd(ll::SimdInstr::F64x2Splat) => hl::Instr::Numeric(hl::NumericOp::F64x2Splat),
+        ll::Instr::Simd(ll::SimdInstr::I8x16ExtractLaneS(lane)) => hl::Instr::ExtractLane(hl::ExtractLaneOp::I8x16ExtractLaneS, lane),
+        ll::Instr::Simd(ll::SimdInstr::I8x16ExtractLaneU(lane)) => hl::Instr::ExtractLane(hl::ExtractLaneOp::I8x16ExtractLaneU, lane),
+        ll::Instr::Simd(ll::SimdInstr::I8x16ReplaceLane(lane)) => hl::Instr::ReplaceLane(hl::ReplaceLaneOp::I8x16ReplaceLane, lane),
+        ll::Instr::Simd(ll::SimdInstr::I16x8ExtractLaneS(lane)) => hl::Instr::ExtractLane(hl::ExtractLaneOp::I16x8ExtractLaneS, lane),
+        ll::Instr::Simd(ll::SimdInstr::I16x8ExtractLaneU(lane)) => hl::Instr::ExtractLane(hl::ExtractLaneOp::I16x8ExtractLaneU, lane),
+        ll::Instr::Simd(ll::SimdInstr::I16x8ReplaceLane(lane)) => hl::Instr::ReplaceLane(hl::ReplaceLaneOp::I16x8ReplaceLane, lane),
+        ll::Instr::Simd(ll::SimdInstr::I32x4ExtractLane(lane)) => hl::Instr::ExtractLane(hl::ExtractLaneOp::I32x4ExtractLane, lane),
+        ll::Instr::Simd(ll::SimdInstr::I32x4ReplaceLane(lane)) => hl::Instr::ReplaceLane(hl::ReplaceLaneOp::I32x4ReplaceLane, lane),
+        ll::Instr::Simd(ll::SimdInstr::I64x2ExtractLane(lane)) => hl::Instr::ExtractLane(hl::ExtractLaneOp::I64x2ExtractLane, lane),
+        ll::Instr::Simd(ll::SimdInstr::I64x2ReplaceLane(lane)) => hl::Instr::ReplaceLane(hl::ReplaceLaneOp::I64x2ReplaceLane, lane),
+        ll::Instr::Simd(ll::SimdInstr::F32x4ExtractLane(lane)) => hl::Instr::ExtractLane(hl::ExtractLaneOp::F32x4ExtractLane, lane),
+        ll::Instr::Simd(ll::SimdInstr::F32x4ReplaceLane(lane)) => hl::Instr::ReplaceLane(hl::ReplaceLaneOp::F32x4ReplaceLane, lane),
+        ll::Instr::Simd(ll::SimdInstr::F64x2ExtractLane(lane)) => hl::Instr::ExtractLane(hl::ExtractLaneOp::F64x2ExtractLane, lane),
+        ll::Instr::Simd(ll::SimdInstr::F64x2ReplaceLane(lane)) => hl::Instr::ReplaceLane(hl::ReplaceLaneOp::F64x2ReplaceLane, lane),
+        ll::Instr::Simd(ll::SimdInstr::I8x16Eq) => hl::Instr::Numeric(hl::NumericOp::I8x16Eq),
+        ll::Instr::Simd(ll::SimdInstr::I8x16Ne) => hl::Instr::Numeric(hl::NumericOp::I8x16Ne),
+        ll::Instr::Simd(ll::SimdInstr::I8x16LtS) => hl::Instr::Numeric(hl::NumericOp::I8x16LtS),
+        ll::Instr::Simd(ll::SimdInstr::I8x16LtU) => hl::Instr::Numeric(hl::NumericOp::I8x16LtU),
+        ll::Instr::Simd(ll::SimdInstr::I8x16GtS) => hl::Instr::Numeric(hl::NumericOp::I8x16GtS),
+        ll::Instr::Simd(ll::SimdInstr::I8x16GtU) => hl::Instr::Numeric(hl::NumericOp::I8x16GtU),
+        ll::Instr::Simd(ll::SimdInstr::I8x16LeS) => hl::Instr::Numeric(hl::NumericOp::I8x16LeS),
+        ll::Instr::Simd(ll::SimdInstr::I8x16LeU) => hl::Instr::Numeric(hl::NumericOp::I8x16LeU),
+        ll::Instr::Simd(ll::SimdInstr::I8x16GeS) => hl::Instr::Numeric(hl::NumericOp::I8x16GeS),
+        ll::Instr::Simd(ll::SimdInstr::I8x16GeU) => hl::Instr::Numeric(hl::NumericOp::I8x16GeU),
+        ll::Instr::Simd(ll::SimdInstr::I16x8Eq) => hl::Instr::Numeric(hl::NumericOp::I16x8Eq),
+        ll::Instr::Simd(ll::SimdInstr::I16x8Ne) => hl::Instr::Numeric(hl::NumericOp::I16x8Ne),
+        ll::Instr::Simd(ll::SimdInstr::I16x8LtS) => hl::Instr::Numeric(hl::NumericOp::I16x8LtS),
+        ll::Instr::Simd(ll::SimdInstr::I16x8LtU) => hl::Instr::Numeric(hl::NumericOp::I16x8LtU),
+        ll::Instr::Simd(ll::SimdInstr::I16x8GtS) => hl::Instr::Numeric(hl::NumericOp::I16x8GtS),
+        ll::Instr::Simd(ll::SimdInstr::I16x8GtU) => hl::Instr::Numeric(hl::NumericOp::I16x8GtU),
+        ll::Instr::Simd(ll::SimdInstr::I16x8LeS) => hl::Instr::Numeric(hl::NumericOp::I16x8LeS),
+        ll::Instr::Simd(ll::SimdInstr::I16x8LeU) => hl::Instr::Numeric(hl::NumericOp::I16x8LeU),
+        ll::Instr::Simd(ll::SimdInstr::I16x8GeS) => hl::Instr::Numeric(hl::NumericOp::I16x8GeS),
+        ll::Instr::Simd(ll::SimdInstr::I16x8GeU) => hl::Instr::Numeric(hl::NumericOp::I16x8GeU),
+        ll::Instr::Simd(ll::SimdInstr::I32x4Eq) => hl::Instr::Numeric(hl::NumericOp::I32x4Eq),
+        ll::Instr::Simd(ll::SimdInstr::I32x4Ne) => hl::Instr::Numeric(hl::NumericOp::I32x4Ne),
+        ll::Instr::Simd(ll::SimdInstr::I32x4LtS) => hl::Instr::Numeric(hl::NumericOp::I32x4LtS),
+        ll::Instr::Simd(ll::SimdInstr::I32x4LtU) => hl::Instr::Numeric(hl::NumericOp::I32x4LtU),
+        ll::Instr::Simd(ll::SimdInstr::I32x4GtS) => hl::Instr::Numeric(hl::NumericOp::I32x4GtS),
+        ll::Instr::Simd(ll::SimdInstr::I32x4GtU) => hl::Instr::Numeric(hl::NumericOp::I32x4GtU),
+        ll::Instr::Simd(ll::SimdInstr::I32x4LeS) => hl::Instr::Numeric(hl::NumericOp::I32x4LeS),
+        ll::Instr::Simd(ll::SimdInstr::I32x4LeU) => hl::Instr::Numeric(hl::NumericOp::I32x4LeU),
+        ll::Instr::Simd(ll::SimdInstr::I32x4GeS) => hl::Instr::Numeric(hl::NumericOp::I32x4GeS),
+        ll::Instr::Simd(ll::SimdInstr::I32x4GeU) => hl::Instr::Numeric(hl::NumericOp::I32x4GeU),
+        ll::Instr::Simd(ll::SimdInstr::F32x4Eq) => hl::Instr::Numeric(hl::NumericOp::F32x4Eq),
+        ll::Instr::Simd(ll::SimdInstr::F32x4Ne) => hl::Instr::Numeric(hl::NumericOp::F32x4Ne),
+        ll::Instr::Simd(ll::SimdInstr::F32x4Lt) => hl::Instr::Numeric(hl::NumericOp::F32x4Lt),
+        ll::Instr::Simd(ll::SimdInstr::F32x4Gt) => hl::Instr::Numeric(hl::NumericOp::F32x4Gt),
+        ll::Instr::Simd(ll::SimdInstr::F32x4Le) => hl::Instr::Numeric(hl::NumericOp::F32x4Le),
+        ll::Instr::Simd(ll::SimdInstr::F32x4Ge) => hl::Instr::Numeric(hl::NumericOp::F32x4Ge),
+        ll::Instr::Simd(ll::SimdInstr::F64x2Eq) => hl::Instr::Numeric(hl::NumericOp::F64x2Eq),
+        ll::Instr::Simd(ll::SimdInstr::F64x2Ne) => hl::Instr::Numeric(hl::NumericOp::F64x2Ne),
+        ll::Instr::Simd(ll::SimdInstr::F64x2Lt) => hl::Instr::Numeric(hl::NumericOp::F64x2Lt),
+        ll::Instr::Simd(ll::SimdInstr::F64x2Gt) => hl::Instr::Numeric(hl::NumericOp::F64x2Gt),
+        ll::Instr::Simd(ll::SimdInstr::F64x2Le) => hl::Instr::Numeric(hl::NumericOp::F64x2Le),
+        ll::Instr::Simd(ll::SimdInstr::F64x2Ge) => hl::Instr::Numeric(hl::NumericOp::F64x2Ge),
+        ll::Instr::Simd(ll::SimdInstr::V128Not) => hl::Instr::Numeric(hl::NumericOp::V128Not),
+        ll::Instr::Simd(ll::SimdInstr::V128And) => hl::Instr::Numeric(hl::NumericOp::V128And),
+        ll::Instr::Simd(ll::SimdInstr::V128Andnot) => hl::Instr::Numeric(hl::NumericOp::V128Andnot),
+        ll::Instr::Simd(ll::SimdInstr::V128Or) => hl::Instr::Numeric(hl::NumericOp::V128Or),
+        ll::Instr::Simd(ll::SimdInstr::V128Xor) => hl::Instr::Numeric(hl::NumericOp::V128Xor),
+        ll::Instr::Simd(ll::SimdInstr::V128Bitselect) => hl::Instr::Numeric(hl::NumericOp::V128Bitselect),
+        ll::Instr::Simd(ll::SimdInstr::V128AnyTrue) => hl::Instr::Numeric(hl::NumericOp::V128AnyTrue),
+        ll::Instr::Simd(ll::SimdInstr::V128Load8Lane(ll::MemoryMemarg(memory_idx, memarg), lane)) => hl::Instr::LoadLane(hl::LoadLaneOp::V128Load8Lane, memory_idx.0.into(), memarg, lane),
+        ll::Instr::Simd(ll::SimdInstr::V128Load16Lane(ll::MemoryMemarg(memory_idx, memarg), lane)) => hl::Instr::LoadLane(hl::LoadLaneOp::V128Load16Lane, memory_idx.0.into(), memarg, lane),
+        ll::Instr::Simd(ll::SimdInstr::V128Load32Lane(ll::MemoryMemarg(memory_idx, memarg), lane)) => hl::Instr::LoadLane(hl::LoadLaneOp::V128Load32Lane, memory_idx.0.into(), memarg, lane),
+        ll::Instr::Simd(ll::SimdInstr::V128Load64Lane(ll::MemoryMemarg(memory_idx, memarg), lane)) => hl::Instr::LoadLane(hl::LoadLaneOp::V128Load64Lane, memory_idx.0.into(), memarg, lane),
+        ll::Instr::Simd(ll::SimdInstr::V128Store8Lane(ll::MemoryMemarg(memory_idx, memarg), lane)) => hl::Instr::StoreLane(hl::StoreLaneOp::V128Store8Lane, memory_idx.0.into(), memarg, lane),
+        ll::Instr::Simd(ll::SimdInstr::V128Store16Lane(ll::MemoryMemarg(memory_idx, memarg), lane)) => hl::Instr::StoreLane(hl::StoreLaneOp::V128Store16Lane, memory_idx.0.into(), memarg, lane),
+        ll::Instr::Simd(ll::SimdInstr::V128Store32Lane(ll::MemoryMemarg(memory_idx, memarg), lane)) => hl::Instr::StoreLane(hl::StoreLaneOp::V128Store32Lane, memory_idx.0.into(), memarg, lane),
+        ll::Instr::Simd(ll::SimdInstr::V128Store64Lane(ll::MemoryMemarg(memory_idx, memarg), lane)) => hl::Instr::StoreLane(hl::StoreLaneOp::V128Store64Lane, memory_idx.0.into(), memarg, lane),
+        ll::Instr::Simd(ll::SimdInstr::V128Load32Zero(ll::MemoryMemarg(memory_idx, memarg))) => hl::Instr::Load(hl::LoadOp::V128Load32Zero, memory_idx.0.into(), memarg),
+        ll::Instr::Simd(ll::SimdInstr::V128Load64Zero(ll::MemoryMemarg(memory_idx, memarg))) => hl::Instr::Load(hl::LoadOp::V128Load64Zero, memory_idx.0.into(), memarg),
+        ll::Instr::Simd(ll::SimdInstr::F32x4DemoteF64x2Zero) => hl::Instr::Numeric(hl::NumericOp::F32x4DemoteF64x2Zero),
+        ll::Instr::Simd(ll::SimdInstr::F64x2PromoteLowF32x4) => hl::Instr::Numeric(hl::NumericOp::F64x2PromoteLowF32x4),
+        ll::Instr::Simd(ll::SimdInstr::I8x16Abs) => hl::Instr::Numeric(hl::NumericOp::I8x16Abs),
+        ll::Instr::Simd(ll::SimdInstr::I8x16Neg) => hl::Instr::Numeric(hl::NumericOp::I8x16Neg),
+        ll::Instr::Simd(ll::SimdInstr::I8x16Popcnt) => hl::Instr::Numeric(hl::NumericOp::I8x16Popcnt),
+        ll::Instr::Simd(ll::SimdInstr::I8x16AllTrue) => hl::Instr::Numeric(hl::NumericOp::I8x16AllTrue),
+        ll::Instr::Simd(ll::SimdInstr::I8x16Bitmask) => hl::Instr::Numeric(hl::NumericOp::I8x16Bitmask),
+        ll::Instr::Simd(ll::SimdInstr::I8x16NarrowI16x8S) => hl::Instr::Numeric(hl::NumericOp::I8x16NarrowI16x8S),
+        ll::Instr::Simd(ll::SimdInstr::I8x16NarrowI16x8U) => hl::Instr::Numeric(hl::NumericOp::I8x16NarrowI16x8U),
+        ll::Instr::Simd(ll::SimdInstr::F32x4Ceil) => hl::Instr::Numeric(hl::NumericOp::F32x4Ceil),
+        ll::Instr::Simd(ll::SimdInstr::F32x4Floor) => hl::Instr::Numeric(hl::NumericOp::F32x4Floor),
+        ll::Instr::Simd(ll::SimdInstr::F32x4Trunc) => hl::Instr::Numeric(hl::NumericOp::F32x4Trunc),
+        ll::Instr::Simd(ll::SimdInstr::F32x4Nearest) => hl::Instr::Numeric(hl::NumericOp::F32x4Nearest),
+        ll::Instr::Simd(ll::SimdInstr::I8x16Shl) => hl::Instr::Numeric(hl::NumericOp::I8x16Shl),
+        ll::Instr::Simd(ll::SimdInstr::I8x16ShrS) => hl::Instr::Numeric(hl::NumericOp::I8x16ShrS),
+        ll::Instr::Simd(ll::SimdInstr::I8x16ShrU) => hl::Instr::Numeric(hl::NumericOp::I8x16ShrU),
+        ll::Instr::Simd(ll::SimdInstr::I8x16Add) => hl::Instr::Numeric(hl::NumericOp::I8x16Add),
+        ll::Instr::Simd(ll::SimdInstr::I8x16AddSatS) => hl::Instr::Numeric(hl::NumericOp::I8x16AddSatS),
+        ll::Instr::Simd(ll::SimdInstr::I8x16AddSatU) => hl::Instr::Numeric(hl::NumericOp::I8x16AddSatU),
+        ll::Instr::Simd(ll::SimdInstr::I8x16Sub) => hl::Instr::Numeric(hl::NumericOp::I8x16Sub),
+        ll::Instr::Simd(ll::SimdInstr::I8x16SubSatS) => hl::Instr::Numeric(hl::NumericOp::I8x16SubSatS),
+        ll::Instr::Simd(ll::SimdInstr::I8x16SubSatU) => hl::Instr::Numeric(hl::NumericOp::I8x16SubSatU),
+        ll::Instr::Simd(ll::SimdInstr::F64x2Ceil) => hl::Instr::Numeric(hl::NumericOp::F64x2Ceil),
+        ll::Instr::Simd(ll::SimdInstr::F64x2Floor) => hl::Instr::Numeric(hl::NumericOp::F64x2Floor),
+        ll::Instr::Simd(ll::SimdInstr::I8x16MinS) => hl::Instr::Numeric(hl::NumericOp::I8x16MinS),
+        ll::Instr::Simd(ll::SimdInstr::I8x16MinU) => hl::Instr::Numeric(hl::NumericOp::I8x16MinU),
+        ll::Instr::Simd(ll::SimdInstr::I8x16MaxS) => hl::Instr::Numeric(hl::NumericOp::I8x16MaxS),
+        ll::Instr::Simd(ll::SimdInstr::I8x16MaxU) => hl::Instr::Numeric(hl::NumericOp::I8x16MaxU),
+        ll::Instr::Simd(ll::SimdInstr::F64x2Trunc) => hl::Instr::Numeric(hl::NumericOp::F64x2Trunc),
+        ll::Instr::Simd(ll::SimdInstr::I8x16AvgrU) => hl::Instr::Numeric(hl::NumericOp::I8x16AvgrU),
+        ll::Instr::Simd(ll::SimdInstr::I16x8ExtaddPairwiseI8x16S) => hl::Instr::Numeric(hl::NumericOp::I16x8ExtaddPairwiseI8x16S),
+        ll::Instr::Simd(ll::SimdInstr::I16x8ExtaddPairwiseI8x16U) => hl::Instr::Numeric(hl::NumericOp::I16x8ExtaddPairwiseI8x16U),
+        ll::Instr::Simd(ll::SimdInstr::I32x4ExtaddPairwiseI16x8S) => hl::Instr::Numeric(hl::NumericOp::I32x4ExtaddPairwiseI16x8S),
+        ll::Instr::Simd(ll::SimdInstr::I32x4ExtaddPairwiseI16x8U) => hl::Instr::Numeric(hl::NumericOp::I32x4ExtaddPairwiseI16x8U),
+        ll::Instr::Simd(ll::SimdInstr::I16x8Abs) => hl::Instr::Numeric(hl::NumericOp::I16x8Abs),
+        ll::Instr::Simd(ll::SimdInstr::I16x8Neg) => hl::Instr::Numeric(hl::NumericOp::I16x8Neg),
+        ll::Instr::Simd(ll::SimdInstr::I16x8Q15mulrSatS) => hl::Instr::Numeric(hl::NumericOp::I16x8Q15mulrSatS),
+        ll::Instr::Simd(ll::SimdInstr::I16x8AllTrue) => hl::Instr::Numeric(hl::NumericOp::I16x8AllTrue),
+        ll::Instr::Simd(ll::SimdInstr::I16x8Bitmask) => hl::Instr::Numeric(hl::NumericOp::I16x8Bitmask),
+        ll::Instr::Simd(ll::SimdInstr::I16x8NarrowI32x4S) => hl::Instr::Numeric(hl::NumericOp::I16x8NarrowI32x4S),
+        ll::Instr::Simd(ll::SimdInstr::I16x8NarrowI32x4U) => hl::Instr::Numeric(hl::NumericOp::I16x8NarrowI32x4U),
+        ll::Instr::Simd(ll::SimdInstr::I16x8ExtendLowI8x16S) => hl::Instr::Numeric(hl::NumericOp::I16x8ExtendLowI8x16S),
+        ll::Instr::Simd(ll::SimdInstr::I16x8ExtendHighI8x16S) => hl::Instr::Numeric(hl::NumericOp::I16x8ExtendHighI8x16S),
+        ll::Instr::Simd(ll::SimdInstr::I16x8ExtendLowI8x16U) => hl::Instr::Numeric(hl::NumericOp::I16x8ExtendLowI8x16U),
+        ll::Instr::Simd(ll::SimdInstr::I16x8ExtendHighI8x16U) => hl::Instr::Numeric(hl::NumericOp::I16x8ExtendHighI8x16U),
+        ll::Instr::Simd(ll::SimdInstr::I16x8Shl) => hl::Instr::Numeric(hl::NumericOp::I16x8Shl),
+        ll::Instr::Simd(ll::SimdInstr::I16x8ShrS) => hl::Instr::Numeric(hl::NumericOp::I16x8ShrS),
+        ll::Instr::Simd(ll::SimdInstr::I16x8ShrU) => hl::Instr::Numeric(hl::NumericOp::I16x8ShrU),
+        ll::Instr::Simd(ll::SimdInstr::I16x8Add) => hl::Instr::Numeric(hl::NumericOp::I16x8Add),
+        ll::Instr::Simd(ll::SimdInstr::I16x8AddSatS) => hl::Instr::Numeric(hl::NumericOp::I16x8AddSatS),
+        ll::Instr::Simd(ll::SimdInstr::I16x8AddSatU) => hl::Instr::Numeric(hl::NumericOp::I16x8AddSatU),
+        ll::Instr::Simd(ll::SimdInstr::I16x8Sub) => hl::Instr::Numeric(hl::NumericOp::I16x8Sub),
+        ll::Instr::Simd(ll::SimdInstr::I16x8SubSatS) => hl::Instr::Numeric(hl::NumericOp::I16x8SubSatS),
+        ll::Instr::Simd(ll::SimdInstr::I16x8SubSatU) => hl::Instr::Numeric(hl::NumericOp::I16x8SubSatU),
+        ll::Instr::Simd(ll::SimdInstr::F64x2Nearest) => hl::Instr::Numeric(hl::NumericOp::F64x2Nearest),
+        ll::Instr::Simd(ll::SimdInstr::I16x8Mul) => hl::Instr::Numeric(hl::NumericOp::I16x8Mul),
+        ll::Instr::Simd(ll::SimdInstr::I16x8MinS) => hl::Instr::Numeric(hl::NumericOp::I16x8MinS),
+        ll::Instr::Simd(ll::SimdInstr::I16x8MinU) => hl::Instr::Numeric(hl::NumericOp::I16x8MinU),
+        ll::Instr::Simd(ll::SimdInstr::I16x8MaxS) => hl::Instr::Numeric(hl::NumericOp::I16x8MaxS),
+        ll::Instr::Simd(ll::SimdInstr::I16x8MaxU) => hl::Instr::Numeric(hl::NumericOp::I16x8MaxU),
+        ll::Instr::Simd(ll::SimdInstr::I16x8AvgrU) => hl::Instr::Numeric(hl::NumericOp::I16x8AvgrU),
+        ll::Instr::Simd(ll::SimdInstr::I16x8ExtmulLowI8x16S) => hl::Instr::Numeric(hl::NumericOp::I16x8ExtmulLowI8x16S),
+        ll::Instr::Simd(ll::SimdInstr::I16x8ExtmulHighI8x16S) => hl::Instr::Numeric(hl::NumericOp::I16x8ExtmulHighI8x16S),
+        ll::Instr::Simd(ll::SimdInstr::I16x8ExtmulLowI8x16U) => hl::Instr::Numeric(hl::NumericOp::I16x8ExtmulLowI8x16U),
+        ll::Instr::Simd(ll::SimdInstr::I16x8ExtmulHighI8x16U) => hl::Instr::Numeric(hl::NumericOp::I16x8ExtmulHighI8x16U),
+        ll::Instr::Simd(ll::SimdInstr::I32x4Abs) => hl::Instr::Numeric(hl::NumericOp::I32x4Abs),
+        ll::Instr::Simd(ll::SimdInstr::I32x4Neg) => hl::Instr::Numeric(hl::NumericOp::I32x4Neg),
+        ll::Instr::Simd(ll::SimdInstr::I32x4AllTrue) => hl::Instr::Numeric(hl::NumericOp::I32x4AllTrue),
+        ll::Instr::Simd(ll::SimdInstr::I32x4Bitmask) => hl::Instr::Numeric(hl::NumericOp::I32x4Bitmask),
+        ll::Instr::Simd(ll::SimdInstr::I32x4ExtendLowI16x8S) => hl::Instr::Numeric(hl::NumericOp::I32x4ExtendLowI16x8S),
+        ll::Instr::Simd(ll::SimdInstr::I32x4ExtendHighI16x8S) => hl::Instr::Numeric(hl::NumericOp::I32x4ExtendHighI16x8S),
+        ll::Instr::Simd(ll::SimdInstr::I32x4ExtendLowI16x8U) => hl::Instr::Numeric(hl::NumericOp::I32x4ExtendLowI16x8U),
+        ll::Instr::Simd(ll::SimdInstr::I32x4ExtendHighI16x8U) => hl::Instr::Numeric(hl::NumericOp::I32x4ExtendHighI16x8U),
+        ll::Instr::Simd(ll::SimdInstr::I32x4Shl) => hl::Instr::Numeric(hl::NumericOp::I32x4Shl),
+        ll::Instr::Simd(ll::SimdInstr::I32x4ShrS) => hl::Instr::Numeric(hl::NumericOp::I32x4ShrS),
+        ll::Instr::Simd(ll::SimdInstr::I32x4ShrU) => hl::Instr::Numeric(hl::NumericOp::I32x4ShrU),
+        ll::Instr::Simd(ll::SimdInstr::I32x4Add) => hl::Instr::Numeric(hl::NumericOp::I32x4Add),
+        ll::Instr::Simd(ll::SimdInstr::I32x4Sub) => hl::Instr::Numeric(hl::NumericOp::I32x4Sub),
+        ll::Instr::Simd(ll::SimdInstr::I32x4Mul) => hl::Instr::Numeric(hl::NumericOp::I32x4Mul),
+        ll::Instr::Simd(ll::SimdInstr::I32x4MinS) => hl::Instr::Numeric(hl::NumericOp::I32x4MinS),
+        ll::Instr::Simd(ll::SimdInstr::I32x4MinU) => hl::Instr::Numeric(hl::NumericOp::I32x4MinU),
+        ll::Instr::Simd(ll::SimdInstr::I32x4MaxS) => hl::Instr::Numeric(hl::NumericOp::I32x4MaxS),
+        ll::Instr::Simd(ll::SimdInstr::I32x4MaxU) => hl::Instr::Numeric(hl::NumericOp::I32x4MaxU),
+        ll::Instr::Simd(ll::SimdInstr::I32x4DotI16x8S) => hl::Instr::Numeric(hl::NumericOp::I32x4DotI16x8S),
+        ll::Instr::Simd(ll::SimdInstr::I32x4ExtmulLowI16x8S) => hl::Instr::Numeric(hl::NumericOp::I32x4ExtmulLowI16x8S),
+        ll::Instr::Simd(ll::SimdInstr::I32x4ExtmulHighI16x8S) => hl::Instr::Numeric(hl::NumericOp::I32x4ExtmulHighI16x8S),
+        ll::Instr::Simd(ll::SimdInstr::I32x4ExtmulLowI16x8U) => hl::Instr::Numeric(hl::NumericOp::I32x4ExtmulLowI16x8U),
+        ll::Instr::Simd(ll::SimdInstr::I32x4ExtmulHighI16x8U) => hl::Instr::Numeric(hl::NumericOp::I32x4ExtmulHighI16x8U),
+        ll::Instr::Simd(ll::SimdInstr::I64x2Abs) => hl::Instr::Numeric(hl::NumericOp::I64x2Abs),
+        ll::Instr::Simd(ll::SimdInstr::I64x2Neg) => hl::Instr::Numeric(hl::NumericOp::I64x2Neg),
+        ll::Instr::Simd(ll::SimdInstr::I64x2AllTrue) => hl::Instr::Numeric(hl::NumericOp::I64x2AllTrue),
+        ll::Instr::Simd(ll::SimdInstr::I64x2Bitmask) => hl::Instr::Numeric(hl::NumericOp::I64x2Bitmask),
+        ll::Instr::Simd(ll::SimdInstr::I64x2ExtendLowI32x4S) => hl::Instr::Numeric(hl::NumericOp::I64x2ExtendLowI32x4S),
+        ll::Instr::Simd(ll::SimdInstr::I64x2ExtendHighI32x4S) => hl::Instr::Numeric(hl::NumericOp::I64x2ExtendHighI32x4S),
+        ll::Instr::Simd(ll::SimdInstr::I64x2ExtendLowI32x4U) => hl::Instr::Numeric(hl::NumericOp::I64x2ExtendLowI32x4U),
+        ll::Instr::Simd(ll::SimdInstr::I64x2ExtendHighI32x4U) => hl::Instr::Numeric(hl::NumericOp::I64x2ExtendHighI32x4U),
+        ll::Instr::Simd(ll::SimdInstr::I64x2Shl) => hl::Instr::Numeric(hl::NumericOp::I64x2Shl),
+        ll::Instr::Simd(ll::SimdInstr::I64x2ShrS) => hl::Instr::Numeric(hl::NumericOp::I64x2ShrS),
+        ll::Instr::Simd(ll::SimdInstr::I64x2ShrU) => hl::Instr::Numeric(hl::NumericOp::I64x2ShrU),
+        ll::Instr::Simd(ll::SimdInstr::I64x2Add) => hl::Instr::Numeric(hl::NumericOp::I64x2Add),
+        ll::Instr::Simd(ll::SimdInstr::I64x2Sub) => hl::Instr::Numeric(hl::NumericOp::I64x2Sub),
+        ll::Instr::Simd(ll::SimdInstr::I64x2Mul) => hl::Instr::Numeric(hl::NumericOp::I64x2Mul),
+        ll::Instr::Simd(ll::SimdInstr::I64x2Eq) => hl::Instr::Numeric(hl::NumericOp::I64x2Eq),
+        ll::Instr::Simd(ll::SimdInstr::I64x2Ne) => hl::Instr::Numeric(hl::NumericOp::I64x2Ne),
+        ll::Instr::Simd(ll::SimdInstr::I64x2LtS) => hl::Instr::Numeric(hl::NumericOp::I64x2LtS),
+        ll::Instr::Simd(ll::SimdInstr::I64x2GtS) => hl::Instr::Numeric(hl::NumericOp::I64x2GtS),
+        ll::Instr::Simd(ll::SimdInstr::I64x2LeS) => hl::Instr::Numeric(hl::NumericOp::I64x2LeS),
+        ll::Instr::Simd(ll::SimdInstr::I64x2GeS) => hl::Instr::Numeric(hl::NumericOp::I64x2GeS),
+        ll::Instr::Simd(ll::SimdInstr::I64x2ExtmulLowI32x4S) => hl::Instr::Numeric(hl::NumericOp::I64x2ExtmulLowI32x4S),
+        ll::Instr::Simd(ll::SimdInstr::I64x2ExtmulHighI32x4S) => hl::Instr::Numeric(hl::NumericOp::I64x2ExtmulHighI32x4S),
+        ll::Instr::Simd(ll::SimdInstr::I64x2ExtmulLowI32x4U) => hl::Instr::Numeric(hl::NumericOp::I64x2ExtmulLowI32x4U),
+        ll::Instr::Simd(ll::SimdInstr::I64x2ExtmulHighI32x4U) => hl::Instr::Numeric(hl::NumericOp::I64x2ExtmulHighI32x4U),
+        ll::Instr::Simd(ll::SimdInstr::F32x4Abs) => hl::Instr::Numeric(hl::NumericOp::F32x4Abs),
+        ll::Instr::Simd(ll::SimdInstr::F32x4Neg) => hl::Instr::Numeric(hl::NumericOp::F32x4Neg),
+        ll::Instr::Simd(ll::SimdInstr::F32x4Sqrt) => hl::Instr::Numeric(hl::NumericOp::F32x4Sqrt),
+        ll::Instr::Simd(ll::SimdInstr::F32x4Add) => hl::Instr::Numeric(hl::NumericOp::F32x4Add),
+        ll::Instr::Simd(ll::SimdInstr::F32x4Sub) => hl::Instr::Numeric(hl::NumericOp::F32x4Sub),
+        ll::Instr::Simd(ll::SimdInstr::F32x4Mul) => hl::Instr::Numeric(hl::NumericOp::F32x4Mul),
+        ll::Instr::Simd(ll::SimdInstr::F32x4Div) => hl::Instr::Numeric(hl::NumericOp::F32x4Div),
+        ll::Instr::Simd(ll::SimdInstr::F32x4Min) => hl::Instr::Numeric(hl::NumericOp::F32x4Min),
+        ll::Instr::Simd(ll::SimdInstr::F32x4Max) => hl::Instr::Numeric(hl::NumericOp::F32x4Max),
+        ll::Instr::Simd(ll::SimdInstr::F32x4Pmin) => hl::Instr::Numeric(hl::NumericOp::F32x4Pmin),
+        ll::Instr::Simd(ll::SimdInstr::F32x4Pmax) => hl::Instr::Numeric(hl::NumericOp::F32x4Pmax),
+        ll::Instr::Simd(ll::SimdInstr::F64x2Abs) => hl::Instr::Numeric(hl::NumericOp::F64x2Abs),
+        ll::Instr::Simd(ll::SimdInstr::F64x2Neg) => hl::Instr::Numeric(hl::NumericOp::F64x2Neg),
+        ll::Instr::Simd(ll::SimdInstr::F64x2Sqrt) => hl::Instr::Numeric(hl::NumericOp::F64x2Sqrt),
+        ll::Instr::Simd(ll::SimdInstr::F64x2Add) => hl::Instr::Numeric(hl::NumericOp::F64x2Add),
+        ll::Instr::Simd(ll::SimdInstr::F64x2Sub) => hl::Instr::Numeric(hl::NumericOp::F64x2Sub),
+        ll::Instr::Simd(ll::SimdInstr::F64x2Mul) => hl::Instr::Numeric(hl::NumericOp::F64x2Mul),
+        ll::Instr::Simd(ll::SimdInstr::F64x2Div) => hl::Instr::Numeric(hl::NumericOp::F64x2Div),
+        ll::Instr::Simd(ll::SimdInstr::F64x2Min) => hl::Instr::Numeric(hl::NumericOp::F64x2Min),
+        ll::Instr::Simd(ll::SimdInstr::F64x2Max) => hl::Instr::Numeric(hl::NumericOp::F64x2Max),
+        ll::Instr::Simd(ll::SimdInstr::F64x2Pmin) => hl::Instr::Numeric(hl::NumericOp::F64x2Pmin),
+        ll::Instr::Simd(ll::SimdInstr::F64x2Pmax) => hl::Instr::Numeric(hl::NumericOp::F64x2Pmax),
+        ll::Instr::Simd(ll::SimdInstr::I32x4TruncSatF32x4S) => hl::Instr::Numeric(hl::NumericOp::I32x4TruncSatF32x4S),
+        ll::Instr::Simd(ll::SimdInstr::I32x4TruncSatF32x4U) => hl::Instr::Numeric(hl::NumericOp::I32x4TruncSatF32x4U),
+        ll::Instr::Simd(ll::SimdInstr::F32x4ConvertI32x4S) => hl::Instr::Numeric(hl::NumericOp::F32x4ConvertI32x4S),
+        ll::Instr::Simd(ll::SimdInstr::F32x4ConvertI32x4U) => hl::Instr::Numeric(hl::NumericOp::F32x4ConvertI32x4U),
+        ll::Instr::Simd(ll::SimdInstr::I32x4TruncSatF64x2SZero) => hl::Instr::Numeric(hl::NumericOp::I32x4TruncSatF64x2SZero),
+        ll::Instr::Simd(ll::SimdInstr::I32x4TruncSatF64x2UZero) => hl::Instr::Numeric(hl::NumericOp::I32x4TruncSatF64x2UZero),
+        ll::Instr::Simd(ll::SimdInstr::F64x2ConvertLowI32x4S) => hl::Instr::Numeric(hl::NumericOp::F64x2ConvertLowI32x4S),
+        ll::Instr::Simd(ll::SimdInstr::F64x2ConvertLowI32x4U) => hl::Instr::Numeric(hl::NumericOp::F64x2ConvertLowI32x4U),
+        ll::Instr::Atomic(ll::AtomicInstr::MemoryAtomicNotify(ll::MemoryMemarg(memory_idx, memarg))) => hl::Instr::AtomicNotify(memory_idx.0.into(), memarg),
+        ll::Instr::Atomic(ll::AtomicInstr::MemoryAtomicWait32(ll::MemoryMemarg(memory_idx, memarg))) => hl::Instr::AtomicWait(hl::AtomicWaitOp::MemoryAtomicWait32, memory_idx.0.into(), memarg),
+        ll::Instr::Atomic(ll::AtomicInstr::MemoryAtomicWait64(ll::MemoryMemarg(memory_idx, memarg))) => hl::Instr::AtomicWait(hl::AtomicWaitOp::MemoryAtomicWait64, memory_idx.0.into(), memarg),
+        ll::Instr::Atomic(ll::AtomicInstr::AtomicFence(_)) => hl::Instr::AtomicFence,
+        ll::Instr::Atomic(ll::AtomicInstr::I32AtomicLoad(ll::MemoryMemarg(memory_idx, memarg))) => hl::Instr::AtomicLoad(hl::AtomicLoadOp::I32AtomicLoad, memory_idx.0.into(), memarg),
+        ll::Instr::Atomic(ll::AtomicInstr::I64AtomicLoad(ll::MemoryMemarg(memory_idx, memarg))) => hl::Instr::AtomicLoad(hl::AtomicLoadOp::I64AtomicLoad, memory_idx.0.into(), memarg),
+        ll::Instr::Atomic(ll::AtomicInstr::I32AtomicLoad8U(ll::MemoryMemarg(memory_idx, memarg))) => hl::Instr::AtomicLoad(hl::AtomicLoadOp::I32AtomicLoad8U, memory_idx.0.into(), memarg),
+        ll::Instr::Atomic(ll::AtomicInstr::I32AtomicLoad16U(ll::MemoryMemarg(memory_idx, memarg))) => hl::Instr::AtomicLoad(hl::AtomicLoadOp::I32AtomicLoad16U, memory_idx.0.into(), memarg),
+        ll::Instr::Atomic(ll::AtomicInstr::I64AtomicLoad8U(ll::MemoryMemarg(memory_idx, memarg))) => hl::Instr::AtomicLoad(hl::AtomicLoadOp::I64AtomicLoad8U, memory_idx.0.into(), memarg),
+        ll::Instr::Atomic(ll::AtomicInstr::I64AtomicLoad16U(ll::MemoryMemarg(memory_idx, memarg))) => hl::Instr::AtomicLoad(hl::AtomicLoadOp::I64AtomicLoad16U, memory_idx.0.into(), memarg),
+        ll::Instr::Atomic(ll::AtomicInstr::I64AtomicLoad32U(ll::MemoryMemarg(memory_idx, memarg))) => hl::Instr::AtomicLoad(hl::AtomicLoadOp::I64AtomicLoad32U, memory_idx.0.into(), memarg),
+        ll::Instr::Atomic(ll::AtomicInstr::I32AtomicStore(ll::MemoryMemarg(memory_idx, memarg))) => hl::Instr::AtomicStore(hl::AtomicStoreOp::I32AtomicStore, memory_idx.0.into(), memarg),
+        ll::Instr::Atomic(ll::AtomicInstr::I64AtomicStore(ll::MemoryMemarg(memory_idx, memarg))) => hl::Instr::AtomicStore(hl::AtomicStoreOp::I64AtomicStore, memory_idx.0.into(), memarg),
+        ll::Instr::Atomic(ll::AtomicInstr::I32AtomicStore8(ll::MemoryMemarg(memory_idx, memarg))) => hl::Instr::AtomicStore(hl::AtomicStoreOp::I32AtomicStore8, memory_idx.0.into(), memarg),
+        ll::Instr::Atomic(ll::AtomicInstr::I32AtomicStore16(ll::MemoryMemarg(memory_idx, memarg))) => hl::Instr::AtomicStore(hl::AtomicStoreOp::I32AtomicStore16, memory_idx.0.into(), memarg),
+        ll::Instr::Atomic(ll::AtomicInstr::I64AtomicStore8(ll::MemoryMemarg(memory_idx, memarg))) => hl::Instr::AtomicStore(hl::AtomicStoreOp::I64AtomicStore8, memory_idx.0.into(), memarg),
+        ll::Instr::Atomic(ll::AtomicInstr::I64AtomicStore16(ll::MemoryMemarg(memory_idx, memarg))) => hl::Instr::AtomicStore(hl::AtomicStoreOp::I64AtomicStore16, memory_idx.0.into(), memarg),
+        ll::Instr::Atomic(ll::AtomicInstr::I64AtomicStore32(ll::MemoryMemarg(memory_idx, memarg))) => hl::Instr::AtomicStore(hl::AtomicStoreOp::I64AtomicStore32, memory_idx.0.into(), memarg),
+        ll::Instr::Atomic(ll::AtomicInstr::I32AtomicRmwAdd(ll::MemoryMemarg(memory_idx, memarg))) => hl::Instr::AtomicRmw(hl::AtomicRmwOp::I32AtomicRmwAdd, memory_idx.0.into(), memarg),
+        ll::Instr::Atomic(ll::AtomicInstr::I64AtomicRmwAdd(ll::MemoryMemarg(memory_idx, memarg))) => hl::Instr::AtomicRmw(hl::AtomicRmwOp::I64AtomicRmwAdd, memory_idx.0.into(), memarg),
+        ll::Instr::Atomic(ll::AtomicInstr::I32AtomicRmw8AddU(ll::MemoryMemarg(memory_idx, memarg))) => hl::Instr::AtomicRmw(hl::AtomicRmwOp::I32AtomicRmw8AddU, memory_idx.0.into(), memarg),
+        ll::Instr::Atomic(ll::AtomicInstr::I32AtomicRmw16AddU(ll::MemoryMemarg(memory_idx, memarg))) => hl::Instr::AtomicRmw(hl::AtomicRmwOp::I32AtomicRmw16AddU, memory_idx.0.into(), memarg),
+        ll::Instr::Atomic(ll::AtomicInstr::I64AtomicRmw8AddU(ll::MemoryMemarg(memory_idx, memarg))) => hl::Instr::AtomicRmw(hl::AtomicRmwOp::I64AtomicRmw8AddU, memory_idx.0.into(), memarg),
+        ll::Instr::Atomic(ll::AtomicInstr::I64AtomicRmw16AddU(ll::MemoryMemarg(memory_idx, memarg))) => hl::Instr::AtomicRmw(hl::AtomicRmwOp::I64AtomicRmw16AddU, memory_idx.0.into(), memarg),
+        ll::Instr::Atomic(ll::AtomicInstr::I64AtomicRmw32AddU(ll::MemoryMemarg(memory_idx, memarg))) => hl::Instr::AtomicRmw(hl::AtomicRmwOp::I64AtomicRmw32AddU, memory_idx.0.into(), memarg),
+        ll::Instr::Atomic(ll::AtomicInstr::I32AtomicRmwSub(ll::MemoryMemarg(memory_idx, memarg))) => hl::Instr::AtomicRmw(hl::AtomicRmwOp::I32AtomicRmwSub, memory_idx.0.into(), memarg),
+        ll::Instr::Atomic(ll::AtomicInstr::I64AtomicRmwSub(ll::MemoryMemarg(memory_idx, memarg))) => hl::Instr::AtomicRmw(hl::AtomicRmwOp::I64AtomicRmwSub, memory_idx.0.into(), memarg),
+        ll::Instr::Atomic(ll::AtomicInstr::I32AtomicRmw8SubU(ll::MemoryMemarg(memory_idx, memarg))) => hl::Instr::AtomicRmw(hl::AtomicRmwOp::I32AtomicRmw8SubU, memory_idx.0.into(), memarg),
+        ll::Instr::Atomic(ll::AtomicInstr::I32AtomicRmw16SubU(ll::MemoryMemarg(memory_idx, memarg))) => hl::Instr::AtomicRmw(hl::AtomicRmwOp::I32AtomicRmw16SubU, memory_idx.0.into(), memarg),
+        ll::Instr::Atomic(ll::AtomicInstr::I64AtomicRmw8SubU(ll::MemoryMemarg(memory_idx, memarg))) => hl::Instr::AtomicRmw(hl::AtomicRmwOp::I64AtomicRmw8SubU, memory_idx.0.into(), memarg),
+        ll::Instr::Atomic(ll::AtomicInstr::I64AtomicRmw16SubU(ll::MemoryMemarg(memory_idx, memarg))) => hl::Instr::AtomicRmw(hl::AtomicRmwOp::I64AtomicRmw16SubU, memory_idx.0.into(), memarg),
+        ll::Instr::Atomic(ll::AtomicInstr::I64AtomicRmw32SubU(ll::MemoryMemarg(memory_idx, memarg))) => hl::Instr::AtomicRmw(hl::AtomicRmwOp::I64AtomicRmw32SubU, memory_idx.0.into(), memarg),
+        ll::Instr::Atomic(ll::AtomicInstr::I32AtomicRmwAnd(ll::MemoryMemarg(memory_idx, memarg))) => hl::Instr::AtomicRmw(hl::AtomicRmwOp::I32AtomicRmwAnd, memory_idx.0.into(), memarg),
+        ll::Instr::Atomic(ll::AtomicInstr::I64AtomicRmwAnd(ll::MemoryMemarg(memory_idx, memarg))) => hl::Instr::AtomicRmw(hl::AtomicRmwOp::I64AtomicRmwAnd, memory_idx.0.into(), memarg),
+        ll::Instr::Atomic(ll::AtomicInstr::I32AtomicRmw8AndU(ll::MemoryMemarg(memory_idx, memarg))) => hl::Instr::AtomicRmw(hl::AtomicRmwOp::I32AtomicRmw8AndU, memory_idx.0.into(), memarg),
+        ll::Instr::Atomic(ll::AtomicInstr::I32AtomicRmw16AndU(ll::MemoryMemarg(memory_idx, memarg))) => hl::Instr::AtomicRmw(hl::AtomicRmwOp::I32AtomicRmw16AndU, memory_idx.0.into(), memarg),
+        ll::Instr::Atomic(ll::AtomicInstr::I64AtomicRmw8AndU(ll::MemoryMemarg(memory_idx, memarg))) => hl::Instr::AtomicRmw(hl::AtomicRmwOp::I64AtomicRmw8AndU, memory_idx.0.into(), memarg),
+        ll::Instr::Atomic(ll::AtomicInstr::I64AtomicRmw16AndU(ll::MemoryMemarg(memory_idx, memarg))) => hl::Instr::AtomicRmw(hl::AtomicRmwOp::I64AtomicRmw16AndU, memory_idx.0.into(), memarg),
+        ll::Instr::Atomic(ll::AtomicInstr::I64AtomicRmw32AndU(ll::MemoryMemarg(memory_idx, memarg))) => hl::Instr::AtomicRmw(hl::AtomicRmwOp::I64AtomicRmw32AndU, memory_idx.0.into(), memarg),
+        ll::Instr::Atomic(ll::AtomicInstr::I32AtomicRmwOr(ll::MemoryMemarg(memory_idx, memarg))) => hl::Instr::AtomicRmw(hl::AtomicRmwOp::I32AtomicRmwOr, memory_idx.0.into(), memarg),
+        ll::Instr::Atomic(ll::AtomicInstr::I64AtomicRmwOr(ll::MemoryMemarg(memory_idx, memarg))) => hl::Instr::AtomicRmw(hl::AtomicRmwOp::I64AtomicRmwOr, memory_idx.0.into(), memarg),
+        ll::Instr::Atomic(ll::AtomicInstr::I32AtomicRmw8OrU(ll::MemoryMemarg(memory_idx, memarg))) => hl::Instr::AtomicRmw(hl::AtomicRmwOp::I32AtomicRmw8OrU, memory_idx.0.into(), memarg),
+        ll::Instr::Atomic(ll::AtomicInstr::I32AtomicRmw16OrU(ll::MemoryMemarg(memory_idx, memarg))) => hl::Instr::AtomicRmw(hl::AtomicRmwOp::I32AtomicRmw16OrU, memory_idx.0.into(), memarg),
+        ll::Instr::Atomic(ll::AtomicInstr::I64AtomicRmw8OrU(ll::MemoryMemarg(memory_idx, memarg))) => hl::Instr::AtomicRmw(hl::AtomicRmwOp::I64AtomicRmw8OrU, memory_idx.0.into(), memarg),
+        ll::Instr::Atomic(ll::AtomicInstr::I64AtomicRmw16OrU(ll::MemoryMemarg(memory_idx, memarg))) => hl::Instr::AtomicRmw(hl::AtomicRmwOp::I64AtomicRmw16OrU, memory_idx.0.into(), memarg),
+        ll::Instr::Atomic(ll::AtomicInstr::I64AtomicRmw32OrU(ll::MemoryMemarg(memory_idx, memarg))) => hl::Instr::AtomicRmw(hl::AtomicRmwOp::I64AtomicRmw32OrU, memory_idx.0.into(), memarg),
+        ll::Instr::Atomic(ll::AtomicInstr::I32AtomicRmwXor(ll::MemoryMemarg(memory_idx, memarg))) => hl::Instr::AtomicRmw(hl::AtomicRmwOp::I32AtomicRmwXor, memory_idx.0.into(), memarg),
+        ll::Instr::Atomic(ll::AtomicInstr::I64AtomicRmwXor(ll::MemoryMemarg(memory_idx, memarg))) => hl::Instr::AtomicRmw(hl::AtomicRmwOp::I64AtomicRmwXor, memory_idx.0.into(), memarg),
+        ll::Instr::Atomic(ll::AtomicInstr::I32AtomicRmw8XorU(ll::MemoryMemarg(memory_idx, memarg))) => hl::Instr::AtomicRmw(hl::AtomicRmwOp::I32AtomicRmw8XorU, memory_idx.0.into(), memarg),
+        ll::Instr::Atomic(ll::AtomicInstr::I32AtomicRmw16XorU(ll::MemoryMemarg(memory_idx, memarg))) => hl::Instr::AtomicRmw(hl::AtomicRmwOp::I32AtomicRmw16XorU, memory_idx.0.into(), memarg),
+        ll::Instr::Atomic(ll::AtomicInstr::I64AtomicRmw8XorU(ll::MemoryMemarg(memory_idx, memarg))) => hl::Instr::AtomicRmw(hl::AtomicRmwOp::I64AtomicRmw8XorU, memory_idx.0.into(), memarg),
+        ll::Instr::Atomic(ll::AtomicInstr::I64AtomicRmw16XorU(ll::MemoryMemarg(memory_idx, memarg))) => hl::Instr::AtomicRmw(hl::AtomicRmwOp::I64AtomicRmw16XorU, memory_idx.0.into(), memarg),
+        ll::Instr::Atomic(ll::AtomicInstr::I64AtomicRmw32XorU(ll::MemoryMemarg(memory_idx, memarg))) => hl::Instr::AtomicRmw(hl::AtomicRmwOp::I64AtomicRmw32XorU, memory_idx.0.into(), memarg),
+        ll::Instr::Atomic(ll::AtomicInstr::I32AtomicRmwXchg(ll::MemoryMemarg(memory_idx, memarg))) => hl::Instr::AtomicRmw(hl::AtomicRmwOp::I32AtomicRmwXchg, memory_idx.0.into(), memarg),
+        ll::Instr::Atomic(ll::AtomicInstr::I64AtomicRmwXchg(ll::MemoryMemarg(memory_idx, memarg))) => hl::Instr::AtomicRmw(hl::AtomicRmwOp::I64AtomicRmwXchg, memory_idx.0.into(), memarg),
+        ll::Instr::Atomic(ll::AtomicInstr::I32AtomicRmw8XchgU(ll::MemoryMemarg(memory_idx, memarg))) => hl::Instr::AtomicRmw(hl::AtomicRmwOp::I32AtomicRmw8XchgU, memory_idx.0.into(), memarg),
+        ll::Instr::Atomic(ll::AtomicInstr::I32AtomicRmw16XchgU(ll::MemoryMemarg(memory_idx, memarg))) => hl::Instr::AtomicRmw(hl::AtomicRmwOp::I32AtomicRmw16XchgU, memory_idx.0.into(), memarg),
+        ll::Instr::Atomic(ll::AtomicInstr::I64AtomicRmw8XchgU(ll::MemoryMemarg(memory_idx, memarg))) => hl::Instr::AtomicRmw(hl::AtomicRmwOp::I64AtomicRmw8XchgU, memory_idx.0.into(), memarg),
+        ll::Instr::Atomic(ll::AtomicInstr::I64AtomicRmw16XchgU(ll::MemoryMemarg(memory_idx, memarg))) => hl::Instr::AtomicRmw(hl::AtomicRmwOp::I64AtomicRmw16XchgU, memory_idx.0.into(), memarg),
+        ll::Instr::Atomic(ll::AtomicInstr::I64AtomicRmw32XchgU(ll::MemoryMemarg(memory_idx, memarg))) => hl::Instr::AtomicRmw(hl::AtomicRmwOp::I64AtomicRmw32XchgU, memory_idx.0.into(), memarg),
+        ll::Instr::Atomic(ll::AtomicInstr::I32AtomicRmwCmpxchg(ll::MemoryMemarg(memory_idx, memarg))) => hl::Instr::AtomicCmpxchg(hl::AtomicCmpxchgOp::I32AtomicRmwCmpxchg, memory_idx.0.into(), memarg),
+        ll::Instr::Atomic(ll::AtomicInstr::I64AtomicRmwCmpxchg(ll::MemoryMemarg(memory_idx, memarg))) => hl::Instr::AtomicCmpxchg(hl::AtomicCmpxchgOp::I64AtomicRmwCmpxchg, memory_idx.0.into(), memarg),
+        ll::Instr::Atomic(ll::AtomicInstr::I32AtomicRmw8CmpxchgU(ll::MemoryMemarg(memory_idx, memarg))) => hl::Instr::AtomicCmpxchg(hl::AtomicCmpxchgOp::I32AtomicRmw8CmpxchgU, memory_idx.0.into(), memarg),
+        ll::Instr::Atomic(ll::AtomicInstr::I32AtomicRmw16CmpxchgU(ll::MemoryMemarg(memory_idx, memarg))) => hl::Instr::AtomicCmpxchg(hl::AtomicCmpxchgOp::I32AtomicRmw16CmpxchgU, memory_idx.0.into(), memarg),
+        ll::Instr::Atomic(ll::AtomicInstr::I64AtomicRmw8CmpxchgU(ll::MemoryMemarg(memory_idx, memarg))) => hl::Instr::AtomicCmpxchg(hl::AtomicCmpxchgOp::I64AtomicRmw8CmpxchgU, memory_idx.0.into(), memarg),
+        ll::Instr::Atomic(ll::AtomicInstr::I64AtomicRmw16CmpxchgU(ll::MemoryMemarg(memory_idx, memarg))) => hl::Instr::AtomicCmpxchg(hl::AtomicCmpxchgOp::I64AtomicRmw16CmpxchgU, memory_idx.0.into(), memarg),
+        ll::Instr::Atomic(ll::AtomicInstr::I64AtomicRmw32CmpxchgU(ll::MemoryMemarg(memory_idx, memarg))) => hl::Instr::AtomicCmpxchg(hl::AtomicCmpxchgOp::I64AtomicRmw32CmpxchgU, memory_idx.0.into(), memarg),
     }
 }
 
@@ -355,20 +876,17 @@ fn from_lowlevel_instr(instr: ll::Instr, types: &[FunctionType]) -> hl::Instr {
 
 struct EncodeState {
     types: HashMap<FunctionType, usize>,
-    function_idx: HashMap<usize, usize>,
-    table_idx: HashMap<usize, usize>,
-    memory_idx: HashMap<usize, usize>,
-    global_idx: HashMap<usize, usize>,
+    indices: IndexRemapping,
 }
 
 macro_rules! element_idx_fns {
     ($insert_fn: ident, $map_fn: ident, $field: ident, $ll_ty: ty) => {
         fn $insert_fn(&mut self, old_idx: usize) {
-            let new_idx = self.$field.len();
-            self.$field.insert(old_idx, new_idx);
+            let new_idx = self.indices.$field.len();
+            self.indices.$field.insert(old_idx, new_idx);
         }
         fn $map_fn(&self, old_idx: usize) -> Idx<$ll_ty> {
-            self.$field[&old_idx].into()
+            self.indices.$field[&old_idx].into()
         }
     };
 }
@@ -379,13 +897,14 @@ impl EncodeState {
         (*self.types.entry(type_).or_insert(new_idx)).into()
     }
     fn get_type_idx(&self, type_: &FunctionType) -> Idx<FunctionType> {
-        (*self.types.get(type_).expect("call_indirect with unknown type")).into()
+        (*self.types.get(type_).expect("call_indirect or block with unknown type")).into()
     }
 
     element_idx_fns!(insert_function_idx, map_function_idx, function_idx, ll::Function);
     element_idx_fns!(insert_table_idx, map_table_idx, table_idx, ll::Table);
     element_idx_fns!(insert_memory_idx, map_memory_idx, memory_idx, ll::Memory);
     element_idx_fns!(insert_global_idx, map_global_idx, global_idx, ll::Global);
+    element_idx_fns!(insert_tag_idx, map_tag_idx, tag_idx, ll::Tag);
 }
 
 impl From<hl::Module> for ll::Module {
@@ -394,25 +913,32 @@ impl From<hl::Module> for ll::Module {
 
         let mut state = EncodeState {
             types: HashMap::new(),
-            function_idx: HashMap::new(),
-            table_idx: HashMap::new(),
-            memory_idx: HashMap::new(),
-            global_idx: HashMap::new(),
+            indices: IndexRemapping::default(),
         };
 
         let imports = to_lowlevel_imports(&module, &mut state);
         let functions = to_lowlevel_functions(&module.functions, &mut state);
         let tables = to_lowlevel_tables(&module.tables, &mut state);
         let memories = to_lowlevel_memories(&module.memories, &mut state);
+        let tags = to_lowlevel_tags(&module.tags, &mut state);
         let globals = to_lowlevel_globals(&module.globals, &mut state);
 
         // also collect and insert types in all call_indirect instructions, maybe they are calling
         // with a signature that not function mentions (which would be unpractical, because it could
         // never be valid at runtime, but is done in the spec tests)
+        // same for multi-value blocks, whose type is given by a type index
         for function in &module.functions {
             for instr in function.code.iter().flat_map(|c| c.body.iter()) {
-                if let hl::Instr::CallIndirect(ty, _) = instr {
-                    state.get_or_insert_type(ty.clone());
+                match *instr {
+                    hl::Instr::CallIndirect(ref ty, _) | hl::Instr::ReturnCallIndirect(ref ty, _) => {
+                        state.get_or_insert_type(ty.clone());
+                    }
+                    // see to_lowlevel_block_type()
+                    hl::Instr::Block(ref ty) | hl::Instr::Loop(ref ty) | hl::Instr::If(ref ty) | hl::Instr::Try(ref ty)
+                    if !ty.params.is_empty() || ty.results.len() > 1 => {
+                        state.get_or_insert_type(ty.clone());
+                    }
+                    _ => {}
                 }
             }
         }
@@ -449,6 +975,11 @@ impl From<hl::Module> for ll::Module {
             sections.push(ll::Section::Memory(ll::WithSize(memories)));
         }
 
+        // Tag (between memory and global section, despite the higher id)
+        if !tags.is_empty() {
+            sections.push(ll::Section::Tag(ll::WithSize(tags)));
+        }
+
         // Global
         if !globals.is_empty() {
             sections.push(ll::Section::Global(ll::WithSize(globals)));
@@ -460,26 +991,48 @@ impl From<hl::Module> for ll::Module {
             sections.push(ll::Section::Export(ll::WithSize(exports)));
         }
 
+        // collect names already here, because functions are consumed by the code section below
+        let names = to_lowlevel_names(&module, &state);
+
         // Start
-        for start in module.start {
+        if let Some(start) = module.start {
             sections.push(ll::Section::Start(ll::WithSize(state.map_function_idx(start.0))));
         }
 
         // Element
-        let elements: Vec<ll::Element> = module.tables.into_iter()
-            .enumerate()
-            .flat_map(|(i, table)| table.elements.into_iter()
-                .map(|element| ll::Element {
-                    table_idx: state.map_table_idx(i),
-                    offset: to_lowlevel_expr(&element.offset, &state),
-                    init: element.functions.iter().map(|fn_idx| state.map_function_idx(fn_idx.0)).collect(),
-                })
-                .collect::<Vec<_>>())
+        let elements: Vec<ll::Element> = module.elements.iter()
+            .map(|element| ll::Element {
+                mode: match element.mode {
+                    hl::ElementMode::Active { table_idx, ref offset } => ll::ElementMode::Active {
+                        table_idx: state.map_table_idx(table_idx.0),
+                        offset: to_lowlevel_expr(offset, &state),
+                    },
+                    hl::ElementMode::Passive => ll::ElementMode::Passive,
+                    hl::ElementMode::Declarative => ll::ElementMode::Declarative,
+                },
+                init: match element.items {
+                    hl::ElementItems::Functions(ref functions) =>
+                        ll::ElementItems::Functions(functions.iter().map(|fn_idx| state.map_function_idx(fn_idx.0)).collect()),
+                    hl::ElementItems::Exprs(elem_type, ref exprs) =>
+                        ll::ElementItems::Exprs(elem_type, exprs.iter().map(|expr| to_lowlevel_expr(expr, &state)).collect()),
+                },
+            })
             .collect();
         if !elements.is_empty() {
             sections.push(ll::Section::Element(ll::WithSize(elements)));
         }
 
+        // DataCount (only required if data segments are referred to from code)
+        let data_count_required = module.functions.iter()
+            .flat_map(|function| function.code.iter().flat_map(|c| c.body.iter()))
+            .any(|instr| match *instr {
+                hl::Instr::MemoryInit(..) | hl::Instr::DataDrop(..) => true,
+                _ => false,
+            });
+        if data_count_required {
+            sections.push(ll::Section::DataCount(ll::WithSize(module.data.len() as u32)));
+        }
+
         // Code
         let code: Vec<ll::WithSize<ll::Code>> = module.functions.into_par_iter()
             .filter_map(|function|
@@ -490,26 +1043,33 @@ impl From<hl::Module> for ll::Module {
         }
 
         // Data
-        let data: Vec<ll::Data> = module.memories.into_iter()
-            .enumerate()
-            .flat_map(|(i, memory)| memory.data.into_iter()
-                .map(|data| ll::Data {
-                    memory_idx: state.map_memory_idx(i),
-                    offset: to_lowlevel_expr(&data.offset, &state),
-                    init: data.bytes,
-                })
-                .collect::<Vec<_>>())
+        let data: Vec<ll::Data> = module.data.into_iter()
+            .map(|data| ll::Data {
+                mode: match data.mode {
+                    hl::DataMode::Active { memory_idx, offset } => ll::DataMode::Active {
+                        memory_idx: state.map_memory_idx(memory_idx.0),
+                        offset: to_lowlevel_expr(&offset, &state),
+                    },
+                    hl::DataMode::Passive => ll::DataMode::Passive,
+                },
+                init: data.bytes,
+            })
             .collect();
         if !data.is_empty() {
             sections.push(ll::Section::Data(ll::WithSize(data)));
         }
 
-        // Custom
+        // Custom: "name" (must come after the data section)
+        if !names.subsections.is_empty() {
+            sections.push(ll::Section::Custom(encode_name_section(&names)));
+        }
+
+        // Custom: all others
         // TODO put the pass-through custom sections in the same order as they were originally
         // necessary, e.g., because custom section "name" must come in some specific order
         // requires saving the order earlier when converting from ll -> hl
         for custom in module.custom_sections {
-            sections.push(ll::Section::Custom(custom));
+            sections.push(ll::Section::Custom(remap_custom_section(custom, &state.indices)));
         }
 
         ll::Module { sections }
@@ -538,6 +1098,7 @@ fn to_lowlevel_imports(module: &hl::Module, state: &mut EncodeState) -> Vec<ll::
     add_imports!(tables, insert_table_idx, Table, |ty| ty);
     add_imports!(memories, insert_memory_idx, Memory, |ty| ty);
     add_imports!(globals, insert_global_idx, Global, |ty| ty);
+    add_imports!(tags, insert_tag_idx, Tag, |ty| ll::TagType(state.get_or_insert_type(ty)));
 
     imports
 }
@@ -567,6 +1128,10 @@ fn to_lowlevel_memories(memories: &[hl::Memory], state: &mut EncodeState) -> Vec
     to_lowlevel_elements!(memories, state, insert_memory_idx, |memory: &hl::Memory| memory.type_.clone())
 }
 
+fn to_lowlevel_tags(tags: &[hl::Tag], state: &mut EncodeState) -> Vec<ll::TagType> {
+    to_lowlevel_elements!(tags, state, insert_tag_idx, |tag: &hl::Tag| ll::TagType(state.get_or_insert_type(tag.type_.clone())))
+}
+
 fn to_lowlevel_globals(globals: &[hl::Global], state: &mut EncodeState) -> Vec<ll::Global> {
     to_lowlevel_elements!(globals, state, insert_global_idx, |global: &hl::Global| ll::Global {
         type_: global.type_,
@@ -593,10 +1158,70 @@ fn to_lowlevel_exports(module: &hl::Module, state: &EncodeState) -> Vec<ll::Expo
     add_exports!(tables, map_table_idx, Table);
     add_exports!(memories, map_memory_idx, Memory);
     add_exports!(globals, map_global_idx, Global);
+    add_exports!(tags, map_tag_idx, Tag);
 
     exports
 }
 
+fn to_lowlevel_names(module: &hl::Module, state: &EncodeState) -> ll::NameSection {
+    let mut subsections = Vec::new();
+
+    if let Some(ref name) = module.name {
+        subsections.push(ll::NameSubSection::Module(ll::WithSize(name.clone())));
+    }
+
+    // collect with high-level indices first, then let remapping translate them to low-level
+    // indices (which also sorts the name maps by the new index, as required)
+
+    let function_names: Vec<ll::FunctionName> = module.functions.iter()
+        .enumerate()
+        .filter_map(|(i, function)| function.name.as_ref().map(|name| ll::FunctionName {
+            function_idx: i.into(),
+            name: name.clone(),
+        }))
+        .collect();
+    if !function_names.is_empty() {
+        subsections.push(ll::NameSubSection::Function(ll::WithSize(function_names)));
+    }
+
+    let local_names: Vec<ll::LocalNames> = module.functions.iter()
+        .enumerate()
+        .filter(|&(_, function)| !function.local_names.is_empty())
+        .map(|(i, function)| ll::LocalNames {
+            function_idx: i.into(),
+            // BTreeMap iterates already sorted by local index
+            names: function.local_names.iter()
+                .map(|(&local_idx, name)| ll::LocalName { local_idx, name: name.clone() })
+                .collect(),
+        })
+        .collect();
+    if !local_names.is_empty() {
+        subsections.push(ll::NameSubSection::Local(ll::WithSize(local_names)));
+    }
+
+    let global_names: Vec<ll::GlobalName> = module.globals.iter()
+        .enumerate()
+        .filter_map(|(i, global)| global.name.as_ref().map(|name| ll::GlobalName {
+            global_idx: i.into(),
+            name: name.clone(),
+        }))
+        .collect();
+    if !global_names.is_empty() {
+        subsections.push(ll::NameSubSection::Global(ll::WithSize(global_names)));
+    }
+
+    let mut names = ll::NameSection { subsections };
+    names.remap_indices(&state.indices);
+    names
+}
+
+fn encode_name_section(names: &ll::NameSection) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    "name".to_string().encode(&mut bytes).expect("writing to Vec<u8> cannot fail");
+    names.encode(&mut bytes).expect("writing to Vec<u8> cannot fail");
+    bytes
+}
+
 fn to_lowlevel_code(code: hl::Code, state: &EncodeState) -> ll::Code {
     let mut locals = Vec::new();
     for type_ in code.locals {
@@ -621,17 +1246,33 @@ fn to_lowlevel_expr(expr: &[hl::Instr], state: &EncodeState) -> ll::Expr {
     ll::Expr(expr.iter().map(|instr| to_lowlevel_instr(instr, state)).collect())
 }
 
+/// use the compact encodings without type index where possible (i.e., blocks from before multi-value)
+fn to_lowlevel_block_type(block_type: &FunctionType, state: &EncodeState) -> ll::BlockType {
+    match (&block_type.params[..], &block_type.results[..]) {
+        (&[], &[]) => ll::BlockType::Empty,
+        (&[], &[ty]) => ll::BlockType::Value(ty),
+        _ => ll::BlockType::FunctionType(state.get_type_idx(block_type)),
+    }
+}
+
 fn to_lowlevel_instr(instr: &hl::Instr, state: &EncodeState) -> ll::Instr {
     match *instr {
         hl::Instr::Unreachable => ll::Instr::Unreachable,
         hl::Instr::Nop => ll::Instr::Nop,
 
-        hl::Instr::Block(block_type) => ll::Instr::Block(block_type),
-        hl::Instr::Loop(block_type) => ll::Instr::Loop(block_type),
-        hl::Instr::If(block_type) => ll::Instr::If(block_type),
+        hl::Instr::Block(ref block_type) => ll::Instr::Block(to_lowlevel_block_type(block_type, state)),
+        hl::Instr::Loop(ref block_type) => ll::Instr::Loop(to_lowlevel_block_type(block_type, state)),
+        hl::Instr::If(ref block_type) => ll::Instr::If(to_lowlevel_block_type(block_type, state)),
         hl::Instr::Else => ll::Instr::Else,
         hl::Instr::End => ll::Instr::End,
 
+        hl::Instr::Try(ref block_type) => ll::Instr::Try(to_lowlevel_block_type(block_type, state)),
+        hl::Instr::Catch(tag_idx) => ll::Instr::Catch(state.map_tag_idx(tag_idx.0)),
+        hl::Instr::CatchAll => ll::Instr::CatchAll,
+        hl::Instr::Delegate(label_idx) => ll::Instr::Delegate(label_idx),
+        hl::Instr::Throw(tag_idx) => ll::Instr::Throw(state.map_tag_idx(tag_idx.0)),
+        hl::Instr::Rethrow(label_idx) => ll::Instr::Rethrow(label_idx),
+
         hl::Instr::Br(label_idx) => ll::Instr::Br(label_idx),
         hl::Instr::BrIf(label_idx) => ll::Instr::BrIf(label_idx),
         hl::Instr::BrTable(ref label_idx_table, default) => ll::Instr::BrTable(label_idx_table.clone(), default),
@@ -639,9 +1280,12 @@ fn to_lowlevel_instr(instr: &hl::Instr, state: &EncodeState) -> ll::Instr {
         hl::Instr::Return => ll::Instr::Return,
         hl::Instr::Call(function_idx) => ll::Instr::Call(state.map_function_idx(function_idx.0)),
         hl::Instr::CallIndirect(ref type_, table_idx) => ll::Instr::CallIndirect(state.get_type_idx(&type_), state.map_table_idx(table_idx.0)),
+        hl::Instr::ReturnCall(function_idx) => ll::Instr::ReturnCall(state.map_function_idx(function_idx.0)),
+        hl::Instr::ReturnCallIndirect(ref type_, table_idx) => ll::Instr::ReturnCallIndirect(state.get_type_idx(&type_), state.map_table_idx(table_idx.0)),
 
         hl::Instr::Drop => ll::Instr::Drop,
         hl::Instr::Select => ll::Instr::Select,
+        hl::Instr::TypedSelect(val_type) => ll::Instr::TypedSelect(vec![val_type]),
 
         hl::Instr::Local(hl::LocalOp::GetLocal, local_idx) => ll::Instr::GetLocal(local_idx.0.into()),
         hl::Instr::Local(hl::LocalOp::SetLocal, local_idx) => ll::Instr::SetLocal(local_idx.0.into()),
@@ -649,33 +1293,51 @@ fn to_lowlevel_instr(instr: &hl::Instr, state: &EncodeState) -> ll::Instr {
         hl::Instr::Global(hl::GlobalOp::GetGlobal, global_idx) => ll::Instr::GetGlobal(state.map_global_idx(global_idx.0)),
         hl::Instr::Global(hl::GlobalOp::SetGlobal, global_idx) => ll::Instr::SetGlobal(state.map_global_idx(global_idx.0)),
 
-        hl::Instr::Load(hl::LoadOp::I32Load, memarg) => ll::Instr::I32Load(memarg),
-        hl::Instr::Load(hl::LoadOp::I64Load, memarg) => ll::Instr::I64Load(memarg),
-        hl::Instr::Load(hl::LoadOp::F32Load, memarg) => ll::Instr::F32Load(memarg),
-        hl::Instr::Load(hl::LoadOp::F64Load, memarg) => ll::Instr::F64Load(memarg),
-        hl::Instr::Load(hl::LoadOp::I32Load8S, memarg) => ll::Instr::I32Load8S(memarg),
-        hl::Instr::Load(hl::LoadOp::I32Load8U, memarg) => ll::Instr::I32Load8U(memarg),
-        hl::Instr::Load(hl::LoadOp::I32Load16S, memarg) => ll::Instr::I32Load16S(memarg),
-        hl::Instr::Load(hl::LoadOp::I32Load16U, memarg) => ll::Instr::I32Load16U(memarg),
-        hl::Instr::Load(hl::LoadOp::I64Load8S, memarg) => ll::Instr::I64Load8S(memarg),
-        hl::Instr::Load(hl::LoadOp::I64Load8U, memarg) => ll::Instr::I64Load8U(memarg),
-        hl::Instr::Load(hl::LoadOp::I64Load16S, memarg) => ll::Instr::I64Load16S(memarg),
-        hl::Instr::Load(hl::LoadOp::I64Load16U, memarg) => ll::Instr::I64Load16U(memarg),
-        hl::Instr::Load(hl::LoadOp::I64Load32S, memarg) => ll::Instr::I64Load32S(memarg),
-        hl::Instr::Load(hl::LoadOp::I64Load32U, memarg) => ll::Instr::I64Load32U(memarg),
-        hl::Instr::Store(hl::StoreOp::I32Store, memarg) => ll::Instr::I32Store(memarg),
-        hl::Instr::Store(hl::StoreOp::I64Store, memarg) => ll::Instr::I64Store(memarg),
-        hl::Instr::Store(hl::StoreOp::F32Store, memarg) => ll::Instr::F32Store(memarg),
-        hl::Instr::Store(hl::StoreOp::F64Store, memarg) => ll::Instr::F64Store(memarg),
-        hl::Instr::Store(hl::StoreOp::I32Store8, memarg) => ll::Instr::I32Store8(memarg),
-        hl::Instr::Store(hl::StoreOp::I32Store16, memarg) => ll::Instr::I32Store16(memarg),
-        hl::Instr::Store(hl::StoreOp::I64Store8, memarg) => ll::Instr::I64Store8(memarg),
-        hl::Instr::Store(hl::StoreOp::I64Store16, memarg) => ll::Instr::I64Store16(memarg),
-        hl::Instr::Store(hl::StoreOp::I64Store32, memarg) => ll::Instr::I64Store32(memarg),
+        hl::Instr::TableGet(table_idx) => ll::Instr::TableGet(state.map_table_idx(table_idx.0)),
+        hl::Instr::TableSet(table_idx) => ll::Instr::TableSet(state.map_table_idx(table_idx.0)),
+
+        hl::Instr::Load(hl::LoadOp::I32Load, memory_idx, memarg) => ll::Instr::I32Load(ll::MemoryMemarg(state.map_memory_idx(memory_idx.0), memarg)),
+        hl::Instr::Load(hl::LoadOp::I64Load, memory_idx, memarg) => ll::Instr::I64Load(ll::MemoryMemarg(state.map_memory_idx(memory_idx.0), memarg)),
+        hl::Instr::Load(hl::LoadOp::F32Load, memory_idx, memarg) => ll::Instr::F32Load(ll::MemoryMemarg(state.map_memory_idx(memory_idx.0), memarg)),
+        hl::Instr::Load(hl::LoadOp::F64Load, memory_idx, memarg) => ll::Instr::F64Load(ll::MemoryMemarg(state.map_memory_idx(memory_idx.0), memarg)),
+        hl::Instr::Load(hl::LoadOp::I32Load8S, memory_idx, memarg) => ll::Instr::I32Load8S(ll::MemoryMemarg(state.map_memory_idx(memory_idx.0), memarg)),
+        hl::Instr::Load(hl::LoadOp::I32Load8U, memory_idx, memarg) => ll::Instr::I32Load8U(ll::MemoryMemarg(state.map_memory_idx(memory_idx.0), memarg)),
+        hl::Instr::Load(hl::LoadOp::I32Load16S, memory_idx, memarg) => ll::Instr::I32Load16S(ll::MemoryMemarg(state.map_memory_idx(memory_idx.0), memarg)),
+        hl::Instr::Load(hl::LoadOp::I32Load16U, memory_idx, memarg) => ll::Instr::I32Load16U(ll::MemoryMemarg(state.map_memory_idx(memory_idx.0), memarg)),
+        hl::Instr::Load(hl::LoadOp::I64Load8S, memory_idx, memarg) => ll::Instr::I64Load8S(ll::MemoryMemarg(state.map_memory_idx(memory_idx.0), memarg)),
+        hl::Instr::Load(hl::LoadOp::I64Load8U, memory_idx, memarg) => ll::Instr::I64Load8U(ll::MemoryMemarg(state.map_memory_idx(memory_idx.0), memarg)),
+        hl::Instr::Load(hl::LoadOp::I64Load16S, memory_idx, memarg) => ll::Instr::I64Load16S(ll::MemoryMemarg(state.map_memory_idx(memory_idx.0), memarg)),
+        hl::Instr::Load(hl::LoadOp::I64Load16U, memory_idx, memarg) => ll::Instr::I64Load16U(ll::MemoryMemarg(state.map_memory_idx(memory_idx.0), memarg)),
+        hl::Instr::Load(hl::LoadOp::I64Load32S, memory_idx, memarg) => ll::Instr::I64Load32S(ll::MemoryMemarg(state.map_memory_idx(memory_idx.0), memarg)),
+        hl::Instr::Load(hl::LoadOp::I64Load32U, memory_idx, memarg) => ll::Instr::I64Load32U(ll::MemoryMemarg(state.map_memory_idx(memory_idx.0), memarg)),
+        hl::Instr::Store(hl::StoreOp::I32Store, memory_idx, memarg) => ll::Instr::I32Store(ll::MemoryMemarg(state.map_memory_idx(memory_idx.0), memarg)),
+        hl::Instr::Store(hl::StoreOp::I64Store, memory_idx, memarg) => ll::Instr::I64Store(ll::MemoryMemarg(state.map_memory_idx(memory_idx.0), memarg)),
+        hl::Instr::Store(hl::StoreOp::F32Store, memory_idx, memarg) => ll::Instr::F32Store(ll::MemoryMemarg(state.map_memory_idx(memory_idx.0), memarg)),
+        hl::Instr::Store(hl::StoreOp::F64Store, memory_idx, memarg) => ll::Instr::F64Store(ll::MemoryMemarg(state.map_memory_idx(memory_idx.0), memarg)),
+        hl::Instr::Store(hl::StoreOp::I32Store8, memory_idx, memarg) => ll::Instr::I32Store8(ll::MemoryMemarg(state.map_memory_idx(memory_idx.0), memarg)),
+        hl::Instr::Store(hl::StoreOp::I32Store16, memory_idx, memarg) => ll::Instr::I32Store16(ll::MemoryMemarg(state.map_memory_idx(memory_idx.0), memarg)),
+        hl::Instr::Store(hl::StoreOp::I64Store8, memory_idx, memarg) => ll::Instr::I64Store8(ll::MemoryMemarg(state.map_memory_idx(memory_idx.0), memarg)),
+        hl::Instr::Store(hl::StoreOp::I64Store16, memory_idx, memarg) => ll::Instr::I64Store16(ll::MemoryMemarg(state.map_memory_idx(memory_idx.0), memarg)),
+        hl::Instr::Store(hl::StoreOp::I64Store32, memory_idx, memarg) => ll::Instr::I64Store32(ll::MemoryMemarg(state.map_memory_idx(memory_idx.0), memarg)),
 
         hl::Instr::MemorySize(memory_idx) => ll::Instr::MemorySize(state.map_memory_idx(memory_idx.0)),
         hl::Instr::MemoryGrow(memory_idx) => ll::Instr::MemoryGrow(state.map_memory_idx(memory_idx.0)),
 
+        hl::Instr::MemoryInit(data_idx, memory_idx) => ll::Instr::Misc(ll::MiscInstr::MemoryInit(data_idx.0.into(), state.map_memory_idx(memory_idx.0))),
+        hl::Instr::DataDrop(data_idx) => ll::Instr::Misc(ll::MiscInstr::DataDrop(data_idx.0.into())),
+        hl::Instr::MemoryCopy(dst_idx, src_idx) => ll::Instr::Misc(ll::MiscInstr::MemoryCopy(state.map_memory_idx(dst_idx.0), state.map_memory_idx(src_idx.0))),
+        hl::Instr::MemoryFill(memory_idx) => ll::Instr::Misc(ll::MiscInstr::MemoryFill(state.map_memory_idx(memory_idx.0))),
+        hl::Instr::TableInit(element_idx, table_idx) => ll::Instr::Misc(ll::MiscInstr::TableInit(element_idx.0.into(), state.map_table_idx(table_idx.0))),
+        hl::Instr::ElemDrop(element_idx) => ll::Instr::Misc(ll::MiscInstr::ElemDrop(element_idx.0.into())),
+        hl::Instr::TableCopy(dst_idx, src_idx) => ll::Instr::Misc(ll::MiscInstr::TableCopy(state.map_table_idx(dst_idx.0), state.map_table_idx(src_idx.0))),
+        hl::Instr::TableGrow(table_idx) => ll::Instr::Misc(ll::MiscInstr::TableGrow(state.map_table_idx(table_idx.0))),
+        hl::Instr::TableSize(table_idx) => ll::Instr::Misc(ll::MiscInstr::TableSize(state.map_table_idx(table_idx.0))),
+        hl::Instr::TableFill(table_idx) => ll::Instr::Misc(ll::MiscInstr::TableFill(state.map_table_idx(table_idx.0))),
+
+        hl::Instr::RefNull(elem_type) => ll::Instr::RefNull(elem_type),
+        hl::Instr::RefIsNull => ll::Instr::RefIsNull,
+        hl::Instr::RefFunc(function_idx) => ll::Instr::RefFunc(state.map_function_idx(function_idx.0)),
+
         hl::Instr::Const(Val::I32(immediate)) => ll::Instr::I32Const(immediate),
         hl::Instr::Const(Val::I64(immediate)) => ll::Instr::I64Const(immediate),
         hl::Instr::Const(Val::F32(immediate)) => ll::Instr::F32Const(immediate),
@@ -804,5 +1466,321 @@ fn to_lowlevel_instr(instr: &hl::Instr, state: &EncodeState) -> ll::Instr {
         hl::Instr::Numeric(hl::NumericOp::I64ReinterpretF64) => ll::Instr::I64ReinterpretF64,
         hl::Instr::Numeric(hl::NumericOp::F32ReinterpretI32) => ll::Instr::F32ReinterpretI32,
         hl::Instr::Numeric(hl::NumericOp::F64ReinterpretI64) => ll::Instr::F64ReinterpretI64,
+        hl::Instr::Numeric(hl::NumericOp::I32Extend8S) => ll::Instr::I32Extend8S,
+        hl::Instr::Numeric(hl::NumericOp::I32Extend16S) => ll::Instr::I32Extend16S,
+        hl::Instr::Numeric(hl::NumericOp::I64Extend8S) => ll::Instr::I64Extend8S,
+        hl::Instr::Numeric(hl::NumericOp::I64Extend16S) => ll::Instr::I64Extend16S,
+        hl::Instr::Numeric(hl::NumericOp::I64Extend32S) => ll::Instr::I64Extend32S,
+        hl::Instr::Numeric(hl::NumericOp::I32TruncSatSF32) => ll::Instr::Misc(ll::MiscInstr::I32TruncSatSF32),
+        hl::Instr::Numeric(hl::NumericOp::I32TruncSatUF32) => ll::Instr::Misc(ll::MiscInstr::I32TruncSatUF32),
+        hl::Instr::Numeric(hl::NumericOp::I32TruncSatSF64) => ll::Instr::Misc(ll::MiscInstr::I32TruncSatSF64),
+        hl::Instr::Numeric(hl::NumericOp::I32TruncSatUF64) => ll::Instr::Misc(ll::MiscInstr::I32TruncSatUF64),
+        hl::Instr::Numeric(hl::NumericOp::I64TruncSatSF32) => ll::Instr::Misc(ll::MiscInstr::I64TruncSatSF32),
+        hl::Instr::Numeric(hl::NumericOp::I64TruncSatUF32) => ll::Instr::Misc(ll::MiscInstr::I64TruncSatUF32),
+        hl::Instr::Numeric(hl::NumericOp::I64TruncSatSF64) => ll::Instr::Misc(ll::MiscInstr::I64TruncSatSF64),
+        hl::Instr::Numeric(hl::NumericOp::I64TruncSatUF64) => ll::Instr::Misc(ll::MiscInstr::I64TruncSatUF64),
+        hl::Instr::Load(hl::LoadOp::V128Load, memory_idx, memarg) => ll::Instr::Simd(ll::SimdInstr::V128Load(ll::MemoryMemarg(state.map_memory_idx(memory_idx.0), memarg))),
+        hl::Instr::Load(hl::LoadOp::V128Load8x8S, memory_idx, memarg) => ll::Instr::Simd(ll::SimdInstr::V128Load8x8S(ll::MemoryMemarg(state.map_memory_idx(memory_idx.0), memarg))),
+        hl::Instr::Load(hl::LoadOp::V128Load8x8U, memory_idx, memarg) => ll::Instr::Simd(ll::SimdInstr::V128Load8x8U(ll::MemoryMemarg(state.map_memory_idx(memory_idx.0), memarg))),
+        hl::Instr::Load(hl::LoadOp::V128Load16x4S, memory_idx, memarg) => ll::Instr::Simd(ll::SimdInstr::V128Load16x4S(ll::MemoryMemarg(state.map_memory_idx(memory_idx.0), memarg))),
+        hl::Instr::Load(hl::LoadOp::V128Load16x4U, memory_idx, memarg) => ll::Instr::Simd(ll::SimdInstr::V128Load16x4U(ll::MemoryMemarg(state.map_memory_idx(memory_idx.0), memarg))),
+        hl::Instr::Load(hl::LoadOp::V128Load32x2S, memory_idx, memarg) => ll::Instr::Simd(ll::SimdInstr::V128Load32x2S(ll::MemoryMemarg(state.map_memory_idx(memory_idx.0), memarg))),
+        hl::Instr::Load(hl::LoadOp::V128Load32x2U, memory_idx, memarg) => ll::Instr::Simd(ll::SimdInstr::V128Load32x2U(ll::MemoryMemarg(state.map_memory_idx(memory_idx.0), memarg))),
+        hl::Instr::Load(hl::LoadOp::V128Load8Splat, memory_idx, memarg) => ll::Instr::Simd(ll::SimdInstr::V128Load8Splat(ll::MemoryMemarg(state.map_memory_idx(memory_idx.0), memarg))),
+        hl::Instr::Load(hl::LoadOp::V128Load16Splat, memory_idx, memarg) => ll::Instr::Simd(ll::SimdInstr::V128Load16Splat(ll::MemoryMemarg(state.map_memory_idx(memory_idx.0), memarg))),
+        hl::Instr::Load(hl::LoadOp::V128Load32Splat, memory_idx, memarg) => ll::Instr::Simd(ll::SimdInstr::V128Load32Splat(ll::MemoryMemarg(state.map_memory_idx(memory_idx.0), memarg))),
+        hl::Instr::Load(hl::LoadOp::V128Load64Splat, memory_idx, memarg) => ll::Instr::Simd(ll::SimdInstr::V128Load64Splat(ll::MemoryMemarg(state.map_memory_idx(memory_idx.0), memarg))),
+        hl::Instr::Store(hl::StoreOp::V128Store, memory_idx, memarg) => ll::Instr::Simd(ll::SimdInstr::V128Store(ll::MemoryMemarg(state.map_memory_idx(memory_idx.0), memarg))),
+        hl::Instr::Const(Val::V128(immediate)) => ll::Instr::Simd(ll::SimdInstr::V128Const(immediate)),
+        hl::Instr::Shuffle(lanes) => ll::Instr::Simd(ll::SimdInstr::I8x16Shuffle(lanes)),
+        hl::Instr::Numeric(hl::NumericOp::I8x16Swizzle) => ll::Instr::Simd(ll::SimdInstr::I8x16Swizzle),
+        hl::Instr::Numeric(hl::NumericOp::I8x16Splat) => ll::Instr::Simd(ll::SimdInstr::I8x16Splat),
+        hl::Instr::Numeric(hl::NumericOp::I16x8Splat) => ll::Instr::Simd(ll::SimdInstr::I16x8Splat),
+        hl::Instr::Numeric(hl::NumericOp::I32x4Splat) => ll::Instr::Simd(ll::SimdInstr::I32x4Splat),
+        hl::Instr::Numeric(hl::NumericOp::I64x2Splat) => ll::Instr::Simd(ll::SimdInstr::I64x2Splat),
+        hl::Instr::Numeric(hl::NumericOp::F32x4Splat) => ll::Instr::Simd(ll::SimdInstr::F32x4Splat),
+        hl::Instr::Numeric(hl::NumericOp::F64x2Splat) => ll::Instr::Simd(ll::SimdInstr::F64x2Splat),
+        hl::Instr::ExtractLane(hl::ExtractLaneOp::I8x16ExtractLaneS, lane) => ll::Instr::Simd(ll::SimdInstr::I8x16ExtractLaneS(lane)),
+        hl::Instr::ExtractLane(hl::ExtractLaneOp::I8x16ExtractLaneU, lane) => ll::Instr::Simd(ll::SimdInstr::I8x16ExtractLaneU(lane)),
+        hl::Instr::ReplaceLane(hl::ReplaceLaneOp::I8x16ReplaceLane, lane) => ll::Instr::Simd(ll::SimdInstr::I8x16ReplaceLane(lane)),
+        hl::Instr::ExtractLane(hl::ExtractLaneOp::I16x8ExtractLaneS, lane) => ll::Instr::Simd(ll::SimdInstr::I16x8ExtractLaneS(lane)),
+        hl::Instr::ExtractLane(hl::ExtractLaneOp::I16x8ExtractLaneU, lane) => ll::Instr::Simd(ll::SimdInstr::I16x8ExtractLaneU(lane)),
+        hl::Instr::ReplaceLane(hl::ReplaceLaneOp::I16x8ReplaceLane, lane) => ll::Instr::Simd(ll::SimdInstr::I16x8ReplaceLane(lane)),
+        hl::Instr::ExtractLane(hl::ExtractLaneOp::I32x4ExtractLane, lane) => ll::Instr::Simd(ll::SimdInstr::I32x4ExtractLane(lane)),
+        hl::Instr::ReplaceLane(hl::ReplaceLaneOp::I32x4ReplaceLane, lane) => ll::Instr::Simd(ll::SimdInstr::I32x4ReplaceLane(lane)),
+        hl::Instr::ExtractLane(hl::ExtractLaneOp::I64x2ExtractLane, lane) => ll::Instr::Simd(ll::SimdInstr::I64x2ExtractLane(lane)),
+        hl::Instr::ReplaceLane(hl::ReplaceLaneOp::I64x2ReplaceLane, lane) => ll::Instr::Simd(ll::SimdInstr::I64x2ReplaceLane(lane)),
+        hl::Instr::ExtractLane(hl::ExtractLaneOp::F32x4ExtractLane, lane) => ll::Instr::Simd(ll::SimdInstr::F32x4ExtractLane(lane)),
+        hl::Instr::ReplaceLane(hl::ReplaceLaneOp::F32x4ReplaceLane, lane) => ll::Instr::Simd(ll::SimdInstr::F32x4ReplaceLane(lane)),
+        hl::Instr::ExtractLane(hl::ExtractLaneOp::F64x2ExtractLane, lane) => ll::Instr::Simd(ll::SimdInstr::F64x2ExtractLane(lane)),
+        hl::Instr::ReplaceLane(hl::ReplaceLaneOp::F64x2ReplaceLane, lane) => ll::Instr::Simd(ll::SimdInstr::F64x2ReplaceLane(lane)),
+        hl::Instr::Numeric(hl::NumericOp::I8x16Eq) => ll::Instr::Simd(ll::SimdInstr::I8x16Eq),
+        hl::Instr::Numeric(hl::NumericOp::I8x16Ne) => ll::Instr::Simd(ll::SimdInstr::I8x16Ne),
+        hl::Instr::Numeric(hl::NumericOp::I8x16LtS) => ll::Instr::Simd(ll::SimdInstr::I8x16LtS),
+        hl::Instr::Numeric(hl::NumericOp::I8x16LtU) => ll::Instr::Simd(ll::SimdInstr::I8x16LtU),
+        hl::Instr::Numeric(hl::NumericOp::I8x16GtS) => ll::Instr::Simd(ll::SimdInstr::I8x16GtS),
+        hl::Instr::Numeric(hl::NumericOp::I8x16GtU) => ll::Instr::Simd(ll::SimdInstr::I8x16GtU),
+        hl::Instr::Numeric(hl::NumericOp::I8x16LeS) => ll::Instr::Simd(ll::SimdInstr::I8x16LeS),
+        hl::Instr::Numeric(hl::NumericOp::I8x16LeU) => ll::Instr::Simd(ll::SimdInstr::I8x16LeU),
+        hl::Instr::Numeric(hl::NumericOp::I8x16GeS) => ll::Instr::Simd(ll::SimdInstr::I8x16GeS),
+        hl::Instr::Numeric(hl::NumericOp::I8x16GeU) => ll::Instr::Simd(ll::SimdInstr::I8x16GeU),
+        hl::Instr::Numeric(hl::NumericOp::I16x8Eq) => ll::Instr::Simd(ll::SimdInstr::I16x8Eq),
+        hl::Instr::Numeric(hl::NumericOp::I16x8Ne) => ll::Instr::Simd(ll::SimdInstr::I16x8Ne),
+        hl::Instr::Numeric(hl::NumericOp::I16x8LtS) => ll::Instr::Simd(ll::SimdInstr::I16x8LtS),
+        hl::Instr::Numeric(hl::NumericOp::I16x8LtU) => ll::Instr::Simd(ll::SimdInstr::I16x8LtU),
+        hl::Instr::Numeric(hl::NumericOp::I16x8GtS) => ll::Instr::Simd(ll::SimdInstr::I16x8GtS),
+        hl::Instr::Numeric(hl::NumericOp::I16x8GtU) => ll::Instr::Simd(ll::SimdInstr::I16x8GtU),
+        hl::Instr::Numeric(hl::NumericOp::I16x8LeS) => ll::Instr::Simd(ll::SimdInstr::I16x8LeS),
+        hl::Instr::Numeric(hl::NumericOp::I16x8LeU) => ll::Instr::Simd(ll::SimdInstr::I16x8LeU),
+        hl::Instr::Numeric(hl::NumericOp::I16x8GeS) => ll::Instr::Simd(ll::SimdInstr::I16x8GeS),
+        hl::Instr::Numeric(hl::NumericOp::I16x8GeU) => ll::Instr::Simd(ll::SimdInstr::I16x8GeU),
+        hl::Instr::Numeric(hl::NumericOp::I32x4Eq) => ll::Instr::Simd(ll::SimdInstr::I32x4Eq),
+        hl::Instr::Numeric(hl::NumericOp::I32x4Ne) => ll::Instr::Simd(ll::SimdInstr::I32x4Ne),
+        hl::Instr::Numeric(hl::NumericOp::I32x4LtS) => ll::Instr::Simd(ll::SimdInstr::I32x4LtS),
+        hl::Instr::Numeric(hl::NumericOp::I32x4LtU) => ll::Instr::Simd(ll::SimdInstr::I32x4LtU),
+        hl::Instr::Numeric(hl::NumericOp::I32x4GtS) => ll::Instr::Simd(ll::SimdInstr::I32x4GtS),
+        hl::Instr::Numeric(hl::NumericOp::I32x4GtU) => ll::Instr::Simd(ll::SimdInstr::I32x4GtU),
+        hl::Instr::Numeric(hl::NumericOp::I32x4LeS) => ll::Instr::Simd(ll::SimdInstr::I32x4LeS),
+        hl::Instr::Numeric(hl::NumericOp::I32x4LeU) => ll::Instr::Simd(ll::SimdInstr::I32x4LeU),
+        hl::Instr::Numeric(hl::NumericOp::I32x4GeS) => ll::Instr::Simd(ll::SimdInstr::I32x4GeS),
+        hl::Instr::Numeric(hl::NumericOp::I32x4GeU) => ll::Instr::Simd(ll::SimdInstr::I32x4GeU),
+        hl::Instr::Numeric(hl::NumericOp::F32x4Eq) => ll::Instr::Simd(ll::SimdInstr::F32x4Eq),
+        hl::Instr::Numeric(hl::NumericOp::F32x4Ne) => ll::Instr::Simd(ll::SimdInstr::F32x4Ne),
+        hl::Instr::Numeric(hl::NumericOp::F32x4Lt) => ll::Instr::Simd(ll::SimdInstr::F32x4Lt),
+        hl::Instr::Numeric(hl::NumericOp::F32x4Gt) => ll::Instr::Simd(ll::SimdInstr::F32x4Gt),
+        hl::Instr::Numeric(hl::NumericOp::F32x4Le) => ll::Instr::Simd(ll::SimdInstr::F32x4Le),
+        hl::Instr::Numeric(hl::NumericOp::F32x4Ge) => ll::Instr::Simd(ll::SimdInstr::F32x4Ge),
+        hl::Instr::Numeric(hl::NumericOp::F64x2Eq) => ll::Instr::Simd(ll::SimdInstr::F64x2Eq),
+        hl::Instr::Numeric(hl::NumericOp::F64x2Ne) => ll::Instr::Simd(ll::SimdInstr::F64x2Ne),
+        hl::Instr::Numeric(hl::NumericOp::F64x2Lt) => ll::Instr::Simd(ll::SimdInstr::F64x2Lt),
+        hl::Instr::Numeric(hl::NumericOp::F64x2Gt) => ll::Instr::Simd(ll::SimdInstr::F64x2Gt),
+        hl::Instr::Numeric(hl::NumericOp::F64x2Le) => ll::Instr::Simd(ll::SimdInstr::F64x2Le),
+        hl::Instr::Numeric(hl::NumericOp::F64x2Ge) => ll::Instr::Simd(ll::SimdInstr::F64x2Ge),
+        hl::Instr::Numeric(hl::NumericOp::V128Not) => ll::Instr::Simd(ll::SimdInstr::V128Not),
+        hl::Instr::Numeric(hl::NumericOp::V128And) => ll::Instr::Simd(ll::SimdInstr::V128And),
+        hl::Instr::Numeric(hl::NumericOp::V128Andnot) => ll::Instr::Simd(ll::SimdInstr::V128Andnot),
+        hl::Instr::Numeric(hl::NumericOp::V128Or) => ll::Instr::Simd(ll::SimdInstr::V128Or),
+        hl::Instr::Numeric(hl::NumericOp::V128Xor) => ll::Instr::Simd(ll::SimdInstr::V128Xor),
+        hl::Instr::Numeric(hl::NumericOp::V128Bitselect) => ll::Instr::Simd(ll::SimdInstr::V128Bitselect),
+        hl::Instr::Numeric(hl::NumericOp::V128AnyTrue) => ll::Instr::Simd(ll::SimdInstr::V128AnyTrue),
+        hl::Instr::LoadLane(hl::LoadLaneOp::V128Load8Lane, memory_idx, memarg, lane) => ll::Instr::Simd(ll::SimdInstr::V128Load8Lane(ll::MemoryMemarg(state.map_memory_idx(memory_idx.0), memarg), lane)),
+        hl::Instr::LoadLane(hl::LoadLaneOp::V128Load16Lane, memory_idx, memarg, lane) => ll::Instr::Simd(ll::SimdInstr::V128Load16Lane(ll::MemoryMemarg(state.map_memory_idx(memory_idx.0), memarg), lane)),
+        hl::Instr::LoadLane(hl::LoadLaneOp::V128Load32Lane, memory_idx, memarg, lane) => ll::Instr::Simd(ll::SimdInstr::V128Load32Lane(ll::MemoryMemarg(state.map_memory_idx(memory_idx.0), memarg), lane)),
+        hl::Instr::LoadLane(hl::LoadLaneOp::V128Load64Lane, memory_idx, memarg, lane) => ll::Instr::Simd(ll::SimdInstr::V128Load64Lane(ll::MemoryMemarg(state.map_memory_idx(memory_idx.0), memarg), lane)),
+        hl::Instr::StoreLane(hl::StoreLaneOp::V128Store8Lane, memory_idx, memarg, lane) => ll::Instr::Simd(ll::SimdInstr::V128Store8Lane(ll::MemoryMemarg(state.map_memory_idx(memory_idx.0), memarg), lane)),
+        hl::Instr::StoreLane(hl::StoreLaneOp::V128Store16Lane, memory_idx, memarg, lane) => ll::Instr::Simd(ll::SimdInstr::V128Store16Lane(ll::MemoryMemarg(state.map_memory_idx(memory_idx.0), memarg), lane)),
+        hl::Instr::StoreLane(hl::StoreLaneOp::V128Store32Lane, memory_idx, memarg, lane) => ll::Instr::Simd(ll::SimdInstr::V128Store32Lane(ll::MemoryMemarg(state.map_memory_idx(memory_idx.0), memarg), lane)),
+        hl::Instr::StoreLane(hl::StoreLaneOp::V128Store64Lane, memory_idx, memarg, lane) => ll::Instr::Simd(ll::SimdInstr::V128Store64Lane(ll::MemoryMemarg(state.map_memory_idx(memory_idx.0), memarg), lane)),
+        hl::Instr::Load(hl::LoadOp::V128Load32Zero, memory_idx, memarg) => ll::Instr::Simd(ll::SimdInstr::V128Load32Zero(ll::MemoryMemarg(state.map_memory_idx(memory_idx.0), memarg))),
+        hl::Instr::Load(hl::LoadOp::V128Load64Zero, memory_idx, memarg) => ll::Instr::Simd(ll::SimdInstr::V128Load64Zero(ll::MemoryMemarg(state.map_memory_idx(memory_idx.0), memarg))),
+        hl::Instr::Numeric(hl::NumericOp::F32x4DemoteF64x2Zero) => ll::Instr::Simd(ll::SimdInstr::F32x4DemoteF64x2Zero),
+        hl::Instr::Numeric(hl::NumericOp::F64x2PromoteLowF32x4) => ll::Instr::Simd(ll::SimdInstr::F64x2PromoteLowF32x4),
+        hl::Instr::Numeric(hl::NumericOp::I8x16Abs) => ll::Instr::Simd(ll::SimdInstr::I8x16Abs),
+        hl::Instr::Numeric(hl::NumericOp::I8x16Neg) => ll::Instr::Simd(ll::SimdInstr::I8x16Neg),
+        hl::Instr::Numeric(hl::NumericOp::I8x16Popcnt) => ll::Instr::Simd(ll::SimdInstr::I8x16Popcnt),
+        hl::Instr::Numeric(hl::NumericOp::I8x16AllTrue) => ll::Instr::Simd(ll::SimdInstr::I8x16AllTrue),
+        hl::Instr::Numeric(hl::NumericOp::I8x16Bitmask) => ll::Instr::Simd(ll::SimdInstr::I8x16Bitmask),
+        hl::Instr::Numeric(hl::NumericOp::I8x16NarrowI16x8S) => ll::Instr::Simd(ll::SimdInstr::I8x16NarrowI16x8S),
+        hl::Instr::Numeric(hl::NumericOp::I8x16NarrowI16x8U) => ll::Instr::Simd(ll::SimdInstr::I8x16NarrowI16x8U),
+        hl::Instr::Numeric(hl::NumericOp::F32x4Ceil) => ll::Instr::Simd(ll::SimdInstr::F32x4Ceil),
+        hl::Instr::Numeric(hl::NumericOp::F32x4Floor) => ll::Instr::Simd(ll::SimdInstr::F32x4Floor),
+        hl::Instr::Numeric(hl::NumericOp::F32x4Trunc) => ll::Instr::Simd(ll::SimdInstr::F32x4Trunc),
+        hl::Instr::Numeric(hl::NumericOp::F32x4Nearest) => ll::Instr::Simd(ll::SimdInstr::F32x4Nearest),
+        hl::Instr::Numeric(hl::NumericOp::I8x16Shl) => ll::Instr::Simd(ll::SimdInstr::I8x16Shl),
+        hl::Instr::Numeric(hl::NumericOp::I8x16ShrS) => ll::Instr::Simd(ll::SimdInstr::I8x16ShrS),
+        hl::Instr::Numeric(hl::NumericOp::I8x16ShrU) => ll::Instr::Simd(ll::SimdInstr::I8x16ShrU),
+        hl::Instr::Numeric(hl::NumericOp::I8x16Add) => ll::Instr::Simd(ll::SimdInstr::I8x16Add),
+        hl::Instr::Numeric(hl::NumericOp::I8x16AddSatS) => ll::Instr::Simd(ll::SimdInstr::I8x16AddSatS),
+        hl::Instr::Numeric(hl::NumericOp::I8x16AddSatU) => ll::Instr::Simd(ll::SimdInstr::I8x16AddSatU),
+        hl::Instr::Numeric(hl::NumericOp::I8x16Sub) => ll::Instr::Simd(ll::SimdInstr::I8x16Sub),
+        hl::Instr::Numeric(hl::NumericOp::I8x16SubSatS) => ll::Instr::Simd(ll::SimdInstr::I8x16SubSatS),
+        hl::Instr::Numeric(hl::NumericOp::I8x16SubSatU) => ll::Instr::Simd(ll::SimdInstr::I8x16SubSatU),
+        hl::Instr::Numeric(hl::NumericOp::F64x2Ceil) => ll::Instr::Simd(ll::SimdInstr::F64x2Ceil),
+        hl::Instr::Numeric(hl::NumericOp::F64x2Floor) => ll::Instr::Simd(ll::SimdInstr::F64x2Floor),
+        hl::Instr::Numeric(hl::NumericOp::I8x16MinS) => ll::Instr::Simd(ll::SimdInstr::I8x16MinS),
+        hl::Instr::Numeric(hl::NumericOp::I8x16MinU) => ll::Instr::Simd(ll::SimdInstr::I8x16MinU),
+        hl::Instr::Numeric(hl::NumericOp::I8x16MaxS) => ll::Instr::Simd(ll::SimdInstr::I8x16MaxS),
+        hl::Instr::Numeric(hl::NumericOp::I8x16MaxU) => ll::Instr::Simd(ll::SimdInstr::I8x16MaxU),
+        hl::Instr::Numeric(hl::NumericOp::F64x2Trunc) => ll::Instr::Simd(ll::SimdInstr::F64x2Trunc),
+        hl::Instr::Numeric(hl::NumericOp::I8x16AvgrU) => ll::Instr::Simd(ll::SimdInstr::I8x16AvgrU),
+        hl::Instr::Numeric(hl::NumericOp::I16x8ExtaddPairwiseI8x16S) => ll::Instr::Simd(ll::SimdInstr::I16x8ExtaddPairwiseI8x16S),
+        hl::Instr::Numeric(hl::NumericOp::I16x8ExtaddPairwiseI8x16U) => ll::Instr::Simd(ll::SimdInstr::I16x8ExtaddPairwiseI8x16U),
+        hl::Instr::Numeric(hl::NumericOp::I32x4ExtaddPairwiseI16x8S) => ll::Instr::Simd(ll::SimdInstr::I32x4ExtaddPairwiseI16x8S),
+        hl::Instr::Numeric(hl::NumericOp::I32x4ExtaddPairwiseI16x8U) => ll::Instr::Simd(ll::SimdInstr::I32x4ExtaddPairwiseI16x8U),
+        hl::Instr::Numeric(hl::NumericOp::I16x8Abs) => ll::Instr::Simd(ll::SimdInstr::I16x8Abs),
+        hl::Instr::Numeric(hl::NumericOp::I16x8Neg) => ll::Instr::Simd(ll::SimdInstr::I16x8Neg),
+        hl::Instr::Numeric(hl::NumericOp::I16x8Q15mulrSatS) => ll::Instr::Simd(ll::SimdInstr::I16x8Q15mulrSatS),
+        hl::Instr::Numeric(hl::NumericOp::I16x8AllTrue) => ll::Instr::Simd(ll::SimdInstr::I16x8AllTrue),
+        hl::Instr::Numeric(hl::NumericOp::I16x8Bitmask) => ll::Instr::Simd(ll::SimdInstr::I16x8Bitmask),
+        hl::Instr::Numeric(hl::NumericOp::I16x8NarrowI32x4S) => ll::Instr::Simd(ll::SimdInstr::I16x8NarrowI32x4S),
+        hl::Instr::Numeric(hl::NumericOp::I16x8NarrowI32x4U) => ll::Instr::Simd(ll::SimdInstr::I16x8NarrowI32x4U),
+        hl::Instr::Numeric(hl::NumericOp::I16x8ExtendLowI8x16S) => ll::Instr::Simd(ll::SimdInstr::I16x8ExtendLowI8x16S),
+        hl::Instr::Numeric(hl::NumericOp::I16x8ExtendHighI8x16S) => ll::Instr::Simd(ll::SimdInstr::I16x8ExtendHighI8x16S),
+        hl::Instr::Numeric(hl::NumericOp::I16x8ExtendLowI8x16U) => ll::Instr::Simd(ll::SimdInstr::I16x8ExtendLowI8x16U),
+        hl::Instr::Numeric(hl::NumericOp::I16x8ExtendHighI8x16U) => ll::Instr::Simd(ll::SimdInstr::I16x8ExtendHighI8x16U),
+        hl::Instr::Numeric(hl::NumericOp::I16x8Shl) => ll::Instr::Simd(ll::SimdInstr::I16x8Shl),
+        hl::Instr::Numeric(hl::NumericOp::I16x8ShrS) => ll::Instr::Simd(ll::SimdInstr::I16x8ShrS),
+        hl::Instr::Numeric(hl::NumericOp::I16x8ShrU) => ll::Instr::Simd(ll::SimdInstr::I16x8ShrU),
+        hl::Instr::Numeric(hl::NumericOp::I16x8Add) => ll::Instr::Simd(ll::SimdInstr::I16x8Add),
+        hl::Instr::Numeric(hl::NumericOp::I16x8AddSatS) => ll::Instr::Simd(ll::SimdInstr::I16x8AddSatS),
+        hl::Instr::Numeric(hl::NumericOp::I16x8AddSatU) => ll::Instr::Simd(ll::SimdInstr::I16x8AddSatU),
+        hl::Instr::Numeric(hl::NumericOp::I16x8Sub) => ll::Instr::Simd(ll::SimdInstr::I16x8Sub),
+        hl::Instr::Numeric(hl::NumericOp::I16x8SubSatS) => ll::Instr::Simd(ll::SimdInstr::I16x8SubSatS),
+        hl::Instr::Numeric(hl::NumericOp::I16x8SubSatU) => ll::Instr::Simd(ll::SimdInstr::I16x8SubSatU),
+        hl::Instr::Numeric(hl::NumericOp::F64x2Nearest) => ll::Instr::Simd(ll::SimdInstr::F64x2Nearest),
+        hl::Instr::Numeric(hl::NumericOp::I16x8Mul) => ll::Instr::Simd(ll::SimdInstr::I16x8Mul),
+        hl::Instr::Numeric(hl::NumericOp::I16x8MinS) => ll::Instr::Simd(ll::SimdInstr::I16x8MinS),
+        hl::Instr::Numeric(hl::NumericOp::I16x8MinU) => ll::Instr::Simd(ll::SimdInstr::I16x8MinU),
+        hl::Instr::Numeric(hl::NumericOp::I16x8MaxS) => ll::Instr::Simd(ll::SimdInstr::I16x8MaxS),
+        hl::Instr::Numeric(hl::NumericOp::I16x8MaxU) => ll::Instr::Simd(ll::SimdInstr::I16x8MaxU),
+        hl::Instr::Numeric(hl::NumericOp::I16x8AvgrU) => ll::Instr::Simd(ll::SimdInstr::I16x8AvgrU),
+        hl::Instr::Numeric(hl::NumericOp::I16x8ExtmulLowI8x16S) => ll::Instr::Simd(ll::SimdInstr::I16x8ExtmulLowI8x16S),
+        hl::Instr::Numeric(hl::NumericOp::I16x8ExtmulHighI8x16S) => ll::Instr::Simd(ll::SimdInstr::I16x8ExtmulHighI8x16S),
+        hl::Instr::Numeric(hl::NumericOp::I16x8ExtmulLowI8x16U) => ll::Instr::Simd(ll::SimdInstr::I16x8ExtmulLowI8x16U),
+        hl::Instr::Numeric(hl::NumericOp::I16x8ExtmulHighI8x16U) => ll::Instr::Simd(ll::SimdInstr::I16x8ExtmulHighI8x16U),
+        hl::Instr::Numeric(hl::NumericOp::I32x4Abs) => ll::Instr::Simd(ll::SimdInstr::I32x4Abs),
+        hl::Instr::Numeric(hl::NumericOp::I32x4Neg) => ll::Instr::Simd(ll::SimdInstr::I32x4Neg),
+        hl::Instr::Numeric(hl::NumericOp::I32x4AllTrue) => ll::Instr::Simd(ll::SimdInstr::I32x4AllTrue),
+        hl::Instr::Numeric(hl::NumericOp::I32x4Bitmask) => ll::Instr::Simd(ll::SimdInstr::I32x4Bitmask),
+        hl::Instr::Numeric(hl::NumericOp::I32x4ExtendLowI16x8S) => ll::Instr::Simd(ll::SimdInstr::I32x4ExtendLowI16x8S),
+        hl::Instr::Numeric(hl::NumericOp::I32x4ExtendHighI16x8S) => ll::Instr::Simd(ll::SimdInstr::I32x4ExtendHighI16x8S),
+        hl::Instr::Numeric(hl::NumericOp::I32x4ExtendLowI16x8U) => ll::Instr::Simd(ll::SimdInstr::I32x4ExtendLowI16x8U),
+        hl::Instr::Numeric(hl::NumericOp::I32x4ExtendHighI16x8U) => ll::Instr::Simd(ll::SimdInstr::I32x4ExtendHighI16x8U),
+        hl::Instr::Numeric(hl::NumericOp::I32x4Shl) => ll::Instr::Simd(ll::SimdInstr::I32x4Shl),
+        hl::Instr::Numeric(hl::NumericOp::I32x4ShrS) => ll::Instr::Simd(ll::SimdInstr::I32x4ShrS),
+        hl::Instr::Numeric(hl::NumericOp::I32x4ShrU) => ll::Instr::Simd(ll::SimdInstr::I32x4ShrU),
+        hl::Instr::Numeric(hl::NumericOp::I32x4Add) => ll::Instr::Simd(ll::SimdInstr::I32x4Add),
+        hl::Instr::Numeric(hl::NumericOp::I32x4Sub) => ll::Instr::Simd(ll::SimdInstr::I32x4Sub),
+        hl::Instr::Numeric(hl::NumericOp::I32x4Mul) => ll::Instr::Simd(ll::SimdInstr::I32x4Mul),
+        hl::Instr::Numeric(hl::NumericOp::I32x4MinS) => ll::Instr::Simd(ll::SimdInstr::I32x4MinS),
+        hl::Instr::Numeric(hl::NumericOp::I32x4MinU) => ll::Instr::Simd(ll::SimdInstr::I32x4MinU),
+        hl::Instr::Numeric(hl::NumericOp::I32x4MaxS) => ll::Instr::Simd(ll::SimdInstr::I32x4MaxS),
+        hl::Instr::Numeric(hl::NumericOp::I32x4MaxU) => ll::Instr::Simd(ll::SimdInstr::I32x4MaxU),
+        hl::Instr::Numeric(hl::NumericOp::I32x4DotI16x8S) => ll::Instr::Simd(ll::SimdInstr::I32x4DotI16x8S),
+        hl::Instr::Numeric(hl::NumericOp::I32x4ExtmulLowI16x8S) => ll::Instr::Simd(ll::SimdInstr::I32x4ExtmulLowI16x8S),
+        hl::Instr::Numeric(hl::NumericOp::I32x4ExtmulHighI16x8S) => ll::Instr::Simd(ll::SimdInstr::I32x4ExtmulHighI16x8S),
+        hl::Instr::Numeric(hl::NumericOp::I32x4ExtmulLowI16x8U) => ll::Instr::Simd(ll::SimdInstr::I32x4ExtmulLowI16x8U),
+        hl::Instr::Numeric(hl::NumericOp::I32x4ExtmulHighI16x8U) => ll::Instr::Simd(ll::SimdInstr::I32x4ExtmulHighI16x8U),
+        hl::Instr::Numeric(hl::NumericOp::I64x2Abs) => ll::Instr::Simd(ll::SimdInstr::I64x2Abs),
+        hl::Instr::Numeric(hl::NumericOp::I64x2Neg) => ll::Instr::Simd(ll::SimdInstr::I64x2Neg),
+        hl::Instr::Numeric(hl::NumericOp::I64x2AllTrue) => ll::Instr::Simd(ll::SimdInstr::I64x2AllTrue),
+        hl::Instr::Numeric(hl::NumericOp::I64x2Bitmask) => ll::Instr::Simd(ll::SimdInstr::I64x2Bitmask),
+        hl::Instr::Numeric(hl::NumericOp::I64x2ExtendLowI32x4S) => ll::Instr::Simd(ll::SimdInstr::I64x2ExtendLowI32x4S),
+        hl::Instr::Numeric(hl::NumericOp::I64x2ExtendHighI32x4S) => ll::Instr::Simd(ll::SimdInstr::I64x2ExtendHighI32x4S),
+        hl::Instr::Numeric(hl::NumericOp::I64x2ExtendLowI32x4U) => ll::Instr::Simd(ll::SimdInstr::I64x2ExtendLowI32x4U),
+        hl::Instr::Numeric(hl::NumericOp::I64x2ExtendHighI32x4U) => ll::Instr::Simd(ll::SimdInstr::I64x2ExtendHighI32x4U),
+        hl::Instr::Numeric(hl::NumericOp::I64x2Shl) => ll::Instr::Simd(ll::SimdInstr::I64x2Shl),
+        hl::Instr::Numeric(hl::NumericOp::I64x2ShrS) => ll::Instr::Simd(ll::SimdInstr::I64x2ShrS),
+        hl::Instr::Numeric(hl::NumericOp::I64x2ShrU) => ll::Instr::Simd(ll::SimdInstr::I64x2ShrU),
+        hl::Instr::Numeric(hl::NumericOp::I64x2Add) => ll::Instr::Simd(ll::SimdInstr::I64x2Add),
+        hl::Instr::Numeric(hl::NumericOp::I64x2Sub) => ll::Instr::Simd(ll::SimdInstr::I64x2Sub),
+        hl::Instr::Numeric(hl::NumericOp::I64x2Mul) => ll::Instr::Simd(ll::SimdInstr::I64x2Mul),
+        hl::Instr::Numeric(hl::NumericOp::I64x2Eq) => ll::Instr::Simd(ll::SimdInstr::I64x2Eq),
+        hl::Instr::Numeric(hl::NumericOp::I64x2Ne) => ll::Instr::Simd(ll::SimdInstr::I64x2Ne),
+        hl::Instr::Numeric(hl::NumericOp::I64x2LtS) => ll::Instr::Simd(ll::SimdInstr::I64x2LtS),
+        hl::Instr::Numeric(hl::NumericOp::I64x2GtS) => ll::Instr::Simd(ll::SimdInstr::I64x2GtS),
+        hl::Instr::Numeric(hl::NumericOp::I64x2LeS) => ll::Instr::Simd(ll::SimdInstr::I64x2LeS),
+        hl::Instr::Numeric(hl::NumericOp::I64x2GeS) => ll::Instr::Simd(ll::SimdInstr::I64x2GeS),
+        hl::Instr::Numeric(hl::NumericOp::I64x2ExtmulLowI32x4S) => ll::Instr::Simd(ll::SimdInstr::I64x2ExtmulLowI32x4S),
+        hl::Instr::Numeric(hl::NumericOp::I64x2ExtmulHighI32x4S) => ll::Instr::Simd(ll::SimdInstr::I64x2ExtmulHighI32x4S),
+        hl::Instr::Numeric(hl::NumericOp::I64x2ExtmulLowI32x4U) => ll::Instr::Simd(ll::SimdInstr::I64x2ExtmulLowI32x4U),
+        hl::Instr::Numeric(hl::NumericOp::I64x2ExtmulHighI32x4U) => ll::Instr::Simd(ll::SimdInstr::I64x2ExtmulHighI32x4U),
+        hl::Instr::Numeric(hl::NumericOp::F32x4Abs) => ll::Instr::Simd(ll::SimdInstr::F32x4Abs),
+        hl::Instr::Numeric(hl::NumericOp::F32x4Neg) => ll::Instr::Simd(ll::SimdInstr::F32x4Neg),
+        hl::Instr::Numeric(hl::NumericOp::F32x4Sqrt) => ll::Instr::Simd(ll::SimdInstr::F32x4Sqrt),
+        hl::Instr::Numeric(hl::NumericOp::F32x4Add) => ll::Instr::Simd(ll::SimdInstr::F32x4Add),
+        hl::Instr::Numeric(hl::NumericOp::F32x4Sub) => ll::Instr::Simd(ll::SimdInstr::F32x4Sub),
+        hl::Instr::Numeric(hl::NumericOp::F32x4Mul) => ll::Instr::Simd(ll::SimdInstr::F32x4Mul),
+        hl::Instr::Numeric(hl::NumericOp::F32x4Div) => ll::Instr::Simd(ll::SimdInstr::F32x4Div),
+        hl::Instr::Numeric(hl::NumericOp::F32x4Min) => ll::Instr::Simd(ll::SimdInstr::F32x4Min),
+        hl::Instr::Numeric(hl::NumericOp::F32x4Max) => ll::Instr::Simd(ll::SimdInstr::F32x4Max),
+        hl::Instr::Numeric(hl::NumericOp::F32x4Pmin) => ll::Instr::Simd(ll::SimdInstr::F32x4Pmin),
+        hl::Instr::Numeric(hl::NumericOp::F32x4Pmax) => ll::Instr::Simd(ll::SimdInstr::F32x4Pmax),
+        hl::Instr::Numeric(hl::NumericOp::F64x2Abs) => ll::Instr::Simd(ll::SimdInstr::F64x2Abs),
+        hl::Instr::Numeric(hl::NumericOp::F64x2Neg) => ll::Instr::Simd(ll::SimdInstr::F64x2Neg),
+        hl::Instr::Numeric(hl::NumericOp::F64x2Sqrt) => ll::Instr::Simd(ll::SimdInstr::F64x2Sqrt),
+        hl::Instr::Numeric(hl::NumericOp::F64x2Add) => ll::Instr::Simd(ll::SimdInstr::F64x2Add),
+        hl::Instr::Numeric(hl::NumericOp::F64x2Sub) => ll::Instr::Simd(ll::SimdInstr::F64x2Sub),
+        hl::Instr::Numeric(hl::NumericOp::F64x2Mul) => ll::Instr::Simd(ll::SimdInstr::F64x2Mul),
+        hl::Instr::Numeric(hl::NumericOp::F64x2Div) => ll::Instr::Simd(ll::SimdInstr::F64x2Div),
+        hl::Instr::Numeric(hl::NumericOp::F64x2Min) => ll::Instr::Simd(ll::SimdInstr::F64x2Min),
+        hl::Instr::Numeric(hl::NumericOp::F64x2Max) => ll::Instr::Simd(ll::SimdInstr::F64x2Max),
+        hl::Instr::Numeric(hl::NumericOp::F64x2Pmin) => ll::Instr::Simd(ll::SimdInstr::F64x2Pmin),
+        hl::Instr::Numeric(hl::NumericOp::F64x2Pmax) => ll::Instr::Simd(ll::SimdInstr::F64x2Pmax),
+        hl::Instr::Numeric(hl::NumericOp::I32x4TruncSatF32x4S) => ll::Instr::Simd(ll::SimdInstr::I32x4TruncSatF32x4S),
+        hl::Instr::Numeric(hl::NumericOp::I32x4TruncSatF32x4U) => ll::Instr::Simd(ll::SimdInstr::I32x4TruncSatF32x4U),
+        hl::Instr::Numeric(hl::NumericOp::F32x4ConvertI32x4S) => ll::Instr::Simd(ll::SimdInstr::F32x4ConvertI32x4S),
+        hl::Instr::Numeric(hl::NumericOp::F32x4ConvertI32x4U) => ll::Instr::Simd(ll::SimdInstr::F32x4ConvertI32x4U),
+        hl::Instr::Numeric(hl::NumericOp::I32x4TruncSatF64x2SZero) => ll::Instr::Simd(ll::SimdInstr::I32x4TruncSatF64x2SZero),
+        hl::Instr::Numeric(hl::NumericOp::I32x4TruncSatF64x2UZero) => ll::Instr::Simd(ll::SimdInstr::I32x4TruncSatF64x2UZero),
+        hl::Instr::Numeric(hl::NumericOp::F64x2ConvertLowI32x4S) => ll::Instr::Simd(ll::SimdInstr::F64x2ConvertLowI32x4S),
+        hl::Instr::Numeric(hl::NumericOp::F64x2ConvertLowI32x4U) => ll::Instr::Simd(ll::SimdInstr::F64x2ConvertLowI32x4U),
+        hl::Instr::AtomicNotify(memory_idx, memarg) => ll::Instr::Atomic(ll::AtomicInstr::MemoryAtomicNotify(ll::MemoryMemarg(state.map_memory_idx(memory_idx.0), memarg))),
+        hl::Instr::AtomicWait(hl::AtomicWaitOp::MemoryAtomicWait32, memory_idx, memarg) => ll::Instr::Atomic(ll::AtomicInstr::MemoryAtomicWait32(ll::MemoryMemarg(state.map_memory_idx(memory_idx.0), memarg))),
+        hl::Instr::AtomicWait(hl::AtomicWaitOp::MemoryAtomicWait64, memory_idx, memarg) => ll::Instr::Atomic(ll::AtomicInstr::MemoryAtomicWait64(ll::MemoryMemarg(state.map_memory_idx(memory_idx.0), memarg))),
+        hl::Instr::AtomicFence => ll::Instr::Atomic(ll::AtomicInstr::AtomicFence(0)),
+        hl::Instr::AtomicLoad(hl::AtomicLoadOp::I32AtomicLoad, memory_idx, memarg) => ll::Instr::Atomic(ll::AtomicInstr::I32AtomicLoad(ll::MemoryMemarg(state.map_memory_idx(memory_idx.0), memarg))),
+        hl::Instr::AtomicLoad(hl::AtomicLoadOp::I64AtomicLoad, memory_idx, memarg) => ll::Instr::Atomic(ll::AtomicInstr::I64AtomicLoad(ll::MemoryMemarg(state.map_memory_idx(memory_idx.0), memarg))),
+        hl::Instr::AtomicLoad(hl::AtomicLoadOp::I32AtomicLoad8U, memory_idx, memarg) => ll::Instr::Atomic(ll::AtomicInstr::I32AtomicLoad8U(ll::MemoryMemarg(state.map_memory_idx(memory_idx.0), memarg))),
+        hl::Instr::AtomicLoad(hl::AtomicLoadOp::I32AtomicLoad16U, memory_idx, memarg) => ll::Instr::Atomic(ll::AtomicInstr::I32AtomicLoad16U(ll::MemoryMemarg(state.map_memory_idx(memory_idx.0), memarg))),
+        hl::Instr::AtomicLoad(hl::AtomicLoadOp::I64AtomicLoad8U, memory_idx, memarg) => ll::Instr::Atomic(ll::AtomicInstr::I64AtomicLoad8U(ll::MemoryMemarg(state.map_memory_idx(memory_idx.0), memarg))),
+        hl::Instr::AtomicLoad(hl::AtomicLoadOp::I64AtomicLoad16U, memory_idx, memarg) => ll::Instr::Atomic(ll::AtomicInstr::I64AtomicLoad16U(ll::MemoryMemarg(state.map_memory_idx(memory_idx.0), memarg))),
+        hl::Instr::AtomicLoad(hl::AtomicLoadOp::I64AtomicLoad32U, memory_idx, memarg) => ll::Instr::Atomic(ll::AtomicInstr::I64AtomicLoad32U(ll::MemoryMemarg(state.map_memory_idx(memory_idx.0), memarg))),
+        hl::Instr::AtomicStore(hl::AtomicStoreOp::I32AtomicStore, memory_idx, memarg) => ll::Instr::Atomic(ll::AtomicInstr::I32AtomicStore(ll::MemoryMemarg(state.map_memory_idx(memory_idx.0), memarg))),
+        hl::Instr::AtomicStore(hl::AtomicStoreOp::I64AtomicStore, memory_idx, memarg) => ll::Instr::Atomic(ll::AtomicInstr::I64AtomicStore(ll::MemoryMemarg(state.map_memory_idx(memory_idx.0), memarg))),
+        hl::Instr::AtomicStore(hl::AtomicStoreOp::I32AtomicStore8, memory_idx, memarg) => ll::Instr::Atomic(ll::AtomicInstr::I32AtomicStore8(ll::MemoryMemarg(state.map_memory_idx(memory_idx.0), memarg))),
+        hl::Instr::AtomicStore(hl::AtomicStoreOp::I32AtomicStore16, memory_idx, memarg) => ll::Instr::Atomic(ll::AtomicInstr::I32AtomicStore16(ll::MemoryMemarg(state.map_memory_idx(memory_idx.0), memarg))),
+        hl::Instr::AtomicStore(hl::AtomicStoreOp::I64AtomicStore8, memory_idx, memarg) => ll::Instr::Atomic(ll::AtomicInstr::I64AtomicStore8(ll::MemoryMemarg(state.map_memory_idx(memory_idx.0), memarg))),
+        hl::Instr::AtomicStore(hl::AtomicStoreOp::I64AtomicStore16, memory_idx, memarg) => ll::Instr::Atomic(ll::AtomicInstr::I64AtomicStore16(ll::MemoryMemarg(state.map_memory_idx(memory_idx.0), memarg))),
+        hl::Instr::AtomicStore(hl::AtomicStoreOp::I64AtomicStore32, memory_idx, memarg) => ll::Instr::Atomic(ll::AtomicInstr::I64AtomicStore32(ll::MemoryMemarg(state.map_memory_idx(memory_idx.0), memarg))),
+        hl::Instr::AtomicRmw(hl::AtomicRmwOp::I32AtomicRmwAdd, memory_idx, memarg) => ll::Instr::Atomic(ll::AtomicInstr::I32AtomicRmwAdd(ll::MemoryMemarg(state.map_memory_idx(memory_idx.0), memarg))),
+        hl::Instr::AtomicRmw(hl::AtomicRmwOp::I64AtomicRmwAdd, memory_idx, memarg) => ll::Instr::Atomic(ll::AtomicInstr::I64AtomicRmwAdd(ll::MemoryMemarg(state.map_memory_idx(memory_idx.0), memarg))),
+        hl::Instr::AtomicRmw(hl::AtomicRmwOp::I32AtomicRmw8AddU, memory_idx, memarg) => ll::Instr::Atomic(ll::AtomicInstr::I32AtomicRmw8AddU(ll::MemoryMemarg(state.map_memory_idx(memory_idx.0), memarg))),
+        hl::Instr::AtomicRmw(hl::AtomicRmwOp::I32AtomicRmw16AddU, memory_idx, memarg) => ll::Instr::Atomic(ll::AtomicInstr::I32AtomicRmw16AddU(ll::MemoryMemarg(state.map_memory_idx(memory_idx.0), memarg))),
+        hl::Instr::AtomicRmw(hl::AtomicRmwOp::I64AtomicRmw8AddU, memory_idx, memarg) => ll::Instr::Atomic(ll::AtomicInstr::I64AtomicRmw8AddU(ll::MemoryMemarg(state.map_memory_idx(memory_idx.0), memarg))),
+        hl::Instr::AtomicRmw(hl::AtomicRmwOp::I64AtomicRmw16AddU, memory_idx, memarg) => ll::Instr::Atomic(ll::AtomicInstr::I64AtomicRmw16AddU(ll::MemoryMemarg(state.map_memory_idx(memory_idx.0), memarg))),
+        hl::Instr::AtomicRmw(hl::AtomicRmwOp::I64AtomicRmw32AddU, memory_idx, memarg) => ll::Instr::Atomic(ll::AtomicInstr::I64AtomicRmw32AddU(ll::MemoryMemarg(state.map_memory_idx(memory_idx.0), memarg))),
+        hl::Instr::AtomicRmw(hl::AtomicRmwOp::I32AtomicRmwSub, memory_idx, memarg) => ll::Instr::Atomic(ll::AtomicInstr::I32AtomicRmwSub(ll::MemoryMemarg(state.map_memory_idx(memory_idx.0), memarg))),
+        hl::Instr::AtomicRmw(hl::AtomicRmwOp::I64AtomicRmwSub, memory_idx, memarg) => ll::Instr::Atomic(ll::AtomicInstr::I64AtomicRmwSub(ll::MemoryMemarg(state.map_memory_idx(memory_idx.0), memarg))),
+        hl::Instr::AtomicRmw(hl::AtomicRmwOp::I32AtomicRmw8SubU, memory_idx, memarg) => ll::Instr::Atomic(ll::AtomicInstr::I32AtomicRmw8SubU(ll::MemoryMemarg(state.map_memory_idx(memory_idx.0), memarg))),
+        hl::Instr::AtomicRmw(hl::AtomicRmwOp::I32AtomicRmw16SubU, memory_idx, memarg) => ll::Instr::Atomic(ll::AtomicInstr::I32AtomicRmw16SubU(ll::MemoryMemarg(state.map_memory_idx(memory_idx.0), memarg))),
+        hl::Instr::AtomicRmw(hl::AtomicRmwOp::I64AtomicRmw8SubU, memory_idx, memarg) => ll::Instr::Atomic(ll::AtomicInstr::I64AtomicRmw8SubU(ll::MemoryMemarg(state.map_memory_idx(memory_idx.0), memarg))),
+        hl::Instr::AtomicRmw(hl::AtomicRmwOp::I64AtomicRmw16SubU, memory_idx, memarg) => ll::Instr::Atomic(ll::AtomicInstr::I64AtomicRmw16SubU(ll::MemoryMemarg(state.map_memory_idx(memory_idx.0), memarg))),
+        hl::Instr::AtomicRmw(hl::AtomicRmwOp::I64AtomicRmw32SubU, memory_idx, memarg) => ll::Instr::Atomic(ll::AtomicInstr::I64AtomicRmw32SubU(ll::MemoryMemarg(state.map_memory_idx(memory_idx.0), memarg))),
+        hl::Instr::AtomicRmw(hl::AtomicRmwOp::I32AtomicRmwAnd, memory_idx, memarg) => ll::Instr::Atomic(ll::AtomicInstr::I32AtomicRmwAnd(ll::MemoryMemarg(state.map_memory_idx(memory_idx.0), memarg))),
+        hl::Instr::AtomicRmw(hl::AtomicRmwOp::I64AtomicRmwAnd, memory_idx, memarg) => ll::Instr::Atomic(ll::AtomicInstr::I64AtomicRmwAnd(ll::MemoryMemarg(state.map_memory_idx(memory_idx.0), memarg))),
+        hl::Instr::AtomicRmw(hl::AtomicRmwOp::I32AtomicRmw8AndU, memory_idx, memarg) => ll::Instr::Atomic(ll::AtomicInstr::I32AtomicRmw8AndU(ll::MemoryMemarg(state.map_memory_idx(memory_idx.0), memarg))),
+        hl::Instr::AtomicRmw(hl::AtomicRmwOp::I32AtomicRmw16AndU, memory_idx, memarg) => ll::Instr::Atomic(ll::AtomicInstr::I32AtomicRmw16AndU(ll::MemoryMemarg(state.map_memory_idx(memory_idx.0), memarg))),
+        hl::Instr::AtomicRmw(hl::AtomicRmwOp::I64AtomicRmw8AndU, memory_idx, memarg) => ll::Instr::Atomic(ll::AtomicInstr::I64AtomicRmw8AndU(ll::MemoryMemarg(state.map_memory_idx(memory_idx.0), memarg))),
+        hl::Instr::AtomicRmw(hl::AtomicRmwOp::I64AtomicRmw16AndU, memory_idx, memarg) => ll::Instr::Atomic(ll::AtomicInstr::I64AtomicRmw16AndU(ll::MemoryMemarg(state.map_memory_idx(memory_idx.0), memarg))),
+        hl::Instr::AtomicRmw(hl::AtomicRmwOp::I64AtomicRmw32AndU, memory_idx, memarg) => ll::Instr::Atomic(ll::AtomicInstr::I64AtomicRmw32AndU(ll::MemoryMemarg(state.map_memory_idx(memory_idx.0), memarg))),
+        hl::Instr::AtomicRmw(hl::AtomicRmwOp::I32AtomicRmwOr, memory_idx, memarg) => ll::Instr::Atomic(ll::AtomicInstr::I32AtomicRmwOr(ll::MemoryMemarg(state.map_memory_idx(memory_idx.0), memarg))),
+        hl::Instr::AtomicRmw(hl::AtomicRmwOp::I64AtomicRmwOr, memory_idx, memarg) => ll::Instr::Atomic(ll::AtomicInstr::I64AtomicRmwOr(ll::MemoryMemarg(state.map_memory_idx(memory_idx.0), memarg))),
+        hl::Instr::AtomicRmw(hl::AtomicRmwOp::I32AtomicRmw8OrU, memory_idx, memarg) => ll::Instr::Atomic(ll::AtomicInstr::I32AtomicRmw8OrU(ll::MemoryMemarg(state.map_memory_idx(memory_idx.0), memarg))),
+        hl::Instr::AtomicRmw(hl::AtomicRmwOp::I32AtomicRmw16OrU, memory_idx, memarg) => ll::Instr::Atomic(ll::AtomicInstr::I32AtomicRmw16OrU(ll::MemoryMemarg(state.map_memory_idx(memory_idx.0), memarg))),
+        hl::Instr::AtomicRmw(hl::AtomicRmwOp::I64AtomicRmw8OrU, memory_idx, memarg) => ll::Instr::Atomic(ll::AtomicInstr::I64AtomicRmw8OrU(ll::MemoryMemarg(state.map_memory_idx(memory_idx.0), memarg))),
+        hl::Instr::AtomicRmw(hl::AtomicRmwOp::I64AtomicRmw16OrU, memory_idx, memarg) => ll::Instr::Atomic(ll::AtomicInstr::I64AtomicRmw16OrU(ll::MemoryMemarg(state.map_memory_idx(memory_idx.0), memarg))),
+        hl::Instr::AtomicRmw(hl::AtomicRmwOp::I64AtomicRmw32OrU, memory_idx, memarg) => ll::Instr::Atomic(ll::AtomicInstr::I64AtomicRmw32OrU(ll::MemoryMemarg(state.map_memory_idx(memory_idx.0), memarg))),
+        hl::Instr::AtomicRmw(hl::AtomicRmwOp::I32AtomicRmwXor, memory_idx, memarg) => ll::Instr::Atomic(ll::AtomicInstr::I32AtomicRmwXor(ll::MemoryMemarg(state.map_memory_idx(memory_idx.0), memarg))),
+        hl::Instr::AtomicRmw(hl::AtomicRmwOp::I64AtomicRmwXor, memory_idx, memarg) => ll::Instr::Atomic(ll::AtomicInstr::I64AtomicRmwXor(ll::MemoryMemarg(state.map_memory_idx(memory_idx.0), memarg))),
+        hl::Instr::AtomicRmw(hl::AtomicRmwOp::I32AtomicRmw8XorU, memory_idx, memarg) => ll::Instr::Atomic(ll::AtomicInstr::I32AtomicRmw8XorU(ll::MemoryMemarg(state.map_memory_idx(memory_idx.0), memarg))),
+        hl::Instr::AtomicRmw(hl::AtomicRmwOp::I32AtomicRmw16XorU, memory_idx, memarg) => ll::Instr::Atomic(ll::AtomicInstr::I32AtomicRmw16XorU(ll::MemoryMemarg(state.map_memory_idx(memory_idx.0), memarg))),
+        hl::Instr::AtomicRmw(hl::AtomicRmwOp::I64AtomicRmw8XorU, memory_idx, memarg) => ll::Instr::Atomic(ll::AtomicInstr::I64AtomicRmw8XorU(ll::MemoryMemarg(state.map_memory_idx(memory_idx.0), memarg))),
+        hl::Instr::AtomicRmw(hl::AtomicRmwOp::I64AtomicRmw16XorU, memory_idx, memarg) => ll::Instr::Atomic(ll::AtomicInstr::I64AtomicRmw16XorU(ll::MemoryMemarg(state.map_memory_idx(memory_idx.0), memarg))),
+        hl::Instr::AtomicRmw(hl::AtomicRmwOp::I64AtomicRmw32XorU, memory_idx, memarg) => ll::Instr::Atomic(ll::AtomicInstr::I64AtomicRmw32XorU(ll::MemoryMemarg(state.map_memory_idx(memory_idx.0), memarg))),
+        hl::Instr::AtomicRmw(hl::AtomicRmwOp::I32AtomicRmwXchg, memory_idx, memarg) => ll::Instr::Atomic(ll::AtomicInstr::I32AtomicRmwXchg(ll::MemoryMemarg(state.map_memory_idx(memory_idx.0), memarg))),
+        hl::Instr::AtomicRmw(hl::AtomicRmwOp::I64AtomicRmwXchg, memory_idx, memarg) => ll::Instr::Atomic(ll::AtomicInstr::I64AtomicRmwXchg(ll::MemoryMemarg(state.map_memory_idx(memory_idx.0), memarg))),
+        hl::Instr::AtomicRmw(hl::AtomicRmwOp::I32AtomicRmw8XchgU, memory_idx, memarg) => ll::Instr::Atomic(ll::AtomicInstr::I32AtomicRmw8XchgU(ll::MemoryMemarg(state.map_memory_idx(memory_idx.0), memarg))),
+        hl::Instr::AtomicRmw(hl::AtomicRmwOp::I32AtomicRmw16XchgU, memory_idx, memarg) => ll::Instr::Atomic(ll::AtomicInstr::I32AtomicRmw16XchgU(ll::MemoryMemarg(state.map_memory_idx(memory_idx.0), memarg))),
+        hl::Instr::AtomicRmw(hl::AtomicRmwOp::I64AtomicRmw8XchgU, memory_idx, memarg) => ll::Instr::Atomic(ll::AtomicInstr::I64AtomicRmw8XchgU(ll::MemoryMemarg(state.map_memory_idx(memory_idx.0), memarg))),
+        hl::Instr::AtomicRmw(hl::AtomicRmwOp::I64AtomicRmw16XchgU, memory_idx, memarg) => ll::Instr::Atomic(ll::AtomicInstr::I64AtomicRmw16XchgU(ll::MemoryMemarg(state.map_memory_idx(memory_idx.0), memarg))),
+        hl::Instr::AtomicRmw(hl::AtomicRmwOp::I64AtomicRmw32XchgU, memory_idx, memarg) => ll::Instr::Atomic(ll::AtomicInstr::I64AtomicRmw32XchgU(ll::MemoryMemarg(state.map_memory_idx(memory_idx.0), memarg))),
+        hl::Instr::AtomicCmpxchg(hl::AtomicCmpxchgOp::I32AtomicRmwCmpxchg, memory_idx, memarg) => ll::Instr::Atomic(ll::AtomicInstr::I32AtomicRmwCmpxchg(ll::MemoryMemarg(state.map_memory_idx(memory_idx.0), memarg))),
+        hl::Instr::AtomicCmpxchg(hl::AtomicCmpxchgOp::I64AtomicRmwCmpxchg, memory_idx, memarg) => ll::Instr::Atomic(ll::AtomicInstr::I64AtomicRmwCmpxchg(ll::MemoryMemarg(state.map_memory_idx(memory_idx.0), memarg))),
+        hl::Instr::AtomicCmpxchg(hl::AtomicCmpxchgOp::I32AtomicRmw8CmpxchgU, memory_idx, memarg) => ll::Instr::Atomic(ll::AtomicInstr::I32AtomicRmw8CmpxchgU(ll::MemoryMemarg(state.map_memory_idx(memory_idx.0), memarg))),
+        hl::Instr::AtomicCmpxchg(hl::AtomicCmpxchgOp::I32AtomicRmw16CmpxchgU, memory_idx, memarg) => ll::Instr::Atomic(ll::AtomicInstr::I32AtomicRmw16CmpxchgU(ll::MemoryMemarg(state.map_memory_idx(memory_idx.0), memarg))),
+        hl::Instr::AtomicCmpxchg(hl::AtomicCmpxchgOp::I64AtomicRmw8CmpxchgU, memory_idx, memarg) => ll::Instr::Atomic(ll::AtomicInstr::I64AtomicRmw8CmpxchgU(ll::MemoryMemarg(state.map_memory_idx(memory_idx.0), memarg))),
+        hl::Instr::AtomicCmpxchg(hl::AtomicCmpxchgOp::I64AtomicRmw16CmpxchgU, memory_idx, memarg) => ll::Instr::Atomic(ll::AtomicInstr::I64AtomicRmw16CmpxchgU(ll::MemoryMemarg(state.map_memory_idx(memory_idx.0), memarg))),
+        hl::Instr::AtomicCmpxchg(hl::AtomicCmpxchgOp::I64AtomicRmw32CmpxchgU, memory_idx, memarg) => ll::Instr::Atomic(ll::AtomicInstr::I64AtomicRmw32CmpxchgU(ll::MemoryMemarg(state.map_memory_idx(memory_idx.0), memarg))),
     }
 }
\ No newline at end of file
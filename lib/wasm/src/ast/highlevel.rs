@@ -1,11 +1,14 @@
-use self::{GlobalOp::*, LoadOp::*, LocalOp::*, StoreOp::*};
-use std::collections::HashSet;
+use self::{AtomicCmpxchgOp::*, AtomicLoadOp::*, AtomicRmwOp::*, AtomicStoreOp::*, AtomicWaitOp::*, ExtractLaneOp::*, GlobalOp::*, LoadLaneOp::*, LoadOp::*, LocalOp::*, ReplaceLaneOp::*, StoreLaneOp::*, StoreOp::*};
+use std::collections::{BTreeMap, HashSet};
+use std::ops::Range;
 use super::{*, ValType::*};
+use WasmBinary;
 
 /* High-level AST:
     - types are inlined instead of referenced by type idx (i.e., no manual handling of Type "pool")
-    - Function + Code sections are merged into one list of functions,
-      same for tables: Table + Element sections and memories: Memory + Data sections.
+    - Function + Code sections are merged into one list of functions.
+    - element and data segments are kept in their own lists (not inside their table/memory), since
+      passive segments do not belong to any table/memory and instructions refer to them by index.
     - imports and exports are part of the respective item, not stored externally and referring to
       their item by index.
     - similar instructions are grouped together, for easier uniform handling, e.g., T.const
@@ -18,10 +21,41 @@ pub struct Module {
     pub globals: Vec<Global>,
     pub tables: Vec<Table>,
     pub memories: Vec<Memory>,
+    // from the exception handling proposal
+    pub tags: Vec<Tag>,
+
+    pub elements: Vec<Element>,
+    pub data: Vec<Data>,
 
     pub start: Option<Idx<Function>>,
 
+    // from the "name" custom section, which is decoded (not kept in custom_sections) and re-encoded
+    pub name: Option<String>,
+
     pub custom_sections: Vec<Vec<u8>>,
+
+    // only if requested when decoding (see Module::from_bytes_with_offsets), not updated when the
+    // module is modified, so it always refers to the original functions and instructions
+    pub offsets: Option<CodeOffsets>,
+}
+
+/// original byte offsets of function bodies and instructions, relative to the start of the module
+/// (as in browser stack traces of WebAssembly frames)
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CodeOffsets {
+    /// contents of the code section (after its id and size), DWARF addresses are relative to this
+    pub code_section: Range<usize>,
+    /// one per function, None for imported functions
+    pub functions: Vec<Option<FunctionOffsets>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FunctionOffsets {
+    /// includes the size field in front of the body (as do function ranges in DWARF)
+    pub body: Range<usize>,
+    /// one per instruction in the original body, i.e., indexed by Idx<Instr>
+    pub instrs: Vec<usize>,
 }
 
 #[derive(Debug, Clone)]
@@ -33,6 +67,10 @@ pub struct Function {
     pub code: Option<Code>,
     // functions (and other elements) can be exported multiple times under different names
     pub export: Vec<String>,
+
+    // debug names from the "name" custom section, local indices include the parameters
+    pub name: Option<String>,
+    pub local_names: BTreeMap<Idx<Local>, String>,
 }
 
 #[derive(Debug, Clone)]
@@ -42,13 +80,14 @@ pub struct Global {
     pub import: Option<(String, String)>,
     pub init: Option<Expr>,
     pub export: Vec<String>,
+    // from the "global names" subsection of the "name" custom section
+    pub name: Option<String>,
 }
 
 #[derive(Debug, Clone)]
 pub struct Table {
     pub type_: TableType,
     pub import: Option<(String, String)>,
-    pub elements: Vec<Element>,
     pub export: Vec<String>,
 }
 
@@ -56,7 +95,15 @@ pub struct Table {
 pub struct Memory {
     pub type_: MemoryType,
     pub import: Option<(String, String)>,
-    pub data: Vec<Data>,
+    pub export: Vec<String>,
+}
+
+/// exception handling: the params of the type are the values carried by a thrown exception, the
+/// results are always empty
+#[derive(Debug, Clone)]
+pub struct Tag {
+    pub type_: FunctionType,
+    pub import: Option<(String, String)>,
     pub export: Vec<String>,
 }
 
@@ -68,16 +115,58 @@ pub struct Code {
 
 #[derive(Debug, Clone)]
 pub struct Element {
-    pub offset: Expr,
-    pub functions: Vec<Idx<Function>>,
+    pub mode: ElementMode,
+    pub items: ElementItems,
+}
+
+#[derive(Debug, Clone)]
+pub enum ElementMode {
+    // copied into the table on instantiation
+    Active { table_idx: Idx<Table>, offset: Expr },
+    // only copied by table.init
+    Passive,
+    // only declares functions that are referenced, cannot be copied
+    Declarative,
+}
+
+#[derive(Debug, Clone)]
+pub enum ElementItems {
+    // implicitly of type funcref
+    Functions(Vec<Idx<Function>>),
+    // reference types: constant expressions (ref.null or ref.func) of the given type
+    Exprs(ElemType, Vec<Expr>),
+}
+
+impl ElementItems {
+    pub fn elem_type(&self) -> ElemType {
+        match *self {
+            ElementItems::Functions(_) => ElemType::FuncRef,
+            ElementItems::Exprs(elem_type, _) => elem_type,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match *self {
+            ElementItems::Functions(ref functions) => functions.len(),
+            ElementItems::Exprs(_, ref exprs) => exprs.len(),
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct Data {
-    pub offset: Expr,
+    pub mode: DataMode,
     pub bytes: Vec<u8>,
 }
 
+#[derive(Debug, Clone)]
+pub enum DataMode {
+    // copied into the memory on instantiation
+    Active { memory_idx: Idx<Memory>, offset: Expr },
+    // only copied by memory.init
+    Passive,
+}
+
 pub type Expr = Vec<Instr>;
 
 #[derive(Debug, Clone, PartialEq)]
@@ -85,12 +174,22 @@ pub enum Instr {
     Unreachable,
     Nop,
 
-    Block(BlockType),
-    Loop(BlockType),
-    If(BlockType),
+    // blocks are typed like functions (multi-value), i.e., [] -> [] for blocks without results
+    Block(FunctionType),
+    Loop(FunctionType),
+    If(FunctionType),
     Else,
     End,
 
+    // exception handling, catch/catch_all end the try (or a previous catch) block and start a
+    // new one, delegate ends a try block and forwards exceptions to the given label
+    Try(FunctionType),
+    Catch(Idx<Tag>),
+    CatchAll,
+    Delegate(Idx<Label>),
+    Throw(Idx<Tag>),
+    Rethrow(Idx<Label>),
+
     Br(Idx<Label>),
     BrIf(Idx<Label>),
     BrTable(Vec<Idx<Label>>, Idx<Label>),
@@ -98,19 +197,61 @@ pub enum Instr {
     Return,
     Call(Idx<Function>),
     CallIndirect(FunctionType, Idx<Table>),
+    // tail calls
+    ReturnCall(Idx<Function>),
+    ReturnCallIndirect(FunctionType, Idx<Table>),
 
     Drop,
     Select,
+    // reference types: explicitly typed select, required for reference operands
+    TypedSelect(ValType),
 
     Local(LocalOp, Idx<Local>),
     Global(GlobalOp, Idx<Global>),
 
-    Load(LoadOp, Memarg),
-    Store(StoreOp, Memarg),
+    // memory index is from the multi-memory proposal
+    Load(LoadOp, Idx<Memory>, Memarg),
+    Store(StoreOp, Idx<Memory>, Memarg),
 
     MemorySize(Idx<Memory>),
     MemoryGrow(Idx<Memory>),
 
+    // bulk memory operations, copy/fill take the destination first
+    MemoryInit(Idx<Data>, Idx<Memory>),
+    DataDrop(Idx<Data>),
+    MemoryCopy(Idx<Memory>, Idx<Memory>),
+    MemoryFill(Idx<Memory>),
+    TableInit(Idx<Element>, Idx<Table>),
+    ElemDrop(Idx<Element>),
+    TableCopy(Idx<Table>, Idx<Table>),
+
+    // reference types
+    RefNull(ElemType),
+    RefIsNull,
+    RefFunc(Idx<Function>),
+    TableGet(Idx<Table>),
+    TableSet(Idx<Table>),
+    TableSize(Idx<Table>),
+    TableGrow(Idx<Table>),
+    TableFill(Idx<Table>),
+
+    // SIMD proposal, lane indices as immediates (v128 loads, stores, and constants are part of
+    // Load, Store, and Const; lane-wise arithmetic is part of Numeric)
+    Shuffle([u8; 16]),
+    ExtractLane(ExtractLaneOp, u8),
+    ReplaceLane(ReplaceLaneOp, u8),
+    LoadLane(LoadLaneOp, Idx<Memory>, Memarg, u8),
+    StoreLane(StoreLaneOp, Idx<Memory>, Memarg, u8),
+
+    // threads proposal, all atomic accesses are sequentially consistent
+    AtomicLoad(AtomicLoadOp, Idx<Memory>, Memarg),
+    AtomicStore(AtomicStoreOp, Idx<Memory>, Memarg),
+    AtomicRmw(AtomicRmwOp, Idx<Memory>, Memarg),
+    AtomicCmpxchg(AtomicCmpxchgOp, Idx<Memory>, Memarg),
+    AtomicWait(AtomicWaitOp, Idx<Memory>, Memarg),
+    AtomicNotify(Idx<Memory>, Memarg),
+    AtomicFence,
+
     Const(Val),
     Numeric(NumericOp),
 }
@@ -139,6 +280,21 @@ pub enum LoadOp {
     I64Load16U,
     I64Load32S,
     I64Load32U,
+
+    // SIMD proposal
+    V128Load,
+    V128Load8x8S,
+    V128Load8x8U,
+    V128Load16x4S,
+    V128Load16x4U,
+    V128Load32x2S,
+    V128Load32x2U,
+    V128Load8Splat,
+    V128Load16Splat,
+    V128Load32Splat,
+    V128Load64Splat,
+    V128Load32Zero,
+    V128Load64Zero,
 }
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
@@ -154,6 +310,122 @@ pub enum StoreOp {
     I64Store8,
     I64Store16,
     I64Store32,
+
+    // SIMD proposal
+    V128Store,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum LoadLaneOp { V128Load8Lane, V128Load16Lane, V128Load32Lane, V128Load64Lane }
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum StoreLaneOp { V128Store8Lane, V128Store16Lane, V128Store32Lane, V128Store64Lane }
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum ExtractLaneOp {
+    I8x16ExtractLaneS,
+    I8x16ExtractLaneU,
+    I16x8ExtractLaneS,
+    I16x8ExtractLaneU,
+    I32x4ExtractLane,
+    I64x2ExtractLane,
+    F32x4ExtractLane,
+    F64x2ExtractLane,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum ReplaceLaneOp {
+    I8x16ReplaceLane,
+    I16x8ReplaceLane,
+    I32x4ReplaceLane,
+    I64x2ReplaceLane,
+    F32x4ReplaceLane,
+    F64x2ReplaceLane,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum AtomicLoadOp {
+    I32AtomicLoad,
+    I64AtomicLoad,
+    I32AtomicLoad8U,
+    I32AtomicLoad16U,
+    I64AtomicLoad8U,
+    I64AtomicLoad16U,
+    I64AtomicLoad32U,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum AtomicStoreOp {
+    I32AtomicStore,
+    I64AtomicStore,
+    I32AtomicStore8,
+    I32AtomicStore16,
+    I64AtomicStore8,
+    I64AtomicStore16,
+    I64AtomicStore32,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum AtomicRmwOp {
+    I32AtomicRmwAdd,
+    I64AtomicRmwAdd,
+    I32AtomicRmw8AddU,
+    I32AtomicRmw16AddU,
+    I64AtomicRmw8AddU,
+    I64AtomicRmw16AddU,
+    I64AtomicRmw32AddU,
+    I32AtomicRmwSub,
+    I64AtomicRmwSub,
+    I32AtomicRmw8SubU,
+    I32AtomicRmw16SubU,
+    I64AtomicRmw8SubU,
+    I64AtomicRmw16SubU,
+    I64AtomicRmw32SubU,
+    I32AtomicRmwAnd,
+    I64AtomicRmwAnd,
+    I32AtomicRmw8AndU,
+    I32AtomicRmw16AndU,
+    I64AtomicRmw8AndU,
+    I64AtomicRmw16AndU,
+    I64AtomicRmw32AndU,
+    I32AtomicRmwOr,
+    I64AtomicRmwOr,
+    I32AtomicRmw8OrU,
+    I32AtomicRmw16OrU,
+    I64AtomicRmw8OrU,
+    I64AtomicRmw16OrU,
+    I64AtomicRmw32OrU,
+    I32AtomicRmwXor,
+    I64AtomicRmwXor,
+    I32AtomicRmw8XorU,
+    I32AtomicRmw16XorU,
+    I64AtomicRmw8XorU,
+    I64AtomicRmw16XorU,
+    I64AtomicRmw32XorU,
+    I32AtomicRmwXchg,
+    I64AtomicRmwXchg,
+    I32AtomicRmw8XchgU,
+    I32AtomicRmw16XchgU,
+    I64AtomicRmw8XchgU,
+    I64AtomicRmw16XchgU,
+    I64AtomicRmw32XchgU,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum AtomicCmpxchgOp {
+    I32AtomicRmwCmpxchg,
+    I64AtomicRmwCmpxchg,
+    I32AtomicRmw8CmpxchgU,
+    I32AtomicRmw16CmpxchgU,
+    I64AtomicRmw8CmpxchgU,
+    I64AtomicRmw16CmpxchgU,
+    I64AtomicRmw32CmpxchgU,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum AtomicWaitOp {
+    MemoryAtomicWait32,
+    MemoryAtomicWait64,
 }
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
@@ -216,6 +488,21 @@ pub enum NumericOp {
     F32ReinterpretI32,
     F64ReinterpretI64,
 
+    I32Extend8S,
+    I32Extend16S,
+    I64Extend8S,
+    I64Extend16S,
+    I64Extend32S,
+
+    I32TruncSatSF32,
+    I32TruncSatUF32,
+    I32TruncSatSF64,
+    I32TruncSatUF64,
+    I64TruncSatSF32,
+    I64TruncSatUF32,
+    I64TruncSatSF64,
+    I64TruncSatUF64,
+
     /* Binary */
     I32Eq,
     I32Ne,
@@ -300,6 +587,206 @@ pub enum NumericOp {
     F64Min,
     F64Max,
     F64Copysign,
+
+    /* SIMD: unary, binary, and v128.bitselect (the only ternary numeric instruction) */
+    I8x16Swizzle,
+    I8x16Splat,
+    I16x8Splat,
+    I32x4Splat,
+    I64x2Splat,
+    F32x4Splat,
+    F64x2Splat,
+    I8x16Eq,
+    I8x16Ne,
+    I8x16LtS,
+    I8x16LtU,
+    I8x16GtS,
+    I8x16GtU,
+    I8x16LeS,
+    I8x16LeU,
+    I8x16GeS,
+    I8x16GeU,
+    I16x8Eq,
+    I16x8Ne,
+    I16x8LtS,
+    I16x8LtU,
+    I16x8GtS,
+    I16x8GtU,
+    I16x8LeS,
+    I16x8LeU,
+    I16x8GeS,
+    I16x8GeU,
+    I32x4Eq,
+    I32x4Ne,
+    I32x4LtS,
+    I32x4LtU,
+    I32x4GtS,
+    I32x4GtU,
+    I32x4LeS,
+    I32x4LeU,
+    I32x4GeS,
+    I32x4GeU,
+    F32x4Eq,
+    F32x4Ne,
+    F32x4Lt,
+    F32x4Gt,
+    F32x4Le,
+    F32x4Ge,
+    F64x2Eq,
+    F64x2Ne,
+    F64x2Lt,
+    F64x2Gt,
+    F64x2Le,
+    F64x2Ge,
+    V128Not,
+    V128And,
+    V128Andnot,
+    V128Or,
+    V128Xor,
+    V128Bitselect,
+    V128AnyTrue,
+    F32x4DemoteF64x2Zero,
+    F64x2PromoteLowF32x4,
+    I8x16Abs,
+    I8x16Neg,
+    I8x16Popcnt,
+    I8x16AllTrue,
+    I8x16Bitmask,
+    I8x16NarrowI16x8S,
+    I8x16NarrowI16x8U,
+    F32x4Ceil,
+    F32x4Floor,
+    F32x4Trunc,
+    F32x4Nearest,
+    I8x16Shl,
+    I8x16ShrS,
+    I8x16ShrU,
+    I8x16Add,
+    I8x16AddSatS,
+    I8x16AddSatU,
+    I8x16Sub,
+    I8x16SubSatS,
+    I8x16SubSatU,
+    F64x2Ceil,
+    F64x2Floor,
+    I8x16MinS,
+    I8x16MinU,
+    I8x16MaxS,
+    I8x16MaxU,
+    F64x2Trunc,
+    I8x16AvgrU,
+    I16x8ExtaddPairwiseI8x16S,
+    I16x8ExtaddPairwiseI8x16U,
+    I32x4ExtaddPairwiseI16x8S,
+    I32x4ExtaddPairwiseI16x8U,
+    I16x8Abs,
+    I16x8Neg,
+    I16x8Q15mulrSatS,
+    I16x8AllTrue,
+    I16x8Bitmask,
+    I16x8NarrowI32x4S,
+    I16x8NarrowI32x4U,
+    I16x8ExtendLowI8x16S,
+    I16x8ExtendHighI8x16S,
+    I16x8ExtendLowI8x16U,
+    I16x8ExtendHighI8x16U,
+    I16x8Shl,
+    I16x8ShrS,
+    I16x8ShrU,
+    I16x8Add,
+    I16x8AddSatS,
+    I16x8AddSatU,
+    I16x8Sub,
+    I16x8SubSatS,
+    I16x8SubSatU,
+    F64x2Nearest,
+    I16x8Mul,
+    I16x8MinS,
+    I16x8MinU,
+    I16x8MaxS,
+    I16x8MaxU,
+    I16x8AvgrU,
+    I16x8ExtmulLowI8x16S,
+    I16x8ExtmulHighI8x16S,
+    I16x8ExtmulLowI8x16U,
+    I16x8ExtmulHighI8x16U,
+    I32x4Abs,
+    I32x4Neg,
+    I32x4AllTrue,
+    I32x4Bitmask,
+    I32x4ExtendLowI16x8S,
+    I32x4ExtendHighI16x8S,
+    I32x4ExtendLowI16x8U,
+    I32x4ExtendHighI16x8U,
+    I32x4Shl,
+    I32x4ShrS,
+    I32x4ShrU,
+    I32x4Add,
+    I32x4Sub,
+    I32x4Mul,
+    I32x4MinS,
+    I32x4MinU,
+    I32x4MaxS,
+    I32x4MaxU,
+    I32x4DotI16x8S,
+    I32x4ExtmulLowI16x8S,
+    I32x4ExtmulHighI16x8S,
+    I32x4ExtmulLowI16x8U,
+    I32x4ExtmulHighI16x8U,
+    I64x2Abs,
+    I64x2Neg,
+    I64x2AllTrue,
+    I64x2Bitmask,
+    I64x2ExtendLowI32x4S,
+    I64x2ExtendHighI32x4S,
+    I64x2ExtendLowI32x4U,
+    I64x2ExtendHighI32x4U,
+    I64x2Shl,
+    I64x2ShrS,
+    I64x2ShrU,
+    I64x2Add,
+    I64x2Sub,
+    I64x2Mul,
+    I64x2Eq,
+    I64x2Ne,
+    I64x2LtS,
+    I64x2GtS,
+    I64x2LeS,
+    I64x2GeS,
+    I64x2ExtmulLowI32x4S,
+    I64x2ExtmulHighI32x4S,
+    I64x2ExtmulLowI32x4U,
+    I64x2ExtmulHighI32x4U,
+    F32x4Abs,
+    F32x4Neg,
+    F32x4Sqrt,
+    F32x4Add,
+    F32x4Sub,
+    F32x4Mul,
+    F32x4Div,
+    F32x4Min,
+    F32x4Max,
+    F32x4Pmin,
+    F32x4Pmax,
+    F64x2Abs,
+    F64x2Neg,
+    F64x2Sqrt,
+    F64x2Add,
+    F64x2Sub,
+    F64x2Mul,
+    F64x2Div,
+    F64x2Min,
+    F64x2Max,
+    F64x2Pmin,
+    F64x2Pmax,
+    I32x4TruncSatF32x4S,
+    I32x4TruncSatF32x4U,
+    F32x4ConvertI32x4S,
+    F32x4ConvertI32x4U,
+    I32x4TruncSatF64x2SZero,
+    I32x4TruncSatF64x2UZero,
+    F64x2ConvertLowI32x4S,
+    F64x2ConvertLowI32x4U,
 }
 
 
@@ -356,6 +843,12 @@ impl NumericOp {
             I64ReinterpretF64 => InstrType::new(&[F64], &[I64]),
             F32ReinterpretI32 => InstrType::new(&[I32], &[F32]),
             F64ReinterpretI64 => InstrType::new(&[I64], &[F64]),
+            I32Extend8S | I32Extend16S => InstrType::new(&[I32], &[I32]),
+            I64Extend8S | I64Extend16S | I64Extend32S => InstrType::new(&[I64], &[I64]),
+            I32TruncSatSF32 | I32TruncSatUF32 => InstrType::new(&[F32], &[I32]),
+            I32TruncSatSF64 | I32TruncSatUF64 => InstrType::new(&[F64], &[I32]),
+            I64TruncSatSF32 | I64TruncSatUF32 => InstrType::new(&[F32], &[I64]),
+            I64TruncSatSF64 | I64TruncSatUF64 => InstrType::new(&[F64], &[I64]),
 
             /* Binary */
 
@@ -369,6 +862,39 @@ impl NumericOp {
             I64Add | I64Sub | I64Mul | I64DivS | I64DivU | I64RemS | I64RemU | I64And | I64Or | I64Xor | I64Shl | I64ShrS | I64ShrU | I64Rotl | I64Rotr => InstrType::new(&[I64, I64], &[I64]),
             F32Add | F32Sub | F32Mul | F32Div | F32Min | F32Max | F32Copysign => InstrType::new(&[F32, F32], &[F32]),
             F64Add | F64Sub | F64Mul | F64Div | F64Min | F64Max | F64Copysign => InstrType::new(&[F64, F64], &[F64]),
+
+            /* SIMD */
+
+            I8x16Splat => InstrType::new(&[I32], &[V128]),
+            I16x8Splat => InstrType::new(&[I32], &[V128]),
+            I32x4Splat => InstrType::new(&[I32], &[V128]),
+            I64x2Splat => InstrType::new(&[I64], &[V128]),
+            F32x4Splat => InstrType::new(&[F32], &[V128]),
+            F64x2Splat => InstrType::new(&[F64], &[V128]),
+            V128Not => InstrType::new(&[V128], &[V128]),
+            I8x16Abs | I8x16Neg | I8x16Popcnt => InstrType::new(&[V128], &[V128]),
+            I16x8ExtaddPairwiseI8x16S | I16x8ExtaddPairwiseI8x16U | I16x8Abs | I16x8Neg | I16x8ExtendLowI8x16S | I16x8ExtendHighI8x16S | I16x8ExtendLowI8x16U | I16x8ExtendHighI8x16U => InstrType::new(&[V128], &[V128]),
+            I32x4ExtaddPairwiseI16x8S | I32x4ExtaddPairwiseI16x8U | I32x4Abs | I32x4Neg | I32x4ExtendLowI16x8S | I32x4ExtendHighI16x8S | I32x4ExtendLowI16x8U | I32x4ExtendHighI16x8U | I32x4TruncSatF32x4S | I32x4TruncSatF32x4U | I32x4TruncSatF64x2SZero | I32x4TruncSatF64x2UZero => InstrType::new(&[V128], &[V128]),
+            I64x2Abs | I64x2Neg | I64x2ExtendLowI32x4S | I64x2ExtendHighI32x4S | I64x2ExtendLowI32x4U | I64x2ExtendHighI32x4U => InstrType::new(&[V128], &[V128]),
+            F32x4DemoteF64x2Zero | F32x4Ceil | F32x4Floor | F32x4Trunc | F32x4Nearest | F32x4Abs | F32x4Neg | F32x4Sqrt | F32x4ConvertI32x4S | F32x4ConvertI32x4U => InstrType::new(&[V128], &[V128]),
+            F64x2PromoteLowF32x4 | F64x2Ceil | F64x2Floor | F64x2Trunc | F64x2Nearest | F64x2Abs | F64x2Neg | F64x2Sqrt | F64x2ConvertLowI32x4S | F64x2ConvertLowI32x4U => InstrType::new(&[V128], &[V128]),
+            V128And | V128Andnot | V128Or | V128Xor => InstrType::new(&[V128, V128], &[V128]),
+            I8x16Swizzle | I8x16Eq | I8x16Ne | I8x16LtS | I8x16LtU | I8x16GtS | I8x16GtU | I8x16LeS | I8x16LeU | I8x16GeS | I8x16GeU | I8x16NarrowI16x8S | I8x16NarrowI16x8U | I8x16Add | I8x16AddSatS | I8x16AddSatU | I8x16Sub | I8x16SubSatS | I8x16SubSatU | I8x16MinS | I8x16MinU | I8x16MaxS | I8x16MaxU | I8x16AvgrU => InstrType::new(&[V128, V128], &[V128]),
+            I16x8Eq | I16x8Ne | I16x8LtS | I16x8LtU | I16x8GtS | I16x8GtU | I16x8LeS | I16x8LeU | I16x8GeS | I16x8GeU | I16x8Q15mulrSatS | I16x8NarrowI32x4S | I16x8NarrowI32x4U | I16x8Add | I16x8AddSatS | I16x8AddSatU | I16x8Sub | I16x8SubSatS | I16x8SubSatU | I16x8Mul | I16x8MinS | I16x8MinU | I16x8MaxS | I16x8MaxU | I16x8AvgrU | I16x8ExtmulLowI8x16S | I16x8ExtmulHighI8x16S | I16x8ExtmulLowI8x16U | I16x8ExtmulHighI8x16U => InstrType::new(&[V128, V128], &[V128]),
+            I32x4Eq | I32x4Ne | I32x4LtS | I32x4LtU | I32x4GtS | I32x4GtU | I32x4LeS | I32x4LeU | I32x4GeS | I32x4GeU | I32x4Add | I32x4Sub | I32x4Mul | I32x4MinS | I32x4MinU | I32x4MaxS | I32x4MaxU | I32x4DotI16x8S | I32x4ExtmulLowI16x8S | I32x4ExtmulHighI16x8S | I32x4ExtmulLowI16x8U | I32x4ExtmulHighI16x8U => InstrType::new(&[V128, V128], &[V128]),
+            I64x2Add | I64x2Sub | I64x2Mul | I64x2Eq | I64x2Ne | I64x2LtS | I64x2GtS | I64x2LeS | I64x2GeS | I64x2ExtmulLowI32x4S | I64x2ExtmulHighI32x4S | I64x2ExtmulLowI32x4U | I64x2ExtmulHighI32x4U => InstrType::new(&[V128, V128], &[V128]),
+            F32x4Eq | F32x4Ne | F32x4Lt | F32x4Gt | F32x4Le | F32x4Ge | F32x4Add | F32x4Sub | F32x4Mul | F32x4Div | F32x4Min | F32x4Max | F32x4Pmin | F32x4Pmax => InstrType::new(&[V128, V128], &[V128]),
+            F64x2Eq | F64x2Ne | F64x2Lt | F64x2Gt | F64x2Le | F64x2Ge | F64x2Add | F64x2Sub | F64x2Mul | F64x2Div | F64x2Min | F64x2Max | F64x2Pmin | F64x2Pmax => InstrType::new(&[V128, V128], &[V128]),
+            I8x16Shl | I8x16ShrS | I8x16ShrU => InstrType::new(&[V128, I32], &[V128]),
+            I16x8Shl | I16x8ShrS | I16x8ShrU => InstrType::new(&[V128, I32], &[V128]),
+            I32x4Shl | I32x4ShrS | I32x4ShrU => InstrType::new(&[V128, I32], &[V128]),
+            I64x2Shl | I64x2ShrS | I64x2ShrU => InstrType::new(&[V128, I32], &[V128]),
+            V128Bitselect => InstrType::new(&[V128, V128, V128], &[V128]),
+            V128AnyTrue => InstrType::new(&[V128], &[I32]),
+            I8x16AllTrue | I8x16Bitmask => InstrType::new(&[V128], &[I32]),
+            I16x8AllTrue | I16x8Bitmask => InstrType::new(&[V128], &[I32]),
+            I32x4AllTrue | I32x4Bitmask => InstrType::new(&[V128], &[I32]),
+            I64x2AllTrue | I64x2Bitmask => InstrType::new(&[V128], &[I32]),
         }
     }
 }
@@ -391,6 +917,9 @@ impl LoadOp {
             I64Load16U => InstrType::new(&[I32], &[I64]),
             I64Load32S => InstrType::new(&[I32], &[I64]),
             I64Load32U => InstrType::new(&[I32], &[I64]),
+
+            V128Load | V128Load8x8S | V128Load8x8U | V128Load16x4S | V128Load16x4U | V128Load32x2S | V128Load32x2U => InstrType::new(&[I32], &[V128]),
+            V128Load8Splat | V128Load16Splat | V128Load32Splat | V128Load64Splat | V128Load32Zero | V128Load64Zero => InstrType::new(&[I32], &[V128]),
         }
     }
 }
@@ -408,6 +937,215 @@ impl StoreOp {
             I64Store8 => InstrType::new(&[I32, I64], &[]),
             I64Store16 => InstrType::new(&[I32, I64], &[]),
             I64Store32 => InstrType::new(&[I32, I64], &[]),
+
+            V128Store => InstrType::new(&[I32, V128], &[]),
+        }
+    }
+}
+
+impl LoadLaneOp {
+    pub fn to_type(&self) -> InstrType {
+        InstrType::new(&[I32, V128], &[V128])
+    }
+}
+
+impl StoreLaneOp {
+    pub fn to_type(&self) -> InstrType {
+        InstrType::new(&[I32, V128], &[])
+    }
+}
+
+impl ExtractLaneOp {
+    pub fn to_type(&self) -> InstrType {
+        match *self {
+            I8x16ExtractLaneS | I8x16ExtractLaneU | I16x8ExtractLaneS | I16x8ExtractLaneU | I32x4ExtractLane => InstrType::new(&[V128], &[I32]),
+            I64x2ExtractLane => InstrType::new(&[V128], &[I64]),
+            F32x4ExtractLane => InstrType::new(&[V128], &[F32]),
+            F64x2ExtractLane => InstrType::new(&[V128], &[F64]),
+        }
+    }
+
+    pub fn lane_count(&self) -> u8 {
+        match *self {
+            I8x16ExtractLaneS | I8x16ExtractLaneU => 16,
+            I16x8ExtractLaneS | I16x8ExtractLaneU => 8,
+            I32x4ExtractLane | F32x4ExtractLane => 4,
+            I64x2ExtractLane | F64x2ExtractLane => 2,
+        }
+    }
+}
+
+impl ReplaceLaneOp {
+    pub fn to_type(&self) -> InstrType {
+        match *self {
+            I8x16ReplaceLane | I16x8ReplaceLane | I32x4ReplaceLane => InstrType::new(&[V128, I32], &[V128]),
+            I64x2ReplaceLane => InstrType::new(&[V128, I64], &[V128]),
+            F32x4ReplaceLane => InstrType::new(&[V128, F32], &[V128]),
+            F64x2ReplaceLane => InstrType::new(&[V128, F64], &[V128]),
+        }
+    }
+
+    pub fn lane_count(&self) -> u8 {
+        match *self {
+            I8x16ReplaceLane => 16,
+            I16x8ReplaceLane => 8,
+            I32x4ReplaceLane | F32x4ReplaceLane => 4,
+            I64x2ReplaceLane | F64x2ReplaceLane => 2,
+        }
+    }
+}
+
+impl LoadOp {
+    /// log2 of the number of bytes accessed, which is the default alignment in the text format
+    pub fn natural_alignment(&self) -> u32 {
+        match *self {
+            I32Load8S | I32Load8U | I64Load8S | I64Load8U => 0,
+            I32Load16S | I32Load16U | I64Load16S | I64Load16U => 1,
+            I32Load | F32Load | I64Load32S | I64Load32U => 2,
+            I64Load | F64Load => 3,
+            V128Load8Splat => 0,
+            V128Load16Splat => 1,
+            V128Load32Splat | V128Load32Zero => 2,
+            V128Load8x8S | V128Load8x8U | V128Load16x4S | V128Load16x4U | V128Load32x2S | V128Load32x2U | V128Load64Splat | V128Load64Zero => 3,
+            V128Load => 4,
+        }
+    }
+}
+
+impl StoreOp {
+    /// log2 of the number of bytes accessed, which is the default alignment in the text format
+    pub fn natural_alignment(&self) -> u32 {
+        match *self {
+            I32Store8 | I64Store8 => 0,
+            I32Store16 | I64Store16 => 1,
+            I32Store | F32Store | I64Store32 => 2,
+            I64Store | F64Store => 3,
+            V128Store => 4,
+        }
+    }
+}
+
+impl LoadLaneOp {
+    /// log2 of the number of bytes accessed, which is the default alignment in the text format
+    pub fn natural_alignment(&self) -> u32 {
+        match *self {
+            V128Load8Lane => 0,
+            V128Load16Lane => 1,
+            V128Load32Lane => 2,
+            V128Load64Lane => 3,
+        }
+    }
+
+    pub fn lane_count(&self) -> u8 {
+        16 >> self.natural_alignment()
+    }
+}
+
+impl StoreLaneOp {
+    /// log2 of the number of bytes accessed, which is the default alignment in the text format
+    pub fn natural_alignment(&self) -> u32 {
+        match *self {
+            V128Store8Lane => 0,
+            V128Store16Lane => 1,
+            V128Store32Lane => 2,
+            V128Store64Lane => 3,
+        }
+    }
+
+    pub fn lane_count(&self) -> u8 {
+        16 >> self.natural_alignment()
+    }
+}
+
+impl AtomicLoadOp {
+    pub fn to_type(&self) -> InstrType {
+        match *self {
+            I32AtomicLoad | I32AtomicLoad8U | I32AtomicLoad16U => InstrType::new(&[I32], &[I32]),
+            I64AtomicLoad | I64AtomicLoad8U | I64AtomicLoad16U | I64AtomicLoad32U => InstrType::new(&[I32], &[I64]),
+        }
+    }
+
+    /// log2 of the number of bytes accessed, which atomic accesses must be aligned to exactly
+    pub fn natural_alignment(&self) -> u32 {
+        match *self {
+            I32AtomicLoad8U | I64AtomicLoad8U => 0,
+            I32AtomicLoad16U | I64AtomicLoad16U => 1,
+            I32AtomicLoad | I64AtomicLoad32U => 2,
+            I64AtomicLoad => 3,
+        }
+    }
+}
+
+impl AtomicStoreOp {
+    pub fn to_type(&self) -> InstrType {
+        match *self {
+            I32AtomicStore | I32AtomicStore8 | I32AtomicStore16 => InstrType::new(&[I32, I32], &[]),
+            I64AtomicStore | I64AtomicStore8 | I64AtomicStore16 | I64AtomicStore32 => InstrType::new(&[I32, I64], &[]),
+        }
+    }
+
+    /// log2 of the number of bytes accessed, which atomic accesses must be aligned to exactly
+    pub fn natural_alignment(&self) -> u32 {
+        match *self {
+            I32AtomicStore8 | I64AtomicStore8 => 0,
+            I32AtomicStore16 | I64AtomicStore16 => 1,
+            I32AtomicStore | I64AtomicStore32 => 2,
+            I64AtomicStore => 3,
+        }
+    }
+}
+
+impl AtomicRmwOp {
+    pub fn to_type(&self) -> InstrType {
+        match *self {
+            I32AtomicRmwAdd | I32AtomicRmw8AddU | I32AtomicRmw16AddU | I32AtomicRmwSub | I32AtomicRmw8SubU | I32AtomicRmw16SubU | I32AtomicRmwAnd | I32AtomicRmw8AndU | I32AtomicRmw16AndU | I32AtomicRmwOr | I32AtomicRmw8OrU | I32AtomicRmw16OrU | I32AtomicRmwXor | I32AtomicRmw8XorU | I32AtomicRmw16XorU | I32AtomicRmwXchg | I32AtomicRmw8XchgU | I32AtomicRmw16XchgU => InstrType::new(&[I32, I32], &[I32]),
+            I64AtomicRmwAdd | I64AtomicRmw8AddU | I64AtomicRmw16AddU | I64AtomicRmw32AddU | I64AtomicRmwSub | I64AtomicRmw8SubU | I64AtomicRmw16SubU | I64AtomicRmw32SubU | I64AtomicRmwAnd | I64AtomicRmw8AndU | I64AtomicRmw16AndU | I64AtomicRmw32AndU | I64AtomicRmwOr | I64AtomicRmw8OrU | I64AtomicRmw16OrU | I64AtomicRmw32OrU | I64AtomicRmwXor | I64AtomicRmw8XorU | I64AtomicRmw16XorU | I64AtomicRmw32XorU | I64AtomicRmwXchg | I64AtomicRmw8XchgU | I64AtomicRmw16XchgU | I64AtomicRmw32XchgU => InstrType::new(&[I32, I64], &[I64]),
+        }
+    }
+
+    /// log2 of the number of bytes accessed, which atomic accesses must be aligned to exactly
+    pub fn natural_alignment(&self) -> u32 {
+        match *self {
+            I32AtomicRmw8AddU | I64AtomicRmw8AddU | I32AtomicRmw8SubU | I64AtomicRmw8SubU | I32AtomicRmw8AndU | I64AtomicRmw8AndU | I32AtomicRmw8OrU | I64AtomicRmw8OrU | I32AtomicRmw8XorU | I64AtomicRmw8XorU | I32AtomicRmw8XchgU | I64AtomicRmw8XchgU => 0,
+            I32AtomicRmw16AddU | I64AtomicRmw16AddU | I32AtomicRmw16SubU | I64AtomicRmw16SubU | I32AtomicRmw16AndU | I64AtomicRmw16AndU | I32AtomicRmw16OrU | I64AtomicRmw16OrU | I32AtomicRmw16XorU | I64AtomicRmw16XorU | I32AtomicRmw16XchgU | I64AtomicRmw16XchgU => 1,
+            I32AtomicRmwAdd | I64AtomicRmw32AddU | I32AtomicRmwSub | I64AtomicRmw32SubU | I32AtomicRmwAnd | I64AtomicRmw32AndU | I32AtomicRmwOr | I64AtomicRmw32OrU | I32AtomicRmwXor | I64AtomicRmw32XorU | I32AtomicRmwXchg | I64AtomicRmw32XchgU => 2,
+            I64AtomicRmwAdd | I64AtomicRmwSub | I64AtomicRmwAnd | I64AtomicRmwOr | I64AtomicRmwXor | I64AtomicRmwXchg => 3,
+        }
+    }
+}
+
+impl AtomicCmpxchgOp {
+    pub fn to_type(&self) -> InstrType {
+        match *self {
+            I32AtomicRmwCmpxchg | I32AtomicRmw8CmpxchgU | I32AtomicRmw16CmpxchgU => InstrType::new(&[I32, I32, I32], &[I32]),
+            I64AtomicRmwCmpxchg | I64AtomicRmw8CmpxchgU | I64AtomicRmw16CmpxchgU | I64AtomicRmw32CmpxchgU => InstrType::new(&[I32, I64, I64], &[I64]),
+        }
+    }
+
+    /// log2 of the number of bytes accessed, which atomic accesses must be aligned to exactly
+    pub fn natural_alignment(&self) -> u32 {
+        match *self {
+            I32AtomicRmw8CmpxchgU | I64AtomicRmw8CmpxchgU => 0,
+            I32AtomicRmw16CmpxchgU | I64AtomicRmw16CmpxchgU => 1,
+            I32AtomicRmwCmpxchg | I64AtomicRmw32CmpxchgU => 2,
+            I64AtomicRmwCmpxchg => 3,
+        }
+    }
+}
+
+impl AtomicWaitOp {
+    pub fn to_type(&self) -> InstrType {
+        match *self {
+            MemoryAtomicWait32 => InstrType::new(&[I32, I32, I64], &[I32]),
+            MemoryAtomicWait64 => InstrType::new(&[I32, I64, I64], &[I32]),
+        }
+    }
+
+    /// log2 of the number of bytes accessed, which atomic accesses must be aligned to exactly
+    pub fn natural_alignment(&self) -> u32 {
+        match *self {
+            MemoryAtomicWait32 => 2,
+            MemoryAtomicWait64 => 3,
         }
     }
 }
@@ -419,27 +1157,90 @@ impl Instr {
         use self::Instr::*;
         match *self {
             Unreachable | Nop => Some(InstrType::default()),
-            Load(ref op, _) => Some(op.to_type()),
-            Store(ref op, _) => Some(op.to_type()),
+            Load(ref op, _, _) => Some(op.to_type()),
+            Store(ref op, _, _) => Some(op.to_type()),
             MemorySize(_) => Some(InstrType::new(&[], &[I32])),
             MemoryGrow(_) => Some(InstrType::new(&[I32], &[I32])),
+            // destination address, source address (or value for fill), length
+            MemoryInit(_, _) | MemoryCopy(_, _) | MemoryFill(_) => Some(InstrType::new(&[I32, I32, I32], &[])),
+            TableInit(_, _) | TableCopy(_, _) => Some(InstrType::new(&[I32, I32, I32], &[])),
+            DataDrop(_) | ElemDrop(_) => Some(InstrType::default()),
+            TypedSelect(ty) => Some(InstrType::new(&[ty, ty, I32], &[ty])),
+            RefNull(elem_type) => Some(InstrType::new(&[], &[elem_type.to_type()])),
+            RefFunc(_) => Some(InstrType::new(&[], &[FuncRef])),
+            TableSize(_) => Some(InstrType::new(&[], &[I32])),
             Const(ref val) => Some(InstrType::new(&[], &[val.to_type()])),
             Numeric(ref op) => Some(op.to_type()),
+            Shuffle(_) => Some(InstrType::new(&[V128, V128], &[V128])),
+            ExtractLane(ref op, _) => Some(op.to_type()),
+            ReplaceLane(ref op, _) => Some(op.to_type()),
+            LoadLane(ref op, _, _, _) => Some(op.to_type()),
+            StoreLane(ref op, _, _, _) => Some(op.to_type()),
+            AtomicLoad(ref op, _, _) => Some(op.to_type()),
+            AtomicStore(ref op, _, _) => Some(op.to_type()),
+            AtomicRmw(ref op, _, _) => Some(op.to_type()),
+            AtomicCmpxchg(ref op, _, _) => Some(op.to_type()),
+            AtomicWait(ref op, _, _) => Some(op.to_type()),
+            // address, count of waiters to wake up -> number of woken waiters
+            AtomicNotify(_, _) => Some(InstrType::new(&[I32, I32], &[I32])),
+            AtomicFence => Some(InstrType::default()),
             CallIndirect(ref func_ty, _) => Some(InstrType::new(&[&func_ty.params[..], &[I32]].concat(), &func_ty.results)),
 
             // nesting...
             Block(_) | Loop(_) | If(_) | Else | End => None,
+            Try(_) | Catch(_) | CatchAll | Delegate(_) => None,
+            // need lookup of the tag type, both never return
+            Throw(_) | Rethrow(_) => None,
             // depends on branch target?
             Br(_) | BrIf(_) | BrTable(_, _) => None,
             // need to inspect function type
             Return | Call(_) => None,
+            // never return (to this function), like return
+            ReturnCall(_) | ReturnCallIndirect(_, _) => None,
             // need abstract type stack "evaluation"
-            Drop | Select => None,
+            Drop | Select | RefIsNull => None,
+            // need lookup of the table element type
+            TableGet(_) | TableSet(_) | TableGrow(_) | TableFill(_) => None,
             // need lookup in locals/globals
             Local(_, _) | Global(_, _) => None,
         }
     }
 
+    /// same as to_type(), but for memory instructions with the memory64 proposal, where addresses
+    /// and sizes are of the index type of the accessed memory, given by index_type
+    pub fn to_type_with_memories(&self, index_type: impl Fn(Idx<Memory>) -> ValType) -> Option<InstrType> {
+        use self::Instr::*;
+        match *self {
+            Load(_, memory_idx, _)
+            | Store(_, memory_idx, _)
+            | LoadLane(_, memory_idx, _, _)
+            | StoreLane(_, memory_idx, _, _)
+            | AtomicLoad(_, memory_idx, _)
+            | AtomicStore(_, memory_idx, _)
+            | AtomicRmw(_, memory_idx, _)
+            | AtomicCmpxchg(_, memory_idx, _)
+            | AtomicWait(_, memory_idx, _)
+            | AtomicNotify(memory_idx, _)
+            | MemoryInit(_, memory_idx) => self.to_type().map(|ty| ty.with_address_type(index_type(memory_idx))),
+            MemorySize(memory_idx) => Some(InstrType::new(&[], &[index_type(memory_idx)])),
+            MemoryGrow(memory_idx) => {
+                let ty = index_type(memory_idx);
+                Some(InstrType::new(&[ty], &[ty]))
+            }
+            MemoryFill(memory_idx) => {
+                let ty = index_type(memory_idx);
+                Some(InstrType::new(&[ty, I32, ty], &[]))
+            }
+            MemoryCopy(dst_idx, src_idx) => {
+                let (dst_ty, src_ty) = (index_type(dst_idx), index_type(src_idx));
+                // length must fit into both memories
+                let length_ty = if dst_ty == I64 && src_ty == I64 { I64 } else { I32 };
+                Some(InstrType::new(&[dst_ty, src_ty, length_ty], &[]))
+            }
+            _ => self.to_type(),
+        }
+    }
+
     /// returns instruction name as in Wasm spec
     pub fn to_name(&self) -> &'static str {
         use self::Instr::*;
@@ -452,14 +1253,22 @@ impl Instr {
             If(_) => "if",
             Else => "else",
             End => "end",
+            Try(_) => "try",
+            Catch(_) => "catch",
+            CatchAll => "catch_all",
+            Delegate(_) => "delegate",
+            Throw(_) => "throw",
+            Rethrow(_) => "rethrow",
             Br(_) => "br",
             BrIf(_) => "br_if",
             BrTable(_, _) => "br_table",
             Return => "return",
             Call(_) => "call",
             CallIndirect(_, _) => "call_indirect",
+            ReturnCall(_) => "return_call",
+            ReturnCallIndirect(_, _) => "return_call_indirect",
             Drop => "drop",
-            Select => "select",
+            Select | TypedSelect(_) => "select",
             Local(GetLocal, _) => "get_local",
             Local(SetLocal, _) => "set_local",
             Local(TeeLocal, _) => "tee_local",
@@ -467,33 +1276,50 @@ impl Instr {
             Global(SetGlobal, _) => "set_global",
             MemorySize(_) => "memory.size",
             MemoryGrow(_) => "memory.grow",
+            MemoryInit(_, _) => "memory.init",
+            DataDrop(_) => "data.drop",
+            MemoryCopy(_, _) => "memory.copy",
+            MemoryFill(_) => "memory.fill",
+            TableInit(_, _) => "table.init",
+            ElemDrop(_) => "elem.drop",
+            TableCopy(_, _) => "table.copy",
+            RefNull(_) => "ref.null",
+            RefIsNull => "ref.is_null",
+            RefFunc(_) => "ref.func",
+            TableGet(_) => "table.get",
+            TableSet(_) => "table.set",
+            TableSize(_) => "table.size",
+            TableGrow(_) => "table.grow",
+            TableFill(_) => "table.fill",
             Const(Val::I32(_)) => "i32.const",
             Const(Val::I64(_)) => "i64.const",
             Const(Val::F32(_)) => "f32.const",
             Const(Val::F64(_)) => "f64.const",
-            Load(I32Load, _) => "i32.load",
-            Load(I64Load, _) => "i64.load",
-            Load(F32Load, _) => "f32.load",
-            Load(F64Load, _) => "f64.load",
-            Load(I32Load8S, _) => "i32.load8_s",
-            Load(I32Load8U, _) => "i32.load8_u",
-            Load(I32Load16S, _) => "i32.load16_s",
-            Load(I32Load16U, _) => "i32.load16_u",
-            Load(I64Load8S, _) => "i64.load8_s",
-            Load(I64Load8U, _) => "i64.load8_u",
-            Load(I64Load16S, _) => "i64.load16_s",
-            Load(I64Load16U, _) => "i64.load16_u",
-            Load(I64Load32S, _) => "i64.load32_s",
-            Load(I64Load32U, _) => "i64.load32_u",
-            Store(I32Store, _) => "i32.store",
-            Store(I64Store, _) => "i64.store",
-            Store(F32Store, _) => "f32.store",
-            Store(F64Store, _) => "f64.store",
-            Store(I32Store8, _) => "i32.store8",
-            Store(I32Store16, _) => "i32.store16",
-            Store(I64Store8, _) => "i64.store8",
-            Store(I64Store16, _) => "i64.store16",
-            Store(I64Store32, _) => "i64.store32",
+            Const(Val::V128(_)) => "v128.const",
+            Shuffle(_) => "i8x16.shuffle",
+            Load(I32Load, _, _) => "i32.load",
+            Load(I64Load, _, _) => "i64.load",
+            Load(F32Load, _, _) => "f32.load",
+            Load(F64Load, _, _) => "f64.load",
+            Load(I32Load8S, _, _) => "i32.load8_s",
+            Load(I32Load8U, _, _) => "i32.load8_u",
+            Load(I32Load16S, _, _) => "i32.load16_s",
+            Load(I32Load16U, _, _) => "i32.load16_u",
+            Load(I64Load8S, _, _) => "i64.load8_s",
+            Load(I64Load8U, _, _) => "i64.load8_u",
+            Load(I64Load16S, _, _) => "i64.load16_s",
+            Load(I64Load16U, _, _) => "i64.load16_u",
+            Load(I64Load32S, _, _) => "i64.load32_s",
+            Load(I64Load32U, _, _) => "i64.load32_u",
+            Store(I32Store, _, _) => "i32.store",
+            Store(I64Store, _, _) => "i64.store",
+            Store(F32Store, _, _) => "f32.store",
+            Store(F64Store, _, _) => "f64.store",
+            Store(I32Store8, _, _) => "i32.store8",
+            Store(I32Store16, _, _) => "i32.store16",
+            Store(I64Store8, _, _) => "i64.store8",
+            Store(I64Store16, _, _) => "i64.store16",
+            Store(I64Store32, _, _) => "i64.store32",
             Numeric(I32Eqz) => "i32.eqz",
             Numeric(I64Eqz) => "i64.eqz",
             Numeric(I32Clz) => "i32.clz",
@@ -541,6 +1367,19 @@ impl Instr {
             Numeric(I64ReinterpretF64) => "i64.reinterpret/f64",
             Numeric(F32ReinterpretI32) => "f32.reinterpret/i32",
             Numeric(F64ReinterpretI64) => "f64.reinterpret/i64",
+            Numeric(I32Extend8S) => "i32.extend8_s",
+            Numeric(I32Extend16S) => "i32.extend16_s",
+            Numeric(I64Extend8S) => "i64.extend8_s",
+            Numeric(I64Extend16S) => "i64.extend16_s",
+            Numeric(I64Extend32S) => "i64.extend32_s",
+            Numeric(I32TruncSatSF32) => "i32.trunc_s:sat/f32",
+            Numeric(I32TruncSatUF32) => "i32.trunc_u:sat/f32",
+            Numeric(I32TruncSatSF64) => "i32.trunc_s:sat/f64",
+            Numeric(I32TruncSatUF64) => "i32.trunc_u:sat/f64",
+            Numeric(I64TruncSatSF32) => "i64.trunc_s:sat/f32",
+            Numeric(I64TruncSatUF32) => "i64.trunc_u:sat/f32",
+            Numeric(I64TruncSatSF64) => "i64.trunc_s:sat/f64",
+            Numeric(I64TruncSatUF64) => "i64.trunc_u:sat/f64",
             Numeric(I32Eq) => "i32.eq",
             Numeric(I32Ne) => "i32.ne",
             Numeric(I32LtS) => "i32.lt_s",
@@ -617,6 +1456,307 @@ impl Instr {
             Numeric(F64Min) => "f64.min",
             Numeric(F64Max) => "f64.max",
             Numeric(F64Copysign) => "f64.copysign",
+            Load(V128Load, _, _) => "v128.load",
+            Load(V128Load8x8S, _, _) => "v128.load8x8_s",
+            Load(V128Load8x8U, _, _) => "v128.load8x8_u",
+            Load(V128Load16x4S, _, _) => "v128.load16x4_s",
+            Load(V128Load16x4U, _, _) => "v128.load16x4_u",
+            Load(V128Load32x2S, _, _) => "v128.load32x2_s",
+            Load(V128Load32x2U, _, _) => "v128.load32x2_u",
+            Load(V128Load8Splat, _, _) => "v128.load8_splat",
+            Load(V128Load16Splat, _, _) => "v128.load16_splat",
+            Load(V128Load32Splat, _, _) => "v128.load32_splat",
+            Load(V128Load64Splat, _, _) => "v128.load64_splat",
+            Load(V128Load32Zero, _, _) => "v128.load32_zero",
+            Load(V128Load64Zero, _, _) => "v128.load64_zero",
+            Store(V128Store, _, _) => "v128.store",
+            ExtractLane(I8x16ExtractLaneS, _) => "i8x16.extract_lane_s",
+            ExtractLane(I8x16ExtractLaneU, _) => "i8x16.extract_lane_u",
+            ExtractLane(I16x8ExtractLaneS, _) => "i16x8.extract_lane_s",
+            ExtractLane(I16x8ExtractLaneU, _) => "i16x8.extract_lane_u",
+            ExtractLane(I32x4ExtractLane, _) => "i32x4.extract_lane",
+            ExtractLane(I64x2ExtractLane, _) => "i64x2.extract_lane",
+            ExtractLane(F32x4ExtractLane, _) => "f32x4.extract_lane",
+            ExtractLane(F64x2ExtractLane, _) => "f64x2.extract_lane",
+            ReplaceLane(I8x16ReplaceLane, _) => "i8x16.replace_lane",
+            ReplaceLane(I16x8ReplaceLane, _) => "i16x8.replace_lane",
+            ReplaceLane(I32x4ReplaceLane, _) => "i32x4.replace_lane",
+            ReplaceLane(I64x2ReplaceLane, _) => "i64x2.replace_lane",
+            ReplaceLane(F32x4ReplaceLane, _) => "f32x4.replace_lane",
+            ReplaceLane(F64x2ReplaceLane, _) => "f64x2.replace_lane",
+            LoadLane(V128Load8Lane, _, _, _) => "v128.load8_lane",
+            LoadLane(V128Load16Lane, _, _, _) => "v128.load16_lane",
+            LoadLane(V128Load32Lane, _, _, _) => "v128.load32_lane",
+            LoadLane(V128Load64Lane, _, _, _) => "v128.load64_lane",
+            StoreLane(V128Store8Lane, _, _, _) => "v128.store8_lane",
+            StoreLane(V128Store16Lane, _, _, _) => "v128.store16_lane",
+            StoreLane(V128Store32Lane, _, _, _) => "v128.store32_lane",
+            StoreLane(V128Store64Lane, _, _, _) => "v128.store64_lane",
+            Numeric(I8x16Swizzle) => "i8x16.swizzle",
+            Numeric(I8x16Splat) => "i8x16.splat",
+            Numeric(I16x8Splat) => "i16x8.splat",
+            Numeric(I32x4Splat) => "i32x4.splat",
+            Numeric(I64x2Splat) => "i64x2.splat",
+            Numeric(F32x4Splat) => "f32x4.splat",
+            Numeric(F64x2Splat) => "f64x2.splat",
+            Numeric(I8x16Eq) => "i8x16.eq",
+            Numeric(I8x16Ne) => "i8x16.ne",
+            Numeric(I8x16LtS) => "i8x16.lt_s",
+            Numeric(I8x16LtU) => "i8x16.lt_u",
+            Numeric(I8x16GtS) => "i8x16.gt_s",
+            Numeric(I8x16GtU) => "i8x16.gt_u",
+            Numeric(I8x16LeS) => "i8x16.le_s",
+            Numeric(I8x16LeU) => "i8x16.le_u",
+            Numeric(I8x16GeS) => "i8x16.ge_s",
+            Numeric(I8x16GeU) => "i8x16.ge_u",
+            Numeric(I16x8Eq) => "i16x8.eq",
+            Numeric(I16x8Ne) => "i16x8.ne",
+            Numeric(I16x8LtS) => "i16x8.lt_s",
+            Numeric(I16x8LtU) => "i16x8.lt_u",
+            Numeric(I16x8GtS) => "i16x8.gt_s",
+            Numeric(I16x8GtU) => "i16x8.gt_u",
+            Numeric(I16x8LeS) => "i16x8.le_s",
+            Numeric(I16x8LeU) => "i16x8.le_u",
+            Numeric(I16x8GeS) => "i16x8.ge_s",
+            Numeric(I16x8GeU) => "i16x8.ge_u",
+            Numeric(I32x4Eq) => "i32x4.eq",
+            Numeric(I32x4Ne) => "i32x4.ne",
+            Numeric(I32x4LtS) => "i32x4.lt_s",
+            Numeric(I32x4LtU) => "i32x4.lt_u",
+            Numeric(I32x4GtS) => "i32x4.gt_s",
+            Numeric(I32x4GtU) => "i32x4.gt_u",
+            Numeric(I32x4LeS) => "i32x4.le_s",
+            Numeric(I32x4LeU) => "i32x4.le_u",
+            Numeric(I32x4GeS) => "i32x4.ge_s",
+            Numeric(I32x4GeU) => "i32x4.ge_u",
+            Numeric(F32x4Eq) => "f32x4.eq",
+            Numeric(F32x4Ne) => "f32x4.ne",
+            Numeric(F32x4Lt) => "f32x4.lt",
+            Numeric(F32x4Gt) => "f32x4.gt",
+            Numeric(F32x4Le) => "f32x4.le",
+            Numeric(F32x4Ge) => "f32x4.ge",
+            Numeric(F64x2Eq) => "f64x2.eq",
+            Numeric(F64x2Ne) => "f64x2.ne",
+            Numeric(F64x2Lt) => "f64x2.lt",
+            Numeric(F64x2Gt) => "f64x2.gt",
+            Numeric(F64x2Le) => "f64x2.le",
+            Numeric(F64x2Ge) => "f64x2.ge",
+            Numeric(V128Not) => "v128.not",
+            Numeric(V128And) => "v128.and",
+            Numeric(V128Andnot) => "v128.andnot",
+            Numeric(V128Or) => "v128.or",
+            Numeric(V128Xor) => "v128.xor",
+            Numeric(V128Bitselect) => "v128.bitselect",
+            Numeric(V128AnyTrue) => "v128.any_true",
+            Numeric(F32x4DemoteF64x2Zero) => "f32x4.demote_f64x2_zero",
+            Numeric(F64x2PromoteLowF32x4) => "f64x2.promote_low_f32x4",
+            Numeric(I8x16Abs) => "i8x16.abs",
+            Numeric(I8x16Neg) => "i8x16.neg",
+            Numeric(I8x16Popcnt) => "i8x16.popcnt",
+            Numeric(I8x16AllTrue) => "i8x16.all_true",
+            Numeric(I8x16Bitmask) => "i8x16.bitmask",
+            Numeric(I8x16NarrowI16x8S) => "i8x16.narrow_i16x8_s",
+            Numeric(I8x16NarrowI16x8U) => "i8x16.narrow_i16x8_u",
+            Numeric(F32x4Ceil) => "f32x4.ceil",
+            Numeric(F32x4Floor) => "f32x4.floor",
+            Numeric(F32x4Trunc) => "f32x4.trunc",
+            Numeric(F32x4Nearest) => "f32x4.nearest",
+            Numeric(I8x16Shl) => "i8x16.shl",
+            Numeric(I8x16ShrS) => "i8x16.shr_s",
+            Numeric(I8x16ShrU) => "i8x16.shr_u",
+            Numeric(I8x16Add) => "i8x16.add",
+            Numeric(I8x16AddSatS) => "i8x16.add_sat_s",
+            Numeric(I8x16AddSatU) => "i8x16.add_sat_u",
+            Numeric(I8x16Sub) => "i8x16.sub",
+            Numeric(I8x16SubSatS) => "i8x16.sub_sat_s",
+            Numeric(I8x16SubSatU) => "i8x16.sub_sat_u",
+            Numeric(F64x2Ceil) => "f64x2.ceil",
+            Numeric(F64x2Floor) => "f64x2.floor",
+            Numeric(I8x16MinS) => "i8x16.min_s",
+            Numeric(I8x16MinU) => "i8x16.min_u",
+            Numeric(I8x16MaxS) => "i8x16.max_s",
+            Numeric(I8x16MaxU) => "i8x16.max_u",
+            Numeric(F64x2Trunc) => "f64x2.trunc",
+            Numeric(I8x16AvgrU) => "i8x16.avgr_u",
+            Numeric(I16x8ExtaddPairwiseI8x16S) => "i16x8.extadd_pairwise_i8x16_s",
+            Numeric(I16x8ExtaddPairwiseI8x16U) => "i16x8.extadd_pairwise_i8x16_u",
+            Numeric(I32x4ExtaddPairwiseI16x8S) => "i32x4.extadd_pairwise_i16x8_s",
+            Numeric(I32x4ExtaddPairwiseI16x8U) => "i32x4.extadd_pairwise_i16x8_u",
+            Numeric(I16x8Abs) => "i16x8.abs",
+            Numeric(I16x8Neg) => "i16x8.neg",
+            Numeric(I16x8Q15mulrSatS) => "i16x8.q15mulr_sat_s",
+            Numeric(I16x8AllTrue) => "i16x8.all_true",
+            Numeric(I16x8Bitmask) => "i16x8.bitmask",
+            Numeric(I16x8NarrowI32x4S) => "i16x8.narrow_i32x4_s",
+            Numeric(I16x8NarrowI32x4U) => "i16x8.narrow_i32x4_u",
+            Numeric(I16x8ExtendLowI8x16S) => "i16x8.extend_low_i8x16_s",
+            Numeric(I16x8ExtendHighI8x16S) => "i16x8.extend_high_i8x16_s",
+            Numeric(I16x8ExtendLowI8x16U) => "i16x8.extend_low_i8x16_u",
+            Numeric(I16x8ExtendHighI8x16U) => "i16x8.extend_high_i8x16_u",
+            Numeric(I16x8Shl) => "i16x8.shl",
+            Numeric(I16x8ShrS) => "i16x8.shr_s",
+            Numeric(I16x8ShrU) => "i16x8.shr_u",
+            Numeric(I16x8Add) => "i16x8.add",
+            Numeric(I16x8AddSatS) => "i16x8.add_sat_s",
+            Numeric(I16x8AddSatU) => "i16x8.add_sat_u",
+            Numeric(I16x8Sub) => "i16x8.sub",
+            Numeric(I16x8SubSatS) => "i16x8.sub_sat_s",
+            Numeric(I16x8SubSatU) => "i16x8.sub_sat_u",
+            Numeric(F64x2Nearest) => "f64x2.nearest",
+            Numeric(I16x8Mul) => "i16x8.mul",
+            Numeric(I16x8MinS) => "i16x8.min_s",
+            Numeric(I16x8MinU) => "i16x8.min_u",
+            Numeric(I16x8MaxS) => "i16x8.max_s",
+            Numeric(I16x8MaxU) => "i16x8.max_u",
+            Numeric(I16x8AvgrU) => "i16x8.avgr_u",
+            Numeric(I16x8ExtmulLowI8x16S) => "i16x8.extmul_low_i8x16_s",
+            Numeric(I16x8ExtmulHighI8x16S) => "i16x8.extmul_high_i8x16_s",
+            Numeric(I16x8ExtmulLowI8x16U) => "i16x8.extmul_low_i8x16_u",
+            Numeric(I16x8ExtmulHighI8x16U) => "i16x8.extmul_high_i8x16_u",
+            Numeric(I32x4Abs) => "i32x4.abs",
+            Numeric(I32x4Neg) => "i32x4.neg",
+            Numeric(I32x4AllTrue) => "i32x4.all_true",
+            Numeric(I32x4Bitmask) => "i32x4.bitmask",
+            Numeric(I32x4ExtendLowI16x8S) => "i32x4.extend_low_i16x8_s",
+            Numeric(I32x4ExtendHighI16x8S) => "i32x4.extend_high_i16x8_s",
+            Numeric(I32x4ExtendLowI16x8U) => "i32x4.extend_low_i16x8_u",
+            Numeric(I32x4ExtendHighI16x8U) => "i32x4.extend_high_i16x8_u",
+            Numeric(I32x4Shl) => "i32x4.shl",
+            Numeric(I32x4ShrS) => "i32x4.shr_s",
+            Numeric(I32x4ShrU) => "i32x4.shr_u",
+            Numeric(I32x4Add) => "i32x4.add",
+            Numeric(I32x4Sub) => "i32x4.sub",
+            Numeric(I32x4Mul) => "i32x4.mul",
+            Numeric(I32x4MinS) => "i32x4.min_s",
+            Numeric(I32x4MinU) => "i32x4.min_u",
+            Numeric(I32x4MaxS) => "i32x4.max_s",
+            Numeric(I32x4MaxU) => "i32x4.max_u",
+            Numeric(I32x4DotI16x8S) => "i32x4.dot_i16x8_s",
+            Numeric(I32x4ExtmulLowI16x8S) => "i32x4.extmul_low_i16x8_s",
+            Numeric(I32x4ExtmulHighI16x8S) => "i32x4.extmul_high_i16x8_s",
+            Numeric(I32x4ExtmulLowI16x8U) => "i32x4.extmul_low_i16x8_u",
+            Numeric(I32x4ExtmulHighI16x8U) => "i32x4.extmul_high_i16x8_u",
+            Numeric(I64x2Abs) => "i64x2.abs",
+            Numeric(I64x2Neg) => "i64x2.neg",
+            Numeric(I64x2AllTrue) => "i64x2.all_true",
+            Numeric(I64x2Bitmask) => "i64x2.bitmask",
+            Numeric(I64x2ExtendLowI32x4S) => "i64x2.extend_low_i32x4_s",
+            Numeric(I64x2ExtendHighI32x4S) => "i64x2.extend_high_i32x4_s",
+            Numeric(I64x2ExtendLowI32x4U) => "i64x2.extend_low_i32x4_u",
+            Numeric(I64x2ExtendHighI32x4U) => "i64x2.extend_high_i32x4_u",
+            Numeric(I64x2Shl) => "i64x2.shl",
+            Numeric(I64x2ShrS) => "i64x2.shr_s",
+            Numeric(I64x2ShrU) => "i64x2.shr_u",
+            Numeric(I64x2Add) => "i64x2.add",
+            Numeric(I64x2Sub) => "i64x2.sub",
+            Numeric(I64x2Mul) => "i64x2.mul",
+            Numeric(I64x2Eq) => "i64x2.eq",
+            Numeric(I64x2Ne) => "i64x2.ne",
+            Numeric(I64x2LtS) => "i64x2.lt_s",
+            Numeric(I64x2GtS) => "i64x2.gt_s",
+            Numeric(I64x2LeS) => "i64x2.le_s",
+            Numeric(I64x2GeS) => "i64x2.ge_s",
+            Numeric(I64x2ExtmulLowI32x4S) => "i64x2.extmul_low_i32x4_s",
+            Numeric(I64x2ExtmulHighI32x4S) => "i64x2.extmul_high_i32x4_s",
+            Numeric(I64x2ExtmulLowI32x4U) => "i64x2.extmul_low_i32x4_u",
+            Numeric(I64x2ExtmulHighI32x4U) => "i64x2.extmul_high_i32x4_u",
+            Numeric(F32x4Abs) => "f32x4.abs",
+            Numeric(F32x4Neg) => "f32x4.neg",
+            Numeric(F32x4Sqrt) => "f32x4.sqrt",
+            Numeric(F32x4Add) => "f32x4.add",
+            Numeric(F32x4Sub) => "f32x4.sub",
+            Numeric(F32x4Mul) => "f32x4.mul",
+            Numeric(F32x4Div) => "f32x4.div",
+            Numeric(F32x4Min) => "f32x4.min",
+            Numeric(F32x4Max) => "f32x4.max",
+            Numeric(F32x4Pmin) => "f32x4.pmin",
+            Numeric(F32x4Pmax) => "f32x4.pmax",
+            Numeric(F64x2Abs) => "f64x2.abs",
+            Numeric(F64x2Neg) => "f64x2.neg",
+            Numeric(F64x2Sqrt) => "f64x2.sqrt",
+            Numeric(F64x2Add) => "f64x2.add",
+            Numeric(F64x2Sub) => "f64x2.sub",
+            Numeric(F64x2Mul) => "f64x2.mul",
+            Numeric(F64x2Div) => "f64x2.div",
+            Numeric(F64x2Min) => "f64x2.min",
+            Numeric(F64x2Max) => "f64x2.max",
+            Numeric(F64x2Pmin) => "f64x2.pmin",
+            Numeric(F64x2Pmax) => "f64x2.pmax",
+            Numeric(I32x4TruncSatF32x4S) => "i32x4.trunc_sat_f32x4_s",
+            Numeric(I32x4TruncSatF32x4U) => "i32x4.trunc_sat_f32x4_u",
+            Numeric(F32x4ConvertI32x4S) => "f32x4.convert_i32x4_s",
+            Numeric(F32x4ConvertI32x4U) => "f32x4.convert_i32x4_u",
+            Numeric(I32x4TruncSatF64x2SZero) => "i32x4.trunc_sat_f64x2_s_zero",
+            Numeric(I32x4TruncSatF64x2UZero) => "i32x4.trunc_sat_f64x2_u_zero",
+            Numeric(F64x2ConvertLowI32x4S) => "f64x2.convert_low_i32x4_s",
+            Numeric(F64x2ConvertLowI32x4U) => "f64x2.convert_low_i32x4_u",
+            AtomicNotify(_, _) => "memory.atomic.notify",
+            AtomicFence => "atomic.fence",
+            AtomicLoad(I32AtomicLoad, _, _) => "i32.atomic.load",
+            AtomicLoad(I64AtomicLoad, _, _) => "i64.atomic.load",
+            AtomicLoad(I32AtomicLoad8U, _, _) => "i32.atomic.load8_u",
+            AtomicLoad(I32AtomicLoad16U, _, _) => "i32.atomic.load16_u",
+            AtomicLoad(I64AtomicLoad8U, _, _) => "i64.atomic.load8_u",
+            AtomicLoad(I64AtomicLoad16U, _, _) => "i64.atomic.load16_u",
+            AtomicLoad(I64AtomicLoad32U, _, _) => "i64.atomic.load32_u",
+            AtomicStore(I32AtomicStore, _, _) => "i32.atomic.store",
+            AtomicStore(I64AtomicStore, _, _) => "i64.atomic.store",
+            AtomicStore(I32AtomicStore8, _, _) => "i32.atomic.store8",
+            AtomicStore(I32AtomicStore16, _, _) => "i32.atomic.store16",
+            AtomicStore(I64AtomicStore8, _, _) => "i64.atomic.store8",
+            AtomicStore(I64AtomicStore16, _, _) => "i64.atomic.store16",
+            AtomicStore(I64AtomicStore32, _, _) => "i64.atomic.store32",
+            AtomicRmw(I32AtomicRmwAdd, _, _) => "i32.atomic.rmw.add",
+            AtomicRmw(I64AtomicRmwAdd, _, _) => "i64.atomic.rmw.add",
+            AtomicRmw(I32AtomicRmw8AddU, _, _) => "i32.atomic.rmw8.add_u",
+            AtomicRmw(I32AtomicRmw16AddU, _, _) => "i32.atomic.rmw16.add_u",
+            AtomicRmw(I64AtomicRmw8AddU, _, _) => "i64.atomic.rmw8.add_u",
+            AtomicRmw(I64AtomicRmw16AddU, _, _) => "i64.atomic.rmw16.add_u",
+            AtomicRmw(I64AtomicRmw32AddU, _, _) => "i64.atomic.rmw32.add_u",
+            AtomicRmw(I32AtomicRmwSub, _, _) => "i32.atomic.rmw.sub",
+            AtomicRmw(I64AtomicRmwSub, _, _) => "i64.atomic.rmw.sub",
+            AtomicRmw(I32AtomicRmw8SubU, _, _) => "i32.atomic.rmw8.sub_u",
+            AtomicRmw(I32AtomicRmw16SubU, _, _) => "i32.atomic.rmw16.sub_u",
+            AtomicRmw(I64AtomicRmw8SubU, _, _) => "i64.atomic.rmw8.sub_u",
+            AtomicRmw(I64AtomicRmw16SubU, _, _) => "i64.atomic.rmw16.sub_u",
+            AtomicRmw(I64AtomicRmw32SubU, _, _) => "i64.atomic.rmw32.sub_u",
+            AtomicRmw(I32AtomicRmwAnd, _, _) => "i32.atomic.rmw.and",
+            AtomicRmw(I64AtomicRmwAnd, _, _) => "i64.atomic.rmw.and",
+            AtomicRmw(I32AtomicRmw8AndU, _, _) => "i32.atomic.rmw8.and_u",
+            AtomicRmw(I32AtomicRmw16AndU, _, _) => "i32.atomic.rmw16.and_u",
+            AtomicRmw(I64AtomicRmw8AndU, _, _) => "i64.atomic.rmw8.and_u",
+            AtomicRmw(I64AtomicRmw16AndU, _, _) => "i64.atomic.rmw16.and_u",
+            AtomicRmw(I64AtomicRmw32AndU, _, _) => "i64.atomic.rmw32.and_u",
+            AtomicRmw(I32AtomicRmwOr, _, _) => "i32.atomic.rmw.or",
+            AtomicRmw(I64AtomicRmwOr, _, _) => "i64.atomic.rmw.or",
+            AtomicRmw(I32AtomicRmw8OrU, _, _) => "i32.atomic.rmw8.or_u",
+            AtomicRmw(I32AtomicRmw16OrU, _, _) => "i32.atomic.rmw16.or_u",
+            AtomicRmw(I64AtomicRmw8OrU, _, _) => "i64.atomic.rmw8.or_u",
+            AtomicRmw(I64AtomicRmw16OrU, _, _) => "i64.atomic.rmw16.or_u",
+            AtomicRmw(I64AtomicRmw32OrU, _, _) => "i64.atomic.rmw32.or_u",
+            AtomicRmw(I32AtomicRmwXor, _, _) => "i32.atomic.rmw.xor",
+            AtomicRmw(I64AtomicRmwXor, _, _) => "i64.atomic.rmw.xor",
+            AtomicRmw(I32AtomicRmw8XorU, _, _) => "i32.atomic.rmw8.xor_u",
+            AtomicRmw(I32AtomicRmw16XorU, _, _) => "i32.atomic.rmw16.xor_u",
+            AtomicRmw(I64AtomicRmw8XorU, _, _) => "i64.atomic.rmw8.xor_u",
+            AtomicRmw(I64AtomicRmw16XorU, _, _) => "i64.atomic.rmw16.xor_u",
+            AtomicRmw(I64AtomicRmw32XorU, _, _) => "i64.atomic.rmw32.xor_u",
+            AtomicRmw(I32AtomicRmwXchg, _, _) => "i32.atomic.rmw.xchg",
+            AtomicRmw(I64AtomicRmwXchg, _, _) => "i64.atomic.rmw.xchg",
+            AtomicRmw(I32AtomicRmw8XchgU, _, _) => "i32.atomic.rmw8.xchg_u",
+            AtomicRmw(I32AtomicRmw16XchgU, _, _) => "i32.atomic.rmw16.xchg_u",
+            AtomicRmw(I64AtomicRmw8XchgU, _, _) => "i64.atomic.rmw8.xchg_u",
+            AtomicRmw(I64AtomicRmw16XchgU, _, _) => "i64.atomic.rmw16.xchg_u",
+            AtomicRmw(I64AtomicRmw32XchgU, _, _) => "i64.atomic.rmw32.xchg_u",
+            AtomicCmpxchg(I32AtomicRmwCmpxchg, _, _) => "i32.atomic.rmw.cmpxchg",
+            AtomicCmpxchg(I64AtomicRmwCmpxchg, _, _) => "i64.atomic.rmw.cmpxchg",
+            AtomicCmpxchg(I32AtomicRmw8CmpxchgU, _, _) => "i32.atomic.rmw8.cmpxchg_u",
+            AtomicCmpxchg(I32AtomicRmw16CmpxchgU, _, _) => "i32.atomic.rmw16.cmpxchg_u",
+            AtomicCmpxchg(I64AtomicRmw8CmpxchgU, _, _) => "i64.atomic.rmw8.cmpxchg_u",
+            AtomicCmpxchg(I64AtomicRmw16CmpxchgU, _, _) => "i64.atomic.rmw16.cmpxchg_u",
+            AtomicCmpxchg(I64AtomicRmw32CmpxchgU, _, _) => "i64.atomic.rmw32.cmpxchg_u",
+            AtomicWait(MemoryAtomicWait32, _, _) => "memory.atomic.wait32",
+            AtomicWait(MemoryAtomicWait64, _, _) => "memory.atomic.wait64",
         }
     }
 }
@@ -634,6 +1774,8 @@ impl Module {
                 body,
             }),
             export: Vec::new(),
+            name: None,
+            local_names: BTreeMap::new(),
         });
         (self.functions.len() - 1).into()
     }
@@ -644,6 +1786,8 @@ impl Module {
             import: Some((module, name)),
             code: None,
             export: Vec::new(),
+            name: None,
+            local_names: BTreeMap::new(),
         });
         (self.functions.len() - 1).into()
     }
@@ -654,6 +1798,7 @@ impl Module {
             import: None,
             init: Some(init),
             export: Vec::new(),
+            name: None,
         });
         (self.globals.len() - 1).into()
     }
@@ -670,6 +1815,36 @@ impl Module {
         }
         types
     }
+
+    /// contents (i.e., after the name) of the first custom section with the given name
+    pub fn custom_section(&self, name: &str) -> Option<&[u8]> {
+        self.custom_sections.iter()
+            .filter_map(|section| {
+                let mut contents = &section[..];
+                match String::decode(&mut contents) {
+                    Ok(ref section_name) if section_name == name => Some(contents),
+                    _ => None,
+                }
+            })
+            .next()
+    }
+
+    /// replaces all custom sections with the given name
+    pub fn set_custom_section(&mut self, name: &str, contents: &[u8]) {
+        self.remove_custom_sections(name);
+        let mut section = Vec::new();
+        name.to_string().encode(&mut section).expect("encoding to a Vec cannot fail");
+        section.extend_from_slice(contents);
+        self.custom_sections.push(section);
+    }
+
+    /// removes all custom sections with the given name
+    pub fn remove_custom_sections(&mut self, name: &str) {
+        self.custom_sections.retain(|section| {
+            let mut contents = &section[..];
+            String::decode(&mut contents).ok().map_or(true, |section_name| section_name != name)
+        });
+    }
 }
 
 impl Function {
@@ -721,3 +1896,31 @@ impl Function {
         self.code.as_ref().map(|code| code.body.len()).unwrap_or(0)
     }
 }
+
+impl CodeOffsets {
+    pub fn offset(&self, function: Idx<Function>, instr: Idx<Instr>) -> Option<usize> {
+        self.functions.get(function.0)?.as_ref()?.instrs.get(instr.0).cloned()
+    }
+
+    /// the function whose body contains the offset
+    pub fn function(&self, offset: usize) -> Option<Idx<Function>> {
+        // bodies are sorted by offset, but imported functions have none, so search linearly
+        self.functions.iter()
+            .position(|function| function.as_ref().map_or(false, |function| function.body.start <= offset && offset < function.body.end))
+            .map(Into::into)
+    }
+
+    /// the instruction that contains the offset (i.e., also for offsets of immediates), the
+    /// inverse of offset()
+    pub fn location(&self, offset: usize) -> Option<(Idx<Function>, Idx<Instr>)> {
+        let function = self.function(offset)?;
+        let instrs = &self.functions[function.0].as_ref()?.instrs;
+        let instr = match instrs.binary_search(&offset) {
+            Ok(i) => i,
+            // before the first instruction, i.e., in the size field or locals
+            Err(0) => return None,
+            Err(i) => i - 1,
+        };
+        Some((function, instr.into()))
+    }
+}
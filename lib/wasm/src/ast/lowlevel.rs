@@ -6,6 +6,83 @@ pub struct Module {
     pub sections: Vec<Section>,
 }
 
+/// Module decoded from a byte slice, where function bodies are only located but not decoded yet
+/// (see binary.rs). Bodies can then be decoded on demand, or in parallel and each straight to
+/// high-level code (see convert.rs), and tools that need only types, imports, exports etc. do not
+/// pay for the code section (the bulk of large binaries).
+#[derive(Debug, Clone)]
+pub struct LazyModule<'a> {
+    /// all sections in their original order, but the code section is empty (see bodies)
+    pub sections: Vec<Section>,
+    /// offset in the module and contents (after the size) of each function body in the code section
+    pub bodies: Vec<(usize, &'a [u8])>,
+}
+
+/// Zero-copy variant of Module, decoded from a byte slice (e.g., a memory-mapped file, see
+/// binary.rs). Names, data segments, custom sections, and function bodies are borrowed from the
+/// slice instead of being allocated, all other sections are the same as in Module.
+#[derive(Debug, Clone)]
+pub struct BorrowedModule<'a> {
+    pub sections: Vec<BorrowedSection<'a>>,
+}
+
+#[derive(Debug, Clone)]
+pub enum BorrowedSection<'a> {
+    Custom(&'a [u8]),
+    Import(Vec<BorrowedImport<'a>>),
+    Export(Vec<BorrowedExport<'a>>),
+    Data(Vec<BorrowedData<'a>>),
+    /// function bodies are not decoded, see LazyModule::bodies
+    Code(Vec<(usize, &'a [u8])>),
+    /// never Custom, Import, Export, Data, or Code
+    Other(Section),
+}
+
+#[derive(Debug, Clone)]
+pub struct BorrowedImport<'a> {
+    pub module: &'a str,
+    pub name: &'a str,
+    pub type_: ImportType,
+}
+
+#[derive(Debug, Clone)]
+pub struct BorrowedExport<'a> {
+    pub name: &'a str,
+    pub type_: ExportType,
+}
+
+#[derive(Debug, Clone)]
+pub struct BorrowedData<'a> {
+    pub mode: DataMode,
+    pub init: &'a [u8],
+}
+
+impl<'a> BorrowedModule<'a> {
+    /// copies all borrowed parts but the function bodies, which are then decoded from the slice
+    /// (e.g., straight to a high-level module, see LazyModule::into_highlevel)
+    pub fn into_lazy(self) -> LazyModule<'a> {
+        let mut bodies = Vec::new();
+        let sections = self.sections.into_iter().map(|section| match section {
+            BorrowedSection::Custom(bytes) => Section::Custom(bytes.to_vec()),
+            BorrowedSection::Import(imports) => Section::Import(WithSize(imports.into_iter()
+                .map(|BorrowedImport { module, name, type_ }| Import { module: module.to_string(), name: name.to_string(), type_ })
+                .collect())),
+            BorrowedSection::Export(exports) => Section::Export(WithSize(exports.into_iter()
+                .map(|BorrowedExport { name, type_ }| Export { name: name.to_string(), type_ })
+                .collect())),
+            BorrowedSection::Data(data) => Section::Data(WithSize(data.into_iter()
+                .map(|BorrowedData { mode, init }| Data { mode, init: init.to_vec() })
+                .collect())),
+            BorrowedSection::Code(code) => {
+                bodies = code;
+                Section::Code(WithSize(Vec::new()))
+            }
+            BorrowedSection::Other(section) => section,
+        }).collect();
+        LazyModule { sections, bodies }
+    }
+}
+
 /// Just a marker; does not save the size itself since that changes during transformations anyway.
 #[derive(Debug, Clone)]
 pub struct WithSize<T>(pub T);
@@ -26,6 +103,10 @@ pub enum Section {
     #[tag = 9] Element(WithSize<Vec<Element>>),
     #[tag = 10] Code(WithSize<Vec<WithSize<Code>>>),
     #[tag = 11] Data(WithSize<Vec<Data>>),
+    // from the bulk memory proposal, must come before the code section (despite the higher id)
+    #[tag = 12] DataCount(WithSize<u32>),
+    // from the exception handling proposal, must come between memory and global section
+    #[tag = 13] Tag(WithSize<Vec<TagType>>),
 }
 
 #[derive(WasmBinary, Debug, Clone)]
@@ -34,22 +115,45 @@ pub struct Global {
     pub init: Expr,
 }
 
-#[derive(WasmBinary, Debug, Clone)]
+/// needs manual impl because the presence of fields depends on the flags, see binary.rs
+#[derive(Debug, Clone)]
 pub struct Element {
-    // always 0x00 in WASM version 1
-    pub table_idx: Idx<Table>,
-    pub offset: Expr,
-    pub init: Vec<Idx<Function>>,
+    pub mode: ElementMode,
+    pub init: ElementItems,
 }
 
-#[derive(WasmBinary, Debug, Clone)]
+/// passive and declarative segments are from the bulk memory proposal, before that all segments
+/// were active and table_idx was always 0x00
+#[derive(Debug, Clone)]
+pub enum ElementMode {
+    Active { table_idx: Idx<Table>, offset: Expr },
+    Passive,
+    Declarative,
+}
+
+/// segments with expressions instead of function indices are from the reference types proposal,
+/// each expression is a constant ref.null or ref.func
+#[derive(Debug, Clone)]
+pub enum ElementItems {
+    Functions(Vec<Idx<Function>>),
+    Exprs(ElemType, Vec<Expr>),
+}
+
+/// needs manual impl because the presence of fields depends on the flags, see binary.rs
+#[derive(Debug, Clone)]
 pub struct Data {
-    // always 0x00 in WASM version 1
-    pub memory_idx: Idx<Memory>,
-    pub offset: Expr,
+    pub mode: DataMode,
     pub init: Vec<u8>,
 }
 
+/// passive segments are from the bulk memory proposal, before that all segments were active and
+/// memory_idx was always 0x00
+#[derive(Debug, Clone)]
+pub enum DataMode {
+    Active { memory_idx: Idx<Memory>, offset: Expr },
+    Passive,
+}
+
 #[derive(WasmBinary, Debug, Clone)]
 pub struct Import {
     pub module: String,
@@ -69,6 +173,7 @@ pub enum ImportType {
     #[tag = 0x1] Table(TableType),
     #[tag = 0x2] Memory(MemoryType),
     #[tag = 0x3] Global(GlobalType),
+    #[tag = 0x4] Tag(TagType),
 }
 
 #[derive(WasmBinary, Debug, Clone)]
@@ -77,6 +182,111 @@ pub enum ExportType {
     #[tag = 0x1] Table(Idx<Table>),
     #[tag = 0x2] Memory(Idx<Memory>),
     #[tag = 0x3] Global(Idx<Global>),
+    #[tag = 0x4] Tag(Idx<Tag>),
+}
+
+// exception handling: the leading attribute byte is always 0x00 (= exception), the function type
+// gives the types of the values carried by the exception (and must not have results)
+#[derive(WasmBinary, Debug, Clone)]
+#[tag = 0x00]
+pub struct TagType(pub Idx<FunctionType>);
+
+/* Custom sections with known contents */
+
+/// Contents of the "name" custom section (after the custom section name itself), see
+/// https://github.com/WebAssembly/design/blob/master/BinaryEncoding.md#name-section
+/// NOTE subsections are not a vec (no count prefix), but just follow each other until the end of
+/// the custom section, see manual impl in binary.rs
+#[derive(Debug, Clone, Default)]
+pub struct NameSection {
+    pub subsections: Vec<NameSubSection>,
+}
+
+#[derive(WasmBinary, Debug, Clone)]
+pub enum NameSubSection {
+    #[tag = 0] Module(WithSize<String>),
+    #[tag = 1] Function(WithSize<Vec<FunctionName>>),
+    #[tag = 2] Local(WithSize<Vec<LocalNames>>),
+    // from the extended name section proposal
+    #[tag = 7] Global(WithSize<Vec<GlobalName>>),
+}
+
+#[derive(WasmBinary, Debug, Clone)]
+pub struct FunctionName {
+    pub function_idx: Idx<Function>,
+    pub name: String,
+}
+
+#[derive(WasmBinary, Debug, Clone)]
+pub struct LocalNames {
+    pub function_idx: Idx<Function>,
+    pub names: Vec<LocalName>,
+}
+
+#[derive(WasmBinary, Debug, Clone)]
+pub struct LocalName {
+    pub local_idx: Idx<Local>,
+    pub name: String,
+}
+
+#[derive(WasmBinary, Debug, Clone)]
+pub struct GlobalName {
+    pub global_idx: Idx<Global>,
+    pub name: String,
+}
+
+/// Contents of the "linking" custom section (of relocatable object files), see
+/// https://github.com/WebAssembly/tool-conventions/blob/master/Linking.md
+/// Only subsections that refer to function/global/table indices are decoded, see manual impls
+/// in binary.rs
+#[derive(Debug, Clone)]
+pub struct LinkingSection {
+    pub version: u32,
+    pub subsections: Vec<LinkingSubSection>,
+}
+
+#[derive(Debug, Clone)]
+pub enum LinkingSubSection {
+    ComdatInfo(Vec<Comdat>),
+    SymbolTable(Vec<SymbolInfo>),
+    // id + contents of all other subsections (segment info, init functions etc.)
+    Other(u8, Vec<u8>),
+}
+
+#[derive(WasmBinary, Debug, Clone)]
+pub struct Comdat {
+    pub name: String,
+    pub flags: u32,
+    pub symbols: Vec<ComdatSymbol>,
+}
+
+#[derive(WasmBinary, Debug, Clone)]
+pub enum ComdatSymbol {
+    #[tag = 0] Data(u32),
+    #[tag = 1] Function(Idx<Function>),
+    #[tag = 2] Global(Idx<Global>),
+    #[tag = 3] Event(u32),
+    #[tag = 4] Table(Idx<Table>),
+    #[tag = 5] Section(u32),
+}
+
+/// name is only present for defined symbols or if the explicit name flag is set
+#[derive(Debug, Clone)]
+pub enum SymbolInfo {
+    Function { flags: u32, function_idx: Idx<Function>, name: Option<String> },
+    Data { flags: u32, name: String, segment: Option<DataSymbolSegment> },
+    Global { flags: u32, global_idx: Idx<Global>, name: Option<String> },
+    Section { flags: u32, section_idx: u32 },
+    Event { flags: u32, event_idx: u32, name: Option<String> },
+    Table { flags: u32, table_idx: Idx<Table>, name: Option<String> },
+}
+
+// only for defined data symbols
+#[derive(WasmBinary, Debug, Clone)]
+pub struct DataSymbolSegment {
+    pub segment_idx: u32,
+    pub offset: u32,
+    pub size: u32,
 }
 
 // Markers for Idx<T>, since in low-level format Function, Table, and Memory have not one type,
@@ -91,6 +301,9 @@ pub struct Table;
 #[derive(Debug)]
 pub struct Memory;
 
+#[derive(Debug)]
+pub struct Tag;
+
 
 /* Code */
 
@@ -109,6 +322,20 @@ pub struct Locals {
 #[derive(Debug, Clone)]
 pub struct Expr(pub Vec<Instr>);
 
+/// needs manual impl because of compressed format, see binary.rs
+/// with multi-value, blocks can also have parameters and multiple results, which are then given
+/// by a function type (in the type section)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BlockType {
+    Empty,
+    Value(ValType),
+    FunctionType(Idx<FunctionType>),
+}
+
+/// memarg of loads and stores, together with the memory index from the multi-memory proposal
+#[derive(Debug, Clone, Copy)]
+pub struct MemoryMemarg(pub Idx<Memory>, pub Memarg);
+
 #[derive(WasmBinary, Debug, Clone)]
 pub enum Instr {
     #[tag = 0x00] Unreachable,
@@ -121,16 +348,31 @@ pub enum Instr {
     #[tag = 0x05] Else,
     #[tag = 0x0b] End,
 
+    // exception handling, Catch and CatchAll also end the previous (try or catch) block
+    #[tag = 0x06] Try(BlockType),
+    #[tag = 0x07] Catch(Idx<Tag>),
+    #[tag = 0x19] CatchAll,
+    // ends a try block without catch clauses, forwards exceptions to the label
+    #[tag = 0x18] Delegate(Idx<Label>),
+    #[tag = 0x08] Throw(Idx<Tag>),
+    // label must refer to a catch or catch_all block
+    #[tag = 0x09] Rethrow(Idx<Label>),
+
     #[tag = 0x0c] Br(Idx<Label>),
     #[tag = 0x0d] BrIf(Idx<Label>),
     #[tag = 0x0e] BrTable(Vec<Idx<Label>>, Idx<Label>),
 
     #[tag = 0x0f] Return,
     #[tag = 0x10] Call(Idx<Function>),
-    #[tag = 0x11] CallIndirect(Idx<FunctionType>, /* unused, always 0x00 in WASM version 1 */ Idx<Table>),
+    #[tag = 0x11] CallIndirect(Idx<FunctionType>, /* always 0x00 without reference types */ Idx<Table>),
+    // tail calls
+    #[tag = 0x12] ReturnCall(Idx<Function>),
+    #[tag = 0x13] ReturnCallIndirect(Idx<FunctionType>, Idx<Table>),
 
     #[tag = 0x1a] Drop,
     #[tag = 0x1b] Select,
+    // reference types: explicitly typed select (required for reference operands), always one type
+    #[tag = 0x1c] TypedSelect(Vec<ValType>),
 
     #[tag = 0x20] GetLocal(Idx<Local>),
     #[tag = 0x21] SetLocal(Idx<Local>),
@@ -138,32 +380,35 @@ pub enum Instr {
     #[tag = 0x23] GetGlobal(Idx<Global>),
     #[tag = 0x24] SetGlobal(Idx<Global>),
 
-    #[tag = 0x28] I32Load(Memarg),
-    #[tag = 0x29] I64Load(Memarg),
-    #[tag = 0x2a] F32Load(Memarg),
-    #[tag = 0x2b] F64Load(Memarg),
-    #[tag = 0x2c] I32Load8S(Memarg),
-    #[tag = 0x2d] I32Load8U(Memarg),
-    #[tag = 0x2e] I32Load16S(Memarg),
-    #[tag = 0x2f] I32Load16U(Memarg),
-    #[tag = 0x30] I64Load8S(Memarg),
-    #[tag = 0x31] I64Load8U(Memarg),
-    #[tag = 0x32] I64Load16S(Memarg),
-    #[tag = 0x33] I64Load16U(Memarg),
-    #[tag = 0x34] I64Load32S(Memarg),
-    #[tag = 0x35] I64Load32U(Memarg),
-    #[tag = 0x36] I32Store(Memarg),
-    #[tag = 0x37] I64Store(Memarg),
-    #[tag = 0x38] F32Store(Memarg),
-    #[tag = 0x39] F64Store(Memarg),
-    #[tag = 0x3a] I32Store8(Memarg),
-    #[tag = 0x3b] I32Store16(Memarg),
-    #[tag = 0x3c] I64Store8(Memarg),
-    #[tag = 0x3d] I64Store16(Memarg),
-    #[tag = 0x3e] I64Store32(Memarg),
-
-    #[tag = 0x3f] MemorySize(/* unused, always 0x00 in WASM version 1 */ Idx<Memory>),
-    #[tag = 0x40] MemoryGrow(/* unused, always 0x00 in WASM version 1 */ Idx<Memory>),
+    #[tag = 0x25] TableGet(Idx<Table>),
+    #[tag = 0x26] TableSet(Idx<Table>),
+
+    #[tag = 0x28] I32Load(MemoryMemarg),
+    #[tag = 0x29] I64Load(MemoryMemarg),
+    #[tag = 0x2a] F32Load(MemoryMemarg),
+    #[tag = 0x2b] F64Load(MemoryMemarg),
+    #[tag = 0x2c] I32Load8S(MemoryMemarg),
+    #[tag = 0x2d] I32Load8U(MemoryMemarg),
+    #[tag = 0x2e] I32Load16S(MemoryMemarg),
+    #[tag = 0x2f] I32Load16U(MemoryMemarg),
+    #[tag = 0x30] I64Load8S(MemoryMemarg),
+    #[tag = 0x31] I64Load8U(MemoryMemarg),
+    #[tag = 0x32] I64Load16S(MemoryMemarg),
+    #[tag = 0x33] I64Load16U(MemoryMemarg),
+    #[tag = 0x34] I64Load32S(MemoryMemarg),
+    #[tag = 0x35] I64Load32U(MemoryMemarg),
+    #[tag = 0x36] I32Store(MemoryMemarg),
+    #[tag = 0x37] I64Store(MemoryMemarg),
+    #[tag = 0x38] F32Store(MemoryMemarg),
+    #[tag = 0x39] F64Store(MemoryMemarg),
+    #[tag = 0x3a] I32Store8(MemoryMemarg),
+    #[tag = 0x3b] I32Store16(MemoryMemarg),
+    #[tag = 0x3c] I64Store8(MemoryMemarg),
+    #[tag = 0x3d] I64Store16(MemoryMemarg),
+    #[tag = 0x3e] I64Store32(MemoryMemarg),
+
+    #[tag = 0x3f] MemorySize(/* always 0x00 without multi-memory */ Idx<Memory>),
+    #[tag = 0x40] MemoryGrow(/* always 0x00 without multi-memory */ Idx<Memory>),
 
     #[tag = 0x41] I32Const(i32),
     #[tag = 0x42] I64Const(i64),
@@ -293,4 +538,371 @@ pub enum Instr {
     #[tag = 0xbd] I64ReinterpretF64,
     #[tag = 0xbe] F32ReinterpretI32,
     #[tag = 0xbf] F64ReinterpretI64,
-}
\ No newline at end of file
+
+    // sign-extension operators
+    #[tag = 0xc0] I32Extend8S,
+    #[tag = 0xc1] I32Extend16S,
+    #[tag = 0xc2] I64Extend8S,
+    #[tag = 0xc3] I64Extend16S,
+    #[tag = 0xc4] I64Extend32S,
+
+    // reference types
+    #[tag = 0xd0] RefNull(ElemType),
+    #[tag = 0xd1] RefIsNull,
+    #[tag = 0xd2] RefFunc(Idx<Function>),
+
+    #[tag = 0xfc] Misc(MiscInstr),
+    #[tag = 0xfd] Simd(SimdInstr),
+    #[tag = 0xfe] Atomic(AtomicInstr),
+}
+
+/// Instructions with the 0xFC prefix byte. All defined sub-opcodes are < 0x80 and thus encoded as
+/// a single byte, but the sub-opcode is a LEB128 u32, so decoding must also accept padded ones.
+#[derive(WasmBinary, Debug, Clone)]
+#[leb128_tags]
+pub enum MiscInstr {
+    // non-trapping (saturating) float-to-int conversions
+    #[tag = 0x00] I32TruncSatSF32,
+    #[tag = 0x01] I32TruncSatUF32,
+    #[tag = 0x02] I32TruncSatSF64,
+    #[tag = 0x03] I32TruncSatUF64,
+    #[tag = 0x04] I64TruncSatSF32,
+    #[tag = 0x05] I64TruncSatUF32,
+    #[tag = 0x06] I64TruncSatSF64,
+    #[tag = 0x07] I64TruncSatUF64,
+
+    // bulk memory operations
+    #[tag = 0x08] MemoryInit(Idx<Data>, /* always 0x00 without multi-memory */ Idx<Memory>),
+    #[tag = 0x09] DataDrop(Idx<Data>),
+    #[tag = 0x0a] MemoryCopy(/* destination */ Idx<Memory>, /* source */ Idx<Memory>),
+    #[tag = 0x0b] MemoryFill(Idx<Memory>),
+    #[tag = 0x0c] TableInit(Idx<Element>, Idx<Table>),
+    #[tag = 0x0d] ElemDrop(Idx<Element>),
+    #[tag = 0x0e] TableCopy(/* destination */ Idx<Table>, /* source */ Idx<Table>),
+
+    // reference types
+    #[tag = 0x0f] TableGrow(Idx<Table>),
+    #[tag = 0x10] TableSize(Idx<Table>),
+    #[tag = 0x11] TableFill(Idx<Table>),
+}
+
+/// Instructions with the 0xFD prefix byte (SIMD proposal). Unlike for MiscInstr, many sub-opcodes
+/// are >= 0x80 and thus take two bytes as LEB128.
+#[derive(WasmBinary, Debug, Clone)]
+#[leb128_tags]
+pub enum SimdInstr {
+    #[tag = 0x00] V128Load(MemoryMemarg),
+    #[tag = 0x01] V128Load8x8S(MemoryMemarg),
+    #[tag = 0x02] V128Load8x8U(MemoryMemarg),
+    #[tag = 0x03] V128Load16x4S(MemoryMemarg),
+    #[tag = 0x04] V128Load16x4U(MemoryMemarg),
+    #[tag = 0x05] V128Load32x2S(MemoryMemarg),
+    #[tag = 0x06] V128Load32x2U(MemoryMemarg),
+    #[tag = 0x07] V128Load8Splat(MemoryMemarg),
+    #[tag = 0x08] V128Load16Splat(MemoryMemarg),
+    #[tag = 0x09] V128Load32Splat(MemoryMemarg),
+    #[tag = 0x0a] V128Load64Splat(MemoryMemarg),
+    #[tag = 0x0b] V128Store(MemoryMemarg),
+    #[tag = 0x0c] V128Const(u128),
+    #[tag = 0x0d] I8x16Shuffle(ShuffleLanes),
+    #[tag = 0x0e] I8x16Swizzle,
+    #[tag = 0x0f] I8x16Splat,
+    #[tag = 0x10] I16x8Splat,
+    #[tag = 0x11] I32x4Splat,
+    #[tag = 0x12] I64x2Splat,
+    #[tag = 0x13] F32x4Splat,
+    #[tag = 0x14] F64x2Splat,
+    #[tag = 0x15] I8x16ExtractLaneS(/* lane */ u8),
+    #[tag = 0x16] I8x16ExtractLaneU(/* lane */ u8),
+    #[tag = 0x17] I8x16ReplaceLane(/* lane */ u8),
+    #[tag = 0x18] I16x8ExtractLaneS(/* lane */ u8),
+    #[tag = 0x19] I16x8ExtractLaneU(/* lane */ u8),
+    #[tag = 0x1a] I16x8ReplaceLane(/* lane */ u8),
+    #[tag = 0x1b] I32x4ExtractLane(/* lane */ u8),
+    #[tag = 0x1c] I32x4ReplaceLane(/* lane */ u8),
+    #[tag = 0x1d] I64x2ExtractLane(/* lane */ u8),
+    #[tag = 0x1e] I64x2ReplaceLane(/* lane */ u8),
+    #[tag = 0x1f] F32x4ExtractLane(/* lane */ u8),
+    #[tag = 0x20] F32x4ReplaceLane(/* lane */ u8),
+    #[tag = 0x21] F64x2ExtractLane(/* lane */ u8),
+    #[tag = 0x22] F64x2ReplaceLane(/* lane */ u8),
+    #[tag = 0x23] I8x16Eq,
+    #[tag = 0x24] I8x16Ne,
+    #[tag = 0x25] I8x16LtS,
+    #[tag = 0x26] I8x16LtU,
+    #[tag = 0x27] I8x16GtS,
+    #[tag = 0x28] I8x16GtU,
+    #[tag = 0x29] I8x16LeS,
+    #[tag = 0x2a] I8x16LeU,
+    #[tag = 0x2b] I8x16GeS,
+    #[tag = 0x2c] I8x16GeU,
+    #[tag = 0x2d] I16x8Eq,
+    #[tag = 0x2e] I16x8Ne,
+    #[tag = 0x2f] I16x8LtS,
+    #[tag = 0x30] I16x8LtU,
+    #[tag = 0x31] I16x8GtS,
+    #[tag = 0x32] I16x8GtU,
+    #[tag = 0x33] I16x8LeS,
+    #[tag = 0x34] I16x8LeU,
+    #[tag = 0x35] I16x8GeS,
+    #[tag = 0x36] I16x8GeU,
+    #[tag = 0x37] I32x4Eq,
+    #[tag = 0x38] I32x4Ne,
+    #[tag = 0x39] I32x4LtS,
+    #[tag = 0x3a] I32x4LtU,
+    #[tag = 0x3b] I32x4GtS,
+    #[tag = 0x3c] I32x4GtU,
+    #[tag = 0x3d] I32x4LeS,
+    #[tag = 0x3e] I32x4LeU,
+    #[tag = 0x3f] I32x4GeS,
+    #[tag = 0x40] I32x4GeU,
+    #[tag = 0x41] F32x4Eq,
+    #[tag = 0x42] F32x4Ne,
+    #[tag = 0x43] F32x4Lt,
+    #[tag = 0x44] F32x4Gt,
+    #[tag = 0x45] F32x4Le,
+    #[tag = 0x46] F32x4Ge,
+    #[tag = 0x47] F64x2Eq,
+    #[tag = 0x48] F64x2Ne,
+    #[tag = 0x49] F64x2Lt,
+    #[tag = 0x4a] F64x2Gt,
+    #[tag = 0x4b] F64x2Le,
+    #[tag = 0x4c] F64x2Ge,
+    #[tag = 0x4d] V128Not,
+    #[tag = 0x4e] V128And,
+    #[tag = 0x4f] V128Andnot,
+    #[tag = 0x50] V128Or,
+    #[tag = 0x51] V128Xor,
+    #[tag = 0x52] V128Bitselect,
+    #[tag = 0x53] V128AnyTrue,
+    #[tag = 0x54] V128Load8Lane(MemoryMemarg, /* lane */ u8),
+    #[tag = 0x55] V128Load16Lane(MemoryMemarg, /* lane */ u8),
+    #[tag = 0x56] V128Load32Lane(MemoryMemarg, /* lane */ u8),
+    #[tag = 0x57] V128Load64Lane(MemoryMemarg, /* lane */ u8),
+    #[tag = 0x58] V128Store8Lane(MemoryMemarg, /* lane */ u8),
+    #[tag = 0x59] V128Store16Lane(MemoryMemarg, /* lane */ u8),
+    #[tag = 0x5a] V128Store32Lane(MemoryMemarg, /* lane */ u8),
+    #[tag = 0x5b] V128Store64Lane(MemoryMemarg, /* lane */ u8),
+    #[tag = 0x5c] V128Load32Zero(MemoryMemarg),
+    #[tag = 0x5d] V128Load64Zero(MemoryMemarg),
+    #[tag = 0x5e] F32x4DemoteF64x2Zero,
+    #[tag = 0x5f] F64x2PromoteLowF32x4,
+    #[tag = 0x60] I8x16Abs,
+    #[tag = 0x61] I8x16Neg,
+    #[tag = 0x62] I8x16Popcnt,
+    #[tag = 0x63] I8x16AllTrue,
+    #[tag = 0x64] I8x16Bitmask,
+    #[tag = 0x65] I8x16NarrowI16x8S,
+    #[tag = 0x66] I8x16NarrowI16x8U,
+    #[tag = 0x67] F32x4Ceil,
+    #[tag = 0x68] F32x4Floor,
+    #[tag = 0x69] F32x4Trunc,
+    #[tag = 0x6a] F32x4Nearest,
+    #[tag = 0x6b] I8x16Shl,
+    #[tag = 0x6c] I8x16ShrS,
+    #[tag = 0x6d] I8x16ShrU,
+    #[tag = 0x6e] I8x16Add,
+    #[tag = 0x6f] I8x16AddSatS,
+    #[tag = 0x70] I8x16AddSatU,
+    #[tag = 0x71] I8x16Sub,
+    #[tag = 0x72] I8x16SubSatS,
+    #[tag = 0x73] I8x16SubSatU,
+    #[tag = 0x74] F64x2Ceil,
+    #[tag = 0x75] F64x2Floor,
+    #[tag = 0x76] I8x16MinS,
+    #[tag = 0x77] I8x16MinU,
+    #[tag = 0x78] I8x16MaxS,
+    #[tag = 0x79] I8x16MaxU,
+    #[tag = 0x7a] F64x2Trunc,
+    #[tag = 0x7b] I8x16AvgrU,
+    #[tag = 0x7c] I16x8ExtaddPairwiseI8x16S,
+    #[tag = 0x7d] I16x8ExtaddPairwiseI8x16U,
+    #[tag = 0x7e] I32x4ExtaddPairwiseI16x8S,
+    #[tag = 0x7f] I32x4ExtaddPairwiseI16x8U,
+    #[tag = 0x80] I16x8Abs,
+    #[tag = 0x81] I16x8Neg,
+    #[tag = 0x82] I16x8Q15mulrSatS,
+    #[tag = 0x83] I16x8AllTrue,
+    #[tag = 0x84] I16x8Bitmask,
+    #[tag = 0x85] I16x8NarrowI32x4S,
+    #[tag = 0x86] I16x8NarrowI32x4U,
+    #[tag = 0x87] I16x8ExtendLowI8x16S,
+    #[tag = 0x88] I16x8ExtendHighI8x16S,
+    #[tag = 0x89] I16x8ExtendLowI8x16U,
+    #[tag = 0x8a] I16x8ExtendHighI8x16U,
+    #[tag = 0x8b] I16x8Shl,
+    #[tag = 0x8c] I16x8ShrS,
+    #[tag = 0x8d] I16x8ShrU,
+    #[tag = 0x8e] I16x8Add,
+    #[tag = 0x8f] I16x8AddSatS,
+    #[tag = 0x90] I16x8AddSatU,
+    #[tag = 0x91] I16x8Sub,
+    #[tag = 0x92] I16x8SubSatS,
+    #[tag = 0x93] I16x8SubSatU,
+    #[tag = 0x94] F64x2Nearest,
+    #[tag = 0x95] I16x8Mul,
+    #[tag = 0x96] I16x8MinS,
+    #[tag = 0x97] I16x8MinU,
+    #[tag = 0x98] I16x8MaxS,
+    #[tag = 0x99] I16x8MaxU,
+    #[tag = 0x9b] I16x8AvgrU,
+    #[tag = 0x9c] I16x8ExtmulLowI8x16S,
+    #[tag = 0x9d] I16x8ExtmulHighI8x16S,
+    #[tag = 0x9e] I16x8ExtmulLowI8x16U,
+    #[tag = 0x9f] I16x8ExtmulHighI8x16U,
+    #[tag = 0xa0] I32x4Abs,
+    #[tag = 0xa1] I32x4Neg,
+    #[tag = 0xa3] I32x4AllTrue,
+    #[tag = 0xa4] I32x4Bitmask,
+    #[tag = 0xa7] I32x4ExtendLowI16x8S,
+    #[tag = 0xa8] I32x4ExtendHighI16x8S,
+    #[tag = 0xa9] I32x4ExtendLowI16x8U,
+    #[tag = 0xaa] I32x4ExtendHighI16x8U,
+    #[tag = 0xab] I32x4Shl,
+    #[tag = 0xac] I32x4ShrS,
+    #[tag = 0xad] I32x4ShrU,
+    #[tag = 0xae] I32x4Add,
+    #[tag = 0xb1] I32x4Sub,
+    #[tag = 0xb5] I32x4Mul,
+    #[tag = 0xb6] I32x4MinS,
+    #[tag = 0xb7] I32x4MinU,
+    #[tag = 0xb8] I32x4MaxS,
+    #[tag = 0xb9] I32x4MaxU,
+    #[tag = 0xba] I32x4DotI16x8S,
+    #[tag = 0xbc] I32x4ExtmulLowI16x8S,
+    #[tag = 0xbd] I32x4ExtmulHighI16x8S,
+    #[tag = 0xbe] I32x4ExtmulLowI16x8U,
+    #[tag = 0xbf] I32x4ExtmulHighI16x8U,
+    #[tag = 0xc0] I64x2Abs,
+    #[tag = 0xc1] I64x2Neg,
+    #[tag = 0xc3] I64x2AllTrue,
+    #[tag = 0xc4] I64x2Bitmask,
+    #[tag = 0xc7] I64x2ExtendLowI32x4S,
+    #[tag = 0xc8] I64x2ExtendHighI32x4S,
+    #[tag = 0xc9] I64x2ExtendLowI32x4U,
+    #[tag = 0xca] I64x2ExtendHighI32x4U,
+    #[tag = 0xcb] I64x2Shl,
+    #[tag = 0xcc] I64x2ShrS,
+    #[tag = 0xcd] I64x2ShrU,
+    #[tag = 0xce] I64x2Add,
+    #[tag = 0xd1] I64x2Sub,
+    #[tag = 0xd5] I64x2Mul,
+    #[tag = 0xd6] I64x2Eq,
+    #[tag = 0xd7] I64x2Ne,
+    #[tag = 0xd8] I64x2LtS,
+    #[tag = 0xd9] I64x2GtS,
+    #[tag = 0xda] I64x2LeS,
+    #[tag = 0xdb] I64x2GeS,
+    #[tag = 0xdc] I64x2ExtmulLowI32x4S,
+    #[tag = 0xdd] I64x2ExtmulHighI32x4S,
+    #[tag = 0xde] I64x2ExtmulLowI32x4U,
+    #[tag = 0xdf] I64x2ExtmulHighI32x4U,
+    #[tag = 0xe0] F32x4Abs,
+    #[tag = 0xe1] F32x4Neg,
+    #[tag = 0xe3] F32x4Sqrt,
+    #[tag = 0xe4] F32x4Add,
+    #[tag = 0xe5] F32x4Sub,
+    #[tag = 0xe6] F32x4Mul,
+    #[tag = 0xe7] F32x4Div,
+    #[tag = 0xe8] F32x4Min,
+    #[tag = 0xe9] F32x4Max,
+    #[tag = 0xea] F32x4Pmin,
+    #[tag = 0xeb] F32x4Pmax,
+    #[tag = 0xec] F64x2Abs,
+    #[tag = 0xed] F64x2Neg,
+    #[tag = 0xef] F64x2Sqrt,
+    #[tag = 0xf0] F64x2Add,
+    #[tag = 0xf1] F64x2Sub,
+    #[tag = 0xf2] F64x2Mul,
+    #[tag = 0xf3] F64x2Div,
+    #[tag = 0xf4] F64x2Min,
+    #[tag = 0xf5] F64x2Max,
+    #[tag = 0xf6] F64x2Pmin,
+    #[tag = 0xf7] F64x2Pmax,
+    #[tag = 0xf8] I32x4TruncSatF32x4S,
+    #[tag = 0xf9] I32x4TruncSatF32x4U,
+    #[tag = 0xfa] F32x4ConvertI32x4S,
+    #[tag = 0xfb] F32x4ConvertI32x4U,
+    #[tag = 0xfc] I32x4TruncSatF64x2SZero,
+    #[tag = 0xfd] I32x4TruncSatF64x2UZero,
+    #[tag = 0xfe] F64x2ConvertLowI32x4S,
+    #[tag = 0xff] F64x2ConvertLowI32x4U,
+}
+
+/// lane indices of i8x16.shuffle, type alias such that derive(WasmBinary) can name the array type
+pub type ShuffleLanes = [u8; 16];
+
+/// Instructions with the 0xFE prefix byte (threads proposal). As for MiscInstr, the sub-opcode is
+/// a LEB128 u32 (even though all are < 0x80).
+#[derive(WasmBinary, Debug, Clone)]
+#[leb128_tags]
+pub enum AtomicInstr {
+    #[tag = 0x00] MemoryAtomicNotify(MemoryMemarg),
+    #[tag = 0x01] MemoryAtomicWait32(MemoryMemarg),
+    #[tag = 0x02] MemoryAtomicWait64(MemoryMemarg),
+    #[tag = 0x03] AtomicFence(/* reserved for memory orderings, always 0x00 */ u8),
+
+    #[tag = 0x10] I32AtomicLoad(MemoryMemarg),
+    #[tag = 0x11] I64AtomicLoad(MemoryMemarg),
+    #[tag = 0x12] I32AtomicLoad8U(MemoryMemarg),
+    #[tag = 0x13] I32AtomicLoad16U(MemoryMemarg),
+    #[tag = 0x14] I64AtomicLoad8U(MemoryMemarg),
+    #[tag = 0x15] I64AtomicLoad16U(MemoryMemarg),
+    #[tag = 0x16] I64AtomicLoad32U(MemoryMemarg),
+    #[tag = 0x17] I32AtomicStore(MemoryMemarg),
+    #[tag = 0x18] I64AtomicStore(MemoryMemarg),
+    #[tag = 0x19] I32AtomicStore8(MemoryMemarg),
+    #[tag = 0x1a] I32AtomicStore16(MemoryMemarg),
+    #[tag = 0x1b] I64AtomicStore8(MemoryMemarg),
+    #[tag = 0x1c] I64AtomicStore16(MemoryMemarg),
+    #[tag = 0x1d] I64AtomicStore32(MemoryMemarg),
+    #[tag = 0x1e] I32AtomicRmwAdd(MemoryMemarg),
+    #[tag = 0x1f] I64AtomicRmwAdd(MemoryMemarg),
+    #[tag = 0x20] I32AtomicRmw8AddU(MemoryMemarg),
+    #[tag = 0x21] I32AtomicRmw16AddU(MemoryMemarg),
+    #[tag = 0x22] I64AtomicRmw8AddU(MemoryMemarg),
+    #[tag = 0x23] I64AtomicRmw16AddU(MemoryMemarg),
+    #[tag = 0x24] I64AtomicRmw32AddU(MemoryMemarg),
+    #[tag = 0x25] I32AtomicRmwSub(MemoryMemarg),
+    #[tag = 0x26] I64AtomicRmwSub(MemoryMemarg),
+    #[tag = 0x27] I32AtomicRmw8SubU(MemoryMemarg),
+    #[tag = 0x28] I32AtomicRmw16SubU(MemoryMemarg),
+    #[tag = 0x29] I64AtomicRmw8SubU(MemoryMemarg),
+    #[tag = 0x2a] I64AtomicRmw16SubU(MemoryMemarg),
+    #[tag = 0x2b] I64AtomicRmw32SubU(MemoryMemarg),
+    #[tag = 0x2c] I32AtomicRmwAnd(MemoryMemarg),
+    #[tag = 0x2d] I64AtomicRmwAnd(MemoryMemarg),
+    #[tag = 0x2e] I32AtomicRmw8AndU(MemoryMemarg),
+    #[tag = 0x2f] I32AtomicRmw16AndU(MemoryMemarg),
+    #[tag = 0x30] I64AtomicRmw8AndU(MemoryMemarg),
+    #[tag = 0x31] I64AtomicRmw16AndU(MemoryMemarg),
+    #[tag = 0x32] I64AtomicRmw32AndU(MemoryMemarg),
+    #[tag = 0x33] I32AtomicRmwOr(MemoryMemarg),
+    #[tag = 0x34] I64AtomicRmwOr(MemoryMemarg),
+    #[tag = 0x35] I32AtomicRmw8OrU(MemoryMemarg),
+    #[tag = 0x36] I32AtomicRmw16OrU(MemoryMemarg),
+    #[tag = 0x37] I64AtomicRmw8OrU(MemoryMemarg),
+    #[tag = 0x38] I64AtomicRmw16OrU(MemoryMemarg),
+    #[tag = 0x39] I64AtomicRmw32OrU(MemoryMemarg),
+    #[tag = 0x3a] I32AtomicRmwXor(MemoryMemarg),
+    #[tag = 0x3b] I64AtomicRmwXor(MemoryMemarg),
+    #[tag = 0x3c] I32AtomicRmw8XorU(MemoryMemarg),
+    #[tag = 0x3d] I32AtomicRmw16XorU(MemoryMemarg),
+    #[tag = 0x3e] I64AtomicRmw8XorU(MemoryMemarg),
+    #[tag = 0x3f] I64AtomicRmw16XorU(MemoryMemarg),
+    #[tag = 0x40] I64AtomicRmw32XorU(MemoryMemarg),
+    #[tag = 0x41] I32AtomicRmwXchg(MemoryMemarg),
+    #[tag = 0x42] I64AtomicRmwXchg(MemoryMemarg),
+    #[tag = 0x43] I32AtomicRmw8XchgU(MemoryMemarg),
+    #[tag = 0x44] I32AtomicRmw16XchgU(MemoryMemarg),
+    #[tag = 0x45] I64AtomicRmw8XchgU(MemoryMemarg),
+    #[tag = 0x46] I64AtomicRmw16XchgU(MemoryMemarg),
+    #[tag = 0x47] I64AtomicRmw32XchgU(MemoryMemarg),
+    #[tag = 0x48] I32AtomicRmwCmpxchg(MemoryMemarg),
+    #[tag = 0x49] I64AtomicRmwCmpxchg(MemoryMemarg),
+    #[tag = 0x4a] I32AtomicRmw8CmpxchgU(MemoryMemarg),
+    #[tag = 0x4b] I32AtomicRmw16CmpxchgU(MemoryMemarg),
+    #[tag = 0x4c] I64AtomicRmw8CmpxchgU(MemoryMemarg),
+    #[tag = 0x4d] I64AtomicRmw16CmpxchgU(MemoryMemarg),
+    #[tag = 0x4e] I64AtomicRmw32CmpxchgU(MemoryMemarg),
+}
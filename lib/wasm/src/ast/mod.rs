@@ -4,4 +4,5 @@ pub mod lowlevel;
 mod common;
 pub use self::common::*;
 
-mod convert;
\ No newline at end of file
+mod convert;
+pub mod remap;
\ No newline at end of file
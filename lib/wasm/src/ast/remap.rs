@@ -0,0 +1,151 @@
+use std::collections::HashMap;
+use std::io;
+use super::*;
+use super::lowlevel as ll;
+use WasmBinary;
+
+/*
+ * Rewriting of indices in custom sections that refer to functions, globals, or tables.
+ * Needed because the index spaces change when converting from high-level to low-level modules:
+ * all imports must come before the defined elements, e.g., the hook imports that Wasabi appends
+ * to the end of the function list shift all defined functions.
+ */
+
+/// Maps old (high-level) indices to new (low-level) indices for each index space.
+/// Local indices are not part of this, because they never change during conversion (parameters
+/// come first and new locals are only ever appended).
+#[derive(Debug, Default)]
+pub struct IndexRemapping {
+    pub(crate) function_idx: HashMap<usize, usize>,
+    pub(crate) table_idx: HashMap<usize, usize>,
+    pub(crate) memory_idx: HashMap<usize, usize>,
+    pub(crate) global_idx: HashMap<usize, usize>,
+    pub(crate) tag_idx: HashMap<usize, usize>,
+}
+
+macro_rules! remap_fn {
+    ($name: ident, $field: ident) => {
+        /// unknown indices are left unchanged, since custom sections are not validated by
+        /// engines, so they might contain garbage anyway
+        pub fn $name<T>(&self, idx: Idx<T>) -> Idx<T> {
+            self.$field.get(&idx.0).cloned().unwrap_or(idx.0).into()
+        }
+    };
+}
+
+impl IndexRemapping {
+    remap_fn!(function, function_idx);
+    remap_fn!(table, table_idx);
+    remap_fn!(memory, memory_idx);
+    remap_fn!(global, global_idx);
+    remap_fn!(tag, tag_idx);
+}
+
+pub trait RemapIndices {
+    fn remap_indices(&mut self, remapping: &IndexRemapping);
+}
+
+impl RemapIndices for ll::NameSection {
+    fn remap_indices(&mut self, remapping: &IndexRemapping) {
+        for subsection in &mut self.subsections {
+            // name maps must be sorted by index, so sort again after remapping
+            match *subsection {
+                ll::NameSubSection::Module(_) => {}
+                ll::NameSubSection::Function(ll::WithSize(ref mut function_names)) => {
+                    for function_name in function_names.iter_mut() {
+                        function_name.function_idx = remapping.function(function_name.function_idx);
+                    }
+                    function_names.sort_by_key(|function_name| function_name.function_idx);
+                }
+                ll::NameSubSection::Local(ll::WithSize(ref mut local_names)) => {
+                    for function_locals in local_names.iter_mut() {
+                        function_locals.function_idx = remapping.function(function_locals.function_idx);
+                    }
+                    local_names.sort_by_key(|function_locals| function_locals.function_idx);
+                }
+                ll::NameSubSection::Global(ll::WithSize(ref mut global_names)) => {
+                    for global_name in global_names.iter_mut() {
+                        global_name.global_idx = remapping.global(global_name.global_idx);
+                    }
+                    global_names.sort_by_key(|global_name| global_name.global_idx);
+                }
+            }
+        }
+    }
+}
+
+/// NOTE relocations ("reloc.*" custom sections) refer to symbols, not directly to functions etc.,
+/// so only the symbol table and comdats need to be rewritten (but see check_custom_sections).
+impl RemapIndices for ll::LinkingSection {
+    fn remap_indices(&mut self, remapping: &IndexRemapping) {
+        for subsection in &mut self.subsections {
+            match *subsection {
+                ll::LinkingSubSection::SymbolTable(ref mut symbols) => {
+                    for symbol in symbols.iter_mut() {
+                        match *symbol {
+                            ll::SymbolInfo::Function { ref mut function_idx, .. } => *function_idx = remapping.function(*function_idx),
+                            ll::SymbolInfo::Global { ref mut global_idx, .. } => *global_idx = remapping.global(*global_idx),
+                            ll::SymbolInfo::Table { ref mut table_idx, .. } => *table_idx = remapping.table(*table_idx),
+                            // events are the tags of the exception handling proposal
+                            ll::SymbolInfo::Event { ref mut event_idx, .. } => *event_idx = remapping.tag(Idx::<ll::Tag>::from(*event_idx as usize)).0 as u32,
+                            ll::SymbolInfo::Data { .. } | ll::SymbolInfo::Section { .. } => {}
+                        }
+                    }
+                }
+                ll::LinkingSubSection::ComdatInfo(ref mut comdats) => {
+                    for symbol in comdats.iter_mut().flat_map(|comdat| comdat.symbols.iter_mut()) {
+                        match *symbol {
+                            ll::ComdatSymbol::Function(ref mut function_idx) => *function_idx = remapping.function(*function_idx),
+                            ll::ComdatSymbol::Global(ref mut global_idx) => *global_idx = remapping.global(*global_idx),
+                            ll::ComdatSymbol::Table(ref mut table_idx) => *table_idx = remapping.table(*table_idx),
+                            ll::ComdatSymbol::Event(ref mut event_idx) => *event_idx = remapping.tag(Idx::<ll::Tag>::from(*event_idx as usize)).0 as u32,
+                            ll::ComdatSymbol::Data(_) | ll::ComdatSymbol::Section(_) => {}
+                        }
+                    }
+                }
+                ll::LinkingSubSection::Other(_, _) => {}
+            }
+        }
+    }
+}
+
+/// Rejects custom sections that cannot be kept valid when encoding a high-level module, i.e.,
+/// relocations ("reloc.*"), because they point to byte offsets in other sections, which change
+/// whenever the module is encoded again (and their type indices are not stable either).
+pub fn check_custom_sections(sections: &[ll::Section]) -> io::Result<()> {
+    for section in sections {
+        if let ll::Section::Custom(ref custom_section) = *section {
+            match String::decode(&mut &custom_section[..]) {
+                Ok(ref name) if name.starts_with("reloc.") => return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("relocatable object files are not supported (custom section \"{}\")", name))),
+                _ => {}
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Rewrites all known index-bearing custom sections (given as raw bytes, including the custom
+/// section name). Unknown or malformed custom sections are returned unchanged.
+pub fn remap_custom_section(custom_section: Vec<u8>, remapping: &IndexRemapping) -> Vec<u8> {
+    let remapped = {
+        let mut reader = &custom_section[..];
+        match String::decode(&mut reader) {
+            Ok(ref name) if name == "name" => remap_contents::<ll::NameSection>(name, reader, remapping),
+            Ok(ref name) if name == "linking" => remap_contents::<ll::LinkingSection>(name, reader, remapping),
+            _ => None
+        }
+    };
+    remapped.unwrap_or(custom_section)
+}
+
+fn remap_contents<T: WasmBinary + RemapIndices>(name: &str, mut contents: &[u8], remapping: &IndexRemapping) -> Option<Vec<u8>> {
+    let mut section = T::decode(&mut contents).ok()?;
+    section.remap_indices(remapping);
+
+    let mut bytes = Vec::new();
+    name.to_string().encode(&mut bytes).ok()?;
+    section.encode(&mut bytes).ok()?;
+    Some(bytes)
+}
@@ -1,12 +1,18 @@
 use ast::*;
 use ast::lowlevel::*;
+use ast::highlevel::{self, CodeOffsets, FunctionOffsets};
 use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
 use leb128::*;
 use rayon::prelude::*;
+use std::collections::HashMap;
 use std::error::Error;
+use std::fmt;
 use std::io;
+use std::io::Read;
 use std::marker::PhantomData;
 use std::mem::size_of;
+use std::ops::Range;
+use std::str;
 
 /* Trait and impl for decoding/encoding between binary format (as per spec) and our own formats (see ast module) */
 
@@ -14,6 +20,17 @@ use std::mem::size_of;
 pub trait WasmBinary: Sized {
     fn decode<R: io::Read>(reader: &mut R) -> io::Result<Self>;
     fn encode<W: io::Write>(&self, writer: &mut W) -> io::Result<usize>;
+    /// number of bytes that encode() writes, but without encoding, such that sizes (e.g., of
+    /// sections and function bodies, see WithSize) can be written before the contents
+    fn encoded_size(&self) -> usize;
+
+    /// encode() preceded by the size of the contents as LEB128 u32, see WithSize
+    /// types with nested WithSize overwrite this, such that the sizes are computed only once
+    fn encode_with_size<W: io::Write>(&self, writer: &mut W) -> io::Result<usize> {
+        let size = self.encoded_size();
+        let bytes_written = size.encode(writer)?;
+        Ok(bytes_written + check_encoded_size(size, self.encode(writer)?)?)
+    }
 
     /// convenience method
     fn error<E>(reason: E) -> io::Result<Self>
@@ -21,6 +38,221 @@ pub trait WasmBinary: Sized {
     {
         Err(io::Error::new(io::ErrorKind::InvalidData, reason))
     }
+
+    /// convenience method for unknown enum tags, the error offset points to the tag byte itself
+    fn tag_error(type_name: &str, byte: u8) -> io::Result<Self> {
+        let mut error = DecodeError::new(format!("expected tag for {}, got 0x{:02x}", type_name, byte));
+        error.bytes_after = 1;
+        Err(io::Error::new(io::ErrorKind::InvalidData, error))
+    }
+
+    /// same for enums with #[leb128_tags], the error offset points to the start of the tag
+    fn leb128_tag_error(type_name: &str, tag: u32) -> io::Result<Self> {
+        let mut error = DecodeError::new(format!("expected tag for {}, got 0x{:x}", type_name, tag));
+        let mut rest = tag >> 7;
+        while rest != 0 {
+            error.bytes_after += 1;
+            rest >>= 7;
+        }
+        error.bytes_after += 1;
+        Err(io::Error::new(io::ErrorKind::InvalidData, error))
+    }
+
+    /// convenience method, records the AST field (e.g., "Global.init") that was being decoded
+    /// when the error happened, used by derive(WasmBinary)
+    fn field_context(error: io::Error, field: &str) -> io::Error {
+        DecodeError::add_context(error, |error| error.path.insert(0, field.to_string()))
+    }
+}
+
+
+/* Errors during decoding, with context where in the binary they happened */
+
+#[derive(Debug)]
+pub struct DecodeError {
+    /// in the whole module, is known only after the error propagated up to Module::decode.
+    /// For malformed multi-byte values (e.g., LEB128 overflow) it points right after the value.
+    pub offset: Option<usize>,
+    /// section id, see Section
+    pub section: Option<u8>,
+    /// only inside the code section (low-level index, i.e., including imported functions)
+    pub function: Option<Idx<Function>>,
+    /// AST fields and vector elements that were being decoded, outermost first
+    pub path: Vec<String>,
+    pub message: String,
+    // how many bytes the current reader has read beyond the error, e.g., because a whole
+    // function body was read into a buffer before decoding it. Updated while the error
+    // propagates up, such that Module::decode can compute the absolute offset.
+    bytes_after: usize,
+}
+
+impl DecodeError {
+    fn new(message: String) -> Self {
+        DecodeError {
+            offset: None,
+            section: None,
+            function: None,
+            path: Vec::new(),
+            message,
+            bytes_after: 0,
+        }
+    }
+
+    /// wraps any io::Error (e.g., from ReadBytesExt or LEB128 decoding) into a DecodeError if it
+    /// is not one already, then lets f add context to it. The io::ErrorKind is kept, since some
+    /// decoders check for UnexpectedEof.
+    fn add_context<F: FnOnce(&mut DecodeError)>(mut error: io::Error, f: F) -> io::Error {
+        let is_decode_error = error.get_ref().map_or(false, |inner| inner.is::<DecodeError>());
+        if !is_decode_error {
+            error = io::Error::new(error.kind(), DecodeError::new(error.to_string()));
+        }
+        f(error.get_mut().and_then(|inner| inner.downcast_mut::<DecodeError>()).unwrap());
+        error
+    }
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut context = Vec::new();
+        if let Some(offset) = self.offset {
+            context.push(format!("at offset 0x{:x}", offset));
+        }
+        if let Some(section) = self.section {
+            context.push(format!("in {} section", section_name(section)));
+        }
+        if let Some(function) = self.function {
+            context.push(format!("in function #{}", function.0));
+        }
+        if !self.path.is_empty() {
+            context.push(format!("while decoding {}", self.path.join(" > ")));
+        }
+        if !context.is_empty() {
+            write!(f, "{}: ", context.join(", "))?;
+        }
+        f.write_str(&self.message)
+    }
+}
+
+impl Error for DecodeError {
+    fn description(&self) -> &str { &self.message }
+}
+
+fn section_name(id: u8) -> &'static str {
+    match id {
+        0 => "custom",
+        1 => "type",
+        2 => "import",
+        3 => "function",
+        4 => "table",
+        5 => "memory",
+        6 => "global",
+        7 => "export",
+        8 => "start",
+        9 => "element",
+        10 => "code",
+        11 => "data",
+        12 => "data count",
+        13 => "tag",
+        _ => "unknown",
+    }
+}
+
+/// counts the bytes read so far, for the offsets in DecodeError
+struct CountingReader<R> {
+    reader: R,
+    count: usize,
+}
+
+impl<R: io::Read> io::Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let bytes_read = self.reader.read(buf)?;
+        self.count += bytes_read;
+        Ok(bytes_read)
+    }
+}
+
+/* Strict decoding (see Module::decode_strict), which rejects malformed binaries that the default
+ * decoder accepts for robustness. The mode is a property of the reader, such that it reaches all
+ * (also derived) decode impls without an additional parameter. */
+
+/// marks the reader (and all readers wrapping it, see Strictness) for strict decoding
+pub(crate) struct StrictReader<R>(pub(crate) R);
+
+impl<R: io::Read> io::Read for StrictReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.0.read(buf)
+    }
+}
+
+/// Uses trait specialization: only StrictReader is strict, readers that wrap another one (also
+/// inside decode impls, e.g., Chain to "put back" bytes) forward to it.
+trait Strictness {
+    fn is_strict(&self) -> bool;
+}
+
+impl<R: io::Read + ?Sized> Strictness for R {
+    default fn is_strict(&self) -> bool { false }
+}
+
+impl<R: io::Read> Strictness for StrictReader<R> {
+    fn is_strict(&self) -> bool { true }
+}
+
+impl<'a, R: io::Read + ?Sized> Strictness for &'a mut R {
+    fn is_strict(&self) -> bool { (**self).is_strict() }
+}
+
+impl<R: io::Read> Strictness for CountingReader<R> {
+    fn is_strict(&self) -> bool { self.reader.is_strict() }
+}
+
+impl<A: io::Read, B: io::Read> Strictness for io::Chain<A, B> {
+    fn is_strict(&self) -> bool { self.get_ref().1.is_strict() }
+}
+
+impl<R: io::Read> Strictness for io::Take<R> {
+    fn is_strict(&self) -> bool { self.get_ref().is_strict() }
+}
+
+/// In strict mode, LEB128 integers of the given bit width must not be longer than ceil(bits / 7)
+/// bytes, and the unused bits of the last byte must be zero (unsigned) or the sign (signed).
+fn decode_leb128<T, R>(reader: &mut R, bits: u32, signed: bool) -> io::Result<T>
+    where R: io::Read + ReadLeb128<T>,
+          for<'a> &'a [u8]: ReadLeb128<T>
+{
+    if !reader.is_strict() {
+        return <R as ReadLeb128<T>>::read_leb128(reader);
+    }
+
+    let max_bytes = ((bits + 6) / 7) as usize;
+    let mut bytes = [0u8; 10];
+    let mut len = 0;
+    loop {
+        let byte = reader.read_u8()?;
+        bytes[len] = byte;
+        len += 1;
+        if byte & 0x80 == 0 {
+            break;
+        }
+        if len == max_bytes {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, format!("integer representation too long (more than {} bytes for {} bits)", max_bytes, bits)));
+        }
+    }
+    if len == max_bytes {
+        let used_bits = bits - 7 * (max_bytes as u32 - 1);
+        let last = bytes[len - 1] & 0x7f;
+        let unused_bits_ok = if signed {
+            // the sign bit and the unused bits above it must all be equal
+            let sign_and_unused = last >> (used_bits - 1);
+            sign_and_unused == 0 || sign_and_unused == 0x7f >> (used_bits - 1)
+        } else {
+            last >> used_bits == 0
+        };
+        if !unused_bits_ok {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, format!("integer too large (unused bits set in last byte 0x{:02x} for {} bits)", bytes[len - 1], bits)));
+        }
+    }
+    <&[u8] as ReadLeb128<T>>::read_leb128(&mut &bytes[..len])
 }
 
 
@@ -34,20 +266,33 @@ impl WasmBinary for u8 {
         writer.write_u8(*self)?;
         Ok(1)
     }
+    fn encoded_size(&self) -> usize { 1 }
 }
 
 impl WasmBinary for u32 {
     fn decode<R: io::Read>(reader: &mut R) -> io::Result<Self> {
-        reader.read_leb128()
+        decode_leb128(reader, 32, false)
+    }
+    fn encode<W: io::Write>(&self, writer: &mut W) -> io::Result<usize> {
+        writer.write_leb128(*self)
+    }
+    fn encoded_size(&self) -> usize { self.leb128_size() }
+}
+
+impl WasmBinary for u64 {
+    fn decode<R: io::Read>(reader: &mut R) -> io::Result<Self> {
+        decode_leb128(reader, 64, false)
     }
     fn encode<W: io::Write>(&self, writer: &mut W) -> io::Result<usize> {
         writer.write_leb128(*self)
     }
+    fn encoded_size(&self) -> usize { self.leb128_size() }
 }
 
 impl WasmBinary for usize {
     fn decode<R: io::Read>(reader: &mut R) -> io::Result<Self> {
-        reader.read_leb128()
+        // lengths and indices are u32 in the binary
+        decode_leb128(reader, 32, false)
     }
     fn encode<W: io::Write>(&self, writer: &mut W) -> io::Result<usize> {
         if *self > u32::max_value() as usize {
@@ -55,24 +300,27 @@ impl WasmBinary for usize {
         }
         writer.write_leb128(*self)
     }
+    fn encoded_size(&self) -> usize { self.leb128_size() }
 }
 
 impl WasmBinary for i32 {
     fn decode<R: io::Read>(reader: &mut R) -> io::Result<Self> {
-        reader.read_leb128()
+        decode_leb128(reader, 32, true)
     }
     fn encode<W: io::Write>(&self, writer: &mut W) -> io::Result<usize> {
         writer.write_leb128(*self)
     }
+    fn encoded_size(&self) -> usize { self.leb128_size() }
 }
 
 impl WasmBinary for i64 {
     fn decode<R: io::Read>(reader: &mut R) -> io::Result<Self> {
-        reader.read_leb128()
+        decode_leb128(reader, 64, true)
     }
     fn encode<W: io::Write>(&self, writer: &mut W) -> io::Result<usize> {
         writer.write_leb128(*self)
     }
+    fn encoded_size(&self) -> usize { self.leb128_size() }
 }
 
 impl WasmBinary for f32 {
@@ -83,6 +331,7 @@ impl WasmBinary for f32 {
         writer.write_f32::<LittleEndian>(*self)?;
         Ok(4)
     }
+    fn encoded_size(&self) -> usize { 4 }
 }
 
 impl WasmBinary for f64 {
@@ -93,6 +342,37 @@ impl WasmBinary for f64 {
         writer.write_f64::<LittleEndian>(*self)?;
         Ok(8)
     }
+    fn encoded_size(&self) -> usize { 8 }
+}
+
+/// v128.const immediate, 16 bytes little-endian (not LEB128)
+impl WasmBinary for u128 {
+    fn decode<R: io::Read>(reader: &mut R) -> io::Result<Self> {
+        let mut bytes = [0u8; 16];
+        reader.read_exact(&mut bytes)?;
+        Ok(bytes.iter().rev().fold(0, |value, &byte| value << 8 | byte as u128))
+    }
+    fn encode<W: io::Write>(&self, writer: &mut W) -> io::Result<usize> {
+        for i in 0..16 {
+            writer.write_u8((*self >> (8 * i)) as u8)?;
+        }
+        Ok(16)
+    }
+    fn encoded_size(&self) -> usize { 16 }
+}
+
+/// lane indices of i8x16.shuffle, one byte each
+impl WasmBinary for [u8; 16] {
+    fn decode<R: io::Read>(reader: &mut R) -> io::Result<Self> {
+        let mut lanes = [0u8; 16];
+        reader.read_exact(&mut lanes)?;
+        Ok(lanes)
+    }
+    fn encode<W: io::Write>(&self, writer: &mut W) -> io::Result<usize> {
+        writer.write_all(self)?;
+        Ok(16)
+    }
+    fn encoded_size(&self) -> usize { 16 }
 }
 
 
@@ -100,20 +380,33 @@ impl WasmBinary for f64 {
 
 impl<T: WasmBinary> WasmBinary for WithSize<T> {
     fn decode<R: io::Read>(reader: &mut R) -> io::Result<Self> {
-        let _forget_original_size = u32::decode(reader)?;
-        Ok(WithSize(T::decode(reader)?))
+        let size = u32::decode(reader)?;
+        if !reader.is_strict() {
+            // forget original size
+            return Ok(WithSize(T::decode(reader)?));
+        }
+
+        // strict: the contents must be exactly size bytes long
+        let mut contents = io::Read::take(&mut *reader, size as u64);
+        let decoded = T::decode(&mut contents).map_err(|e| if e.kind() == io::ErrorKind::UnexpectedEof {
+            DecodeError::add_context(e, |e| e.message = "unexpected end of section or function".to_string())
+        } else {
+            e
+        })?;
+        if contents.limit() > 0 {
+            return Self::error(format!("section size mismatch: {} of {} bytes left after the contents", contents.limit(), size));
+        }
+        Ok(WithSize(decoded))
     }
 
     fn encode<W: io::Write>(&self, writer: &mut W) -> io::Result<usize> {
-        let mut buf = Vec::new();
-        let new_size = self.0.encode(&mut buf)?;
-
-        // write new size, then contents from buffer to actual writer
-        let mut bytes_written = new_size.encode(writer)?;
-        writer.write_all(&buf)?;
-        bytes_written += new_size;
+        // compute new size up front, so contents can be written directly without a buffer
+        self.0.encode_with_size(writer)
+    }
 
-        Ok(bytes_written)
+    fn encoded_size(&self) -> usize {
+        let new_size = self.0.encoded_size();
+        new_size.leb128_size() + new_size
     }
 }
 
@@ -122,8 +415,8 @@ impl<T: WasmBinary> WasmBinary for Vec<T> {
         let size = usize::decode(reader)?;
 
         let mut vec: Vec<T> = Vec::with_capacity(size * size_of::<T>());
-        for _ in 0..size {
-            vec.push(T::decode(reader)?);
+        for i in 0..size {
+            vec.push(T::decode(reader).map_err(|e| DecodeError::add_context(e, |e| e.path.insert(0, format!("[{}]", i))))?);
         };
 
         Ok(vec)
@@ -136,15 +429,31 @@ impl<T: WasmBinary> WasmBinary for Vec<T> {
         }
         Ok(bytes_written)
     }
+
+    default fn encoded_size(&self) -> usize {
+        self.len().leb128_size() + self.iter().map(T::encoded_size).sum::<usize>()
+    }
+
+    default fn encode_with_size<W: io::Write>(&self, writer: &mut W) -> io::Result<usize> {
+        let size = self.encoded_size();
+        let bytes_written = size.encode(writer)?;
+        Ok(bytes_written + check_encoded_size(size, self.encode(writer)?)?)
+    }
 }
 
 impl WasmBinary for String {
     fn decode<R: io::Read>(reader: &mut R) -> io::Result<Self> {
         // reuse Vec<u8> implementation, then consume buf so no re-allocation is necessary
+        let strict = reader.is_strict();
         let buf: Vec<u8> = Vec::decode(reader)?;
         String::from_utf8(buf).map_err(|e| io::Error::new(
             io::ErrorKind::InvalidData,
-            format!("utf-8 conversion error: {}", e.to_string())))
+            if strict {
+                // same wording as the spec tests
+                format!("malformed UTF-8 encoding: {}", e.to_string())
+            } else {
+                format!("utf-8 conversion error: {}", e.to_string())
+            }))
     }
 
     fn encode<W: io::Write>(&self, writer: &mut W) -> io::Result<usize> {
@@ -154,27 +463,55 @@ impl WasmBinary for String {
         }
         Ok(bytes_written)
     }
+
+    fn encoded_size(&self) -> usize {
+        self.len().leb128_size() + self.len()
+    }
 }
 
 /// Uses trait specialization (https://github.com/rust-lang/rfcs/blob/master/text/1210-impl-specialization.md)
 /// to provide parallel decoding/encoding (right now only Code section has the necessary Vec<WithSize<T>> structure).
 impl<T: WasmBinary + Send + Sync> WasmBinary for Vec<WithSize<T>> {
     fn decode<R: io::Read>(reader: &mut R) -> io::Result<Self> {
+        let reader = &mut CountingReader { reader, count: 0 };
         let num_elements = usize::decode(reader)?;
 
         // read all elements into buffers of the given size (non-parallel, but hopefully fast)
+        // also remember where each buffer ends, for the offset of decoding errors inside it
         let mut bufs = Vec::with_capacity(num_elements * size_of::<Vec<u8>>());
         for _ in 0..num_elements {
             let num_bytes = usize::decode(reader)?;
             let mut buf = vec![0u8; num_bytes];
             reader.read_exact(&mut buf)?;
-            bufs.push(buf);
+            bufs.push((buf, reader.count));
         }
+        let total_bytes = reader.count;
+        let strict = reader.is_strict();
 
         // parallel decode of each buffer
         let decoded: io::Result<Vec<WithSize<T>>> = bufs.into_par_iter()
-            .map(|buf| -> io::Result<WithSize<T>> {
-                Ok(WithSize(T::decode(&mut &buf[..])?))
+            .enumerate()
+            .map(|(i, (buf, buf_end))| -> io::Result<WithSize<T>> {
+                let mut buf_reader = &buf[..];
+                let element = if strict {
+                    T::decode(&mut StrictReader(&mut buf_reader))
+                } else {
+                    T::decode(&mut buf_reader)
+                };
+                // strict: trailing bytes after an element (e.g., after the end of a function body)
+                let element = element.and_then(|element| if strict && !buf_reader.is_empty() {
+                    Err(io::Error::new(io::ErrorKind::InvalidData, format!("section size mismatch: {} of {} bytes left after the contents", buf_reader.len(), buf.len())))
+                } else {
+                    Ok(element)
+                });
+                element
+                    .map(WithSize)
+                    .map_err(|e| DecodeError::add_context(e, |e| {
+                        e.bytes_after += buf_reader.len() + (total_bytes - buf_end);
+                        e.path.insert(0, format!("[{}]", i));
+                        // right now, the code section is the only Vec<WithSize<T>>, see above
+                        e.function = Some(i.into());
+                    }))
             })
             .collect();
 
@@ -182,24 +519,70 @@ impl<T: WasmBinary + Send + Sync> WasmBinary for Vec<WithSize<T>> {
     }
 
     fn encode<W: io::Write>(&self, writer: &mut W) -> io::Result<usize> {
-        let new_size = self.len();
-        let mut bytes_written = new_size.encode(writer)?;
-
-        // encode elements to buffers in parallel
-        let encoded: io::Result<Vec<Vec<u8>>> = self.par_iter()
-            .map(|element: &WithSize<T>| {
-                let mut buf = Vec::new();
-                element.0.encode(&mut buf)?;
+        encode_with_element_sizes(self, &element_sizes(self), writer)
+    }
+
+    fn encoded_size(&self) -> usize {
+        vec_with_sizes_encoded_size(&element_sizes(self))
+    }
+
+    /// the size of the whole vec depends on the sizes of all elements, so compute those only once
+    /// (instead of once for the size of the vec and again when writing each element)
+    fn encode_with_size<W: io::Write>(&self, writer: &mut W) -> io::Result<usize> {
+        let element_sizes = element_sizes(self);
+        let size = vec_with_sizes_encoded_size(&element_sizes);
+        let bytes_written = size.encode(writer)?;
+        Ok(bytes_written + check_encoded_size(size, encode_with_element_sizes(self, &element_sizes, writer)?)?)
+    }
+}
+
+/// sizes of the contents of each WithSize (in parallel, since these are function bodies, which
+/// make up most of a module), computed only once and then used for encoding, see below
+fn element_sizes<T: WasmBinary + Send + Sync>(vec: &[WithSize<T>]) -> Vec<usize> {
+    vec.par_iter()
+        .map(|element| element.0.encoded_size())
+        .collect()
+}
+
+fn vec_with_sizes_encoded_size(element_sizes: &[usize]) -> usize {
+    element_sizes.len().leb128_size() + element_sizes.iter()
+        .map(|&size| size.leb128_size() + size)
+        .sum::<usize>()
+}
+
+/// number of elements that are encoded in parallel into buffers before these are written out in
+/// order, such that not all function bodies of a module are buffered at the same time
+const PARALLEL_ENCODE_CHUNK_SIZE: usize = 1024;
+
+/// encodes chunks of elements in parallel into one buffer per element, then writes each buffer
+/// (preceded by its already computed size) in the original order
+fn encode_with_element_sizes<T: WasmBinary + Send + Sync, W: io::Write>(vec: &[WithSize<T>], element_sizes: &[usize], writer: &mut W) -> io::Result<usize> {
+    let mut bytes_written = vec.len().encode(writer)?;
+    for (elements, sizes) in vec.chunks(PARALLEL_ENCODE_CHUNK_SIZE).zip(element_sizes.chunks(PARALLEL_ENCODE_CHUNK_SIZE)) {
+        let bufs: io::Result<Vec<Vec<u8>>> = elements.par_iter()
+            .zip(sizes.par_iter())
+            .map(|(element, &size)| {
+                let mut buf = Vec::with_capacity(size);
+                check_encoded_size(size, element.0.encode(&mut buf)?)?;
                 Ok(buf)
             })
             .collect();
-
-        // write sizes and buffer contents to actual writer (non-parallel, but hopefully fast)
-        for buf in encoded? {
-            bytes_written += buf.encode(writer)?;
+        for (buf, &size) in bufs?.iter().zip(sizes) {
+            bytes_written += size.encode(writer)?;
+            writer.write_all(buf)?;
+            bytes_written += buf.len();
         }
+    }
+    Ok(bytes_written)
+}
 
+/// the sizes of WithSize are written before the contents, so if encoded_size() was wrong, the
+/// output is garbage (and it is a bug in encoded_size())
+fn check_encoded_size(encoded_size: usize, bytes_written: usize) -> io::Result<usize> {
+    if encoded_size == bytes_written {
         Ok(bytes_written)
+    } else {
+        Err(io::Error::new(io::ErrorKind::Other, format!("encoded_size() was {} bytes, but encode() wrote {} bytes", encoded_size, bytes_written)))
     }
 }
 
@@ -208,26 +591,69 @@ impl<T: WasmBinary + Send + Sync> WasmBinary for Vec<WithSize<T>> {
 
 impl WasmBinary for Module {
     fn decode<R: io::Read>(reader: &mut R) -> io::Result<Self> {
+        let reader = &mut CountingReader { reader, count: 0 };
+        // all errors get the absolute offset, the section and function index are added below
+        let with_offset = |e: io::Error, count: usize| DecodeError::add_context(e, |e| {
+            e.offset = Some(count - e.bytes_after);
+            e.bytes_after = 0;
+        });
+
         let mut magic_number = [0u8; 4];
-        reader.read_exact(&mut magic_number)?;
+        reader.read_exact(&mut magic_number).map_err(|e| with_offset(e, reader.count))?;
         if &magic_number != b"\0asm" {
-            return Self::error("magic bytes do not match");
+            return Self::error("magic bytes do not match").map_err(|e| with_offset(e, 0));
         }
 
-        let version = reader.read_u32::<LittleEndian>()?;
+        let version = reader.read_u32::<LittleEndian>().map_err(|e| with_offset(e, reader.count))?;
         if version != 1 {
-            return Self::error("not version 1");
+            return Self::error("not version 1").map_err(|e| with_offset(e, 4));
         }
 
+        let strict = reader.is_strict();
+        // strict: id of the last non-custom section, for checking their order
+        let mut last_id = None;
+
         let mut sections = Vec::new();
         loop {
-            match Section::decode(reader) {
-                Ok(section) => sections.push(section),
+            // read section id separately, so that we know it in case of errors
+            let id = match u8::decode(reader) {
+                Ok(id) => id,
                 Err(ref e) if e.kind() == io::ErrorKind::UnexpectedEof => break,
-                Err(e) => return Err(e)
+                Err(e) => return Err(with_offset(e, reader.count))
+            };
+            if strict {
+                check_section_order(id, &mut last_id).map_err(|e| DecodeError::add_context(with_offset(e, reader.count), |e| e.section = Some(id)))?;
+            }
+
+            // "put back" the id byte, so that the derived impl can dispatch on it
+            let buf = [id; 1];
+            let section = Section::decode(&mut io::Read::chain(&buf[..], &mut *reader));
+            match section {
+                Ok(section) => {
+                    if strict {
+                        if let Section::Custom(ref contents) = section {
+                            check_custom_section_name(contents).map_err(|e| DecodeError::add_context(with_offset(e, reader.count), |e| e.section = Some(id)))?;
+                        }
+                    }
+                    sections.push(section);
+                }
+                Err(e) => {
+                    let function_imports = function_imports(&sections);
+                    return Err(DecodeError::add_context(with_offset(e, reader.count), |e| {
+                        e.section = Some(id);
+                        // function index from the code section does not include imported functions
+                        if let Some(function) = e.function {
+                            e.function = Some((function.0 + function_imports).into());
+                        }
+                    }));
+                }
             };
         }
 
+        if strict {
+            check_section_lengths(&sections).map_err(|e| with_offset(e, reader.count))?;
+        }
+
         Ok(Module { sections })
     }
     fn encode<W: io::Write>(&self, writer: &mut W) -> io::Result<usize> {
@@ -239,6 +665,104 @@ impl WasmBinary for Module {
         }
         Ok(bytes_written)
     }
+    fn encoded_size(&self) -> usize {
+        8 + self.sections.iter().map(Section::encoded_size).sum::<usize>()
+    }
+}
+
+impl Module {
+    /// Like decode(), but rejects all malformed binaries as the spec does, which decode() accepts
+    /// for robustness: non-custom sections that are duplicated or out of order, over-long LEB128
+    /// integers or ones with unused bits set, names that are not UTF-8, and bytes left after the
+    /// contents of a section or function body.
+    pub fn decode_strict<R: io::Read>(reader: &mut R) -> io::Result<Self> {
+        Module::decode(&mut StrictReader(reader))
+    }
+}
+
+/// strict: non-custom sections must appear at most once and in this order (which is not by id,
+/// because the data count and tag sections were added later), custom sections anywhere
+const SECTION_ORDER: [u8; 13] = [1, 2, 3, 4, 5, 13, 6, 7, 8, 9, 12, 10, 11];
+
+/// the error offset points to the id byte of the offending section
+fn check_section_order(id: u8, last_id: &mut Option<u8>) -> io::Result<()> {
+    let error = |message: String| {
+        let mut error = DecodeError::new(message);
+        error.bytes_after = 1;
+        Err(io::Error::new(io::ErrorKind::InvalidData, error))
+    };
+    if id == 0 {
+        return Ok(());
+    }
+    let position = match SECTION_ORDER.iter().position(|&other| other == id) {
+        Some(position) => position,
+        None => return error(format!("malformed section id {}", id)),
+    };
+    if let Some(last_id) = *last_id {
+        let last_position = SECTION_ORDER.iter().position(|&other| other == last_id).unwrap();
+        if position == last_position {
+            return error(format!("unexpected content after last section: duplicate {} section", section_name(id)));
+        }
+        if position < last_position {
+            return error(format!("unexpected content after last section: {} section must come before {} section", section_name(id), section_name(last_id)));
+        }
+    }
+    *last_id = Some(id);
+    Ok(())
+}
+
+/// strict: custom section names must be UTF-8, even though the custom section is kept as bytes.
+/// The error offset points to the end of the section.
+fn check_custom_section_name(mut contents: &[u8]) -> io::Result<()> {
+    let name_len = usize::decode(&mut StrictReader(&mut contents)).map_err(|e| if e.kind() == io::ErrorKind::UnexpectedEof {
+        DecodeError::add_context(e, |e| e.message = "unexpected end of section or function (custom section without name)".to_string())
+    } else {
+        e
+    })?;
+    if name_len > contents.len() {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, format!("unexpected end of section or function (custom section name is {} bytes, but only {} bytes left)", name_len, contents.len())));
+    }
+    str::from_utf8(&contents[..name_len])
+        .map(|_| ())
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("malformed UTF-8 encoding of custom section name: {}", e)))
+}
+
+/// strict: the number of function bodies and data segments must be the same as declared in the
+/// function and data count section, respectively
+fn check_section_lengths(sections: &[Section]) -> io::Result<()> {
+    let (mut functions, mut bodies, mut data_count, mut data) = (0, 0, None, 0);
+    for section in sections {
+        match *section {
+            Section::Function(WithSize(ref types)) => functions = types.len(),
+            Section::Code(WithSize(ref code)) => bodies = code.len(),
+            Section::DataCount(WithSize(count)) => data_count = Some(count as usize),
+            Section::Data(WithSize(ref segments)) => data = segments.len(),
+            _ => {}
+        }
+    }
+    if functions != bodies {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, format!("function and code section have inconsistent lengths: {} functions, but {} bodies", functions, bodies)));
+    }
+    match data_count {
+        Some(data_count) if data_count != data =>
+            Err(io::Error::new(io::ErrorKind::InvalidData, format!("data count and data section have inconsistent lengths: {} declared, but {} segments", data_count, data))),
+        _ => Ok(())
+    }
+}
+
+/// number of imported functions, i.e., the function index of the first body in the code section
+fn function_imports(sections: &[Section]) -> usize {
+    sections.iter()
+        .filter_map(|section| match *section {
+            Section::Import(WithSize(ref imports)) => Some(imports),
+            _ => None,
+        })
+        .flat_map(|imports| imports.iter())
+        .filter(|import| match import.type_ {
+            ImportType::Function(_) => true,
+            _ => false,
+        })
+        .count()
 }
 
 /// needs manual impl because of block handling: End op-code terminates body, but only if block stack is empty
@@ -249,15 +773,7 @@ impl WasmBinary for Expr {
         let mut block_depth = 0;
         while block_depth >= 0 {
             let instr = Instr::decode(reader)?;
-
-            block_depth += match instr {
-                Instr::Block(..) | Instr::Loop(..) | Instr::If(..) => 1,
-                // Else ends a block, but also starts a new one
-                Instr::Else => -1 + 1,
-                Instr::End => -1,
-                _ => 0
-            };
-
+            block_depth += block_depth_change(&instr);
             instructions.push(instr);
         }
 
@@ -270,63 +786,1055 @@ impl WasmBinary for Expr {
         }
         Ok(bytes_written)
     }
+    fn encoded_size(&self) -> usize {
+        self.0.iter().map(Instr::encoded_size).sum()
+    }
+}
+
+fn block_depth_change(instr: &Instr) -> isize {
+    match *instr {
+        Instr::Block(..) | Instr::Loop(..) | Instr::If(..) | Instr::Try(..) => 1,
+        // Else (and Catch/CatchAll) ends a block, but also starts a new one
+        Instr::Else | Instr::Catch(..) | Instr::CatchAll => -1 + 1,
+        Instr::End | Instr::Delegate(..) => -1,
+        _ => 0
+    }
 }
 
 /// needs manual impl because of compressed format: even though BlockType is "logically" an enum,
-/// it has no tag, because they know that 0x40 (empty block) and ValType are disjoint.
+/// it has no tag, because they know that 0x40 (empty block), ValType, and type indices are disjoint:
+/// type indices are encoded as positive signed LEB128 (s33), the others as single negative bytes.
 impl WasmBinary for BlockType {
     fn decode<R: io::Read>(reader: &mut R) -> io::Result<Self> {
-        Ok(BlockType(match u8::decode(reader)? {
-            0x40 => None,
+        Ok(match u8::decode(reader)? {
+            0x40 => BlockType::Empty,
+            byte @ 0x7b..=0x7f | byte @ 0x6f..=0x70 => {
+                let buf = [byte; 1];
+                BlockType::Value(ValType::decode(&mut &buf[..])?)
+            }
             byte => {
-                let mut buf = [byte; 1];
-                Some(ValType::decode(&mut &buf[..])?)
+                // "put back" the first byte of the type index
+                let buf = [byte; 1];
+                // s33, so in strict mode at most 5 bytes
+                let type_idx = decode_leb128::<i64, _>(&mut io::Read::chain(&buf[..], &mut *reader), 33, true)?;
+                if type_idx < 0 || type_idx > u32::max_value() as i64 {
+                    return Self::error(format!("expected BlockType, got invalid type index {}", type_idx));
+                }
+                BlockType::FunctionType((type_idx as usize).into())
             }
-        }))
+        })
+    }
+    fn encode<W: io::Write>(&self, writer: &mut W) -> io::Result<usize> {
+        match *self {
+            BlockType::Empty => 0x40u8.encode(writer),
+            BlockType::Value(ref val_type) => val_type.encode(writer),
+            BlockType::FunctionType(type_idx) => (type_idx.0 as i64).encode(writer),
+        }
+    }
+    fn encoded_size(&self) -> usize {
+        match *self {
+            BlockType::Empty => 1,
+            BlockType::Value(ref val_type) => val_type.encoded_size(),
+            BlockType::FunctionType(type_idx) => (type_idx.0 as i64).encoded_size(),
+        }
     }
+}
+
+/// needs manual impl because the memory index (from the multi-memory proposal) is only present if
+/// bit 6 of the alignment field is set, and then comes between alignment and offset.
+impl WasmBinary for MemoryMemarg {
+    fn decode<R: io::Read>(reader: &mut R) -> io::Result<Self> {
+        let alignment = u32::decode(reader)?;
+        let memory_idx = if alignment & MEMARG_FLAG_MEMORY_IDX != 0 { Idx::decode(reader)? } else { 0.into() };
+        // NOTE 64-bit offsets are only valid for memory64 memories, which is checked by validation
+        let offset = u64::decode(reader)?;
+        Ok(MemoryMemarg(memory_idx, Memarg { alignment: alignment & !MEMARG_FLAG_MEMORY_IDX, offset }))
+    }
+
     fn encode<W: io::Write>(&self, writer: &mut W) -> io::Result<usize> {
-        match self {
-            &BlockType(None) => 0x40u8.encode(writer),
-            &BlockType(Some(ref val_type)) => val_type.encode(writer)
+        let MemoryMemarg(memory_idx, memarg) = *self;
+        let mut bytes_written = 0;
+        // keep the MVP encoding for memory 0
+        if memory_idx.0 == 0 {
+            bytes_written += memarg.alignment.encode(writer)?;
+        } else {
+            bytes_written += (memarg.alignment | MEMARG_FLAG_MEMORY_IDX).encode(writer)?;
+            bytes_written += memory_idx.encode(writer)?;
         }
+        bytes_written += memarg.offset.encode(writer)?;
+        Ok(bytes_written)
+    }
+
+    fn encoded_size(&self) -> usize {
+        let MemoryMemarg(memory_idx, memarg) = *self;
+        let alignment_and_memory_idx = if memory_idx.0 == 0 {
+            memarg.alignment.encoded_size()
+        } else {
+            (memarg.alignment | MEMARG_FLAG_MEMORY_IDX).encoded_size() + memory_idx.encoded_size()
+        };
+        alignment_and_memory_idx + memarg.offset.encoded_size()
     }
 }
 
+const MEMARG_FLAG_MEMORY_IDX: u32 = 0x40;
+
 /// needs manual impl because the tag if max is present comes at the beginning of the struct, not
-/// before the max field.
+/// before the max field. The tag is a flags byte: bit 0 is set if there is a maximum size, bit 1
+/// (from the threads proposal) for shared memories, bit 2 (from the memory64 proposal) for 64-bit
+/// memories, whose sizes are also encoded as u64.
 impl WasmBinary for Limits {
     fn decode<R: io::Read>(reader: &mut R) -> io::Result<Self> {
-        Ok(match u8::decode(reader)? {
-            0x00 => Limits {
-                initial_size: u32::decode(reader)?,
-                max_size: None,
-            },
-            0x01 => Limits {
-                initial_size: u32::decode(reader)?,
-                max_size: Some(u32::decode(reader)?),
+        let flags = u8::decode(reader)?;
+        if flags > 0x07 {
+            return Self::tag_error("Limits", flags);
+        }
+        let memory64 = flags & 0x04 != 0;
+        let initial_size = decode_limits_size(reader, memory64)?;
+        let max_size = if flags & 0x01 != 0 { Some(decode_limits_size(reader, memory64)?) } else { None };
+        Ok(Limits {
+            initial_size,
+            max_size,
+            shared: flags & 0x02 != 0,
+            memory64,
+        })
+    }
+
+    fn encode<W: io::Write>(&self, writer: &mut W) -> io::Result<usize> {
+        let flags = self.max_size.is_some() as u8 | (self.shared as u8) << 1 | (self.memory64 as u8) << 2;
+        let mut bytes_written = flags.encode(writer)?;
+        bytes_written += self.initial_size.encode(writer)?;
+        if let Some(max_size) = self.max_size {
+            bytes_written += max_size.encode(writer)?;
+        }
+        Ok(bytes_written)
+    }
+
+    fn encoded_size(&self) -> usize {
+        1 + self.initial_size.encoded_size() + self.max_size.map_or(0, |max_size| max_size.encoded_size())
+    }
+}
+
+fn decode_limits_size<R: io::Read>(reader: &mut R, memory64: bool) -> io::Result<u64> {
+    if memory64 {
+        u64::decode(reader)
+    } else {
+        Ok(u32::decode(reader)? as u64)
+    }
+}
+
+/// needs manual impl because the flags (from the bulk memory proposal) determine which fields are
+/// present: bit 0 is set for passive and declarative segments, bit 1 for an explicit table index
+/// (if active) or for declarative segments (if not active). Bit 2 (from the reference types
+/// proposal) is set if the segment contains constant expressions instead of function indices.
+impl WasmBinary for Element {
+    fn decode<R: io::Read>(reader: &mut R) -> io::Result<Self> {
+        let flags = u32::decode(reader)?;
+        let mode = match flags {
+            0 | 4 => ElementMode::Active { table_idx: 0.into(), offset: Expr::decode(reader)? },
+            1 | 5 => ElementMode::Passive,
+            2 | 6 => ElementMode::Active { table_idx: Idx::decode(reader)?, offset: Expr::decode(reader)? },
+            3 | 7 => ElementMode::Declarative,
+            flags => return Self::error(format!("expected element segment flags 0 to 7, got {}", flags)),
+        };
+        // element kind (for function indices) or reference type (for expressions), both are
+        // implicitly funcref if the table is implicit
+        let explicit_type = flags & 0b011 != 0;
+        let init = if flags & 0b100 == 0 {
+            if explicit_type {
+                match u8::decode(reader)? {
+                    0x00 => {}
+                    byte => return Self::tag_error("ElemKind", byte),
+                }
+            }
+            ElementItems::Functions(Vec::decode(reader)?)
+        } else {
+            let type_ = if explicit_type { ElemType::decode(reader)? } else { ElemType::FuncRef };
+            ElementItems::Exprs(type_, Vec::decode(reader)?)
+        };
+        Ok(Element { mode, init })
+    }
+
+    fn encode<W: io::Write>(&self, writer: &mut W) -> io::Result<usize> {
+        let flags = element_flags(self);
+        let explicit_type = flags & 0b011 != 0;
+
+        let mut bytes_written = flags.encode(writer)?;
+        if let ElementMode::Active { table_idx, ref offset } = self.mode {
+            if flags & 0b010 != 0 {
+                bytes_written += table_idx.encode(writer)?;
+            }
+            bytes_written += offset.encode(writer)?;
+        }
+        match self.init {
+            ElementItems::Functions(ref functions) => {
+                if explicit_type {
+                    bytes_written += 0x00u8.encode(writer)?;
+                }
+                bytes_written += functions.encode(writer)?;
+            }
+            ElementItems::Exprs(type_, ref exprs) => {
+                if explicit_type {
+                    bytes_written += type_.encode(writer)?;
+                }
+                bytes_written += exprs.encode(writer)?;
+            }
+        }
+        Ok(bytes_written)
+    }
+
+    fn encoded_size(&self) -> usize {
+        let flags = element_flags(self);
+        let explicit_type = flags & 0b011 != 0;
+
+        let mut size = flags.encoded_size();
+        if let ElementMode::Active { table_idx, ref offset } = self.mode {
+            if flags & 0b010 != 0 {
+                size += table_idx.encoded_size();
+            }
+            size += offset.encoded_size();
+        }
+        match self.init {
+            ElementItems::Functions(ref functions) => {
+                if explicit_type {
+                    size += 0x00u8.encoded_size();
+                }
+                size += functions.encoded_size();
+            }
+            ElementItems::Exprs(type_, ref exprs) => {
+                if explicit_type {
+                    size += type_.encoded_size();
+                }
+                size += exprs.encoded_size();
+            }
+        }
+        size
+    }
+}
+
+fn element_flags(element: &Element) -> u32 {
+    // use the MVP encoding (implicit table 0 and funcref) whenever possible, so that older
+    // engines can still load it
+    let implicit_type = match element.init {
+        ElementItems::Functions(_) | ElementItems::Exprs(ElemType::FuncRef, _) => true,
+        ElementItems::Exprs(ElemType::ExternRef, _) => false,
+    };
+    let mut flags = match element.mode {
+        ElementMode::Active { table_idx, .. } if table_idx.0 == 0 && implicit_type => 0u32,
+        ElementMode::Active { .. } => 2,
+        ElementMode::Passive => 1,
+        ElementMode::Declarative => 3,
+    };
+    if let ElementItems::Exprs(_, _) = element.init {
+        flags |= 0b100;
+    }
+    flags
+}
+
+/// needs manual impl because the flags (from the bulk memory proposal) determine which fields are
+/// present: 0 = active in memory 0, 1 = passive, 2 = active with explicit memory index.
+impl WasmBinary for Data {
+    fn decode<R: io::Read>(reader: &mut R) -> io::Result<Self> {
+        Ok(Data { mode: decode_data_mode(reader)?, init: Vec::decode(reader)? })
+    }
+
+    fn encode<W: io::Write>(&self, writer: &mut W) -> io::Result<usize> {
+        let mut bytes_written = 0;
+        match self.mode {
+            DataMode::Active { memory_idx, ref offset } if memory_idx.0 == 0 => {
+                bytes_written += 0u32.encode(writer)?;
+                bytes_written += offset.encode(writer)?;
+            }
+            DataMode::Active { memory_idx, ref offset } => {
+                bytes_written += 2u32.encode(writer)?;
+                bytes_written += memory_idx.encode(writer)?;
+                bytes_written += offset.encode(writer)?;
+            }
+            DataMode::Passive => {
+                bytes_written += 1u32.encode(writer)?;
+            }
+        }
+        bytes_written += self.init.encode(writer)?;
+        Ok(bytes_written)
+    }
+
+    fn encoded_size(&self) -> usize {
+        let mode_size = match self.mode {
+            DataMode::Active { memory_idx, ref offset } if memory_idx.0 == 0 => 1 + offset.encoded_size(),
+            DataMode::Active { memory_idx, ref offset } => 1 + memory_idx.encoded_size() + offset.encoded_size(),
+            DataMode::Passive => 1,
+        };
+        mode_size + self.init.encoded_size()
+    }
+}
+
+/// also used for zero-copy decoding of data segments, see BorrowedModule
+fn decode_data_mode<R: io::Read>(reader: &mut R) -> io::Result<DataMode> {
+    Ok(match u32::decode(reader)? {
+        0 => DataMode::Active { memory_idx: 0.into(), offset: Expr::decode(reader)? },
+        1 => DataMode::Passive,
+        2 => DataMode::Active { memory_idx: Idx::decode(reader)?, offset: Expr::decode(reader)? },
+        flags => return Err(io::Error::new(io::ErrorKind::InvalidData, format!("expected data segment flags 0 to 2, got {}", flags))),
+    })
+}
+
+/// needs manual impl because subsections have no count prefix, but continue until the end of the
+/// custom section. Also, unknown subsections are skipped instead of producing an error, since the
+/// name section is only debug information and newer toolchains emit additional subsections.
+impl WasmBinary for NameSection {
+    fn decode<R: io::Read>(reader: &mut R) -> io::Result<Self> {
+        let mut subsections = Vec::new();
+        loop {
+            let id = match u8::decode(reader) {
+                Ok(id) => id,
+                Err(ref e) if e.kind() == io::ErrorKind::UnexpectedEof => break,
+                Err(e) => return Err(e)
+            };
+            match id {
+                0..=2 | 7 => {
+                    // "put back" the id byte, so that the derived impl can dispatch on it
+                    let buf = [id; 1];
+                    subsections.push(NameSubSection::decode(&mut io::Read::chain(&buf[..], &mut *reader))?);
+                }
+                _ => {
+                    let _skip_unknown_subsection: Vec<u8> = Vec::decode(reader)?;
+                }
+            }
+        }
+        Ok(NameSection { subsections })
+    }
+
+    fn encode<W: io::Write>(&self, writer: &mut W) -> io::Result<usize> {
+        let mut bytes_written = 0;
+        for subsection in &self.subsections {
+            bytes_written += subsection.encode(writer)?;
+        }
+        Ok(bytes_written)
+    }
+
+    fn encoded_size(&self) -> usize {
+        self.subsections.iter().map(NameSubSection::encoded_size).sum()
+    }
+}
+
+/// needs manual impl because subsections continue until the end of the custom section and because
+/// we want to keep unknown subsections as raw bytes
+impl WasmBinary for LinkingSection {
+    fn decode<R: io::Read>(reader: &mut R) -> io::Result<Self> {
+        let version = u32::decode(reader)?;
+        let mut subsections = Vec::new();
+        loop {
+            match LinkingSubSection::decode(reader) {
+                Ok(subsection) => subsections.push(subsection),
+                Err(ref e) if e.kind() == io::ErrorKind::UnexpectedEof => break,
+                Err(e) => return Err(e)
+            };
+        }
+        Ok(LinkingSection { version, subsections })
+    }
+
+    fn encode<W: io::Write>(&self, writer: &mut W) -> io::Result<usize> {
+        let mut bytes_written = self.version.encode(writer)?;
+        for subsection in &self.subsections {
+            bytes_written += subsection.encode(writer)?;
+        }
+        Ok(bytes_written)
+    }
+
+    fn encoded_size(&self) -> usize {
+        self.version.encoded_size() + self.subsections.iter().map(LinkingSubSection::encoded_size).sum::<usize>()
+    }
+}
+
+impl WasmBinary for LinkingSubSection {
+    fn decode<R: io::Read>(reader: &mut R) -> io::Result<Self> {
+        let id = u8::decode(reader)?;
+        let contents: Vec<u8> = Vec::decode(reader)?;
+        if id != 7 && id != 8 {
+            return Ok(LinkingSubSection::Other(id, contents));
+        }
+
+        let mut contents_reader = &contents[..];
+        let subsection = if id == 7 {
+            Vec::decode(&mut contents_reader).map(LinkingSubSection::ComdatInfo)
+        } else {
+            Vec::decode(&mut contents_reader).map(LinkingSubSection::SymbolTable)
+        };
+        // contents were already read completely, so errors inside happened before their end
+        subsection.map_err(|e| DecodeError::add_context(e, |e| e.bytes_after += contents_reader.len()))
+    }
+
+    fn encode<W: io::Write>(&self, writer: &mut W) -> io::Result<usize> {
+        // size of the contents must be known before writing (like WithSize)
+        Ok(match *self {
+            LinkingSubSection::ComdatInfo(ref comdats) => 7u8.encode(writer)? + comdats.encode_with_size(writer)?,
+            LinkingSubSection::SymbolTable(ref symbols) => 8u8.encode(writer)? + symbols.encode_with_size(writer)?,
+            LinkingSubSection::Other(id, ref contents) => {
+                let bytes_written = id.encode(writer)? + contents.len().encode(writer)?;
+                writer.write_all(contents)?;
+                bytes_written + contents.len()
+            }
+        })
+    }
+
+    fn encoded_size(&self) -> usize {
+        let contents_size = self.contents_size();
+        1 + contents_size.leb128_size() + contents_size
+    }
+}
+
+impl LinkingSubSection {
+    fn contents_size(&self) -> usize {
+        match *self {
+            LinkingSubSection::ComdatInfo(ref comdats) => comdats.encoded_size(),
+            LinkingSubSection::SymbolTable(ref symbols) => symbols.encoded_size(),
+            LinkingSubSection::Other(_, ref contents) => contents.len(),
+        }
+    }
+}
+
+const SYMBOL_FLAG_UNDEFINED: u32 = 0x10;
+const SYMBOL_FLAG_EXPLICIT_NAME: u32 = 0x40;
+
+/// needs manual impl because presence of the name and data segment fields depends on the flags
+impl WasmBinary for SymbolInfo {
+    fn decode<R: io::Read>(reader: &mut R) -> io::Result<Self> {
+        let kind = u8::decode(reader)?;
+        let flags = u32::decode(reader)?;
+        let defined = flags & SYMBOL_FLAG_UNDEFINED == 0;
+        let has_name = defined || flags & SYMBOL_FLAG_EXPLICIT_NAME != 0;
+
+        macro_rules! decode_name {
+            () => (if has_name { Some(String::decode(reader)?) } else { None });
+        }
+
+        Ok(match kind {
+            0 => SymbolInfo::Function { flags, function_idx: Idx::decode(reader)?, name: decode_name!() },
+            1 => SymbolInfo::Data {
+                flags,
+                name: String::decode(reader)?,
+                segment: if defined { Some(DataSymbolSegment::decode(reader)?) } else { None },
             },
-            byte => Self::error(format!("expected tag for Limits, got 0x{:02x}", byte))?
+            2 => SymbolInfo::Global { flags, global_idx: Idx::decode(reader)?, name: decode_name!() },
+            3 => SymbolInfo::Section { flags, section_idx: u32::decode(reader)? },
+            4 => SymbolInfo::Event { flags, event_idx: u32::decode(reader)?, name: decode_name!() },
+            5 => SymbolInfo::Table { flags, table_idx: Idx::decode(reader)?, name: decode_name!() },
+            kind => Self::tag_error("SymbolInfo", kind)?
         })
     }
 
     fn encode<W: io::Write>(&self, writer: &mut W) -> io::Result<usize> {
         let mut bytes_written = 0;
-        match self.max_size {
-            None => {
-                bytes_written += 0x00u8.encode(writer)?;
-                bytes_written += self.initial_size.encode(writer)?;
+        match *self {
+            SymbolInfo::Function { flags, ref function_idx, ref name } => {
+                bytes_written += 0u8.encode(writer)?;
+                bytes_written += flags.encode(writer)?;
+                bytes_written += function_idx.encode(writer)?;
+                if let Some(ref name) = *name { bytes_written += name.encode(writer)?; }
+            }
+            SymbolInfo::Data { flags, ref name, ref segment } => {
+                bytes_written += 1u8.encode(writer)?;
+                bytes_written += flags.encode(writer)?;
+                bytes_written += name.encode(writer)?;
+                if let Some(ref segment) = *segment { bytes_written += segment.encode(writer)?; }
+            }
+            SymbolInfo::Global { flags, ref global_idx, ref name } => {
+                bytes_written += 2u8.encode(writer)?;
+                bytes_written += flags.encode(writer)?;
+                bytes_written += global_idx.encode(writer)?;
+                if let Some(ref name) = *name { bytes_written += name.encode(writer)?; }
             }
-            Some(ref max_size) => {
-                bytes_written += 0x01u8.encode(writer)?;
-                bytes_written += self.initial_size.encode(writer)?;
-                bytes_written += max_size.encode(writer)?;
+            SymbolInfo::Section { flags, section_idx } => {
+                bytes_written += 3u8.encode(writer)?;
+                bytes_written += flags.encode(writer)?;
+                bytes_written += section_idx.encode(writer)?;
+            }
+            SymbolInfo::Event { flags, event_idx, ref name } => {
+                bytes_written += 4u8.encode(writer)?;
+                bytes_written += flags.encode(writer)?;
+                bytes_written += event_idx.encode(writer)?;
+                if let Some(ref name) = *name { bytes_written += name.encode(writer)?; }
+            }
+            SymbolInfo::Table { flags, ref table_idx, ref name } => {
+                bytes_written += 5u8.encode(writer)?;
+                bytes_written += flags.encode(writer)?;
+                bytes_written += table_idx.encode(writer)?;
+                if let Some(ref name) = *name { bytes_written += name.encode(writer)?; }
             }
         }
         Ok(bytes_written)
     }
+
+    fn encoded_size(&self) -> usize {
+        let name_size = |name: &Option<String>| name.as_ref().map_or(0, String::encoded_size);
+        // kind byte first
+        1 + match *self {
+            SymbolInfo::Function { flags, ref function_idx, ref name } =>
+                flags.encoded_size() + function_idx.encoded_size() + name_size(name),
+            SymbolInfo::Data { flags, ref name, ref segment } =>
+                flags.encoded_size() + name.encoded_size() + segment.as_ref().map_or(0, DataSymbolSegment::encoded_size),
+            SymbolInfo::Global { flags, ref global_idx, ref name } =>
+                flags.encoded_size() + global_idx.encoded_size() + name_size(name),
+            SymbolInfo::Section { flags, section_idx } =>
+                flags.encoded_size() + section_idx.encoded_size(),
+            SymbolInfo::Event { flags, event_idx, ref name } =>
+                flags.encoded_size() + event_idx.encoded_size() + name_size(name),
+            SymbolInfo::Table { flags, ref table_idx, ref name } =>
+                flags.encoded_size() + table_idx.encoded_size() + name_size(name),
+        }
+    }
 }
 
 impl<T> WasmBinary for PhantomData<T> {
     fn decode<R: io::Read>(_: &mut R) -> io::Result<Self> { Ok(PhantomData) }
     fn encode<W: io::Write>(&self, _: &mut W) -> io::Result<usize> { Ok(0) }
-}
\ No newline at end of file
+    fn encoded_size(&self) -> usize { 0 }
+}
+
+
+/* Original byte offsets of function bodies and instructions (see highlevel::CodeOffsets) */
+
+/// id, offset of the contents, and contents of all sections, without decoding them
+pub fn raw_sections(bytes: &[u8]) -> io::Result<Vec<(u8, usize, &[u8])>> {
+    if bytes.len() < 8 || &bytes[..4] != b"\0asm" {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "magic bytes do not match"));
+    }
+    let mut reader = &bytes[8..];
+    let mut sections = Vec::new();
+    while !reader.is_empty() {
+        let id = reader.read_u8()?;
+        let size = u32::decode(&mut reader)? as usize;
+        if size > reader.len() {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, format!("{} section is larger than the rest of the module", section_name(id))));
+        }
+        let (contents, rest) = reader.split_at(size);
+        sections.push((id, bytes.len() - reader.len(), contents));
+        reader = rest;
+    }
+    Ok(sections)
+}
+
+impl CodeOffsets {
+    /// decodes the module again (separately from Module::decode), including all instructions of
+    /// all function bodies, so this roughly doubles the decoding time and should only be called
+    /// if the offsets are actually needed
+    pub fn from_module_bytes(bytes: &[u8]) -> io::Result<Self> {
+        let sections = raw_sections(bytes)?;
+        let mut functions: Vec<Option<FunctionOffsets>> = Vec::new();
+        let mut code_section = 0..0;
+
+        for &(id, section_offset, mut reader) in &sections {
+            match id {
+                // imported functions come first in the index space, but have no body
+                2 => for import in Vec::<Import>::decode(&mut reader)? {
+                    if let ImportType::Function(_) = import.type_ {
+                        functions.push(None);
+                    }
+                },
+                10 => {
+                    code_section = section_offset..section_offset + reader.len();
+                    let offset = |reader: &[u8]| code_section.end - reader.len();
+                    for _ in 0..u32::decode(&mut reader)? {
+                        let body_start = offset(reader);
+                        let size = u32::decode(&mut reader)? as usize;
+                        if size > reader.len() {
+                            return Err(io::Error::new(io::ErrorKind::InvalidData, format!("function body at offset 0x{:x} is larger than the code section", body_start)));
+                        }
+                        let (mut body, rest) = reader.split_at(size);
+                        reader = rest;
+                        let body_end = offset(reader);
+
+                        Vec::<Locals>::decode(&mut body)?;
+                        let mut instrs = Vec::new();
+                        let mut block_depth = 0;
+                        while block_depth >= 0 {
+                            instrs.push(body_end - body.len());
+                            block_depth += block_depth_change(&Instr::decode(&mut body)?);
+                        }
+
+                        functions.push(Some(FunctionOffsets { body: body_start..body_end, instrs }));
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Ok(CodeOffsets { code_section, functions })
+    }
+}
+
+
+/* Lazy decoding of function bodies (see lowlevel::LazyModule) */
+
+impl<'a> LazyModule<'a> {
+    /// decodes all sections but the code section, of which the function bodies are only located
+    pub fn decode(bytes: &'a [u8]) -> io::Result<Self> {
+        // same error context as in Module::decode, position is the absolute offset after the error
+        let with_offset = |e: io::Error, position: usize| DecodeError::add_context(e, |e| {
+            e.offset = Some(position - e.bytes_after);
+            e.bytes_after = 0;
+        });
+        let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message.to_string());
+
+        let mut reader = bytes;
+        let mut magic_number = [0u8; 4];
+        io::Read::read_exact(&mut reader, &mut magic_number).map_err(|e| with_offset(e, bytes.len() - reader.len()))?;
+        if &magic_number != b"\0asm" {
+            return Err(with_offset(invalid("magic bytes do not match"), 0));
+        }
+        let version = reader.read_u32::<LittleEndian>().map_err(|e| with_offset(e, bytes.len() - reader.len()))?;
+        if version != 1 {
+            return Err(with_offset(invalid("not version 1"), 4));
+        }
+
+        let mut sections = Vec::new();
+        let mut bodies = Vec::new();
+        while !reader.is_empty() {
+            let id = reader[0];
+            let in_section = |e: io::Error, position: usize| DecodeError::add_context(with_offset(e, position), |e| e.section = Some(id));
+
+            if id != 10 {
+                match Section::decode(&mut reader) {
+                    Ok(section) => sections.push(section),
+                    Err(e) => return Err(in_section(e, bytes.len() - reader.len())),
+                }
+                continue;
+            }
+
+            reader = &reader[1..];
+            let size = u32::decode(&mut reader).map_err(|e| in_section(e, bytes.len() - reader.len()))? as usize;
+            if size > reader.len() {
+                return Err(in_section(invalid("section is larger than the rest of the module"), bytes.len() - reader.len()));
+            }
+            let (mut contents, rest) = reader.split_at(size);
+            reader = rest;
+            let section_end = bytes.len() - reader.len();
+
+            bodies = locate_bodies(&mut contents, section_end).map_err(|e| in_section(e, section_end - contents.len()))?;
+            sections.push(Section::Code(WithSize(Vec::new())));
+        }
+
+        Ok(LazyModule { sections, bodies })
+    }
+
+    /// decodes the i-th body of the code section (i.e., function index minus function_imports())
+    pub fn decode_body(&self, i: usize) -> io::Result<Code> {
+        let (offset, body) = self.bodies[i];
+        let mut reader = body;
+        Code::decode(&mut reader).map_err(|e| DecodeError::add_context(e, |e| {
+            e.offset = Some(offset + (body.len() - reader.len()) - e.bytes_after);
+            e.bytes_after = 0;
+            e.section = Some(10);
+            e.function = Some((self.function_imports() + i).into());
+            e.path.insert(0, format!("[{}]", i));
+            e.path.insert(0, "Section::Code".to_string());
+        }))
+    }
+
+    /// number of imported functions, i.e., the function index of the first body
+    pub fn function_imports(&self) -> usize {
+        function_imports(&self.sections)
+    }
+
+    /// the fully decoded module, same as Module::decode() on the same bytes
+    /// NOTE this has all low-level bodies in memory at once, for a high-level module use
+    /// into_highlevel() instead, which converts each body right after decoding it
+    pub fn into_module(self) -> io::Result<Module> {
+        let bodies: io::Result<Vec<WithSize<Code>>> = (0..self.bodies.len()).into_par_iter()
+            .map(|i| self.decode_body(i).map(WithSize))
+            .collect();
+        let bodies = bodies?;
+        let mut sections = self.sections;
+        for section in &mut sections {
+            if let Section::Code(WithSize(ref mut code)) = *section {
+                *code = bodies;
+                break;
+            }
+        }
+        Ok(Module { sections })
+    }
+}
+
+
+/// offset (in the module, code section ends at section_end) and contents of each function body,
+/// on error, reader is at the position of the error
+fn locate_bodies<'a>(reader: &mut &'a [u8], section_end: usize) -> io::Result<Vec<(usize, &'a [u8])>> {
+    let body_count = u32::decode(reader)?;
+    let mut bodies = Vec::new();
+    for i in 0..body_count {
+        let body_error = |e: io::Error| DecodeError::add_context(e, |e| {
+            e.path.insert(0, format!("[{}]", i));
+            e.path.insert(0, "Section::Code".to_string());
+        });
+        let size = u32::decode(reader).map_err(body_error)? as usize;
+        if size > reader.len() {
+            return Err(body_error(io::Error::new(io::ErrorKind::InvalidData, "function body is larger than the code section")));
+        }
+        let (body, rest) = reader.split_at(size);
+        bodies.push((section_end - reader.len(), body));
+        *reader = rest;
+    }
+    Ok(bodies)
+}
+
+
+/* Byte-exact re-encoding of unmodified parts of a module (opt-in, see OriginalEncoding) */
+
+/// The original bytes of a module, such that parts that were not modified can be encoded again
+/// exactly as in the original, i.e., with the same (possibly padded) LEB128 integers and sizes,
+/// see Module::encode_preserving(). Parts are identified by their position, not their contents:
+/// sections by their id (and custom sections by their order), elements of sections (e.g.,
+/// function bodies) by their index. Only the section ranges are kept, elements are located and
+/// compared with the original only when encoding.
+pub struct OriginalEncoding<'a> {
+    bytes: &'a [u8],
+    sections: Vec<OriginalSection>,
+}
+
+struct OriginalSection {
+    id: u8,
+    /// section id and size
+    header: Range<usize>,
+    contents: Range<usize>,
+}
+
+impl<'a> OriginalEncoding<'a> {
+    pub fn new(bytes: &'a [u8]) -> io::Result<Self> {
+        let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message.to_string());
+        if bytes.len() < 8 || &bytes[..4] != b"\0asm" {
+            return Err(invalid("magic bytes do not match"));
+        }
+
+        let mut reader = &bytes[8..];
+        let mut sections = Vec::new();
+        while !reader.is_empty() {
+            let start = bytes.len() - reader.len();
+            let id = reader.read_u8()?;
+            let (size, size_bytes): (usize, usize) = reader.read_leb128_with_size()?;
+            if size > reader.len() {
+                return Err(invalid("section is larger than the rest of the module"));
+            }
+            let contents_start = start + 1 + size_bytes;
+            sections.push(OriginalSection {
+                id,
+                header: start..contents_start,
+                contents: contents_start..contents_start + size,
+            });
+            reader = &reader[size..];
+        }
+
+        Ok(OriginalEncoding { bytes, sections })
+    }
+
+    /// the n-th original section with the same id
+    fn section(&self, id: u8, n: usize) -> Option<&OriginalSection> {
+        self.sections.iter()
+            .filter(|section| section.id == id)
+            .nth(n)
+    }
+
+    fn encode_section<W: io::Write>(&self, section: &Section, original: &OriginalSection, writer: &mut W) -> io::Result<usize> {
+        let original_contents = &self.bytes[original.contents.clone()];
+        let mut contents = Vec::new();
+        match *section {
+            Section::Type(WithSize(ref vec)) => encode_vec_preserving(vec, original_contents, &mut contents)?,
+            Section::Import(WithSize(ref vec)) => encode_vec_preserving(vec, original_contents, &mut contents)?,
+            Section::Function(WithSize(ref vec)) => encode_vec_preserving(vec, original_contents, &mut contents)?,
+            Section::Table(WithSize(ref vec)) => encode_vec_preserving(vec, original_contents, &mut contents)?,
+            Section::Memory(WithSize(ref vec)) => encode_vec_preserving(vec, original_contents, &mut contents)?,
+            Section::Global(WithSize(ref vec)) => encode_vec_preserving(vec, original_contents, &mut contents)?,
+            Section::Export(WithSize(ref vec)) => encode_vec_preserving(vec, original_contents, &mut contents)?,
+            Section::Element(WithSize(ref vec)) => encode_vec_preserving(vec, original_contents, &mut contents)?,
+            Section::Code(WithSize(ref bodies)) => encode_code_preserving(bodies, original_contents, &mut contents)?,
+            Section::Data(WithSize(ref vec)) => encode_vec_preserving(vec, original_contents, &mut contents)?,
+            Section::Tag(WithSize(ref vec)) => encode_vec_preserving(vec, original_contents, &mut contents)?,
+            // no elements, so the section is preserved only as a whole
+            Section::Custom(ref bytes) => { contents.extend_from_slice(bytes); }
+            Section::Start(WithSize(ref idx)) => { idx.encode(&mut contents)?; }
+            Section::DataCount(WithSize(count)) => { count.encode(&mut contents)?; }
+        }
+
+        let header = &self.bytes[original.header.clone()];
+        let mut bytes_written = if contents[..] == *original_contents {
+            writer.write_all(header)?;
+            header.len()
+        } else {
+            // the size of a modified section keeps the length of the original size (if it fits)
+            original.id.encode(writer)? + writer.write_leb128_padded(contents.len(), header.len() - 1)?
+        };
+        writer.write_all(&contents)?;
+        bytes_written += contents.len();
+        Ok(bytes_written)
+    }
+}
+
+/// elements that are the same as the original element at the same index are written with their
+/// original bytes, all others are encoded regularly
+fn encode_vec_preserving<T: WasmBinary>(vec: &[T], original: &[u8], contents: &mut Vec<u8>) -> io::Result<()> {
+    let mut original_reader = original;
+    let original_len = encode_len_preserving(vec.len(), &mut original_reader, contents)?;
+
+    let mut encoded = Vec::new();
+    for (i, element) in vec.iter().enumerate() {
+        encoded.clear();
+        element.encode(&mut encoded)?;
+        if i < original_len {
+            let original_element = original_reader;
+            let decoded = T::decode(&mut original_reader)?;
+            let original_element = &original_element[..original_element.len() - original_reader.len()];
+            if is_same_encoding(&encoded, &decoded)? {
+                contents.extend_from_slice(original_element);
+                continue;
+            }
+        }
+        contents.extend_from_slice(&encoded);
+    }
+    Ok(())
+}
+
+/// like encode_vec_preserving(), but only the sizes of the original bodies are decoded up front,
+/// and bodies are compared (i.e., decoded) and encoded in parallel
+fn encode_code_preserving(bodies: &[WithSize<Code>], original: &[u8], contents: &mut Vec<u8>) -> io::Result<()> {
+    let mut original_reader = original;
+    let original_len = encode_len_preserving(bodies.len(), &mut original_reader, contents)?;
+
+    // original bytes of each body, with and without its size
+    let mut original_bodies = Vec::with_capacity(original_len);
+    for _ in 0..original_len {
+        let with_size = original_reader;
+        let (size, size_bytes): (usize, usize) = original_reader.read_leb128_with_size()?;
+        if size > original_reader.len() {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "function body is larger than the code section"));
+        }
+        original_bodies.push((&with_size[..size_bytes + size], size_bytes));
+        original_reader = &original_reader[size..];
+    }
+
+    let encoded: io::Result<Vec<Vec<u8>>> = bodies.par_iter().enumerate()
+        .map(|(i, body)| {
+            let mut encoded = Vec::new();
+            body.0.encode(&mut encoded)?;
+            let mut buf = Vec::new();
+            match original_bodies.get(i) {
+                Some(&(with_size, size_bytes)) => {
+                    if is_same_encoding(&encoded, &Code::decode(&mut &with_size[size_bytes..])?)? {
+                        return Ok(with_size.to_vec());
+                    }
+                    // the size of a modified body keeps the length of the original size (if it fits)
+                    buf.write_leb128_padded(encoded.len(), size_bytes)?;
+                }
+                None => { encoded.len().encode(&mut buf)?; }
+            }
+            buf.extend(encoded);
+            Ok(buf)
+        })
+        .collect();
+    for buf in encoded? {
+        contents.extend(buf);
+    }
+    Ok(())
+}
+
+/// the original vector length if unchanged, otherwise padded to its original length (if it fits),
+/// returns the original vector length
+fn encode_len_preserving(len: usize, original_reader: &mut &[u8], contents: &mut Vec<u8>) -> io::Result<usize> {
+    let original_len_bytes = *original_reader;
+    let (original_len, len_bytes): (usize, usize) = original_reader.read_leb128_with_size()?;
+    if len == original_len {
+        contents.extend_from_slice(&original_len_bytes[..len_bytes]);
+    } else {
+        contents.write_leb128_padded(len, len_bytes)?;
+    }
+    Ok(original_len)
+}
+
+/// compares the regular encodings, i.e., ignoring padding etc. of the original
+fn is_same_encoding<T: WasmBinary>(encoded: &[u8], original: &T) -> io::Result<bool> {
+    let mut original_encoded = Vec::with_capacity(encoded.len());
+    original.encode(&mut original_encoded)?;
+    Ok(*encoded == original_encoded[..])
+}
+
+impl Section {
+    pub fn id(&self) -> u8 {
+        match *self {
+            Section::Custom(_) => 0,
+            Section::Type(_) => 1,
+            Section::Import(_) => 2,
+            Section::Function(_) => 3,
+            Section::Table(_) => 4,
+            Section::Memory(_) => 5,
+            Section::Global(_) => 6,
+            Section::Export(_) => 7,
+            Section::Start(_) => 8,
+            Section::Element(_) => 9,
+            Section::Code(_) => 10,
+            Section::Data(_) => 11,
+            Section::DataCount(_) => 12,
+            Section::Tag(_) => 13,
+        }
+    }
+}
+
+impl Module {
+    /// like encode(), but parts that are the same as in the original are written with their
+    /// original bytes, such that decode -> encode is the identity if the module was not modified
+    pub fn encode_preserving<W: io::Write>(&self, original: &OriginalEncoding, writer: &mut W) -> io::Result<usize> {
+        writer.write_all(b"\0asm")?;
+        writer.write_all(&[1, 0, 0, 0])?;
+        let mut bytes_written = 8;
+        // how many sections with the same id came before, to find the original section
+        let mut sections_before: HashMap<u8, usize> = HashMap::new();
+        for section in &self.sections {
+            let id = section.id();
+            let n = sections_before.entry(id).or_insert(0);
+            bytes_written += match original.section(id, *n) {
+                Some(original_section) => original.encode_section(section, original_section, writer)?,
+                None => section.encode(writer)?,
+            };
+            *n += 1;
+        }
+        Ok(bytes_written)
+    }
+}
+
+impl highlevel::Module {
+    /// see lowlevel::Module::encode_preserving(), but an unmodified module is written byte-for-byte
+    /// only if converting back to low-level gives the same sections as decoded. The conversion
+    /// groups imports by kind and numbers types by their first use, so if the original did not,
+    /// the import and type sections (and all parts that refer to type indices) are encoded
+    /// regularly from the converted module. Every other section (and function body) whose
+    /// contents are unchanged by the conversion is still written with its original bytes.
+    pub fn encode_preserving<W: io::Write>(self, original: &OriginalEncoding, writer: &mut W) -> io::Result<usize> {
+        let module: Module = self.into();
+        module.encode_preserving(original, writer)
+    }
+}
+
+
+/* Zero-copy decoding from byte slices (see lowlevel::BorrowedModule) */
+
+impl<'a> BorrowedModule<'a> {
+    pub fn decode(bytes: &'a [u8]) -> io::Result<Self> {
+        // same error context as in Module::decode, position is the absolute offset after the error
+        let with_offset = |e: io::Error, position: usize| DecodeError::add_context(e, |e| {
+            e.offset = Some(position - e.bytes_after);
+            e.bytes_after = 0;
+        });
+        let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message.to_string());
+
+        let mut reader = bytes;
+        let mut magic_number = [0u8; 4];
+        io::Read::read_exact(&mut reader, &mut magic_number).map_err(|e| with_offset(e, bytes.len() - reader.len()))?;
+        if &magic_number != b"\0asm" {
+            return Err(with_offset(invalid("magic bytes do not match"), 0));
+        }
+        let version = reader.read_u32::<LittleEndian>().map_err(|e| with_offset(e, bytes.len() - reader.len()))?;
+        if version != 1 {
+            return Err(with_offset(invalid("not version 1"), 4));
+        }
+
+        let mut sections = Vec::new();
+        while !reader.is_empty() {
+            let section_start = reader;
+            let id = reader[0];
+            let in_section = |e: io::Error, position: usize| DecodeError::add_context(with_offset(e, position), |e| e.section = Some(id));
+
+            // every section is sliced by its size, so the contents must be used up exactly
+            reader = &reader[1..];
+            let mut contents = decode_borrowed_bytes(&mut reader).map_err(|e| in_section(e, bytes.len() - reader.len()))?;
+            let section_end = bytes.len() - reader.len();
+            let contents_size = contents.len();
+            sections.push(match id {
+                // the only sections with names, raw bytes, or function bodies
+                0 | 2 | 7 | 11 => decode_borrowed_section(id, &mut contents).map_err(|e| in_section(e, section_end - contents.len()))?,
+                10 => BorrowedSection::Code(locate_bodies(&mut contents, section_end).map_err(|e| in_section(e, section_end - contents.len()))?),
+                _ => {
+                    let mut section_reader = &section_start[..section_start.len() - reader.len()];
+                    let section = Section::decode(&mut section_reader).map_err(|e| in_section(e, section_end - section_reader.len()))?;
+                    contents = section_reader;
+                    BorrowedSection::Other(section)
+                }
+            });
+            if !contents.is_empty() {
+                return Err(in_section(io::Error::new(io::ErrorKind::InvalidData, format!("section size mismatch: {} of {} bytes left after the contents", contents.len(), contents_size)), section_end));
+            }
+        }
+
+        Ok(BorrowedModule { sections })
+    }
+
+    /// the fully decoded module, same as Module::decode() on the same bytes
+    pub fn into_module(self) -> io::Result<Module> {
+        self.into_lazy().into_module()
+    }
+}
+
+fn decode_borrowed_section<'a>(id: u8, reader: &mut &'a [u8]) -> io::Result<BorrowedSection<'a>> {
+    Ok(match id {
+        0 => {
+            let contents = *reader;
+            *reader = &[];
+            BorrowedSection::Custom(contents)
+        }
+        2 => BorrowedSection::Import(decode_borrowed_vec(reader, decode_borrowed_import)
+            .map_err(|e| Section::field_context(e, "Section::Import"))?),
+        7 => BorrowedSection::Export(decode_borrowed_vec(reader, decode_borrowed_export)
+            .map_err(|e| Section::field_context(e, "Section::Export"))?),
+        11 => BorrowedSection::Data(decode_borrowed_vec(reader, decode_borrowed_data)
+            .map_err(|e| Section::field_context(e, "Section::Data"))?),
+        _ => unreachable!("section {} cannot borrow from the input", id),
+    })
+}
+
+fn decode_borrowed_import<'a>(reader: &mut &'a [u8]) -> io::Result<BorrowedImport<'a>> {
+    Ok(BorrowedImport {
+        module: decode_borrowed_str(reader).map_err(|e| Import::field_context(e, "Import.module"))?,
+        name: decode_borrowed_str(reader).map_err(|e| Import::field_context(e, "Import.name"))?,
+        type_: ImportType::decode(reader).map_err(|e| Import::field_context(e, "Import.type_"))?,
+    })
+}
+
+fn decode_borrowed_export<'a>(reader: &mut &'a [u8]) -> io::Result<BorrowedExport<'a>> {
+    Ok(BorrowedExport {
+        name: decode_borrowed_str(reader).map_err(|e| Export::field_context(e, "Export.name"))?,
+        type_: ExportType::decode(reader).map_err(|e| Export::field_context(e, "Export.type_"))?,
+    })
+}
+
+fn decode_borrowed_data<'a>(reader: &mut &'a [u8]) -> io::Result<BorrowedData<'a>> {
+    Ok(BorrowedData {
+        mode: decode_data_mode(reader)?,
+        init: decode_borrowed_bytes(reader)?,
+    })
+}
+
+/// same as Vec<T>::decode, incl. the error context
+fn decode_borrowed_vec<'a, T, F>(reader: &mut &'a [u8], decode_element: F) -> io::Result<Vec<T>>
+    where F: Fn(&mut &'a [u8]) -> io::Result<T>
+{
+    let size = usize::decode(reader)?;
+    let mut vec = Vec::new();
+    for i in 0..size {
+        vec.push(decode_element(reader).map_err(|e| DecodeError::add_context(e, |e| e.path.insert(0, format!("[{}]", i))))?);
+    }
+    Ok(vec)
+}
+
+/// same as Vec<u8>::decode, but without copying
+fn decode_borrowed_bytes<'a>(reader: &mut &'a [u8]) -> io::Result<&'a [u8]> {
+    let size = usize::decode(reader)?;
+    if size > reader.len() {
+        *reader = &[];
+        return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "failed to fill whole buffer"));
+    }
+    let (bytes, rest) = reader.split_at(size);
+    *reader = rest;
+    Ok(bytes)
+}
+
+/// same as String::decode, but without copying
+fn decode_borrowed_str<'a>(reader: &mut &'a [u8]) -> io::Result<&'a str> {
+    str::from_utf8(decode_borrowed_bytes(reader)?).map_err(|e| io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("utf-8 conversion error: {}", e.to_string())))
+}
@@ -0,0 +1,507 @@
+use ast::{ElemType, FunctionType, Idx, Label, Limits, Local, Memarg, MemoryType, Mutability, TableType, Val, ValType};
+use ast::highlevel::{Code, Data, DataMode, Element, ElementItems, ElementMode, Function, Global, GlobalOp, Instr, LoadOp, LocalOp, Memory, Module, NumericOp, StoreOp, Table};
+use ast::highlevel::Instr::*;
+use std::collections::BTreeMap;
+use std::io;
+
+/*
+ * Builder API for adding functions (with structured control flow), globals, memories, tables,
+ * segments, exports, and the start function to a high-level module, instead of pushing to the
+ * fields of Module and assembling instruction vectors (with matching Else/End) by hand.
+ * Simple mistakes, e.g., indices of items that do not exist, branches to labels outside of their
+ * block, or duplicate names, return an error right away instead of giving a module that fails
+ * validation later. Type errors inside function bodies are still only found by Module::validate().
+ * Indices must refer to items that were already added, except for recursive calls.
+ */
+
+/// 64 KiB pages, at most 4 GiB for 32-bit memories
+const MAX_PAGES: u64 = 1 << 16;
+/// for memory64, the whole 64-bit address space
+const MAX_PAGES_64: u64 = 1 << 48;
+const PAGE_SIZE: u64 = 65536;
+
+fn error<T>(message: String) -> io::Result<T> {
+    Err(io::Error::new(io::ErrorKind::InvalidInput, message))
+}
+
+pub struct ModuleBuilder<'a> {
+    module: &'a mut Module,
+}
+
+impl<'a> ModuleBuilder<'a> {
+    /// the module does not need to be empty, e.g., for adding functions during instrumentation
+    pub fn new(module: &'a mut Module) -> Self {
+        ModuleBuilder { module }
+    }
+
+    /// the closure builds the body (the final End is added automatically), parameter names are
+    /// kept as debug names and can be looked up with FunctionBuilder::local()
+    pub fn function<F>(&mut self, params: &[(&str, ValType)], results: &[ValType], body: F) -> io::Result<Idx<Function>>
+        where F: FnOnce(&mut FunctionBuilder) -> io::Result<()>
+    {
+        let idx = self.module.functions.len().into();
+        let function = {
+            let mut builder = FunctionBuilder::new(&*self.module, idx, params, results)?;
+            body(&mut builder)?;
+            builder.finish()
+        };
+        self.module.functions.push(function);
+        Ok(idx)
+    }
+
+    pub fn function_import(&mut self, module: &str, name: &str, type_: FunctionType) -> Idx<Function> {
+        self.module.add_function_import(type_, module.to_string(), name.to_string())
+    }
+
+    /// the type of the global is the one of the initial value
+    pub fn global(&mut self, mut_: Mutability, init: Val) -> Idx<Global> {
+        self.module.add_global(init.to_type(), mut_, vec![Const(init), End])
+    }
+
+    /// sizes are in 64 KiB pages
+    pub fn memory(&mut self, initial_size: u32, max_size: Option<u32>) -> io::Result<Idx<Memory>> {
+        self.add_memory(initial_size as u64, max_size.map(|max_size| max_size as u64), false)
+    }
+
+    /// memory with 64-bit addresses (memory64 proposal), sizes are in 64 KiB pages
+    pub fn memory64(&mut self, initial_size: u64, max_size: Option<u64>) -> io::Result<Idx<Memory>> {
+        self.add_memory(initial_size, max_size, true)
+    }
+
+    fn add_memory(&mut self, initial_size: u64, max_size: Option<u64>, memory64: bool) -> io::Result<Idx<Memory>> {
+        check_limits("memory", initial_size, max_size)?;
+        let max_pages = if memory64 { MAX_PAGES_64 } else { MAX_PAGES };
+        if initial_size > max_pages || max_size.map_or(false, |max_size| max_size > max_pages) {
+            return error(format!("memory size must be at most {} pages", max_pages));
+        }
+        self.module.memories.push(Memory {
+            type_: MemoryType(limits(initial_size, max_size, memory64)),
+            import: None,
+            export: Vec::new(),
+        });
+        Ok((self.module.memories.len() - 1).into())
+    }
+
+    /// sizes are in number of elements
+    pub fn table(&mut self, elem_type: ElemType, initial_size: u32, max_size: Option<u32>) -> io::Result<Idx<Table>> {
+        let (initial_size, max_size) = (initial_size as u64, max_size.map(|max_size| max_size as u64));
+        check_limits("table", initial_size, max_size)?;
+        self.module.tables.push(Table {
+            type_: TableType(elem_type, limits(initial_size, max_size, false)),
+            import: None,
+            export: Vec::new(),
+        });
+        Ok((self.module.tables.len() - 1).into())
+    }
+
+    /// active data segment, which must fit into the initial size of the memory
+    pub fn data(&mut self, memory: Idx<Memory>, offset: u64, bytes: &[u8]) -> io::Result<Idx<Data>> {
+        let memory64 = {
+            let limits = &self.existing_memory(memory)?.type_.0;
+            if !limits.memory64 && offset > u32::max_value() as u64 {
+                return error(format!("offset {} is too large for 32-bit memory #{}", offset, memory.0));
+            }
+            let memory_size = match limits.initial_size.checked_mul(PAGE_SIZE) {
+                Some(memory_size) => memory_size,
+                None => return error(format!("size of memory #{} with {} pages overflows", memory.0, limits.initial_size)),
+            };
+            if offset.checked_add(bytes.len() as u64).map_or(true, |end| end > memory_size) {
+                return error(format!("data segment at offset {} with {} bytes does not fit into memory #{} with {} pages",
+                                     offset, bytes.len(), memory.0, limits.initial_size));
+            }
+            limits.memory64
+        };
+        let offset = if memory64 { Val::I64(offset as i64) } else { Val::I32(offset as i32) };
+        self.module.data.push(Data {
+            mode: DataMode::Active { memory_idx: memory, offset: vec![Const(offset), End] },
+            bytes: bytes.to_vec(),
+        });
+        Ok((self.module.data.len() - 1).into())
+    }
+
+    /// active element segment, which must fit into the initial size of the (funcref) table
+    pub fn elements(&mut self, table: Idx<Table>, offset: u32, functions: &[Idx<Function>]) -> io::Result<Idx<Element>> {
+        {
+            let TableType(elem_type, limits) = self.existing_table(table)?.type_;
+            if elem_type != ElemType::FuncRef {
+                return error(format!("table #{} has elements of type {:?}, not functions", table.0, elem_type));
+            }
+            if offset as u64 + functions.len() as u64 > limits.initial_size {
+                return error(format!("element segment at offset {} with {} functions does not fit into table #{} of size {}",
+                                     offset, functions.len(), table.0, limits.initial_size));
+            }
+        }
+        for &function in functions {
+            self.existing_function(function)?;
+        }
+        self.module.elements.push(Element {
+            mode: ElementMode::Active { table_idx: table, offset: vec![Const(Val::I32(offset as i32)), End] },
+            items: ElementItems::Functions(functions.to_vec()),
+        });
+        Ok((self.module.elements.len() - 1).into())
+    }
+
+    /// the start function must have type [] -> [] and can only be set once
+    pub fn start(&mut self, function: Idx<Function>) -> io::Result<()> {
+        if let Some(start) = self.module.start {
+            return error(format!("start function is already set to function #{}", start.0));
+        }
+        {
+            let type_ = &self.existing_function(function)?.type_;
+            if !type_.params.is_empty() || !type_.results.is_empty() {
+                return error(format!("start function #{} must have type [] -> [], but has {:?}", function.0, type_));
+            }
+        }
+        self.module.start = Some(function);
+        Ok(())
+    }
+
+    pub fn export_function(&mut self, function: Idx<Function>, name: &str) -> io::Result<()> {
+        self.check_export_name(name)?;
+        match self.module.functions.get_mut(function.0) {
+            Some(function) => function.export.push(name.to_string()),
+            None => return error(format!("function #{} does not exist", function.0)),
+        }
+        Ok(())
+    }
+
+    pub fn export_global(&mut self, global: Idx<Global>, name: &str) -> io::Result<()> {
+        self.check_export_name(name)?;
+        match self.module.globals.get_mut(global.0) {
+            Some(global) => global.export.push(name.to_string()),
+            None => return error(format!("global #{} does not exist", global.0)),
+        }
+        Ok(())
+    }
+
+    pub fn export_table(&mut self, table: Idx<Table>, name: &str) -> io::Result<()> {
+        self.check_export_name(name)?;
+        match self.module.tables.get_mut(table.0) {
+            Some(table) => table.export.push(name.to_string()),
+            None => return error(format!("table #{} does not exist", table.0)),
+        }
+        Ok(())
+    }
+
+    pub fn export_memory(&mut self, memory: Idx<Memory>, name: &str) -> io::Result<()> {
+        self.check_export_name(name)?;
+        match self.module.memories.get_mut(memory.0) {
+            Some(memory) => memory.export.push(name.to_string()),
+            None => return error(format!("memory #{} does not exist", memory.0)),
+        }
+        Ok(())
+    }
+
+    /// export names must be unique across all kinds of items
+    fn check_export_name(&self, name: &str) -> io::Result<()> {
+        let module = &*self.module;
+        let mut exports = module.functions.iter().flat_map(|function| function.export.iter())
+            .chain(module.globals.iter().flat_map(|global| global.export.iter()))
+            .chain(module.tables.iter().flat_map(|table| table.export.iter()))
+            .chain(module.memories.iter().flat_map(|memory| memory.export.iter()))
+            .chain(module.tags.iter().flat_map(|tag| tag.export.iter()));
+        if exports.any(|export| export == name) {
+            return error(format!("duplicate export name '{}'", name));
+        }
+        Ok(())
+    }
+
+    fn existing_function(&self, function: Idx<Function>) -> io::Result<&Function> {
+        match self.module.functions.get(function.0) {
+            Some(function) => Ok(function),
+            None => error(format!("function #{} does not exist", function.0)),
+        }
+    }
+
+    fn existing_table(&self, table: Idx<Table>) -> io::Result<&Table> {
+        match self.module.tables.get(table.0) {
+            Some(table) => Ok(table),
+            None => error(format!("table #{} does not exist", table.0)),
+        }
+    }
+
+    fn existing_memory(&self, memory: Idx<Memory>) -> io::Result<&Memory> {
+        match self.module.memories.get(memory.0) {
+            Some(memory) => Ok(memory),
+            None => error(format!("memory #{} does not exist", memory.0)),
+        }
+    }
+}
+
+fn check_limits(kind: &str, initial_size: u64, max_size: Option<u64>) -> io::Result<()> {
+    match max_size {
+        Some(max_size) if initial_size > max_size =>
+            error(format!("{} has initial size {} larger than maximum size {}", kind, initial_size, max_size)),
+        _ => Ok(())
+    }
+}
+
+fn limits(initial_size: u64, max_size: Option<u64>, memory64: bool) -> Limits {
+    Limits {
+        initial_size,
+        max_size,
+        shared: false,
+        memory64,
+    }
+}
+
+
+/* Function bodies */
+
+/// Appends instructions to a function body, methods can be chained. Blocks, loops, and ifs take
+/// closures for their contents, such that Else and End are inserted automatically.
+pub struct FunctionBuilder<'a> {
+    module: &'a Module,
+    function: Idx<Function>,
+    type_: FunctionType,
+    locals: Vec<ValType>,
+    name: Option<String>,
+    local_names: BTreeMap<Idx<Local>, String>,
+    body: Vec<Instr>,
+    // ids of the enclosing blocks, innermost last, see BlockLabel
+    blocks: Vec<usize>,
+    block_count: usize,
+}
+
+/// label of a block, loop, or if, only valid inside the closure that builds its contents
+#[derive(Debug, Copy, Clone)]
+pub struct BlockLabel {
+    // unique per function, such that labels used outside of their block are detected, even if
+    // another block is at the same depth by now
+    id: usize,
+    depth: usize,
+}
+
+impl<'a> FunctionBuilder<'a> {
+    fn new(module: &'a Module, function: Idx<Function>, params: &[(&str, ValType)], results: &[ValType]) -> io::Result<Self> {
+        let mut builder = FunctionBuilder {
+            module,
+            function,
+            type_: FunctionType::new(params.iter().map(|&(_, type_)| type_).collect(), results.to_vec()),
+            locals: Vec::new(),
+            name: None,
+            local_names: BTreeMap::new(),
+            body: Vec::new(),
+            blocks: Vec::new(),
+            block_count: 0,
+        };
+        for (i, &(name, _)) in params.iter().enumerate() {
+            builder.name_local(i.into(), name)?;
+        }
+        Ok(builder)
+    }
+
+    fn finish(mut self) -> Function {
+        self.body.push(End);
+        Function {
+            type_: self.type_,
+            import: None,
+            code: Some(Code {
+                locals: self.locals,
+                body: self.body,
+            }),
+            export: Vec::new(),
+            name: self.name,
+            local_names: self.local_names,
+        }
+    }
+
+    /// index of the function that is being built, e.g., for recursive calls
+    pub fn function(&self) -> Idx<Function> {
+        self.function
+    }
+
+    /// debug name of the function
+    pub fn name(&mut self, name: &str) -> &mut Self {
+        self.name = Some(name.to_string());
+        self
+    }
+
+    /// add a new local (after the parameters and existing locals) and return its index
+    pub fn add_local(&mut self, name: &str, type_: ValType) -> io::Result<Idx<Local>> {
+        let idx = (self.type_.params.len() + self.locals.len()).into();
+        self.name_local(idx, name)?;
+        self.locals.push(type_);
+        Ok(idx)
+    }
+
+    /// index of the parameter or local with the given name
+    pub fn local(&self, name: &str) -> io::Result<Idx<Local>> {
+        match self.local_names.iter().find(|&(_, local_name)| local_name == name) {
+            Some((&idx, _)) => Ok(idx),
+            None => error(format!("no parameter or local named '{}'", name)),
+        }
+    }
+
+    fn name_local(&mut self, idx: Idx<Local>, name: &str) -> io::Result<()> {
+        if self.local_names.values().any(|local_name| local_name == name) {
+            return error(format!("duplicate parameter or local name '{}'", name));
+        }
+        self.local_names.insert(idx, name.to_string());
+        Ok(())
+    }
+
+    /* Structured control flow */
+
+    pub fn block(&mut self, type_: FunctionType, contents: impl FnOnce(&mut Self, BlockLabel) -> io::Result<()>) -> io::Result<&mut Self> {
+        let label = self.begin_block(Block(type_));
+        contents(self, label)?;
+        Ok(self.end_block())
+    }
+
+    /// branches to the label of a loop continue with the next iteration
+    pub fn loop_(&mut self, type_: FunctionType, contents: impl FnOnce(&mut Self, BlockLabel) -> io::Result<()>) -> io::Result<&mut Self> {
+        let label = self.begin_block(Loop(type_));
+        contents(self, label)?;
+        Ok(self.end_block())
+    }
+
+    /// without else branch, so the block must produce the same values that it takes
+    pub fn if_(&mut self, type_: FunctionType, then: impl FnOnce(&mut Self, BlockLabel) -> io::Result<()>) -> io::Result<&mut Self> {
+        if type_.params != type_.results {
+            return error(format!("if without else must have the same parameters as results, but has type {:?}", type_));
+        }
+        let label = self.begin_block(If(type_));
+        then(self, label)?;
+        Ok(self.end_block())
+    }
+
+    /// both branches get the same label
+    pub fn if_else(&mut self, type_: FunctionType, then: impl FnOnce(&mut Self, BlockLabel) -> io::Result<()>, else_: impl FnOnce(&mut Self, BlockLabel) -> io::Result<()>) -> io::Result<&mut Self> {
+        let label = self.begin_block(If(type_));
+        then(self, label)?;
+        self.body.push(Else);
+        else_(self, label)?;
+        Ok(self.end_block())
+    }
+
+    fn begin_block(&mut self, begin: Instr) -> BlockLabel {
+        self.body.push(begin);
+        let label = BlockLabel { id: self.block_count, depth: self.blocks.len() };
+        self.block_count += 1;
+        self.blocks.push(label.id);
+        label
+    }
+
+    fn end_block(&mut self) -> &mut Self {
+        self.blocks.pop();
+        self.body.push(End);
+        self
+    }
+
+    /// relative label index (0 = innermost block) of a label
+    fn label(&self, label: BlockLabel) -> io::Result<Idx<Label>> {
+        if self.blocks.get(label.depth) != Some(&label.id) {
+            return error("branch to a label outside of its block".to_string());
+        }
+        Ok((self.blocks.len() - 1 - label.depth).into())
+    }
+
+    /* Instructions */
+
+    /// any other instruction, without checks, except that blocks must be built with block(), loop_(),
+    /// if_(), or if_else() instead
+    pub fn instr(&mut self, instr: Instr) -> io::Result<&mut Self> {
+        match instr {
+            Block(_) | Loop(_) | If(_) | Else | End | Try(_) | Catch(_) | CatchAll | Delegate(_) =>
+                return error(format!("{} cannot be added directly, use block(), loop_(), if_(), or if_else() instead", instr.to_name())),
+            _ => {}
+        }
+        self.body.push(instr);
+        Ok(self)
+    }
+
+    pub fn unreachable(&mut self) -> io::Result<&mut Self> { self.instr(Unreachable) }
+    pub fn nop(&mut self) -> io::Result<&mut Self> { self.instr(Nop) }
+    pub fn drop(&mut self) -> io::Result<&mut Self> { self.instr(Drop) }
+    pub fn select(&mut self) -> io::Result<&mut Self> { self.instr(Select) }
+    pub fn return_(&mut self) -> io::Result<&mut Self> { self.instr(Return) }
+
+    pub fn br(&mut self, label: BlockLabel) -> io::Result<&mut Self> {
+        let label = self.label(label)?;
+        self.instr(Br(label))
+    }
+
+    pub fn br_if(&mut self, label: BlockLabel) -> io::Result<&mut Self> {
+        let label = self.label(label)?;
+        self.instr(BrIf(label))
+    }
+
+    pub fn br_table(&mut self, table: &[BlockLabel], default: BlockLabel) -> io::Result<&mut Self> {
+        let table = table.iter().map(|&label| self.label(label)).collect::<io::Result<_>>()?;
+        let default = self.label(default)?;
+        self.instr(BrTable(table, default))
+    }
+
+    pub fn call(&mut self, function: Idx<Function>) -> io::Result<&mut Self> {
+        // the function that is being built is not in the module yet
+        if function.0 > self.module.functions.len() {
+            return error(format!("function #{} does not exist", function.0));
+        }
+        self.instr(Call(function))
+    }
+
+    pub fn call_indirect(&mut self, type_: FunctionType, table: Idx<Table>) -> io::Result<&mut Self> {
+        if table.0 >= self.module.tables.len() {
+            return error(format!("table #{} does not exist", table.0));
+        }
+        self.instr(CallIndirect(type_, table))
+    }
+
+    pub fn local_get(&mut self, local: Idx<Local>) -> io::Result<&mut Self> { self.local_op(LocalOp::GetLocal, local) }
+    pub fn local_set(&mut self, local: Idx<Local>) -> io::Result<&mut Self> { self.local_op(LocalOp::SetLocal, local) }
+    pub fn local_tee(&mut self, local: Idx<Local>) -> io::Result<&mut Self> { self.local_op(LocalOp::TeeLocal, local) }
+
+    fn local_op(&mut self, op: LocalOp, local: Idx<Local>) -> io::Result<&mut Self> {
+        let (params, locals) = (self.type_.params.len(), self.locals.len());
+        if local.0 >= params + locals {
+            return error(format!("local #{} does not exist, function has {} parameters and {} locals", local.0, params, locals));
+        }
+        self.instr(Instr::Local(op, local))
+    }
+
+    pub fn global_get(&mut self, global: Idx<Global>) -> io::Result<&mut Self> {
+        self.existing_global(global)?;
+        self.instr(Instr::Global(GlobalOp::GetGlobal, global))
+    }
+
+    pub fn global_set(&mut self, global: Idx<Global>) -> io::Result<&mut Self> {
+        if let Mutability::Const = self.existing_global(global)?.type_.1 {
+            return error(format!("global #{} is immutable", global.0));
+        }
+        self.instr(Instr::Global(GlobalOp::SetGlobal, global))
+    }
+
+    fn existing_global(&self, global: Idx<Global>) -> io::Result<&Global> {
+        match self.module.globals.get(global.0) {
+            Some(global) => Ok(global),
+            None => error(format!("global #{} does not exist", global.0)),
+        }
+    }
+
+    /// with the natural alignment of the operation
+    pub fn load(&mut self, op: LoadOp, memory: Idx<Memory>, offset: u64) -> io::Result<&mut Self> {
+        self.check_memarg(memory, offset)?;
+        self.instr(Load(op, memory, Memarg { alignment: op.natural_alignment(), offset }))
+    }
+
+    /// with the natural alignment of the operation
+    pub fn store(&mut self, op: StoreOp, memory: Idx<Memory>, offset: u64) -> io::Result<&mut Self> {
+        self.check_memarg(memory, offset)?;
+        self.instr(Store(op, memory, Memarg { alignment: op.natural_alignment(), offset }))
+    }
+
+    fn check_memarg(&self, memory: Idx<Memory>, offset: u64) -> io::Result<()> {
+        let memory64 = match self.module.memories.get(memory.0) {
+            Some(memory) => memory.type_.0.memory64,
+            None => return error(format!("memory #{} does not exist", memory.0)),
+        };
+        if !memory64 && offset > u32::max_value() as u64 {
+            return error(format!("offset {} is too large for 32-bit memory #{}", offset, memory.0));
+        }
+        Ok(())
+    }
+
+    pub fn const_(&mut self, val: Val) -> io::Result<&mut Self> { self.instr(Const(val)) }
+    pub fn numeric(&mut self, op: NumericOp) -> io::Result<&mut Self> { self.instr(Numeric(op)) }
+}
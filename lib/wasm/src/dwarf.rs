@@ -0,0 +1,613 @@
+use ast::Idx;
+use ast::highlevel::{CodeOffsets, Function, Instr};
+use binary::raw_sections;
+use byteorder::{LittleEndian, ReadBytesExt};
+use leb128::ReadLeb128;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap};
+use std::io;
+use std::str;
+use WasmBinary;
+
+/*
+ * Source-level debug information from DWARF custom sections (as emitted by, e.g., clang -g), mapped
+ * to highlevel function and instruction indices.
+ * For WebAssembly, DWARF "addresses" are byte offsets relative to the start of the code section
+ * contents (i.e., after the section id and size), so we need the original byte offset of every
+ * instruction (see CodeOffsets), which is why this works on the module bytes and not on a decoded
+ * module.
+ * Only the parts needed here are supported (but for DWARF versions 2 to 5): the line number
+ * programs in .debug_line and the names of subprograms in .debug_info.
+ */
+
+#[derive(Debug, Clone, Default)]
+pub struct DebugInfo {
+    /// paths of all source files, SourceLocation.file is an index into this
+    pub files: Vec<String>,
+    /// per function with line information, the source location of its instructions, but only
+    /// for the first instruction of every run of instructions with the same location (sorted by
+    /// instruction index). None for instructions without a source location.
+    pub locations: BTreeMap<Idx<Function>, Vec<(Idx<Instr>, Option<SourceLocation>)>>,
+    /// source-level names of functions (from the DW_TAG_subprogram entries in .debug_info)
+    pub function_names: BTreeMap<Idx<Function>, String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SourceLocation {
+    pub file: usize,
+    pub line: u64,
+    /// 0 if the column is unknown
+    pub column: u64,
+}
+
+impl DebugInfo {
+    /// returns None if the module does not contain a .debug_line section
+    pub fn from_module_bytes(bytes: &[u8], offsets: &CodeOffsets) -> io::Result<Option<DebugInfo>> {
+        let sections = raw_sections(bytes)?;
+        let custom_section = |name: &str| sections.iter()
+            .filter_map(|&(id, _, contents)| if id == 0 { Some(contents) } else { None })
+            .filter_map(|mut contents| match String::decode(&mut contents) {
+                Ok(ref section_name) if section_name == name => Some(contents),
+                _ => None,
+            })
+            .next();
+
+        let debug_line = match custom_section(".debug_line") {
+            Some(debug_line) => debug_line,
+            None => return Ok(None),
+        };
+        let strings = Strings {
+            debug_str: custom_section(".debug_str").unwrap_or(&[]),
+            debug_line_str: custom_section(".debug_line_str").unwrap_or(&[]),
+        };
+        let code_section = offsets.code_section.clone();
+
+        let mut debug_info = DebugInfo::default();
+        let rows = line_rows(debug_line, &strings, code_section.len() as u64, &mut debug_info.files)?;
+        for (i, function) in offsets.functions.iter().enumerate() {
+            let function = match *function {
+                Some(ref function) => function,
+                None => continue,
+            };
+            let mut locations: Vec<(Idx<Instr>, Option<SourceLocation>)> = Vec::new();
+            for (instr_idx, &offset) in function.instrs.iter().enumerate() {
+                let location = location_at(&rows, (offset - code_section.start) as u64);
+                if locations.last().map_or(true, |&(_, ref previous)| *previous != location) {
+                    locations.push((instr_idx.into(), location));
+                }
+            }
+            if locations.iter().any(|&(_, ref location)| location.is_some()) {
+                debug_info.locations.insert(i.into(), locations);
+            }
+        }
+
+        if let (Some(debug_info_section), Some(debug_abbrev)) = (custom_section(".debug_info"), custom_section(".debug_abbrev")) {
+            debug_info.function_names = function_names(debug_info_section, debug_abbrev, &strings, offsets)?;
+        }
+
+        Ok(Some(debug_info))
+    }
+
+    pub fn location(&self, function: Idx<Function>, instr: Idx<Instr>) -> Option<&SourceLocation> {
+        let locations = self.locations.get(&function)?;
+        // the last run that starts at or before the instruction
+        let run = match locations.binary_search_by_key(&instr, |&(run_begin, _)| run_begin) {
+            Ok(i) => i,
+            Err(0) => return None,
+            Err(i) => i - 1,
+        };
+        locations[run].1.as_ref()
+    }
+}
+
+fn error(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+
+/* Reading DWARF sections */
+
+/// string sections that attributes can refer to
+struct Strings<'a> {
+    debug_str: &'a [u8],
+    debug_line_str: &'a [u8],
+}
+
+/// header information of the current unit that is needed for reading attribute values
+struct Unit<'a> {
+    version: u16,
+    dwarf64: bool,
+    address_size: u8,
+    strings: &'a Strings<'a>,
+}
+
+enum Value<'a> {
+    Unsigned(u64),
+    Str(&'a str),
+    /// blocks, signed values, flags, and indirect strings/addresses (which we do not resolve)
+    Other,
+}
+
+/// returns the contents of the unit (after its length field) and whether it is in 64-bit DWARF format
+fn read_unit<'a>(reader: &mut &'a [u8]) -> io::Result<(&'a [u8], bool)> {
+    let (length, dwarf64) = match reader.read_u32::<LittleEndian>()? {
+        0xffff_ffff => (reader.read_u64::<LittleEndian>()?, true),
+        length => (length as u64, false),
+    };
+    if length > reader.len() as u64 {
+        return Err(error(format!("DWARF unit length {} exceeds the section", length)));
+    }
+    let (unit, rest) = reader.split_at(length as usize);
+    *reader = rest;
+    Ok((unit, dwarf64))
+}
+
+fn read_offset(reader: &mut &[u8], dwarf64: bool) -> io::Result<u64> {
+    if dwarf64 {
+        reader.read_u64::<LittleEndian>()
+    } else {
+        reader.read_u32::<LittleEndian>().map(|offset| offset as u64)
+    }
+}
+
+fn read_address(reader: &mut &[u8], address_size: u8) -> io::Result<u64> {
+    match address_size {
+        1..=8 => reader.read_uint::<LittleEndian>(address_size as usize),
+        _ => Err(error(format!("unsupported DWARF address size {}", address_size))),
+    }
+}
+
+/// null-terminated string
+fn read_str<'a>(reader: &mut &'a [u8]) -> io::Result<&'a str> {
+    let bytes: &'a [u8] = *reader;
+    match bytes.iter().position(|&byte| byte == 0) {
+        Some(len) => {
+            *reader = &bytes[len + 1..];
+            str::from_utf8(&bytes[..len]).map_err(|e| error(format!("invalid DWARF string: {}", e)))
+        }
+        None => Err(error("unterminated DWARF string".into())),
+    }
+}
+
+fn str_at(section: &[u8], offset: u64) -> io::Result<&str> {
+    match section.get(offset as usize..) {
+        Some(mut reader) => read_str(&mut reader),
+        None => Err(error(format!("DWARF string offset 0x{:x} is out of bounds", offset))),
+    }
+}
+
+fn skip(reader: &mut &[u8], len: u64) -> io::Result<()> {
+    if len > reader.len() as u64 {
+        return Err(error("unexpected end of DWARF section".into()));
+    }
+    *reader = &reader[len as usize..];
+    Ok(())
+}
+
+/// reads an attribute value of the given form (DW_FORM_*)
+fn read_value<'a>(reader: &mut &'a [u8], form: u64, unit: &Unit<'a>) -> io::Result<Value<'a>> {
+    use self::Value::*;
+    Ok(match form {
+        // addr
+        0x01 => Unsigned(read_address(reader, unit.address_size)?),
+        // data1, ref1, flag
+        0x0b | 0x11 | 0x0c => Unsigned(reader.read_u8()? as u64),
+        // data2, ref2
+        0x05 | 0x12 => Unsigned(reader.read_u16::<LittleEndian>()? as u64),
+        // data4, ref4, ref_sup4
+        0x06 | 0x13 | 0x1c => Unsigned(reader.read_u32::<LittleEndian>()? as u64),
+        // data8, ref8, ref_sig8, ref_sup8
+        0x07 | 0x14 | 0x20 | 0x24 => Unsigned(reader.read_u64::<LittleEndian>()?),
+        // udata, ref_udata, loclistx, rnglistx
+        0x0f | 0x15 | 0x22 | 0x23 => Unsigned(reader.read_leb128()?),
+        // ref_addr (address-sized only in DWARF 2), sec_offset, strp_sup, GNU_ref_alt, GNU_strp_alt
+        0x10 if unit.version == 2 => Unsigned(read_address(reader, unit.address_size)?),
+        0x10 | 0x17 | 0x1d | 0x1f20 | 0x1f21 => Unsigned(read_offset(reader, unit.dwarf64)?),
+
+        // string, strp, line_strp
+        0x08 => Str(read_str(reader)?),
+        0x0e => Str(str_at(unit.strings.debug_str, read_offset(reader, unit.dwarf64)?)?),
+        0x1f => Str(str_at(unit.strings.debug_line_str, read_offset(reader, unit.dwarf64)?)?),
+
+        // strx, addrx, GNU_addr_index, GNU_str_index: need .debug_str_offsets/.debug_addr
+        0x1a | 0x1b | 0x1f01 | 0x1f02 => {
+            let _index: u64 = reader.read_leb128()?;
+            Other
+        }
+        // strx1-4, addrx1-4
+        0x25 | 0x29 => { skip(reader, 1)?; Other }
+        0x26 | 0x2a => { skip(reader, 2)?; Other }
+        0x27 | 0x2b => { skip(reader, 3)?; Other }
+        0x28 | 0x2c => { skip(reader, 4)?; Other }
+
+        // sdata
+        0x0d => {
+            let _value: i64 = reader.read_leb128()?;
+            Other
+        }
+        // data16
+        0x1e => { skip(reader, 16)?; Other }
+        // block1, block2, block4, block, exprloc
+        0x0a => {
+            let len = reader.read_u8()? as u64;
+            skip(reader, len)?;
+            Other
+        }
+        0x03 => {
+            let len = reader.read_u16::<LittleEndian>()? as u64;
+            skip(reader, len)?;
+            Other
+        }
+        0x04 => {
+            let len = reader.read_u32::<LittleEndian>()? as u64;
+            skip(reader, len)?;
+            Other
+        }
+        0x09 | 0x18 => {
+            let len: u64 = reader.read_leb128()?;
+            skip(reader, len)?;
+            Other
+        }
+        // flag_present, implicit_const (the value is in the abbreviation)
+        0x19 | 0x21 => Other,
+        // indirect: the actual form precedes the value
+        0x16 => {
+            let form: u64 = reader.read_leb128()?;
+            read_value(reader, form, unit)?
+        }
+        form => return Err(error(format!("unknown DWARF form 0x{:x}", form))),
+    })
+}
+
+
+/* .debug_line */
+
+/// one row of the line number matrix, only with the registers we care about
+struct Row {
+    address: u64,
+    end_sequence: bool,
+    location: Option<SourceLocation>,
+}
+
+/// the rows of all sequences of all line number programs, sorted by address (sequence ends first)
+fn line_rows(debug_line: &[u8], strings: &Strings, code_size: u64, files: &mut Vec<String>) -> io::Result<Vec<Row>> {
+    let mut file_indices: HashMap<String, usize> = files.iter().cloned().enumerate().map(|(i, file)| (file, i)).collect();
+    let mut rows = Vec::new();
+
+    let mut reader = debug_line;
+    while !reader.is_empty() {
+        let (mut unit, dwarf64) = read_unit(&mut reader)?;
+        let version = unit.read_u16::<LittleEndian>()?;
+        if version < 2 || version > 5 {
+            return Err(error(format!("unsupported .debug_line version {}", version)));
+        }
+        // addresses of set_address are sized by the opcode length anyway
+        let address_size = if version >= 5 {
+            let address_size = unit.read_u8()?;
+            let _segment_selector_size = unit.read_u8()?;
+            address_size
+        } else {
+            4
+        };
+        let header_length = read_offset(&mut unit, dwarf64)?;
+        if header_length > unit.len() as u64 {
+            return Err(error(format!("line number program header length {} exceeds the unit", header_length)));
+        }
+        let (mut header, mut program) = unit.split_at(header_length as usize);
+        let unit_info = Unit { version, dwarf64, address_size, strings };
+
+        let minimum_instruction_length = header.read_u8()? as u64;
+        if version >= 4 {
+            let _maximum_operations_per_instruction = header.read_u8()?;
+        }
+        let _default_is_stmt = header.read_u8()?;
+        let line_base = header.read_i8()? as i64;
+        let line_range = header.read_u8()? as u64;
+        if line_range == 0 {
+            return Err(error("line number program has a line range of 0".into()));
+        }
+        let opcode_base = header.read_u8()?;
+        let mut standard_opcode_lengths = Vec::new();
+        for _ in 1..opcode_base {
+            standard_opcode_lengths.push(header.read_u8()?);
+        }
+
+        // file register values to global file indices (None for file 0 before DWARF 5)
+        let mut unit_files: Vec<Option<usize>> = Vec::new();
+        let mut intern = |path: String| -> usize {
+            let next_index = files.len();
+            let index = *file_indices.entry(path.clone()).or_insert(next_index);
+            if index == next_index {
+                files.push(path);
+            }
+            index
+        };
+        let directories: Vec<&str> = if version >= 5 {
+            let directories = read_entry_list(&mut header, &unit_info)?;
+            for (path, directory) in read_entry_list(&mut header, &unit_info)? {
+                let directory = directories.get(directory as usize).map_or("", |&(directory, _)| directory);
+                unit_files.push(Some(intern(join_path(directory, path))));
+            }
+            directories.into_iter().map(|(directory, _)| directory).collect()
+        } else {
+            // directory 0 is the compilation directory, which is not part of the line number program
+            let mut directories = vec![""];
+            loop {
+                match read_str(&mut header)? {
+                    "" => break,
+                    directory => directories.push(directory),
+                }
+            }
+            unit_files.push(None);
+            loop {
+                let path = read_str(&mut header)?;
+                if path.is_empty() {
+                    break;
+                }
+                let directory: u64 = header.read_leb128()?;
+                let _modification_time: u64 = header.read_leb128()?;
+                let _length: u64 = header.read_leb128()?;
+                let directory = directories.get(directory as usize).cloned().unwrap_or("");
+                unit_files.push(Some(intern(join_path(directory, path))));
+            }
+            directories
+        };
+
+        // state machine registers, reset after each sequence
+        let (mut address, mut file, mut line, mut column) = (0u64, 1u64, 1u64, 0u64);
+        let mut sequence: Vec<Row> = Vec::new();
+        while !program.is_empty() {
+            let opcode = program.read_u8()?;
+            let mut emit_row = false;
+            if opcode >= opcode_base {
+                // special opcode: advance address and line at once, then append a row
+                let adjusted_opcode = (opcode - opcode_base) as u64;
+                address += (adjusted_opcode / line_range) * minimum_instruction_length;
+                line = (line as i64 + line_base + (adjusted_opcode % line_range) as i64) as u64;
+                emit_row = true;
+            } else {
+                match opcode {
+                    // extended opcodes
+                    0 => {
+                        let len: u64 = program.read_leb128()?;
+                        if len == 0 || len > program.len() as u64 {
+                            return Err(error(format!("invalid length {} of extended line number opcode", len)));
+                        }
+                        let (mut extended, rest) = program.split_at(len as usize);
+                        program = rest;
+                        match extended.read_u8()? {
+                            // end_sequence
+                            0x01 => {
+                                sequence.push(Row { address, end_sequence: true, location: None });
+                                // functions removed by the linker have "tombstone" addresses (0 or -1)
+                                let keep = match sequence.first() {
+                                    Some(first) => first.address != 0 && first.address < code_size,
+                                    None => false,
+                                };
+                                if keep {
+                                    rows.append(&mut sequence);
+                                } else {
+                                    sequence.clear();
+                                }
+                                address = 0;
+                                file = 1;
+                                line = 1;
+                                column = 0;
+                            }
+                            // set_address
+                            0x02 => {
+                                let address_size = extended.len() as u8;
+                                address = read_address(&mut extended, address_size)?;
+                            }
+                            // define_file (only before DWARF 5)
+                            0x03 => {
+                                let path = read_str(&mut extended)?;
+                                let directory: u64 = extended.read_leb128()?;
+                                let directory = directories.get(directory as usize).cloned().unwrap_or("");
+                                unit_files.push(Some(intern(join_path(directory, path))));
+                            }
+                            // set_discriminator and vendor extensions
+                            _ => {}
+                        }
+                    }
+                    // copy
+                    1 => emit_row = true,
+                    // advance_pc
+                    2 => {
+                        let advance: u64 = program.read_leb128()?;
+                        address += advance * minimum_instruction_length;
+                    }
+                    // advance_line
+                    3 => {
+                        let advance: i64 = program.read_leb128()?;
+                        line = (line as i64 + advance) as u64;
+                    }
+                    // set_file
+                    4 => file = program.read_leb128()?,
+                    // set_column
+                    5 => column = program.read_leb128()?,
+                    // negate_stmt, set_basic_block, set_prologue_end, set_epilogue_begin
+                    6 | 7 | 10 | 11 => {}
+                    // const_add_pc: advance address like special opcode 255
+                    8 => address += ((255 - opcode_base as u64) / line_range) * minimum_instruction_length,
+                    // fixed_advance_pc
+                    9 => address += program.read_u16::<LittleEndian>()? as u64,
+                    // set_isa and unknown standard opcodes: skip their ULEB128 operands
+                    _ => for _ in 0..standard_opcode_lengths[opcode as usize - 1] {
+                        let _operand: u64 = program.read_leb128()?;
+                    }
+                }
+            }
+
+            if emit_row {
+                let location = match unit_files.get(file as usize) {
+                    // line 0 means the instruction has no source location
+                    Some(&Some(file)) if line != 0 => Some(SourceLocation { file, line, column }),
+                    _ => None,
+                };
+                sequence.push(Row { address, end_sequence: false, location });
+            }
+        }
+    }
+
+    // stable sort, such that of rows with the same address, the last one wins (see location_at)
+    // except that the end of a sequence must not hide the start of the next one
+    rows.sort_by_key(|row| (row.address, !row.end_sequence));
+    Ok(rows)
+}
+
+/// DWARF 5 directory and file name entries: path and directory index of each entry
+fn read_entry_list<'a>(header: &mut &'a [u8], unit: &Unit<'a>) -> io::Result<Vec<(&'a str, u64)>> {
+    // DW_LNCT_*
+    const PATH: u64 = 0x1;
+    const DIRECTORY_INDEX: u64 = 0x2;
+
+    let format_count = header.read_u8()?;
+    let mut format = Vec::new();
+    for _ in 0..format_count {
+        let content_type: u64 = header.read_leb128()?;
+        let form: u64 = header.read_leb128()?;
+        format.push((content_type, form));
+    }
+
+    let count: u64 = header.read_leb128()?;
+    let mut entries = Vec::new();
+    for _ in 0..count {
+        let mut entry = ("", 0);
+        for &(content_type, form) in &format {
+            match (content_type, read_value(header, form, unit)?) {
+                (PATH, Value::Str(path)) => entry.0 = path,
+                (DIRECTORY_INDEX, Value::Unsigned(directory)) => entry.1 = directory,
+                _ => {}
+            }
+        }
+        entries.push(entry);
+    }
+    Ok(entries)
+}
+
+fn join_path(directory: &str, path: &str) -> String {
+    if directory.is_empty() || path.starts_with('/') {
+        path.to_string()
+    } else if directory.ends_with('/') {
+        format!("{}{}", directory, path)
+    } else {
+        format!("{}/{}", directory, path)
+    }
+}
+
+/// the location of the last row at or before the address (if that row is not a sequence end)
+fn location_at(rows: &[Row], address: u64) -> Option<SourceLocation> {
+    let rows_before = match rows.binary_search_by(|row| if row.address <= address { Ordering::Less } else { Ordering::Greater }) {
+        Ok(i) | Err(i) => i,
+    };
+    if rows_before == 0 {
+        return None;
+    }
+    rows[rows_before - 1].location.clone()
+}
+
+
+/* .debug_info */
+
+struct Abbreviation {
+    tag: u64,
+    /// attribute name and form
+    attributes: Vec<(u64, u64)>,
+}
+
+fn abbreviations(debug_abbrev: &[u8], offset: u64) -> io::Result<HashMap<u64, Abbreviation>> {
+    let mut reader = match debug_abbrev.get(offset as usize..) {
+        Some(reader) => reader,
+        None => return Err(error(format!("abbreviation offset 0x{:x} is out of bounds", offset))),
+    };
+    let mut abbreviations = HashMap::new();
+    loop {
+        let code: u64 = reader.read_leb128()?;
+        if code == 0 {
+            return Ok(abbreviations);
+        }
+        let tag: u64 = reader.read_leb128()?;
+        let _has_children = reader.read_u8()?;
+        let mut attributes = Vec::new();
+        loop {
+            let name: u64 = reader.read_leb128()?;
+            let form: u64 = reader.read_leb128()?;
+            if name == 0 && form == 0 {
+                break;
+            }
+            // implicit_const
+            if form == 0x21 {
+                let _value: i64 = reader.read_leb128()?;
+            }
+            attributes.push((name, form));
+        }
+        abbreviations.insert(code, Abbreviation { tag, attributes });
+    }
+}
+
+fn function_names(debug_info: &[u8], debug_abbrev: &[u8], strings: &Strings, offsets: &CodeOffsets) -> io::Result<BTreeMap<Idx<Function>, String>> {
+    const DW_TAG_SUBPROGRAM: u64 = 0x2e;
+    const DW_AT_NAME: u64 = 0x03;
+    const DW_AT_LOW_PC: u64 = 0x11;
+
+    let mut names = BTreeMap::new();
+    let mut reader = debug_info;
+    while !reader.is_empty() {
+        let (mut unit, dwarf64) = read_unit(&mut reader)?;
+        let version = unit.read_u16::<LittleEndian>()?;
+        let (abbreviation_offset, address_size) = match version {
+            2..=4 => {
+                let abbreviation_offset = read_offset(&mut unit, dwarf64)?;
+                (abbreviation_offset, unit.read_u8()?)
+            }
+            5 => {
+                let unit_type = unit.read_u8()?;
+                let address_size = unit.read_u8()?;
+                let abbreviation_offset = read_offset(&mut unit, dwarf64)?;
+                match unit_type {
+                    // DW_UT_compile, DW_UT_partial
+                    0x01 | 0x03 => {}
+                    // DW_UT_skeleton, DW_UT_split_compile: followed by the DWO id
+                    0x04 | 0x05 => skip(&mut unit, 8)?,
+                    // type units do not contain functions with code
+                    _ => continue,
+                }
+                (abbreviation_offset, address_size)
+            }
+            _ => return Err(error(format!("unsupported .debug_info version {}", version))),
+        };
+        let abbreviations = abbreviations(debug_abbrev, abbreviation_offset)?;
+        let unit_info = Unit { version, dwarf64, address_size, strings };
+
+        // flat walk over all entries, null entries (code 0) just end a list of children
+        while !unit.is_empty() {
+            let code: u64 = unit.read_leb128()?;
+            if code == 0 {
+                continue;
+            }
+            let abbreviation = match abbreviations.get(&code) {
+                Some(abbreviation) => abbreviation,
+                None => return Err(error(format!("unknown DWARF abbreviation code {}", code))),
+            };
+            let mut low_pc = None;
+            let mut name = None;
+            for &(attribute, form) in &abbreviation.attributes {
+                match (attribute, read_value(&mut unit, form, &unit_info)?) {
+                    (DW_AT_LOW_PC, Value::Unsigned(address)) => low_pc = Some(address),
+                    (DW_AT_NAME, Value::Str(value)) => name = Some(value),
+                    _ => {}
+                }
+            }
+            if abbreviation.tag == DW_TAG_SUBPROGRAM {
+                if let (Some(low_pc), Some(name)) = (low_pc, name) {
+                    let offset = (low_pc as usize).checked_add(offsets.code_section.start);
+                    if let Some(function) = offset.and_then(|offset| offsets.function(offset)) {
+                        names.entry(function).or_insert_with(|| name.to_string());
+                    }
+                }
+            }
+        }
+    }
+    Ok(names)
+}
@@ -6,6 +6,7 @@ extern crate byteorder;
 #[macro_use]
 extern crate derive_new;
 extern crate leb128;
+extern crate memmap;
 extern crate rayon;
 extern crate serde;
 #[macro_use]
@@ -15,7 +16,11 @@ extern crate test_utilities;
 
 pub mod ast;
 mod binary;
-pub use self::binary::WasmBinary;
+pub mod builder;
+pub mod dwarf;
+pub mod validate;
+pub mod wat;
+pub use self::binary::{DecodeError, OriginalEncoding, WasmBinary};
 
 #[cfg(test)]
 mod tests;
@@ -24,9 +29,10 @@ mod tests;
  * convenience for working files (which is the most common io::Read/Write anyway)
  */
 
-use ast::{highlevel, lowlevel};
-use std::fs::File;
+use ast::{highlevel, lowlevel, remap};
+use std::fs::{self, File};
 use std::io::{self, BufReader, BufWriter};
+use std::ops::Deref;
 use std::path::Path;
 
 impl lowlevel::Module {
@@ -34,18 +40,94 @@ impl lowlevel::Module {
         Self::decode(&mut BufReader::new(File::open(path)?))
     }
 
+    /// rejects malformed binaries that from_file() accepts, see Module::decode_strict
+    pub fn from_file_strict<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::decode_strict(&mut BufReader::new(File::open(path)?))
+    }
+
     pub fn to_file<P: AsRef<Path>>(&self, path: P) -> io::Result<usize> {
         self.encode(&mut BufWriter::new(File::create(path)?))
     }
 }
 
+/// read-only memory-mapped file, e.g., to decode very large binaries without copying them (see
+/// lowlevel::BorrowedModule::from_mapped_file)
+pub struct MappedFile(memmap::Mmap);
+
+impl MappedFile {
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        // unsafe because the file could be modified (by other processes) while it is mapped
+        Ok(MappedFile(unsafe { memmap::Mmap::map(&File::open(path)?)? }))
+    }
+}
+
+impl Deref for MappedFile {
+    type Target = [u8];
+    fn deref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl<'a> lowlevel::BorrowedModule<'a> {
+    pub fn from_mapped_file(file: &'a MappedFile) -> io::Result<Self> {
+        Self::decode(file)
+    }
+}
+
 impl highlevel::Module {
     pub fn from_file<P: AsRef<Path>>(path: P) -> io::Result<Self> {
-        Ok(lowlevel::Module::from_file(path)?.into())
+        Self::from_lowlevel(lowlevel::Module::from_file(path)?)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> io::Result<Self> {
+        Self::from_lowlevel(lowlevel::Module::decode(&mut &bytes[..])?)
+    }
+
+    /// rejects malformed binaries that from_bytes() accepts, see lowlevel::Module::decode_strict
+    pub fn from_bytes_strict(bytes: &[u8]) -> io::Result<Self> {
+        Self::from_lowlevel(lowlevel::Module::decode_strict(&mut &bytes[..])?)
+    }
+
+    /// same result as from_file(), but decodes function bodies lazily and in parallel, such that
+    /// the low-level code is never in memory as a whole (see lowlevel::LazyModule), e.g., for
+    /// very large binaries
+    pub fn from_file_lazy<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::from_bytes_lazy(&fs::read(path)?)
+    }
+
+    pub fn from_bytes_lazy(bytes: &[u8]) -> io::Result<Self> {
+        lowlevel::LazyModule::decode(bytes)?.into_highlevel()
+    }
+
+    /// only the module info (types, imports, exports etc.), without decoding any function body,
+    /// see LazyModule::into_highlevel_without_code
+    pub fn from_file_without_code<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        lowlevel::LazyModule::decode(&fs::read(path)?)?.into_highlevel_without_code()
+    }
+
+    fn from_lowlevel(module: lowlevel::Module) -> io::Result<Self> {
+        remap::check_custom_sections(&module.sections)?;
+        module.check_body_count()?;
+        Ok(module.into())
     }
 
     pub fn to_file<P: AsRef<Path>>(self, path: P) -> io::Result<usize> {
         let module: lowlevel::Module = self.into();
         module.to_file(path)
     }
+
+    /// also keeps the original byte offsets of function bodies and instructions (see CodeOffsets)
+    pub fn from_file_with_offsets<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::from_bytes_with_offsets(&fs::read(path)?)
+    }
+
+    pub fn from_bytes_with_offsets(bytes: &[u8]) -> io::Result<Self> {
+        let mut module = Self::from_bytes(bytes)?;
+        module.offsets = Some(highlevel::CodeOffsets::from_module_bytes(bytes)?);
+        Ok(module)
+    }
+
+    pub fn from_wat_file<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        wat::parse_module(&fs::read_to_string(path)?)
+    }
 }
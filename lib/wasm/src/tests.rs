@@ -1,28 +1,1183 @@
-use ast::{highlevel, lowlevel};
-use binary::WasmBinary;
+use ast::{highlevel, lowlevel, ElemType, FunctionType, Idx, ValType};
+use binary::{raw_sections, DecodeError, OriginalEncoding, StrictReader, WasmBinary};
+use builder::ModuleBuilder;
+use dwarf::{DebugInfo, SourceLocation};
+use MappedFile;
+use std::collections::HashMap;
 use std::fs::File;
 use std::io::{self, Read};
+use std::path::Path;
 use test::Bencher;
 use test_utilities::*;
+use validate::Features;
+use wat;
 
 const TEST_INPUTS: &'static str = "../../tests/inputs";
+const INVALID_INPUTS: &'static str = "../../tests/invalid";
+const MALFORMED_INPUTS: &'static str = "../../tests/malformed";
 const LARGE_WASM_FILE: &'static str = "../../tests/inputs/real-world/bananabread/bb.wasm";
+/// compiler output (rustc + wasm-ld) with several imports and types, a table, a name section, and
+/// padded LEB128s in the code, see tests/inputs/no-core-rs/build.sh
+const RUST_WASM_FILE: &'static str = "../../tests/inputs/no-core-rs/program.wasm";
+/// the same, but with the memory imported before all functions, i.e., imports not grouped by kind
+const RUST_IMPORTED_MEMORY_WASM_FILE: &'static str = "../../tests/inputs/no-core-rs/program-imported-memory.wasm";
+
+/// decode an output file again and validate it in-process (i.e., checks the encoder as well), and
+/// cross-check with WABT's wasm-validate, so bugs in our own validator do not hide encoder bugs
+fn validate_output(path: &Path) {
+    let module = highlevel::Module::from_file(path)
+        .expect(&format!("could not decode wasm output file '{}'", path.display()));
+    if let Err(error) = module.validate() {
+        panic!("invalid wasm output file '{}': {}", path.display(), error);
+    }
+    wasm_validate(path)
+        .expect(&format!("could not validate wasm output file '{}'", path.display()));
+}
 
 #[test]
 fn decode_encode_is_valid_wasm() {
     for path in wasm_files(TEST_INPUTS).unwrap() {
         let module = highlevel::Module::from_file(&path)
             .expect(&format!("could not decode valid wasm file '{}'", path.display()));
+        if let Err(error) = module.validate() {
+            panic!("valid wasm file '{}' does not validate: {}", path.display(), error);
+        }
 
         let output_path = &output_file(path, "encode").unwrap();
         module.to_file(output_path)
             .expect(&format!("could not encode wasm to file '{}'", output_path.display()));
 
-        wasm_validate(output_path)
-            .expect(&format!("could not validate wasm output file '{}'", output_path.display()));
+        validate_output(output_path);
+    }
+}
+
+#[test]
+fn parse_wat_encode_is_valid_wasm() {
+    for path in wat_files(TEST_INPUTS).unwrap() {
+        let module = highlevel::Module::from_wat_file(&path)
+            .expect(&format!("could not parse wat file '{}'", path.display()));
+
+        let output_path = &output_file(path.with_extension("wasm"), "wat").unwrap();
+        module.to_file(output_path)
+            .expect(&format!("could not encode wasm to file '{}'", output_path.display()));
+
+        validate_output(output_path);
+    }
+}
+
+#[test]
+fn invalid_wat_is_rejected() {
+    for path in wat_files(INVALID_INPUTS).unwrap() {
+        let result = highlevel::Module::from_wat_file(&path)
+            .and_then(|module| Ok(module.validate()?));
+        assert!(result.is_err(), "invalid wat file '{}' was accepted", path.display());
+    }
+}
+
+#[test]
+fn wat_folded_and_flat_instructions_are_equivalent() {
+    let flat = wat::parse_module(r#"(module
+        (func $f (param $x i32) (result i32)
+            get_local $x
+            if (result i32)
+                i32.const 1
+            else
+                get_local $x
+                i32.const 1
+                i32.sub
+                call $f
+            end))"#).unwrap();
+    let folded = wat::parse_module(r#"(module
+        (func $f (param $x i32) (result i32)
+            (if (result i32) (local.get $x)
+                (then (i32.const 1))
+                (else (call $f (i32.sub (local.get $x) (i32.const 1)))))))"#).unwrap();
+
+    assert_eq!(flat.functions[0].code.as_ref().unwrap().body, folded.functions[0].code.as_ref().unwrap().body);
+    assert_eq!(folded.functions[0].name, Some("f".to_string()));
+    assert_eq!(folded.functions[0].local_names.values().collect::<Vec<_>>(), vec!["x"]);
+}
+
+#[test]
+fn builder_inserts_else_and_end_like_wat() {
+    use ast::{Mutability, Val, ValType::I32};
+    use ast::highlevel::NumericOp::{I32Add, I32GeS, I32LtS, I32Mul, I32Sub};
+
+    let mut module = highlevel::Module::default();
+    let main = {
+        let mut builder = ModuleBuilder::new(&mut module);
+        let print = builder.function_import("env", "print", FunctionType::new(vec![I32], vec![]));
+        let factorial = builder.function(&[("n", I32)], &[I32], |f| {
+            let (n, factorial) = (f.local("n")?, f.function());
+            f.local_get(n)?.const_(Val::I32(2))?.numeric(I32LtS)?
+                .if_else(FunctionType::new(vec![], vec![I32]), |f, _| {
+                    f.const_(Val::I32(1))?;
+                    Ok(())
+                }, |f, _| {
+                    f.local_get(n)?.local_get(n)?.const_(Val::I32(1))?.numeric(I32Sub)?.call(factorial)?.numeric(I32Mul)?;
+                    Ok(())
+                })?;
+            Ok(())
+        }).unwrap();
+        let main = builder.function(&[], &[], |f| {
+            let i = f.add_local("i", I32)?;
+            f.block(FunctionType::new(vec![], vec![]), |f, exit| {
+                f.loop_(FunctionType::new(vec![], vec![]), |f, repeat| {
+                    f.local_get(i)?.const_(Val::I32(10))?.numeric(I32GeS)?.br_if(exit)?
+                        .local_get(i)?.call(factorial)?.call(print)?
+                        .local_get(i)?.const_(Val::I32(1))?.numeric(I32Add)?.local_set(i)?
+                        .br(repeat)?;
+                    Ok(())
+                })?;
+                Ok(())
+            })?;
+            Ok(())
+        }).unwrap();
+
+        builder.start(main).unwrap();
+        builder.export_function(factorial, "factorial").unwrap();
+        let memory = builder.memory(1, None).unwrap();
+        builder.data(memory, 16, b"hello").unwrap();
+        builder.export_memory(memory, "memory").unwrap();
+        let table = builder.table(ElemType::FuncRef, 2, Some(2)).unwrap();
+        builder.elements(table, 0, &[factorial, main]).unwrap();
+        let counter = builder.global(Mutability::Mut, Val::I32(0));
+        builder.export_global(counter, "counter").unwrap();
+        main
+    };
+    module.validate().unwrap();
+    assert_eq!(module.start, Some(main));
+
+    let expected = wat::parse_module(r#"(module
+        (import "env" "print" (func $print (param i32)))
+        (func $factorial (param $n i32) (result i32)
+            (if (result i32) (i32.lt_s (local.get $n) (i32.const 2))
+                (then (i32.const 1))
+                (else (i32.mul (local.get $n) (call $factorial (i32.sub (local.get $n) (i32.const 1)))))))
+        (func $main (local $i i32)
+            (block $exit
+                (loop $repeat
+                    (br_if $exit (i32.ge_s (local.get $i) (i32.const 10)))
+                    (call $print (call $factorial (local.get $i)))
+                    (local.set $i (i32.add (local.get $i) (i32.const 1)))
+                    (br $repeat)))))"#).unwrap();
+    assert_eq!(module.functions.len(), expected.functions.len());
+    for (built, expected) in module.functions.iter().zip(expected.functions.iter()) {
+        assert_eq!(built.code.as_ref().map(|code| (&code.locals, &code.body)), expected.code.as_ref().map(|code| (&code.locals, &code.body)));
+        assert_eq!(built.local_names, expected.local_names);
+    }
+}
+
+#[test]
+fn builder_rejects_branch_to_label_of_ended_block() {
+    let mut module = highlevel::Module::default();
+    let error = ModuleBuilder::new(&mut module).function(&[], &[], |f| {
+        let mut ended = None;
+        f.block(FunctionType::new(vec![], vec![]), |_, label| {
+            ended = Some(label);
+            Ok(())
+        })?;
+        // at the same depth as the ended block, but a different one
+        f.block(FunctionType::new(vec![], vec![]), |f, _| {
+            f.br(ended.unwrap())?;
+            Ok(())
+        })?;
+        Ok(())
+    }).unwrap_err();
+    assert_eq!(error.to_string(), "branch to a label outside of its block");
+    assert!(module.functions.is_empty());
+}
+
+#[test]
+fn builder_rejects_duplicate_export_names() {
+    let mut module = highlevel::Module::default();
+    let mut builder = ModuleBuilder::new(&mut module);
+    let function = builder.function(&[], &[], |_| Ok(())).unwrap();
+    let memory = builder.memory(1, None).unwrap();
+    builder.export_function(function, "f").unwrap();
+    let error = builder.export_memory(memory, "f").unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+    assert_eq!(error.to_string(), "duplicate export name 'f'");
+}
+
+#[test]
+fn builder_supports_memory64_and_checks_data_sizes() {
+    let mut module = highlevel::Module::default();
+    let mut builder = ModuleBuilder::new(&mut module);
+
+    // 32-bit memories are limited to 4 GiB, memory64 ones are not
+    assert!(builder.memory(65537, None).is_err());
+    let memory = builder.memory64(1 << 20, Some(1 << 40)).unwrap();
+    builder.data(memory, 1 << 35, b"beyond 4 GiB").unwrap();
+
+    // size in bytes overflows u64
+    let huge = builder.memory64(1 << 48, None).unwrap();
+    assert!(builder.data(huge, 0, b"").is_err());
+
+    // does not fit, even though offset + length overflows
+    let small = builder.memory(1, None).unwrap();
+    assert!(builder.data(small, 65536, b"x").is_err());
+    assert!(builder.data(memory, u64::max_value(), b"x").is_err());
+
+    assert!(builder.memory64(2, Some(1)).is_err());
+    module.validate().unwrap();
+}
+
+/// one module per proposal, with the instructions and module fields that the proposal adds
+const PROPOSAL_MODULES: &[(&str, &str)] = &[
+    ("multi-value", r#"(module
+        (func (param i32) (result i32 i32)
+            local.get 0
+            block (param i32) (result i32 i32)
+                i32.const 1
+            end))"#),
+    ("sign-extension-ops", r#"(module
+        (func (param i32 i64) (result i64)
+            (drop (i32.extend16_s (i32.extend8_s (local.get 0))))
+            (i64.extend32_s (i64.extend16_s (i64.extend8_s (local.get 1))))))"#),
+    ("nontrapping-float-to-int-conversions", r#"(module
+        (func (param f32 f64) (result i64)
+            (drop (i32.trunc_sat_f32_s (local.get 0)))
+            (drop (i32.trunc_sat_f64_u (local.get 1)))
+            (drop (i64.trunc_sat_f32_u (local.get 0)))
+            (i64.trunc_sat_f64_s (local.get 1))))"#),
+    ("bulk-memory", r#"(module
+        (table 1 funcref)
+        (memory 1)
+        (func $f)
+        (elem func $f)
+        (data "passive")
+        (data (i32.const 8) "active")
+        (func
+            i32.const 0
+            i32.const 0
+            i32.const 7
+            memory.init 0
+            data.drop 0
+            elem.drop 0))"#),
+    ("reference-types", r#"(module
+        (table 1 funcref)
+        (table $externs 1 externref)
+        (func $f)
+        (elem (table $externs) (i32.const 0) externref (ref.null extern))
+        (elem (i32.const 0) funcref (ref.func $f))
+        (func (param externref)
+            i32.const 0
+            local.get 0
+            table.set $externs))"#),
+    ("multi-memory", r#"(module
+        (memory 1)
+        (memory $second 1)
+        (func (param i32) (result i32)
+            (i32.store $second offset=4 (local.get 0) (i32.load (local.get 0)))
+            (drop (memory.grow $second (memory.size $second)))
+            (i32.load8_u 1 align=1 (local.get 0))))"#),
+    ("simd", r#"(module
+        (func (param v128) (result i32)
+            (i8x16.shuffle 0 1 2 3 16 17 18 19 4 5 6 7 20 21 22 23
+                (local.get 0)
+                (i32x4.add (local.get 0) (v128.const i32x4 1 2 3 0xffffffff)))
+            (i32x4.extract_lane 3)))"#),
+    ("threads", r#"(module
+        (memory 1 2 shared)
+        (func (param i32) (result i64)
+            (drop (i32.atomic.rmw8.cmpxchg_u (local.get 0) (i32.const 0) (i32.const 1)))
+            atomic.fence
+            (i64.atomic.rmw.add offset=8 (local.get 0) (i64.const 1))))"#),
+    ("exception-handling", r#"(module
+        (tag $e (import "env" "e") (param i32))
+        (tag $f (export "f") (param i64))
+        (func (result i32)
+            (try (result i32)
+                (do (throw $f (i64.const 1)))
+                (catch $e)
+                (catch_all
+                    try
+                        (throw $e (i32.const 2))
+                    delegate 1
+                    rethrow 0))))"#),
+    ("tail-call", r#"(module
+        (table 1 funcref)
+        (func $f (param i32) (result i32)
+            (if (result i32) (local.get 0)
+                (then (return_call $f (i32.const 0)))
+                (else (return_call_indirect (param i32) (result i32) (i32.const 1) (i32.const 0))))))"#),
+    ("memory64", r#"(module
+        (memory i64 1 65536)
+        (func (param i64) (result i64)
+            (i64.store offset=4294967296 (local.get 0) (i64.const 42))
+            (drop (memory.grow (i64.const 1)))
+            (i64.load (local.get 0))))"#),
+];
+
+/// the module of a proposal from PROPOSAL_MODULES, encoded and decoded again
+fn encode_decode_proposal(proposal: &str) -> (Vec<u8>, highlevel::Module) {
+    let &(_, wat) = PROPOSAL_MODULES.iter().find(|&&(name, _)| name == proposal)
+        .expect(&format!("no module for proposal '{}'", proposal));
+    let mut bytes = Vec::new();
+    lowlevel::Module::from(wat::parse_module(wat).unwrap()).encode(&mut bytes).unwrap();
+    let decoded = lowlevel::Module::decode(&mut &bytes[..]).unwrap().into();
+    (bytes, decoded)
+}
+
+#[test]
+fn proposal_modules_survive_encode_decode() {
+    for &(proposal, wat) in PROPOSAL_MODULES {
+        let module = wat::parse_module(wat).expect(&format!("could not parse module of proposal '{}'", proposal));
+        let (_, decoded) = encode_decode_proposal(proposal);
+
+        assert_eq!(module.functions.len(), decoded.functions.len(), "proposal '{}'", proposal);
+        for (function, decoded) in module.functions.iter().zip(decoded.functions.iter()) {
+            assert_eq!(function.code.as_ref().map(|code| &code.body), decoded.code.as_ref().map(|code| &code.body),
+                       "proposal '{}'", proposal);
+        }
+        if let Err(error) = decoded.validate() {
+            panic!("module of proposal '{}' does not validate after encode and decode: {}", proposal, error);
+        }
+    }
+}
+
+#[test]
+fn multi_value_block_type_is_a_type_index() {
+    let (bytes, decoded) = encode_decode_proposal("multi-value");
+    // block with type index 0, i.e., the same type [i32] -> [i32 i32] as the function
+    assert!(bytes.windows(2).any(|bytes| bytes == [0x02, 0x00]));
+    match decoded.functions[0].code.as_ref().unwrap().body[1] {
+        highlevel::Instr::Block(ref type_) => assert_eq!(type_.results.len(), 2),
+        ref instr => panic!("expected block, got {:?}", instr),
+    }
+}
+
+/// decodes a module with a single function [] -> [] and no locals, whose body is instrs (without end)
+fn decode_body(instrs: &[u8], strict: bool) -> io::Result<Vec<highlevel::Instr>> {
+    let mut bytes = b"\0asm\x01\0\0\0\x01\x04\x01\x60\x00\x00\x03\x02\x01\x00\x0a".to_vec();
+    bytes.extend_from_slice(&[instrs.len() as u8 + 4, 0x01, instrs.len() as u8 + 2, 0x00]);
+    bytes.extend_from_slice(instrs);
+    bytes.push(0x0b);
+    let module: highlevel::Module = if strict {
+        lowlevel::Module::decode_strict(&mut &bytes[..])?.into()
+    } else {
+        lowlevel::Module::decode(&mut &bytes[..])?.into()
+    };
+    Ok(module.functions[0].code.as_ref().unwrap().body.clone())
+}
+
+#[test]
+fn sign_extension_and_sat_conversion_opcodes() {
+    use ast::{InstrType, ValType};
+    use ast::highlevel::Instr::{End, Numeric};
+    use ast::highlevel::NumericOp::*;
+
+    assert_eq!(decode_body(&[0xc0, 0xc1, 0xc2, 0xc3, 0xc4], false).unwrap(),
+               vec![Numeric(I32Extend8S), Numeric(I32Extend16S), Numeric(I64Extend8S), Numeric(I64Extend16S), Numeric(I64Extend32S), End]);
+    assert!(decode_body(&[0xc5], false).is_err(), "0xc5 is not an opcode");
+
+    // sub-opcode after the 0xfc prefix is a LEB128 u32, so padded ones must be accepted (also in
+    // strict mode, as long as they are at most 5 bytes)
+    for &strict in &[false, true] {
+        assert_eq!(decode_body(&[0xfc, 0x00, 0xfc, 0x83, 0x00, 0xfc, 0x87, 0x80, 0x80, 0x80, 0x00], strict).unwrap(),
+                   vec![Numeric(I32TruncSatSF32), Numeric(I32TruncSatUF64), Numeric(I64TruncSatUF64), End]);
+    }
+    assert!(decode_body(&[0xfc, 0x87, 0x80, 0x80, 0x80, 0x80, 0x00], true).is_err(), "sub-opcode is longer than 5 bytes");
+    assert!(decode_body(&[0xfc, 0x7f], false).is_err(), "0x7f is not a 0xfc sub-opcode");
+
+    // encoding uses the shortest form again
+    let mut bytes = Vec::new();
+    lowlevel::Module::from(wat::parse_module("(module (func (param f64) (result i64) (i64.trunc_sat_f64_u (local.get 0))))").unwrap())
+        .encode(&mut bytes).unwrap();
+    assert!(bytes.windows(3).any(|bytes| bytes == [0xfc, 0x07, 0x0b]));
+
+    assert_eq!(I64TruncSatUF32.to_type(), InstrType::new(&[ValType::F32], &[ValType::I64]));
+    assert_eq!(Numeric(I64Extend32S).to_name(), "i64.extend32_s");
+}
+
+#[test]
+fn bulk_memory_passive_segments_and_data_count() {
+    let (bytes, decoded) = encode_decode_proposal("bulk-memory");
+    let lowlevel = lowlevel::Module::decode(&mut &bytes[..]).unwrap();
+
+    // data count section is required because of memory.init and data.drop, and comes before code
+    let section_ids: Vec<u8> = lowlevel.sections.iter().map(|section| match *section {
+        lowlevel::Section::DataCount(lowlevel::WithSize(count)) => { assert_eq!(count, 2); 12 }
+        lowlevel::Section::Code(_) => 10,
+        _ => 0,
+    }).filter(|&id| id != 0).collect();
+    assert_eq!(section_ids, vec![12, 10]);
+
+    match decoded.elements[0].mode {
+        highlevel::ElementMode::Passive => {}
+        ref mode => panic!("expected passive element segment, got {:?}", mode),
+    }
+    match decoded.data[0].mode {
+        highlevel::DataMode::Passive => {}
+        ref mode => panic!("expected passive data segment, got {:?}", mode),
+    }
+    assert_eq!(decoded.data[1].bytes, b"active");
+}
+
+#[test]
+fn reference_types_element_expressions_and_table_indices() {
+    let (_, decoded) = encode_decode_proposal("reference-types");
+    assert_eq!(decoded.tables.len(), 2);
+    for (element, expected_table) in decoded.elements.iter().zip(&[1, 0]) {
+        match element.mode {
+            highlevel::ElementMode::Active { table_idx, .. } => assert_eq!(table_idx.0, *expected_table),
+            ref mode => panic!("expected active element segment, got {:?}", mode),
+        }
+    }
+    match decoded.elements[0].items {
+        highlevel::ElementItems::Exprs(ElemType::ExternRef, ref exprs) =>
+            assert_eq!(exprs[0], vec![highlevel::Instr::RefNull(ElemType::ExternRef), highlevel::Instr::End]),
+        ref items => panic!("expected externref expressions, got {:?}", items),
+    }
+}
+
+#[test]
+fn multi_memory_indices_of_loads_and_stores() {
+    let (bytes, decoded) = encode_decode_proposal("multi-memory");
+    // i32.store to memory 1: alignment 2 with bit 6 set (= memory index follows), memory 1, offset 4
+    assert!(bytes.windows(4).any(|bytes| bytes == [0x36, 0x42, 0x01, 0x04]));
+    let memory_idxs: Vec<usize> = decoded.functions[0].code.as_ref().unwrap().body.iter().filter_map(|instr| match *instr {
+        highlevel::Instr::Load(_, memory_idx, _) | highlevel::Instr::Store(_, memory_idx, _) => Some(memory_idx.0),
+        _ => None,
+    }).collect();
+    assert_eq!(memory_idxs, vec![0, 1, 1]);
+}
+
+#[test]
+fn multiple_memories_are_rejected_without_multi_memory() {
+    let path = Path::new(INVALID_INPUTS).join("multiple-memories.wasm");
+    let module = highlevel::Module::from_file(&path).unwrap();
+    assert_eq!(module.memories.len(), 2);
+    module.validate().unwrap();
+    assert!(module.validate_with(Features { multi_memory: false }).is_err(),
+            "'{}' was accepted without multi-memory", path.display());
+}
+
+#[test]
+fn simd_sub_opcodes_are_leb128() {
+    let (bytes, _) = encode_decode_proposal("simd");
+    // i32x4.add has sub-opcode 0xae, which must be LEB128-encoded after the 0xfd prefix
+    assert!(bytes.windows(3).any(|bytes| bytes == [0xfd, 0xae, 0x01]));
+    // i8x16.shuffle (sub-opcode 0x0d) is followed by its 16 lane indices as bytes
+    assert!(bytes.windows(18).any(|bytes| bytes == [0xfd, 0x0d, 0, 1, 2, 3, 16, 17, 18, 19, 4, 5, 6, 7, 20, 21, 22, 23]));
+}
+
+#[test]
+fn threads_shared_memory_and_atomic_immediates() {
+    let (bytes, decoded) = encode_decode_proposal("threads");
+    // limits flags: maximum size present and shared
+    assert!(bytes.windows(3).any(|bytes| bytes == [0x03, 0x01, 0x02]));
+    // atomic.fence has a reserved zero byte
+    assert!(bytes.windows(3).any(|bytes| bytes == [0xfe, 0x03, 0x00]));
+    assert!(decoded.memories[0].type_.0.shared);
+
+    // sub-opcode after the 0xfe prefix is a LEB128 u32, so also a padded atomic.fence is accepted
+    assert_eq!(decode_body(&[0xfe, 0x83, 0x00, 0x00], false).unwrap(), vec![highlevel::Instr::AtomicFence, highlevel::Instr::End]);
+}
+
+#[test]
+fn exception_handling_tag_section_and_imports() {
+    let (bytes, decoded) = encode_decode_proposal("exception-handling");
+    // tag section: id 13, size 3, one tag (attribute 0 and a type index)
+    assert!(bytes.windows(4).any(|bytes| bytes == [13, 0x03, 0x01, 0x00]));
+    assert_eq!(decoded.tags.len(), 2);
+    assert_eq!(decoded.tags[0].import, Some(("env".to_string(), "e".to_string())));
+    assert_eq!(decoded.tags[1].export, vec!["f".to_string()]);
+}
+
+#[test]
+fn tail_call_opcodes() {
+    let (bytes, _) = encode_decode_proposal("tail-call");
+    // return_call to function 0
+    assert!(bytes.windows(2).any(|bytes| bytes == [0x12, 0x00]));
+    // return_call_indirect with type 0 and table 0
+    assert!(bytes.windows(3).any(|bytes| bytes == [0x13, 0x00, 0x00]));
+}
+
+#[test]
+fn memory64_limits_and_offsets() {
+    let (bytes, decoded) = encode_decode_proposal("memory64");
+    // i64.store with alignment 3 and offset 2^32 as u64 LEB128
+    assert!(bytes.windows(7).any(|bytes| bytes == [0x37, 0x03, 0x80, 0x80, 0x80, 0x80, 0x10]));
+    let limits = decoded.memories[0].type_.0;
+    assert!(limits.memory64);
+    assert_eq!(limits.max_size, Some(65536));
+}
+
+#[test]
+fn print_parse_wat_is_identity() {
+    for path in wasm_files(TEST_INPUTS).unwrap() {
+        let module = highlevel::Module::from_file(&path).unwrap();
+        let wat = module.to_string();
+
+        let module = wat::parse_module(&wat)
+            .expect(&format!("could not parse printed wat of '{}'", path.display()));
+        assert!(wat == module.to_string(), "printing parsed wat of '{}' gives different text", path.display());
+
+        let output_path = &output_file(&path, "print-parse-wat").unwrap();
+        module.to_file(output_path).unwrap();
+        validate_output(output_path);
+    }
+}
+
+#[test]
+fn names_survive_decode_encode() {
+    for path in wasm_files(TEST_INPUTS).unwrap() {
+        let module = highlevel::Module::from_file(&path)
+            .expect(&format!("could not decode valid wasm file '{}'", path.display()));
+        let names: Vec<_> = module.functions.iter()
+            .map(|function| (function.name.clone(), function.local_names.clone()))
+            .collect();
+
+        let output_path = &output_file(&path, "names").unwrap();
+        module.to_file(output_path)
+            .expect(&format!("could not encode wasm to file '{}'", output_path.display()));
+
+        let module = highlevel::Module::from_file(output_path).unwrap();
+        let names_after: Vec<_> = module.functions.iter()
+            .map(|function| (function.name.clone(), function.local_names.clone()))
+            .collect();
+        assert_eq!(names, names_after, "names differ after decode -> encode of '{}'", path.display());
+    }
+}
+
+#[test]
+fn names_follow_functions_when_imports_are_appended() {
+    // name that identifies a function independent of its index (good enough for the test inputs)
+    fn describe(function: &highlevel::Function) -> String {
+        format!("{:?} {:?} {}", function.import, function.type_, function.instr_count())
+    }
+
+    for path in wasm_files(TEST_INPUTS).unwrap() {
+        let mut module = highlevel::Module::from_file(&path).unwrap();
+        // like Wasabi's hooks: appended in high-level, but must come before all defined functions in binary
+        module.add_function_import(FunctionType::new(vec![], vec![]), "env".into(), "appended".into());
+        for function in &mut module.functions {
+            function.name = Some(describe(function));
+        }
+
+        let output_path = &output_file(&path, "names-remapped").unwrap();
+        module.to_file(output_path).unwrap();
+
+        let module = highlevel::Module::from_file(output_path).unwrap();
+        for function in &module.functions {
+            assert_eq!(function.name, Some(describe(function)), "wrong function name in '{}'", output_path.display());
+        }
+    }
+}
+
+#[test]
+fn relocatable_object_files_are_rejected() {
+    // empty module with a (otherwise empty) "reloc.CODE" custom section
+    let bytes = b"\0asm\x01\0\0\0\x00\x0b\x0areloc.CODE";
+    lowlevel::Module::decode(&mut &bytes[..]).unwrap();
+    for result in vec![highlevel::Module::from_bytes(bytes), highlevel::Module::from_bytes_strict(bytes)] {
+        let error = result.unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert!(error.to_string().contains("reloc.CODE"), "unexpected error: {}", error);
+    }
+}
+
+fn decode_error(bytes: &[u8]) -> DecodeError {
+    let error = lowlevel::Module::decode(&mut &bytes[..]).unwrap_err();
+    let error = error.into_inner().expect("decode error without context");
+    *error.downcast::<DecodeError>().unwrap()
+}
+
+#[test]
+fn decode_error_has_offset_section_and_path() {
+    // type section with one function type, whose parameter has the invalid type 0x7a
+    let error = decode_error(b"\0asm\x01\0\0\0\x01\x05\x01\x60\x01\x7a\x00");
+    assert_eq!(error.offset, Some(13));
+    assert_eq!(error.section, Some(1));
+    assert_eq!(error.function, None);
+    assert_eq!(error.path, vec!["Section::Type", "[0]", "FunctionType.params", "[0]"]);
+}
+
+#[test]
+fn decode_error_in_function_body_has_function_idx() {
+    // one function [] -> [] with invalid opcode 0xff in its body
+    let error = decode_error(b"\0asm\x01\0\0\0\
+        \x01\x04\x01\x60\x00\x00\
+        \x03\x02\x01\x00\
+        \x0a\x05\x01\x03\x00\xff\x0b");
+    assert_eq!(error.offset, Some(23));
+    assert_eq!(error.section, Some(10));
+    assert_eq!(error.function, Some(0.into()));
+    assert_eq!(error.path, vec!["Section::Code", "[0]", "Code.body"]);
+}
+
+#[test]
+fn encoded_size_is_the_same_as_encode() {
+    for path in wasm_files(TEST_INPUTS).unwrap() {
+        let module = lowlevel::Module::from_file(&path).unwrap();
+        for section in &module.sections {
+            let mut buf = Vec::new();
+            let bytes_written = section.encode(&mut buf).unwrap();
+            assert_eq!(bytes_written, buf.len());
+            assert_eq!(section.encoded_size(), buf.len(), "wrong encoded size of section in '{}'", path.display());
+        }
+
+        let mut buf = Vec::new();
+        module.encode(&mut buf).unwrap();
+        assert_eq!(module.encoded_size(), buf.len(), "wrong encoded size of module '{}'", path.display());
+    }
+}
+
+/// encoded_size() that is off by one, to check that the encoder does not silently write garbage
+struct WrongSize;
+
+impl WasmBinary for WrongSize {
+    fn decode<R: io::Read>(_: &mut R) -> io::Result<Self> {
+        Ok(WrongSize)
+    }
+
+    fn encode<W: io::Write>(&self, writer: &mut W) -> io::Result<usize> {
+        0u8.encode(writer)
+    }
+
+    fn encoded_size(&self) -> usize {
+        2
+    }
+}
+
+#[test]
+fn wrong_encoded_size_is_an_error() {
+    let mut buf = Vec::new();
+    assert_eq!(lowlevel::WithSize(WrongSize).encode(&mut buf).unwrap_err().kind(), io::ErrorKind::Other);
+    let mut buf = Vec::new();
+    assert!(lowlevel::WithSize(vec![lowlevel::WithSize(WrongSize)]).encode(&mut buf).is_err());
+}
+
+#[test]
+fn lazy_decode_error_in_function_body_is_the_same() {
+    let bytes = b"\0asm\x01\0\0\0\
+        \x01\x04\x01\x60\x00\x00\
+        \x03\x02\x01\x00\
+        \x0a\x05\x01\x03\x00\xff\x0b";
+    // locating the bodies succeeds, only decoding the body fails
+    let module = lowlevel::LazyModule::decode(bytes).unwrap();
+    let error = module.decode_body(0).unwrap_err();
+    let error = *error.into_inner().unwrap().downcast::<DecodeError>().unwrap();
+    let expected = decode_error(bytes);
+    assert_eq!(error.offset, expected.offset);
+    assert_eq!(error.section, expected.section);
+    assert_eq!(error.function, expected.function);
+    assert_eq!(error.path, expected.path);
+}
+
+#[test]
+fn lazy_decode_error_in_real_function_body_is_the_same() {
+    for path in &[RUST_WASM_FILE, RUST_IMPORTED_MEMORY_WASM_FILE] {
+        let mut bytes = ::std::fs::read(path).unwrap();
+        let lazy = lowlevel::LazyModule::decode(&bytes).unwrap();
+        assert!(lazy.function_imports() > 1 && lazy.bodies.len() > 1, "expected imports and multiple bodies in '{}'", path);
+
+        // invalid opcode at the start of the last body
+        let offsets = highlevel::CodeOffsets::from_module_bytes(&bytes).unwrap();
+        let last_body = offsets.functions.last().unwrap().as_ref().unwrap();
+        bytes[last_body.instrs[0]] = 0xff;
+
+        let module = lowlevel::LazyModule::decode(&bytes).unwrap();
+        let error = module.decode_body(module.bodies.len() - 1).unwrap_err();
+        let error = *error.into_inner().unwrap().downcast::<DecodeError>().unwrap();
+        let expected = decode_error(&bytes);
+        assert_eq!(error.offset, Some(last_body.instrs[0]));
+        assert_eq!(error.offset, expected.offset);
+        assert_eq!(error.section, expected.section);
+        assert_eq!(error.function, Some((offsets.functions.len() - 1).into()));
+        assert_eq!(error.function, expected.function);
+        assert_eq!(error.path, expected.path);
+    }
+}
+
+#[test]
+fn lazy_decoding_is_the_same_as_eager_decoding() {
+    for path in wasm_files(TEST_INPUTS).unwrap() {
+        let mut bytes = Vec::new();
+        File::open(&path).unwrap().read_to_end(&mut bytes).unwrap();
+        let eager = lowlevel::Module::decode(&mut &bytes[..]).unwrap();
+
+        let lazy = lowlevel::LazyModule::decode(&bytes)
+            .expect(&format!("could not lazily decode valid wasm file '{}'", path.display()));
+        assert_eq!(format!("{:?}", lazy.clone().into_module().unwrap()), format!("{:?}", eager),
+                   "lazy and eager low-level decoding differ for '{}'", path.display());
+
+        let mut eager: highlevel::Module = eager.into();
+        assert_eq!(format!("{:?}", lazy.clone().into_highlevel().unwrap()), format!("{:?}", eager),
+                   "lazy and eager high-level decoding differ for '{}'", path.display());
+
+        // module info only: everything but the code is the same
+        for function in &mut eager.functions {
+            function.code = None;
+        }
+        assert_eq!(format!("{:?}", lazy.into_highlevel_without_code().unwrap()), format!("{:?}", eager),
+                   "module without code differs for '{}'", path.display());
+    }
+}
+
+#[test]
+fn function_and_body_count_mismatch_is_an_error() {
+    // two functions, but only one body
+    let bytes = b"\0asm\x01\0\0\0\
+        \x01\x04\x01\x60\x00\x00\
+        \x03\x03\x02\x00\x00\
+        \x0a\x04\x01\x02\x00\x0b";
+    assert_eq!(highlevel::Module::from_bytes(bytes).unwrap_err().kind(), io::ErrorKind::InvalidData);
+    assert_eq!(highlevel::Module::from_bytes_lazy(bytes).unwrap_err().kind(), io::ErrorKind::InvalidData);
+    let lazy = lowlevel::LazyModule::decode(bytes).unwrap();
+    assert_eq!(lazy.into_highlevel_without_code().unwrap_err().kind(), io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn borrowed_decoding_is_the_same_as_owned_decoding() {
+    for path in wasm_files(TEST_INPUTS).unwrap() {
+        let file = MappedFile::open(&path).unwrap();
+        let bytes = &file[..];
+        let owned = lowlevel::Module::decode(&mut &bytes[..]).unwrap();
+        let borrowed = lowlevel::BorrowedModule::from_mapped_file(&file)
+            .expect(&format!("could not decode valid wasm file '{}' without copying", path.display()));
+
+        // names, bytes, and function bodies point into the input, i.e., were not copied
+        let input = bytes.as_ptr() as usize..bytes.as_ptr() as usize + bytes.len();
+        let assert_borrowed = |slice: &[u8]| assert!(input.start <= slice.as_ptr() as usize && slice.as_ptr() as usize + slice.len() <= input.end,
+                                                     "not borrowed from the input '{}'", path.display());
+        for section in &borrowed.sections {
+            match *section {
+                lowlevel::BorrowedSection::Custom(bytes) => assert_borrowed(bytes),
+                lowlevel::BorrowedSection::Import(ref imports) => for import in imports {
+                    assert_borrowed(import.module.as_bytes());
+                    assert_borrowed(import.name.as_bytes());
+                },
+                lowlevel::BorrowedSection::Export(ref exports) => for export in exports {
+                    assert_borrowed(export.name.as_bytes());
+                },
+                lowlevel::BorrowedSection::Data(ref data) => for data in data {
+                    assert_borrowed(data.init);
+                },
+                lowlevel::BorrowedSection::Code(ref bodies) => for &(_, body) in bodies {
+                    assert_borrowed(body);
+                },
+                lowlevel::BorrowedSection::Other(_) => {}
+            }
+        }
+
+        assert_eq!(format!("{:?}", borrowed.clone().into_module().unwrap()), format!("{:?}", owned),
+                   "borrowed and owned decoding differ for '{}'", path.display());
+        let owned: highlevel::Module = owned.into();
+        assert_eq!(format!("{:?}", borrowed.into_highlevel().unwrap()), format!("{:?}", owned),
+                   "borrowed and owned high-level decoding differ for '{}'", path.display());
+    }
+}
+
+#[test]
+fn borrowed_decoding_rejects_trailing_bytes() {
+    // after the last section
+    let error = lowlevel::BorrowedModule::decode(b"\0asm\x01\0\0\0\x01\x04\x01\x60\x00\x00\x00").unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+
+    // after the contents of a section
+    let error = lowlevel::BorrowedModule::decode(b"\0asm\x01\0\0\0\x01\x05\x01\x60\x00\x00\x00").unwrap_err();
+    let error = *error.into_inner().unwrap().downcast::<DecodeError>().unwrap();
+    assert_eq!(error.section, Some(1));
+    assert!(error.message.starts_with("section size mismatch"));
+}
+
+#[test]
+fn strict_decoding_is_the_same_for_well_formed_binaries() {
+    for path in wasm_files(TEST_INPUTS).unwrap() {
+        let mut bytes = Vec::new();
+        File::open(&path).unwrap().read_to_end(&mut bytes).unwrap();
+        let strict = lowlevel::Module::decode_strict(&mut &bytes[..])
+            .expect(&format!("could not strictly decode valid wasm file '{}'", path.display()));
+        assert_eq!(format!("{:?}", strict), format!("{:?}", lowlevel::Module::decode(&mut &bytes[..]).unwrap()),
+                   "strict and default decoding differ for '{}'", path.display());
+    }
+}
+
+#[test]
+fn invalid_wat_is_well_formed() {
+    // invalid modules are not malformed, i.e., strict decoding accepts them and only validation fails
+    for path in wat_files(INVALID_INPUTS).unwrap() {
+        if let Ok(module) = highlevel::Module::from_wat_file(&path) {
+            let mut bytes = Vec::new();
+            lowlevel::Module::from(module).encode(&mut bytes).unwrap();
+            let module: highlevel::Module = lowlevel::Module::decode_strict(&mut &bytes[..])
+                .expect(&format!("invalid wat file '{}' was encoded to a malformed binary", path.display()))
+                .into();
+            assert!(module.validate().is_err(), "invalid wat file '{}' was accepted after encoding", path.display());
+        }
+    }
+}
+
+#[test]
+fn malformed_binaries_are_rejected_in_strict_mode() {
+    for path in wast_files(MALFORMED_INPUTS).unwrap() {
+        let mut wast = String::new();
+        File::open(&path).unwrap().read_to_string(&mut wast).unwrap();
+        for (i, (bytes, message)) in assert_malformed_modules(&wast).into_iter().enumerate() {
+            match lowlevel::Module::decode_strict(&mut &bytes[..]) {
+                Ok(_) => panic!("malformed module #{} in '{}' was accepted", i, path.display()),
+                Err(error) => assert!(error.to_string().contains(&message),
+                                      "malformed module #{} in '{}': expected error '{}', got '{}'", i, path.display(), message, error),
+            }
+        }
+    }
+}
+
+/// module bytes and expected error message of all (assert_malformed (module binary ...) "message")
+/// commands in a .wast file of the spec test suite (but only what is used in tests/malformed)
+fn assert_malformed_modules(wast: &str) -> Vec<(Vec<u8>, String)> {
+    wast.split("(assert_malformed").skip(1).map(|command| {
+        // strings inside (module binary ...) are the bytes, the one after it is the message
+        let (mut module, mut message) = (Vec::new(), Vec::new());
+        let mut chars = command.chars();
+        let mut depth = 1;
+        while depth > 0 {
+            match chars.next().expect("unterminated assert_malformed") {
+                '(' => depth += 1,
+                ')' => depth -= 1,
+                // line comment
+                ';' => { chars.by_ref().take_while(|&c| c != '\n').count(); }
+                '"' => {
+                    let string = if depth == 1 { &mut message } else { &mut module };
+                    loop {
+                        match chars.next().expect("unterminated string") {
+                            '"' => break,
+                            '\\' => match chars.next().expect("unterminated escape") {
+                                'n' => string.push(b'\n'),
+                                't' => string.push(b'\t'),
+                                c @ '\\' | c @ '"' | c @ '\'' => string.push(c as u8),
+                                high => {
+                                    let low = chars.next().expect("unterminated escape");
+                                    string.push(u8::from_str_radix(&format!("{}{}", high, low), 16).expect("invalid escape"));
+                                }
+                            },
+                            c => string.extend_from_slice(c.to_string().as_bytes()),
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        (module, String::from_utf8(message).unwrap())
+    }).collect()
+}
+
+#[test]
+fn strict_leb128_allows_padding_but_not_unused_bits() {
+    assert_eq!(i32::decode(&mut StrictReader(&b"\xff\xff\xff\xff\x7f"[..])).unwrap(), -1);
+    assert_eq!(i32::decode(&mut StrictReader(&b"\x80\x80\x80\x80\x78"[..])).unwrap(), i32::min_value());
+    assert_eq!(u32::decode(&mut StrictReader(&b"\xff\xff\xff\xff\x0f"[..])).unwrap(), u32::max_value());
+    assert_eq!(u32::decode(&mut StrictReader(&b"\x80\x80\x80\x80\x00"[..])).unwrap(), 0);
+
+    assert!(i32::decode(&mut StrictReader(&b"\xff\xff\xff\xff\x77"[..])).is_err());
+    assert!(u32::decode(&mut StrictReader(&b"\xff\xff\xff\xff\x1f"[..])).is_err());
+    assert!(u32::decode(&mut StrictReader(&b"\x80\x80\x80\x80\x80\x00"[..])).is_err());
+    // the default decoder ignores the unused bits
+    assert_eq!(u32::decode(&mut &b"\xff\xff\xff\xff\x1f"[..]).unwrap(), u32::max_value());
+}
+
+#[test]
+fn strict_decode_error_points_to_trailing_bytes() {
+    // one function [] -> [] with a byte after the end of its body
+    let error = lowlevel::Module::decode_strict(&mut &b"\0asm\x01\0\0\0\
+        \x01\x04\x01\x60\x00\x00\
+        \x03\x02\x01\x00\
+        \x0a\x05\x01\x03\x00\x0b\x00"[..]).unwrap_err();
+    let error = *error.into_inner().unwrap().downcast::<DecodeError>().unwrap();
+    assert_eq!(error.offset, Some(24));
+    assert_eq!(error.section, Some(10));
+    assert_eq!(error.function, Some(0.into()));
+    assert!(error.message.starts_with("section size mismatch"));
+}
+
+#[test]
+fn strict_decoding_rejects_trailing_bytes_in_sections_of_real_binaries() {
+    // the real binaries, but with an extra byte at the end of the section with the given id
+    fn with_trailing_byte(bytes: &[u8], id: u8) -> Vec<u8> {
+        let mut result = bytes[..8].to_vec();
+        for (section_id, _, contents) in raw_sections(bytes).unwrap() {
+            let trailing: &[u8] = if section_id == id { &[0] } else { &[] };
+            result.push(section_id);
+            (contents.len() + trailing.len()).encode(&mut result).unwrap();
+            result.extend_from_slice(contents);
+            result.extend_from_slice(trailing);
+        }
+        result
+    }
+
+    for path in &[RUST_WASM_FILE, RUST_IMPORTED_MEMORY_WASM_FILE] {
+        let bytes = ::std::fs::read(path).unwrap();
+        // the padded LEB128s of the linker are well-formed, as is the module with re-encoded sizes
+        lowlevel::Module::decode_strict(&mut &bytes[..]).unwrap();
+        lowlevel::Module::decode_strict(&mut &with_trailing_byte(&bytes, 0xff)[..]).unwrap();
+
+        // type, import, and code section
+        for &id in &[1, 2, 10] {
+            let error = lowlevel::Module::decode_strict(&mut &with_trailing_byte(&bytes, id)[..]).unwrap_err();
+            let error = *error.into_inner().unwrap().downcast::<DecodeError>().unwrap();
+            assert_eq!(error.section, Some(id), "wrong section for trailing byte in '{}': {}", path, error);
+            assert!(error.message.starts_with("section size mismatch"), "unexpected error for trailing byte in '{}': {}", path, error);
+        }
     }
 }
 
+/// type section and function body with (valid, but non-minimal) padded LEB128 sizes
+const PADDED_MODULE: &'static [u8] = b"\0asm\x01\0\0\0\
+    \x01\x84\x80\x80\x80\x00\x01\x60\x00\x00\
+    \x03\x02\x01\x00\
+    \x0a\x05\x01\x82\x00\x00\x0b";
+
+#[test]
+fn decode_encode_preserving_is_identity() {
+    let mut inputs = vec![PADDED_MODULE.to_vec()];
+    for path in wasm_files(TEST_INPUTS).unwrap() {
+        let mut bytes = Vec::new();
+        File::open(&path).unwrap().read_to_end(&mut bytes).unwrap();
+        inputs.push(bytes);
+    }
+
+    for bytes in inputs {
+        let module = lowlevel::Module::decode(&mut &bytes[..]).unwrap();
+        let original = OriginalEncoding::new(&bytes).unwrap();
+        let mut output = Vec::new();
+        module.encode_preserving(&original, &mut output).unwrap();
+        assert!(output == bytes, "decode -> encode_preserving changed the bytes of a module with {} sections", module.sections.len());
+    }
+}
+
+#[test]
+fn encode_preserving_keeps_unmodified_parts_only() {
+    let mut module = lowlevel::Module::decode(&mut &PADDED_MODULE[..]).unwrap();
+    let original = OriginalEncoding::new(PADDED_MODULE).unwrap();
+
+    // new section is encoded regularly, the original sections are untouched
+    module.sections.push(lowlevel::Section::Custom(b"\x04test".to_vec()));
+    let mut output = Vec::new();
+    module.encode_preserving(&original, &mut output).unwrap();
+    assert_eq!(&output[..PADDED_MODULE.len()], PADDED_MODULE);
+    assert_eq!(&output[PADDED_MODULE.len()..], b"\x00\x05\x04test");
+
+    // modified function body (and thus code section) keep the length of their sizes, the type section is untouched
+    if let lowlevel::Section::Code(lowlevel::WithSize(ref mut bodies)) = module.sections[2] {
+        bodies[0].0.body.0.insert(0, lowlevel::Instr::Nop);
+    }
+    let mut output = Vec::new();
+    module.encode_preserving(&original, &mut output).unwrap();
+    assert_eq!(&output[..22], &PADDED_MODULE[..22]);
+    assert_eq!(&output[22..], b"\x0a\x06\x01\x83\x00\x00\x01\x0b\x00\x05\x04test");
+
+    // new element in a section: existing elements are untouched, the padded size keeps its length
+    if let lowlevel::Section::Type(lowlevel::WithSize(ref mut types)) = module.sections[0] {
+        types.push(FunctionType::new(vec![ValType::I32], vec![]));
+    }
+    let mut output = Vec::new();
+    module.encode_preserving(&original, &mut output).unwrap();
+    assert_eq!(&output[8..22], b"\x01\x88\x80\x80\x80\x00\x02\x60\x00\x00\x60\x01\x7f\x00");
+}
+
+#[test]
+fn encode_preserving_identifies_parts_by_position() {
+    // two bodies with the same contents, but only the first with a padded size
+    let bytes = b"\0asm\x01\0\0\0\
+        \x01\x04\x01\x60\x00\x00\
+        \x03\x03\x02\x00\x00\
+        \x0a\x08\x02\x82\x00\x00\x0b\x02\x00\x0b";
+    let mut module = lowlevel::Module::decode(&mut &bytes[..]).unwrap();
+    let original = OriginalEncoding::new(bytes).unwrap();
+    let mut output = Vec::new();
+    module.encode_preserving(&original, &mut output).unwrap();
+    assert_eq!(&output[..], &bytes[..]);
+
+    // sections are found by id, not by their position in the module
+    module.sections.insert(0, lowlevel::Section::Custom(b"\x04test".to_vec()));
+    let mut output = Vec::new();
+    module.encode_preserving(&original, &mut output).unwrap();
+    assert_eq!(&output[..8], &bytes[..8]);
+    assert_eq!(&output[8..15], b"\x00\x05\x04test");
+    assert_eq!(&output[15..], &bytes[8..]);
+}
+
+#[test]
+fn encode_preserving_highlevel_keeps_sections_unchanged_by_the_conversion() {
+    let mut inputs = vec![PADDED_MODULE.to_vec()];
+    for path in wasm_files(TEST_INPUTS).unwrap() {
+        let mut bytes = Vec::new();
+        File::open(&path).unwrap().read_to_end(&mut bytes).unwrap();
+        inputs.push(bytes);
+    }
+
+    for bytes in inputs {
+        let original = OriginalEncoding::new(&bytes).unwrap();
+        let module = highlevel::Module::from_bytes(&bytes).unwrap();
+        let mut output = Vec::new();
+        module.clone().encode_preserving(&original, &mut output).unwrap();
+
+        // the contract (see highlevel::Module::encode_preserving): every section of the converted
+        // module whose regular encoding is the same as that of the original section at the same
+        // position is written with the original bytes, all others with the converted contents
+        let converted = lowlevel::Module::from(module);
+        let decoded = lowlevel::Module::decode(&mut &bytes[..]).unwrap();
+        let original_sections = raw_sections(&bytes).unwrap();
+        let output_decoded = lowlevel::Module::decode(&mut &output[..]).unwrap();
+        let output_sections = raw_sections(&output).unwrap();
+        assert_eq!(output_sections.len(), converted.sections.len());
+
+        let mut sections_before: HashMap<u8, usize> = HashMap::new();
+        for ((section, output_section), &(id, _, output_contents)) in converted.sections.iter().zip(&output_decoded.sections).zip(&output_sections) {
+            assert_eq!(section.id(), id);
+            let n = sections_before.entry(id).or_insert(0);
+            let original_section = decoded.sections.iter().filter(|section| section.id() == id).nth(*n);
+            let original_contents = original_sections.iter().filter(|&&(original_id, _, _)| original_id == id).nth(*n);
+            *n += 1;
+
+            let regular = regular_encoding(section);
+            match (original_section, original_contents) {
+                (Some(original_section), Some(&(_, _, original_contents))) if regular_encoding(original_section) == regular =>
+                    assert!(output_contents == original_contents, "encode_preserving of the high-level module changed the bytes of an unchanged section {}", id),
+                _ => assert!(regular_encoding(output_section) == regular, "encode_preserving of the high-level module changed the contents of section {}", id),
+            }
+        }
+    }
+}
+
+#[test]
+fn encode_preserving_highlevel_is_identity_only_if_the_conversion_keeps_the_order() {
+    // imports grouped by kind, types in order of first use: same after the conversion
+    let grouped = b"\0asm\x01\0\0\0\
+        \x01\x84\x80\x80\x80\x00\x01\x60\x00\x00\
+        \x02\x0e\x02\x01m\x01f\x00\x00\x01m\x01m\x02\x00\x00\
+        \x03\x02\x01\x00\
+        \x0a\x05\x01\x82\x00\x00\x0b";
+    let module = highlevel::Module::from_bytes(grouped).unwrap();
+    let mut output = Vec::new();
+    module.encode_preserving(&OriginalEncoding::new(grouped).unwrap(), &mut output).unwrap();
+    assert_eq!(&output[..], &grouped[..]);
+
+    // memory import before the function import: the import section is written in the converted
+    // order (i.e., changed), but the padded type section and function body are still preserved
+    let ungrouped = b"\0asm\x01\0\0\0\
+        \x01\x84\x80\x80\x80\x00\x01\x60\x00\x00\
+        \x02\x0e\x02\x01m\x01m\x02\x00\x00\x01m\x01f\x00\x00\
+        \x03\x02\x01\x00\
+        \x0a\x05\x01\x82\x00\x00\x0b";
+    let module = highlevel::Module::from_bytes(ungrouped).unwrap();
+    let mut output = Vec::new();
+    module.encode_preserving(&OriginalEncoding::new(ungrouped).unwrap(), &mut output).unwrap();
+    assert_eq!(output.len(), ungrouped.len());
+    assert_eq!(&output[..18], &ungrouped[..18]);
+    assert_eq!(&output[18..34], &grouped[18..34]);
+    assert_eq!(&output[34..], &ungrouped[34..]);
+}
+
+#[test]
+fn encode_preserving_keeps_padded_leb128s_of_real_binaries() {
+    for path in &[RUST_WASM_FILE, RUST_IMPORTED_MEMORY_WASM_FILE] {
+        let bytes = ::std::fs::read(path).unwrap();
+        let original = OriginalEncoding::new(&bytes).unwrap();
+
+        // the linker pads function indices and memory offsets in the code to 5 bytes
+        let module = lowlevel::Module::decode(&mut &bytes[..]).unwrap();
+        let mut regular = Vec::new();
+        module.encode(&mut regular).unwrap();
+        assert!(regular.len() < bytes.len(), "expected padded LEB128s in '{}'", path);
+        let mut output = Vec::new();
+        module.encode_preserving(&original, &mut output).unwrap();
+        assert!(output == bytes, "decode -> encode_preserving changed the bytes of '{}'", path);
+
+        // the conversion to high-level and back renumbers the types (the imports use them in
+        // another order than the type section), so this is not the identity, but the unchanged
+        // function bodies still keep their padding
+        let module = highlevel::Module::from_bytes(&bytes).unwrap();
+        let mut regular = Vec::new();
+        lowlevel::Module::from(module.clone()).encode(&mut regular).unwrap();
+        let mut output = Vec::new();
+        module.encode_preserving(&original, &mut output).unwrap();
+        assert!(output != bytes, "expected the types of '{}' to be renumbered", path);
+        assert!(output.len() > regular.len(), "no padding of '{}' was preserved", path);
+    }
+}
+
+fn regular_encoding(section: &lowlevel::Section) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    section.encode(&mut bytes).unwrap();
+    bytes
+}
+
+#[test]
+fn code_offsets_map_instructions_and_back() {
+    for path in wasm_files(TEST_INPUTS).unwrap() {
+        let mut bytes = Vec::new();
+        File::open(&path).unwrap().read_to_end(&mut bytes).unwrap();
+        let module = highlevel::Module::from_bytes_with_offsets(&bytes)
+            .expect(&format!("could not decode valid wasm file '{}'", path.display()));
+        let offsets = module.offsets.as_ref().unwrap();
+        assert_eq!(offsets.functions.len(), module.functions.len());
+
+        for (i, function) in module.functions.iter().enumerate() {
+            let func_idx = i.into();
+            let function_offsets = match offsets.functions[i] {
+                Some(ref function_offsets) => function_offsets,
+                None => {
+                    assert!(function.code.is_none());
+                    continue;
+                }
+            };
+            assert_eq!(function_offsets.instrs.len(), function.instr_count(), "in '{}'", path.display());
+            for instr_idx in 0..function.instr_count() {
+                let offset = offsets.offset(func_idx, instr_idx.into()).unwrap();
+                assert_eq!(offsets.location(offset), Some((func_idx, instr_idx.into())), "in '{}'", path.display());
+            }
+            // the body ends with the final end instruction
+            let end = *function_offsets.instrs.last().unwrap();
+            assert_eq!(bytes[end], 0x0b);
+            assert_eq!(end + 1, function_offsets.body.end);
+        }
+    }
+}
+
+#[test]
+fn dwarf_line_table_maps_to_instructions() {
+    fn custom_section(module: &mut Vec<u8>, name: &str, contents: &[u8]) {
+        module.push(0);
+        module.push((1 + name.len() + contents.len()) as u8);
+        module.push(name.len() as u8);
+        module.extend_from_slice(name.as_bytes());
+        module.extend_from_slice(contents);
+    }
+
+    let module = wat::parse_module(r#"(module
+        (import "env" "f" (func))
+        (func (drop (i32.const 1)) (nop)))"#).unwrap();
+    let mut bytes = Vec::new();
+    lowlevel::Module::from(module).encode(&mut bytes).unwrap();
+
+    // code section contents: function count, body size, locals, i32.const at 3, drop at 5, nop at 6, end at 7
+    custom_section(&mut bytes, ".debug_line", b"\x35\0\0\0\x04\0\x1b\0\0\0\
+        \x01\x01\x01\xfb\x0e\x0d\0\x01\x01\x01\x01\0\0\0\x01\0\0\x01\
+        \0a.c\0\0\0\0\0\
+        \0\x05\x02\x03\0\0\0\x03\x09\x01\
+        \x02\x03\x03\x01\x01\
+        \x02\x02\0\x01\x01");
+    custom_section(&mut bytes, ".debug_abbrev", b"\x01\x11\x01\0\0\x02\x2e\0\x11\x01\x03\x08\0\0\0");
+    // subprogram with low_pc 1 (the body size field of the function) and name "main"
+    custom_section(&mut bytes, ".debug_info", b"\x13\0\0\0\x04\0\0\0\0\0\x04\x01\x02\x01\0\0\0main\0\0");
+
+    let offsets = highlevel::CodeOffsets::from_module_bytes(&bytes).unwrap();
+    let debug_info = DebugInfo::from_module_bytes(&bytes, &offsets).unwrap().expect("no debug info found");
+    assert_eq!(debug_info.files, vec!["a.c".to_string()]);
+    let line = |line| Some(SourceLocation { file: 0, line, column: 0 });
+    let locations: Vec<(Idx<highlevel::Instr>, _)> = vec![(0.into(), line(10)), (2.into(), line(11))];
+    assert_eq!(debug_info.locations[&Idx::from(1)], locations);
+    assert_eq!(debug_info.location(1.into(), 1.into()), line(10).as_ref());
+    assert_eq!(debug_info.function_names[&Idx::from(1)], "main");
+
+    // still decodes as a regular module, with the DWARF sections as custom sections
+    lowlevel::Module::decode(&mut &bytes[..]).unwrap();
+}
+
 /*
  * Speed benchmarks (for parallelization of decoding/encoding) on a "large" wasm file (~2MB for now)
  */
@@ -36,6 +1191,24 @@ fn decode_lowlevel_speed(bencher: &mut Bencher) {
         lowlevel::Module::decode(&mut io::Cursor::new(&buf)).unwrap())
 }
 
+#[bench]
+fn decode_borrowed_speed(bencher: &mut Bencher) {
+    let mut buf = Vec::new();
+    File::open(LARGE_WASM_FILE).unwrap().read_to_end(&mut buf).unwrap();
+
+    bencher.iter(||
+        lowlevel::BorrowedModule::decode(&buf).unwrap())
+}
+
+#[bench]
+fn decode_lazy_to_highlevel_speed(bencher: &mut Bencher) {
+    let mut buf = Vec::new();
+    File::open(LARGE_WASM_FILE).unwrap().read_to_end(&mut buf).unwrap();
+
+    bencher.iter(||
+        lowlevel::LazyModule::decode(&buf).unwrap().into_highlevel().unwrap())
+}
+
 #[bench]
 fn encode_lowlevel_speed(bencher: &mut Bencher) {
     let module = lowlevel::Module::from_file(LARGE_WASM_FILE).unwrap();
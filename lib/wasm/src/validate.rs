@@ -0,0 +1,856 @@
+use ast::{ElemType, FunctionType, GlobalType, Idx, InstrType, Label, Limits, Memarg, Mutability, ValType};
+use ast::highlevel::{Code, Data, DataMode, Element, ElementItems, ElementMode, Function, GlobalOp, Instr, Memory, Module, Table, Tag};
+use ast::highlevel::Instr::*;
+use std::collections::HashSet;
+use std::error;
+use std::fmt;
+use std::io;
+
+/*
+ * Validation of high-level modules according to the WebAssembly spec (version 1, plus the
+ * proposals supported by the AST, e.g., multi-value, bulk memory, reference types, multi-memory, and exception handling), i.e., without going through
+ * the binary format or an external tool.
+ * Function bodies are type-checked with the algorithm from the spec's appendix (operand stack of
+ * possibly unknown types + control frames).
+ */
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValidationError {
+    /// None for errors outside of function bodies, e.g., duplicate export names
+    pub function: Option<Idx<Function>>,
+    pub instr: Option<Idx<Instr>>,
+    pub message: String,
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(function) = self.function {
+            write!(f, "function #{}", function.0)?;
+            if let Some(instr) = self.instr {
+                write!(f, ", instruction #{}", instr.0)?;
+            }
+            f.write_str(": ")?;
+        }
+        f.write_str(&self.message)
+    }
+}
+
+impl error::Error for ValidationError {
+    fn description(&self) -> &str { &self.message }
+}
+
+impl From<ValidationError> for io::Error {
+    fn from(error: ValidationError) -> Self {
+        io::Error::new(io::ErrorKind::InvalidData, error.to_string())
+    }
+}
+
+fn module_error<T>(message: String) -> Result<T, ValidationError> {
+    Err(ValidationError { function: None, instr: None, message })
+}
+
+/// Proposals that can be disabled, e.g., to check that a module also runs on engines without them.
+/// All are enabled by default.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Features {
+    pub multi_memory: bool,
+}
+
+impl Default for Features {
+    fn default() -> Self {
+        Features { multi_memory: true }
+    }
+}
+
+impl Module {
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        self.validate_with(Features::default())
+    }
+
+    pub fn validate_with(&self, features: Features) -> Result<(), ValidationError> {
+        // without multi-memory, only a single memory as in version 1 (multiple tables are allowed with reference types)
+        if !features.multi_memory && self.memories.len() > 1 {
+            return module_error(format!("multiple memories ({}) are not allowed without multi-memory", self.memories.len()));
+        }
+
+        let declared_refs = self.declared_function_refs();
+        for (idx, function) in self.functions.iter().enumerate() {
+            let error = |message| ValidationError { function: Some(idx.into()), instr: None, message };
+            match (&function.import, &function.code) {
+                (&Some(_), &None) => {}
+                (&None, &Some(ref code)) => FunctionValidator::new(self, &declared_refs, idx.into(), function, code).validate()?,
+                (&Some(_), &Some(_)) => return Err(error("function is both imported and defined".into())),
+                (&None, &None) => return Err(error("function is neither imported nor defined".into())),
+            }
+        }
+
+        for (idx, table) in self.tables.iter().enumerate() {
+            limits(&table.type_.1, u32::max_value() as u64, &format!("table #{}", idx))?;
+            if table.type_.1.shared {
+                return module_error(format!("table #{} cannot be shared", idx));
+            }
+            if table.type_.1.memory64 {
+                return module_error(format!("table #{} cannot be 64-bit", idx));
+            }
+        }
+
+        for (idx, memory) in self.memories.iter().enumerate() {
+            // 2^16 pages of 64 KiB = 4 GiB, i.e., the whole 32-bit address space
+            // for memory64, 2^48 pages = 2^64 bytes, i.e., the whole 64-bit address space
+            let upper_bound = if memory.type_.0.memory64 { 1 << 48 } else { 1 << 16 };
+            limits(&memory.type_.0, upper_bound, &format!("memory #{}", idx))?;
+        }
+
+        for (idx, tag) in self.tags.iter().enumerate() {
+            if !tag.type_.results.is_empty() {
+                return module_error(format!("tag #{} must not have results, but has type {:?}", idx, tag.type_));
+            }
+        }
+
+        for (idx, element) in self.elements.iter().enumerate() {
+            if let ElementMode::Active { table_idx, ref offset } = element.mode {
+                match self.tables.get(table_idx.0) {
+                    None => return module_error(format!("element segment #{} refers to unknown table #{}", idx, table_idx.0)),
+                    Some(table) if table.type_.0 != element.items.elem_type() =>
+                        return module_error(format!("element segment #{} has type {}, but table #{} has type {}",
+                                                    idx, element.items.elem_type().to_type(), table_idx.0, table.type_.0.to_type())),
+                    _ => {}
+                }
+                const_expr(self, offset, ValType::I32, &format!("offset of element segment #{}", idx))?;
+            }
+            match element.items {
+                ElementItems::Functions(ref functions) => for &function_idx in functions {
+                    if function_idx.0 >= self.functions.len() {
+                        return module_error(format!("element segment #{} references unknown function #{}", idx, function_idx.0));
+                    }
+                },
+                ElementItems::Exprs(elem_type, ref exprs) => for (item_idx, expr) in exprs.iter().enumerate() {
+                    const_expr(self, expr, elem_type.to_type(), &format!("item #{} of element segment #{}", item_idx, idx))?;
+                },
+            }
+        }
+
+        for (idx, data) in self.data.iter().enumerate() {
+            if let DataMode::Active { memory_idx, ref offset } = data.mode {
+                if memory_idx.0 >= self.memories.len() {
+                    return module_error(format!("data segment #{} refers to unknown memory #{}", idx, memory_idx.0));
+                }
+                let index_type = self.memories[memory_idx.0].type_.index_type();
+                const_expr(self, offset, index_type, &format!("offset of data segment #{}", idx))?;
+            }
+        }
+
+        for (idx, global) in self.globals.iter().enumerate() {
+            match (&global.import, &global.init) {
+                (&Some(_), &None) => {}
+                (&None, &Some(ref init)) => const_expr(self, init, global.type_.0, &format!("initializer of global #{}", idx))?,
+                (&Some(_), &Some(_)) => return module_error(format!("global #{} is both imported and defined", idx)),
+                (&None, &None) => return module_error(format!("global #{} is neither imported nor initialized", idx)),
+            }
+        }
+
+        if let Some(start) = self.start {
+            match self.functions.get(start.0) {
+                None => return module_error(format!("unknown start function #{}", start.0)),
+                Some(function) if !function.type_.params.is_empty() || !function.type_.results.is_empty() =>
+                    return module_error(format!("start function #{} must have type [] -> [], but has {:?}", start.0, function.type_)),
+                _ => {}
+            }
+        }
+
+        // all exports share a single namespace
+        let mut export_names = HashSet::new();
+        let all_exports = self.functions.iter().flat_map(|f| f.export.iter())
+            .chain(self.tables.iter().flat_map(|t| t.export.iter()))
+            .chain(self.memories.iter().flat_map(|m| m.export.iter()))
+            .chain(self.globals.iter().flat_map(|g| g.export.iter()))
+            .chain(self.tags.iter().flat_map(|t| t.export.iter()));
+        for name in all_exports {
+            if !export_names.insert(name) {
+                return module_error(format!("duplicate export name \"{}\"", name));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Module {
+    /// functions that may be referenced by ref.func in function bodies, i.e., that are referenced
+    /// outside of functions (in element segments, exports, or global initializers)
+    fn declared_function_refs(&self) -> HashSet<Idx<Function>> {
+        let mut refs = HashSet::new();
+        for element in &self.elements {
+            match element.items {
+                ElementItems::Functions(ref functions) => refs.extend(functions.iter().cloned()),
+                ElementItems::Exprs(_, ref exprs) => refs.extend(exprs.iter().flat_map(|expr| expr.iter()).filter_map(ref_func)),
+            }
+        }
+        for (idx, function) in self.functions.iter().enumerate() {
+            if !function.export.is_empty() {
+                refs.insert(idx.into());
+            }
+        }
+        for global in &self.globals {
+            refs.extend(global.init.iter().flat_map(|init| init.iter()).filter_map(ref_func));
+        }
+        refs
+    }
+}
+
+fn ref_func(instr: &Instr) -> Option<Idx<Function>> {
+    match *instr {
+        RefFunc(function_idx) => Some(function_idx),
+        _ => None,
+    }
+}
+
+fn limits(limits: &Limits, upper_bound: u64, what: &str) -> Result<(), ValidationError> {
+    if limits.initial_size > upper_bound {
+        return module_error(format!("initial size {} of {} exceeds the maximum of {}", limits.initial_size, what, upper_bound));
+    }
+    if let Some(max_size) = limits.max_size {
+        if max_size > upper_bound {
+            return module_error(format!("maximum size {} of {} exceeds the maximum of {}", max_size, what, upper_bound));
+        }
+        if max_size < limits.initial_size {
+            return module_error(format!("maximum size {} of {} is smaller than its initial size {}", max_size, what, limits.initial_size));
+        }
+    } else if limits.shared {
+        return module_error(format!("shared {} must have a maximum size", what));
+    }
+    Ok(())
+}
+
+/// constant expressions are a single T.const, ref.null, ref.func, or get_global of an imported,
+/// immutable global
+fn const_expr(module: &Module, expr: &[Instr], expected: ValType, what: &str) -> Result<(), ValidationError> {
+    let ty = match *expr {
+        [Const(val), End] => val.to_type(),
+        [RefNull(elem_type), End] => elem_type.to_type(),
+        [RefFunc(function_idx), End] => {
+            if function_idx.0 >= module.functions.len() {
+                return module_error(format!("{} references unknown function #{}", what, function_idx.0));
+            }
+            ValType::FuncRef
+        }
+        [Global(GlobalOp::GetGlobal, global_idx), End] => match module.globals.get(global_idx.0) {
+            Some(global) => match (&global.import, global.type_) {
+                (&Some(_), GlobalType(ty, Mutability::Const)) => ty,
+                _ => return module_error(format!("{} references global #{}, which is not an imported, immutable global", what, global_idx.0)),
+            },
+            None => return module_error(format!("{} references unknown global #{}", what, global_idx.0)),
+        },
+        _ => return module_error(format!("{} is not a constant expression: {:?}", what, expr)),
+    };
+    if ty != expected {
+        return module_error(format!("{} has type {}, but expected {}", what, ty, expected));
+    }
+    Ok(())
+}
+
+
+/* Type checking of function bodies */
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum FrameKind { Function, Block, Loop, If, Else, Try, Catch, CatchAll }
+
+struct Frame {
+    kind: FrameKind,
+    params: Vec<ValType>,
+    results: Vec<ValType>,
+    // operand stack height when entering the block
+    height: usize,
+    // the rest of the block is unreachable, i.e., the operand stack is polymorphic
+    unreachable: bool,
+}
+
+impl Frame {
+    /// branches to a loop go to its beginning, i.e., take the loop's inputs
+    fn label_types(&self) -> &[ValType] {
+        if self.kind == FrameKind::Loop { &self.params } else { &self.results }
+    }
+}
+
+struct FunctionValidator<'a> {
+    module: &'a Module,
+    declared_refs: &'a HashSet<Idx<Function>>,
+    function_idx: Idx<Function>,
+    function: &'a Function,
+    code: &'a Code,
+    instr_idx: usize,
+    // None is the unknown type, i.e., (popped) values in unreachable code
+    vals: Vec<Option<ValType>>,
+    frames: Vec<Frame>,
+}
+
+impl<'a> FunctionValidator<'a> {
+    fn new(module: &'a Module, declared_refs: &'a HashSet<Idx<Function>>, function_idx: Idx<Function>, function: &'a Function, code: &'a Code) -> Self {
+        FunctionValidator {
+            module,
+            declared_refs,
+            function_idx,
+            function,
+            code,
+            instr_idx: 0,
+            vals: Vec::new(),
+            frames: vec![Frame {
+                kind: FrameKind::Function,
+                // parameters are locals, not values on the operand stack
+                params: Vec::new(),
+                results: function.type_.results.clone(),
+                height: 0,
+                unreachable: false,
+            }],
+        }
+    }
+
+    fn error<T>(&self, message: String) -> Result<T, ValidationError> {
+        Err(ValidationError {
+            function: Some(self.function_idx),
+            instr: Some(self.instr_idx.into()),
+            message,
+        })
+    }
+
+    fn validate(mut self) -> Result<(), ValidationError> {
+        let code = self.code;
+        for (instr_idx, instr) in code.body.iter().enumerate() {
+            self.instr_idx = instr_idx;
+            if self.frames.is_empty() {
+                return self.error(format!("instruction {} after the end of the function", instr.to_name()));
+            }
+            self.instr(instr)?;
+        }
+        if !self.frames.is_empty() {
+            self.instr_idx = self.code.body.len();
+            return self.error(format!("missing end of {} block(s)", self.frames.len()));
+        }
+        Ok(())
+    }
+
+    /* Operand stack */
+
+    fn push(&mut self, ty: Option<ValType>) {
+        self.vals.push(ty);
+    }
+
+    fn pop(&mut self) -> Result<Option<ValType>, ValidationError> {
+        let (height, unreachable) = {
+            let frame = self.frames.last().unwrap();
+            (frame.height, frame.unreachable)
+        };
+        if self.vals.len() == height {
+            if unreachable {
+                return Ok(None);
+            }
+            return self.error("operand stack underflow".into());
+        }
+        Ok(self.vals.pop().unwrap())
+    }
+
+    fn pop_expect(&mut self, expected: ValType) -> Result<(), ValidationError> {
+        match self.pop()? {
+            Some(actual) if actual != expected =>
+                self.error(format!("type mismatch: expected {} on the operand stack, but got {}", expected, actual)),
+            _ => Ok(()),
+        }
+    }
+
+    fn pop_all(&mut self, expected: &[ValType]) -> Result<(), ValidationError> {
+        for &ty in expected.iter().rev() {
+            self.pop_expect(ty)?;
+        }
+        Ok(())
+    }
+
+    fn push_all(&mut self, types: &[ValType]) {
+        for &ty in types {
+            self.push(Some(ty));
+        }
+    }
+
+    /* Control frames */
+
+    /// the block inputs must already be popped from the operand stack
+    fn push_frame(&mut self, kind: FrameKind, type_: &FunctionType) {
+        let height = self.vals.len();
+        self.frames.push(Frame {
+            kind,
+            params: type_.params.clone(),
+            results: type_.results.clone(),
+            height,
+            unreachable: false,
+        });
+        self.push_all(&type_.params);
+    }
+
+    fn begin_block(&mut self, kind: FrameKind, type_: &FunctionType) -> Result<(), ValidationError> {
+        self.pop_all(&type_.params)?;
+        self.push_frame(kind, type_);
+        Ok(())
+    }
+
+    fn pop_frame(&mut self) -> Result<Frame, ValidationError> {
+        let results = self.frames.last().unwrap().results.clone();
+        self.pop_all(&results)?;
+        let frame = self.frames.pop().unwrap();
+        if self.vals.len() != frame.height {
+            return self.error(format!("{} value(s) left on the operand stack at the end of the block, expected {:?}",
+                                      self.vals.len() - frame.height, frame.results));
+        }
+        Ok(frame)
+    }
+
+    fn set_unreachable(&mut self) {
+        let frame = self.frames.last_mut().unwrap();
+        self.vals.truncate(frame.height);
+        frame.unreachable = true;
+    }
+
+    fn label_types(&self, label: Idx<Label>) -> Result<Vec<ValType>, ValidationError> {
+        if label.0 >= self.frames.len() {
+            return self.error(format!("unknown label {}, only {} block(s) are enclosing", label.0, self.frames.len()));
+        }
+        Ok(self.frames[self.frames.len() - 1 - label.0].label_types().to_vec())
+    }
+
+    /* Index spaces */
+
+    fn local_type(&self, local_idx: usize) -> Result<ValType, ValidationError> {
+        let params = &self.function.type_.params;
+        match params.get(local_idx).or_else(|| self.code.locals.get(local_idx - params.len())) {
+            Some(&ty) => Ok(ty),
+            None => self.error(format!("unknown local #{}, function has {} parameter(s) and {} local(s)", local_idx, params.len(), self.code.locals.len())),
+        }
+    }
+
+    /// returns the index type of the memory (i64 for memory64, i32 otherwise)
+    fn require_memory(&self, memory_idx: Idx<Memory>) -> Result<ValType, ValidationError> {
+        match self.module.memories.get(memory_idx.0) {
+            Some(memory) => Ok(memory.type_.index_type()),
+            None => self.error(format!("unknown memory #{}", memory_idx.0)),
+        }
+    }
+
+    /// the type of memory instructions depends on the index type of the accessed memories
+    /// NOTE the memories must be checked with require_memory() before
+    fn memory_instr_type(&self, instr: &Instr) -> InstrType {
+        let memories = &self.module.memories;
+        instr.to_type_with_memories(|memory_idx| memories[memory_idx.0].type_.index_type())
+            .expect("memory instructions always have a type")
+    }
+
+    /// offsets into 32-bit memories must fit into 32 bits themselves
+    fn require_offset(&self, memarg: Memarg, index_type: ValType) -> Result<(), ValidationError> {
+        if index_type == ValType::I32 && memarg.offset > u32::max_value() as u64 {
+            return self.error(format!("offset {} is out of range for a 32-bit memory", memarg.offset));
+        }
+        Ok(())
+    }
+
+    /// returns the element type of the table
+    fn require_table(&self, table_idx: Idx<Table>) -> Result<ElemType, ValidationError> {
+        match self.module.tables.get(table_idx.0) {
+            Some(table) => Ok(table.type_.0),
+            None => self.error(format!("unknown table #{}", table_idx.0)),
+        }
+    }
+
+    /// returns the type of the values carried by exceptions with this tag
+    fn require_tag(&self, tag_idx: Idx<Tag>) -> Result<Vec<ValType>, ValidationError> {
+        match self.module.tags.get(tag_idx.0) {
+            Some(tag) => Ok(tag.type_.params.clone()),
+            None => self.error(format!("unknown tag #{}", tag_idx.0)),
+        }
+    }
+
+    /// a tail call returns the callee results from the current function, so they must match
+    fn require_tail_call_results(&self, callee_results: &[ValType]) -> Result<(), ValidationError> {
+        if callee_results != &self.function.type_.results[..] {
+            return self.error(format!("tail call to a function with results {:?}, but the current function returns {:?}",
+                                      callee_results, self.function.type_.results));
+        }
+        Ok(())
+    }
+
+    fn require_data(&self, data_idx: Idx<Data>) -> Result<(), ValidationError> {
+        if data_idx.0 >= self.module.data.len() {
+            return self.error(format!("unknown data segment #{}", data_idx.0));
+        }
+        Ok(())
+    }
+
+    /// returns the element type of the segment
+    fn require_element(&self, element_idx: Idx<Element>) -> Result<ElemType, ValidationError> {
+        match self.module.elements.get(element_idx.0) {
+            Some(element) => Ok(element.items.elem_type()),
+            None => self.error(format!("unknown element segment #{}", element_idx.0)),
+        }
+    }
+
+    fn require_alignment(&self, memarg: Memarg, natural_alignment: u32) -> Result<(), ValidationError> {
+        if memarg.alignment > natural_alignment {
+            return self.error(format!("alignment 2^{} is larger than natural alignment 2^{}", memarg.alignment, natural_alignment));
+        }
+        Ok(())
+    }
+
+    /// atomic accesses must be aligned exactly to their size, not just at most
+    fn require_exact_alignment(&self, memarg: Memarg, natural_alignment: u32) -> Result<(), ValidationError> {
+        if memarg.alignment != natural_alignment {
+            return self.error(format!("alignment 2^{} of atomic access is not its natural alignment 2^{}", memarg.alignment, natural_alignment));
+        }
+        Ok(())
+    }
+
+    fn require_lane(&self, lane: u8, lane_count: u8) -> Result<(), ValidationError> {
+        if lane >= lane_count {
+            return self.error(format!("lane index {} out of range, must be < {}", lane, lane_count));
+        }
+        Ok(())
+    }
+
+    fn instr(&mut self, instr: &Instr) -> Result<(), ValidationError> {
+        let module = self.module;
+        match *instr {
+            Unreachable => self.set_unreachable(),
+            Nop => {}
+
+            Block(ref type_) => self.begin_block(FrameKind::Block, type_)?,
+            Loop(ref type_) => self.begin_block(FrameKind::Loop, type_)?,
+            If(ref type_) => {
+                self.pop_expect(ValType::I32)?;
+                self.begin_block(FrameKind::If, type_)?;
+            }
+            Else => {
+                if self.frames.last().unwrap().kind != FrameKind::If {
+                    return self.error("else without matching if".into());
+                }
+                let frame = self.pop_frame()?;
+                // the else branch gets the same inputs again
+                self.push_frame(FrameKind::Else, &FunctionType::new(frame.params, frame.results));
+            }
+            End => {
+                let frame = self.pop_frame()?;
+                // without else branch, the (implicit) else branch just passes its inputs through
+                if frame.kind == FrameKind::If && frame.params != frame.results {
+                    return self.error(format!("if without else must have the same inputs and results, but has {:?} -> {:?}", frame.params, frame.results));
+                }
+                self.push_all(&frame.results);
+            }
+
+            Try(ref type_) => self.begin_block(FrameKind::Try, type_)?,
+            Catch(tag_idx) => {
+                let tag_params = self.require_tag(tag_idx)?;
+                match self.frames.last().unwrap().kind {
+                    FrameKind::Try | FrameKind::Catch => {}
+                    _ => return self.error("catch without matching try".into()),
+                }
+                let frame = self.pop_frame()?;
+                // the catch block gets the values carried by the exception as inputs
+                self.push_frame(FrameKind::Catch, &FunctionType::new(tag_params, frame.results));
+            }
+            CatchAll => {
+                match self.frames.last().unwrap().kind {
+                    FrameKind::Try | FrameKind::Catch => {}
+                    _ => return self.error("catch_all without matching try".into()),
+                }
+                let frame = self.pop_frame()?;
+                self.push_frame(FrameKind::CatchAll, &FunctionType::new(Vec::new(), frame.results));
+            }
+            Delegate(label) => {
+                if self.frames.last().unwrap().kind != FrameKind::Try {
+                    return self.error("delegate without matching try".into());
+                }
+                let frame = self.pop_frame()?;
+                // label is relative to the blocks enclosing the try block
+                if label.0 >= self.frames.len() {
+                    return self.error(format!("unknown label {}, only {} block(s) are enclosing", label.0, self.frames.len()));
+                }
+                self.push_all(&frame.results);
+            }
+            Throw(tag_idx) => {
+                let tag_params = self.require_tag(tag_idx)?;
+                self.pop_all(&tag_params)?;
+                self.set_unreachable();
+            }
+            Rethrow(label) => {
+                if label.0 >= self.frames.len() {
+                    return self.error(format!("unknown label {}, only {} block(s) are enclosing", label.0, self.frames.len()));
+                }
+                match self.frames[self.frames.len() - 1 - label.0].kind {
+                    FrameKind::Catch | FrameKind::CatchAll => {}
+                    _ => return self.error(format!("rethrow label {} does not refer to a catch block", label.0)),
+                }
+                self.set_unreachable();
+            }
+
+            Br(label) => {
+                let types = self.label_types(label)?;
+                self.pop_all(&types)?;
+                self.set_unreachable();
+            }
+            BrIf(label) => {
+                self.pop_expect(ValType::I32)?;
+                let types = self.label_types(label)?;
+                self.pop_all(&types)?;
+                self.push_all(&types);
+            }
+            BrTable(ref table, default) => {
+                self.pop_expect(ValType::I32)?;
+                let default_types = self.label_types(default)?;
+                for &label in table {
+                    let types = self.label_types(label)?;
+                    if types != default_types {
+                        return self.error(format!("br_table targets have different types: label {} has {:?}, default label {} has {:?}",
+                                                  label.0, types, default.0, default_types));
+                    }
+                }
+                self.pop_all(&default_types)?;
+                self.set_unreachable();
+            }
+            Return => {
+                let results = self.function.type_.results.clone();
+                self.pop_all(&results)?;
+                self.set_unreachable();
+            }
+
+            Call(function_idx) => match module.functions.get(function_idx.0) {
+                Some(function) => {
+                    self.pop_all(&function.type_.params)?;
+                    self.push_all(&function.type_.results);
+                }
+                None => return self.error(format!("unknown function #{}", function_idx.0)),
+            },
+            CallIndirect(ref type_, table_idx) => {
+                if self.require_table(table_idx)? != ElemType::FuncRef {
+                    return self.error(format!("call_indirect on table #{}, which is not a funcref table", table_idx.0));
+                }
+                self.pop_expect(ValType::I32)?;
+                self.pop_all(&type_.params)?;
+                self.push_all(&type_.results);
+            }
+            // tail calls: the callee results are returned directly to the caller of this function
+            ReturnCall(function_idx) => match module.functions.get(function_idx.0) {
+                Some(function) => {
+                    self.require_tail_call_results(&function.type_.results)?;
+                    self.pop_all(&function.type_.params)?;
+                    self.set_unreachable();
+                }
+                None => return self.error(format!("unknown function #{}", function_idx.0)),
+            },
+            ReturnCallIndirect(ref type_, table_idx) => {
+                if self.require_table(table_idx)? != ElemType::FuncRef {
+                    return self.error(format!("return_call_indirect on table #{}, which is not a funcref table", table_idx.0));
+                }
+                self.require_tail_call_results(&type_.results)?;
+                self.pop_expect(ValType::I32)?;
+                self.pop_all(&type_.params)?;
+                self.set_unreachable();
+            }
+
+            Drop => { self.pop()?; }
+            Select => {
+                self.pop_expect(ValType::I32)?;
+                let ty1 = self.pop()?;
+                let ty2 = self.pop()?;
+                match (ty1, ty2) {
+                    (Some(ty1), Some(ty2)) if ty1 != ty2 =>
+                        return self.error(format!("select operands have different types {} and {}", ty1, ty2)),
+                    (Some(ty), _) | (_, Some(ty)) if ty.is_ref() =>
+                        return self.error(format!("select without type annotation on reference operands of type {}", ty)),
+                    _ => self.push(ty1.or(ty2)),
+                }
+            }
+            TypedSelect(_) => self.instr_type(&instr.to_type().unwrap())?,
+
+            Local(op, local_idx) => {
+                let ty = self.local_type(local_idx.0)?;
+                self.instr_type(&op.to_type(ty))?;
+            }
+            Global(op, global_idx) => match module.globals.get(global_idx.0) {
+                Some(global) => {
+                    if let (GlobalOp::SetGlobal, GlobalType(_, Mutability::Const)) = (op, global.type_) {
+                        return self.error(format!("set_global of immutable global #{}", global_idx.0));
+                    }
+                    self.instr_type(&op.to_type(global.type_.0))?;
+                }
+                None => return self.error(format!("unknown global #{}", global_idx.0)),
+            },
+
+            Load(op, memory_idx, memarg) => {
+                let index_type = self.require_memory(memory_idx)?;
+                self.require_offset(memarg, index_type)?;
+                self.require_alignment(memarg, op.natural_alignment())?;
+                let ty = self.memory_instr_type(instr);
+                self.instr_type(&ty)?;
+            }
+            Store(op, memory_idx, memarg) => {
+                let index_type = self.require_memory(memory_idx)?;
+                self.require_offset(memarg, index_type)?;
+                self.require_alignment(memarg, op.natural_alignment())?;
+                let ty = self.memory_instr_type(instr);
+                self.instr_type(&ty)?;
+            }
+            MemorySize(memory_idx) | MemoryGrow(memory_idx) | MemoryFill(memory_idx) => {
+                self.require_memory(memory_idx)?;
+                let ty = self.memory_instr_type(instr);
+                self.instr_type(&ty)?;
+            }
+            MemoryInit(data_idx, memory_idx) => {
+                self.require_data(data_idx)?;
+                self.require_memory(memory_idx)?;
+                let ty = self.memory_instr_type(instr);
+                self.instr_type(&ty)?;
+            }
+            DataDrop(data_idx) => self.require_data(data_idx)?,
+            MemoryCopy(dst_idx, src_idx) => {
+                self.require_memory(dst_idx)?;
+                self.require_memory(src_idx)?;
+                let ty = self.memory_instr_type(instr);
+                self.instr_type(&ty)?;
+            }
+            TableInit(element_idx, table_idx) => {
+                let element_type = self.require_element(element_idx)?;
+                let table_type = self.require_table(table_idx)?;
+                if element_type != table_type {
+                    return self.error(format!("table.init of element segment #{} with type {} into table #{} with type {}",
+                                              element_idx.0, element_type.to_type(), table_idx.0, table_type.to_type()));
+                }
+                self.instr_type(&instr.to_type().unwrap())?;
+            }
+            ElemDrop(element_idx) => { self.require_element(element_idx)?; }
+            TableCopy(dst_idx, src_idx) => {
+                let dst_type = self.require_table(dst_idx)?;
+                let src_type = self.require_table(src_idx)?;
+                if dst_type != src_type {
+                    return self.error(format!("table.copy from table #{} with type {} to table #{} with type {}",
+                                              src_idx.0, src_type.to_type(), dst_idx.0, dst_type.to_type()));
+                }
+                self.instr_type(&instr.to_type().unwrap())?;
+            }
+
+            RefNull(_) => self.instr_type(&instr.to_type().unwrap())?,
+            RefIsNull => {
+                match self.pop()? {
+                    Some(ty) if !ty.is_ref() =>
+                        return self.error(format!("ref.is_null on non-reference operand of type {}", ty)),
+                    _ => self.push(Some(ValType::I32)),
+                }
+            }
+            RefFunc(function_idx) => {
+                if function_idx.0 >= module.functions.len() {
+                    return self.error(format!("unknown function #{}", function_idx.0));
+                }
+                if !self.declared_refs.contains(&function_idx) {
+                    return self.error(format!("ref.func of function #{}, which is not declared in an element segment, export, or global", function_idx.0));
+                }
+                self.instr_type(&instr.to_type().unwrap())?;
+            }
+            TableGet(table_idx) => {
+                let ty = self.require_table(table_idx)?.to_type();
+                self.instr_type(&InstrType::new(&[ValType::I32], &[ty]))?;
+            }
+            TableSet(table_idx) => {
+                let ty = self.require_table(table_idx)?.to_type();
+                self.instr_type(&InstrType::new(&[ValType::I32, ty], &[]))?;
+            }
+            TableSize(table_idx) => {
+                self.require_table(table_idx)?;
+                self.instr_type(&instr.to_type().unwrap())?;
+            }
+            TableGrow(table_idx) => {
+                let ty = self.require_table(table_idx)?.to_type();
+                self.instr_type(&InstrType::new(&[ty, ValType::I32], &[ValType::I32]))?;
+            }
+            TableFill(table_idx) => {
+                let ty = self.require_table(table_idx)?.to_type();
+                self.instr_type(&InstrType::new(&[ValType::I32, ty, ValType::I32], &[]))?;
+            }
+
+            Shuffle(ref lanes) => {
+                if let Some(lane) = lanes.iter().find(|&&lane| lane >= 32) {
+                    return self.error(format!("i8x16.shuffle lane index {} out of range, must be < 32", lane));
+                }
+                self.instr_type(&instr.to_type().unwrap())?;
+            }
+            ExtractLane(op, lane) => {
+                self.require_lane(lane, op.lane_count())?;
+                self.instr_type(&op.to_type())?;
+            }
+            ReplaceLane(op, lane) => {
+                self.require_lane(lane, op.lane_count())?;
+                self.instr_type(&op.to_type())?;
+            }
+            LoadLane(op, memory_idx, memarg, lane) => {
+                let index_type = self.require_memory(memory_idx)?;
+                self.require_offset(memarg, index_type)?;
+                self.require_alignment(memarg, op.natural_alignment())?;
+                self.require_lane(lane, op.lane_count())?;
+                let ty = self.memory_instr_type(instr);
+                self.instr_type(&ty)?;
+            }
+            StoreLane(op, memory_idx, memarg, lane) => {
+                let index_type = self.require_memory(memory_idx)?;
+                self.require_offset(memarg, index_type)?;
+                self.require_alignment(memarg, op.natural_alignment())?;
+                self.require_lane(lane, op.lane_count())?;
+                let ty = self.memory_instr_type(instr);
+                self.instr_type(&ty)?;
+            }
+            AtomicLoad(op, memory_idx, memarg) => {
+                let index_type = self.require_memory(memory_idx)?;
+                self.require_offset(memarg, index_type)?;
+                self.require_exact_alignment(memarg, op.natural_alignment())?;
+                let ty = self.memory_instr_type(instr);
+                self.instr_type(&ty)?;
+            }
+            AtomicStore(op, memory_idx, memarg) => {
+                let index_type = self.require_memory(memory_idx)?;
+                self.require_offset(memarg, index_type)?;
+                self.require_exact_alignment(memarg, op.natural_alignment())?;
+                let ty = self.memory_instr_type(instr);
+                self.instr_type(&ty)?;
+            }
+            AtomicRmw(op, memory_idx, memarg) => {
+                let index_type = self.require_memory(memory_idx)?;
+                self.require_offset(memarg, index_type)?;
+                self.require_exact_alignment(memarg, op.natural_alignment())?;
+                let ty = self.memory_instr_type(instr);
+                self.instr_type(&ty)?;
+            }
+            AtomicCmpxchg(op, memory_idx, memarg) => {
+                let index_type = self.require_memory(memory_idx)?;
+                self.require_offset(memarg, index_type)?;
+                self.require_exact_alignment(memarg, op.natural_alignment())?;
+                let ty = self.memory_instr_type(instr);
+                self.instr_type(&ty)?;
+            }
+            AtomicWait(op, memory_idx, memarg) => {
+                let index_type = self.require_memory(memory_idx)?;
+                self.require_offset(memarg, index_type)?;
+                self.require_exact_alignment(memarg, op.natural_alignment())?;
+                let ty = self.memory_instr_type(instr);
+                self.instr_type(&ty)?;
+            }
+            AtomicNotify(memory_idx, memarg) => {
+                let index_type = self.require_memory(memory_idx)?;
+                self.require_offset(memarg, index_type)?;
+                self.require_exact_alignment(memarg, 2)?;
+                let ty = self.memory_instr_type(instr);
+                self.instr_type(&ty)?;
+            }
+            AtomicFence => {}
+
+            Const(_) | Numeric(_) => self.instr_type(&instr.to_type().unwrap())?,
+        }
+        Ok(())
+    }
+
+    fn instr_type(&mut self, ty: &InstrType) -> Result<(), ValidationError> {
+        self.pop_all(&ty.inputs)?;
+        self.push_all(&ty.results);
+        Ok(())
+    }
+}
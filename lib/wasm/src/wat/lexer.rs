@@ -0,0 +1,266 @@
+use std::fmt;
+use std::io;
+use std::str::Chars;
+use std::iter::Peekable;
+
+/*
+ * Tokenizer and S-expression reader for the WebAssembly text format.
+ * Numbers are not interpreted here (their meaning depends on the expected type), they are just
+ * keywords/"atoms" like `i32.add` or `offset=4`.
+ */
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Pos {
+    pub line: usize,
+    pub col: usize,
+}
+
+impl fmt::Display for Pos {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.col)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Token {
+    LParen,
+    RParen,
+    /// keywords, numbers, and reserved tokens like `offset=4`
+    Atom(String),
+    /// identifiers, without the leading $
+    Id(String),
+    /// string literals, with escapes already resolved (i.e., not necessarily valid UTF-8)
+    String(Vec<u8>),
+}
+
+#[derive(Debug, Clone)]
+pub enum Sexpr {
+    Atom(Token, Pos),
+    List(Vec<Sexpr>, Pos),
+}
+
+impl Sexpr {
+    pub fn pos(&self) -> Pos {
+        match *self {
+            Sexpr::Atom(_, pos) | Sexpr::List(_, pos) => pos,
+        }
+    }
+
+    pub fn as_keyword(&self) -> Option<&str> {
+        match *self {
+            Sexpr::Atom(Token::Atom(ref keyword), _) => Some(keyword),
+            _ => None,
+        }
+    }
+
+    pub fn as_id(&self) -> Option<&str> {
+        match *self {
+            Sexpr::Atom(Token::Id(ref id), _) => Some(id),
+            _ => None,
+        }
+    }
+
+    /// the first keyword of a list, e.g., "func" for (func $f ...)
+    pub fn list_head(&self) -> Option<&str> {
+        match *self {
+            Sexpr::List(ref items, _) => items.first().and_then(Sexpr::as_keyword),
+            _ => None,
+        }
+    }
+}
+
+pub fn error<T>(pos: Pos, message: String) -> io::Result<T> {
+    Err(io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", pos, message)))
+}
+
+struct Lexer<'a> {
+    chars: Peekable<Chars<'a>>,
+    pos: Pos,
+}
+
+impl<'a> Lexer<'a> {
+    fn bump(&mut self) -> Option<char> {
+        let c = self.chars.next()?;
+        if c == '\n' {
+            self.pos.line += 1;
+            self.pos.col = 1;
+        } else {
+            self.pos.col += 1;
+        }
+        Some(c)
+    }
+
+    fn skip_whitespace_and_comments(&mut self) -> io::Result<()> {
+        loop {
+            match self.chars.peek().cloned() {
+                Some(c) if c.is_whitespace() => { self.bump(); }
+                Some(';') => {
+                    let start = self.pos;
+                    self.bump();
+                    if self.bump() != Some(';') {
+                        return error(start, "unexpected character ';'".into());
+                    }
+                    // line comment
+                    while let Some(c) = self.bump() {
+                        if c == '\n' { break; }
+                    }
+                }
+                Some('(') => {
+                    // block comments (; ... ;) can be nested, everything else is a real paren
+                    let mut lookahead = self.chars.clone();
+                    lookahead.next();
+                    if lookahead.peek() != Some(&';') {
+                        return Ok(());
+                    }
+                    let start = self.pos;
+                    self.bump();
+                    self.bump();
+                    let mut depth = 1;
+                    while depth > 0 {
+                        match self.bump() {
+                            Some('(') if self.chars.peek() == Some(&';') => {
+                                self.bump();
+                                depth += 1;
+                            }
+                            Some(';') if self.chars.peek() == Some(&')') => {
+                                self.bump();
+                                depth -= 1;
+                            }
+                            Some(_) => {}
+                            None => return error(start, "unterminated block comment".into()),
+                        }
+                    }
+                }
+                _ => return Ok(()),
+            }
+        }
+    }
+
+    fn next_token(&mut self) -> io::Result<Option<(Token, Pos)>> {
+        self.skip_whitespace_and_comments()?;
+        let pos = self.pos;
+        let token = match self.chars.peek().cloned() {
+            None => return Ok(None),
+            Some('(') => {
+                self.bump();
+                Token::LParen
+            }
+            Some(')') => {
+                self.bump();
+                Token::RParen
+            }
+            Some('"') => {
+                self.bump();
+                Token::String(self.string(pos)?)
+            }
+            Some('$') => {
+                self.bump();
+                let id = self.idchars();
+                if id.is_empty() {
+                    return error(pos, "empty identifier".into());
+                }
+                Token::Id(id)
+            }
+            Some(c) if is_idchar(c) => Token::Atom(self.idchars()),
+            Some(c) => return error(pos, format!("unexpected character '{}'", c)),
+        };
+        Ok(Some((token, pos)))
+    }
+
+    fn idchars(&mut self) -> String {
+        let mut result = String::new();
+        while let Some(c) = self.chars.peek().cloned() {
+            if !is_idchar(c) { break; }
+            self.bump();
+            result.push(c);
+        }
+        result
+    }
+
+    fn string(&mut self, start: Pos) -> io::Result<Vec<u8>> {
+        let mut bytes = Vec::new();
+        loop {
+            let pos = self.pos;
+            match self.bump() {
+                None => return error(start, "unterminated string literal".into()),
+                Some('"') => return Ok(bytes),
+                Some('\\') => match self.bump() {
+                    Some('n') => bytes.push(b'\n'),
+                    Some('t') => bytes.push(b'\t'),
+                    Some('r') => bytes.push(b'\r'),
+                    Some('"') => bytes.push(b'"'),
+                    Some('\'') => bytes.push(b'\''),
+                    Some('\\') => bytes.push(b'\\'),
+                    Some('u') => {
+                        if self.bump() != Some('{') {
+                            return error(pos, "expected '{' after \\u in string literal".into());
+                        }
+                        let mut code_point = 0u32;
+                        loop {
+                            match self.bump() {
+                                Some('}') => break,
+                                Some(c) if c.is_digit(16) && code_point <= 0x10ffff =>
+                                    code_point = code_point * 16 + c.to_digit(16).unwrap(),
+                                _ => return error(pos, "invalid unicode escape in string literal".into()),
+                            }
+                        }
+                        match ::std::char::from_u32(code_point) {
+                            Some(c) => {
+                                let mut buf = [0; 4];
+                                bytes.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+                            }
+                            None => return error(pos, format!("invalid unicode code point {:x} in string literal", code_point)),
+                        }
+                    }
+                    Some(high) if high.is_digit(16) => match self.bump() {
+                        Some(low) if low.is_digit(16) =>
+                            bytes.push((high.to_digit(16).unwrap() * 16 + low.to_digit(16).unwrap()) as u8),
+                        _ => return error(pos, "invalid hex escape in string literal".into()),
+                    },
+                    _ => return error(pos, "invalid escape in string literal".into()),
+                },
+                Some(c) => {
+                    let mut buf = [0; 4];
+                    bytes.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+                }
+            }
+        }
+    }
+}
+
+pub fn is_idchar(c: char) -> bool {
+    match c {
+        '0'..='9' | 'a'..='z' | 'A'..='Z' => true,
+        '!' | '#' | '$' | '%' | '&' | '\'' | '*' | '+' | '-' | '.' | '/' | ':' | '<' | '=' | '>' | '?' | '@' | '\\' | '^' | '_' | '`' | '|' | '~' => true,
+        _ => false,
+    }
+}
+
+/// read all top-level S-expressions from the input
+pub fn read_sexprs(input: &str) -> io::Result<Vec<Sexpr>> {
+    let mut lexer = Lexer {
+        chars: input.chars().peekable(),
+        pos: Pos { line: 1, col: 1 },
+    };
+
+    // stack of currently open lists, the bottom element collects the top-level expressions
+    let mut stack: Vec<(Vec<Sexpr>, Pos)> = vec![(Vec::new(), lexer.pos)];
+    while let Some((token, pos)) = lexer.next_token()? {
+        match token {
+            Token::LParen => stack.push((Vec::new(), pos)),
+            Token::RParen => {
+                if stack.len() == 1 {
+                    return error(pos, "unexpected ')'".into());
+                }
+                let (items, start) = stack.pop().unwrap();
+                stack.last_mut().unwrap().0.push(Sexpr::List(items, start));
+            }
+            token => stack.last_mut().unwrap().0.push(Sexpr::Atom(token, pos)),
+        }
+    }
+
+    if stack.len() > 1 {
+        return error(stack.last().unwrap().1, "unclosed '('".into());
+    }
+    Ok(stack.pop().unwrap().0)
+}
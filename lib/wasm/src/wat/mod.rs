@@ -0,0 +1,15 @@
+//! WebAssembly text format (.wat), parsed directly into the high-level AST (no binary in between)
+//! and printed from it (see the Display impls for Module, Function, and Instr).
+//!
+//! Supports the MVP module fields, folded and flat instructions, identifiers for all index spaces
+//! and labels, inline imports/exports, and both the old (e.g., get_local) and current
+//! (e.g., local.get) instruction names. Identifiers of functions, locals, and globals become their
+//! debug names, like wat2wasm --debug-names.
+
+mod lexer;
+mod numbers;
+mod parser;
+mod print;
+
+pub use self::parser::parse_module;
+pub use self::print::{module_to_wat, InstrOrigins, PrintOptions};
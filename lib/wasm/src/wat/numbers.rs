@@ -0,0 +1,189 @@
+/*
+ * Interpretation of number literals in the text format. Integers can be given in decimal or
+ * hexadecimal notation, with an optional sign and _ as digit separator. Floats additionally allow
+ * inf, nan, nan:0x<payload>, and hexadecimal floats, e.g., 0x1.8p3.
+ */
+
+fn split_sign(literal: &str) -> (bool, &str) {
+    if literal.starts_with('-') {
+        (true, &literal[1..])
+    } else if literal.starts_with('+') {
+        (false, &literal[1..])
+    } else {
+        (false, literal)
+    }
+}
+
+fn parse_magnitude(literal: &str) -> Option<u64> {
+    let (digits, radix) = if literal.starts_with("0x") {
+        (&literal[2..], 16)
+    } else {
+        (literal, 10)
+    };
+    if digits.is_empty() || digits.starts_with('_') || digits.ends_with('_') {
+        return None;
+    }
+    let mut result: u64 = 0;
+    for c in digits.chars().filter(|&c| c != '_') {
+        let digit = c.to_digit(radix)?;
+        result = result.checked_mul(radix as u64)?.checked_add(digit as u64)?;
+    }
+    Some(result)
+}
+
+/// unsigned integers, e.g., indices, limits, or memarg offsets
+pub fn parse_u32(literal: &str) -> Option<u32> {
+    let magnitude = parse_magnitude(literal)?;
+    if magnitude <= u32::max_value() as u64 { Some(magnitude as u32) } else { None }
+}
+
+/// memory64 limits and memarg offsets
+pub fn parse_u64(literal: &str) -> Option<u64> {
+    parse_magnitude(literal)
+}
+
+/// lane indices of SIMD instructions
+pub fn parse_u8(literal: &str) -> Option<u8> {
+    let magnitude = parse_magnitude(literal)?;
+    if magnitude <= u8::max_value() as u64 { Some(magnitude as u8) } else { None }
+}
+
+/// i8x16 and i16x8 lanes of v128.const, signed or unsigned as for i32.const
+pub fn parse_i8(literal: &str) -> Option<i8> {
+    let (negative, literal) = split_sign(literal);
+    let magnitude = parse_magnitude(literal)?;
+    if negative {
+        if magnitude <= 1 << 7 { Some((magnitude as i64).wrapping_neg() as i8) } else { None }
+    } else {
+        if magnitude <= u8::max_value() as u64 { Some(magnitude as u8 as i8) } else { None }
+    }
+}
+
+pub fn parse_i16(literal: &str) -> Option<i16> {
+    let (negative, literal) = split_sign(literal);
+    let magnitude = parse_magnitude(literal)?;
+    if negative {
+        if magnitude <= 1 << 15 { Some((magnitude as i64).wrapping_neg() as i16) } else { None }
+    } else {
+        if magnitude <= u16::max_value() as u64 { Some(magnitude as u16 as i16) } else { None }
+    }
+}
+
+/// i32.const accepts both signed and unsigned interpretation, i.e., -2^31 up to 2^32-1
+pub fn parse_i32(literal: &str) -> Option<i32> {
+    let (negative, literal) = split_sign(literal);
+    let magnitude = parse_magnitude(literal)?;
+    if negative {
+        if magnitude <= 1 << 31 { Some((magnitude as i64).wrapping_neg() as i32) } else { None }
+    } else {
+        if magnitude <= u32::max_value() as u64 { Some(magnitude as u32 as i32) } else { None }
+    }
+}
+
+/// same as for i32, i.e., -2^63 up to 2^64-1
+pub fn parse_i64(literal: &str) -> Option<i64> {
+    let (negative, literal) = split_sign(literal);
+    let magnitude = parse_magnitude(literal)?;
+    if negative {
+        if magnitude <= 1 << 63 { Some(magnitude.wrapping_neg() as i64) } else { None }
+    } else {
+        Some(magnitude as i64)
+    }
+}
+
+pub fn parse_f32(literal: &str) -> Option<f32> {
+    let (negative, unsigned) = split_sign(literal);
+    let value = if unsigned.starts_with("nan:0x") {
+        let payload = parse_magnitude(&unsigned[4..])?;
+        if payload == 0 || payload >= 1 << 23 {
+            return None;
+        }
+        f32::from_bits(0x7f80_0000 | payload as u32)
+    } else {
+        // NOTE goes via f64, which might round twice for hex floats with many digits
+        parse_f64(unsigned)? as f32
+    };
+    Some(if negative { -value } else { value })
+}
+
+pub fn parse_f64(literal: &str) -> Option<f64> {
+    let (negative, unsigned) = split_sign(literal);
+    let value = match unsigned {
+        "inf" => ::std::f64::INFINITY,
+        "nan" => ::std::f64::NAN,
+        _ if unsigned.starts_with("nan:0x") => {
+            let payload = parse_magnitude(&unsigned[4..])?;
+            if payload == 0 || payload >= 1 << 52 {
+                return None;
+            }
+            f64::from_bits(0x7ff0_0000_0000_0000 | payload)
+        }
+        _ if unsigned.starts_with("0x") => parse_hex_float(&unsigned[2..])?,
+        _ => {
+            // Rust's float parsing does not know about digit separators, but otherwise matches
+            let without_separators: String = unsigned.chars().filter(|&c| c != '_').collect();
+            if !without_separators.starts_with(|c: char| c.is_digit(10)) {
+                return None;
+            }
+            without_separators.parse().ok()?
+        }
+    };
+    Some(if negative { -value } else { value })
+}
+
+/// hexadecimal float without the 0x prefix, e.g., 1.8p3 or 1f
+fn parse_hex_float(literal: &str) -> Option<f64> {
+    let literal: String = literal.chars().filter(|&c| c != '_').collect();
+    let (mantissa_str, exponent_str) = match literal.find(|c| c == 'p' || c == 'P') {
+        Some(i) => (&literal[..i], Some(&literal[i + 1..])),
+        None => (&literal[..], None),
+    };
+    let (integer_str, fraction_str) = match mantissa_str.find('.') {
+        Some(i) => (&mantissa_str[..i], &mantissa_str[i + 1..]),
+        None => (mantissa_str, ""),
+    };
+    if integer_str.is_empty() {
+        return None;
+    }
+
+    // accumulate up to 64 bits of mantissa, digits beyond that only shift the exponent
+    let mut mantissa: u64 = 0;
+    let mut exponent: i32 = 0;
+    for c in integer_str.chars() {
+        let digit = c.to_digit(16)? as u64;
+        if mantissa >> 60 == 0 {
+            mantissa = mantissa * 16 + digit;
+        } else {
+            exponent += 4;
+        }
+    }
+    for c in fraction_str.chars() {
+        let digit = c.to_digit(16)? as u64;
+        if mantissa >> 60 == 0 {
+            mantissa = mantissa * 16 + digit;
+            exponent -= 4;
+        }
+    }
+    if let Some(exponent_str) = exponent_str {
+        let (negative, digits) = split_sign(exponent_str);
+        if digits.is_empty() || !digits.chars().all(|c| c.is_digit(10)) {
+            return None;
+        }
+        let explicit_exponent: i32 = digits.parse().ok()?;
+        exponent += if negative { -explicit_exponent } else { explicit_exponent };
+    }
+
+    // scale in steps, since 2^exponent alone might not be representable
+    let mut value = mantissa as f64;
+    while exponent > 0 {
+        let step = if exponent > 1000 { 1000 } else { exponent };
+        value *= 2f64.powi(step);
+        exponent -= step;
+    }
+    while exponent < 0 {
+        let step = if exponent < -1000 { -1000 } else { exponent };
+        value *= 2f64.powi(step);
+        exponent -= step;
+    }
+    Some(value)
+}
@@ -0,0 +1,1777 @@
+use ast::{*, highlevel as hl};
+use ast::highlevel::{AtomicCmpxchgOp, AtomicLoadOp, AtomicRmwOp, AtomicStoreOp, AtomicWaitOp, ExtractLaneOp, GlobalOp::*, LoadLaneOp, LoadOp::*, LocalOp::*, NumericOp, ReplaceLaneOp, StoreLaneOp, StoreOp::*};
+use std::collections::{BTreeMap, HashMap};
+use std::io;
+use super::lexer::{error, read_sexprs, Pos, Sexpr, Token};
+use super::numbers::*;
+
+/*
+ * Parser from the text format to the high-level AST. Works in three passes over the module fields:
+ * 1. declare all types and the ids of functions, tables, memories, globals, tags, and element and
+ *    data segments (since they can be referenced before they are defined, e.g., in calls to
+ *    functions further down),
+ * 2. parse imports and definitions of functions, tables, memories, globals, tags, and segments (in order,
+ *    which is also the order of the high-level index spaces),
+ * 3. attach exports and the start function to the parsed items.
+ */
+
+pub fn parse_module(input: &str) -> io::Result<hl::Module> {
+    let sexprs = read_sexprs(input)?;
+
+    // either a single (module ...) or just the module fields (as in the spec's abbreviation)
+    let (module_id, fields) = match sexprs.first().map(|sexpr| (sexpr, sexpr.list_head())) {
+        Some((&Sexpr::List(ref items, pos), Some("module"))) => {
+            if let Some(sexpr) = sexprs.get(1) {
+                return error(sexpr.pos(), "unexpected content after module".into());
+            }
+            let mut items = Items::new(&items[1..], pos);
+            let id = items.opt_id();
+            (id, items)
+        }
+        _ => (None, Items::new(&sexprs[..], Pos { line: 1, col: 1 })),
+    };
+
+    let mut context = Context::default();
+    for field in fields.clone() {
+        context.declare(field)?;
+    }
+
+    let mut module = hl::Module::default();
+    module.name = module_id.map(String::from);
+    for field in fields.clone() {
+        context.define(field, &mut module)?;
+    }
+    for field in fields {
+        context.attach(field, &mut module)?;
+    }
+    Ok(module)
+}
+
+
+/* Helpers for iterating over the contents of a list */
+
+#[derive(Clone)]
+struct Items<'a> {
+    items: &'a [Sexpr],
+    next: usize,
+    // for errors at the end of the list
+    pos: Pos,
+}
+
+impl<'a> Iterator for Items<'a> {
+    type Item = &'a Sexpr;
+
+    fn next(&mut self) -> Option<&'a Sexpr> {
+        let item = self.items.get(self.next)?;
+        self.next += 1;
+        Some(item)
+    }
+}
+
+impl<'a> Items<'a> {
+    fn new(items: &'a [Sexpr], pos: Pos) -> Self {
+        Items { items, next: 0, pos }
+    }
+
+    /// contents of a list after its head keyword
+    fn of_list(sexpr: &'a Sexpr) -> Option<Items<'a>> {
+        match *sexpr {
+            Sexpr::List(ref items, pos) if !items.is_empty() => Some(Items::new(&items[1..], pos)),
+            _ => None,
+        }
+    }
+
+    fn peek(&self) -> Option<&'a Sexpr> {
+        self.items.get(self.next)
+    }
+
+    fn peek_head(&self) -> Option<&'a str> {
+        self.peek().and_then(Sexpr::list_head)
+    }
+
+    fn is_empty(&self) -> bool {
+        self.next >= self.items.len()
+    }
+
+    fn expect(&mut self, what: &str) -> io::Result<&'a Sexpr> {
+        let pos = self.pos;
+        match self.next() {
+            Some(sexpr) => Ok(sexpr),
+            None => error(pos, format!("expected {} before end of list", what)),
+        }
+    }
+
+    /// consume the next item if it is a list with the given head keyword
+    fn next_list(&mut self, head: &str) -> Option<Items<'a>> {
+        if self.peek_head() == Some(head) {
+            self.next().and_then(Items::of_list)
+        } else {
+            None
+        }
+    }
+
+    fn opt_id(&mut self) -> Option<&'a str> {
+        let id = self.peek().and_then(Sexpr::as_id)?;
+        self.next += 1;
+        Some(id)
+    }
+
+    fn string(&mut self, what: &str) -> io::Result<&'a [u8]> {
+        match *self.expect(what)? {
+            Sexpr::Atom(Token::String(ref bytes), _) => Ok(bytes),
+            ref sexpr => error(sexpr.pos(), format!("expected {}, got {}", what, describe(sexpr))),
+        }
+    }
+
+    fn name(&mut self, what: &str) -> io::Result<String> {
+        let pos = self.peek().map(Sexpr::pos).unwrap_or(self.pos);
+        match String::from_utf8(self.string(what)?.to_vec()) {
+            Ok(name) => Ok(name),
+            Err(_) => error(pos, format!("{} is not valid UTF-8", what)),
+        }
+    }
+
+    fn u64(&mut self, what: &str) -> io::Result<u64> {
+        let sexpr = self.expect(what)?;
+        match sexpr.as_keyword().and_then(parse_u64) {
+            Some(u) => Ok(u),
+            None => error(sexpr.pos(), format!("expected {}, got {}", what, describe(sexpr))),
+        }
+    }
+
+    fn end(&mut self) -> io::Result<()> {
+        match self.peek() {
+            Some(sexpr) => error(sexpr.pos(), format!("unexpected {}", describe(sexpr))),
+            None => Ok(()),
+        }
+    }
+}
+
+fn describe(sexpr: &Sexpr) -> String {
+    match *sexpr {
+        Sexpr::Atom(Token::Atom(ref keyword), _) => format!("'{}'", keyword),
+        Sexpr::Atom(Token::Id(ref id), _) => format!("'${}'", id),
+        Sexpr::Atom(Token::String(_), _) => "string".into(),
+        Sexpr::Atom(ref token, _) => format!("{:?}", token),
+        Sexpr::List(_, _) => match sexpr.list_head() {
+            Some(head) => format!("'({} ...)'", head),
+            None => "list".into(),
+        },
+    }
+}
+
+/// numeric index or identifier
+fn is_index(sexpr: &Sexpr) -> bool {
+    match *sexpr {
+        Sexpr::Atom(Token::Id(_), _) => true,
+        Sexpr::Atom(Token::Atom(ref keyword), _) => keyword.starts_with(|c: char| c.is_digit(10)),
+        _ => false,
+    }
+}
+
+fn val_type(sexpr: &Sexpr) -> io::Result<ValType> {
+    match sexpr.as_keyword() {
+        Some("i32") => Ok(ValType::I32),
+        Some("i64") => Ok(ValType::I64),
+        Some("f32") => Ok(ValType::F32),
+        Some("f64") => Ok(ValType::F64),
+        Some("v128") => Ok(ValType::V128),
+        Some("funcref") => Ok(ValType::FuncRef),
+        Some("externref") => Ok(ValType::ExternRef),
+        _ => error(sexpr.pos(), format!("expected value type, got {}", describe(sexpr))),
+    }
+}
+
+
+/* Index spaces and identifiers */
+
+#[derive(Default)]
+struct IndexSpace {
+    ids: HashMap<String, usize>,
+    count: usize,
+}
+
+impl IndexSpace {
+    fn declare(&mut self, id: Option<&str>, pos: Pos, what: &str) -> io::Result<()> {
+        if let Some(id) = id {
+            if self.ids.insert(id.to_string(), self.count).is_some() {
+                return error(pos, format!("redefinition of {} ${}", what, id));
+            }
+        }
+        self.count += 1;
+        Ok(())
+    }
+
+    fn resolve<T>(&self, sexpr: &Sexpr, what: &str) -> io::Result<Idx<T>> {
+        let idx = match *sexpr {
+            Sexpr::Atom(Token::Id(ref id), pos) => match self.ids.get(id) {
+                Some(&idx) => idx,
+                None => return error(pos, format!("unknown {} ${}", what, id)),
+            },
+            Sexpr::Atom(Token::Atom(ref keyword), pos) => match parse_u32(keyword) {
+                Some(idx) => idx as usize,
+                None => return error(pos, format!("expected {} index, got '{}'", what, keyword)),
+            },
+            _ => return error(sexpr.pos(), format!("expected {} index, got {}", what, describe(sexpr))),
+        };
+        Ok(idx.into())
+    }
+}
+
+#[derive(Default)]
+struct Context {
+    types: Vec<FunctionType>,
+    type_ids: IndexSpace,
+    functions: IndexSpace,
+    tables: IndexSpace,
+    memories: IndexSpace,
+    globals: IndexSpace,
+    tags: IndexSpace,
+    elements: IndexSpace,
+    data: IndexSpace,
+}
+
+impl Context {
+    /// pass 1: types and identifiers
+    fn declare(&mut self, field: &Sexpr) -> io::Result<()> {
+        let pos = field.pos();
+        let (head, mut items) = match (field.list_head(), Items::of_list(field)) {
+            (Some(head), Some(items)) => (head, items),
+            _ => return error(pos, format!("expected module field, got {}", describe(field))),
+        };
+        match head {
+            "type" => {
+                let id = items.opt_id();
+                self.type_ids.declare(id, pos, "type")?;
+                let mut func_items = match items.next_list("func") {
+                    Some(func_items) => func_items,
+                    None => return error(pos, "expected function type (func ...)".into()),
+                };
+                let type_ = self.params_results(&mut func_items, None)?;
+                func_items.end()?;
+                items.end()?;
+                self.types.push(type_);
+            }
+            "func" => self.functions.declare(items.opt_id(), pos, "function")?,
+            "table" => {
+                self.tables.declare(items.opt_id(), pos, "table")?;
+                // inline elements are an (anonymous) element segment of their own
+                if items.any(|sexpr| sexpr.list_head() == Some("elem")) {
+                    self.elements.declare(None, pos, "element segment")?;
+                }
+            }
+            "memory" => {
+                self.memories.declare(items.opt_id(), pos, "memory")?;
+                if items.any(|sexpr| sexpr.list_head() == Some("data")) {
+                    self.data.declare(None, pos, "data segment")?;
+                }
+            }
+            "global" => self.globals.declare(items.opt_id(), pos, "global")?,
+            "tag" => self.tags.declare(items.opt_id(), pos, "tag")?,
+            "elem" => self.elements.declare(items.opt_id(), pos, "element segment")?,
+            "data" => self.data.declare(items.opt_id(), pos, "data segment")?,
+            "import" => {
+                items.name("import module name")?;
+                items.name("import name")?;
+                let desc = items.expect("import description")?;
+                let id = Items::of_list(desc).and_then(|mut desc_items| desc_items.opt_id());
+                match desc.list_head() {
+                    Some("func") => self.functions.declare(id, desc.pos(), "function")?,
+                    Some("table") => self.tables.declare(id, desc.pos(), "table")?,
+                    Some("memory") => self.memories.declare(id, desc.pos(), "memory")?,
+                    Some("global") => self.globals.declare(id, desc.pos(), "global")?,
+                    Some("tag") => self.tags.declare(id, desc.pos(), "tag")?,
+                    _ => return error(desc.pos(), format!("expected import description, got {}", describe(desc))),
+                }
+            }
+            "export" | "start" => {}
+            _ => return error(pos, format!("unknown module field '{}'", head)),
+        }
+        Ok(())
+    }
+
+    /// pass 2: imports and definitions
+    fn define(&self, field: &Sexpr, module: &mut hl::Module) -> io::Result<()> {
+        let pos = field.pos();
+        let mut items = Items::of_list(field).unwrap();
+        match field.list_head().unwrap() {
+            "func" => {
+                let id = items.opt_id();
+                let function = self.function(id, &mut items, None)?;
+                module.functions.push(function);
+            }
+            "table" => {
+                items.opt_id();
+                self.table(&mut items, None, module)?;
+            }
+            "memory" => {
+                items.opt_id();
+                self.memory(&mut items, None, module)?;
+            }
+            "global" => {
+                let id = items.opt_id();
+                let global = self.global(id, &mut items, None)?;
+                module.globals.push(global);
+            }
+            "tag" => {
+                items.opt_id();
+                let tag = self.tag(&mut items, None)?;
+                module.tags.push(tag);
+            }
+            "elem" => {
+                items.opt_id();
+                let element = self.element(&mut items, pos)?;
+                module.elements.push(element);
+            }
+            "data" => {
+                items.opt_id();
+                let data = self.data(&mut items, pos)?;
+                module.data.push(data);
+            }
+            "import" => {
+                let import = Some((items.name("import module name")?, items.name("import name")?));
+                let desc = items.next().unwrap();
+                items.end()?;
+                let mut desc_items = Items::of_list(desc).unwrap();
+                let id = desc_items.opt_id();
+                match desc.list_head().unwrap() {
+                    "func" => module.functions.push(self.function(id, &mut desc_items, import)?),
+                    "table" => self.table(&mut desc_items, import, module)?,
+                    "memory" => self.memory(&mut desc_items, import, module)?,
+                    "global" => module.globals.push(self.global(id, &mut desc_items, import)?),
+                    "tag" => module.tags.push(self.tag(&mut desc_items, import)?),
+                    _ => unreachable!("checked in declare()"),
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// pass 3: everything that refers to the items of pass 2
+    fn attach(&self, field: &Sexpr, module: &mut hl::Module) -> io::Result<()> {
+        let pos = field.pos();
+        let mut items = Items::of_list(field).unwrap();
+        match field.list_head().unwrap() {
+            "export" => {
+                let name = items.name("export name")?;
+                let desc = items.expect("export description")?;
+                let mut desc_items = match Items::of_list(desc) {
+                    Some(desc_items) => desc_items,
+                    None => return error(desc.pos(), format!("expected export description, got {}", describe(desc))),
+                };
+                let idx = desc_items.expect("index")?;
+                desc_items.end()?;
+                items.end()?;
+                let exports = match desc.list_head() {
+                    Some("func") => self.functions.resolve(idx, "function")
+                        .and_then(|idx| item(&mut module.functions, idx, pos, "function")).map(|f| &mut f.export),
+                    Some("table") => self.tables.resolve(idx, "table")
+                        .and_then(|idx| item(&mut module.tables, idx, pos, "table")).map(|t| &mut t.export),
+                    Some("memory") => self.memories.resolve(idx, "memory")
+                        .and_then(|idx| item(&mut module.memories, idx, pos, "memory")).map(|m| &mut m.export),
+                    Some("global") => self.globals.resolve(idx, "global")
+                        .and_then(|idx| item(&mut module.globals, idx, pos, "global")).map(|g| &mut g.export),
+                    Some("tag") => self.tags.resolve(idx, "tag")
+                        .and_then(|idx| item(&mut module.tags, idx, pos, "tag")).map(|t| &mut t.export),
+                    _ => return error(desc.pos(), format!("expected export description, got {}", describe(desc))),
+                }?;
+                exports.push(name);
+            }
+            "start" => {
+                let function_idx = self.functions.resolve(items.expect("function index")?, "function")?;
+                items.end()?;
+                if function_idx.0 >= module.functions.len() {
+                    return error(pos, format!("start function index {} out of bounds", function_idx.0));
+                }
+                module.start = Some(function_idx);
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /* Module fields */
+
+    /// function definition or import, after the optional identifier
+    fn function(&self, id: Option<&str>, items: &mut Items, import: Option<(String, String)>) -> io::Result<hl::Function> {
+        let (export, inline_import) = exports_and_import(items)?;
+        let import = import.or(inline_import);
+
+        let mut param_ids = Vec::new();
+        let type_ = self.type_use(items, Some(&mut param_ids))?;
+
+        let mut local_ids = param_ids;
+        let code = if import.is_some() {
+            items.end()?;
+            None
+        } else {
+            let mut locals = Vec::new();
+            while let Some(mut local_items) = items.next_list("local") {
+                self.val_types(&mut local_items, &mut locals, &mut local_ids, "local")?;
+            }
+
+            let mut body = Body::new(self, &local_ids);
+            while !items.is_empty() {
+                body.instr(items)?;
+            }
+            Some(hl::Code {
+                locals,
+                body: body.finish(items.pos)?,
+            })
+        };
+
+        let local_names = local_ids.into_iter()
+            .enumerate()
+            .filter_map(|(idx, id)| id.map(|id| (idx.into(), id)))
+            .collect::<BTreeMap<_, _>>();
+
+        Ok(hl::Function {
+            type_,
+            import,
+            code,
+            export,
+            name: id.map(String::from),
+            local_names,
+        })
+    }
+
+    /// also adds the inline element segment (if any) to the module
+    fn table(&self, items: &mut Items, import: Option<(String, String)>, module: &mut hl::Module) -> io::Result<()> {
+        let (export, inline_import) = exports_and_import(items)?;
+        let import = import.or(inline_import);
+
+        let table_idx = module.tables.len().into();
+        let (elem_type, limits) = if import.is_none() && items.peek().map_or(false, |sexpr| !is_index(sexpr)) {
+            // abbreviation: element type followed by inline elements, table has exactly their size
+            let elem_type = elem_type(items)?;
+            let mut elem_items = match items.next_list("elem") {
+                Some(elem_items) => elem_items,
+                None => return error(items.pos, "expected table limits or inline elements".into()),
+            };
+            // either all function indices or all element expressions
+            let contents = if elem_items.peek().map_or(false, |sexpr| sexpr.list_head().is_some()) {
+                hl::ElementItems::Exprs(elem_type, self.elem_exprs(&mut elem_items)?)
+            } else {
+                hl::ElementItems::Functions(self.elem_functions(&mut elem_items)?)
+            };
+            let size = contents.len() as u64;
+            module.elements.push(hl::Element {
+                mode: hl::ElementMode::Active {
+                    table_idx,
+                    offset: vec![hl::Instr::Const(Val::I32(0)), hl::Instr::End],
+                },
+                items: contents,
+            });
+            (elem_type, Limits { initial_size: size, max_size: Some(size), shared: false, memory64: false })
+        } else {
+            let limits = limits(items)?;
+            (elem_type(items)?, limits)
+        };
+        items.end()?;
+
+        module.tables.push(hl::Table {
+            type_: TableType(elem_type, limits),
+            import,
+            export,
+        });
+        Ok(())
+    }
+
+    /// also adds the inline data segment (if any) to the module
+    fn memory(&self, items: &mut Items, import: Option<(String, String)>, module: &mut hl::Module) -> io::Result<()> {
+        const PAGE_SIZE: usize = 64 * 1024;
+
+        let (export, inline_import) = exports_and_import(items)?;
+        let import = import.or(inline_import);
+
+        // memory64 proposal: optional index type, i32 if omitted
+        let memory64 = match items.peek().and_then(Sexpr::as_keyword) {
+            Some("i64") => { items.next(); true }
+            Some("i32") => { items.next(); false }
+            _ => false,
+        };
+
+        let memory_idx = module.memories.len().into();
+        let mut limits = match items.next_list("data") {
+            Some(_) if import.is_some() => return error(items.pos, "imported memory cannot have inline data".into()),
+            // abbreviation: inline data, memory has exactly the size to hold it
+            Some(mut data_items) => {
+                let mut bytes = Vec::new();
+                while !data_items.is_empty() {
+                    bytes.extend_from_slice(data_items.string("data string")?);
+                }
+                let pages = ((bytes.len() + PAGE_SIZE - 1) / PAGE_SIZE) as u64;
+                let offset = if memory64 { Val::I64(0) } else { Val::I32(0) };
+                module.data.push(hl::Data {
+                    mode: hl::DataMode::Active {
+                        memory_idx,
+                        offset: vec![hl::Instr::Const(offset), hl::Instr::End],
+                    },
+                    bytes,
+                });
+                Limits { initial_size: pages, max_size: Some(pages), shared: false, memory64: false }
+            }
+            None => {
+                let mut limits = limits(items)?;
+                // threads proposal
+                if items.peek().and_then(Sexpr::as_keyword) == Some("shared") {
+                    items.next();
+                    limits.shared = true;
+                }
+                limits
+            }
+        };
+        limits.memory64 = memory64;
+        items.end()?;
+
+        module.memories.push(hl::Memory {
+            type_: MemoryType(limits),
+            import,
+            export,
+        });
+        Ok(())
+    }
+
+    /// element segment after the optional identifier, in the MVP syntax (table index, offset,
+    /// function indices) or with (table x), "func", and "declare" from the bulk memory proposal,
+    /// or with an element type followed by element expressions (reference types proposal)
+    fn element(&self, items: &mut Items, pos: Pos) -> io::Result<hl::Element> {
+        let mode = if items.peek().and_then(Sexpr::as_keyword) == Some("declare") {
+            items.next();
+            hl::ElementMode::Declarative
+        } else if is_segment_target_or_offset(items) {
+            hl::ElementMode::Active {
+                table_idx: self.segment_target(items, &self.tables, "table")?,
+                offset: self.offset(items, pos)?,
+            }
+        } else {
+            hl::ElementMode::Passive
+        };
+        let contents = match items.peek().and_then(Sexpr::as_keyword) {
+            Some("func") => {
+                items.next();
+                hl::ElementItems::Functions(self.elem_functions(items)?)
+            }
+            Some("funcref") | Some("externref") | Some("anyfunc") => {
+                let elem_type = elem_type(items)?;
+                hl::ElementItems::Exprs(elem_type, self.elem_exprs(items)?)
+            }
+            _ => hl::ElementItems::Functions(self.elem_functions(items)?),
+        };
+        Ok(hl::Element { mode, items: contents })
+    }
+
+    fn elem_functions(&self, items: &mut Items) -> io::Result<Vec<Idx<hl::Function>>> {
+        items.map(|sexpr| self.functions.resolve(sexpr, "function")).collect()
+    }
+
+    /// each is (item instr*) or a single folded instruction
+    fn elem_exprs(&self, items: &mut Items) -> io::Result<Vec<hl::Expr>> {
+        let mut exprs = Vec::new();
+        while !items.is_empty() {
+            let pos = items.pos;
+            exprs.push(self.abbreviated_expr(items, "item", pos)?);
+        }
+        Ok(exprs)
+    }
+
+    /// data segment after the optional identifier, passive if there is no memory and offset
+    fn data(&self, items: &mut Items, pos: Pos) -> io::Result<hl::Data> {
+        let mode = if is_segment_target_or_offset(items) {
+            hl::DataMode::Active {
+                memory_idx: self.segment_target(items, &self.memories, "memory")?,
+                offset: self.offset(items, pos)?,
+            }
+        } else {
+            hl::DataMode::Passive
+        };
+        let mut bytes = Vec::new();
+        while !items.is_empty() {
+            bytes.extend_from_slice(items.string("data string")?);
+        }
+        Ok(hl::Data { mode, bytes })
+    }
+
+    /// exception handling: tag definition or import, after the optional identifier
+    fn tag(&self, items: &mut Items, import: Option<(String, String)>) -> io::Result<hl::Tag> {
+        let (export, inline_import) = exports_and_import(items)?;
+        let import = import.or(inline_import);
+        let type_ = self.type_use(items, None)?;
+        items.end()?;
+        Ok(hl::Tag {
+            type_,
+            import,
+            export,
+        })
+    }
+
+    fn global(&self, id: Option<&str>, items: &mut Items, import: Option<(String, String)>) -> io::Result<hl::Global> {
+        let (export, inline_import) = exports_and_import(items)?;
+        let import = import.or(inline_import);
+
+        let type_ = match items.next_list("mut") {
+            Some(mut mut_items) => {
+                let val_type = val_type(mut_items.expect("value type")?)?;
+                mut_items.end()?;
+                GlobalType(val_type, Mutability::Mut)
+            }
+            None => GlobalType(val_type(items.expect("global type")?)?, Mutability::Const),
+        };
+
+        let init = if import.is_some() {
+            items.end()?;
+            None
+        } else {
+            Some(self.const_expr(items)?)
+        };
+
+        Ok(hl::Global {
+            type_,
+            import,
+            init,
+            export,
+            name: id.map(String::from),
+        })
+    }
+
+    /* Parts of module fields */
+
+    /// (type x)? (param ...)* (result ...)*, where the param/result lists take precedence if both
+    /// are given (we do not check for mismatches, that is the job of a validator)
+    fn type_use(&self, items: &mut Items, param_ids: Option<&mut Vec<Option<String>>>) -> io::Result<FunctionType> {
+        let type_ref = match items.next_list("type") {
+            Some(mut type_items) => {
+                let type_idx: Idx<FunctionType> = self.type_ids.resolve(type_items.expect("type index")?, "type")?;
+                type_items.end()?;
+                match self.types.get(type_idx.0) {
+                    Some(type_) => Some(type_.clone()),
+                    None => return error(type_items.pos, format!("type index {} out of bounds", type_idx.0)),
+                }
+            }
+            None => None,
+        };
+        let inline = self.params_results(items, param_ids)?;
+        Ok(match type_ref {
+            Some(type_) if inline.params.is_empty() && inline.results.is_empty() => type_,
+            _ => inline,
+        })
+    }
+
+    fn params_results(&self, items: &mut Items, mut param_ids: Option<&mut Vec<Option<String>>>) -> io::Result<FunctionType> {
+        let mut params = Vec::new();
+        let mut ids = Vec::new();
+        while let Some(mut param_items) = items.next_list("param") {
+            self.val_types(&mut param_items, &mut params, &mut ids, "param")?;
+        }
+        if let Some(ref mut param_ids) = param_ids {
+            param_ids.extend(ids);
+        }
+
+        let mut results = Vec::new();
+        while let Some(mut result_items) = items.next_list("result") {
+            while !result_items.is_empty() {
+                results.push(val_type(result_items.next().unwrap())?);
+            }
+        }
+        Ok(FunctionType::new(params, results))
+    }
+
+    /// contents of (param ...) or (local ...): either one named or many anonymous value types
+    fn val_types(&self, items: &mut Items, types: &mut Vec<ValType>, ids: &mut Vec<Option<String>>, what: &str) -> io::Result<()> {
+        if let Some(id) = items.opt_id() {
+            types.push(val_type(items.expect(&format!("{} type", what))?)?);
+            ids.push(Some(id.to_string()));
+            items.end()
+        } else {
+            for sexpr in items {
+                types.push(val_type(sexpr)?);
+                ids.push(None);
+            }
+            Ok(())
+        }
+    }
+
+    /// table or memory of an active element or data segment, either as plain index or
+    /// (table x)/(memory x)
+    fn segment_target<T>(&self, items: &mut Items, space: &IndexSpace, what: &str) -> io::Result<Idx<T>> {
+        let pos = items.peek().map(Sexpr::pos).unwrap_or(items.pos);
+        let idx: Idx<T> = if let Some(mut target_items) = items.next_list(what) {
+            let idx = space.resolve(target_items.expect("index")?, what)?;
+            target_items.end()?;
+            idx
+        } else if items.peek().map_or(false, is_index) {
+            space.resolve(items.next().unwrap(), what)?
+        } else {
+            0.into()
+        };
+        // segments are parsed before all tables/memories are, so check against the declared ones
+        if idx.0 >= space.count {
+            return error(pos, format!("{} index {} out of bounds (module has {} {}s)", what, idx.0, space.count, what));
+        }
+        Ok(idx)
+    }
+
+    /// (offset instr*) or a single folded instruction
+    fn offset(&self, items: &mut Items, pos: Pos) -> io::Result<hl::Expr> {
+        self.abbreviated_expr(items, "offset", pos)
+    }
+
+    /// (<keyword> instr*) or a single folded instruction
+    fn abbreviated_expr(&self, items: &mut Items, keyword: &str, pos: Pos) -> io::Result<hl::Expr> {
+        if let Some(mut expr_items) = items.next_list(keyword) {
+            return self.const_expr(&mut expr_items);
+        }
+        match items.peek() {
+            Some(&Sexpr::List(_, list_pos)) => {
+                let list = &items.items[items.next..items.next + 1];
+                items.next += 1;
+                self.const_expr(&mut Items::new(list, list_pos))
+            }
+            _ => error(pos, format!("expected segment {}", keyword)),
+        }
+    }
+
+    /// (initializer or offset) expressions, which do not have access to any locals
+    fn const_expr(&self, items: &mut Items) -> io::Result<hl::Expr> {
+        let mut body = Body::new(self, &[]);
+        while !items.is_empty() {
+            body.instr(items)?;
+        }
+        body.finish(items.pos)
+    }
+}
+
+/// active segments start with their table/memory or offset, passive ones directly with their contents
+fn is_segment_target_or_offset(items: &Items) -> bool {
+    items.peek().map_or(false, |sexpr| is_index(sexpr) || sexpr.list_head().is_some())
+}
+
+fn item<'a, T>(items: &'a mut Vec<T>, idx: Idx<T>, pos: Pos, what: &str) -> io::Result<&'a mut T> {
+    let len = items.len();
+    match items.get_mut(idx.0) {
+        Some(item) => Ok(item),
+        None => error(pos, format!("{} index {} out of bounds (module has {} {}s)", what, idx.0, len, what)),
+    }
+}
+
+/// inline (export "name")* (import "module" "name")?
+fn exports_and_import(items: &mut Items) -> io::Result<(Vec<String>, Option<(String, String)>)> {
+    let mut exports = Vec::new();
+    while let Some(mut export_items) = items.next_list("export") {
+        exports.push(export_items.name("export name")?);
+        export_items.end()?;
+    }
+    let import = match items.next_list("import") {
+        Some(mut import_items) => {
+            let import = (import_items.name("import module name")?, import_items.name("import name")?);
+            import_items.end()?;
+            Some(import)
+        }
+        None => None,
+    };
+    Ok((exports, import))
+}
+
+fn limits(items: &mut Items) -> io::Result<Limits> {
+    // NOTE sizes are parsed as u64 for memory64, the bounds for 32-bit tables and memories are checked by validation
+    let initial_size = items.u64("initial size")?;
+    let max_size = if items.peek().map_or(false, is_index) {
+        Some(items.u64("maximum size")?)
+    } else {
+        None
+    };
+    Ok(Limits { initial_size, max_size, shared: false, memory64: false })
+}
+
+fn elem_type(items: &mut Items) -> io::Result<ElemType> {
+    let sexpr = items.expect("element type")?;
+    match sexpr.as_keyword() {
+        // anyfunc is the old name
+        Some("funcref") | Some("anyfunc") => Ok(ElemType::FuncRef),
+        Some("externref") => Ok(ElemType::ExternRef),
+        _ => error(sexpr.pos(), format!("expected element type, got {}", describe(sexpr))),
+    }
+}
+
+
+/* Instructions */
+
+struct Body<'a> {
+    context: &'a Context,
+    locals: IndexSpace,
+    // identifiers of all enclosing blocks, innermost last
+    labels: Vec<Option<String>>,
+    instrs: Vec<hl::Instr>,
+}
+
+impl<'a> Body<'a> {
+    fn new(context: &'a Context, local_ids: &[Option<String>]) -> Self {
+        let mut locals = IndexSpace::default();
+        for id in local_ids {
+            // duplicate ids would have been rejected by a validator anyway, last one wins
+            if let Some(ref id) = *id {
+                locals.ids.insert(id.clone(), locals.count);
+            }
+            locals.count += 1;
+        }
+        Body {
+            context,
+            locals,
+            labels: Vec::new(),
+            instrs: Vec::new(),
+        }
+    }
+
+    fn finish(mut self, pos: Pos) -> io::Result<hl::Expr> {
+        if !self.labels.is_empty() {
+            return error(pos, format!("{} unclosed block(s) at end of expression", self.labels.len()));
+        }
+        self.instrs.push(hl::Instr::End);
+        Ok(self.instrs)
+    }
+
+    /// parse a single instruction (in flat syntax) or a folded instruction (as a list)
+    fn instr(&mut self, items: &mut Items) -> io::Result<()> {
+        let sexpr = items.next().unwrap();
+        let pos = sexpr.pos();
+        match *sexpr {
+            Sexpr::Atom(Token::Atom(ref keyword), _) => match keyword.as_str() {
+                "block" | "loop" | "if" | "try" => {
+                    let label = items.opt_id().map(String::from);
+                    let block_type = self.context.type_use(items, None)?;
+                    self.labels.push(label);
+                    self.instrs.push(match keyword.as_str() {
+                        "block" => hl::Instr::Block(block_type),
+                        "loop" => hl::Instr::Loop(block_type),
+                        "try" => hl::Instr::Try(block_type),
+                        _ => hl::Instr::If(block_type),
+                    });
+                }
+                "else" | "end" | "catch" | "catch_all" => {
+                    // optional repetition of the label, must match the one of the block
+                    // (catch is followed by the tag index, so the label is only there if two are)
+                    let has_label = keyword != "catch" || items.items.get(items.next + 1).map_or(false, is_index);
+                    if let Some(id) = if has_label { items.opt_id() } else { None } {
+                        if self.labels.last().and_then(Option::as_ref).map(String::as_str) != Some(id) {
+                            return error(pos, format!("mismatching label ${} after {}", id, keyword));
+                        }
+                    }
+                    if keyword == "else" {
+                        self.instrs.push(hl::Instr::Else);
+                    } else if keyword == "catch" {
+                        let tag_idx = self.context.tags.resolve(items.expect("tag index")?, "tag")?;
+                        self.instrs.push(hl::Instr::Catch(tag_idx));
+                    } else if keyword == "catch_all" {
+                        self.instrs.push(hl::Instr::CatchAll);
+                    } else {
+                        if self.labels.pop().is_none() {
+                            return error(pos, "unexpected 'end' without enclosing block".into());
+                        }
+                        self.instrs.push(hl::Instr::End);
+                    }
+                }
+                "delegate" => {
+                    // ends the try block, so the label is relative to the enclosing blocks
+                    if self.labels.pop().is_none() {
+                        return error(pos, "unexpected 'delegate' without enclosing block".into());
+                    }
+                    let label = self.label(items.expect("label")?)?;
+                    self.instrs.push(hl::Instr::Delegate(label));
+                }
+                keyword => {
+                    let instr = self.plain_instr(keyword, pos, items)?;
+                    self.instrs.push(instr);
+                }
+            },
+            Sexpr::List(_, _) => {
+                let keyword = match sexpr.list_head() {
+                    Some(keyword) => keyword,
+                    None => return error(pos, format!("expected folded instruction, got {}", describe(sexpr))),
+                };
+                let mut items = Items::of_list(sexpr).unwrap();
+                self.folded_instr(keyword, pos, &mut items)?;
+            }
+            _ => return error(pos, format!("expected instruction, got {}", describe(sexpr))),
+        }
+        Ok(())
+    }
+
+    fn folded_instr(&mut self, keyword: &str, pos: Pos, items: &mut Items) -> io::Result<()> {
+        match keyword {
+            "block" | "loop" => {
+                let label = items.opt_id().map(String::from);
+                let block_type = self.context.type_use(items, None)?;
+                self.labels.push(label);
+                self.instrs.push(if keyword == "block" { hl::Instr::Block(block_type) } else { hl::Instr::Loop(block_type) });
+                while !items.is_empty() {
+                    self.instr(items)?;
+                }
+                self.labels.pop();
+                self.instrs.push(hl::Instr::End);
+            }
+            "if" => {
+                let label = items.opt_id().map(String::from);
+                let block_type = self.context.type_use(items, None)?;
+                // condition (as folded instructions) is evaluated outside of the if block
+                while !items.is_empty() && items.peek_head() != Some("then") {
+                    match items.peek() {
+                        Some(&Sexpr::List(_, _)) => self.instr(items)?,
+                        Some(sexpr) => return error(sexpr.pos(), format!("expected folded instruction or (then ...), got {}", describe(sexpr))),
+                        None => unreachable!(),
+                    }
+                }
+
+                self.labels.push(label);
+                self.instrs.push(hl::Instr::If(block_type));
+                let mut then_items = match items.next_list("then") {
+                    Some(then_items) => then_items,
+                    None => return error(pos, "expected (then ...) in folded if".into()),
+                };
+                while !then_items.is_empty() {
+                    self.instr(&mut then_items)?;
+                }
+                if let Some(mut else_items) = items.next_list("else") {
+                    self.instrs.push(hl::Instr::Else);
+                    while !else_items.is_empty() {
+                        self.instr(&mut else_items)?;
+                    }
+                }
+                items.end()?;
+                self.labels.pop();
+                self.instrs.push(hl::Instr::End);
+            }
+            "try" => {
+                let label = items.opt_id().map(String::from);
+                let block_type = self.context.type_use(items, None)?;
+                self.labels.push(label);
+                self.instrs.push(hl::Instr::Try(block_type));
+                let mut do_items = match items.next_list("do") {
+                    Some(do_items) => do_items,
+                    None => return error(pos, "expected (do ...) in folded try".into()),
+                };
+                while !do_items.is_empty() {
+                    self.instr(&mut do_items)?;
+                }
+                if let Some(mut delegate_items) = items.next_list("delegate") {
+                    self.labels.pop();
+                    let label = self.label(delegate_items.expect("label")?)?;
+                    delegate_items.end()?;
+                    items.end()?;
+                    self.instrs.push(hl::Instr::Delegate(label));
+                    return Ok(());
+                }
+                while let Some(mut catch_items) = items.next_list("catch") {
+                    let tag_idx = self.context.tags.resolve(catch_items.expect("tag index")?, "tag")?;
+                    self.instrs.push(hl::Instr::Catch(tag_idx));
+                    while !catch_items.is_empty() {
+                        self.instr(&mut catch_items)?;
+                    }
+                }
+                if let Some(mut catch_all_items) = items.next_list("catch_all") {
+                    self.instrs.push(hl::Instr::CatchAll);
+                    while !catch_all_items.is_empty() {
+                        self.instr(&mut catch_all_items)?;
+                    }
+                }
+                items.end()?;
+                self.labels.pop();
+                self.instrs.push(hl::Instr::End);
+            }
+            keyword => {
+                // immediates come first, then the operands as folded instructions
+                let instr = self.plain_instr(keyword, pos, items)?;
+                while !items.is_empty() {
+                    self.instr(items)?;
+                }
+                self.instrs.push(instr);
+            }
+        }
+        Ok(())
+    }
+
+    /// all non-structured instructions, consumes their immediates from items
+    fn plain_instr(&mut self, keyword: &str, pos: Pos, items: &mut Items) -> io::Result<hl::Instr> {
+        use ast::highlevel::Instr::*;
+        Ok(match keyword {
+            "unreachable" => Unreachable,
+            "nop" => Nop,
+            "return" => Return,
+            "drop" => Drop,
+            "select" => match items.next_list("result") {
+                Some(mut result_items) => {
+                    let ty = val_type(result_items.expect("select type")?)?;
+                    result_items.end()?;
+                    TypedSelect(ty)
+                }
+                None => Select,
+            },
+
+            "br" => Br(self.label(items.expect("label")?)?),
+            "br_if" => BrIf(self.label(items.expect("label")?)?),
+            "br_table" => {
+                let mut labels = Vec::new();
+                while items.peek().map_or(false, is_index) {
+                    labels.push(self.label(items.next().unwrap())?);
+                }
+                match labels.pop() {
+                    Some(default) => BrTable(labels, default),
+                    None => return error(pos, "expected at least one label for br_table".into()),
+                }
+            }
+
+            "throw" => Throw(self.context.tags.resolve(items.expect("tag index")?, "tag")?),
+            "rethrow" => Rethrow(self.label(items.expect("label")?)?),
+
+            "call" => Call(self.context.functions.resolve(items.expect("function index")?, "function")?),
+            "call_indirect" => {
+                let table_idx = self.opt_table(items)?;
+                CallIndirect(self.context.type_use(items, None)?, table_idx)
+            }
+            "return_call" => ReturnCall(self.context.functions.resolve(items.expect("function index")?, "function")?),
+            "return_call_indirect" => {
+                let table_idx = self.opt_table(items)?;
+                ReturnCallIndirect(self.context.type_use(items, None)?, table_idx)
+            }
+
+            "get_local" | "local.get" => Local(GetLocal, self.locals.resolve(items.expect("local index")?, "local")?),
+            "set_local" | "local.set" => Local(SetLocal, self.locals.resolve(items.expect("local index")?, "local")?),
+            "tee_local" | "local.tee" => Local(TeeLocal, self.locals.resolve(items.expect("local index")?, "local")?),
+            "get_global" | "global.get" => Global(GetGlobal, self.context.globals.resolve(items.expect("global index")?, "global")?),
+            "set_global" | "global.set" => Global(SetGlobal, self.context.globals.resolve(items.expect("global index")?, "global")?),
+
+            "current_memory" | "memory.size" => MemorySize(self.opt_memory(items)?),
+            "grow_memory" | "memory.grow" => MemoryGrow(self.opt_memory(items)?),
+
+            "memory.init" => {
+                // memory index is optional, so it is only there if followed by another index
+                let first = items.expect("data segment index")?;
+                if items.peek().map_or(false, is_index) {
+                    let memory_idx = self.context.memories.resolve(first, "memory")?;
+                    MemoryInit(self.context.data.resolve(items.next().unwrap(), "data segment")?, memory_idx)
+                } else {
+                    MemoryInit(self.context.data.resolve(first, "data segment")?, 0.into())
+                }
+            }
+            "data.drop" => DataDrop(self.context.data.resolve(items.expect("data segment index")?, "data segment")?),
+            "memory.copy" => if items.peek().map_or(false, is_index) {
+                let dst_idx = self.context.memories.resolve(items.next().unwrap(), "memory")?;
+                MemoryCopy(dst_idx, self.context.memories.resolve(items.expect("source memory index")?, "memory")?)
+            } else {
+                MemoryCopy(0.into(), 0.into())
+            },
+            "memory.fill" => MemoryFill(self.opt_memory(items)?),
+            "table.init" => {
+                // table index is optional, so it is only there if followed by another index
+                let first = items.expect("element segment index")?;
+                if items.peek().map_or(false, is_index) {
+                    let table_idx = self.context.tables.resolve(first, "table")?;
+                    TableInit(self.context.elements.resolve(items.next().unwrap(), "element segment")?, table_idx)
+                } else {
+                    TableInit(self.context.elements.resolve(first, "element segment")?, 0.into())
+                }
+            }
+            "elem.drop" => ElemDrop(self.context.elements.resolve(items.expect("element segment index")?, "element segment")?),
+            "table.copy" => if items.peek().map_or(false, is_index) {
+                let dst_idx = self.context.tables.resolve(items.next().unwrap(), "table")?;
+                TableCopy(dst_idx, self.context.tables.resolve(items.expect("source table index")?, "table")?)
+            } else {
+                TableCopy(0.into(), 0.into())
+            },
+
+            "ref.null" => {
+                let sexpr = items.expect("reference type")?;
+                match sexpr.as_keyword() {
+                    Some("func") => RefNull(ElemType::FuncRef),
+                    Some("extern") => RefNull(ElemType::ExternRef),
+                    _ => return error(sexpr.pos(), format!("expected func or extern, got {}", describe(sexpr))),
+                }
+            }
+            "ref.is_null" => RefIsNull,
+            "ref.func" => RefFunc(self.context.functions.resolve(items.expect("function index")?, "function")?),
+            "table.get" => TableGet(self.opt_table(items)?),
+            "table.set" => TableSet(self.opt_table(items)?),
+            "table.size" => TableSize(self.opt_table(items)?),
+            "table.grow" => TableGrow(self.opt_table(items)?),
+            "table.fill" => TableFill(self.opt_table(items)?),
+
+            "i32.const" => Const(Val::I32(self.literal(items, "i32", parse_i32)?)),
+            "i64.const" => Const(Val::I64(self.literal(items, "i64", parse_i64)?)),
+            "f32.const" => Const(Val::F32(self.literal(items, "f32", parse_f32)?)),
+            "f64.const" => Const(Val::F64(self.literal(items, "f64", parse_f64)?)),
+            "v128.const" => Const(Val::V128(v128_literal(items)?)),
+
+            "i8x16.shuffle" => {
+                let mut lanes = [0u8; 16];
+                for lane_idx in lanes.iter_mut() {
+                    *lane_idx = lane(items.expect("lane index")?)?;
+                }
+                Shuffle(lanes)
+            }
+            "i8x16.extract_lane_s" => ExtractLane(ExtractLaneOp::I8x16ExtractLaneS, lane(items.expect("lane index")?)?),
+            "i8x16.extract_lane_u" => ExtractLane(ExtractLaneOp::I8x16ExtractLaneU, lane(items.expect("lane index")?)?),
+            "i8x16.replace_lane" => ReplaceLane(ReplaceLaneOp::I8x16ReplaceLane, lane(items.expect("lane index")?)?),
+            "i16x8.extract_lane_s" => ExtractLane(ExtractLaneOp::I16x8ExtractLaneS, lane(items.expect("lane index")?)?),
+            "i16x8.extract_lane_u" => ExtractLane(ExtractLaneOp::I16x8ExtractLaneU, lane(items.expect("lane index")?)?),
+            "i16x8.replace_lane" => ReplaceLane(ReplaceLaneOp::I16x8ReplaceLane, lane(items.expect("lane index")?)?),
+            "i32x4.extract_lane" => ExtractLane(ExtractLaneOp::I32x4ExtractLane, lane(items.expect("lane index")?)?),
+            "i32x4.replace_lane" => ReplaceLane(ReplaceLaneOp::I32x4ReplaceLane, lane(items.expect("lane index")?)?),
+            "i64x2.extract_lane" => ExtractLane(ExtractLaneOp::I64x2ExtractLane, lane(items.expect("lane index")?)?),
+            "i64x2.replace_lane" => ReplaceLane(ReplaceLaneOp::I64x2ReplaceLane, lane(items.expect("lane index")?)?),
+            "f32x4.extract_lane" => ExtractLane(ExtractLaneOp::F32x4ExtractLane, lane(items.expect("lane index")?)?),
+            "f32x4.replace_lane" => ReplaceLane(ReplaceLaneOp::F32x4ReplaceLane, lane(items.expect("lane index")?)?),
+            "f64x2.extract_lane" => ExtractLane(ExtractLaneOp::F64x2ExtractLane, lane(items.expect("lane index")?)?),
+            "f64x2.replace_lane" => ReplaceLane(ReplaceLaneOp::F64x2ReplaceLane, lane(items.expect("lane index")?)?),
+
+            "memory.atomic.notify" => AtomicNotify(self.opt_memory(items)?, memarg(items, 2)?),
+            "atomic.fence" => AtomicFence,
+
+            keyword => if let Some(op) = load_op(keyword) {
+                Load(op, self.opt_memory(items)?, memarg(items, op.natural_alignment())?)
+            } else if let Some(op) = store_op(keyword) {
+                Store(op, self.opt_memory(items)?, memarg(items, op.natural_alignment())?)
+            } else if let Some(op) = load_lane_op(keyword) {
+                let (memory_idx, memarg, lane) = self.memarg_and_lane(items, op.natural_alignment())?;
+                LoadLane(op, memory_idx, memarg, lane)
+            } else if let Some(op) = store_lane_op(keyword) {
+                let (memory_idx, memarg, lane) = self.memarg_and_lane(items, op.natural_alignment())?;
+                StoreLane(op, memory_idx, memarg, lane)
+            } else if let Some(op) = atomic_load_op(keyword) {
+                AtomicLoad(op, self.opt_memory(items)?, memarg(items, op.natural_alignment())?)
+            } else if let Some(op) = atomic_store_op(keyword) {
+                AtomicStore(op, self.opt_memory(items)?, memarg(items, op.natural_alignment())?)
+            } else if let Some(op) = atomic_rmw_op(keyword) {
+                AtomicRmw(op, self.opt_memory(items)?, memarg(items, op.natural_alignment())?)
+            } else if let Some(op) = atomic_cmpxchg_op(keyword) {
+                AtomicCmpxchg(op, self.opt_memory(items)?, memarg(items, op.natural_alignment())?)
+            } else if let Some(op) = atomic_wait_op(keyword) {
+                AtomicWait(op, self.opt_memory(items)?, memarg(items, op.natural_alignment())?)
+            } else if let Some(op) = numeric_op(keyword) {
+                Numeric(op)
+            } else {
+                return error(pos, format!("unknown instruction '{}'", keyword));
+            }
+        })
+    }
+
+    /// table index immediate that defaults to table 0 if omitted
+    fn opt_table(&self, items: &mut Items) -> io::Result<Idx<hl::Table>> {
+        if items.peek().map_or(false, is_index) {
+            self.context.tables.resolve(items.next().unwrap(), "table")
+        } else {
+            Ok(0.into())
+        }
+    }
+
+    /// memory index immediate (multi-memory proposal) that defaults to memory 0 if omitted
+    fn opt_memory(&self, items: &mut Items) -> io::Result<Idx<hl::Memory>> {
+        if items.peek().map_or(false, is_index) {
+            self.context.memories.resolve(items.next().unwrap(), "memory")
+        } else {
+            Ok(0.into())
+        }
+    }
+
+    /// immediates of v128.loadN_lane/v128.storeN_lane: optional memory index, memarg, and lane index.
+    /// A single index (without a second one after the memarg) is the lane, not the memory.
+    fn memarg_and_lane(&self, items: &mut Items, natural_alignment: u32) -> io::Result<(Idx<hl::Memory>, Memarg, u8)> {
+        let first = if items.peek().map_or(false, is_index) { items.next() } else { None };
+        let memarg = memarg(items, natural_alignment)?;
+        match first {
+            Some(memory) if items.peek().map_or(false, is_index) => {
+                let memory_idx = self.context.memories.resolve(memory, "memory")?;
+                Ok((memory_idx, memarg, lane(items.next().unwrap())?))
+            }
+            Some(lane_idx) => Ok((0.into(), memarg, lane(lane_idx)?)),
+            None => Ok((0.into(), memarg, lane(items.expect("lane index")?)?)),
+        }
+    }
+
+    fn label(&self, sexpr: &Sexpr) -> io::Result<Idx<Label>> {
+        match *sexpr {
+            Sexpr::Atom(Token::Id(ref id), pos) => {
+                match self.labels.iter().rev().position(|label| label.as_ref() == Some(id)) {
+                    Some(depth) => Ok(depth.into()),
+                    None => error(pos, format!("unknown label ${}", id)),
+                }
+            }
+            _ => IndexSpace::default().resolve(sexpr, "label"),
+        }
+    }
+
+    fn literal<T>(&self, items: &mut Items, type_: &str, parse: fn(&str) -> Option<T>) -> io::Result<T> {
+        let sexpr = items.expect(&format!("{} literal", type_))?;
+        match sexpr.as_keyword().and_then(parse) {
+            Some(value) => Ok(value),
+            None => error(sexpr.pos(), format!("invalid {} literal {}", type_, describe(sexpr))),
+        }
+    }
+}
+
+/// lane index immediate of SIMD instructions, checked against the lane count during validation
+fn lane(sexpr: &Sexpr) -> io::Result<u8> {
+    match sexpr.as_keyword().and_then(parse_u8) {
+        Some(lane) => Ok(lane),
+        None => error(sexpr.pos(), format!("invalid lane index {}", describe(sexpr))),
+    }
+}
+
+/// shape (e.g., i32x4) followed by one literal per lane
+fn v128_literal(items: &mut Items) -> io::Result<u128> {
+    let shape = items.expect("v128 shape")?;
+    let (lane_count, parse_lane): (u32, fn(&str) -> Option<u64>) = match shape.as_keyword() {
+        Some("i8x16") => (16, |literal| parse_i8(literal).map(|lane| lane as u8 as u64)),
+        Some("i16x8") => (8, |literal| parse_i16(literal).map(|lane| lane as u16 as u64)),
+        Some("i32x4") => (4, |literal| parse_i32(literal).map(|lane| lane as u32 as u64)),
+        Some("i64x2") => (2, |literal| parse_i64(literal).map(|lane| lane as u64)),
+        Some("f32x4") => (4, |literal| parse_f32(literal).map(|lane| lane.to_bits() as u64)),
+        Some("f64x2") => (2, |literal| parse_f64(literal).map(|lane| lane.to_bits())),
+        _ => return error(shape.pos(), format!("expected v128 shape (e.g., i32x4), got {}", describe(shape))),
+    };
+    let lane_bits = 128 / lane_count;
+    let mut value = 0u128;
+    for i in 0..lane_count {
+        let sexpr = items.expect("v128 lane literal")?;
+        match sexpr.as_keyword().and_then(parse_lane) {
+            Some(lane) => value |= (lane as u128) << (i * lane_bits),
+            None => return error(sexpr.pos(), format!("invalid v128 lane literal {}", describe(sexpr))),
+        }
+    }
+    Ok(value)
+}
+
+/// offset=<u64>? align=<power of two>?, alignment is stored as its log2 (as in the binary format)
+fn memarg(items: &mut Items, natural_alignment: u32) -> io::Result<Memarg> {
+    let mut memarg = Memarg { alignment: natural_alignment, offset: 0 };
+    while let Some(sexpr) = items.peek() {
+        let pos = sexpr.pos();
+        match sexpr.as_keyword() {
+            Some(keyword) if keyword.starts_with("offset=") => {
+                memarg.offset = match parse_u64(&keyword["offset=".len()..]) {
+                    Some(offset) => offset,
+                    None => return error(pos, format!("invalid memory offset '{}'", keyword)),
+                };
+            }
+            Some(keyword) if keyword.starts_with("align=") => {
+                memarg.alignment = match parse_u32(&keyword["align=".len()..]) {
+                    Some(align) if align.is_power_of_two() => align.trailing_zeros(),
+                    _ => return error(pos, format!("invalid alignment '{}'", keyword)),
+                };
+            }
+            _ => break,
+        }
+        items.next();
+    }
+    Ok(memarg)
+}
+
+fn load_op(keyword: &str) -> Option<hl::LoadOp> {
+    Some(match keyword {
+        "i32.load" => I32Load,
+        "i64.load" => I64Load,
+        "f32.load" => F32Load,
+        "f64.load" => F64Load,
+        "i32.load8_s" => I32Load8S,
+        "i32.load8_u" => I32Load8U,
+        "i32.load16_s" => I32Load16S,
+        "i32.load16_u" => I32Load16U,
+        "i64.load8_s" => I64Load8S,
+        "i64.load8_u" => I64Load8U,
+        "i64.load16_s" => I64Load16S,
+        "i64.load16_u" => I64Load16U,
+        "i64.load32_s" => I64Load32S,
+        "i64.load32_u" => I64Load32U,
+        "v128.load" => V128Load,
+        "v128.load8x8_s" => V128Load8x8S,
+        "v128.load8x8_u" => V128Load8x8U,
+        "v128.load16x4_s" => V128Load16x4S,
+        "v128.load16x4_u" => V128Load16x4U,
+        "v128.load32x2_s" => V128Load32x2S,
+        "v128.load32x2_u" => V128Load32x2U,
+        "v128.load8_splat" => V128Load8Splat,
+        "v128.load16_splat" => V128Load16Splat,
+        "v128.load32_splat" => V128Load32Splat,
+        "v128.load64_splat" => V128Load64Splat,
+        "v128.load32_zero" => V128Load32Zero,
+        "v128.load64_zero" => V128Load64Zero,
+        _ => return None,
+    })
+}
+
+fn load_lane_op(keyword: &str) -> Option<LoadLaneOp> {
+    Some(match keyword {
+        "v128.load8_lane" => LoadLaneOp::V128Load8Lane,
+        "v128.load16_lane" => LoadLaneOp::V128Load16Lane,
+        "v128.load32_lane" => LoadLaneOp::V128Load32Lane,
+        "v128.load64_lane" => LoadLaneOp::V128Load64Lane,
+        _ => return None,
+    })
+}
+
+fn store_lane_op(keyword: &str) -> Option<StoreLaneOp> {
+    Some(match keyword {
+        "v128.store8_lane" => StoreLaneOp::V128Store8Lane,
+        "v128.store16_lane" => StoreLaneOp::V128Store16Lane,
+        "v128.store32_lane" => StoreLaneOp::V128Store32Lane,
+        "v128.store64_lane" => StoreLaneOp::V128Store64Lane,
+        _ => return None,
+    })
+}
+
+fn store_op(keyword: &str) -> Option<hl::StoreOp> {
+    Some(match keyword {
+        "i32.store" => I32Store,
+        "i64.store" => I64Store,
+        "f32.store" => F32Store,
+        "f64.store" => F64Store,
+        "i32.store8" => I32Store8,
+        "i32.store16" => I32Store16,
+        "i64.store8" => I64Store8,
+        "i64.store16" => I64Store16,
+        "i64.store32" => I64Store32,
+        "v128.store" => V128Store,
+        _ => return None,
+    })
+}
+
+fn atomic_load_op(keyword: &str) -> Option<AtomicLoadOp> {
+    Some(match keyword {
+        "i32.atomic.load" => AtomicLoadOp::I32AtomicLoad,
+        "i64.atomic.load" => AtomicLoadOp::I64AtomicLoad,
+        "i32.atomic.load8_u" => AtomicLoadOp::I32AtomicLoad8U,
+        "i32.atomic.load16_u" => AtomicLoadOp::I32AtomicLoad16U,
+        "i64.atomic.load8_u" => AtomicLoadOp::I64AtomicLoad8U,
+        "i64.atomic.load16_u" => AtomicLoadOp::I64AtomicLoad16U,
+        "i64.atomic.load32_u" => AtomicLoadOp::I64AtomicLoad32U,
+        _ => return None,
+    })
+}
+
+fn atomic_store_op(keyword: &str) -> Option<AtomicStoreOp> {
+    Some(match keyword {
+        "i32.atomic.store" => AtomicStoreOp::I32AtomicStore,
+        "i64.atomic.store" => AtomicStoreOp::I64AtomicStore,
+        "i32.atomic.store8" => AtomicStoreOp::I32AtomicStore8,
+        "i32.atomic.store16" => AtomicStoreOp::I32AtomicStore16,
+        "i64.atomic.store8" => AtomicStoreOp::I64AtomicStore8,
+        "i64.atomic.store16" => AtomicStoreOp::I64AtomicStore16,
+        "i64.atomic.store32" => AtomicStoreOp::I64AtomicStore32,
+        _ => return None,
+    })
+}
+
+fn atomic_rmw_op(keyword: &str) -> Option<AtomicRmwOp> {
+    Some(match keyword {
+        "i32.atomic.rmw.add" => AtomicRmwOp::I32AtomicRmwAdd,
+        "i64.atomic.rmw.add" => AtomicRmwOp::I64AtomicRmwAdd,
+        "i32.atomic.rmw8.add_u" => AtomicRmwOp::I32AtomicRmw8AddU,
+        "i32.atomic.rmw16.add_u" => AtomicRmwOp::I32AtomicRmw16AddU,
+        "i64.atomic.rmw8.add_u" => AtomicRmwOp::I64AtomicRmw8AddU,
+        "i64.atomic.rmw16.add_u" => AtomicRmwOp::I64AtomicRmw16AddU,
+        "i64.atomic.rmw32.add_u" => AtomicRmwOp::I64AtomicRmw32AddU,
+        "i32.atomic.rmw.sub" => AtomicRmwOp::I32AtomicRmwSub,
+        "i64.atomic.rmw.sub" => AtomicRmwOp::I64AtomicRmwSub,
+        "i32.atomic.rmw8.sub_u" => AtomicRmwOp::I32AtomicRmw8SubU,
+        "i32.atomic.rmw16.sub_u" => AtomicRmwOp::I32AtomicRmw16SubU,
+        "i64.atomic.rmw8.sub_u" => AtomicRmwOp::I64AtomicRmw8SubU,
+        "i64.atomic.rmw16.sub_u" => AtomicRmwOp::I64AtomicRmw16SubU,
+        "i64.atomic.rmw32.sub_u" => AtomicRmwOp::I64AtomicRmw32SubU,
+        "i32.atomic.rmw.and" => AtomicRmwOp::I32AtomicRmwAnd,
+        "i64.atomic.rmw.and" => AtomicRmwOp::I64AtomicRmwAnd,
+        "i32.atomic.rmw8.and_u" => AtomicRmwOp::I32AtomicRmw8AndU,
+        "i32.atomic.rmw16.and_u" => AtomicRmwOp::I32AtomicRmw16AndU,
+        "i64.atomic.rmw8.and_u" => AtomicRmwOp::I64AtomicRmw8AndU,
+        "i64.atomic.rmw16.and_u" => AtomicRmwOp::I64AtomicRmw16AndU,
+        "i64.atomic.rmw32.and_u" => AtomicRmwOp::I64AtomicRmw32AndU,
+        "i32.atomic.rmw.or" => AtomicRmwOp::I32AtomicRmwOr,
+        "i64.atomic.rmw.or" => AtomicRmwOp::I64AtomicRmwOr,
+        "i32.atomic.rmw8.or_u" => AtomicRmwOp::I32AtomicRmw8OrU,
+        "i32.atomic.rmw16.or_u" => AtomicRmwOp::I32AtomicRmw16OrU,
+        "i64.atomic.rmw8.or_u" => AtomicRmwOp::I64AtomicRmw8OrU,
+        "i64.atomic.rmw16.or_u" => AtomicRmwOp::I64AtomicRmw16OrU,
+        "i64.atomic.rmw32.or_u" => AtomicRmwOp::I64AtomicRmw32OrU,
+        "i32.atomic.rmw.xor" => AtomicRmwOp::I32AtomicRmwXor,
+        "i64.atomic.rmw.xor" => AtomicRmwOp::I64AtomicRmwXor,
+        "i32.atomic.rmw8.xor_u" => AtomicRmwOp::I32AtomicRmw8XorU,
+        "i32.atomic.rmw16.xor_u" => AtomicRmwOp::I32AtomicRmw16XorU,
+        "i64.atomic.rmw8.xor_u" => AtomicRmwOp::I64AtomicRmw8XorU,
+        "i64.atomic.rmw16.xor_u" => AtomicRmwOp::I64AtomicRmw16XorU,
+        "i64.atomic.rmw32.xor_u" => AtomicRmwOp::I64AtomicRmw32XorU,
+        "i32.atomic.rmw.xchg" => AtomicRmwOp::I32AtomicRmwXchg,
+        "i64.atomic.rmw.xchg" => AtomicRmwOp::I64AtomicRmwXchg,
+        "i32.atomic.rmw8.xchg_u" => AtomicRmwOp::I32AtomicRmw8XchgU,
+        "i32.atomic.rmw16.xchg_u" => AtomicRmwOp::I32AtomicRmw16XchgU,
+        "i64.atomic.rmw8.xchg_u" => AtomicRmwOp::I64AtomicRmw8XchgU,
+        "i64.atomic.rmw16.xchg_u" => AtomicRmwOp::I64AtomicRmw16XchgU,
+        "i64.atomic.rmw32.xchg_u" => AtomicRmwOp::I64AtomicRmw32XchgU,
+        _ => return None,
+    })
+}
+
+fn atomic_cmpxchg_op(keyword: &str) -> Option<AtomicCmpxchgOp> {
+    Some(match keyword {
+        "i32.atomic.rmw.cmpxchg" => AtomicCmpxchgOp::I32AtomicRmwCmpxchg,
+        "i64.atomic.rmw.cmpxchg" => AtomicCmpxchgOp::I64AtomicRmwCmpxchg,
+        "i32.atomic.rmw8.cmpxchg_u" => AtomicCmpxchgOp::I32AtomicRmw8CmpxchgU,
+        "i32.atomic.rmw16.cmpxchg_u" => AtomicCmpxchgOp::I32AtomicRmw16CmpxchgU,
+        "i64.atomic.rmw8.cmpxchg_u" => AtomicCmpxchgOp::I64AtomicRmw8CmpxchgU,
+        "i64.atomic.rmw16.cmpxchg_u" => AtomicCmpxchgOp::I64AtomicRmw16CmpxchgU,
+        "i64.atomic.rmw32.cmpxchg_u" => AtomicCmpxchgOp::I64AtomicRmw32CmpxchgU,
+        _ => return None,
+    })
+}
+
+fn atomic_wait_op(keyword: &str) -> Option<AtomicWaitOp> {
+    Some(match keyword {
+        "memory.atomic.wait32" => AtomicWaitOp::MemoryAtomicWait32,
+        "memory.atomic.wait64" => AtomicWaitOp::MemoryAtomicWait64,
+        _ => return None,
+    })
+}
+
+/// accepts both the old (e.g., i32.wrap/i64) and current (e.g., i32.wrap_i64) names of conversions
+fn numeric_op(keyword: &str) -> Option<NumericOp> {
+    use ast::highlevel::NumericOp::*;
+    Some(match keyword {
+        "i32.eqz" => I32Eqz,
+        "i64.eqz" => I64Eqz,
+        "i32.clz" => I32Clz,
+        "i32.ctz" => I32Ctz,
+        "i32.popcnt" => I32Popcnt,
+        "i64.clz" => I64Clz,
+        "i64.ctz" => I64Ctz,
+        "i64.popcnt" => I64Popcnt,
+        "f32.abs" => F32Abs,
+        "f32.neg" => F32Neg,
+        "f32.ceil" => F32Ceil,
+        "f32.floor" => F32Floor,
+        "f32.trunc" => F32Trunc,
+        "f32.nearest" => F32Nearest,
+        "f32.sqrt" => F32Sqrt,
+        "f64.abs" => F64Abs,
+        "f64.neg" => F64Neg,
+        "f64.ceil" => F64Ceil,
+        "f64.floor" => F64Floor,
+        "f64.trunc" => F64Trunc,
+        "f64.nearest" => F64Nearest,
+        "f64.sqrt" => F64Sqrt,
+
+        "i32.wrap/i64" | "i32.wrap_i64" => I32WrapI64,
+        "i32.trunc_s/f32" | "i32.trunc_f32_s" => I32TruncSF32,
+        "i32.trunc_u/f32" | "i32.trunc_f32_u" => I32TruncUF32,
+        "i32.trunc_s/f64" | "i32.trunc_f64_s" => I32TruncSF64,
+        "i32.trunc_u/f64" | "i32.trunc_f64_u" => I32TruncUF64,
+        "i64.extend_s/i32" | "i64.extend_i32_s" => I64ExtendSI32,
+        "i64.extend_u/i32" | "i64.extend_i32_u" => I64ExtendUI32,
+        "i64.trunc_s/f32" | "i64.trunc_f32_s" => I64TruncSF32,
+        "i64.trunc_u/f32" | "i64.trunc_f32_u" => I64TruncUF32,
+        "i64.trunc_s/f64" | "i64.trunc_f64_s" => I64TruncSF64,
+        "i64.trunc_u/f64" | "i64.trunc_f64_u" => I64TruncUF64,
+        "f32.convert_s/i32" | "f32.convert_i32_s" => F32ConvertSI32,
+        "f32.convert_u/i32" | "f32.convert_i32_u" => F32ConvertUI32,
+        "f32.convert_s/i64" | "f32.convert_i64_s" => F32ConvertSI64,
+        "f32.convert_u/i64" | "f32.convert_i64_u" => F32ConvertUI64,
+        "f32.demote/f64" | "f32.demote_f64" => F32DemoteF64,
+        "f64.convert_s/i32" | "f64.convert_i32_s" => F64ConvertSI32,
+        "f64.convert_u/i32" | "f64.convert_i32_u" => F64ConvertUI32,
+        "f64.convert_s/i64" | "f64.convert_i64_s" => F64ConvertSI64,
+        "f64.convert_u/i64" | "f64.convert_i64_u" => F64ConvertUI64,
+        "f64.promote/f32" | "f64.promote_f32" => F64PromoteF32,
+        "i32.reinterpret/f32" | "i32.reinterpret_f32" => I32ReinterpretF32,
+        "i64.reinterpret/f64" | "i64.reinterpret_f64" => I64ReinterpretF64,
+        "f32.reinterpret/i32" | "f32.reinterpret_i32" => F32ReinterpretI32,
+        "f64.reinterpret/i64" | "f64.reinterpret_i64" => F64ReinterpretI64,
+        "i32.extend8_s" => I32Extend8S,
+        "i32.extend16_s" => I32Extend16S,
+        "i64.extend8_s" => I64Extend8S,
+        "i64.extend16_s" => I64Extend16S,
+        "i64.extend32_s" => I64Extend32S,
+        "i32.trunc_s:sat/f32" | "i32.trunc_sat_f32_s" => I32TruncSatSF32,
+        "i32.trunc_u:sat/f32" | "i32.trunc_sat_f32_u" => I32TruncSatUF32,
+        "i32.trunc_s:sat/f64" | "i32.trunc_sat_f64_s" => I32TruncSatSF64,
+        "i32.trunc_u:sat/f64" | "i32.trunc_sat_f64_u" => I32TruncSatUF64,
+        "i64.trunc_s:sat/f32" | "i64.trunc_sat_f32_s" => I64TruncSatSF32,
+        "i64.trunc_u:sat/f32" | "i64.trunc_sat_f32_u" => I64TruncSatUF32,
+        "i64.trunc_s:sat/f64" | "i64.trunc_sat_f64_s" => I64TruncSatSF64,
+        "i64.trunc_u:sat/f64" | "i64.trunc_sat_f64_u" => I64TruncSatUF64,
+
+        "i32.eq" => I32Eq,
+        "i32.ne" => I32Ne,
+        "i32.lt_s" => I32LtS,
+        "i32.lt_u" => I32LtU,
+        "i32.gt_s" => I32GtS,
+        "i32.gt_u" => I32GtU,
+        "i32.le_s" => I32LeS,
+        "i32.le_u" => I32LeU,
+        "i32.ge_s" => I32GeS,
+        "i32.ge_u" => I32GeU,
+        "i64.eq" => I64Eq,
+        "i64.ne" => I64Ne,
+        "i64.lt_s" => I64LtS,
+        "i64.lt_u" => I64LtU,
+        "i64.gt_s" => I64GtS,
+        "i64.gt_u" => I64GtU,
+        "i64.le_s" => I64LeS,
+        "i64.le_u" => I64LeU,
+        "i64.ge_s" => I64GeS,
+        "i64.ge_u" => I64GeU,
+        "f32.eq" => F32Eq,
+        "f32.ne" => F32Ne,
+        "f32.lt" => F32Lt,
+        "f32.gt" => F32Gt,
+        "f32.le" => F32Le,
+        "f32.ge" => F32Ge,
+        "f64.eq" => F64Eq,
+        "f64.ne" => F64Ne,
+        "f64.lt" => F64Lt,
+        "f64.gt" => F64Gt,
+        "f64.le" => F64Le,
+        "f64.ge" => F64Ge,
+
+        "i32.add" => I32Add,
+        "i32.sub" => I32Sub,
+        "i32.mul" => I32Mul,
+        "i32.div_s" => I32DivS,
+        "i32.div_u" => I32DivU,
+        "i32.rem_s" => I32RemS,
+        "i32.rem_u" => I32RemU,
+        "i32.and" => I32And,
+        "i32.or" => I32Or,
+        "i32.xor" => I32Xor,
+        "i32.shl" => I32Shl,
+        "i32.shr_s" => I32ShrS,
+        "i32.shr_u" => I32ShrU,
+        "i32.rotl" => I32Rotl,
+        "i32.rotr" => I32Rotr,
+        "i64.add" => I64Add,
+        "i64.sub" => I64Sub,
+        "i64.mul" => I64Mul,
+        "i64.div_s" => I64DivS,
+        "i64.div_u" => I64DivU,
+        "i64.rem_s" => I64RemS,
+        "i64.rem_u" => I64RemU,
+        "i64.and" => I64And,
+        "i64.or" => I64Or,
+        "i64.xor" => I64Xor,
+        "i64.shl" => I64Shl,
+        "i64.shr_s" => I64ShrS,
+        "i64.shr_u" => I64ShrU,
+        "i64.rotl" => I64Rotl,
+        "i64.rotr" => I64Rotr,
+        "f32.add" => F32Add,
+        "f32.sub" => F32Sub,
+        "f32.mul" => F32Mul,
+        "f32.div" => F32Div,
+        "f32.min" => F32Min,
+        "f32.max" => F32Max,
+        "f32.copysign" => F32Copysign,
+        "f64.add" => F64Add,
+        "f64.sub" => F64Sub,
+        "f64.mul" => F64Mul,
+        "f64.div" => F64Div,
+        "f64.min" => F64Min,
+        "f64.max" => F64Max,
+        "f64.copysign" => F64Copysign,
+
+        "i8x16.swizzle" => I8x16Swizzle,
+        "i8x16.splat" => I8x16Splat,
+        "i16x8.splat" => I16x8Splat,
+        "i32x4.splat" => I32x4Splat,
+        "i64x2.splat" => I64x2Splat,
+        "f32x4.splat" => F32x4Splat,
+        "f64x2.splat" => F64x2Splat,
+        "i8x16.eq" => I8x16Eq,
+        "i8x16.ne" => I8x16Ne,
+        "i8x16.lt_s" => I8x16LtS,
+        "i8x16.lt_u" => I8x16LtU,
+        "i8x16.gt_s" => I8x16GtS,
+        "i8x16.gt_u" => I8x16GtU,
+        "i8x16.le_s" => I8x16LeS,
+        "i8x16.le_u" => I8x16LeU,
+        "i8x16.ge_s" => I8x16GeS,
+        "i8x16.ge_u" => I8x16GeU,
+        "i16x8.eq" => I16x8Eq,
+        "i16x8.ne" => I16x8Ne,
+        "i16x8.lt_s" => I16x8LtS,
+        "i16x8.lt_u" => I16x8LtU,
+        "i16x8.gt_s" => I16x8GtS,
+        "i16x8.gt_u" => I16x8GtU,
+        "i16x8.le_s" => I16x8LeS,
+        "i16x8.le_u" => I16x8LeU,
+        "i16x8.ge_s" => I16x8GeS,
+        "i16x8.ge_u" => I16x8GeU,
+        "i32x4.eq" => I32x4Eq,
+        "i32x4.ne" => I32x4Ne,
+        "i32x4.lt_s" => I32x4LtS,
+        "i32x4.lt_u" => I32x4LtU,
+        "i32x4.gt_s" => I32x4GtS,
+        "i32x4.gt_u" => I32x4GtU,
+        "i32x4.le_s" => I32x4LeS,
+        "i32x4.le_u" => I32x4LeU,
+        "i32x4.ge_s" => I32x4GeS,
+        "i32x4.ge_u" => I32x4GeU,
+        "f32x4.eq" => F32x4Eq,
+        "f32x4.ne" => F32x4Ne,
+        "f32x4.lt" => F32x4Lt,
+        "f32x4.gt" => F32x4Gt,
+        "f32x4.le" => F32x4Le,
+        "f32x4.ge" => F32x4Ge,
+        "f64x2.eq" => F64x2Eq,
+        "f64x2.ne" => F64x2Ne,
+        "f64x2.lt" => F64x2Lt,
+        "f64x2.gt" => F64x2Gt,
+        "f64x2.le" => F64x2Le,
+        "f64x2.ge" => F64x2Ge,
+        "v128.not" => V128Not,
+        "v128.and" => V128And,
+        "v128.andnot" => V128Andnot,
+        "v128.or" => V128Or,
+        "v128.xor" => V128Xor,
+        "v128.bitselect" => V128Bitselect,
+        "v128.any_true" => V128AnyTrue,
+        "f32x4.demote_f64x2_zero" => F32x4DemoteF64x2Zero,
+        "f64x2.promote_low_f32x4" => F64x2PromoteLowF32x4,
+        "i8x16.abs" => I8x16Abs,
+        "i8x16.neg" => I8x16Neg,
+        "i8x16.popcnt" => I8x16Popcnt,
+        "i8x16.all_true" => I8x16AllTrue,
+        "i8x16.bitmask" => I8x16Bitmask,
+        "i8x16.narrow_i16x8_s" => I8x16NarrowI16x8S,
+        "i8x16.narrow_i16x8_u" => I8x16NarrowI16x8U,
+        "f32x4.ceil" => F32x4Ceil,
+        "f32x4.floor" => F32x4Floor,
+        "f32x4.trunc" => F32x4Trunc,
+        "f32x4.nearest" => F32x4Nearest,
+        "i8x16.shl" => I8x16Shl,
+        "i8x16.shr_s" => I8x16ShrS,
+        "i8x16.shr_u" => I8x16ShrU,
+        "i8x16.add" => I8x16Add,
+        "i8x16.add_sat_s" => I8x16AddSatS,
+        "i8x16.add_sat_u" => I8x16AddSatU,
+        "i8x16.sub" => I8x16Sub,
+        "i8x16.sub_sat_s" => I8x16SubSatS,
+        "i8x16.sub_sat_u" => I8x16SubSatU,
+        "f64x2.ceil" => F64x2Ceil,
+        "f64x2.floor" => F64x2Floor,
+        "i8x16.min_s" => I8x16MinS,
+        "i8x16.min_u" => I8x16MinU,
+        "i8x16.max_s" => I8x16MaxS,
+        "i8x16.max_u" => I8x16MaxU,
+        "f64x2.trunc" => F64x2Trunc,
+        "i8x16.avgr_u" => I8x16AvgrU,
+        "i16x8.extadd_pairwise_i8x16_s" => I16x8ExtaddPairwiseI8x16S,
+        "i16x8.extadd_pairwise_i8x16_u" => I16x8ExtaddPairwiseI8x16U,
+        "i32x4.extadd_pairwise_i16x8_s" => I32x4ExtaddPairwiseI16x8S,
+        "i32x4.extadd_pairwise_i16x8_u" => I32x4ExtaddPairwiseI16x8U,
+        "i16x8.abs" => I16x8Abs,
+        "i16x8.neg" => I16x8Neg,
+        "i16x8.q15mulr_sat_s" => I16x8Q15mulrSatS,
+        "i16x8.all_true" => I16x8AllTrue,
+        "i16x8.bitmask" => I16x8Bitmask,
+        "i16x8.narrow_i32x4_s" => I16x8NarrowI32x4S,
+        "i16x8.narrow_i32x4_u" => I16x8NarrowI32x4U,
+        "i16x8.extend_low_i8x16_s" => I16x8ExtendLowI8x16S,
+        "i16x8.extend_high_i8x16_s" => I16x8ExtendHighI8x16S,
+        "i16x8.extend_low_i8x16_u" => I16x8ExtendLowI8x16U,
+        "i16x8.extend_high_i8x16_u" => I16x8ExtendHighI8x16U,
+        "i16x8.shl" => I16x8Shl,
+        "i16x8.shr_s" => I16x8ShrS,
+        "i16x8.shr_u" => I16x8ShrU,
+        "i16x8.add" => I16x8Add,
+        "i16x8.add_sat_s" => I16x8AddSatS,
+        "i16x8.add_sat_u" => I16x8AddSatU,
+        "i16x8.sub" => I16x8Sub,
+        "i16x8.sub_sat_s" => I16x8SubSatS,
+        "i16x8.sub_sat_u" => I16x8SubSatU,
+        "f64x2.nearest" => F64x2Nearest,
+        "i16x8.mul" => I16x8Mul,
+        "i16x8.min_s" => I16x8MinS,
+        "i16x8.min_u" => I16x8MinU,
+        "i16x8.max_s" => I16x8MaxS,
+        "i16x8.max_u" => I16x8MaxU,
+        "i16x8.avgr_u" => I16x8AvgrU,
+        "i16x8.extmul_low_i8x16_s" => I16x8ExtmulLowI8x16S,
+        "i16x8.extmul_high_i8x16_s" => I16x8ExtmulHighI8x16S,
+        "i16x8.extmul_low_i8x16_u" => I16x8ExtmulLowI8x16U,
+        "i16x8.extmul_high_i8x16_u" => I16x8ExtmulHighI8x16U,
+        "i32x4.abs" => I32x4Abs,
+        "i32x4.neg" => I32x4Neg,
+        "i32x4.all_true" => I32x4AllTrue,
+        "i32x4.bitmask" => I32x4Bitmask,
+        "i32x4.extend_low_i16x8_s" => I32x4ExtendLowI16x8S,
+        "i32x4.extend_high_i16x8_s" => I32x4ExtendHighI16x8S,
+        "i32x4.extend_low_i16x8_u" => I32x4ExtendLowI16x8U,
+        "i32x4.extend_high_i16x8_u" => I32x4ExtendHighI16x8U,
+        "i32x4.shl" => I32x4Shl,
+        "i32x4.shr_s" => I32x4ShrS,
+        "i32x4.shr_u" => I32x4ShrU,
+        "i32x4.add" => I32x4Add,
+        "i32x4.sub" => I32x4Sub,
+        "i32x4.mul" => I32x4Mul,
+        "i32x4.min_s" => I32x4MinS,
+        "i32x4.min_u" => I32x4MinU,
+        "i32x4.max_s" => I32x4MaxS,
+        "i32x4.max_u" => I32x4MaxU,
+        "i32x4.dot_i16x8_s" => I32x4DotI16x8S,
+        "i32x4.extmul_low_i16x8_s" => I32x4ExtmulLowI16x8S,
+        "i32x4.extmul_high_i16x8_s" => I32x4ExtmulHighI16x8S,
+        "i32x4.extmul_low_i16x8_u" => I32x4ExtmulLowI16x8U,
+        "i32x4.extmul_high_i16x8_u" => I32x4ExtmulHighI16x8U,
+        "i64x2.abs" => I64x2Abs,
+        "i64x2.neg" => I64x2Neg,
+        "i64x2.all_true" => I64x2AllTrue,
+        "i64x2.bitmask" => I64x2Bitmask,
+        "i64x2.extend_low_i32x4_s" => I64x2ExtendLowI32x4S,
+        "i64x2.extend_high_i32x4_s" => I64x2ExtendHighI32x4S,
+        "i64x2.extend_low_i32x4_u" => I64x2ExtendLowI32x4U,
+        "i64x2.extend_high_i32x4_u" => I64x2ExtendHighI32x4U,
+        "i64x2.shl" => I64x2Shl,
+        "i64x2.shr_s" => I64x2ShrS,
+        "i64x2.shr_u" => I64x2ShrU,
+        "i64x2.add" => I64x2Add,
+        "i64x2.sub" => I64x2Sub,
+        "i64x2.mul" => I64x2Mul,
+        "i64x2.eq" => I64x2Eq,
+        "i64x2.ne" => I64x2Ne,
+        "i64x2.lt_s" => I64x2LtS,
+        "i64x2.gt_s" => I64x2GtS,
+        "i64x2.le_s" => I64x2LeS,
+        "i64x2.ge_s" => I64x2GeS,
+        "i64x2.extmul_low_i32x4_s" => I64x2ExtmulLowI32x4S,
+        "i64x2.extmul_high_i32x4_s" => I64x2ExtmulHighI32x4S,
+        "i64x2.extmul_low_i32x4_u" => I64x2ExtmulLowI32x4U,
+        "i64x2.extmul_high_i32x4_u" => I64x2ExtmulHighI32x4U,
+        "f32x4.abs" => F32x4Abs,
+        "f32x4.neg" => F32x4Neg,
+        "f32x4.sqrt" => F32x4Sqrt,
+        "f32x4.add" => F32x4Add,
+        "f32x4.sub" => F32x4Sub,
+        "f32x4.mul" => F32x4Mul,
+        "f32x4.div" => F32x4Div,
+        "f32x4.min" => F32x4Min,
+        "f32x4.max" => F32x4Max,
+        "f32x4.pmin" => F32x4Pmin,
+        "f32x4.pmax" => F32x4Pmax,
+        "f64x2.abs" => F64x2Abs,
+        "f64x2.neg" => F64x2Neg,
+        "f64x2.sqrt" => F64x2Sqrt,
+        "f64x2.add" => F64x2Add,
+        "f64x2.sub" => F64x2Sub,
+        "f64x2.mul" => F64x2Mul,
+        "f64x2.div" => F64x2Div,
+        "f64x2.min" => F64x2Min,
+        "f64x2.max" => F64x2Max,
+        "f64x2.pmin" => F64x2Pmin,
+        "f64x2.pmax" => F64x2Pmax,
+        "i32x4.trunc_sat_f32x4_s" => I32x4TruncSatF32x4S,
+        "i32x4.trunc_sat_f32x4_u" => I32x4TruncSatF32x4U,
+        "f32x4.convert_i32x4_s" => F32x4ConvertI32x4S,
+        "f32x4.convert_i32x4_u" => F32x4ConvertI32x4U,
+        "i32x4.trunc_sat_f64x2_s_zero" => I32x4TruncSatF64x2SZero,
+        "i32x4.trunc_sat_f64x2_u_zero" => I32x4TruncSatF64x2UZero,
+        "f64x2.convert_low_i32x4_s" => F64x2ConvertLowI32x4S,
+        "f64x2.convert_low_i32x4_u" => F64x2ConvertLowI32x4U,
+
+        _ => return None,
+    })
+}
@@ -0,0 +1,634 @@
+use ast::{*, highlevel as hl};
+use ast::highlevel::Instr;
+use std::collections::{HashMap, HashSet};
+use std::fmt::{self, Write};
+use super::lexer::is_idchar;
+
+/*
+ * Printer from the high-level AST to the text format, e.g., for debugging instrumented modules.
+ * Since the text format (like the binary) requires imports to come before all definitions,
+ * indices are printed in that order, not the high-level one. Items with debug names are referred to
+ * by identifier instead, e.g., (call $main) instead of (call 3).
+ */
+
+/// for instrumented modules: for each function (by high-level index), the index of the original
+/// instruction that each instruction of its instrumented body belongs to (None for instructions
+/// that do not belong to any, e.g., a function begin hook).
+pub type InstrOrigins = HashMap<Idx<hl::Function>, Vec<Option<Idx<Instr>>>>;
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PrintOptions<'a> {
+    /// comment (;@<instr idx>;) after an instruction whenever the instruction index changes, i.e.,
+    /// after every instruction, or if origins are given, after the first of every instrumented group
+    pub instr_idx_comments: bool,
+    pub instr_origins: Option<&'a InstrOrigins>,
+}
+
+pub fn module_to_wat(module: &hl::Module, options: PrintOptions) -> String {
+    let refs = Refs::new(module);
+    let mut out = String::new();
+
+    out.push_str("(module");
+    if let Some(ref name) = module.name {
+        if let Some(id) = to_id(name) {
+            write!(out, " ${}", id).unwrap();
+        }
+    }
+
+    // imports first, in the (text/binary) index order
+    for (idx, function) in module.functions.iter().enumerate().filter(|&(_, f)| f.import.is_some()) {
+        function_to_wat(function, idx.into(), &refs, options, &mut out);
+    }
+    for (idx, table) in module.tables.iter().enumerate().filter(|&(_, t)| t.import.is_some()) {
+        table_to_wat(table, idx.into(), &refs, &mut out);
+    }
+    for (idx, memory) in module.memories.iter().enumerate().filter(|&(_, m)| m.import.is_some()) {
+        memory_to_wat(memory, idx.into(), &refs, &mut out);
+    }
+    for (idx, global) in module.globals.iter().enumerate().filter(|&(_, g)| g.import.is_some()) {
+        global_to_wat(global, idx.into(), &refs, &mut out);
+    }
+    for (idx, tag) in module.tags.iter().enumerate().filter(|&(_, t)| t.import.is_some()) {
+        tag_to_wat(tag, idx.into(), &refs, &mut out);
+    }
+
+    for (idx, table) in module.tables.iter().enumerate().filter(|&(_, t)| t.import.is_none()) {
+        table_to_wat(table, idx.into(), &refs, &mut out);
+    }
+    for (idx, memory) in module.memories.iter().enumerate().filter(|&(_, m)| m.import.is_none()) {
+        memory_to_wat(memory, idx.into(), &refs, &mut out);
+    }
+    for (idx, tag) in module.tags.iter().enumerate().filter(|&(_, t)| t.import.is_none()) {
+        tag_to_wat(tag, idx.into(), &refs, &mut out);
+    }
+    for (idx, global) in module.globals.iter().enumerate().filter(|&(_, g)| g.import.is_none()) {
+        global_to_wat(global, idx.into(), &refs, &mut out);
+    }
+    for (idx, function) in module.functions.iter().enumerate().filter(|&(_, f)| f.import.is_none()) {
+        function_to_wat(function, idx.into(), &refs, options, &mut out);
+    }
+
+    if let Some(start) = module.start {
+        write!(out, "\n  (start {})", refs.function(start)).unwrap();
+    }
+
+    // segments have no imports, so their high-level and text indices are the same
+    for element in &module.elements {
+        out.push_str("\n  (elem");
+        let items_keyword = match element.items {
+            hl::ElementItems::Functions(_) => "func".to_string(),
+            hl::ElementItems::Exprs(elem_type, _) => elem_type.to_type().to_string(),
+        };
+        match element.mode {
+            hl::ElementMode::Active { table_idx, ref offset } => {
+                if refs.tables.text_idx(table_idx.0) != 0 {
+                    write!(out, " (table {})", refs.table(table_idx)).unwrap();
+                    abbreviated_expr_to_wat("offset", offset, &refs, &mut out);
+                    // explicit table requires the newer syntax with "func"
+                    write!(out, " {}", items_keyword).unwrap();
+                } else {
+                    abbreviated_expr_to_wat("offset", offset, &refs, &mut out);
+                    if let hl::ElementItems::Exprs(_, _) = element.items {
+                        write!(out, " {}", items_keyword).unwrap();
+                    }
+                }
+            }
+            hl::ElementMode::Passive => write!(out, " {}", items_keyword).unwrap(),
+            hl::ElementMode::Declarative => write!(out, " declare {}", items_keyword).unwrap(),
+        }
+        match element.items {
+            hl::ElementItems::Functions(ref functions) => for &function_idx in functions {
+                write!(out, " {}", refs.function(function_idx)).unwrap();
+            },
+            hl::ElementItems::Exprs(_, ref exprs) => for expr in exprs {
+                abbreviated_expr_to_wat("item", expr, &refs, &mut out);
+            },
+        }
+        out.push(')');
+    }
+    for data in &module.data {
+        out.push_str("\n  (data");
+        if let hl::DataMode::Active { memory_idx, ref offset } = data.mode {
+            if refs.memories.text_idx(memory_idx.0) != 0 {
+                write!(out, " (memory {})", refs.memory(memory_idx)).unwrap();
+            }
+            abbreviated_expr_to_wat("offset", offset, &refs, &mut out);
+        }
+        out.push(' ');
+        string_to_wat(&data.bytes, &mut out);
+        out.push(')');
+    }
+
+    out.push_str(")\n");
+    out
+}
+
+impl fmt::Display for hl::Module {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&module_to_wat(self, PrintOptions::default()))
+    }
+}
+
+/// without the surrounding module, indices are printed as they are in the high-level AST
+impl fmt::Display for hl::Function {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut out = String::new();
+        function_to_wat(self, 0.into(), &Refs::default(), PrintOptions::default(), &mut out);
+        // remove the leading newline + indentation for module fields
+        f.write_str(out.trim_left())
+    }
+}
+
+impl fmt::Display for Instr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut out = String::new();
+        instr_to_wat(self, &Refs::default(), &[], &mut out);
+        f.write_str(&out)
+    }
+}
+
+
+/* References to items by identifier or text format index */
+
+#[derive(Default)]
+struct IndexSpaceRefs {
+    // by high-level index, empty if unknown (e.g., when printing a function without its module)
+    text_indices: Vec<usize>,
+    ids: Vec<Option<String>>,
+}
+
+impl IndexSpaceRefs {
+    /// imports come first, otherwise keep the high-level order
+    fn new<'a, T: 'a>(items: &'a [T], import: impl Fn(&T) -> bool, name: impl Fn(&'a T) -> Option<&'a str>) -> Self {
+        let mut text_indices = vec![0; items.len()];
+        let mut next_text_idx = 0;
+        for imports in &[true, false] {
+            for (idx, item) in items.iter().enumerate() {
+                if import(item) == *imports {
+                    text_indices[idx] = next_text_idx;
+                    next_text_idx += 1;
+                }
+            }
+        }
+        IndexSpaceRefs {
+            text_indices,
+            ids: unique_ids(items.iter().map(name)),
+        }
+    }
+
+    fn text_idx(&self, idx: usize) -> usize {
+        self.text_indices.get(idx).cloned().unwrap_or(idx)
+    }
+
+    fn get(&self, idx: usize) -> String {
+        match self.ids.get(idx) {
+            Some(&Some(ref id)) => format!("${}", id),
+            _ => self.text_idx(idx).to_string(),
+        }
+    }
+
+    /// identifier and index comment for the definition of an item
+    fn definition(&self, idx: usize) -> String {
+        if self.text_indices.is_empty() {
+            return String::new();
+        }
+        match self.ids.get(idx) {
+            Some(&Some(ref id)) => format!(" ${} (;{};)", id, self.text_idx(idx)),
+            _ => format!(" (;{};)", self.text_idx(idx)),
+        }
+    }
+}
+
+#[derive(Default)]
+struct Refs {
+    functions: IndexSpaceRefs,
+    tables: IndexSpaceRefs,
+    memories: IndexSpaceRefs,
+    globals: IndexSpaceRefs,
+    tags: IndexSpaceRefs,
+}
+
+impl Refs {
+    fn new(module: &hl::Module) -> Self {
+        Refs {
+            functions: IndexSpaceRefs::new(&module.functions, |f| f.import.is_some(), |f| f.name.as_ref().map(String::as_str)),
+            tables: IndexSpaceRefs::new(&module.tables, |t| t.import.is_some(), |_| None),
+            memories: IndexSpaceRefs::new(&module.memories, |m| m.import.is_some(), |_| None),
+            globals: IndexSpaceRefs::new(&module.globals, |g| g.import.is_some(), |g| g.name.as_ref().map(String::as_str)),
+            tags: IndexSpaceRefs::new(&module.tags, |t| t.import.is_some(), |_| None),
+        }
+    }
+
+    fn function(&self, idx: Idx<hl::Function>) -> String { self.functions.get(idx.0) }
+    fn table(&self, idx: Idx<hl::Table>) -> String { self.tables.get(idx.0) }
+    fn memory(&self, idx: Idx<hl::Memory>) -> String { self.memories.get(idx.0) }
+    fn global(&self, idx: Idx<hl::Global>) -> String { self.globals.get(idx.0) }
+    fn tag(&self, idx: Idx<hl::Tag>) -> String { self.tags.get(idx.0) }
+}
+
+/// debug names can contain arbitrary characters and duplicates, identifiers cannot
+fn unique_ids<'a>(names: impl Iterator<Item=Option<&'a str>>) -> Vec<Option<String>> {
+    let mut used = HashSet::new();
+    names.map(|name| {
+        let id = to_id(name?)?;
+        let mut unique_id = id.clone();
+        let mut counter = 1;
+        while !used.insert(unique_id.clone()) {
+            unique_id = format!("{}.{}", id, counter);
+            counter += 1;
+        }
+        Some(unique_id)
+    }).collect()
+}
+
+fn to_id(name: &str) -> Option<String> {
+    if name.is_empty() {
+        return None;
+    }
+    Some(name.chars()
+        .map(|c| if is_idchar(c) { c } else { '_' })
+        .collect())
+}
+
+
+/* Module fields */
+
+fn exports_import_to_wat(export: &[String], import: &Option<(String, String)>, out: &mut String) {
+    for name in export {
+        out.push_str(" (export ");
+        string_to_wat(name.as_bytes(), out);
+        out.push(')');
+    }
+    if let Some((ref module, ref name)) = *import {
+        out.push_str(" (import ");
+        string_to_wat(module.as_bytes(), out);
+        out.push(' ');
+        string_to_wat(name.as_bytes(), out);
+        out.push(')');
+    }
+}
+
+fn function_to_wat(function: &hl::Function, idx: Idx<hl::Function>, refs: &Refs, options: PrintOptions, out: &mut String) {
+    write!(out, "\n  (func{}", refs.functions.definition(idx.0)).unwrap();
+    exports_import_to_wat(&function.export, &function.import, out);
+
+    let locals: &[ValType] = function.code.as_ref().map(|code| &code.locals[..]).unwrap_or(&[]);
+    let local_ids = unique_ids((0..function.type_.params.len() + locals.len())
+        .map(|local_idx| function.local_names.get(&Idx::<Local>::from(local_idx)).map(String::as_str)));
+
+    vals_to_wat("param", &function.type_.params, &local_ids, out);
+    vals_to_wat("result", &function.type_.results, &[], out);
+
+    if let Some(ref code) = function.code {
+        if !code.locals.is_empty() {
+            out.push_str("\n   ");
+            vals_to_wat("local", &code.locals, &local_ids[function.type_.params.len()..], out);
+        }
+
+        let origins = options.instr_origins.and_then(|origins| origins.get(&idx));
+        let mut last_origin = None;
+        let mut depth = 0;
+        // last end is implicit in the text format
+        let body = &code.body[..code.body.len().saturating_sub(1)];
+        for (i, instr) in body.iter().enumerate() {
+            match *instr {
+                Instr::Else | Instr::End | Instr::Catch(_) | Instr::CatchAll | Instr::Delegate(_) if depth > 0 => depth -= 1,
+                _ => {}
+            }
+
+            out.push('\n');
+            for _ in 0..depth + 2 {
+                out.push_str("  ");
+            }
+            instr_to_wat(instr, refs, &local_ids, out);
+
+            if options.instr_idx_comments {
+                let origin = match origins {
+                    Some(origins) => origins.get(i).cloned().unwrap_or(None),
+                    None => Some(i.into()),
+                };
+                if origin != last_origin {
+                    if let Some(origin) = origin {
+                        write!(out, " (;@{};)", origin.0).unwrap();
+                    }
+                    last_origin = origin;
+                }
+            }
+
+            match *instr {
+                Instr::Block(_) | Instr::Loop(_) | Instr::If(_) | Instr::Else => depth += 1,
+                Instr::Try(_) | Instr::Catch(_) | Instr::CatchAll => depth += 1,
+                _ => {}
+            }
+        }
+    }
+    out.push(')');
+}
+
+/// named values each get their own (param $name type), consecutive unnamed ones are grouped
+fn vals_to_wat(keyword: &str, types: &[ValType], ids: &[Option<String>], out: &mut String) {
+    let mut group_open = false;
+    for (i, type_) in types.iter().enumerate() {
+        match ids.get(i) {
+            Some(&Some(ref id)) => {
+                if group_open {
+                    out.push(')');
+                    group_open = false;
+                }
+                write!(out, " ({} ${} {})", keyword, id, type_).unwrap();
+            }
+            _ => {
+                if !group_open {
+                    write!(out, " ({}", keyword).unwrap();
+                    group_open = true;
+                }
+                write!(out, " {}", type_).unwrap();
+            }
+        }
+    }
+    if group_open {
+        out.push(')');
+    }
+}
+
+fn table_to_wat(table: &hl::Table, idx: Idx<hl::Table>, refs: &Refs, out: &mut String) {
+    write!(out, "\n  (table{}", refs.tables.definition(idx.0)).unwrap();
+    exports_import_to_wat(&table.export, &table.import, out);
+    let TableType(elem_type, limits) = table.type_;
+    limits_to_wat(&limits, out);
+    match elem_type {
+        // old name of funcref, as with instruction names (see Instr::to_name())
+        ElemType::FuncRef => out.push_str(" anyfunc)"),
+        ElemType::ExternRef => out.push_str(" externref)"),
+    }
+}
+
+fn memory_to_wat(memory: &hl::Memory, idx: Idx<hl::Memory>, refs: &Refs, out: &mut String) {
+    write!(out, "\n  (memory{}", refs.memories.definition(idx.0)).unwrap();
+    exports_import_to_wat(&memory.export, &memory.import, out);
+    limits_to_wat(&memory.type_.0, out);
+    out.push(')');
+}
+
+fn tag_to_wat(tag: &hl::Tag, idx: Idx<hl::Tag>, refs: &Refs, out: &mut String) {
+    write!(out, "\n  (tag{}", refs.tags.definition(idx.0)).unwrap();
+    exports_import_to_wat(&tag.export, &tag.import, out);
+    vals_to_wat("param", &tag.type_.params, &[], out);
+    out.push(')');
+}
+
+fn global_to_wat(global: &hl::Global, idx: Idx<hl::Global>, refs: &Refs, out: &mut String) {
+    write!(out, "\n  (global{}", refs.globals.definition(idx.0)).unwrap();
+    exports_import_to_wat(&global.export, &global.import, out);
+    match global.type_ {
+        GlobalType(val_type, Mutability::Const) => write!(out, " {}", val_type).unwrap(),
+        GlobalType(val_type, Mutability::Mut) => write!(out, " (mut {})", val_type).unwrap(),
+    }
+    if let Some(ref init) = global.init {
+        for instr in init.iter().filter(|instr| **instr != Instr::End) {
+            out.push_str(" (");
+            instr_to_wat(instr, refs, &[], out);
+            out.push(')');
+        }
+    }
+    out.push(')');
+}
+
+fn limits_to_wat(limits: &Limits, out: &mut String) {
+    if limits.memory64 {
+        out.push_str(" i64");
+    }
+    write!(out, " {}", limits.initial_size).unwrap();
+    if let Some(max_size) = limits.max_size {
+        write!(out, " {}", max_size).unwrap();
+    }
+    if limits.shared {
+        out.push_str(" shared");
+    }
+}
+
+/// single instruction expressions (e.g., segment offsets) as folded instruction, otherwise as
+/// (<keyword> ...)
+fn abbreviated_expr_to_wat(keyword: &str, expr: &[Instr], refs: &Refs, out: &mut String) {
+    let instrs: Vec<&Instr> = expr.iter().filter(|instr| **instr != Instr::End).collect();
+    if instrs.len() == 1 {
+        out.push_str(" (");
+        instr_to_wat(instrs[0], refs, &[], out);
+        out.push(')');
+    } else {
+        write!(out, " ({}", keyword).unwrap();
+        for instr in instrs {
+            out.push(' ');
+            instr_to_wat(instr, refs, &[], out);
+        }
+        out.push(')');
+    }
+}
+
+/// as string literal, escaping everything but printable ASCII characters
+fn string_to_wat(bytes: &[u8], out: &mut String) {
+    out.push('"');
+    for &byte in bytes {
+        match byte {
+            b'"' | b'\\' => write!(out, "\\{}", byte as char).unwrap(),
+            0x20..=0x7e => out.push(byte as char),
+            _ => write!(out, "\\{:02x}", byte).unwrap(),
+        }
+    }
+    out.push('"');
+}
+
+
+/* Instructions */
+
+fn instr_to_wat(instr: &Instr, refs: &Refs, local_ids: &[Option<String>], out: &mut String) {
+    use ast::highlevel::Instr::*;
+
+    out.push_str(instr.to_name());
+    match *instr {
+        Block(ref type_) | Loop(ref type_) | If(ref type_) | Try(ref type_) => {
+            vals_to_wat("param", &type_.params, &[], out);
+            vals_to_wat("result", &type_.results, &[], out);
+        }
+
+        Br(label) | BrIf(label) | Delegate(label) | Rethrow(label) => write!(out, " {}", label.0).unwrap(),
+        Catch(tag_idx) | Throw(tag_idx) => write!(out, " {}", refs.tag(tag_idx)).unwrap(),
+        BrTable(ref table, default) => {
+            for label in table {
+                write!(out, " {}", label.0).unwrap();
+            }
+            write!(out, " {}", default.0).unwrap();
+        }
+
+        Call(function_idx) | ReturnCall(function_idx) => write!(out, " {}", refs.function(function_idx)).unwrap(),
+        CallIndirect(ref type_, table_idx) | ReturnCallIndirect(ref type_, table_idx) => {
+            if refs.tables.text_idx(table_idx.0) != 0 {
+                write!(out, " {}", refs.table(table_idx)).unwrap();
+            }
+            vals_to_wat("param", &type_.params, &[], out);
+            vals_to_wat("result", &type_.results, &[], out);
+        }
+
+        Local(_, local_idx) => match local_ids.get(local_idx.0) {
+            Some(&Some(ref id)) => write!(out, " ${}", id).unwrap(),
+            _ => write!(out, " {}", local_idx.0).unwrap(),
+        },
+        Global(_, global_idx) => write!(out, " {}", refs.global(global_idx)).unwrap(),
+
+        Load(ref op, memory_idx, memarg) => {
+            if refs.memories.text_idx(memory_idx.0) != 0 {
+                write!(out, " {}", refs.memory(memory_idx)).unwrap();
+            }
+            memarg_to_wat(memarg, op.natural_alignment(), out)
+        }
+        Store(ref op, memory_idx, memarg) => {
+            if refs.memories.text_idx(memory_idx.0) != 0 {
+                write!(out, " {}", refs.memory(memory_idx)).unwrap();
+            }
+            memarg_to_wat(memarg, op.natural_alignment(), out)
+        }
+
+        MemorySize(memory_idx) | MemoryGrow(memory_idx) | MemoryFill(memory_idx) => {
+            if refs.memories.text_idx(memory_idx.0) != 0 {
+                write!(out, " {}", refs.memory(memory_idx)).unwrap();
+            }
+        }
+
+        MemoryInit(data_idx, memory_idx) => {
+            if refs.memories.text_idx(memory_idx.0) != 0 {
+                write!(out, " {}", refs.memory(memory_idx)).unwrap();
+            }
+            write!(out, " {}", data_idx.0).unwrap();
+        }
+        DataDrop(data_idx) => write!(out, " {}", data_idx.0).unwrap(),
+        MemoryCopy(dst_idx, src_idx) => {
+            if refs.memories.text_idx(dst_idx.0) != 0 || refs.memories.text_idx(src_idx.0) != 0 {
+                write!(out, " {} {}", refs.memory(dst_idx), refs.memory(src_idx)).unwrap();
+            }
+        }
+        TableInit(element_idx, table_idx) => {
+            if refs.tables.text_idx(table_idx.0) != 0 {
+                write!(out, " {}", refs.table(table_idx)).unwrap();
+            }
+            write!(out, " {}", element_idx.0).unwrap();
+        }
+        ElemDrop(element_idx) => write!(out, " {}", element_idx.0).unwrap(),
+        TableCopy(dst_idx, src_idx) => {
+            if refs.tables.text_idx(dst_idx.0) != 0 || refs.tables.text_idx(src_idx.0) != 0 {
+                write!(out, " {} {}", refs.table(dst_idx), refs.table(src_idx)).unwrap();
+            }
+        }
+
+        TypedSelect(ty) => vals_to_wat("result", &[ty], &[], out),
+        RefNull(ElemType::FuncRef) => out.push_str(" func"),
+        RefNull(ElemType::ExternRef) => out.push_str(" extern"),
+        RefFunc(function_idx) => write!(out, " {}", refs.function(function_idx)).unwrap(),
+        TableGet(table_idx) | TableSet(table_idx) | TableSize(table_idx) | TableGrow(table_idx) | TableFill(table_idx) => {
+            if refs.tables.text_idx(table_idx.0) != 0 {
+                write!(out, " {}", refs.table(table_idx)).unwrap();
+            }
+        }
+
+        Const(Val::I32(value)) => write!(out, " {}", value).unwrap(),
+        Const(Val::I64(value)) => write!(out, " {}", value).unwrap(),
+        Const(Val::F32(value)) => {
+            let special = special_float_to_wat(value.is_sign_negative(), value.is_nan(), value.is_infinite(), value.to_bits() as u64 & 0x7f_ffff, 0x40_0000);
+            write!(out, " {}", special.unwrap_or_else(|| format!("{:?}", value))).unwrap()
+        }
+        Const(Val::F64(value)) => {
+            let special = special_float_to_wat(value.is_sign_negative(), value.is_nan(), value.is_infinite(), value.to_bits() & 0xf_ffff_ffff_ffff, 0x8_0000_0000_0000);
+            write!(out, " {}", special.unwrap_or_else(|| format!("{:?}", value))).unwrap()
+        }
+
+        // always print as i32x4, since the original shape is not kept in the AST
+        Const(Val::V128(value)) => {
+            out.push_str(" i32x4");
+            for i in 0..4 {
+                write!(out, " 0x{:08x}", (value >> (32 * i)) as u32).unwrap();
+            }
+        }
+        Shuffle(ref lanes) => {
+            for lane in lanes {
+                write!(out, " {}", lane).unwrap();
+            }
+        }
+        ExtractLane(_, lane) | ReplaceLane(_, lane) => write!(out, " {}", lane).unwrap(),
+        LoadLane(ref op, memory_idx, memarg, lane) => {
+            if refs.memories.text_idx(memory_idx.0) != 0 {
+                write!(out, " {}", refs.memory(memory_idx)).unwrap();
+            }
+            memarg_to_wat(memarg, op.natural_alignment(), out);
+            write!(out, " {}", lane).unwrap();
+        }
+        StoreLane(ref op, memory_idx, memarg, lane) => {
+            if refs.memories.text_idx(memory_idx.0) != 0 {
+                write!(out, " {}", refs.memory(memory_idx)).unwrap();
+            }
+            memarg_to_wat(memarg, op.natural_alignment(), out);
+            write!(out, " {}", lane).unwrap();
+        }
+
+        AtomicNotify(memory_idx, memarg) => {
+            if refs.memories.text_idx(memory_idx.0) != 0 {
+                write!(out, " {}", refs.memory(memory_idx)).unwrap();
+            }
+            memarg_to_wat(memarg, 2, out)
+        }
+        AtomicLoad(ref op, memory_idx, memarg) => {
+            if refs.memories.text_idx(memory_idx.0) != 0 {
+                write!(out, " {}", refs.memory(memory_idx)).unwrap();
+            }
+            memarg_to_wat(memarg, op.natural_alignment(), out)
+        }
+        AtomicStore(ref op, memory_idx, memarg) => {
+            if refs.memories.text_idx(memory_idx.0) != 0 {
+                write!(out, " {}", refs.memory(memory_idx)).unwrap();
+            }
+            memarg_to_wat(memarg, op.natural_alignment(), out)
+        }
+        AtomicRmw(ref op, memory_idx, memarg) => {
+            if refs.memories.text_idx(memory_idx.0) != 0 {
+                write!(out, " {}", refs.memory(memory_idx)).unwrap();
+            }
+            memarg_to_wat(memarg, op.natural_alignment(), out)
+        }
+        AtomicCmpxchg(ref op, memory_idx, memarg) => {
+            if refs.memories.text_idx(memory_idx.0) != 0 {
+                write!(out, " {}", refs.memory(memory_idx)).unwrap();
+            }
+            memarg_to_wat(memarg, op.natural_alignment(), out)
+        }
+        AtomicWait(ref op, memory_idx, memarg) => {
+            if refs.memories.text_idx(memory_idx.0) != 0 {
+                write!(out, " {}", refs.memory(memory_idx)).unwrap();
+            }
+            memarg_to_wat(memarg, op.natural_alignment(), out)
+        }
+
+        _ => {}
+    }
+}
+
+fn memarg_to_wat(memarg: Memarg, natural_alignment: u32, out: &mut String) {
+    if memarg.offset != 0 {
+        write!(out, " offset={}", memarg.offset).unwrap();
+    }
+    if memarg.alignment != natural_alignment {
+        write!(out, " align={}", 1u64 << memarg.alignment).unwrap();
+    }
+}
+
+/// NaNs (with their payload) and infinities, all other floats are printed with Debug, which gives
+/// the shortest representation that parses to the same value
+fn special_float_to_wat(is_negative: bool, is_nan: bool, is_infinite: bool, nan_payload: u64, canonical_nan_payload: u64) -> Option<String> {
+    let sign = if is_negative { "-" } else { "" };
+    if is_nan && nan_payload == canonical_nan_payload {
+        Some(format!("{}nan", sign))
+    } else if is_nan {
+        Some(format!("{}nan:0x{:x}", sign, nan_payload))
+    } else if is_infinite {
+        Some(format!("{}inf", sign))
+    } else {
+        None
+    }
+}
@@ -21,6 +21,8 @@ pub enum HighLevelHook {
     // together for call_pre and call_post
     Call,
     Return,
+    // tail calls (return_call and return_call_indirect), replace call_post and return
+    ReturnCall,
 
     Drop,
     Select,
@@ -28,15 +30,48 @@ pub enum HighLevelHook {
     Const,
     Unary,
     Binary,
+    Ternary,
 
     Load,
     Store,
 
     MemorySize,
     MemoryGrow,
+    // bulk memory operations
+    MemoryFill,
+    MemoryCopy,
+    MemoryInit,
 
     Local,
     Global,
+
+    // reference types
+    TableGet,
+    TableSet,
+    TableSize,
+    TableGrow,
+    TableFill,
+    RefNull,
+    RefIsNull,
+    RefFunc,
+
+    // SIMD
+    ExtractLane,
+    ReplaceLane,
+    Shuffle,
+
+    // threads proposal
+    AtomicLoad,
+    AtomicStore,
+    AtomicRmw,
+    AtomicCmpxchg,
+    AtomicWait,
+    AtomicNotify,
+    AtomicFence,
+
+    // exception handling
+    Throw,
+    Catch,
 }
 
 #[derive(Debug)]
@@ -45,7 +80,7 @@ pub struct EnabledHooks(HashSet<HighLevelHook>);
 impl EnabledHooks {
     pub fn all() -> Self {
         use self::HighLevelHook::*;
-        static VARIANTS: [HighLevelHook; 22] = [Start, Nop, Unreachable, Br, BrIf, BrTable, If, Begin, End, Call, Return, Drop, Select, Const, Unary, Binary, Load, Store, MemorySize, MemoryGrow, Local, Global];
+        static VARIANTS: [HighLevelHook; 47] = [Start, Nop, Unreachable, Br, BrIf, BrTable, If, Begin, End, Call, Return, ReturnCall, Drop, Select, Const, Unary, Binary, Ternary, Load, Store, MemorySize, MemoryGrow, MemoryFill, MemoryCopy, MemoryInit, Local, Global, TableGet, TableSet, TableSize, TableGrow, TableFill, RefNull, RefIsNull, RefFunc, ExtractLane, ReplaceLane, Shuffle, AtomicLoad, AtomicStore, AtomicRmw, AtomicCmpxchg, AtomicWait, AtomicNotify, AtomicFence, Throw, Catch];
         EnabledHooks(VARIANTS.iter().cloned().collect())
     }
 
@@ -1,13 +1,15 @@
 use self::BlockStackElement::*;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use wasm::ast::{highlevel::Instr, Idx, Label};
 
 /*
  * Data structure for representing the "control stack", i.e., the implicit nested block structure
  * of control-flow instructions.
  * Needed for:
- *  - resolving End instructions to their corresponding begins (i.e., Block, Loop, If, Else)
+ *  - resolving End instructions to their corresponding begins (i.e., Block, Loop, If, Else, Try, Catch, CatchAll)
  *  - resolving labels of branches to actual instruction indices (which requires the previous as a first step)
+ *  - resolving the blocks that are left when an exception unwinds to a handler (at runtime, from
+ *    the innermost block at the throw site, see blocks())
  */
 
 #[derive(Debug)]
@@ -15,6 +17,9 @@ pub struct BlockStack {
     block_stack: Vec<BlockStackElement>,
     /// pre-computed on new()
     begin_end_map: HashMap<Idx<Instr>, Idx<Instr>>,
+    /// pre-computed on new(), all blocks of the function by their begin (-1 for the function itself)
+    /// together with the begin of their parent block
+    blocks: BTreeMap<i64, (BlockStackElement, i64)>,
 }
 
 #[derive(Debug, Clone)]
@@ -40,6 +45,21 @@ pub enum BlockStackElement {
         begin_if: Idx<Instr>,
         end: Idx<Instr>,
     },
+    // exception handling: end is the end (or delegate) of the whole try-catch construct
+    Try {
+        begin: Idx<Instr>,
+        end: Idx<Instr>,
+    },
+    Catch {
+        begin_catch: Idx<Instr>,
+        begin_try: Idx<Instr>,
+        end: Idx<Instr>,
+    },
+    CatchAll {
+        begin_catch: Idx<Instr>,
+        begin_try: Idx<Instr>,
+        end: Idx<Instr>,
+    },
 }
 
 impl BlockStack {
@@ -51,13 +71,14 @@ impl BlockStack {
         for (iidx, instr) in instrs[..instrs.len() - 1].iter().enumerate() {
             let iidx = iidx.into();
             match *instr {
-                Instr::Block(_) | Instr::Loop(_) | Instr::If(_) => begin_stack.push(iidx),
-                Instr::Else | Instr::End => {
+                Instr::Block(_) | Instr::Loop(_) | Instr::If(_) | Instr::Try(_) => begin_stack.push(iidx),
+                Instr::Else | Instr::Catch(_) | Instr::CatchAll | Instr::End | Instr::Delegate(_) => {
                     let begin_iidx = begin_stack.pop().expect("invalid block nesting: could not end block, stack was empty");
                     begin_end_map.insert(begin_iidx, iidx);
-                    // special case: Else also starts its own block
-                    if let Instr::Else = instr {
-                        begin_stack.push(iidx);
+                    // special case: Else, Catch, and CatchAll also start their own block
+                    match *instr {
+                        Instr::Else | Instr::Catch(_) | Instr::CatchAll => begin_stack.push(iidx),
+                        _ => {}
                     }
                 }
                 _ => {}
@@ -65,10 +86,47 @@ impl BlockStack {
         }
         assert!(begin_stack.is_empty(), "invalid block nesting: some blocks were not closed, stack at end is {:?}", begin_stack);
 
-        BlockStack {
+        let mut block_stack = BlockStack {
             block_stack: vec![Function { end: (instrs.len() - 1).into() }],
             begin_end_map,
+            blocks: BTreeMap::new(),
+        };
+
+        // second pass: "simulate" the block stack once for the whole function, to know the parent
+        // of each block
+        let function = block_stack.block_stack[0].clone();
+        block_stack.blocks.insert(-1, (function, -1));
+        let mut parents: Vec<i64> = vec![-1];
+        for (iidx, instr) in instrs[..instrs.len() - 1].iter().enumerate() {
+            let iidx: Idx<Instr> = iidx.into();
+            match *instr {
+                Instr::Block(_) => block_stack.begin_block(iidx),
+                Instr::Loop(_) => block_stack.begin_loop(iidx),
+                Instr::If(_) => block_stack.begin_if(iidx),
+                Instr::Try(_) => block_stack.begin_try(iidx),
+                Instr::Else => { block_stack.else_(); }
+                Instr::Catch(_) => { block_stack.catch(iidx); }
+                Instr::CatchAll => { block_stack.catch_all(iidx); }
+                Instr::End | Instr::Delegate(_) => {
+                    block_stack.end();
+                    parents.pop();
+                    continue;
+                }
+                _ => continue,
+            }
+            // siblings (else, catch) replace the previous block, so they have the same parent
+            match *instr {
+                Instr::Else | Instr::Catch(_) | Instr::CatchAll => { parents.pop(); }
+                _ => {}
+            }
+            let parent = *parents.last().expect("invalid block nesting: missing function block");
+            let block = block_stack.block_stack.last().unwrap().clone();
+            block_stack.blocks.insert(iidx.0 as i64, (block, parent));
+            parents.push(iidx.0 as i64);
         }
+        block_stack.block_stack.truncate(1);
+
+        block_stack
     }
 
     pub fn begin_block(&mut self, begin: Idx<Instr>) {
@@ -107,6 +165,41 @@ impl BlockStack {
         self.block_stack.push(if_);
     }
 
+    pub fn begin_try(&mut self, begin: Idx<Instr>) {
+        // follow all catch clauses to the end of the whole try-catch construct
+        let mut end = *self.begin_end_map.get(&begin)
+            .expect(&format!("invalid block nesting: could not find end/catch for try begin at {:?}", begin));
+        while let Some(&next) = self.begin_end_map.get(&end) {
+            end = next;
+        }
+        self.block_stack.push(Try { begin, end });
+    }
+
+    /// returns the try or previous catch block (of which this catch is a "sibling")
+    pub fn catch(&mut self, begin_catch: Idx<Instr>) -> BlockStackElement {
+        let (begin_try, end, block_element) = self.pop_try_or_catch();
+        self.block_stack.push(Catch { begin_catch, begin_try, end });
+        block_element
+    }
+
+    /// same as catch(), but for catch_all
+    pub fn catch_all(&mut self, begin_catch: Idx<Instr>) -> BlockStackElement {
+        let (begin_try, end, block_element) = self.pop_try_or_catch();
+        self.block_stack.push(CatchAll { begin_catch, begin_try, end });
+        block_element
+    }
+
+    fn pop_try_or_catch(&mut self) -> (Idx<Instr>, Idx<Instr>, BlockStackElement) {
+        match self.block_stack.pop() {
+            Some(block_element) => match block_element {
+                Try { begin: begin_try, end }
+                | Catch { begin_try, end, .. } => (begin_try, end, block_element),
+                block => panic!("invalid block nesting: expected try or catch on block stack, but got {:?}", block),
+            }
+            None => panic!("invalid block nesting: expected try, but stack was empty"),
+        }
+    }
+
     /// returns matching If block (of which this else is a "sibling")
     pub fn else_(&mut self) -> BlockStackElement {
         match self.block_stack.pop() {
@@ -141,12 +234,20 @@ impl BlockStack {
             match *target_block {
                 Loop { begin, .. } => begin,
                 Function { end } | Block { end, .. } | If { end, .. } | Else { end, .. } => end,
+                Try { end, .. } | Catch { end, .. } | CatchAll { end, .. } => end,
             }
         };
 
         BranchTarget { absolute_instr, ended_blocks }
     }
 
+    /// all blocks of the function by their begin instruction (-1 for the function itself), together
+    /// with the begin of their parent block, such that at runtime all blocks between the innermost
+    /// block at a throw site and an exception handler can be resolved
+    pub fn blocks(&self) -> &BTreeMap<i64, (BlockStackElement, i64)> {
+        &self.blocks
+    }
+
     /// similar to br_target(), call to get all implicitly ended blocks by a return
     pub fn return_target(&self) -> BranchTarget {
         BranchTarget {
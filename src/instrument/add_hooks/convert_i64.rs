@@ -1,20 +1,22 @@
-use wasm::ast::{Val, ValType, ValType::I32, ValType::I64};
-use wasm::ast::highlevel::{Instr, Instr::Const, Instr::Numeric, NumericOp::I32WrapI64, NumericOp::I64ShrS};
+use wasm::ast::{Val, ValType, ValType::I32, ValType::I64, ValType::V128};
+use wasm::ast::highlevel::{Instr, Instr::Const, Instr::ExtractLane, Instr::Numeric, NumericOp::I32WrapI64, NumericOp::I64ShrS, ExtractLaneOp::I32x4ExtractLane};
 use std::slice::from_ref;
 
 /*
- * Helper functions for turning i64's into two i32's so that we can pass them to JavaScript
+ * Helper functions for turning i64's into two i32's (and v128's into four i32 lanes) so that we
+ * can pass them to JavaScript
  */
 
 pub fn convert_i64_type(ty: &ValType) -> &[ValType] {
     match ty {
         &I64 => &[I32, I32],
+        &V128 => &[I32, I32, I32, I32],
         ty => from_ref(ty),
     }
 }
 
 /// instr is assumed to have no side-effects or influences on the stack (other than pushing one value)
-/// so that we can execute it safely twice (once for lower and higher bit half).
+/// so that we can execute it safely twice (once for lower and higher bit half), or four times for v128.
 /// ty is necessary because for some instructions, the type cannot be determined but needs external information, e.g., for GetLocal
 pub fn convert_i64_instr(instr: Instr, ty: ValType) -> Vec<Instr> {
     match ty {
@@ -26,6 +28,8 @@ pub fn convert_i64_instr(instr: Instr, ty: ValType) -> Vec<Instr> {
             Numeric(I64ShrS),
             Numeric(I32WrapI64), // high bits
         ],
+        // lane 0 (lowest bits) first
+        V128 => (0..4).flat_map(|lane| vec![instr.clone(), ExtractLane(I32x4ExtractLane, lane)]).collect(),
         _ => vec![instr],
     }
 }
@@ -25,7 +25,7 @@ pub fn save_stack_to_locals(locals: &[Idx<ast::Local>]) -> Vec<Instr> {
     return instrs;
 }
 
-/// restores locals back onto stack and inserts code that converts i64 -> (i32, i32)
+/// restores locals back onto stack and inserts code that converts i64 -> (i32, i32) and v128 -> 4 x i32
 /// function is necessary to get the types of the locals
 pub fn restore_locals_with_i64_handling(locals: &[Idx<ast::Local>], function: &Function) -> Vec<Instr> {
     let mut instrs = Vec::new();
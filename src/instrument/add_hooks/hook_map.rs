@@ -1,6 +1,6 @@
 use wasm::ast::{FunctionType, Idx, ValType, ValType::*};
 use wasm::ast::highlevel::{Function, Instr, Instr::*, Module};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use parking_lot::{RwLock, RwLockUpgradableReadGuard};
 use super::block_stack::BlockStackElement;
 use super::convert_i64::convert_i64_type;
@@ -24,6 +24,7 @@ impl Arg {
     fn to_lowlevel_param_name(&self) -> String {
         match self.ty {
             I64 => self.name.clone() + "_low, " + &self.name + "_high",
+            V128 => (0..4).map(|lane| format!("{}_{}", self.name, lane)).collect::<Vec<_>>().join(", "),
             _ => self.name.clone()
         }
     }
@@ -32,6 +33,8 @@ impl Arg {
     fn to_lowlevel_long_expr(&self) -> String {
         match self.ty {
             I64 => format!("new Long({})", self.to_lowlevel_param_name()),
+            // four i32 lanes, reinterpret with, e.g., new Float64Array(value.buffer)
+            V128 => format!("Int32Array.of({})", self.to_lowlevel_param_name()),
             _ => self.name.clone()
         }
     }
@@ -67,7 +70,7 @@ impl Hook {
             // prepend two I32 for (function idx, instr idx)
             let mut lowlevel_args = vec![I32, I32];
             lowlevel_args.extend(args.iter()
-                // and expand i64 to a tuple of (i32, i32) since there is no JS interop for i64
+                // and expand i64 to a tuple of (i32, i32) since there is no JS interop for i64 (same for v128)
                 .flat_map(|Arg { name: _name, ref ty }| convert_i64_type(ty)));
 
             Function {
@@ -76,6 +79,8 @@ impl Hook {
                 import: Some(("__wasabi_hooks".to_string(), lowlevel_name)),
                 code: None,
                 export: Vec::new(),
+                name: None,
+                local_names: BTreeMap::new(),
             }
         };
 
@@ -137,24 +142,142 @@ impl HookMap {
             // NOTE js_args is very hacky! We rely on the Hook constructor to close the parenthesis and insert the call statement to endBrTableBlock() here
             BrTable(_, _) => Hook::new(name, args!(tableIdx: I32, brTablesInfoIdx: I32), name, "Wasabi.module.info.brTables[brTablesInfoIdx].table, Wasabi.module.info.brTables[brTablesInfoIdx].default, tableIdx); Wasabi.endBrTableBlocks(brTablesInfoIdx, tableIdx, func"),
 
-            MemorySize(_) => Hook::new(name, args!(currentSizePages: I32), name, "currentSizePages"),
-            MemoryGrow(_) => Hook::new(name, args!(deltaPages: I32, previousSizePages: I32), name, "deltaPages, previousSizePages"),
+            // memory64: addresses and sizes of 64-bit memories are i64 (passed as Long to the high-level hooks)
+            // the index types are given as polymorphic_tys, which is empty for 32-bit memories
+            MemorySize(_) => {
+                let args = args!(memory: I32, currentSizePages: index_ty(polymorphic_tys, 0));
+                let js_args = &format!("{}, memory", &args[1].to_lowlevel_long_expr());
+                Hook::new(name, args, "memory_size", js_args)
+            }
+            MemoryGrow(_) => {
+                let ty = index_ty(polymorphic_tys, 0);
+                let args = args!(memory: I32, deltaPages: ty, previousSizePages: ty);
+                let js_args = &format!("{}, {}, memory", &args[1].to_lowlevel_long_expr(), &args[2].to_lowlevel_long_expr());
+                Hook::new(name, args, "memory_grow", js_args)
+            }
+            MemoryFill(_) => {
+                let ty = index_ty(polymorphic_tys, 0);
+                let args = args!(memory: I32, addr: ty, value: I32, length: ty);
+                let js_args = &format!("{}, value, {}, memory", &args[1].to_lowlevel_long_expr(), &args[3].to_lowlevel_long_expr());
+                Hook::new(name, args, "memory_fill", js_args)
+            }
+            MemoryCopy(_, _) => {
+                let (dest_ty, src_ty) = (index_ty(polymorphic_tys, 0), index_ty(polymorphic_tys, 1));
+                // the length must be valid for both memories, so it is only an i64 if both are 64-bit
+                let length_ty = if dest_ty == I64 && src_ty == I64 { I64 } else { I32 };
+                let args = args!(destMemory: I32, srcMemory: I32, destAddr: dest_ty, srcAddr: src_ty, length: length_ty);
+                let js_args = &format!("{}, destMemory, srcMemory", args[2..].iter().map(Arg::to_lowlevel_long_expr).collect::<Vec<_>>().join(", "));
+                Hook::new(name, args, "memory_copy", js_args)
+            }
+            MemoryInit(_, _) => {
+                let args = args!(dataIdx: I32, memory: I32, destAddr: index_ty(polymorphic_tys, 0), srcOffset: I32, length: I32);
+                let js_args = &format!("dataIdx, {}, srcOffset, length, memory", &args[2].to_lowlevel_long_expr());
+                Hook::new(name, args, "memory_init", js_args)
+            }
+
+            RefFunc(_) => Hook::new(name, args!(targetFunc: I32), "ref_func", "targetFunc"),
+            TableSize(_) => Hook::new(name, args!(table: I32, currentSize: I32), "table_size", "table, currentSize"),
+
+            LoadLane(_, _, _, _) => {
+                let addr_ty = index_ty(polymorphic_tys, 0);
+                let args = args!(memory: I32, offset: addr_ty, align: I32, lane: I32, addr: addr_ty, input: V128, value: V128);
+                let instr_name = instr.to_name();
+                let js_args = &format!("\"{}\", {{{}, lane}}, {}", instr_name, memarg_js(&args[1], &args[4]), &args[6].to_lowlevel_long_expr());
+                Hook::new(name, args, "load", js_args)
+            }
+            StoreLane(_, _, _, _) => {
+                let addr_ty = index_ty(polymorphic_tys, 0);
+                let args = args!(memory: I32, offset: addr_ty, align: I32, lane: I32, addr: addr_ty, value: V128);
+                let instr_name = instr.to_name();
+                let js_args = &format!("\"{}\", {{{}, lane}}, {}", instr_name, memarg_js(&args[1], &args[4]), &args[5].to_lowlevel_long_expr());
+                Hook::new(name, args, "store", js_args)
+            }
+            Shuffle(_) => {
+                let args = args!(lanes: V128, input0: V128, input1: V128, result: V128);
+                let js_args = &format!("Array.from(new Uint8Array({}.buffer)), {}", &args[0].to_lowlevel_long_expr(), args[1..].iter().map(Arg::to_lowlevel_long_expr).collect::<Vec<_>>().join(", "));
+                Hook::new(name, args, "shuffle", js_args)
+            }
+            ExtractLane(op, _) => {
+                let ty = op.to_type();
+                let args = args!(lane: I32, input: V128, result: ty.results[0]);
+                let instr_name = instr.to_name();
+                let js_args = &format!("\"{}\", lane, {}", instr_name, args[1..].iter().map(Arg::to_lowlevel_long_expr).collect::<Vec<_>>().join(", "));
+                Hook::new(name, args, "extract_lane", js_args)
+            }
+            ReplaceLane(op, _) => {
+                let ty = op.to_type();
+                let args = args!(lane: I32, input: V128, value: ty.inputs[1], result: V128);
+                let instr_name = instr.to_name();
+                let js_args = &format!("\"{}\", lane, {}", instr_name, args[1..].iter().map(Arg::to_lowlevel_long_expr).collect::<Vec<_>>().join(", "));
+                Hook::new(name, args, "replace_lane", js_args)
+            }
 
-            Load(op, _) => {
+            Load(op, _, _) => {
                 let ty = op.to_type().results[0];
-                let args = args!(offset: I32, align: I32, addr: I32, value: ty);
+                let addr_ty = index_ty(polymorphic_tys, 0);
+                let args = args!(memory: I32, offset: addr_ty, align: I32, addr: addr_ty, value: ty);
                 let instr_name = instr.to_name();
-                let js_args = &format!("\"{}\", {{addr, offset, align}}, {}", instr_name, &args[3].to_lowlevel_long_expr());
+                let js_args = &format!("\"{}\", {{{}}}, {}", instr_name, memarg_js(&args[1], &args[3]), &args[4].to_lowlevel_long_expr());
                 Hook::new(name, args, "load", js_args)
             }
-            Store(op, _) => {
+            Store(op, _, _) => {
                 let ty = op.to_type().inputs[1];
-                let args = args!(offset: I32, align: I32, addr: I32, value: ty);
+                let addr_ty = index_ty(polymorphic_tys, 0);
+                let args = args!(memory: I32, offset: addr_ty, align: I32, addr: addr_ty, value: ty);
                 let instr_name = instr.to_name();
-                let js_args = &format!("\"{}\", {{addr, offset, align}}, {}", instr_name, &args[3].to_lowlevel_long_expr());
+                let js_args = &format!("\"{}\", {{{}}}, {}", instr_name, memarg_js(&args[1], &args[3]), &args[4].to_lowlevel_long_expr());
                 Hook::new(name, args, "store", js_args)
             }
 
+            // all atomic accesses are sequentially consistent (the only ordering in the threads proposal)
+            AtomicLoad(op, _, _) => {
+                let ty = op.to_type().results[0];
+                let addr_ty = index_ty(polymorphic_tys, 0);
+                let args = args!(memory: I32, offset: addr_ty, align: I32, addr: addr_ty, value: ty);
+                let instr_name = instr.to_name();
+                let js_args = &format!("\"{}\", {{{}}}, {}, \"seq_cst\"", instr_name, memarg_js(&args[1], &args[3]), &args[4].to_lowlevel_long_expr());
+                Hook::new(name, args, "atomic_load", js_args)
+            }
+            AtomicStore(op, _, _) => {
+                let ty = op.to_type().inputs[1];
+                let addr_ty = index_ty(polymorphic_tys, 0);
+                let args = args!(memory: I32, offset: addr_ty, align: I32, addr: addr_ty, value: ty);
+                let instr_name = instr.to_name();
+                let js_args = &format!("\"{}\", {{{}}}, {}, \"seq_cst\"", instr_name, memarg_js(&args[1], &args[3]), &args[4].to_lowlevel_long_expr());
+                Hook::new(name, args, "atomic_store", js_args)
+            }
+            AtomicRmw(op, _, _) => {
+                let ty = op.to_type().inputs[1];
+                let addr_ty = index_ty(polymorphic_tys, 0);
+                let args = args!(memory: I32, offset: addr_ty, align: I32, addr: addr_ty, operand: ty, oldValue: ty);
+                let instr_name = instr.to_name();
+                let js_args = &format!("\"{}\", {{{}}}, {}, \"seq_cst\"", instr_name, memarg_js(&args[1], &args[3]), args[4..].iter().map(Arg::to_lowlevel_long_expr).collect::<Vec<_>>().join(", "));
+                Hook::new(name, args, "atomic_rmw", js_args)
+            }
+            AtomicCmpxchg(op, _, _) => {
+                let ty = op.to_type().inputs[1];
+                let addr_ty = index_ty(polymorphic_tys, 0);
+                let args = args!(memory: I32, offset: addr_ty, align: I32, addr: addr_ty, expected: ty, replacement: ty, oldValue: ty);
+                let instr_name = instr.to_name();
+                let js_args = &format!("\"{}\", {{{}}}, {}, \"seq_cst\"", instr_name, memarg_js(&args[1], &args[3]), args[4..].iter().map(Arg::to_lowlevel_long_expr).collect::<Vec<_>>().join(", "));
+                Hook::new(name, args, "atomic_cmpxchg", js_args)
+            }
+            AtomicWait(op, _, _) => {
+                let ty = op.to_type().inputs[1];
+                let addr_ty = index_ty(polymorphic_tys, 0);
+                let args = args!(memory: I32, offset: addr_ty, align: I32, addr: addr_ty, expected: ty, timeout: I64, result: I32);
+                let instr_name = instr.to_name();
+                let js_args = &format!("\"{}\", {{{}}}, {}, {}, [\"ok\", \"not-equal\", \"timed-out\"][result]", instr_name, memarg_js(&args[1], &args[3]), &args[4].to_lowlevel_long_expr(), &args[5].to_lowlevel_long_expr());
+                Hook::new(name, args, "atomic_wait", js_args)
+            }
+            AtomicNotify(_, _) => {
+                let addr_ty = index_ty(polymorphic_tys, 0);
+                let args = args!(memory: I32, offset: addr_ty, align: I32, addr: addr_ty, count: I32, woken: I32);
+                let js_args = &format!("{{{}}}, count, woken", memarg_js(&args[1], &args[3]));
+                Hook::new(name, args, "atomic_notify", js_args)
+            }
+            AtomicFence => Hook::new(name, args!(), "atomic_fence", "\"seq_cst\""),
+
             Const(val) => {
                 let args = args!(value: val.to_type());
                 let js_args = &args[0].to_lowlevel_long_expr();
@@ -165,6 +288,8 @@ impl HookMap {
                 let highlevel_name = match ty.inputs.len() {
                     1 => "unary",
                     2 => "binary",
+                    // only v128.bitselect
+                    3 => "ternary",
                     _ => unreachable!()
                 };
                 let inputs = ty.inputs.iter().enumerate().map(|(i, &ty)| Arg { name: format!("input{}", i), ty });
@@ -190,7 +315,7 @@ impl HookMap {
                 let js_args = &args[0].to_lowlevel_long_expr();
                 Hook::new(name, args, "drop", js_args)
             }
-            Select => {
+            Select | TypedSelect(_) => {
                 assert_eq!(polymorphic_tys.len(), 2, "select has two polymorphic arguments");
                 assert_eq!(polymorphic_tys[0], polymorphic_tys[1], "select arguments must be equal");
                 let args = args!(condition: I32, input0: polymorphic_tys[0], input1: polymorphic_tys[1]);
@@ -224,16 +349,73 @@ impl HookMap {
                 Hook::new(name, args, "call_pre", js_args)
             }
             CallIndirect(_, _) => {
-                let mut args = args!(tableIndex: I32);
+                // table: which table (multiple tables with reference types), tableIndex: index into that table
+                let mut args = args!(table: I32, tableIndex: I32);
                 args.extend(polymorphic_tys.iter().enumerate().map(|(i, &ty)| Arg { name: format!("arg{}", i), ty }));
-                let js_args = &format!("Wasabi.resolveTableIdx(tableIndex), [{}], tableIndex", args[1..].iter().map(Arg::to_lowlevel_long_expr).collect::<Vec<_>>().join(", "));
+                let js_args = &format!("Wasabi.resolveTableIdx(tableIndex, table), [{}], tableIndex", args[2..].iter().map(Arg::to_lowlevel_long_expr).collect::<Vec<_>>().join(", "));
                 Hook::new(name, args, "call_pre", js_args)
             }
+            // same arguments as call_pre, but a different high-level hook, since there is no call_post
+            ReturnCall(_) => {
+                let mut args = args!(targetFunc: I32);
+                args.extend(polymorphic_tys.iter().enumerate().map(|(i, &ty)| Arg { name: format!("arg{}", i), ty }));
+                let js_args = &format!("targetFunc, [{}]", args[1..].iter().map(Arg::to_lowlevel_long_expr).collect::<Vec<_>>().join(", "));
+                Hook::new(name, args, "return_call", js_args)
+            }
+            ReturnCallIndirect(_, _) => {
+                let mut args = args!(table: I32, tableIndex: I32);
+                args.extend(polymorphic_tys.iter().enumerate().map(|(i, &ty)| Arg { name: format!("arg{}", i), ty }));
+                let js_args = &format!("Wasabi.resolveTableIdx(tableIndex, table), [{}], tableIndex", args[2..].iter().map(Arg::to_lowlevel_long_expr).collect::<Vec<_>>().join(", "));
+                Hook::new(name, args, "return_call", js_args)
+            }
+
+            // polymorphic over the reference type (of the table or operand), given as the only element of polymorphic_tys
+            RefNull(_) => {
+                assert_eq!(polymorphic_tys.len(), 1, "ref.null has one reference type");
+                Hook::new(name, args!(), "ref_null", &format!("\"{}\"", polymorphic_tys[0]))
+            }
+            RefIsNull => {
+                assert_eq!(polymorphic_tys.len(), 1, "ref.is_null has one reference type");
+                Hook::new(name, args!(value: polymorphic_tys[0], result: I32), "ref_is_null", "value, result === 1")
+            }
+            TableGet(_) => {
+                assert_eq!(polymorphic_tys.len(), 1, "table instructions have one reference type");
+                Hook::new(name, args!(table: I32, index: I32, value: polymorphic_tys[0]), "table_get", "table, index, value")
+            }
+            TableSet(_) => {
+                assert_eq!(polymorphic_tys.len(), 1, "table instructions have one reference type");
+                Hook::new(name, args!(table: I32, index: I32, value: polymorphic_tys[0]), "table_set", "table, index, value")
+            }
+            TableGrow(_) => {
+                assert_eq!(polymorphic_tys.len(), 1, "table instructions have one reference type");
+                Hook::new(name, args!(table: I32, value: polymorphic_tys[0], delta: I32, previousSize: I32), "table_grow", "table, value, delta, previousSize")
+            }
+            TableFill(_) => {
+                assert_eq!(polymorphic_tys.len(), 1, "table instructions have one reference type");
+                Hook::new(name, args!(table: I32, index: I32, value: polymorphic_tys[0], length: I32), "table_fill", "table, index, value, length")
+            }
+
+            // polymorphic over the types of the values carried by the exception (the params of the tag)
+            Throw(_) => {
+                let mut args = args!(tag: I32);
+                args.extend(polymorphic_tys.iter().enumerate().map(|(i, &ty)| Arg { name: format!("arg{}", i), ty }));
+                let js_args = &format!("tag, [{}]", args[1..].iter().map(Arg::to_lowlevel_long_expr).collect::<Vec<_>>().join(", "));
+                Hook::new(name, args, "throw_", js_args)
+            }
+            Catch(_) => {
+                let mut args = args!(tag: I32);
+                args.extend(polymorphic_tys.iter().enumerate().map(|(i, &ty)| Arg { name: format!("value{}", i), ty }));
+                let js_args = &format!("tag, [{}]", args[1..].iter().map(Arg::to_lowlevel_long_expr).collect::<Vec<_>>().join(", "));
+                Hook::new(name, args, "catch_", js_args)
+            }
+            // NOTE the tag (and thus the values) of exceptions caught by catch_all is unknown
+            CatchAll => Hook::new(name, args!(), "catch_", "null, []"),
 
 
             /* instructions that need additional information and thus have own method */
 
-            Block(_) | Loop(_) | Else | End => panic!("cannot get hook for block-type instruction with this method, please use the other methods specialized to the block type"),
+            Block(_) | Loop(_) | Else | End | Try(_) | Delegate(_) => panic!("cannot get hook for block-type instruction with this method, please use the other methods specialized to the block type"),
+            DataDrop(_) | TableInit(_, _) | ElemDrop(_) | TableCopy(_, _) | Rethrow(_) => panic!("no hook for instruction {}", instr.to_name()),
         };
         self.get_or_insert(hook)
     }
@@ -271,6 +453,28 @@ impl HookMap {
         self.get_or_insert(Hook::new("begin_else", args!(ifInstr: I32), "begin", "\"else\", {func, instr: ifInstr}"))
     }
 
+    pub fn begin_try(&self) -> Instr {
+        self.get_or_insert(Hook::new("begin_try", vec![], "begin", "\"try\""))
+    }
+
+    pub fn begin_catch(&self) -> Instr {
+        self.get_or_insert(Hook::new("begin_catch", args!(tryInstr: I32), "begin", "\"catch\", {func, instr: tryInstr}"))
+    }
+
+    pub fn begin_catch_all(&self) -> Instr {
+        self.get_or_insert(Hook::new("begin_catch_all", args!(tryInstr: I32), "begin", "\"catch_all\", {func, instr: tryInstr}"))
+    }
+
+    /// called at the begin of an exception handler (catch or catch_all), calls the end hooks of all
+    /// blocks that were left by the exception, from the innermost block at the throw site (which
+    /// is only known at runtime) up to the handler, see Wasabi.endUnwoundBlocks()
+    pub fn end_unwound(&self) -> Instr {
+        let mut hook = Hook::new("end_unwound", args!(handlerBegin: I32, innermostBegin: I32), "end", "");
+        // NOTE does not call a single high-level hook, so replace the generated JavaScript
+        hook.js = "\"end_unwound\": function (func, instr, handlerBegin, innermostBegin) {\n    Wasabi.endUnwoundBlocks(func, handlerBegin, innermostBegin);\n},".to_string();
+        self.get_or_insert(hook)
+    }
+
     pub fn end(&self, block: &BlockStackElement) -> Instr {
         self.get_or_insert(match *block {
             BlockStackElement::Function { .. } => Hook::new("end_function", vec![], "end", "\"function\", {func, instr: -1}"),
@@ -278,6 +482,9 @@ impl HookMap {
             BlockStackElement::Loop { .. } => Hook::new("end_loop", args!(beginInstr: I32), "end", "\"loop\", {func, instr: beginInstr}"),
             BlockStackElement::If { .. } => Hook::new("end_if", args!(beginInstr: I32), "end", "\"if\", {func, instr: beginInstr}"),
             BlockStackElement::Else { .. } => Hook::new("end_else", args!(elseInstr: I32, ifInstr: I32), "end", "\"else\", {func, instr: elseInstr}, {func, instr: ifInstr}"),
+            BlockStackElement::Try { .. } => Hook::new("end_try", args!(beginInstr: I32), "end", "\"try\", {func, instr: beginInstr}"),
+            BlockStackElement::Catch { .. } => Hook::new("end_catch", args!(catchInstr: I32, tryInstr: I32), "end", "\"catch\", {func, instr: catchInstr}, {func, instr: tryInstr}"),
+            BlockStackElement::CatchAll { .. } => Hook::new("end_catch_all", args!(catchInstr: I32, tryInstr: I32), "end", "\"catch_all\", {func, instr: catchInstr}, {func, instr: tryInstr}"),
         })
     }
 
@@ -320,6 +527,20 @@ impl HookMap {
 /* utility functions */
 
 /// e.g. "call" + [I32, F64] -> "call_iF"
+/// memory64: index type of the accessed memory (or memories), see add_hooks/mod.rs
+fn index_ty(polymorphic_tys: &[ValType], i: usize) -> ValType {
+    polymorphic_tys.get(i).cloned().unwrap_or(I32)
+}
+
+/// JavaScript fields of the memarg object of load/store-like hooks, with address and offset as
+/// Long for 64-bit memories
+fn memarg_js(offset: &Arg, addr: &Arg) -> String {
+    match addr.ty {
+        I64 => format!("addr: {}, offset: {}, align, memory", addr.to_lowlevel_long_expr(), offset.to_lowlevel_long_expr()),
+        _ => "addr, offset, align, memory".to_string(),
+    }
+}
+
 fn mangle_polymorphic_name(name: &str, tys: &[ValType]) -> String {
     let mut mangled = name.to_string();
     if !tys.is_empty() {
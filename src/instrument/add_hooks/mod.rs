@@ -6,8 +6,10 @@ use self::hook_map::HookMap;
 use self::static_info::*;
 use self::type_stack::TypeStack;
 use serde_json;
-use wasm::ast::{BlockType, Idx, InstrType, Mutability, Val, ValType::*, FunctionType};
+use wasm::ast::{Idx, InstrType, Mutability, Val, ValType, ValType::*, FunctionType};
 use wasm::ast::highlevel::{Function, GlobalOp::*, Instr, Instr::*, LocalOp::*, Module};
+use wasm::dwarf::DebugInfo;
+use wasm::wat::InstrOrigins;
 use rayon::prelude::*;
 use parking_lot::RwLock;
 
@@ -21,12 +23,34 @@ mod duplicate_stack;
 /// instruments every instruction in Jalangi-style with a callback that takes inputs, outputs, and
 /// other relevant information.
 pub fn add_hooks(module: &mut Module, enabled_hooks: &EnabledHooks) -> Option<String> {
-    // make sure table is exported, needed for Wasabi runtime to resolve table indices to function indices.
-    for table in &mut module.tables {
+    add_hooks_with_origins(module, enabled_hooks).map(|(js, _)| js)
+}
+
+/// same as add_hooks, but additionally returns from which original instruction each instruction
+/// of the instrumented functions stems (e.g., for printing the instrumented module as text)
+pub fn add_hooks_with_origins(module: &mut Module, enabled_hooks: &EnabledHooks) -> Option<(String, InstrOrigins)> {
+    add_hooks_with_debug_info(module, enabled_hooks, None)
+}
+
+/// same as add_hooks_with_origins, but additionally puts the source locations and function names
+/// from the DWARF debug info of the original module into the static info (see Wasabi.loc2source)
+pub fn add_hooks_with_debug_info(module: &mut Module, enabled_hooks: &EnabledHooks, debug_info: Option<&DebugInfo>) -> Option<(String, InstrOrigins)> {
+    // make sure all tables are exported, needed for Wasabi runtime to resolve table indices to function indices.
+    // export names must be unique, so include the table index (multiple tables with reference types)
+    for (idx, table) in module.tables.iter_mut().enumerate() {
         if table.export.is_empty() {
-            table.export.push("__wasabi_table".into());
+            table.export.push(format!("__wasabi_table_{}", idx));
+        }
+    }
+    // same for memories, so that the runtime can inspect all of them (multi-memory)
+    for (idx, memory) in module.memories.iter_mut().enumerate() {
+        if memory.export.is_empty() {
+            memory.export.push(format!("__wasabi_memory_{}", idx));
         }
     }
+    // instrumenting moves all code to other byte offsets, so a source map of the original module
+    // would point to the wrong positions (main.rs writes a new one on request)
+    module.remove_custom_sections("sourceMappingURL");
     // FIXME is this a valid workaround for wrong Firefox exported function .name property?
 //    if let Some(function) = module.functions.first_mut() {
 //        if function.export.is_empty() {
@@ -34,10 +58,28 @@ pub fn add_hooks(module: &mut Module, enabled_hooks: &EnabledHooks) -> Option<St
 //        }
 //    }
 
-    // NOTE must be after exporting table and function, so that their export names are in the static info object
-    let module_info: ModuleInfo = (&*module).into();
+    // NOTE must be after exporting tables, memories, and function, so that their export names are in the static info object
+    let mut module_info: ModuleInfo = (&*module).into();
+    if let Some(debug_info) = debug_info {
+        module_info.add_debug_info(debug_info);
+    }
     let module_info = RwLock::new(module_info);
+    // for typing table instructions, which are polymorphic over the element type of their table
+    let table_types: Vec<_> = module.tables.iter().map(|table| table.type_.0.to_type()).collect();
+    // for typing memory instructions, whose addresses are i64 for 64-bit memories (memory64 proposal)
+    let memory_index_types: Vec<_> = module.memories.iter().map(|memory| memory.type_.index_type()).collect();
+    let memory_index_type = |memory_idx: Idx<::wasm::ast::highlevel::Memory>| memory_index_types[memory_idx.0];
+    // for typing throw and catch, which are polymorphic over the values carried by the exception
+    let tag_types: Vec<_> = module.tags.iter().map(|tag| tag.type_.params.clone()).collect();
+    // blocks that are left by an exception have no static end location (unlike for br, return etc.)
+    // so their end hooks are called at runtime from the handler; this requires catch_all, so only
+    // do it for modules that use exception handling (and would not run on engines without it anyway)
+    let end_unwound_blocks = enabled_hooks.is_enabled(HighLevelHook::End)
+        && (!module.tags.is_empty() || module.functions.iter()
+        .flat_map(|function| function.code.iter().flat_map(|code| code.body.iter()))
+        .any(|instr| if let Try(_) = *instr { true } else { false }));
     let hooks = HookMap::new(&module);
+    let instr_origins = RwLock::new(InstrOrigins::new());
 
     // add global for start, set to false on the first execution of the start function
     let start_not_executed_global = module.add_global(I32, Mutability::Mut, vec![Const(Val::I32(1)), End]);
@@ -70,7 +112,7 @@ pub fn add_hooks(module: &mut Module, enabled_hooks: &EnabledHooks) -> Option<St
             instrumented_body.extend_from_slice(&[
                 Global(GetGlobal, start_not_executed_global),
                 // ...(if this is the start function and it hasn't run yet)
-                If(BlockType(None)),
+                If(FunctionType::default()),
                 Const(Val::I32(0)),
                 Global(SetGlobal, start_not_executed_global),
                 fidx.to_const(),
@@ -93,9 +135,34 @@ pub fn add_hooks(module: &mut Module, enabled_hooks: &EnabledHooks) -> Option<St
         // remember implicit return for instrumentation: add "synthetic" return hook call to last end
         let implicit_return = !original_body.ends_with(&[Return, End]);
 
+        // at runtime: begin of the innermost block that is currently executing (-1 for the function),
+        // such that the handler knows from where an exception was thrown, see hooks.end_unwound()
+        let innermost_block_tmp = if end_unwound_blocks {
+            module_info.write().functions[fidx.0].blocks = block_stack.blocks().clone();
+
+            let innermost_block_tmp = function.add_fresh_local(I32);
+            instrumented_body.extend_from_slice(&[
+                Const(Val::I32(-1)),
+                Local(SetLocal, innermost_block_tmp),
+                // catches exceptions not handled inside the function, see function end below
+                // NOTE labels need not be adjusted, this block just replaces the function block as the target
+                Try(FunctionType::new(vec![], function.type_.results.clone())),
+            ]);
+            Some(innermost_block_tmp)
+        } else {
+            None
+        };
+
         let mut unreachable = 0;
 
+        // all instructions inserted since the last original instruction belong to it
+        let mut origins = Vec::with_capacity(instrumented_body.capacity());
+        let mut current_origin = None;
+
         for (iidx, instr) in original_body.into_iter().enumerate() {
+            origins.resize(instrumented_body.len(), current_origin);
+            current_origin = Some(iidx.into());
+
             // FIXME super hacky: do not instrument dead code, since my type checking cannot handle
             // the unconstrained return types of return, br, br_table, unreachable and then
             // type_stack.pop_val() blows up because I cannot produce the right types "out of thin
@@ -104,8 +171,10 @@ pub fn add_hooks(module: &mut Module, enabled_hooks: &EnabledHooks) -> Option<St
             // "unreachable depth" abomination.
             if unreachable > 0 {
                 match instr {
-                    Block(_) | Loop(_) | If(_) => unreachable += 1,
-                    End => unreachable -= 1,
+                    Block(_) | Loop(_) | If(_) | Try(_) => unreachable += 1,
+                    End | Delegate(_) => unreachable -= 1,
+                    // catch clauses of the current try are reachable again (through an exception)
+                    Catch(_) | CatchAll if unreachable == 1 => unreachable = 0,
                     _ => {}
                 };
                 if unreachable > 0 {
@@ -154,11 +223,12 @@ pub fn add_hooks(module: &mut Module, enabled_hooks: &EnabledHooks) -> Option<St
 
                 /* Control Instructions: Blocks */
 
-                Block(block_ty) => {
+                Block(ref block_ty) => {
                     block_stack.begin_block(iidx);
-                    type_stack.begin(block_ty);
+                    type_stack.begin(block_ty.clone());
 
-                    instrumented_body.push(instr);
+                    instrumented_body.push(instr.clone());
+                    instrumented_body.append(&mut set_innermost_block(innermost_block_tmp, iidx.0 as i64));
 
                     if enabled_hooks.is_enabled(HighLevelHook::Begin) {
                         instrumented_body.extend_from_slice(&[
@@ -168,11 +238,12 @@ pub fn add_hooks(module: &mut Module, enabled_hooks: &EnabledHooks) -> Option<St
                         ])
                     }
                 }
-                Loop(block_ty) => {
+                Loop(ref block_ty) => {
                     block_stack.begin_loop(iidx);
-                    type_stack.begin(block_ty);
+                    type_stack.begin(block_ty.clone());
 
-                    instrumented_body.push(instr);
+                    instrumented_body.push(instr.clone());
+                    instrumented_body.append(&mut set_innermost_block(innermost_block_tmp, iidx.0 as i64));
 
                     if enabled_hooks.is_enabled(HighLevelHook::Begin) {
                         instrumented_body.extend_from_slice(&[
@@ -182,10 +253,10 @@ pub fn add_hooks(module: &mut Module, enabled_hooks: &EnabledHooks) -> Option<St
                         ])
                     }
                 }
-                If(block_ty) => {
+                If(ref block_ty) => {
                     block_stack.begin_if(iidx);
                     type_stack.instr(&InstrType::new(&[I32], &[]));
-                    type_stack.begin(block_ty);
+                    type_stack.begin(block_ty.clone());
 
                     // if_ hook for the condition (always executed on either branch)
                     if enabled_hooks.is_enabled(HighLevelHook::If) {
@@ -201,7 +272,8 @@ pub fn add_hooks(module: &mut Module, enabled_hooks: &EnabledHooks) -> Option<St
                     }
 
                     // actual if block start
-                    instrumented_body.push(instr);
+                    instrumented_body.push(instr.clone());
+                    instrumented_body.append(&mut set_innermost_block(innermost_block_tmp, iidx.0 as i64));
 
                     // begin hook (not executed when condition implies else branch)
                     if enabled_hooks.is_enabled(HighLevelHook::Begin) {
@@ -232,6 +304,7 @@ pub fn add_hooks(module: &mut Module, enabled_hooks: &EnabledHooks) -> Option<St
                     }
 
                     instrumented_body.push(instr);
+                    instrumented_body.append(&mut set_innermost_block(innermost_block_tmp, iidx.0 as i64));
 
                     if enabled_hooks.is_enabled(HighLevelHook::Begin) {
                         instrumented_body.extend_from_slice(&[
@@ -272,7 +345,122 @@ pub fn add_hooks(module: &mut Module, enabled_hooks: &EnabledHooks) -> Option<St
                         instrumented_body.push(hooks.end(&block))
                     }
 
+                    if let BlockStackElement::Function { .. } = block {
+                        // exceptions that are not handled inside the function leave all its blocks
+                        // (including the function itself), then propagate further to the caller
+                        if let Some(innermost_block_tmp) = innermost_block_tmp {
+                            instrumented_body.extend_from_slice(&[
+                                CatchAll,
+                                fidx.to_const(),
+                                iidx.to_const(),
+                                Const(Val::I32(-1)),
+                                Local(GetLocal, innermost_block_tmp),
+                                hooks.end_unwound(),
+                                Rethrow(0.into()),
+                                // of the try block inserted at the function begin
+                                End,
+                            ]);
+                        }
+                        instrumented_body.push(instr);
+                    } else {
+                        instrumented_body.push(instr);
+                        // the parent is the innermost block again
+                        let parent_begin = block_stack.blocks()[&block.begin()].1;
+                        instrumented_body.append(&mut set_innermost_block(innermost_block_tmp, parent_begin));
+                    }
+                }
+                Try(ref block_ty) => {
+                    block_stack.begin_try(iidx);
+                    type_stack.begin(block_ty.clone());
+
+                    instrumented_body.push(instr.clone());
+                    instrumented_body.append(&mut set_innermost_block(innermost_block_tmp, iidx.0 as i64));
+
+                    if enabled_hooks.is_enabled(HighLevelHook::Begin) {
+                        instrumented_body.extend_from_slice(&[
+                            location.0,
+                            location.1,
+                            hooks.begin_try(),
+                        ])
+                    }
+                }
+                Catch(_) | CatchAll => {
+                    let (previous_block, tag_tys) = match instr {
+                        Catch(tag_idx) => (block_stack.catch(iidx), tag_types[tag_idx.0].clone()),
+                        _ => (block_stack.catch_all(iidx), vec![]),
+                    };
+                    let (begin_try, previous_begins) = match previous_block {
+                        BlockStackElement::Try { begin, .. } => (begin, vec![begin.to_const()]),
+                        BlockStackElement::Catch { begin_catch, begin_try, .. } => (begin_try, vec![begin_catch.to_const(), begin_try.to_const()]),
+                        _ => unreachable!(),
+                    };
+
+                    type_stack.catch(&tag_tys);
+
+                    // end hook of the try or previous catch block, if it completes normally
+                    if enabled_hooks.is_enabled(HighLevelHook::End) {
+                        instrumented_body.extend_from_slice(&[
+                            location.0.clone(),
+                            location.1.clone(),
+                        ]);
+                        instrumented_body.extend_from_slice(&previous_begins);
+                        instrumented_body.push(hooks.end(&previous_block));
+                    }
+
+                    instrumented_body.push(instr.clone());
+
+                    // end hooks of all blocks that were left by the exception
+                    if let Some(innermost_block_tmp) = innermost_block_tmp {
+                        instrumented_body.extend_from_slice(&[
+                            location.0.clone(),
+                            location.1.clone(),
+                            begin_try.to_const(),
+                            Local(GetLocal, innermost_block_tmp),
+                            hooks.end_unwound(),
+                        ]);
+                        instrumented_body.append(&mut set_innermost_block(Some(innermost_block_tmp), iidx.0 as i64));
+                    }
+
+                    if enabled_hooks.is_enabled(HighLevelHook::Begin) {
+                        instrumented_body.extend_from_slice(&[
+                            location.0.clone(),
+                            location.1.clone(),
+                            begin_try.to_const(),
+                            if let Catch(_) = instr { hooks.begin_catch() } else { hooks.begin_catch_all() },
+                        ]);
+                    }
+
+                    // catch hook with the values carried by the exception (none for catch_all)
+                    if enabled_hooks.is_enabled(HighLevelHook::Catch) {
+                        let value_tmps = function.add_fresh_locals(&tag_tys);
+
+                        instrumented_body.append(&mut save_stack_to_locals(&value_tmps));
+                        instrumented_body.extend_from_slice(&[
+                            location.0,
+                            location.1,
+                        ]);
+                        if let Catch(tag_idx) = instr {
+                            instrumented_body.push(tag_idx.to_const());
+                        }
+                        instrumented_body.append(&mut restore_locals_with_i64_handling(&value_tmps, &function));
+                        instrumented_body.push(hooks.instr(&instr, &tag_tys));
+                    }
+                }
+                Delegate(_) => {
+                    // like end, but of a try block (delegate has no catch clauses)
+                    let block = block_stack.end();
+                    assert_eq!(iidx, block.end());
+                    type_stack.end();
+
+                    if enabled_hooks.is_enabled(HighLevelHook::End) {
+                        instrumented_body.append(&mut block.to_end_hook_args(fidx));
+                        instrumented_body.push(hooks.end(&block))
+                    }
+
                     instrumented_body.push(instr);
+
+                    let parent_begin = block_stack.blocks()[&block.begin()].1;
+                    instrumented_body.append(&mut set_innermost_block(innermost_block_tmp, parent_begin));
                 }
 
 
@@ -337,7 +525,7 @@ pub fn add_hooks(module: &mut Module, enabled_hooks: &EnabledHooks) -> Option<St
                             instrumented_body.extend_from_slice(&[
                                 // NOTE see tee_local above
                                 Local(GetLocal, condition_tmp),
-                                If(BlockType(None)),
+                                If(FunctionType::default()),
                             ]);
                             for block in br_target.ended_blocks {
                                 instrumented_body.append(&mut block.to_end_hook_args(fidx));
@@ -382,6 +570,37 @@ pub fn add_hooks(module: &mut Module, enabled_hooks: &EnabledHooks) -> Option<St
                 }
 
 
+                /* Control Instructions: Exceptions */
+                // NOTE end hooks for the blocks that are left are called in the handler (see Catch above)
+
+                Throw(tag_idx) => {
+                    let tag_tys = tag_types[tag_idx.0].clone();
+                    type_stack.instr(&InstrType::new(&tag_tys, &[]));
+
+                    if enabled_hooks.is_enabled(HighLevelHook::Throw) {
+                        let arg_tmps = function.add_fresh_locals(&tag_tys);
+
+                        instrumented_body.append(&mut save_stack_to_locals(&arg_tmps));
+                        instrumented_body.extend_from_slice(&[
+                            location.0,
+                            location.1,
+                            tag_idx.to_const(),
+                        ]);
+                        instrumented_body.append(&mut restore_locals_with_i64_handling(&arg_tmps, &function));
+                        instrumented_body.push(hooks.instr(&instr, &tag_tys));
+                    }
+
+                    instrumented_body.push(instr);
+
+                    unreachable = 1;
+                }
+                Rethrow(_) => {
+                    instrumented_body.push(instr);
+
+                    unreachable = 1;
+                }
+
+
                 /* Control Instructions: Calls & Returns */
 
                 Return => {
@@ -449,7 +668,7 @@ pub fn add_hooks(module: &mut Module, enabled_hooks: &EnabledHooks) -> Option<St
                         instrumented_body.push(instr);
                     }
                 }
-                CallIndirect(ref func_ty, _ /* table idx == 0 in WASM version 1 */) => {
+                CallIndirect(ref func_ty, table_idx) => {
                     type_stack.instr(&instr.to_type().unwrap());
 
                     if enabled_hooks.is_enabled(HighLevelHook::Call) {
@@ -464,6 +683,7 @@ pub fn add_hooks(module: &mut Module, enabled_hooks: &EnabledHooks) -> Option<St
                             Local(GetLocal, target_table_idx_tmp),
                             location.0.clone(),
                             location.1.clone(),
+                            table_idx.to_const(),
                             Local(GetLocal, target_table_idx_tmp),
                         ]);
                         instrumented_body.append(&mut restore_locals_with_i64_handling(&arg_tmps, &function));
@@ -489,6 +709,75 @@ pub fn add_hooks(module: &mut Module, enabled_hooks: &EnabledHooks) -> Option<St
                 }
 
 
+                /* Control Instructions: Tail Calls */
+                // NOTE a tail call returns and calls at the same time, so there is no call_post hook
+                // (the callee returns directly to the caller of this function). Instead, the
+                // return_call hook (analogous to call_pre) is followed by the end hooks of all blocks
+                // (including the function), as for a return.
+
+                ReturnCall(target_func_idx) => {
+                    let ref func_ty = module_info.read().functions[target_func_idx.0].type_;
+                    type_stack.instr(&InstrType::new(&func_ty.params, &[]));
+
+                    if enabled_hooks.is_enabled(HighLevelHook::ReturnCall) {
+                        let arg_tmps = function.add_fresh_locals(&func_ty.params);
+
+                        instrumented_body.append(&mut save_stack_to_locals(&arg_tmps));
+                        instrumented_body.extend_from_slice(&[
+                            location.0,
+                            location.1,
+                            target_func_idx.to_const(),
+                        ]);
+                        instrumented_body.append(&mut restore_locals_with_i64_handling(&arg_tmps, &function));
+                        instrumented_body.push(hooks.instr(&instr, &func_ty.params));
+                    }
+
+                    // end hooks for all blocks of this function, which is left by the tail call
+                    if enabled_hooks.is_enabled(HighLevelHook::End) {
+                        for block in block_stack.return_target().ended_blocks {
+                            instrumented_body.append(&mut block.to_end_hook_args(fidx));
+                            instrumented_body.push(hooks.end(&block));
+                        }
+                    }
+
+                    instrumented_body.push(instr);
+
+                    unreachable = 1;
+                }
+                ReturnCallIndirect(ref func_ty, table_idx) => {
+                    type_stack.instr(&InstrType::new(&[&func_ty.params[..], &[I32]].concat(), &[]));
+
+                    if enabled_hooks.is_enabled(HighLevelHook::ReturnCall) {
+                        let target_table_idx_tmp = function.add_fresh_local(I32);
+                        let arg_tmps = function.add_fresh_locals(&func_ty.params);
+
+                        instrumented_body.push(Local(SetLocal, target_table_idx_tmp));
+                        instrumented_body.append(&mut save_stack_to_locals(&arg_tmps));
+                        instrumented_body.extend_from_slice(&[
+                            Local(GetLocal, target_table_idx_tmp),
+                            location.0,
+                            location.1,
+                            table_idx.to_const(),
+                            Local(GetLocal, target_table_idx_tmp),
+                        ]);
+                        instrumented_body.append(&mut restore_locals_with_i64_handling(&arg_tmps, &function));
+                        instrumented_body.push(hooks.instr(&instr, &func_ty.params));
+                    }
+
+                    // end hooks for all blocks of this function, which is left by the tail call
+                    if enabled_hooks.is_enabled(HighLevelHook::End) {
+                        for block in block_stack.return_target().ended_blocks {
+                            instrumented_body.append(&mut block.to_end_hook_args(fidx));
+                            instrumented_body.push(hooks.end(&block));
+                        }
+                    }
+
+                    instrumented_body.push(instr.clone());
+
+                    unreachable = 1;
+                }
+
+
                 /* Parametric Instructions */
 
                 Drop => {
@@ -509,7 +798,8 @@ pub fn add_hooks(module: &mut Module, enabled_hooks: &EnabledHooks) -> Option<St
                         instrumented_body.push(instr);
                     }
                 }
-                Select => {
+                // typed select (from reference types) is instrumented just like untyped select
+                Select | TypedSelect(_) => {
                     assert_eq!(type_stack.pop_val(), I32, "select condition should be i32");
                     let ty = type_stack.pop_val();
                     assert_eq!(type_stack.pop_val(), ty, "select arguments should have same type");
@@ -577,26 +867,173 @@ pub fn add_hooks(module: &mut Module, enabled_hooks: &EnabledHooks) -> Option<St
 
                 /* Memory Instructions */
 
-                MemorySize(_ /* memory idx == 0 in WASM version 1 */) => {
-                    type_stack.instr(&instr.to_type().unwrap());
+                MemorySize(memory_idx) => {
+                    type_stack.instr(&instr.to_type_with_memories(&memory_index_type).unwrap());
 
                     instrumented_body.push(instr.clone());
 
                     if enabled_hooks.is_enabled(HighLevelHook::MemorySize) {
+                        let index_ty = memory_index_type(memory_idx);
                         instrumented_body.extend_from_slice(&[
                             location.0,
                             location.1,
-                            // optimization: just call memory_size again instead of duplicating result into local
+                            memory_idx.to_const(),
+                        ]);
+                        // optimization: just call memory_size again instead of duplicating result into local
+                        instrumented_body.append(&mut convert_i64_instr(instr.clone(), index_ty));
+                        instrumented_body.push(hooks.instr(&instr, memory64_polymorphic_tys(&[index_ty])));
+                    }
+                }
+                MemoryGrow(memory_idx) => {
+                    let ty = instr.to_type_with_memories(&memory_index_type).unwrap();
+                    type_stack.instr(&ty);
+
+                    if enabled_hooks.is_enabled(HighLevelHook::MemoryGrow) {
+                        let input_tmp = function.add_fresh_local(ty.inputs[0]);
+                        let result_tmp = function.add_fresh_local(ty.results[0]);
+
+                        instrumented_body.extend_from_slice(&[
+                            Local(TeeLocal, input_tmp),
                             instr.clone(),
-                            hooks.instr(&instr, &[])
+                            Local(TeeLocal, result_tmp),
+                            location.0,
+                            location.1,
+                            memory_idx.to_const(),
+                        ]);
+                        instrumented_body.append(&mut restore_locals_with_i64_handling(&[input_tmp, result_tmp], &function));
+                        instrumented_body.push(hooks.instr(&instr, memory64_polymorphic_tys(&ty.inputs)));
+                    } else {
+                        instrumented_body.push(instr);
+                    }
+                }
+                MemoryFill(memory_idx) => {
+                    let ty = instr.to_type_with_memories(&memory_index_type).unwrap();
+                    type_stack.instr(&ty);
+
+                    if enabled_hooks.is_enabled(HighLevelHook::MemoryFill) {
+                        let input_tmps = function.add_fresh_locals(&ty.inputs);
+
+                        instrumented_body.append(&mut save_stack_to_locals(&input_tmps));
+                        instrumented_body.extend_from_slice(&[
+                            instr.clone(),
+                            location.0,
+                            location.1,
+                            memory_idx.to_const(),
+                        ]);
+                        instrumented_body.append(&mut restore_locals_with_i64_handling(&input_tmps, &function));
+                        instrumented_body.push(hooks.instr(&instr, memory64_polymorphic_tys(&[memory_index_type(memory_idx)])));
+                    } else {
+                        instrumented_body.push(instr);
+                    }
+                }
+                MemoryCopy(dst_idx, src_idx) => {
+                    let ty = instr.to_type_with_memories(&memory_index_type).unwrap();
+                    type_stack.instr(&ty);
+
+                    if enabled_hooks.is_enabled(HighLevelHook::MemoryCopy) {
+                        let input_tmps = function.add_fresh_locals(&ty.inputs);
+
+                        instrumented_body.append(&mut save_stack_to_locals(&input_tmps));
+                        instrumented_body.extend_from_slice(&[
+                            instr.clone(),
+                            location.0,
+                            location.1,
+                            dst_idx.to_const(),
+                            src_idx.to_const(),
+                        ]);
+                        instrumented_body.append(&mut restore_locals_with_i64_handling(&input_tmps, &function));
+                        instrumented_body.push(hooks.instr(&instr, memory64_polymorphic_tys(&[memory_index_type(dst_idx), memory_index_type(src_idx)])));
+                    } else {
+                        instrumented_body.push(instr);
+                    }
+                }
+                MemoryInit(data_idx, memory_idx) => {
+                    let ty = instr.to_type_with_memories(&memory_index_type).unwrap();
+                    type_stack.instr(&ty);
+
+                    if enabled_hooks.is_enabled(HighLevelHook::MemoryInit) {
+                        let input_tmps = function.add_fresh_locals(&ty.inputs);
+
+                        instrumented_body.append(&mut save_stack_to_locals(&input_tmps));
+                        instrumented_body.extend_from_slice(&[
+                            instr.clone(),
+                            location.0,
+                            location.1,
+                            data_idx.to_const(),
+                            memory_idx.to_const(),
                         ]);
+                        instrumented_body.append(&mut restore_locals_with_i64_handling(&input_tmps, &function));
+                        instrumented_body.push(hooks.instr(&instr, memory64_polymorphic_tys(&[memory_index_type(memory_idx)])));
+                    } else {
+                        instrumented_body.push(instr);
                     }
                 }
-                MemoryGrow(_ /* memory idx == 0 in WASM version 1 */) => {
+                // no hooks for these (yet), since they do not access memory or values on the stack
+                DataDrop(_) | TableInit(_, _) | ElemDrop(_) | TableCopy(_, _) => {
                     type_stack.instr(&instr.to_type().unwrap());
+                    instrumented_body.push(instr);
+                }
 
-                    if enabled_hooks.is_enabled(HighLevelHook::MemoryGrow) {
-                        let input_tmp = function.add_fresh_local(I32);
+
+
+                /* Table and Reference Instructions */
+
+                TableGet(table_idx) | TableSet(table_idx) | TableSize(table_idx) | TableGrow(table_idx) | TableFill(table_idx) => {
+                    let elem_ty = table_types[table_idx.0];
+                    let (ty, hook) = match instr {
+                        TableGet(_) => (InstrType::new(&[I32], &[elem_ty]), HighLevelHook::TableGet),
+                        TableSet(_) => (InstrType::new(&[I32, elem_ty], &[]), HighLevelHook::TableSet),
+                        TableSize(_) => (InstrType::new(&[], &[I32]), HighLevelHook::TableSize),
+                        TableGrow(_) => (InstrType::new(&[elem_ty, I32], &[I32]), HighLevelHook::TableGrow),
+                        TableFill(_) => (InstrType::new(&[I32, elem_ty, I32], &[]), HighLevelHook::TableFill),
+                        _ => unreachable!(),
+                    };
+                    type_stack.instr(&ty);
+
+                    if enabled_hooks.is_enabled(hook) {
+                        let input_tmps = function.add_fresh_locals(&ty.inputs);
+                        let result_tmps = function.add_fresh_locals(&ty.results);
+
+                        instrumented_body.append(&mut save_stack_to_locals(&input_tmps));
+                        instrumented_body.push(instr.clone());
+                        instrumented_body.append(&mut save_stack_to_locals(&result_tmps));
+                        instrumented_body.extend_from_slice(&[
+                            location.0,
+                            location.1,
+                            table_idx.to_const(),
+                        ]);
+                        instrumented_body.append(&mut restore_locals_with_i64_handling(
+                            &[input_tmps, result_tmps].concat(),
+                            &function));
+                        // table.size is the only one without an operand or result of the element type
+                        let polymorphic_tys = match instr {
+                            TableSize(_) => vec![],
+                            _ => vec![elem_ty],
+                        };
+                        instrumented_body.push(hooks.instr(&instr, &polymorphic_tys));
+                    } else {
+                        instrumented_body.push(instr);
+                    }
+                }
+                RefNull(elem_type) => {
+                    type_stack.instr(&instr.to_type().unwrap());
+
+                    instrumented_body.push(instr.clone());
+
+                    if enabled_hooks.is_enabled(HighLevelHook::RefNull) {
+                        instrumented_body.extend_from_slice(&[
+                            location.0,
+                            location.1,
+                            hooks.instr(&instr, &[elem_type.to_type()])
+                        ]);
+                    }
+                }
+                RefIsNull => {
+                    let ty = type_stack.pop_val();
+                    type_stack.push_val(I32);
+
+                    if enabled_hooks.is_enabled(HighLevelHook::RefIsNull) {
+                        let input_tmp = function.add_fresh_local(ty);
                         let result_tmp = function.add_fresh_local(I32);
 
                         instrumented_body.extend_from_slice(&[
@@ -607,17 +1044,31 @@ pub fn add_hooks(module: &mut Module, enabled_hooks: &EnabledHooks) -> Option<St
                             location.1,
                             Local(GetLocal, input_tmp),
                             Local(GetLocal, result_tmp),
-                            hooks.instr(&instr, &[])
+                            hooks.instr(&instr, &[ty])
                         ]);
                     } else {
                         instrumented_body.push(instr);
                     }
                 }
+                RefFunc(function_idx) => {
+                    type_stack.instr(&instr.to_type().unwrap());
+
+                    instrumented_body.push(instr.clone());
+
+                    if enabled_hooks.is_enabled(HighLevelHook::RefFunc) {
+                        instrumented_body.extend_from_slice(&[
+                            location.0,
+                            location.1,
+                            function_idx.to_const(),
+                            hooks.instr(&instr, &[])
+                        ]);
+                    }
+                }
 
                 /* rest are "grouped instructions", i.e., where many instructions can be handled in a similar manner */
 
-                Load(op, memarg) => {
-                    let ty = op.to_type();
+                Load(_, memory_idx, memarg) => {
+                    let ty = instr.to_type_with_memories(&memory_index_type).unwrap();
                     type_stack.instr(&ty);
 
                     if enabled_hooks.is_enabled(HighLevelHook::Load) {
@@ -630,17 +1081,18 @@ pub fn add_hooks(module: &mut Module, enabled_hooks: &EnabledHooks) -> Option<St
                             Local(TeeLocal, value_tmp),
                             location.0,
                             location.1,
-                            Const(Val::I32(memarg.offset as i32)),
-                            Const(Val::I32(memarg.alignment as i32)),
+                            memory_idx.to_const(),
                         ]);
+                        instrumented_body.append(&mut offset_to_consts(memarg.offset, ty.inputs[0]));
+                        instrumented_body.push(Const(Val::I32(memarg.alignment as i32)));
                         instrumented_body.append(&mut restore_locals_with_i64_handling(&[addr_tmp, value_tmp], &function));
-                        instrumented_body.push(hooks.instr(&instr, &[]));
+                        instrumented_body.push(hooks.instr(&instr, memory64_polymorphic_tys(&ty.inputs[..1])));
                     } else {
                         instrumented_body.push(instr);
                     }
                 }
-                Store(op, memarg) => {
-                    let ty = op.to_type();
+                Store(_, memory_idx, memarg) => {
+                    let ty = instr.to_type_with_memories(&memory_index_type).unwrap();
                     type_stack.instr(&ty);
 
                     if enabled_hooks.is_enabled(HighLevelHook::Store) {
@@ -652,15 +1104,170 @@ pub fn add_hooks(module: &mut Module, enabled_hooks: &EnabledHooks) -> Option<St
                             instr.clone(),
                             location.0,
                             location.1,
-                            Const(Val::I32(memarg.offset as i32)),
-                            Const(Val::I32(memarg.alignment as i32)),
+                            memory_idx.to_const(),
                         ]);
+                        instrumented_body.append(&mut offset_to_consts(memarg.offset, ty.inputs[0]));
+                        instrumented_body.push(Const(Val::I32(memarg.alignment as i32)));
                         instrumented_body.append(&mut restore_locals_with_i64_handling(&[addr_tmp, value_tmp], &function));
+                        instrumented_body.push(hooks.instr(&instr, memory64_polymorphic_tys(&ty.inputs[..1])));
+                    } else {
+                        instrumented_body.push(instr);
+                    }
+                }
+
+                LoadLane(_, memory_idx, memarg, lane) => {
+                    let ty = instr.to_type_with_memories(&memory_index_type).unwrap();
+                    type_stack.instr(&ty);
+
+                    if enabled_hooks.is_enabled(HighLevelHook::Load) {
+                        let input_tmps = function.add_fresh_locals(&ty.inputs);
+                        let result_tmps = function.add_fresh_locals(&ty.results);
+
+                        instrumented_body.append(&mut save_stack_to_locals(&input_tmps));
+                        instrumented_body.push(instr.clone());
+                        instrumented_body.append(&mut save_stack_to_locals(&result_tmps));
+                        instrumented_body.extend_from_slice(&[
+                            location.0,
+                            location.1,
+                            memory_idx.to_const(),
+                        ]);
+                        instrumented_body.append(&mut offset_to_consts(memarg.offset, ty.inputs[0]));
+                        instrumented_body.push(Const(Val::I32(memarg.alignment as i32)));
+                        instrumented_body.push(Const(Val::I32(lane as i32)));
+                        instrumented_body.append(&mut restore_locals_with_i64_handling(&[input_tmps, result_tmps].concat(), &function));
+                        instrumented_body.push(hooks.instr(&instr, memory64_polymorphic_tys(&ty.inputs[..1])));
+                    } else {
+                        instrumented_body.push(instr);
+                    }
+                }
+                StoreLane(_, memory_idx, memarg, lane) => {
+                    let ty = instr.to_type_with_memories(&memory_index_type).unwrap();
+                    type_stack.instr(&ty);
+
+                    if enabled_hooks.is_enabled(HighLevelHook::Store) {
+                        let input_tmps = function.add_fresh_locals(&ty.inputs);
+
+                        instrumented_body.append(&mut save_stack_to_locals(&input_tmps));
+                        instrumented_body.extend_from_slice(&[
+                            instr.clone(),
+                            location.0,
+                            location.1,
+                            memory_idx.to_const(),
+                        ]);
+                        instrumented_body.append(&mut offset_to_consts(memarg.offset, ty.inputs[0]));
+                        instrumented_body.push(Const(Val::I32(memarg.alignment as i32)));
+                        instrumented_body.push(Const(Val::I32(lane as i32)));
+                        instrumented_body.append(&mut restore_locals_with_i64_handling(&input_tmps, &function));
+                        instrumented_body.push(hooks.instr(&instr, memory64_polymorphic_tys(&ty.inputs[..1])));
+                    } else {
+                        instrumented_body.push(instr);
+                    }
+                }
+
+
+                /* SIMD Lane Instructions */
+
+                Shuffle(lanes) => {
+                    let ty = instr.to_type().unwrap();
+                    type_stack.instr(&ty);
+
+                    if enabled_hooks.is_enabled(HighLevelHook::Shuffle) {
+                        let input_tmps = function.add_fresh_locals(&ty.inputs);
+                        let result_tmps = function.add_fresh_locals(&ty.results);
+                        // pass lane indices as v128 constant, lane 0 in the lowest byte
+                        let lanes = lanes.iter().rev().fold(0u128, |value, &lane| value << 8 | lane as u128);
+
+                        instrumented_body.append(&mut save_stack_to_locals(&input_tmps));
+                        instrumented_body.push(instr.clone());
+                        instrumented_body.append(&mut save_stack_to_locals(&result_tmps));
+                        instrumented_body.extend_from_slice(&[
+                            location.0,
+                            location.1,
+                        ]);
+                        instrumented_body.append(&mut convert_i64_instr(Const(Val::V128(lanes)), V128));
+                        instrumented_body.append(&mut restore_locals_with_i64_handling(&[input_tmps, result_tmps].concat(), &function));
                         instrumented_body.push(hooks.instr(&instr, &[]));
                     } else {
                         instrumented_body.push(instr);
                     }
                 }
+                ExtractLane(_, lane) | ReplaceLane(_, lane) => {
+                    let ty = instr.to_type().unwrap();
+                    type_stack.instr(&ty);
+
+                    let hook = match instr {
+                        ExtractLane(_, _) => HighLevelHook::ExtractLane,
+                        _ => HighLevelHook::ReplaceLane,
+                    };
+                    if enabled_hooks.is_enabled(hook) {
+                        let input_tmps = function.add_fresh_locals(&ty.inputs);
+                        let result_tmps = function.add_fresh_locals(&ty.results);
+
+                        instrumented_body.append(&mut save_stack_to_locals(&input_tmps));
+                        instrumented_body.push(instr.clone());
+                        instrumented_body.append(&mut save_stack_to_locals(&result_tmps));
+                        instrumented_body.extend_from_slice(&[
+                            location.0,
+                            location.1,
+                            Const(Val::I32(lane as i32)),
+                        ]);
+                        instrumented_body.append(&mut restore_locals_with_i64_handling(&[input_tmps, result_tmps].concat(), &function));
+                        instrumented_body.push(hooks.instr(&instr, &[]));
+                    } else {
+                        instrumented_body.push(instr);
+                    }
+                }
+
+
+                /* Atomic Memory Instructions (threads proposal) */
+
+                AtomicLoad(_, memory_idx, memarg)
+                | AtomicStore(_, memory_idx, memarg)
+                | AtomicRmw(_, memory_idx, memarg)
+                | AtomicCmpxchg(_, memory_idx, memarg)
+                | AtomicWait(_, memory_idx, memarg)
+                | AtomicNotify(memory_idx, memarg) => {
+                    let ty = instr.to_type_with_memories(&memory_index_type).unwrap();
+                    type_stack.instr(&ty);
+
+                    let hook = match instr {
+                        AtomicLoad(_, _, _) => HighLevelHook::AtomicLoad,
+                        AtomicStore(_, _, _) => HighLevelHook::AtomicStore,
+                        AtomicRmw(_, _, _) => HighLevelHook::AtomicRmw,
+                        AtomicCmpxchg(_, _, _) => HighLevelHook::AtomicCmpxchg,
+                        AtomicWait(_, _, _) => HighLevelHook::AtomicWait,
+                        _ => HighLevelHook::AtomicNotify,
+                    };
+                    if enabled_hooks.is_enabled(hook) {
+                        let input_tmps = function.add_fresh_locals(&ty.inputs);
+                        let result_tmps = function.add_fresh_locals(&ty.results);
+
+                        instrumented_body.append(&mut save_stack_to_locals(&input_tmps));
+                        instrumented_body.push(instr.clone());
+                        instrumented_body.append(&mut save_stack_to_locals(&result_tmps));
+                        instrumented_body.extend_from_slice(&[
+                            location.0,
+                            location.1,
+                            memory_idx.to_const(),
+                        ]);
+                        instrumented_body.append(&mut offset_to_consts(memarg.offset, ty.inputs[0]));
+                        instrumented_body.push(Const(Val::I32(memarg.alignment as i32)));
+                        instrumented_body.append(&mut restore_locals_with_i64_handling(&[input_tmps, result_tmps].concat(), &function));
+                        instrumented_body.push(hooks.instr(&instr, memory64_polymorphic_tys(&ty.inputs[..1])));
+                    } else {
+                        instrumented_body.push(instr);
+                    }
+                }
+                AtomicFence => {
+                    instrumented_body.push(instr.clone());
+                    if enabled_hooks.is_enabled(HighLevelHook::AtomicFence) {
+                        instrumented_body.extend_from_slice(&[
+                            location.0,
+                            location.1,
+                            hooks.instr(&instr, &[])
+                        ]);
+                    }
+                }
 
 
                 /* Numeric Instructions */
@@ -685,7 +1292,8 @@ pub fn add_hooks(module: &mut Module, enabled_hooks: &EnabledHooks) -> Option<St
                     type_stack.instr(&ty);
 
                     if (enabled_hooks.is_enabled(HighLevelHook::Unary) && ty.inputs.len() == 1)
-                        || (enabled_hooks.is_enabled(HighLevelHook::Binary) && ty.inputs.len() == 2) {
+                        || (enabled_hooks.is_enabled(HighLevelHook::Binary) && ty.inputs.len() == 2)
+                        || (enabled_hooks.is_enabled(HighLevelHook::Ternary) && ty.inputs.len() == 3) {
                         let input_tmps = function.add_fresh_locals(&ty.inputs);
                         let result_tmps = function.add_fresh_locals(&ty.results);
 
@@ -707,6 +1315,9 @@ pub fn add_hooks(module: &mut Module, enabled_hooks: &EnabledHooks) -> Option<St
             }
         }
 
+        origins.resize(instrumented_body.len(), current_origin);
+        instr_origins.write().insert(fidx, origins);
+
         // finally, switch dummy body out against instrumented body
         ::std::mem::replace(&mut function.code.as_mut().unwrap().body, instrumented_body);
     });
@@ -728,7 +1339,7 @@ pub fn add_hooks(module: &mut Module, enabled_hooks: &EnabledHooks) -> Option<St
         module.functions.push(hook.wasm);
     }
 
-    Some(generate_js(module_info.into_inner(), &js_hooks))
+    Some((generate_js(module_info.into_inner(), &js_hooks), instr_origins.into_inner()))
 }
 
 /// convenience to hand (function/instr/local/global) indices to hooks
@@ -743,6 +1354,28 @@ impl<T> ToConst for Idx<T> {
     }
 }
 
+/// memory64: hooks of memory instructions are only polymorphic if they access a 64-bit memory, so
+/// that the hook names (and types) for regular 32-bit memories stay the same
+fn memory64_polymorphic_tys(index_tys: &[ValType]) -> &[ValType] {
+    if index_tys.contains(&I64) { index_tys } else { &[] }
+}
+
+/// memarg offset as hook argument, which is an i64 for 64-bit memories (and thus must be split)
+fn offset_to_consts(offset: u64, index_ty: ValType) -> Vec<Instr> {
+    match index_ty {
+        I64 => convert_i64_instr(Const(Val::I64(offset as i64)), I64),
+        _ => vec![Const(Val::I32(offset as i32))],
+    }
+}
+
+/// for exception handling: remember the innermost block at runtime (if enabled at all)
+fn set_innermost_block(innermost_block_tmp: Option<Idx<::wasm::ast::Local>>, begin: i64) -> Vec<Instr> {
+    match innermost_block_tmp {
+        Some(innermost_block_tmp) => vec![Const(Val::I32(begin as i32)), Local(SetLocal, innermost_block_tmp)],
+        None => vec![],
+    }
+}
+
 impl BlockStackElement {
     fn to_end_hook_args(&self, fidx: Idx<Function>) -> Vec<Instr> {
         match self {
@@ -750,7 +1383,24 @@ impl BlockStackElement {
             | BlockStackElement::Block { begin, end }
             | BlockStackElement::Loop { begin, end }
             | BlockStackElement::If { begin_if: begin, end, .. } => vec![fidx.to_const(), end.to_const(), begin.to_const()],
-            | BlockStackElement::Else { begin_else, begin_if, end } => vec![fidx.to_const(), end.to_const(), begin_else.to_const(), begin_if.to_const()]
+            | BlockStackElement::Else { begin_else, begin_if, end } => vec![fidx.to_const(), end.to_const(), begin_else.to_const(), begin_if.to_const()],
+            | BlockStackElement::Try { begin, end } => vec![fidx.to_const(), end.to_const(), begin.to_const()],
+            | BlockStackElement::Catch { begin_catch, begin_try, end }
+            | BlockStackElement::CatchAll { begin_catch, begin_try, end } => vec![fidx.to_const(), end.to_const(), begin_catch.to_const(), begin_try.to_const()],
+        }
+    }
+    /// as in the static info, i.e., -1 for the function
+    fn begin(&self) -> i64 {
+        use self::block_stack::BlockStackElement::*;
+        match self {
+            | Function { .. } => -1,
+            | Block { begin, .. }
+            | Loop { begin, .. }
+            | If { begin_if: begin, .. }
+            | Else { begin_else: begin, .. }
+            | Try { begin, .. }
+            | Catch { begin_catch: begin, .. }
+            | CatchAll { begin_catch: begin, .. } => begin.0 as i64
         }
     }
     fn end(&self) -> Idx<Instr> {
@@ -760,7 +1410,10 @@ impl BlockStackElement {
             | Block { end, .. }
             | Loop { end, .. }
             | If { end, .. }
-            | Else { end, .. } => *end
+            | Else { end, .. }
+            | Try { end, .. }
+            | Catch { end, .. }
+            | CatchAll { end, .. } => *end
         }
     }
 }
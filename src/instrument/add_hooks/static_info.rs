@@ -1,7 +1,10 @@
 use serde::{Serialize, Serializer};
+use serde::ser::SerializeSeq;
+use std::collections::BTreeMap;
 use super::block_stack::{BlockStack, BlockStackElement};
-use wasm::ast::{FunctionType, Idx, Label, ValType};
-use wasm::ast::highlevel::{Function, Instr, Module};
+use wasm::dwarf::{DebugInfo, SourceLocation};
+use wasm::ast::{FunctionType, Idx, Label, Local, ValType};
+use wasm::ast::highlevel::{CodeOffsets, Function, Instr, Module};
 
 /*
  * Structs for static information that is generated during instrumentation and output as JSON
@@ -10,29 +13,61 @@ use wasm::ast::highlevel::{Function, Instr, Module};
 
 #[derive(Serialize)]
 pub struct ModuleInfo {
+    pub name: Option<String>,
     pub functions: Vec<FunctionInfo>,
     #[serde(serialize_with = "serialize_types")]
     pub globals: Vec<ValType>,
     pub start: Option<Idx<Function>>,
-    #[serde(rename = "tableExportName")]
-    pub table_export_name: Option<String>,
+    // one per table, for resolving call_indirect targets at runtime
+    #[serde(rename = "tableExportNames")]
+    pub table_export_names: Vec<Option<String>>,
+    #[serde(rename = "memoryExportNames")]
+    pub memory_export_names: Vec<Option<String>>,
 //    #[serde(rename = "firstFunctionExportName")]
 //    pub first_function_export_name: Option<String>,
     #[serde(rename = "brTables")]
     pub br_tables: Vec<BrTableInfo>,
+    // from DWARF debug info (if present), FunctionInfo.source_locations index into this
+    #[serde(rename = "sourceFiles", skip_serializing_if = "Vec::is_empty")]
+    pub source_files: Vec<String>,
+    // only if the module was decoded with offsets, for mapping locations to byte offsets (e.g.,
+    // in stack traces) and back at runtime
+    #[serde(rename = "codeOffsets", skip_serializing_if = "Option::is_none")]
+    pub code_offsets: Option<CodeOffsets>,
 }
 
 impl<'a> From<&'a Module> for ModuleInfo {
     fn from(module: &Module) -> Self {
         ModuleInfo {
+            name: module.name.clone(),
             functions: module.functions.iter().map(Into::into).collect(),
             globals: module.globals.iter().map(|g| g.type_.0).collect(),
             start: module.start,
-            // if the module has no table, there cannot be a call_indirect, so this null will never be read from JS runtime
-            table_export_name: module.tables.get(0).and_then(|table| table.export.iter().cloned().next()),
+            // null only for tables that are not exported, which add_hooks() makes sure does not happen
+            table_export_names: module.tables.iter().map(|table| table.export.iter().cloned().next()).collect(),
+            memory_export_names: module.memories.iter().map(|memory| memory.export.iter().cloned().next()).collect(),
             // FIXME is this a valid workaround for wrong Firefox exported function .name property?
 //            first_function_export_name: module.functions.get(0).and_then(|func| func.export.iter().cloned().next()),
             br_tables: vec![],
+            source_files: vec![],
+            code_offsets: module.offsets.clone(),
+        }
+    }
+}
+
+impl ModuleInfo {
+    /// must be called before instrumentation, since the debug info refers to original instructions
+    pub fn add_debug_info(&mut self, debug_info: &DebugInfo) {
+        self.source_files = debug_info.files.clone();
+        for (&func, locations) in &debug_info.locations {
+            if let Some(function) = self.functions.get_mut(func.0) {
+                function.source_locations = locations.clone();
+            }
+        }
+        for (&func, name) in &debug_info.function_names {
+            if let Some(function) = self.functions.get_mut(func.0) {
+                function.source_name = Some(name.clone());
+            }
         }
     }
 }
@@ -48,6 +83,21 @@ pub struct FunctionInfo {
     #[serde(serialize_with = "serialize_types")]
     pub locals: Vec<ValType>,
     pub instr_count: usize,
+    // from the "name" custom section (if present), for printing symbols instead of indices
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub local_names: BTreeMap<Idx<Local>, String>,
+    // only for functions of modules that use exception handling: all blocks by their begin
+    // (together with their parent), for calling end hooks of blocks left by exceptions at runtime
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub blocks: BTreeMap<i64, (BlockStackElement, i64)>,
+    // from DWARF debug info (if present): only the first instruction of every run with the same
+    // source location, as [instr, file, line, column] or just [instr] for no location
+    #[serde(serialize_with = "serialize_source_locations", skip_serializing_if = "Vec::is_empty")]
+    pub source_locations: Vec<(Idx<Instr>, Option<SourceLocation>)>,
+    // source-level name, unlike name not mangled (e.g., for C++)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source_name: Option<String>,
 }
 
 impl<'a> From<&'a Function> for FunctionInfo {
@@ -58,6 +108,11 @@ impl<'a> From<&'a Function> for FunctionInfo {
             export: function.export.clone(),
             locals: function.code.iter().flat_map(|code| code.locals.clone()).collect(),
             instr_count: function.instr_count(),
+            name: function.name.clone(),
+            local_names: function.local_names.clone(),
+            blocks: BTreeMap::new(),
+            source_locations: vec![],
+            source_name: None,
         }
     }
 }
@@ -82,6 +137,17 @@ fn serialize_types<S>(tys: &[ValType], s: S) -> Result<S::Ok, S::Error> where S:
     s.serialize_str(&type_str)
 }
 
+fn serialize_source_locations<S>(locations: &[(Idx<Instr>, Option<SourceLocation>)], s: S) -> Result<S::Ok, S::Error> where S: Serializer {
+    let mut seq = s.serialize_seq(Some(locations.len()))?;
+    for &(instr, ref location) in locations {
+        match *location {
+            Some(ref location) => seq.serialize_element(&(instr, location.file, location.line, location.column))?,
+            None => seq.serialize_element(&(instr,))?,
+        }
+    }
+    seq.end()
+}
+
 #[derive(Serialize)]
 /// for resolving br_table instruction targets at runtime
 pub struct BrTableInfo {
@@ -131,6 +197,9 @@ impl Serialize for BlockStackElement {
             Loop { begin, end } => ("loop", begin, end).serialize(serializer),
             If { begin_if, end, .. } => ("if", begin_if, end).serialize(serializer),
             Else { begin_else, end, begin_if } => ("else", begin_else, end, begin_if).serialize(serializer),
+            Try { begin, end } => ("try", begin, end).serialize(serializer),
+            Catch { begin_catch, end, begin_try } => ("catch", begin_catch, end, begin_try).serialize(serializer),
+            CatchAll { begin_catch, end, begin_try } => ("catch_all", begin_catch, end, begin_try).serialize(serializer),
         }
     }
 }
\ No newline at end of file
@@ -1,4 +1,4 @@
-use wasm::ast::{BlockType, ValType, InstrType};
+use wasm::ast::{FunctionType, ValType, InstrType};
 use self::TypeStackElement::*;
 
 /*
@@ -17,7 +17,7 @@ pub struct TypeStack(Vec<TypeStackElement>);
 #[derive(Debug, PartialEq)]
 enum TypeStackElement {
     Val(ValType),
-    BlockBegin(BlockType),
+    BlockBegin(FunctionType),
     FunctionBegin,
 // TODO see add_hooks/mod.rs
 //    Unreachable,
@@ -52,14 +52,26 @@ impl TypeStack {
         }
     }
 
-    pub fn begin(&mut self, block_ty: BlockType) {
-        self.0.push(BlockBegin(block_ty))
+    /// pops and validates the block inputs (multi-value), which are then pushed again inside the block
+    pub fn begin(&mut self, block_ty: FunctionType) {
+        for &param_ty in block_ty.params.iter().rev() {
+            assert_eq!(param_ty, self.pop_val(), "block expected input type, but stack top was");
+        }
+        self.push_block(block_ty);
+    }
+
+    fn push_block(&mut self, block_ty: FunctionType) {
+        let param_tys = block_ty.params.clone();
+        self.0.push(BlockBegin(block_ty));
+        for param_ty in param_tys {
+            self.push_val(param_ty);
+        }
     }
 
     /// implicitly pops all types from the stack until the last block begin
-    /// pushes that blocks result type on the stack
-    /// returns the BlockType of that last block, or None if the last block was the whole function
-    pub fn end(&mut self) -> Option<BlockType> {
+    /// pushes that blocks result types on the stack
+    /// returns the type of that last block, or None if the last block was the whole function
+    pub fn end(&mut self) -> Option<FunctionType> {
         loop {
             match self.0.pop() {
                 None => panic!("could not end block, no block begin was found on type stack"),
@@ -67,8 +79,8 @@ impl TypeStack {
                 Some(BlockBegin(block_ty)) => {
                     // NOTE there is no validation that the stack is correct at the end of a block
                     // it is unclear to me how it exactly works with, e.g., br/return + drops
-                    if let BlockType(Some(ty)) = block_ty {
-                        self.push_val(ty);
+                    for &result_ty in block_ty.results.iter() {
+                        self.push_val(result_ty);
                     }
                     return Some(block_ty);
                 }
@@ -80,11 +92,22 @@ impl TypeStack {
     pub fn else_(&mut self) {
         // reuse code from end...
         let block_ty = self.end().expect("else cannot end a function");
-        // but undo pushing of block result (this will be done by the "real" end)
-        if let BlockType(Some(ty)) = block_ty {
-            assert_eq!(ty, self.pop_val());
+        // but undo pushing of block results (this will be done by the "real" end)
+        for &result_ty in block_ty.results.iter().rev() {
+            assert_eq!(result_ty, self.pop_val());
+        }
+        // the else branch gets the same inputs as the if branch (which were already popped at if)
+        self.push_block(block_ty);
+    }
+
+    /// same as else_, but a catch clause gets the values carried by the exception as inputs
+    /// (which are empty for catch_all)
+    pub fn catch(&mut self, tag_param_tys: &[ValType]) {
+        let block_ty = self.end().expect("catch cannot end a function");
+        for &result_ty in block_ty.results.iter().rev() {
+            assert_eq!(result_ty, self.pop_val());
         }
-        self.begin(block_ty);
+        self.push_block(FunctionType::new(tag_param_tys.to_vec(), block_ty.results));
     }
 
 // TODO see add_hooks/mod.rs
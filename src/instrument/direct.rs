@@ -1,41 +1,43 @@
-use wasm::ast::{FunctionType, Mutability, Val, ValType::*};
-use wasm::ast::highlevel::{Instr::*, Module, GlobalOp::*, NumericOp::*};
+use wasm::ast::{Mutability, Val, ValType::*};
+use wasm::ast::highlevel::{Instr::*, Module, NumericOp::*};
+use wasm::builder::ModuleBuilder;
 
 /* direct or "low-level" instrumentations, i.e., where the byte code is manually modified */
 
 pub fn add_empty_function(module: &mut Module) -> Option<String> {
-    module.add_function(
-        FunctionType::new(vec![], vec![]),
-        vec![],
-        vec![End]);
+    ModuleBuilder::new(module).function(&[], &[], |_| Ok(()))
+        .expect("empty function cannot be invalid");
     None
 }
 
 pub fn count_calls(module: &mut Module) -> Option<String> {
-    let counter = module.add_global(I32, Mutability::Mut, vec![Const(Val::I32(0)), End]);
-
-    let getter = module.add_function(
-        FunctionType::new(vec![], vec![I32]),
-        vec![],
-        vec![Global(GetGlobal, counter), End]);
-    module.function(getter).export = vec!["get_counter".into()];
-
-    let increment = module.add_function(
-        FunctionType::new(vec![], vec![]),
-        vec![],
-        vec![
-            Global(GetGlobal, counter),
-            Const(Val::I32(1)),
-            Numeric(I32Add),
-            Global(SetGlobal, counter),
-            End
-        ]);
+    let (getter, increment) = {
+        let mut builder = ModuleBuilder::new(module);
+        let counter = builder.global(Mutability::Mut, Val::I32(0));
+
+        let getter = builder.function(&[], &[I32], |f| {
+            f.global_get(counter)?;
+            Ok(())
+        }).expect("counter global was just added");
+        builder.export_function(getter, "get_counter")
+            .expect("could not export get_counter");
+
+        let increment = builder.function(&[], &[], |f| {
+            f.global_get(counter)?
+                .const_(Val::I32(1))?
+                .numeric(I32Add)?
+                .global_set(counter)?;
+            Ok(())
+        }).expect("counter global was just added");
+
+        (getter, increment)
+    };
 
     for (i, function) in module.functions() {
         // ignore the functions we added
         if i != getter && i != increment {
             function.modify_instr(|instr| match instr {
-                Call(..) | CallIndirect(..) => vec![Call(increment), instr],
+                Call(..) | CallIndirect(..) | ReturnCall(..) | ReturnCallIndirect(..) => vec![Call(increment), instr],
                 instr => vec![instr]
             })
         }
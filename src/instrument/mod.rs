@@ -1,6 +1,9 @@
 // direct modification of binary, very low-level
 pub mod direct;
 
+// source maps from the instrumented to the original binary (or its sources)
+pub mod source_map;
+
 // hook-style instrumentation, analysis happens in callbacks
 mod add_hooks;
-pub use self::add_hooks::add_hooks;
\ No newline at end of file
+pub use self::add_hooks::{add_hooks, add_hooks_with_debug_info, add_hooks_with_origins};
\ No newline at end of file
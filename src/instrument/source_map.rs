@@ -0,0 +1,229 @@
+use serde_json;
+use std::collections::HashMap;
+use std::io;
+use wasm::ast::Idx;
+use wasm::ast::highlevel::{CodeOffsets, Function, Instr, Module};
+use wasm::dwarf::DebugInfo;
+use wasm::wat::InstrOrigins;
+
+/*
+ * Source maps (version 3, see https://sourcemaps.info/spec.html) for instrumented binaries, such
+ * that browser devtools show the original code instead of offsets into the instrumented module.
+ * For WebAssembly, source maps have only a single "line" (0), and the generated "column" is the
+ * byte offset of an instruction in the module.
+ */
+
+/// where the instructions of the original module come from, i.e., the targets of the source map
+pub struct OriginalPositions {
+    sources: Vec<String>,
+    source_root: Option<String>,
+    positions: HashMap<Idx<Function>, Vec<Option<Position>>>,
+}
+
+/// source index, line, and column (all 0-based)
+type Position = (usize, u64, u64);
+
+impl OriginalPositions {
+    /// maps to the byte offsets of the original instructions in the original binary (as source)
+    pub fn from_offsets(source: String, offsets: &CodeOffsets) -> Self {
+        OriginalPositions {
+            sources: vec![source],
+            source_root: None,
+            positions: positions(offsets, |_, offset| Some((0, 0, offset as u64))),
+        }
+    }
+
+    /// maps to source files and lines from the DWARF debug info of the original module
+    pub fn from_debug_info(debug_info: &DebugInfo, offsets: &CodeOffsets) -> Self {
+        OriginalPositions {
+            sources: debug_info.files.clone(),
+            source_root: None,
+            // DWARF lines and columns are 1-based (column 0 is unknown)
+            positions: positions(offsets, |(func, instr), _| debug_info.location(func, instr.into())
+                .map(|location| (location.file, location.line - 1, location.column.saturating_sub(1)))),
+        }
+    }
+
+    /// composes with the source map of the original binary (e.g., from its sourceMappingURL)
+    pub fn from_source_map(json: &str, offsets: &CodeOffsets) -> io::Result<Self> {
+        let source_map: SourceMap = serde_json::from_str(json)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("invalid source map: {}", e)))?;
+        if source_map.version != 3 {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, format!("unsupported source map version {}", source_map.version)));
+        }
+        let segments = decode_mappings(&source_map.mappings)?;
+        Ok(OriginalPositions {
+            sources: source_map.sources,
+            source_root: source_map.source_root,
+            positions: positions(offsets, |_, offset| {
+                // the last segment at or before the offset covers it
+                let segments_before = match segments.binary_search_by_key(&(offset as u64), |&(column, _)| column) {
+                    Ok(i) => i + 1,
+                    Err(i) => i,
+                };
+                if segments_before == 0 {
+                    None
+                } else {
+                    segments[segments_before - 1].1
+                }
+            }),
+        })
+    }
+
+    /// source, line, and column (0-based) of an instruction of the original module
+    pub fn position(&self, function: Idx<Function>, instr: Idx<Instr>) -> Option<(&str, u64, u64)> {
+        let (source, line, column) = (*self.positions.get(&function)?.get(instr.0)?)?;
+        Some((self.sources.get(source)?, line, column))
+    }
+}
+
+/// the original position of every instruction with an original byte offset
+fn positions(offsets: &CodeOffsets, position: impl Fn((Idx<Function>, usize), usize) -> Option<Position>) -> HashMap<Idx<Function>, Vec<Option<Position>>> {
+    offsets.functions.iter()
+        .enumerate()
+        .filter_map(|(func, function)| function.as_ref().map(|function| {
+            let func = func.into();
+            (func, function.instrs.iter()
+                .enumerate()
+                .map(|(instr, &offset)| position((func, instr), offset))
+                .collect())
+        }))
+        .collect()
+}
+
+/// the source map (as JSON) for the instrumented module, given the binary it was encoded to
+pub fn source_map(module: &Module, binary: &[u8], instr_origins: &InstrOrigins, original: &OriginalPositions) -> io::Result<String> {
+    let offsets = CodeOffsets::from_module_bytes(binary)?;
+    // bodies are in the order of the highlevel functions, but imports are not (see CodeOffsets)
+    let bodies = module.functions.iter()
+        .enumerate()
+        .filter(|&(_, function)| function.code.is_some())
+        .map(|(func, _)| Idx::<Function>::from(func))
+        .zip(offsets.functions.iter().filter_map(Option::as_ref));
+
+    let mut mappings = String::new();
+    // all fields are relative to the previous segment
+    let (mut previous_offset, mut previous_source, mut previous_line, mut previous_column) = (0, 0, 0, 0);
+    // only add a segment when the position changes, unmapped instructions "end" the previous one
+    let mut previous_position = None;
+    for (func, function_offsets) in bodies {
+        let origins = instr_origins.get(&func);
+        let original_positions = original.positions.get(&func);
+        for (instr, &offset) in function_offsets.instrs.iter().enumerate() {
+            let position = origins.and_then(|origins| *origins.get(instr)?)
+                .and_then(|original_instr| *original_positions?.get(original_instr.0)?);
+            if position == previous_position {
+                continue;
+            }
+            previous_position = position;
+
+            if !mappings.is_empty() {
+                mappings.push(',');
+            }
+            vlq_encode(&mut mappings, offset as i64 - previous_offset);
+            previous_offset = offset as i64;
+            if let Some((source, line, column)) = position {
+                vlq_encode(&mut mappings, source as i64 - previous_source);
+                vlq_encode(&mut mappings, line as i64 - previous_line);
+                vlq_encode(&mut mappings, column as i64 - previous_column);
+                previous_source = source as i64;
+                previous_line = line as i64;
+                previous_column = column as i64;
+            }
+        }
+    }
+
+    serde_json::to_string(&SourceMap {
+        version: 3,
+        source_root: original.source_root.clone(),
+        sources: original.sources.clone(),
+        names: Vec::new(),
+        mappings,
+    }).map_err(|e| io::Error::new(io::ErrorKind::Other, e))
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SourceMap {
+    version: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    source_root: Option<String>,
+    sources: Vec<String>,
+    #[serde(default)]
+    names: Vec<String>,
+    mappings: String,
+}
+
+/// segments of the first line (WebAssembly has no others): generated column and original position
+/// (None for unmapped segments), sorted by column
+fn decode_mappings(mappings: &str) -> io::Result<Vec<(u64, Option<Position>)>> {
+    let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, format!("invalid source map mappings: {}", message));
+
+    let mut segments = Vec::new();
+    let (mut offset, mut source, mut line, mut column) = (0i64, 0i64, 0i64, 0i64);
+    for segment in mappings.split(';').next().unwrap_or("").split(',').filter(|segment| !segment.is_empty()) {
+        let fields = vlq_decode(segment).ok_or_else(|| invalid(segment))?;
+        offset += fields[0];
+        let position = if fields.len() >= 4 {
+            source += fields[1];
+            line += fields[2];
+            column += fields[3];
+            if source < 0 || line < 0 || column < 0 {
+                return Err(invalid("negative position"));
+            }
+            Some((source as usize, line as u64, column as u64))
+        } else {
+            None
+        };
+        if offset < 0 {
+            return Err(invalid("negative column"));
+        }
+        segments.push((offset as u64, position));
+    }
+    segments.sort_by_key(|&(offset, _)| offset);
+    Ok(segments)
+}
+
+const BASE64: &'static [u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// base64 VLQ: 5 bits per digit (least significant first), bit 6 = continuation, sign in lowest bit
+fn vlq_encode(out: &mut String, value: i64) {
+    let mut vlq = if value < 0 { ((-value as u64) << 1) | 1 } else { (value as u64) << 1 };
+    loop {
+        let mut digit = vlq & 0x1f;
+        vlq >>= 5;
+        if vlq != 0 {
+            digit |= 0x20;
+        }
+        out.push(BASE64[digit as usize] as char);
+        if vlq == 0 {
+            break;
+        }
+    }
+}
+
+/// None if the segment is malformed or empty
+fn vlq_decode(segment: &str) -> Option<Vec<i64>> {
+    let mut values = Vec::new();
+    let (mut vlq, mut shift) = (0u64, 0);
+    for byte in segment.bytes() {
+        let digit = BASE64.iter().position(|&base64_byte| base64_byte == byte)? as u64;
+        if shift > 60 {
+            return None;
+        }
+        vlq |= (digit & 0x1f) << shift;
+        if digit & 0x20 == 0 {
+            let magnitude = (vlq >> 1) as i64;
+            values.push(if vlq & 1 == 1 { -magnitude } else { magnitude });
+            vlq = 0;
+            shift = 0;
+        } else {
+            shift += 5;
+        }
+    }
+    if shift != 0 || values.is_empty() {
+        None
+    } else {
+        Some(values)
+    }
+}
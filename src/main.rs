@@ -1,10 +1,14 @@
 extern crate wasabi;
 extern crate wasm;
 
-use std::{env, fs, io, path::PathBuf};
+use std::{env, fs, io, path::{Path, PathBuf}};
 use wasabi::config::EnabledHooks;
-use wasabi::instrument::add_hooks;
-use wasm::ast::highlevel::Module;
+use wasabi::instrument::add_hooks_with_debug_info;
+use wasabi::instrument::source_map::{source_map, OriginalPositions};
+use wasm::WasmBinary;
+use wasm::ast::{highlevel::{CodeOffsets, Module}, lowlevel};
+use wasm::dwarf::DebugInfo;
+use wasm::wat::{module_to_wat, PrintOptions};
 
 // TODO use proper command-line option parser like clap, or structopt on top of it (https://docs.rs/structopt/0.2.10/structopt/)
 // TODO use failure crate and failure::Error type for error handling
@@ -15,6 +19,8 @@ fn main() {
 
 Usage: wasabi [options] <input_wasm_file> [<output_dir>]
 
+The input can also be given in text format (*.wat).
+
 Produces two files in <output_dir> (default: out/):
   - an instrumented version of the <input_wasm_file> and
   - a JavaScript file with static analysis information, (Wasabi-internal) low-level hooks, Wasabi runtime, and Wasabi loader.
@@ -22,7 +28,15 @@ Produces two files in <output_dir> (default: out/):
 Options:
   --hooks=<comma-separated list>     Instrument ONLY for the given hooks.
   --no-hooks=<comma-separated list>  Instrument for all BUT the given hooks.
-                                     (Default: Instrument for all hooks.)"#,
+                                     (Default: Instrument for all hooks.)
+  --emit-wat                         Also write the instrumented module in text format to
+                                     <output_dir>, with comments for the original instruction
+                                     indices.
+  --code-offsets                     Include the original byte offsets of all instructions in the
+                                     static information (for Wasabi.loc2offset/offset2loc).
+  --source-map                       Also write a source map for the instrumented module to
+                                     <output_dir>, which maps to the source map or DWARF debug
+                                     info of the input (if present) or else to the input binary."#,
                   error);
     }
 }
@@ -31,8 +45,17 @@ fn main_inner() -> io::Result<()> {
     let (options, args): (Vec<String>, Vec<String>) = env::args()
         // skip first argument (program name)
         .skip(1)
-        // --hooks and --no-hooks options
-        .partition(|arg| arg.starts_with("--hooks") || arg.starts_with("--no-hooks"));
+        // --hooks, --no-hooks, --emit-wat, --code-offsets, and --source-map options
+        .partition(|arg| arg.starts_with("--"));
+    let (emit_wat, options): (Vec<String>, Vec<String>) = options.into_iter()
+        .partition(|option| option == "--emit-wat");
+    let emit_wat = !emit_wat.is_empty();
+    let (code_offsets, options): (Vec<String>, Vec<String>) = options.into_iter()
+        .partition(|option| option == "--code-offsets");
+    let code_offsets = !code_offsets.is_empty();
+    let (emit_source_map, options): (Vec<String>, Vec<String>) = options.into_iter()
+        .partition(|option| option == "--source-map");
+    let emit_source_map = !emit_source_map.is_empty();
     let mut args = args.into_iter();
     let input_file = PathBuf::from(args.next().ok_or(io_err("expected at least one argument"))?);
     let output_dir = PathBuf::from(args.next().unwrap_or("out".to_string()));
@@ -43,6 +66,8 @@ fn main_inner() -> io::Result<()> {
     output_file_stem.push(input_filename_no_ext);
     let output_file_wasm = output_file_stem.with_extension("wasm");
     let output_file_js = output_file_stem.with_extension("wasabi.js");
+    let output_file_wat = output_file_stem.with_extension("wat");
+    let output_file_map = output_file_stem.with_extension("wasm.map");
 
     let enabled_hooks = match options.as_slice() {
         [] => EnabledHooks::all(),
@@ -54,15 +79,95 @@ fn main_inner() -> io::Result<()> {
     };
 
     // instrument Wasm and generate JavaScript
-    let mut module = Module::from_file(input_file.clone())?;
-    let js = add_hooks(&mut module, &enabled_hooks).unwrap();
+    let (mut module, debug_info, original_positions) = match input_file.extension().and_then(|ext| ext.to_str()) {
+        Some("wat") => {
+            if emit_source_map {
+                eprintln!("Warning: source maps are only supported for binary input modules");
+            }
+            (Module::from_wat_file(input_file.clone())?, None, None)
+        }
+        _ => {
+            // keep the bytes, DWARF refers to the original byte offsets of instructions
+            let bytes = fs::read(input_file.clone())?;
+            let mut module = Module::from_bytes_lazy(&bytes)?;
+            // finding the original offsets decodes all function bodies a second time, so only do
+            // it if DWARF line info, a source map, or --code-offsets actually need them
+            let has_debug_line = module.custom_section(".debug_line").is_some();
+            let (debug_info, original_positions) = if has_debug_line || emit_source_map || code_offsets {
+                let offsets = CodeOffsets::from_module_bytes(&bytes)?;
+                // debug info is optional, so do not fail on DWARF that we cannot parse
+                let debug_info = if has_debug_line {
+                    DebugInfo::from_module_bytes(&bytes, &offsets).unwrap_or_else(|e| {
+                        eprintln!("Warning: ignoring DWARF debug info: {}", e);
+                        None
+                    })
+                } else {
+                    None
+                };
+                let original_positions = if emit_source_map {
+                    Some(find_original_positions(&module, &input_file, &offsets, debug_info.as_ref()))
+                } else {
+                    None
+                };
+                if code_offsets {
+                    module.offsets = Some(offsets);
+                }
+                (debug_info, original_positions)
+            } else {
+                (None, None)
+            };
+            (module, debug_info, original_positions)
+        }
+    };
+    module.validate().map_err(|e| io_err(&format!("invalid input module: {}", e)))?;
+    let (js, instr_origins) = add_hooks_with_debug_info(&mut module, &enabled_hooks, debug_info.as_ref()).unwrap();
+    module.validate().map_err(|e| io::Error::new(io::ErrorKind::Other, format!("instrumented module is invalid (bug in Wasabi): {}", e)))?;
 
     // write output files
     fs::create_dir_all(output_dir)?;
-    module.to_file(output_file_wasm)?;
+    if emit_wat {
+        fs::write(output_file_wat, module_to_wat(&module, PrintOptions {
+            instr_idx_comments: true,
+            instr_origins: Some(&instr_origins),
+        }))?;
+    }
+    match original_positions {
+        Some(original_positions) => {
+            // instrumented code is in a different place, so the source map must be generated from the binary
+            let mut source_map_url = Vec::new();
+            output_file_map.file_name().unwrap().to_string_lossy().to_string().encode(&mut source_map_url)?;
+            module.set_custom_section("sourceMappingURL", &source_map_url);
+            let mut binary = Vec::new();
+            lowlevel::Module::from(module.clone()).encode(&mut binary)?;
+            fs::write(output_file_map, source_map(&module, &binary, &instr_origins, &original_positions)?)?;
+            fs::write(output_file_wasm, binary)?;
+        }
+        None => {
+            module.to_file(output_file_wasm)?;
+        }
+    }
     fs::write(output_file_js, js)
 }
 
+/// targets of the source map: the existing source map of the input, else its DWARF debug info,
+/// else the input binary itself
+fn find_original_positions(module: &Module, input_file: &Path, offsets: &CodeOffsets, debug_info: Option<&DebugInfo>) -> OriginalPositions {
+    if let Some(mut source_map_url) = module.custom_section("sourceMappingURL") {
+        // only file paths (relative to the input file) are supported as URLs
+        let input_source_map = String::decode(&mut source_map_url)
+            .and_then(|url| fs::read_to_string(input_file.with_file_name(url)))
+            .and_then(|json| OriginalPositions::from_source_map(&json, offsets));
+        match input_source_map {
+            Ok(original_positions) => return original_positions,
+            Err(e) => eprintln!("Warning: ignoring source map of the input module: {}", e),
+        }
+    }
+    match debug_info {
+        Some(debug_info) => OriginalPositions::from_debug_info(debug_info, offsets),
+        None => OriginalPositions::from_offsets(input_file.file_name().unwrap().to_string_lossy().to_string(), offsets),
+    }
+}
+
 fn io_err(str: &str) -> io::Error {
     io::Error::new(io::ErrorKind::InvalidInput, str.to_string())
 }
\ No newline at end of file
@@ -1,6 +1,12 @@
-use instrument::{add_hooks, direct::*};
+use config::EnabledHooks;
+use instrument::{add_hooks, add_hooks_with_origins, direct::*};
+use instrument::source_map::{source_map, OriginalPositions};
 use test_utilities::*;
-use wasm::ast::highlevel::Module;
+use wasm::WasmBinary;
+use wasm::ast::{Idx, ValType};
+use wasm::ast::ValType::*;
+use wasm::ast::highlevel::{CodeOffsets, Function, Module};
+use wasm::ast::lowlevel;
 
 const TEST_INPUTS: &'static str = "tests/inputs";
 
@@ -16,7 +22,144 @@ fn count_calls_instrumentation_produces_valid_wasm() {
 
 #[test]
 fn add_hooks_instrumentation_produces_valid_wasm() {
-    test_instrument(add_hooks, "add-hooks");
+    test_instrument(|module| add_hooks(module, &EnabledHooks::all()), "add-hooks");
+}
+
+/// for each proposal: a fixture that uses it, one of the low-level hooks that instrumenting it must
+/// add (with its arguments after the location, i64 split into two i32), and the high-level hook it calls
+const PROPOSAL_HOOKS: &[(&str, &str, &[ValType], &str)] = &[
+    ("multi-value.wat", "call_post_iI", &[I32, I32, I32], "call_post"),
+    ("multi-value.wat", "return_iI", &[I32, I32, I32], "return_"),
+    ("sign-ext-sat-conversions.wat", "i64.extend8_s", &[I32, I32, I32, I32], "unary"),
+    ("sign-ext-sat-conversions.wat", "i64.trunc_u:sat/f64", &[F64, I32, I32], "unary"),
+    ("bulk-memory.wat", "memory.init", &[I32, I32, I32, I32, I32], "memory_init"),
+    ("reference-types.wat", "table.grow_r", &[I32, FuncRef, I32, I32], "table_grow"),
+    ("reference-types.wat", "ref.is_null_x", &[ExternRef, I32], "ref_is_null"),
+    ("multi-memory.wat", "i64.store8", &[I32, I32, I32, I32, I32, I32], "store"),
+    ("simd.wat", "i8x16.shuffle", &[I32; 16], "shuffle"),
+    ("simd.wat", "f64x2.extract_lane", &[I32, I32, I32, I32, I32, F64], "extract_lane"),
+    ("atomics.wat", "memory.atomic.wait64", &[I32, I32, I32, I32, I32, I32, I32, I32, I32], "atomic_wait"),
+    ("exceptions.wat", "throw_iI", &[I32, I32, I32, I32], "throw_"),
+    ("exceptions.wat", "catch_iI", &[I32, I32, I32, I32], "catch_"),
+    ("tail-calls.wat", "return_call_II", &[I32, I32, I32, I32, I32], "return_call"),
+    ("memory64.wat", "memory.grow_I", &[I32, I32, I32, I32, I32], "memory_grow"),
+];
+
+#[test]
+fn add_hooks_instruments_proposal_instructions() {
+    let runtime_hook_names = include_str!("../lib/runtime.js").split("HOOK_NAMES: [").nth(1).unwrap().split(']').next().unwrap();
+    for &(fixture, hook, args, highlevel_hook) in PROPOSAL_HOOKS {
+        let path = format!("{}/small/{}", TEST_INPUTS, fixture);
+        let mut module = Module::from_wat_file(&path).unwrap();
+        let js = add_hooks(&mut module, &EnabledHooks::all()).unwrap();
+
+        let function = module.functions.iter()
+            .find(|function| function.import == Some(("__wasabi_hooks".to_string(), hook.to_string())))
+            .expect(&format!("no hook {} in instrumented '{}'", hook, path));
+        // the first two arguments are the location (function and instruction index)
+        assert_eq!(&function.type_.params[2..], args, "wrong arguments of hook {} in '{}'", hook, path);
+
+        let hook_js = js.split(&format!("\"{}\": function (func, instr", hook)).nth(1)
+            .expect(&format!("no JavaScript for hook {} in '{}'", hook, path));
+        assert!(hook_js.lines().nth(1).unwrap().trim_left().starts_with(&format!("Wasabi.analysis.{}(", highlevel_hook)),
+                "hook {} in '{}' does not call high-level hook {}", hook, path, highlevel_hook);
+        assert!(runtime_hook_names.contains(&format!("\"{}\"", highlevel_hook)), "{} is not in Wasabi.HOOK_NAMES", highlevel_hook);
+    }
+}
+
+#[test]
+fn unary_hook_gets_sign_extension_and_sat_conversion_values() {
+    let calls = run_analysis("sign-ext-sat-conversions", r#"(module
+        (func (export "main") (param i32 f64) (result i32)
+            (drop (i64.trunc_sat_f64_s (local.get 1)))
+            (i32.trunc_sat_f64_u (local.get 1))
+            (i32.extend8_s (local.get 0))
+            i32.add))"#, "0x80, -1e30", "unary");
+    assert_eq!(calls, vec![
+        r#"["i64.trunc_s:sat/f64",-1e+30,"-9223372036854775808"]"#,
+        r#"["i32.trunc_u:sat/f64",-1e+30,0]"#,
+        r#"["i32.extend8_s",128,-128]"#,
+    ]);
+}
+
+/// instrument a module in text format with all hooks, run its exported "main" function with node
+/// (needs to be on $PATH) and return the arguments (without location) of all calls to one
+/// high-level hook, as JSON arrays (with Long's as strings)
+fn run_analysis(name: &str, wat: &str, main_args: &str, highlevel_hook: &str) -> Vec<String> {
+    use std::process::Command;
+
+    let mut module = wasm::wat::parse_module(wat).unwrap();
+    let javascript = add_hooks(&mut module, &EnabledHooks::all()).unwrap();
+    let output_path = output_file(format!("{}/{}.wasm", TEST_INPUTS, name), "runtime").unwrap();
+    module.to_file(&output_path).unwrap();
+    let javascript_path = output_path.with_extension("wasabi.js");
+    ::std::fs::write(&javascript_path, javascript).unwrap();
+
+    // the generated JavaScript is meant for the browser, so run it as a script in the global scope
+    // (and not with node -e, where long.js would only export Long via the global module object)
+    let driver_path = output_path.with_extension("driver.js");
+    let driver = format!(r#"
+        const fs = require("fs");
+        require("vm").runInThisContext(fs.readFileSync({:?}, "utf8"));
+        const calls = [];
+        Wasabi.analysis.{} = (location, ...args) => calls.push(JSON.stringify(args.map(arg => arg instanceof Long ? arg.toString() : arg)));
+        WebAssembly.instantiate(fs.readFileSync({:?})).then(({{instance}}) => {{
+            instance.exports.main({});
+            console.log(JSON.stringify(calls));
+        }});"#, javascript_path, highlevel_hook, output_path, main_args);
+    ::std::fs::write(&driver_path, driver).unwrap();
+    let output = Command::new("node").arg(&driver_path).output().unwrap();
+    assert!(output.status.success(), "running '{}' failed:\n{}", driver_path.display(), String::from_utf8_lossy(&output.stderr));
+
+    // other output are, e.g., debug messages about hooks not provided by the analysis
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    ::serde_json::from_str(stdout.lines().last().unwrap()).unwrap()
+}
+
+#[test]
+fn source_map_maps_instrumented_instructions_to_original_offsets() {
+    for path in wasm_files(TEST_INPUTS).unwrap() {
+        let bytes = ::std::fs::read(&path).unwrap();
+        let mut module = Module::from_bytes_with_offsets(&bytes).unwrap();
+        let original_offsets = module.offsets.clone().unwrap();
+        let (_, instr_origins) = add_hooks_with_origins(&mut module, &EnabledHooks::all()).unwrap();
+
+        let mut output = Vec::new();
+        lowlevel::Module::from(module.clone()).encode(&mut output).unwrap();
+        let original = OriginalPositions::from_offsets("original.wasm".into(), &original_offsets);
+        let json = source_map(&module, &output, &instr_origins, &original).unwrap();
+
+        // read the source map again, which gives the positions of the instrumented instructions
+        let output_offsets = CodeOffsets::from_module_bytes(&output).unwrap();
+        let instrumented = OriginalPositions::from_source_map(&json, &output_offsets).unwrap();
+        // bodies are in the order of the highlevel functions, imports come first in the binary
+        let functions = module.functions.iter().enumerate()
+            .filter(|&(_, function)| function.code.is_some())
+            .map(|(func, _)| func);
+        let output_functions = output_offsets.functions.iter().enumerate()
+            .filter(|&(_, function)| function.is_some())
+            .map(|(func, _)| func);
+        for (func, output_func) in functions.zip(output_functions) {
+            let func: Idx<Function> = func.into();
+            for (instr, &origin) in instr_origins[&func].iter().enumerate() {
+                let expected = origin.and_then(|origin| original_offsets.offset(func, origin))
+                    .map(|offset| ("original.wasm", 0, offset as u64));
+                assert_eq!(instrumented.position(output_func.into(), instr.into()), expected,
+                           "wrong position of instruction #{} of function #{} in '{}'", instr, func.0, path.display());
+            }
+        }
+    }
+}
+
+#[test]
+fn add_hooks_removes_source_map_url_of_the_original_module() {
+    let mut module = wasm::wat::parse_module("(module (func (export \"main\") nop))").unwrap();
+    let mut url = Vec::new();
+    "original.wasm.map".to_string().encode(&mut url).unwrap();
+    module.set_custom_section("sourceMappingURL", &url);
+
+    add_hooks(&mut module, &EnabledHooks::all()).unwrap();
+    assert_eq!(module.custom_section("sourceMappingURL"), None);
 }
 
 /// utility function
@@ -28,6 +171,10 @@ fn test_instrument(instrument: impl Fn(&mut Module) -> Option<String>, instrumen
         let output_path = output_file(&path, instrument_name).unwrap();
         module.to_file(&output_path).unwrap();
 
+        let module = Module::from_file(&output_path).unwrap();
+        if let Err(error) = module.validate() {
+            panic!("could not instrument wasm file '{}' with {}: {}", path.display(), instrument_name, error);
+        }
         wasm_validate(&output_path)
             .expect(&format!("could not instrument wasm file '{}' with {}", path.display(), instrument_name));
 
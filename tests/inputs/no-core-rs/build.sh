@@ -0,0 +1,7 @@
+#!/bin/sh
+# unlike for the other inputs, the binaries are checked in (and not in build/), such that the tests
+# always have real compiler output with padded LEB128s etc., even without a wasm32 toolchain
+# no_core, because the wasm32 standard library is often not installed, -O to reduce size
+rustc +nightly --target=wasm32-unknown-unknown --crate-type=cdylib -O program.rs -o program.wasm
+# memory is imported before all functions, i.e., imports are not grouped by kind
+rustc +nightly --target=wasm32-unknown-unknown --crate-type=cdylib -O program.rs -o program-imported-memory.wasm -C link-arg=--import-memory
@@ -0,0 +1,110 @@
+// Small Rust program without core (so it builds without the wasm32 standard library), for which
+// rustc + wasm-ld produce a realistic binary: several imports and function types, a name section,
+// a table for indirect calls, data segments, and padded LEB128 indices in the code.
+#![feature(no_core, lang_items)]
+#![no_core]
+#![allow(internal_features)]
+
+#[lang = "pointee_sized"] pub trait PointeeSized {}
+#[lang = "meta_sized"] pub trait MetaSized: PointeeSized {}
+#[lang = "sized"] pub trait Sized: MetaSized {}
+#[lang = "copy"] pub trait Copy {}
+#[lang = "legacy_receiver"] pub trait LegacyReceiver {}
+impl<T: ?Sized> LegacyReceiver for &T {}
+// the compiler generates the actual drop glue, like for core::ptr::drop_in_place
+#[allow(unconditional_recursion)]
+#[lang = "drop_glue"] pub unsafe fn drop_in_place<T: ?Sized>(to_drop: *mut T) { drop_in_place(to_drop) }
+#[lang = "sync"] pub unsafe trait Sync {}
+impl Copy for i32 {}
+impl Copy for i64 {}
+impl Copy for f64 {}
+impl Copy for bool {}
+unsafe impl Sync for i32 {}
+unsafe impl Sync for [i32; 4] {}
+
+#[lang = "add"] pub trait Add<Rhs = Self> { type Output; fn add(self, rhs: Rhs) -> Self::Output; }
+#[lang = "sub"] pub trait Sub<Rhs = Self> { type Output; fn sub(self, rhs: Rhs) -> Self::Output; }
+#[lang = "mul"] pub trait Mul<Rhs = Self> { type Output; fn mul(self, rhs: Rhs) -> Self::Output; }
+#[lang = "eq"] pub trait PartialEq<Rhs = Self> { fn eq(&self, other: &Rhs) -> bool; fn ne(&self, other: &Rhs) -> bool; }
+#[lang = "partial_ord"] pub trait PartialOrd<Rhs = Self>: PartialEq<Rhs> {
+    fn lt(&self, other: &Rhs) -> bool; fn le(&self, other: &Rhs) -> bool;
+    fn gt(&self, other: &Rhs) -> bool; fn ge(&self, other: &Rhs) -> bool;
+}
+
+macro_rules! arith {
+    ($($t:ty)*) => ($(
+        impl Add for $t { type Output = $t; fn add(self, rhs: $t) -> $t { self + rhs } }
+        impl Sub for $t { type Output = $t; fn sub(self, rhs: $t) -> $t { self - rhs } }
+        impl Mul for $t { type Output = $t; fn mul(self, rhs: $t) -> $t { self * rhs } }
+        impl PartialEq for $t { fn eq(&self, other: &$t) -> bool { *self == *other } fn ne(&self, other: &$t) -> bool { *self != *other } }
+        impl PartialOrd for $t {
+            fn lt(&self, other: &$t) -> bool { *self < *other } fn le(&self, other: &$t) -> bool { *self <= *other }
+            fn gt(&self, other: &$t) -> bool { *self > *other } fn ge(&self, other: &$t) -> bool { *self >= *other }
+        }
+    )*)
+}
+arith!(i32 i64 f64);
+
+#[link(wasm_import_module = "env")]
+extern "C" {
+    fn log_i32(value: i32);
+    fn log_f64(value: f64);
+    fn now() -> f64;
+}
+
+static TABLE: [i32; 4] = [2, 3, 5, 7];
+static mut COUNTER: i32 = 0;
+
+#[no_mangle]
+#[inline(never)]
+pub extern "C" fn fib(n: i32) -> i32 {
+    if n < 2 { n } else { fib(n - 1) + fib(n - 2) }
+}
+
+#[no_mangle]
+#[inline(never)]
+pub extern "C" fn square(n: i32) -> i32 {
+    n * n
+}
+
+#[no_mangle]
+#[inline(never)]
+pub extern "C" fn sum_i64(a: i64, b: i64, c: i64) -> i64 {
+    a + b * c
+}
+
+#[no_mangle]
+#[inline(never)]
+pub extern "C" fn select(which: i32) -> extern "C" fn(i32) -> i32 {
+    if which == 0 { fib } else { square }
+}
+
+#[no_mangle]
+#[inline(never)]
+pub extern "C" fn apply(f: extern "C" fn(i32) -> i32, x: i32) -> i32 {
+    unsafe { COUNTER = COUNTER + 1; }
+    f(x)
+}
+
+#[no_mangle]
+pub extern "C" fn table_sum() -> i32 {
+    let mut sum = 0;
+    let mut i = 0;
+    while i < 4 {
+        sum = sum + unsafe { *((&TABLE as *const [i32; 4] as i32 + i * 4) as *const i32) };
+        i = i + 1;
+    }
+    sum
+}
+
+#[no_mangle]
+pub extern "C" fn main() -> i32 {
+    let start = unsafe { now() };
+    let result = apply(select(0), 10) + apply(select(1), 3) + table_sum();
+    unsafe {
+        log_i32(result);
+        log_i32(sum_i64(1, 2, 3) as i32);
+        log_f64(now() - start);
+        COUNTER
+    }
+}
@@ -0,0 +1,27 @@
+(module
+    ;; shared memories must have a maximum size
+    (memory $shared (export "memory") 1 4 shared)
+    (global $lock (mut i32) (i32.const 0))
+    (func $acquire (param $addr i32)
+        (block $done
+            (loop $retry
+                ;; cmpxchg returns the old value, 0 means the lock was free
+                (br_if $done (i32.eqz (i32.atomic.rmw.cmpxchg (local.get $addr) (i32.const 0) (i32.const 1))))
+                (drop (memory.atomic.wait32 (local.get $addr) (i32.const 1) (i64.const -1)))
+                (br $retry))))
+    (func $release (param $addr i32)
+        (i32.atomic.store (local.get $addr) (i32.const 0))
+        (drop (memory.atomic.notify (local.get $addr) (i32.const 1))))
+    (func (export "main") (param $addr i32) (result i64)
+        (call $acquire (local.get $addr))
+        (drop (i32.atomic.rmw.add offset=4 (local.get $addr) (i32.const 1)))
+        (drop (i64.atomic.rmw8.xchg_u (local.get $addr) (i64.const 255)))
+        (drop (i32.atomic.rmw16.sub_u (local.get $addr) (i32.const 2)))
+        (drop (i64.atomic.rmw32.cmpxchg_u offset=8 (local.get $addr) (i64.const 0) (i64.const 42)))
+        (i64.atomic.store8 (local.get $addr) (i64.const 7))
+        (drop (i32.atomic.load16_u (local.get $addr)))
+        (drop (memory.atomic.wait64 offset=8 (local.get $addr) (i64.const 42) (i64.const 1000)))
+        atomic.fence
+        (call $release (local.get $addr))
+        (i64.atomic.load offset=8 (local.get $addr)))
+)
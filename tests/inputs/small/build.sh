@@ -1,5 +1,7 @@
 #!/bin/sh
-for wat in *.wat; do wat2wasm "$wat"; done
+# proposals used by the test inputs, same as test_utilities::WABT_FEATURES
+FEATURES="--enable-sign-extension --enable-saturating-float-to-int --enable-multi-value --enable-bulk-memory --enable-reference-types --enable-simd --enable-threads --enable-exceptions --enable-tail-call --enable-multi-memory --enable-memory64"
+for wat in *.wat; do wat2wasm $FEATURES "$wat"; done
 rm -rf build/
 mkdir build
 mv *.wasm build/
\ No newline at end of file
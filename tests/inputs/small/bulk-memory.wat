@@ -0,0 +1,38 @@
+(module
+    (table 4 funcref)
+    (memory 1)
+    (func $f (result i32)
+        i32.const 42
+    )
+    ;; active, passive, and declarative segments
+    (elem (i32.const 0) $f)
+    (elem $passive-elem func $f $f)
+    (elem declare func $f)
+    (data (i32.const 0) "active")
+    (data $passive-data "passive")
+    (func (export "main") (param i32)
+        ;; memory.init + data.drop
+        i32.const 16
+        i32.const 0
+        i32.const 7
+        memory.init $passive-data
+        data.drop $passive-data
+        ;; memory.copy (destination, source, length)
+        i32.const 32
+        i32.const 16
+        i32.const 7
+        memory.copy
+        ;; memory.fill (destination, value, length)
+        local.get 0
+        i32.const 255
+        i32.const 8
+        memory.fill
+        ;; table.init + elem.drop + table.copy
+        i32.const 1
+        i32.const 0
+        i32.const 2
+        table.init $passive-elem
+        elem.drop $passive-elem
+        (table.copy (i32.const 3) (i32.const 0) (i32.const 1))
+    )
+)
@@ -0,0 +1,38 @@
+(module
+    (tag $imported (import "env" "error") (param i32))
+    (tag $pair (export "pair") (param i32 i64))
+    (tag $empty)
+    (func $thrower (param $x i32)
+        (if (i32.eqz (local.get $x))
+            (then (throw $pair (i32.const 1) (i64.const 2))))
+        (throw $imported (local.get $x))
+    )
+    (func (export "main") (param $x i32) (result i32)
+        ;; folded try with typed catch clauses and catch_all
+        (try $outer (result i32)
+            (do
+                (block $b
+                    (call $thrower (local.get $x))
+                    (br $b))
+                (i32.const 0))
+            (catch $imported
+                i32.const 1
+                i32.add)
+            (catch $pair
+                drop)
+            (catch_all
+                (i32.const -1)))
+        ;; flat syntax, with rethrow from a nested catch and delegate
+        try $t
+            try
+                (throw $empty)
+            delegate $t
+        catch $empty
+            try
+                (throw $empty)
+            catch_all
+                rethrow 1
+            end
+        end
+    )
+)
@@ -0,0 +1,17 @@
+(module
+    ;; memory 0 is 64-bit, memory 1 is a regular 32-bit memory
+    (memory $mem64 (export "memory") i64 1 65536)
+    (memory $mem32 1)
+    (data (memory $mem64) (i64.const 0x1_0000) "memory64")
+    (func (export "main") (param $addr i64) (result i64)
+        ;; addresses and offsets into a 64-bit memory are i64, offsets can exceed 32 bits
+        (i64.store offset=4294967296 (local.get $addr) (i64.load offset=8 (local.get $addr)))
+        (i32.store8 $mem64 (i64.const 16) (i32.load8_u $mem32 (i32.const 16)))
+        ;; size and grow take and return i64 page counts
+        (drop (memory.grow $mem64 (memory.size $mem64)))
+        ;; copying between a 64-bit and a 32-bit memory uses the smaller (i32) type for the length
+        (memory.copy $mem64 $mem32 (i64.const 0) (i32.const 8) (i32.const 8))
+        (memory.fill $mem64 (i64.const 0) (i32.const 0) (i64.const 4))
+        (i64.load (local.get $addr))
+    )
+)
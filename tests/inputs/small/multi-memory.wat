@@ -0,0 +1,17 @@
+(module
+    ;; imported memory is memory 0, the defined one memory 1
+    (import "env" "memory" (memory $imported 1))
+    (memory $second (export "second") 1 2)
+    (data (memory $second) (i32.const 8) "second memory")
+    (func (export "main") (param $addr i32) (result i32)
+        ;; loads and stores with explicit memory index
+        (i32.store $second offset=4 (local.get $addr) (i32.load $imported (local.get $addr)))
+        (i64.store8 1 (i32.const 0) (i64.load16_u $second align=1 (i32.const 8)))
+        ;; size and grow of the second memory
+        (drop (memory.grow $second (memory.size $second)))
+        ;; copy from the second to the first memory, then fill the second one
+        (memory.copy $imported $second (i32.const 0) (i32.const 8) (i32.const 13))
+        (memory.fill $second (i32.const 0) (i32.const 0) (i32.const 4))
+        (i32.load8_u (local.get $addr))
+    )
+)
@@ -0,0 +1,58 @@
+(module
+    (type $pair (func (param i32) (result i32 i64)))
+    ;; function with multiple results
+    (func $swap (param i64 i32) (result i32 i64)
+        local.get 1
+        local.get 0
+    )
+    (func $f (export "f") (param i32) (result i64)
+        (local i64)
+        ;; call with multiple results
+        i64.const 42
+        local.get 0
+        call $swap
+        ;; block with parameters and multiple results
+        block (param i32 i64) (result i64 i32)
+            local.set 1
+            drop
+            local.get 1
+            i32.const 1
+        end
+        drop
+        ;; block type given by type index
+        i32.const 3
+        block (type $pair)
+            i64.const 7
+        end
+        drop
+        drop
+        ;; loop with parameter, branch to loop takes the parameter
+        i32.const 10
+        loop $l (param i32) (result i32)
+            i32.const 1
+            i32.sub
+            local.tee 0
+            local.get 0
+            br_if $l
+        end
+        ;; if with parameter and without else
+        i32.const 1
+        if (param i32) (result i32)
+            i32.const 2
+            i32.add
+        end
+        ;; if with parameter and else
+        i32.const 0
+        if (param i32) (result i64 i32)
+            ;; branch with multiple values
+            i64.extend_u/i32
+            i32.const 1
+            br 0
+        else
+            i64.extend_u/i32
+            i32.const 0
+        end
+        drop
+        return
+    )
+)
@@ -0,0 +1,35 @@
+(module
+    (type $i32->i32 (func (param i32) (result i32)))
+    ;; two tables with different element types
+    (table $funcs 2 funcref)
+    (table $externs (export "externs") 1 externref)
+    (global $null-func funcref (ref.null func))
+    (global $g (mut externref) (ref.null extern))
+    (func $double (type $i32->i32)
+        local.get 0
+        i32.const 2
+        i32.mul
+    )
+    (func $inc (type $i32->i32)
+        local.get 0
+        i32.const 1
+        i32.add
+    )
+    ;; element segment with expressions instead of function indices
+    (elem (table $funcs) (i32.const 0) funcref (ref.func $double) (ref.null func))
+    (elem $passive funcref (item ref.func $inc))
+    (func (export "main") (param $ref externref) (param $i i32) (result i32)
+        ;; table.set + table.get on the externref table
+        (table.set $externs (i32.const 0) (local.get $ref))
+        (global.set $g (table.get $externs (i32.const 0)))
+        ;; ref.is_null + typed select
+        (drop (select (result externref) (global.get $g) (ref.null extern) (ref.is_null (local.get $ref))))
+        ;; table.grow, table.size, table.fill on the funcref table
+        (drop (table.grow $funcs (ref.func $inc) (i32.const 2)))
+        (drop (table.size $funcs))
+        (table.fill $funcs (i32.const 1) (ref.null func) (i32.const 1))
+        (table.init $funcs $passive (i32.const 1) (i32.const 0) (i32.const 1))
+        ;; call_indirect with explicit table
+        (call_indirect $funcs (type $i32->i32) (local.get $i) (i32.const 0))
+    )
+)
@@ -0,0 +1,23 @@
+(module
+    (func $f (export "f") (param f64) (result i64)
+        ;; sign-extension operators
+        i32.const 255
+        i32.extend8_s
+        i32.extend16_s
+        i64.extend_i32_s
+        i64.extend8_s
+        i64.extend16_s
+        i64.extend32_s
+        drop
+        ;; non-trapping float-to-int conversions
+        local.get 0
+        i32.trunc_sat_f64_s
+        drop
+        local.get 0
+        f32.demote_f64
+        i32.trunc_sat_f32_u
+        drop
+        local.get 0
+        i64.trunc_sat_f64_u
+    )
+)
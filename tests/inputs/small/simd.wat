@@ -0,0 +1,31 @@
+(module
+    (memory 1)
+    (global $v (mut v128) (v128.const i32x4 1 2 3 4))
+    (func $add-lanes (param $a v128) (param $b v128) (result v128)
+        (i32x4.add (local.get $a) (local.get $b))
+    )
+    (func (export "main") (param $addr i32) (result i32)
+        (local $v v128)
+        ;; v128 load and store, extending and splat loads
+        (local.set $v (v128.load offset=16 (local.get $addr)))
+        (v128.store (i32.const 32) (i16x8.add (local.get $v) (v128.load8x8_s (local.get $addr))))
+        (drop (v128.load32_splat align=1 (local.get $addr)))
+        ;; lane loads and stores
+        (local.set $v (v128.load16_lane 3 (local.get $addr) (local.get $v)))
+        (v128.store64_lane offset=8 1 (local.get $addr) (local.get $v))
+        ;; constants with different shapes, shuffle, swizzle, bitselect
+        (local.set $v (i8x16.shuffle 0 17 2 19 4 21 6 23 8 25 10 27 12 29 14 31
+            (v128.const i8x16 -1 2 3 4 5 6 7 8 9 10 11 12 13 14 15 255)
+            (v128.const f32x4 1.5 -0 nan inf)))
+        (local.set $v (v128.bitselect (local.get $v) (global.get $v) (i8x16.swizzle (local.get $v) (local.get $v))))
+        ;; splat, lane access, shifts, comparisons, conversions
+        (local.set $v (f64x2.replace_lane 1 (f64x2.splat (f64.const 2.5)) (f64x2.extract_lane 0 (local.get $v))))
+        (local.set $v (i64x2.shl (i64x2.splat (i64.const -1)) (i32.const 3)))
+        (local.set $v (f32x4.convert_i32x4_u (i32x4.lt_s (local.get $v) (global.get $v))))
+        (global.set $v (call $add-lanes (local.get $v) (v128.const i64x2 0x7fffffffffffffff -2)))
+        ;; reductions to i32
+        (i32.add
+            (i8x16.extract_lane_u 15 (local.get $v))
+            (i32.add (v128.any_true (local.get $v)) (i32x4.bitmask (global.get $v))))
+    )
+)
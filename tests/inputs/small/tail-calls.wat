@@ -0,0 +1,18 @@
+(module
+    (type $unary (func (param i64) (result i64)))
+    (table 1 funcref)
+    (elem (i32.const 0) $fac-acc)
+    (func $fac-acc (param $n i64) (param $acc i64) (result i64)
+        (if (result i64) (i64.eqz (local.get $n))
+            (then (local.get $acc))
+            (else
+                (return_call $fac-acc
+                    (i64.sub (local.get $n) (i64.const 1))
+                    (i64.mul (local.get $n) (local.get $acc))))))
+    (func $fac (export "fac") (type $unary)
+        (block (result i64)
+            (return_call $fac-acc (local.get 0) (i64.const 1))))
+    (func (export "fac-indirect") (param i64) (result i64)
+        (return_call_indirect (param i64 i64) (result i64)
+            (local.get 0) (i64.const 1) (i32.const 0)))
+)
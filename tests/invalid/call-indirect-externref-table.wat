@@ -0,0 +1,5 @@
+(module
+  (table 1 externref)
+  (func (call_indirect (i32.const 0)))
+)
+;; error: call_indirect requires a table of type funcref
@@ -0,0 +1,6 @@
+;; addresses into a 64-bit memory must be i64, not i32
+(module
+    (memory i64 1)
+    (func (result i32)
+        (i32.load (i32.const 0)))
+)
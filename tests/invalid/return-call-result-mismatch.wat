@@ -0,0 +1,7 @@
+;; a tail call must return the same results as the calling function
+(module
+    (func $callee (result i64)
+        i64.const 0)
+    (func (result i32)
+        return_call $callee)
+)
@@ -0,0 +1,96 @@
+;; LEB128 integers may be at most ceil(N / 7) bytes long for an N-bit integer, and the unused bits
+;; of the last byte must be zero (unsigned) or equal to the sign bit (signed)
+
+(assert_malformed
+  (module binary
+    "\00asm" "\01\00\00\00"
+    "\05\08\01"                          ;; memory section with 1 entry
+    "\00\82\80\80\80\80\00"              ;; no max, minimum 2 with one byte too many
+  )
+  "integer representation too long"
+)
+
+(assert_malformed
+  (module binary
+    "\00asm" "\01\00\00\00"
+    "\05\07\01"                          ;; memory section with 1 entry
+    "\00\82\80\80\80\70"                 ;; no max, minimum 2 with unused bits set
+  )
+  "integer too large"
+)
+
+(assert_malformed
+  (module binary
+    "\00asm" "\01\00\00\00"
+    "\01\09"                             ;; type section
+    "\81\80\80\80\80\00"                 ;; 1 type with one byte too many
+    "\60\00\00"                          ;; () -> ()
+  )
+  "integer representation too long"
+)
+
+(assert_malformed
+  (module binary
+    "\00asm" "\01\00\00\00"
+    "\01\05\01\60\00\01\7f"              ;; type section: () -> i32
+    "\03\02\01\00"                       ;; function section
+    "\0a\0b\01"                          ;; code section with 1 body
+    "\09\00"                             ;; body size, no locals
+    "\41\80\80\80\80\80\00"              ;; i32.const 0 with one byte too many
+    "\0b"                                ;; end
+  )
+  "integer representation too long"
+)
+
+(assert_malformed
+  (module binary
+    "\00asm" "\01\00\00\00"
+    "\01\05\01\60\00\01\7f"              ;; type section: () -> i32
+    "\03\02\01\00"                       ;; function section
+    "\0a\0a\01"                          ;; code section with 1 body
+    "\08\00"                             ;; body size, no locals
+    "\41\80\80\80\80\70"                 ;; i32.const 0 with unused bits set
+    "\0b"                                ;; end
+  )
+  "integer too large"
+)
+
+(assert_malformed
+  (module binary
+    "\00asm" "\01\00\00\00"
+    "\01\05\01\60\00\01\7e"              ;; type section: () -> i64
+    "\03\02\01\00"                       ;; function section
+    "\0a\10\01"                          ;; code section with 1 body
+    "\0e\00"                             ;; body size, no locals
+    "\42\80\80\80\80\80\80\80\80\80\80\00"  ;; i64.const 0 with one byte too many
+    "\0b"                                ;; end
+  )
+  "integer representation too long"
+)
+
+(assert_malformed
+  (module binary
+    "\00asm" "\01\00\00\00"
+    "\01\05\01\60\00\01\7e"              ;; type section: () -> i64
+    "\03\02\01\00"                       ;; function section
+    "\0a\0f\01"                          ;; code section with 1 body
+    "\0d\00"                             ;; body size, no locals
+    "\42\80\80\80\80\80\80\80\80\80\02"  ;; i64.const 0 with unused bits set
+    "\0b"                                ;; end
+  )
+  "integer too large"
+)
+
+(assert_malformed
+  (module binary
+    "\00asm" "\01\00\00\00"
+    "\01\04\01\60\00\00"                 ;; type section: () -> ()
+    "\03\02\01\00"                       ;; function section
+    "\0a\0c\01"                          ;; code section with 1 body
+    "\0a\00"                             ;; body size, no locals
+    "\02\80\80\80\80\80\00"              ;; block with type index 0 (s33) with one byte too many
+    "\0b"                                ;; end of block
+    "\0b"                                ;; end
+  )
+  "integer representation too long"
+)
@@ -0,0 +1,62 @@
+;; names must be valid UTF-8, also those of custom sections
+
+(assert_malformed
+  (module binary
+    "\00asm" "\01\00\00\00"
+    "\00\02\01\ff"                       ;; custom section, name with invalid byte
+  )
+  "malformed UTF-8 encoding"
+)
+
+(assert_malformed
+  (module binary
+    "\00asm" "\01\00\00\00"
+    "\00\03\02\c0\80"                    ;; custom section, name with overlong encoding of 0
+  )
+  "malformed UTF-8 encoding"
+)
+
+(assert_malformed
+  (module binary
+    "\00asm" "\01\00\00\00"
+    "\00\04\03\ed\a0\80"                 ;; custom section, name with surrogate
+  )
+  "malformed UTF-8 encoding"
+)
+
+(assert_malformed
+  (module binary
+    "\00asm" "\01\00\00\00"
+    "\00\00"                             ;; custom section without name
+  )
+  "unexpected end"
+)
+
+(assert_malformed
+  (module binary
+    "\00asm" "\01\00\00\00"
+    "\00\02\05\61"                       ;; custom section, name longer than the section
+  )
+  "unexpected end"
+)
+
+(assert_malformed
+  (module binary
+    "\00asm" "\01\00\00\00"
+    "\02\07\01"                          ;; import section with 1 entry
+    "\01\ff"                             ;; module name with invalid byte
+    "\01\61"                             ;; name "a"
+    "\00\00"                             ;; function of type 0
+  )
+  "malformed UTF-8 encoding"
+)
+
+(assert_malformed
+  (module binary
+    "\00asm" "\01\00\00\00"
+    "\07\05\01"                          ;; export section with 1 entry
+    "\01\ff"                             ;; name with invalid byte
+    "\00\00"                             ;; function 0
+  )
+  "malformed UTF-8 encoding"
+)
@@ -0,0 +1,115 @@
+;; non-custom sections must appear at most once and in order, and their contents (and those of
+;; function bodies) must be exactly as long as their size says
+
+(assert_malformed
+  (module binary
+    "\00asm" "\01\00\00\00"
+    "\01\04\01\60\00\00"                 ;; type section
+    "\01\04\01\60\00\00"                 ;; type section again
+  )
+  "unexpected content after last section"
+)
+
+(assert_malformed
+  (module binary
+    "\00asm" "\01\00\00\00"
+    "\03\02\01\00"                       ;; function section
+    "\01\04\01\60\00\00"                 ;; type section after it
+  )
+  "unexpected content after last section"
+)
+
+(assert_malformed
+  (module binary
+    "\00asm" "\01\00\00\00"
+    "\01\04\01\60\00\00"                 ;; type section
+    "\03\02\01\00"                       ;; function section
+    "\0a\04\01\02\00\0b"                 ;; code section
+    "\0c\01\00"                          ;; data count section after it
+  )
+  "unexpected content after last section"
+)
+
+(assert_malformed
+  (module binary
+    "\00asm" "\01\00\00\00"
+    "\0e\01\00"                          ;; section with unknown id
+  )
+  "malformed section id"
+)
+
+(assert_malformed
+  (module binary
+    "\00asm" "\01\00\00\00"
+    "\01\05\01\60\00\00"                 ;; type section, size 1 byte too large
+    "\00"                                ;; trailing byte
+  )
+  "section size mismatch"
+)
+
+(assert_malformed
+  (module binary
+    "\00asm" "\01\00\00\00"
+    "\01\03\01\60\00\00"                 ;; type section, size 1 byte too small
+  )
+  "unexpected end"
+)
+
+(assert_malformed
+  (module binary
+    "\00asm" "\01\00\00\00"
+    "\01\04\01\60\00\00"                 ;; type section
+    "\03\02\01\00"                       ;; function section
+    "\0a\05\01"                          ;; code section with 1 body
+    "\03\00\0b"                          ;; body size, no locals, end
+    "\00"                                ;; trailing byte
+  )
+  "section size mismatch"
+)
+
+(assert_malformed
+  (module binary
+    "\00asm" "\01\00\00\00"
+    "\01\04\01\60\00\00"                 ;; type section
+    "\03\02\01\00"                       ;; function section
+    "\0a\04\01"                          ;; code section with 1 body
+    "\02\00\01"                          ;; body size, no locals, nop (missing end)
+  )
+  "unexpected end"
+)
+
+(assert_malformed
+  (module binary
+    "\00asm" "\01\00\00\00"
+    "\01\04\01\60\00\00"                 ;; type section
+    "\03\03\02\00\00"                    ;; function section with 2 functions
+    "\0a\04\01\02\00\0b"                 ;; code section with 1 body
+  )
+  "function and code section have inconsistent lengths"
+)
+
+(assert_malformed
+  (module binary
+    "\00asm" "\01\00\00\00"
+    "\01\04\01\60\00\00"                 ;; type section
+    "\03\02\01\00"                       ;; function section, but no code section
+  )
+  "function and code section have inconsistent lengths"
+)
+
+(assert_malformed
+  (module binary
+    "\00asm" "\01\00\00\00"
+    "\0a\04\01\02\00\0b"                 ;; code section, but no function section
+  )
+  "function and code section have inconsistent lengths"
+)
+
+(assert_malformed
+  (module binary
+    "\00asm" "\01\00\00\00"
+    "\05\03\01\00\01"                    ;; memory section
+    "\0c\01\01"                          ;; data count section with 1 segment, but no data section
+  )
+  "data count and data section have inconsistent lengths"
+)